@@ -0,0 +1,532 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use eframe::egui::{
+    self, text::LayoutJob, Color32, ComboBox, Context, RichText, TextFormat, TextStyle, Ui,
+};
+use egui_extras::{Column, TableBuilder};
+use log_analyzer::models::filter::{Filter, FilterAction};
+use log_analyzer::models::log_line::LogLine;
+use log_analyzer::models::log_line_styled::{LogLineStyled, StyledGroups};
+use log_analyzer::models::metrics::PipelineMetrics;
+use log_analyzer::models::notification::NotificationLevel;
+use log_analyzer::models::source_stats::SourceStats;
+use log_analyzer::{Event, LogAnalyzer};
+use log_source::source::log_source::SourceType;
+use tokio::sync::broadcast::{self, error::TryRecvError};
+
+use crate::line_cache::LineCache;
+
+/// Lines fetched at once by the tables
+const PAGE: usize = 200;
+/// Time between two refreshes while no input is received
+const TICK_RATE: Duration = Duration::from_millis(250);
+/// Columns of the log and search tables
+const COLUMNS: [&str; 6] = ["Index", "Date", "App", "Severity", "Function", "Payload"];
+const SOURCE_TYPES: [(SourceType, &str); 3] = [
+    (SourceType::FILE, "File"),
+    (SourceType::WS, "WebSocket"),
+    (SourceType::STDIN, "Stdin"),
+];
+const FILTER_ACTIONS: [FilterAction; 3] = [
+    FilterAction::INCLUDE,
+    FilterAction::EXCLUDE,
+    FilterAction::MARKER,
+];
+
+#[derive(Default)]
+struct SourceForm {
+    source_type: usize,
+    address: String,
+    format: Option<String>,
+}
+
+#[derive(Default)]
+struct FormatForm {
+    alias: String,
+    regex: String,
+}
+
+#[derive(Default)]
+struct FilterForm {
+    alias: String,
+    action: FilterAction,
+    app: String,
+    severity: String,
+    payload: String,
+}
+
+/// Desktop frontend of a `LogAnalyzer`: sources, formats and filters on the side, the filtered
+/// log in the center and the search results below
+pub struct GuiApp {
+    log_analyzer: Arc<dyn LogAnalyzer>,
+    events: broadcast::Receiver<Event>,
+    log_lines: LineCache<Arc<LogLine>>,
+    search_lines: LineCache<LogLineStyled>,
+    source_form: SourceForm,
+    format_form: FormatForm,
+    filter_form: FilterForm,
+    search: String,
+    /// Keep the log scrolled to the last line
+    follow: bool,
+    filtering: bool,
+    searching: bool,
+    /// Last throughput of the pipeline received
+    metrics: PipelineMetrics,
+    /// Lines, lag and errors of each source, refreshed every `TICK_RATE`
+    source_stats: Vec<SourceStats>,
+    stats_refreshed: Option<Instant>,
+    /// Last notification or error, displayed in the status bar
+    status: Option<(NotificationLevel, String)>,
+}
+
+impl GuiApp {
+    pub fn new(log_analyzer: Arc<dyn LogAnalyzer>) -> Self {
+        Self {
+            events: log_analyzer.on_event(),
+            log_analyzer,
+            log_lines: LineCache::new(PAGE),
+            search_lines: LineCache::new(PAGE),
+            source_form: SourceForm::default(),
+            format_form: FormatForm::default(),
+            filter_form: FilterForm {
+                action: FilterAction::INCLUDE,
+                ..Default::default()
+            },
+            search: String::new(),
+            follow: true,
+            filtering: false,
+            searching: false,
+            metrics: PipelineMetrics::default(),
+            source_stats: Vec::new(),
+            stats_refreshed: None,
+            status: None,
+        }
+    }
+
+    pub fn notify(&mut self, level: NotificationLevel, message: &str) {
+        self.status = Some((level, message.to_string()));
+    }
+
+    /// Read the stats of the sources again if they are older than `TICK_RATE`, as every input
+    /// redraws the window
+    fn refresh_source_stats(&mut self) {
+        if self.stats_refreshed.is_some_and(|refreshed| refreshed.elapsed() < TICK_RATE) {
+            return;
+        }
+        self.source_stats = self.log_analyzer.get_source_stats();
+        self.stats_refreshed = Some(Instant::now());
+    }
+
+    /// Drain the events of the analyzer, dropping the cached lines they invalidate
+    fn handle_events(&mut self) {
+        loop {
+            match self.events.try_recv() {
+                Ok(Event::NewLines(_, _)) => self.log_lines.clear(),
+                Ok(Event::NewSearchLines(_, _)) => self.search_lines.clear(),
+                Ok(Event::Filtering) => self.filtering = true,
+                Ok(Event::FilterPreview) => {
+                    self.log_lines.clear();
+                    self.search_lines.clear();
+                }
+                Ok(Event::FilterFinished) => {
+                    self.filtering = false;
+                    self.log_lines.clear();
+                    self.search_lines.clear();
+                }
+                Ok(Event::Searching) => {
+                    self.searching = true;
+                    self.search_lines.clear();
+                }
+                Ok(Event::SearchFinished) => {
+                    self.searching = false;
+                    self.search_lines.clear();
+                }
+                Ok(
+                    event @ (Event::Notification(_)
+                    | Event::SourceError { .. }
+                    | Event::SourceEof { .. }
+                    | Event::SourceResumed { .. }
+                    | Event::Burst(_)
+                    | Event::Alert { .. }),
+                ) => {
+                    if let Some(notification) = event.notification() {
+                        self.status = Some((notification.level, notification.message))
+                    }
+                }
+                Ok(Event::LinesDropped { .. }) => {}
+                Ok(Event::LinesDiscarded { .. }) => {
+                    self.log_lines.clear();
+                    self.search_lines.clear();
+                }
+                Ok(Event::Metrics(metrics)) => self.metrics = metrics,
+                Ok(Event::Processing(_, _)) => {}
+                // Some events were missed, refetch everything
+                Err(TryRecvError::Lagged(_)) => {
+                    self.log_lines.clear();
+                    self.search_lines.clear();
+                }
+                Err(_) => break,
+            }
+        }
+    }
+
+    fn add_source(&mut self) {
+        let (source_type, _) = SOURCE_TYPES[self.source_form.source_type];
+        match self.log_analyzer.add_log(
+            source_type.into(),
+            &self.source_form.address,
+            self.source_form.format.as_ref(),
+        ) {
+            Ok(()) => self.source_form.address.clear(),
+            Err(err) => self.notify(NotificationLevel::Error, &err.to_string()),
+        }
+    }
+
+    fn add_format(&mut self) {
+        match self
+            .log_analyzer
+            .add_format(&self.format_form.alias, &self.format_form.regex)
+        {
+            Ok(()) => self.format_form = FormatForm::default(),
+            Err(err) => self.notify(NotificationLevel::Error, &err.to_string()),
+        }
+    }
+
+    fn add_filter(&mut self) {
+        let form = &self.filter_form;
+        if form.alias.is_empty() {
+            self.notify(NotificationLevel::Error, "The alias is empty");
+            return;
+        }
+        let filter = Filter {
+            alias: form.alias.clone(),
+            action: form.action,
+            filter: LogLine {
+                app: form.app.as_str().into(),
+                severity: form.severity.as_str().into(),
+                payload: form.payload.as_str().into(),
+                ..Default::default()
+            },
+        };
+        let action = form.action;
+        self.log_analyzer.add_filter(filter);
+        self.filter_form = FilterForm {
+            action,
+            ..Default::default()
+        };
+    }
+
+    fn draw_side_panel(&mut self, ui: &mut Ui) {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.heading("Sources");
+            let stats = &self.source_stats;
+            for (enabled, address, format) in self.log_analyzer.get_logs() {
+                let mut checked = enabled;
+                let label = match format {
+                    Some(format) => format!("{} ({})", address, format),
+                    None => address.clone(),
+                };
+                let counts = stats
+                    .iter()
+                    .find(|stats| stats.source == address)
+                    .cloned()
+                    .unwrap_or_default();
+                let checkbox = ui.checkbox(&mut checked, label).on_hover_text(format!(
+                    "{} raw | {} filtered | {} search | {} dropped | {} deferred",
+                    counts.raw, counts.filtered, counts.search, counts.dropped, counts.deferred
+                ));
+                if checkbox.changed() {
+                    self.log_analyzer.toggle_source(&address);
+                }
+                // Unreadable source, retried meanwhile if followed
+                if let Some(error) = &counts.error {
+                    let color = ui.visuals().error_fg_color;
+                    ui.label(RichText::new(format!("⚠ {}", error)).color(color));
+                }
+            }
+            self.draw_source_form(ui);
+
+            ui.separator();
+            ui.heading("Formats");
+            for format in self.log_analyzer.get_formats() {
+                ui.label(&format.alias).on_hover_text(&format.regex);
+            }
+            self.draw_format_form(ui);
+
+            ui.separator();
+            ui.heading("Filters");
+            for (enabled, filter) in self.log_analyzer.get_filters() {
+                let mut checked = enabled;
+                let mut label = RichText::new(format!("{} ({:?})", filter.alias, filter.action));
+                if let Some((r, g, b)) = filter.filter.color {
+                    label = label.color(Color32::from_rgb(r, g, b));
+                }
+                if ui.checkbox(&mut checked, label).changed() {
+                    self.log_analyzer.toggle_filter(&filter.alias);
+                }
+            }
+            self.draw_filter_form(ui);
+        });
+    }
+
+    fn draw_source_form(&mut self, ui: &mut Ui) {
+        let formats = self.log_analyzer.get_formats();
+        let form = &mut self.source_form;
+        let add = egui::CollapsingHeader::new("Add source").show(ui, |ui| {
+            ComboBox::from_id_salt("source_type")
+                .selected_text(SOURCE_TYPES[form.source_type].1)
+                .show_ui(ui, |ui| {
+                    for (i, (_, name)) in SOURCE_TYPES.iter().enumerate() {
+                        ui.selectable_value(&mut form.source_type, i, *name);
+                    }
+                });
+            ui.add(egui::TextEdit::singleline(&mut form.address).hint_text("Path or address"));
+            ComboBox::from_id_salt("source_format")
+                .selected_text(form.format.as_deref().unwrap_or("No format"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut form.format, None, "No format");
+                    for format in formats {
+                        ui.selectable_value(
+                            &mut form.format,
+                            Some(format.alias.clone()),
+                            &format.alias,
+                        );
+                    }
+                });
+            ui.add_enabled(!form.address.is_empty(), egui::Button::new("Add")).clicked()
+        });
+        if add.body_returned == Some(true) {
+            self.add_source();
+        }
+    }
+
+    fn draw_format_form(&mut self, ui: &mut Ui) {
+        let form = &mut self.format_form;
+        let add = egui::CollapsingHeader::new("Add format").show(ui, |ui| {
+            ui.add(egui::TextEdit::singleline(&mut form.alias).hint_text("Alias"));
+            ui.add(
+                egui::TextEdit::singleline(&mut form.regex)
+                    .hint_text("(?P<SEVERITY>\\w+) (?P<PAYLOAD>.*)"),
+            );
+            ui.add_enabled(!form.regex.is_empty(), egui::Button::new("Add")).clicked()
+        });
+        if add.body_returned == Some(true) {
+            self.add_format();
+        }
+    }
+
+    fn draw_filter_form(&mut self, ui: &mut Ui) {
+        let form = &mut self.filter_form;
+        let add = egui::CollapsingHeader::new("Add filter").show(ui, |ui| {
+            ui.add(egui::TextEdit::singleline(&mut form.alias).hint_text("Alias"));
+            ComboBox::from_id_salt("filter_action")
+                .selected_text(format!("{:?}", form.action))
+                .show_ui(ui, |ui| {
+                    for action in FILTER_ACTIONS {
+                        ui.selectable_value(&mut form.action, action, format!("{:?}", action));
+                    }
+                });
+            ui.add(egui::TextEdit::singleline(&mut form.app).hint_text("App regex"));
+            ui.add(egui::TextEdit::singleline(&mut form.severity).hint_text("Severity regex"));
+            ui.add(egui::TextEdit::singleline(&mut form.payload).hint_text("Payload regex"));
+            ui.add_enabled(!form.alias.is_empty(), egui::Button::new("Add")).clicked()
+        });
+        if add.body_returned == Some(true) {
+            self.add_filter();
+        }
+    }
+
+    fn draw_status_bar(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} lines, {} filtered, {} found",
+                self.log_analyzer.get_total_raw_lines(),
+                self.log_analyzer.get_total_filtered_lines(),
+                self.log_analyzer.get_total_searched_lines(),
+            ));
+            // Badge of the sources the pipeline doesn't keep up with
+            let stats = &self.source_stats;
+            let dropped: usize = stats.iter().map(|stats| stats.dropped).sum();
+            let lag = match (stats.iter().any(|stats| stats.lagging), dropped) {
+                (false, 0) => None,
+                (true, 0) => Some("lagging".to_string()),
+                (false, dropped) => Some(format!("dropped {}", dropped)),
+                (true, dropped) => Some(format!("lagging, dropped {}", dropped)),
+            };
+            if let Some(lag) = lag {
+                let color = ui.visuals().error_fg_color;
+                ui.label(RichText::new(lag).color(egui::Color32::WHITE).background_color(color));
+            }
+            if !self.metrics.is_idle() {
+                ui.label(format!("{} lines/s", self.metrics.processed_per_sec));
+            }
+            if self.filtering {
+                ui.spinner();
+                ui.label("Filtering");
+            }
+            if self.searching {
+                ui.spinner();
+                ui.label("Searching");
+            }
+            ui.checkbox(&mut self.follow, "Follow");
+            if let Some((level, message)) = &self.status {
+                let color = match level {
+                    NotificationLevel::Info => ui.visuals().text_color(),
+                    NotificationLevel::Warning => ui.visuals().warn_fg_color,
+                    NotificationLevel::Error => ui.visuals().error_fg_color,
+                };
+                ui.separator();
+                ui.label(RichText::new(message).color(color));
+            }
+        });
+    }
+
+    fn draw_search_panel(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Search");
+            let input = ui.add(
+                egui::TextEdit::singleline(&mut self.search)
+                    .hint_text("Regex, press enter to search")
+                    .desired_width(f32::INFINITY),
+            );
+            if input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                self.log_analyzer.add_search(&self.search);
+            }
+        });
+
+        let total = self.log_analyzer.get_total_searched_lines();
+        let log_analyzer = &self.log_analyzer;
+        let lines = &mut self.search_lines;
+        let highlight = ui.visuals().selection.bg_fill;
+        log_table(ui, "search_table", total, false, |index, row| {
+            let Some(line) = lines.get(index, |from, to| log_analyzer.get_search_lines(from, to))
+            else {
+                return;
+            };
+            let color = line.color.map(|(r, g, b)| Color32::from_rgb(r, g, b));
+            for groups in [
+                &line.index,
+                &line.date,
+                &line.app,
+                &line.severity,
+                &line.function,
+                &line.payload,
+            ] {
+                row.col(|ui| {
+                    ui.label(styled_job(ui, groups, color, highlight));
+                });
+            }
+        });
+    }
+
+    fn draw_log(&mut self, ui: &mut Ui) {
+        let total = self.log_analyzer.get_total_filtered_lines();
+        let log_analyzer = &self.log_analyzer;
+        let lines = &mut self.log_lines;
+        log_table(ui, "log_table", total, self.follow, |index, row| {
+            let Some(line) = lines.get(index, |from, to| log_analyzer.get_log_lines(from, to))
+            else {
+                return;
+            };
+            let color = line.color.map(|(r, g, b)| Color32::from_rgb(r, g, b));
+            let index = line.index.to_string();
+            for value in [
+                index.as_str(),
+                &line.date,
+                &line.app,
+                &line.severity,
+                &line.function,
+                &line.payload,
+            ] {
+                row.col(|ui| {
+                    let mut text = RichText::new(value).monospace();
+                    if let Some(color) = color {
+                        text = text.color(color);
+                    }
+                    ui.label(text);
+                });
+            }
+        });
+    }
+}
+
+impl eframe::App for GuiApp {
+    fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
+        self.handle_events();
+        self.refresh_source_stats();
+
+        egui::SidePanel::left("side_panel")
+            .resizable(true)
+            .default_width(260.0)
+            .show(ctx, |ui| self.draw_side_panel(ui));
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| self.draw_status_bar(ui));
+        egui::TopBottomPanel::bottom("search_panel")
+            .resizable(true)
+            .default_height(220.0)
+            .show(ctx, |ui| self.draw_search_panel(ui));
+        egui::CentralPanel::default().show(ctx, |ui| self.draw_log(ui));
+
+        // Lines keep arriving without any input
+        ctx.request_repaint_after(TICK_RATE);
+    }
+}
+
+/// Draw a table of `total` rows with the `COLUMNS` of the lines, only drawing the visible rows
+fn log_table(
+    ui: &mut Ui,
+    id: &str,
+    total: usize,
+    stick_to_bottom: bool,
+    mut draw_row: impl FnMut(usize, &mut egui_extras::TableRow),
+) {
+    let row_height = ui.text_style_height(&TextStyle::Monospace) + 4.0;
+    ui.push_id(id, |ui| {
+        let mut table = TableBuilder::new(ui)
+            .striped(true)
+            .stick_to_bottom(stick_to_bottom)
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center));
+        for column in COLUMNS {
+            table = match column {
+                "Payload" => table.column(Column::remainder().clip(true)),
+                _ => table.column(Column::auto().resizable(true).clip(true)),
+            };
+        }
+        table
+            .header(row_height, |mut header| {
+                for column in COLUMNS {
+                    header.col(|ui| {
+                        ui.strong(column);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(row_height, total, |mut row| draw_row(row.index(), &mut row));
+            });
+    });
+}
+
+/// Layout of the groups of a search line, highlighting the matched groups
+fn styled_job(
+    ui: &Ui,
+    groups: &StyledGroups,
+    color: Option<Color32>,
+    highlight: Color32,
+) -> LayoutJob {
+    let font_id = TextStyle::Monospace.resolve(ui.style());
+    let color = color.unwrap_or_else(|| ui.visuals().text_color());
+    let mut job = LayoutJob::default();
+    for (group, text) in groups {
+        let format = TextFormat {
+            font_id: font_id.clone(),
+            color,
+            background: match group {
+                Some(_) => highlight,
+                None => Color32::TRANSPARENT,
+            },
+            ..Default::default()
+        };
+        job.append(text, 0.0, format);
+    }
+    job
+}
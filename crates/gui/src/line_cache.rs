@@ -0,0 +1,60 @@
+/// Lines fetched from the analyzer around the last rows drawn.
+///
+/// The tables only draw the visible rows, asking for them one by one: the cache fetches a page
+/// of lines at once so scrolling queries the analyzer once per page instead of once per row
+pub struct LineCache<T> {
+    page: usize,
+    from: usize,
+    lines: Vec<T>,
+}
+
+impl<T> LineCache<T> {
+    pub fn new(page: usize) -> Self {
+        Self {
+            page: page.max(1),
+            from: 0,
+            lines: Vec::new(),
+        }
+    }
+
+    /// Get the line at `index`, fetching the page around it with `fetch(from, to)` if it is not
+    /// cached
+    pub fn get(&mut self, index: usize, fetch: impl FnOnce(usize, usize) -> Vec<T>) -> Option<&T> {
+        if !(self.from..self.from + self.lines.len()).contains(&index) {
+            self.from = index.saturating_sub(self.page / 2);
+            self.lines = fetch(self.from, self.from + self.page);
+        }
+        self.lines.get(index - self.from)
+    }
+
+    /// Forget the cached lines, to be called when the analyzer reports changes
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_are_fetched_once() {
+        let lines: Vec<usize> = (0..100).collect();
+        let mut fetches = 0;
+        let mut cache = LineCache::new(10);
+        let mut fetch = |from: usize, to: usize| {
+            fetches += 1;
+            lines[from.min(100)..to.min(100)].to_vec()
+        };
+
+        assert_eq!(cache.get(50, &mut fetch), Some(&50));
+        assert_eq!(cache.get(54, &mut fetch), Some(&54));
+        assert_eq!(cache.get(45, &mut fetch), Some(&45));
+        assert_eq!(cache.get(99, &mut fetch), Some(&99));
+        assert_eq!(cache.get(120, &mut fetch), None);
+        assert_eq!(fetches, 3);
+
+        cache.clear();
+        assert_eq!(cache.get(99, |from, to| (from..to).map(|i| i * 2).collect()), Some(&198));
+    }
+}
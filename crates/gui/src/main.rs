@@ -0,0 +1,64 @@
+mod app;
+mod line_cache;
+
+use std::fs;
+use std::sync::Arc;
+
+use app::GuiApp;
+use log_analyzer::models::notification::NotificationLevel;
+use log_analyzer::models::settings::Settings;
+use log_analyzer::{LogAnalyzer, LogAnalyzerBuilder};
+use log_source::source::log_source::SourceType;
+
+const USAGE: &str = "Usage: lap-gui [--settings <file>] [--format <alias>] [files...]";
+
+/// Open the `lap` settings `file`, restoring its formats, filters and sources
+fn load_settings(log_analyzer: &dyn LogAnalyzer, file: &str) -> Result<(), String> {
+    let json = fs::read_to_string(file).map_err(|err| err.to_string())?;
+    let mut settings = Settings::from_json(&json).map_err(|err| err.to_string())?;
+    log_analyzer
+        .restore_session(&settings.take_session())
+        .map_err(|err| err.to_string())
+}
+
+fn main() -> eframe::Result {
+    let mut settings = None;
+    let mut format = None;
+    let mut files = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--settings" => settings = args.next(),
+            "--format" => format = args.next(),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ => files.push(arg),
+        }
+    }
+
+    let log_analyzer: Arc<dyn LogAnalyzer> = LogAnalyzerBuilder::new().build();
+    let mut errors = Vec::new();
+    if let Some(file) = &settings {
+        if let Err(err) = load_settings(log_analyzer.as_ref(), file) {
+            errors.push(format!("Settings partially loaded: {}", err));
+        }
+    }
+    for file in &files {
+        if let Err(err) = log_analyzer.add_log(SourceType::FILE.into(), file, format.as_ref()) {
+            errors.push(format!("Unable to open {}: {}", file, err));
+        }
+    }
+
+    let mut app = GuiApp::new(log_analyzer);
+    for err in errors {
+        app.notify(NotificationLevel::Error, &err);
+    }
+
+    eframe::run_native(
+        "Log Analyzer Pro",
+        eframe::NativeOptions::default(),
+        Box::new(|_| Ok(Box::new(app))),
+    )
+}
@@ -0,0 +1,66 @@
+/*
+ * C bindings of the log analyzer engine.
+ *
+ * Filters, events and lines are exchanged as JSON strings with the same layout as the HTTP
+ * API of `lap serve`. Strings returned by the library belong to the caller and are released
+ * with lap_string_free. Functions returning int return LAP_OK on success and LAP_ERROR on
+ * failure, described by lap_last_error. A panic of the engine is returned as a failure
+ * instead of unwinding into the caller.
+ */
+#ifndef LOG_ANALYZER_H
+#define LOG_ANALYZER_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define LAP_OK 0
+#define LAP_ERROR -1
+
+#define LAP_SOURCE_FILE 0
+#define LAP_SOURCE_WS 1
+#define LAP_SOURCE_STDIN 2
+
+typedef struct LapAnalyzer LapAnalyzer;
+
+/* Start an engine with `workers` processing threads, 0 for one per CPU */
+LapAnalyzer *lap_analyzer_new(size_t workers);
+/* Stop the sources and the threads of the engine, waiting for them, and release it */
+void lap_analyzer_free(LapAnalyzer *analyzer);
+
+/* Define a format named `alias` with a regex of named groups, e.g. (?P<PAYLOAD>.*) */
+int lap_add_format(const LapAnalyzer *analyzer, const char *alias, const char *regex);
+/* Add a source of type LAP_SOURCE_*. `format` is the alias of its format or NULL */
+int lap_add_source(const LapAnalyzer *analyzer, size_t source_type, const char *address,
+                   const char *format);
+/* Add a filter given as JSON, e.g. {"alias": "Errors", "action": "INCLUDE", "filter": {"severity": "ERROR"}} */
+int lap_add_filter(const LapAnalyzer *analyzer, const char *filter_json, bool enabled);
+/* Enable or disable the filter named `alias` */
+int lap_toggle_filter(const LapAnalyzer *analyzer, const char *alias);
+/* Search `regex` in the filtered log */
+int lap_add_search(const LapAnalyzer *analyzer, const char *regex);
+
+/* Next event as JSON, e.g. {"NewLines":[0,99]}, or NULL when there is none. Never blocks */
+char *lap_poll_event(const LapAnalyzer *analyzer);
+/* Lines from..to of the filtered log as a JSON array */
+char *lap_get_log_lines(const LapAnalyzer *analyzer, size_t from, size_t to);
+/* Lines from..to of the search results as a JSON array */
+char *lap_get_search_lines(const LapAnalyzer *analyzer, size_t from, size_t to);
+size_t lap_get_total_filtered_lines(const LapAnalyzer *analyzer);
+size_t lap_get_total_searched_lines(const LapAnalyzer *analyzer);
+/* Throughput of the pipeline over the last second as JSON, also received as Metrics events */
+char *lap_get_metrics(const LapAnalyzer *analyzer);
+
+/* Message of the last failed call of this thread, or NULL. Owned by the library */
+const char *lap_last_error(void);
+/* Release a string returned by the library */
+void lap_string_free(char *value);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* LOG_ANALYZER_H */
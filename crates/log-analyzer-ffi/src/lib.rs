@@ -0,0 +1,409 @@
+//! C ABI of the analysis engine, declared in `include/log_analyzer.h`.
+//!
+//! Structured values (filters, events, lines) cross the boundary as JSON strings, with the
+//! same layout as the HTTP API of `lap serve`. Strings returned by the library are owned by
+//! the caller and released with `lap_string_free`. A panic of the engine is caught at the
+//! boundary and returned as a failure, described by `lap_last_error`.
+//!
+//! # Safety
+//! The pointers given to the functions must be null or valid: handles returned by
+//! `lap_analyzer_new` and not yet freed, and nul-terminated strings
+#![allow(clippy::missing_safety_doc)]
+
+use std::cell::RefCell;
+use std::any::Any;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::sync::Arc;
+
+use log_analyzer::models::filter::Filter;
+use log_analyzer::{Event, LogAnalyzer, LogAnalyzerBuilder, LogService};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::TryRecvError};
+
+/// Returned by the functions that succeeded
+pub const LAP_OK: c_int = 0;
+/// Returned by the functions that failed. `lap_last_error` describes the failure
+pub const LAP_ERROR: c_int = -1;
+
+/// Engine handle created by `lap_analyzer_new`
+pub struct LapAnalyzer {
+    analyzer: Arc<LogService>,
+    events: Mutex<broadcast::Receiver<Event>>,
+}
+
+thread_local! {
+    /// Message of the last failed call of this thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Run the body of a function of the C ABI, returning `failed` if it panics instead of
+/// unwinding into the caller. The panic is described by the last error
+fn guarded<T>(failed: T, call: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(call)).unwrap_or_else(|panic| {
+        set_last_error(format!("The engine panicked: {}", panic_message(panic.as_ref())));
+        failed
+    })
+}
+
+/// Message of a caught panic, as given to `panic!`
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    match panic.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => panic.downcast_ref::<String>().cloned().unwrap_or_default(),
+    }
+}
+
+/// Run a call and convert its result to the status returned through the C ABI
+fn status(call: impl FnOnce() -> Result<(), String>) -> c_int {
+    guarded(LAP_ERROR, || match call() {
+        Ok(()) => LAP_OK,
+        Err(err) => {
+            set_last_error(err);
+            LAP_ERROR
+        }
+    })
+}
+
+unsafe fn handle<'a>(analyzer: *const LapAnalyzer) -> Result<&'a LapAnalyzer, String> {
+    analyzer
+        .as_ref()
+        .ok_or_else(|| "The analyzer is null".to_string())
+}
+
+unsafe fn string(value: *const c_char, name: &str) -> Result<String, String> {
+    if value.is_null() {
+        return Err(format!("The {} is null", name));
+    }
+    CStr::from_ptr(value)
+        .to_str()
+        .map(str::to_string)
+        .map_err(|_| format!("The {} is not valid UTF-8", name))
+}
+
+/// Serialize `value` to a string owned by the caller, or null on failure
+fn json<T: Serialize>(value: &T) -> *mut c_char {
+    match serde_json::to_string(value)
+        .map_err(|err| err.to_string())
+        .and_then(|json| CString::new(json).map_err(|err| err.to_string()))
+    {
+        Ok(json) => json.into_raw(),
+        Err(err) => {
+            set_last_error(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Start an engine with `workers` processing threads, 0 for one per CPU.
+/// Release it with `lap_analyzer_free`
+#[no_mangle]
+pub extern "C" fn lap_analyzer_new(workers: usize) -> *mut LapAnalyzer {
+    guarded(ptr::null_mut(), || {
+        let mut builder = LogAnalyzerBuilder::new();
+        if workers > 0 {
+            builder = builder.workers(workers);
+        }
+        let analyzer = builder.build();
+        let events = Mutex::new(analyzer.on_event());
+
+        Box::into_raw(Box::new(LapAnalyzer { analyzer, events }))
+    })
+}
+
+/// Stop the sources and the threads of the engine, returning once they exited, and release
+/// the handle
+#[no_mangle]
+pub unsafe extern "C" fn lap_analyzer_free(analyzer: *mut LapAnalyzer) {
+    guarded((), || {
+        if !analyzer.is_null() {
+            let LapAnalyzer { analyzer, events } = *Box::from_raw(analyzer);
+            drop(events);
+            LogService::shutdown(analyzer);
+        }
+    })
+}
+
+/// Define a format named `alias` with a regex of named groups
+#[no_mangle]
+pub unsafe extern "C" fn lap_add_format(
+    analyzer: *const LapAnalyzer,
+    alias: *const c_char,
+    regex: *const c_char,
+) -> c_int {
+    status(|| {
+        let analyzer = handle(analyzer)?;
+        let (alias, regex) = (string(alias, "alias")?, string(regex, "regex")?);
+        analyzer
+            .analyzer
+            .add_format(&alias, &regex)
+            .map_err(|err| err.to_string())
+    })
+}
+
+/// Add a source of `source_type` (0 file, 1 websocket, 2 stdin) read at `address`.
+/// `format` is the alias of the format of its lines, or null to keep them unformatted
+#[no_mangle]
+pub unsafe extern "C" fn lap_add_source(
+    analyzer: *const LapAnalyzer,
+    source_type: usize,
+    address: *const c_char,
+    format: *const c_char,
+) -> c_int {
+    status(|| {
+        let analyzer = handle(analyzer)?;
+        let address = string(address, "address")?;
+        let format = match format.is_null() {
+            true => None,
+            false => Some(string(format, "format")?),
+        };
+        analyzer
+            .analyzer
+            .add_log(source_type, &address, format.as_ref())
+            .map_err(|err| err.to_string())
+    })
+}
+
+/// Add a filter given as JSON, e.g. `{"alias": "Errors", "action": "INCLUDE",
+/// "filter": {"severity": "ERROR"}}`, enabled or not
+#[no_mangle]
+pub unsafe extern "C" fn lap_add_filter(
+    analyzer: *const LapAnalyzer,
+    filter_json: *const c_char,
+    enabled: bool,
+) -> c_int {
+    status(|| {
+        let analyzer = handle(analyzer)?;
+        let filter: Filter = serde_json::from_str(&string(filter_json, "filter")?)
+            .map_err(|err| format!("Invalid filter: {}", err))?;
+        let alias = filter.alias.clone();
+        analyzer.analyzer.add_filter(filter);
+        if enabled {
+            analyzer.analyzer.toggle_filter(&alias);
+        }
+        Ok(())
+    })
+}
+
+/// Enable or disable the filter named `alias`
+#[no_mangle]
+pub unsafe extern "C" fn lap_toggle_filter(
+    analyzer: *const LapAnalyzer,
+    alias: *const c_char,
+) -> c_int {
+    status(|| {
+        let analyzer = handle(analyzer)?;
+        analyzer.analyzer.toggle_filter(&string(alias, "alias")?);
+        Ok(())
+    })
+}
+
+/// Search `regex` in the filtered log. Invalid regexes are reported as a notification event
+#[no_mangle]
+pub unsafe extern "C" fn lap_add_search(
+    analyzer: *const LapAnalyzer,
+    regex: *const c_char,
+) -> c_int {
+    status(|| {
+        let analyzer = handle(analyzer)?;
+        analyzer.analyzer.add_search(&string(regex, "regex")?);
+        Ok(())
+    })
+}
+
+/// Next event of the engine as JSON, e.g. `{"NewLines":[0,99]}`, or null when there is none.
+/// Never blocks: poll it from the event loop of the frontend
+#[no_mangle]
+pub unsafe extern "C" fn lap_poll_event(analyzer: *const LapAnalyzer) -> *mut c_char {
+    guarded(ptr::null_mut(), || {
+        let analyzer = match handle(analyzer) {
+            Ok(analyzer) => analyzer,
+            Err(err) => {
+                set_last_error(err);
+                return ptr::null_mut();
+            }
+        };
+
+        let mut events = analyzer.events.lock();
+        loop {
+            match events.try_recv() {
+                Ok(event) => return json(&event),
+                // The oldest events were dropped, continue with the ones kept
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(_) => return ptr::null_mut(),
+            }
+        }
+    })
+}
+
+/// Lines `from..to` of the filtered log as a JSON array
+#[no_mangle]
+pub unsafe extern "C" fn lap_get_log_lines(
+    analyzer: *const LapAnalyzer,
+    from: usize,
+    to: usize,
+) -> *mut c_char {
+    guarded(ptr::null_mut(), || match handle(analyzer) {
+        Ok(analyzer) => json(&analyzer.analyzer.get_log_lines(from, to)),
+        Err(err) => {
+            set_last_error(err);
+            ptr::null_mut()
+        }
+    })
+}
+
+/// Lines `from..to` of the search results as a JSON array, with the matches highlighted
+#[no_mangle]
+pub unsafe extern "C" fn lap_get_search_lines(
+    analyzer: *const LapAnalyzer,
+    from: usize,
+    to: usize,
+) -> *mut c_char {
+    guarded(ptr::null_mut(), || match handle(analyzer) {
+        Ok(analyzer) => json(&analyzer.analyzer.get_search_lines(from, to)),
+        Err(err) => {
+            set_last_error(err);
+            ptr::null_mut()
+        }
+    })
+}
+
+/// Number of lines in the filtered log
+#[no_mangle]
+pub unsafe extern "C" fn lap_get_total_filtered_lines(analyzer: *const LapAnalyzer) -> usize {
+    guarded(0, || {
+        handle(analyzer)
+            .map(|analyzer| analyzer.analyzer.get_total_filtered_lines())
+            .unwrap_or_default()
+    })
+}
+
+/// Number of lines in the search results
+#[no_mangle]
+pub unsafe extern "C" fn lap_get_total_searched_lines(analyzer: *const LapAnalyzer) -> usize {
+    guarded(0, || {
+        handle(analyzer)
+            .map(|analyzer| analyzer.analyzer.get_total_searched_lines())
+            .unwrap_or_default()
+    })
+}
+
+/// Throughput of the pipeline over the last second as JSON, e.g.
+/// `{"ingested_per_sec": 1000, "processed_per_sec": 1000, "queue_depth": 0, ...}`
+#[no_mangle]
+pub unsafe extern "C" fn lap_get_metrics(analyzer: *const LapAnalyzer) -> *mut c_char {
+    guarded(ptr::null_mut(), || match handle(analyzer) {
+        Ok(analyzer) => json(&analyzer.analyzer.get_metrics()),
+        Err(err) => {
+            set_last_error(err);
+            ptr::null_mut()
+        }
+    })
+}
+
+/// Message of the last failed call of this thread, or null. Owned by the library and valid
+/// until the next failed call of this thread
+#[no_mangle]
+pub extern "C" fn lap_last_error() -> *const c_char {
+    guarded(ptr::null(), || {
+        LAST_ERROR.with(|last| {
+            last.borrow()
+                .as_ref()
+                .map(|message| message.as_ptr())
+                .unwrap_or(ptr::null())
+        })
+    })
+}
+
+/// Release a string returned by the library
+#[no_mangle]
+pub unsafe extern "C" fn lap_string_free(value: *mut c_char) {
+    guarded((), || {
+        if !value.is_null() {
+            drop(CString::from_raw(value));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use test_utils::{wait_until, DEADLINE};
+
+    fn c(value: &str) -> CString {
+        CString::new(value).unwrap()
+    }
+
+    unsafe fn take(value: *mut c_char) -> Option<String> {
+        if value.is_null() {
+            return None;
+        }
+        let string = CStr::from_ptr(value).to_str().unwrap().to_string();
+        lap_string_free(value);
+        Some(string)
+    }
+
+    #[test]
+    fn lines_of_a_file_are_fetched_as_json() {
+        let dir = test_utils::tempdir().unwrap();
+        let path = dir.path().join("ffi.log");
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(file, "INFO started\nERROR failed\nINFO stopped").unwrap();
+
+        unsafe {
+            let analyzer = lap_analyzer_new(1);
+            let (alias, regex) = (c("Default"), c(r"(?P<SEVERITY>\w+) (?P<PAYLOAD>.*)"));
+            assert_eq!(lap_add_format(analyzer, alias.as_ptr(), regex.as_ptr()), LAP_OK);
+            let filter = c(r#"{"alias": "Errors", "action": "INCLUDE", "filter": {"severity": "ERROR"}}"#);
+            assert_eq!(lap_add_filter(analyzer, filter.as_ptr(), true), LAP_OK);
+            let address = c(path.to_str().unwrap());
+            assert_eq!(
+                lap_add_source(analyzer, 0, address.as_ptr(), alias.as_ptr()),
+                LAP_OK
+            );
+
+            wait_until(DEADLINE, || lap_get_total_filtered_lines(analyzer) > 0);
+
+            let lines = take(lap_get_log_lines(analyzer, 0, 10)).unwrap();
+            let lines: serde_json::Value = serde_json::from_str(&lines).unwrap();
+            assert_eq!(lines[0]["payload"], "failed");
+            assert!(take(lap_poll_event(analyzer)).is_some());
+
+            lap_analyzer_free(analyzer);
+        }
+    }
+
+    #[test]
+    fn failures_are_described_by_the_last_error() {
+        unsafe {
+            let analyzer = lap_analyzer_new(1);
+            let (alias, regex) = (c("Broken"), c("(?P<PAYLOAD>"));
+            assert_eq!(lap_add_format(analyzer, alias.as_ptr(), regex.as_ptr()), LAP_ERROR);
+            let error = CStr::from_ptr(lap_last_error()).to_str().unwrap();
+            assert!(error.starts_with("Could not compile regex"));
+
+            assert_eq!(lap_add_search(ptr::null(), regex.as_ptr()), LAP_ERROR);
+            lap_analyzer_free(analyzer);
+        }
+    }
+
+    #[test]
+    fn panics_are_returned_as_errors() {
+        assert_eq!(status(|| panic!("line {} can't be parsed", 2)), LAP_ERROR);
+        let error = unsafe { CStr::from_ptr(lap_last_error()) };
+        assert_eq!(error.to_str().unwrap(), "The engine panicked: line 2 can't be parsed");
+
+        assert!(guarded(ptr::null_mut::<c_char>(), || panic!("static")).is_null());
+        let error = unsafe { CStr::from_ptr(lap_last_error()) };
+        assert_eq!(error.to_str().unwrap(), "The engine panicked: static");
+    }
+}
@@ -0,0 +1,31 @@
+#!/bin/sh
+# Compare the benchmarks of the working tree against a git revision, HEAD by default.
+# The revision is benchmarked in a temporary worktree and saved as a criterion baseline named
+# after its commit, then the working tree is benchmarked against it. Extra arguments filter
+# the benchmarks, e.g. `compare.sh main filter/`
+#
+# Usage: crates/log-analyzer/benches/compare.sh [revision] [benchmark filter]
+set -e
+
+revision=${1:-HEAD}
+root=$(git rev-parse --show-toplevel)
+baseline=$(git -C "$root" rev-parse --short "$revision")
+
+# Revisions older than the benchmarks have nothing to compare with
+if ! git -C "$root" cat-file -e "$baseline:crates/log-analyzer/benches/pipeline.rs" 2>/dev/null; then
+    echo "$revision ($baseline) has no pipeline benchmark to compare with" >&2
+    exit 1
+fi
+
+worktree=$(mktemp -d)
+
+git -C "$root" worktree add --detach "$worktree" "$revision" >/dev/null
+trap 'git -C "$root" worktree remove --force "$worktree"' EXIT
+
+# Both runs share the build directory and the criterion results
+export CARGO_TARGET_DIR="$root/target"
+export CRITERION_HOME="$root/target/criterion"
+
+shift $(( $# > 0 ? 1 : 0 ))
+(cd "$worktree" && cargo bench -p log-analyzer --bench pipeline -- --save-baseline "$baseline" "$@")
+(cd "$root" && cargo bench -p log-analyzer --bench pipeline -- --baseline "$baseline" "$@")
@@ -0,0 +1,220 @@
+//! Throughput of the processing stages over the fixture logs: formatting, filtering, search and
+//! the windows of lines read from the analysis store.
+//!
+//! Run with `cargo bench -p log-analyzer`, or compare the working tree against a revision with
+//! `crates/log-analyzer/benches/compare.sh <revision>`
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use regex::Regex;
+
+use log_analyzer::models::filter::{Filter, FilterAction};
+use log_analyzer::models::format::Format;
+use log_analyzer::models::log_line::LogLine;
+use log_analyzer::stores::analysis_store::{AnalysisStore, InMemmoryAnalysisStore};
+use log_analyzer::LineProcessor;
+
+/// Times the lines of a fixture are repeated, for runs long enough to be measured steadily
+const REPEAT: usize = 100;
+
+/// Lines of the windows read from the store, about a screen of the log
+const WINDOW: usize = 200;
+
+/// (name, fixture, format) of the logs benchmarked, with the formats of the README
+const FIXTURES: [(&str, &str, &str); 2] = [
+    (
+        "application",
+        include_str!("fixtures/application.log"),
+        r"(?P<DATE>[\d]{4}-[\d]{2}-[\d]{2} [\d]{2}:[\d]{2}:[\d]{2},[\d]{3}) - \[(?P<SEVERITY>[\w]*)\] - \[([ \w]{4})\] - \[(?P<TIMESTAMP>[ \d]*)\] (?P<PAYLOAD>.*)",
+    ),
+    (
+        "system",
+        include_str!("fixtures/system.log"),
+        r"(?P<DATE>[\d]{4}-[\d]{2}-[\d]{2} [\d]{2}:[\d]{2}:[\d]{2}.[\d]*) \((?P<APP>[\w\d]*)[/ ]?(?P<FUNCTION>.*)\) (?P<PAYLOAD>.*)",
+    ),
+];
+
+const SEARCH: &str = r"timeout|refused|SIGKILL";
+
+fn lines(fixture: &str) -> Vec<&str> {
+    let lines: Vec<&str> = fixture.lines().collect();
+    lines.repeat(REPEAT)
+}
+
+fn format(regex: &str) -> Format {
+    Format::new("Bench", regex).unwrap()
+}
+
+fn filter(alias: &str, action: FilterAction, filter: LogLine) -> Filter {
+    Filter {
+        alias: alias.into(),
+        action,
+        filter,
+    }
+}
+
+/// An include, an exclude and a marker, as a typical analysis would enable
+fn filters() -> Vec<Filter> {
+    vec![
+        filter(
+            "Problems",
+            FilterAction::INCLUDE,
+            LogLine {
+                payload: "timeout|refused|rejected|Failed|Killed|SIGKILL|503".into(),
+                ..Default::default()
+            },
+        ),
+        filter(
+            "No heartbeats",
+            FilterAction::EXCLUDE,
+            LogLine {
+                payload: "heartbeat|watchdog ping".into(),
+                ..Default::default()
+            },
+        ),
+        filter(
+            "Errors",
+            FilterAction::MARKER,
+            LogLine {
+                severity: "ERROR".into(),
+                color: Some((255, 0, 0)),
+                ..Default::default()
+            },
+        ),
+    ]
+}
+
+fn formatted(processor: &LineProcessor, name: &str, lines: &[&str]) -> Vec<LogLine> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| processor.format(name, line, index))
+        .collect()
+}
+
+fn bench_format(c: &mut Criterion) {
+    let mut group = c.benchmark_group("format");
+    group.sample_size(20);
+    for (name, fixture, regex) in FIXTURES {
+        let lines = lines(fixture);
+        let processor = LineProcessor::new(Some(&format(regex)), Vec::new()).unwrap();
+        group.throughput(Throughput::Elements(lines.len() as u64));
+        group.bench_function(name, |b| b.iter(|| formatted(&processor, name, &lines)));
+    }
+    group.finish();
+}
+
+fn bench_filter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("filter");
+    group.sample_size(20);
+    for (name, fixture, regex) in FIXTURES {
+        let lines = lines(fixture);
+        let processor = LineProcessor::new(Some(&format(regex)), filters()).unwrap();
+        group.throughput(Throughput::Elements(lines.len() as u64));
+
+        // Fields filtered in place, only the kept lines being built
+        group.bench_function(format!("{name}/process"), |b| {
+            b.iter(|| {
+                lines
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, line)| processor.process(name, line, index))
+                    .count()
+            })
+        });
+        let formatted = formatted(&processor, name, &lines);
+        group.bench_function(format!("{name}/formatted"), |b| {
+            b.iter_batched(
+                || formatted.clone(),
+                |formatted| {
+                    formatted
+                        .into_iter()
+                        .filter_map(|line| processor.filter(line))
+                        .count()
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search");
+    group.sample_size(20);
+    let search = Regex::new(SEARCH).unwrap();
+    for (name, fixture, regex) in FIXTURES {
+        let lines = lines(fixture);
+        let processor = LineProcessor::new(Some(&format(regex)), Vec::new()).unwrap();
+        let formatted = formatted(&processor, name, &lines);
+        group.throughput(Throughput::Elements(lines.len() as u64));
+
+        group.bench_function(format!("{name}/match"), |b| {
+            b.iter(|| {
+                formatted
+                    .iter()
+                    .filter(|line| LineProcessor::search(&search, line))
+                    .count()
+            })
+        });
+        let matches: Vec<&LogLine> = formatted
+            .iter()
+            .filter(|line| LineProcessor::search(&search, line))
+            .collect();
+        group.bench_function(format!("{name}/highlight"), |b| {
+            b.iter(|| {
+                matches
+                    .iter()
+                    .map(|line| LineProcessor::highlight(&search, line))
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_store(c: &mut Criterion) {
+    let mut group = c.benchmark_group("store");
+    let (name, fixture, regex) = FIXTURES[0];
+    let lines = lines(fixture);
+    let processor = LineProcessor::new(Some(&format(regex)), Vec::new()).unwrap();
+    let formatted = formatted(&processor, name, &lines);
+
+    group.sample_size(20);
+    group.throughput(Throughput::Elements(formatted.len() as u64));
+    group.bench_function("add_lines", |b| {
+        b.iter_batched(
+            InMemmoryAnalysisStore::new,
+            // The filled store is returned to be dropped out of the measure
+            |store| {
+                store.add_lines(&formatted);
+                store
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    let store = InMemmoryAnalysisStore::new();
+    store.add_lines(&formatted);
+    let total = formatted.len();
+    // Windows spread over the log, like a scroll through it
+    let positions: Vec<usize> = (0..total).step_by(total / 100).collect();
+    group.sample_size(100);
+    group.throughput(Throughput::Elements(positions.len() as u64));
+    group.bench_function("window", |b| {
+        b.iter(|| {
+            for from in &positions {
+                black_box(store.get_log_lines(*from, from + WINDOW));
+            }
+        })
+    });
+    group.bench_function("window_containing", |b| {
+        b.iter(|| {
+            for index in &positions {
+                black_box(store.get_log_lines_containing(*index, WINDOW));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_format, bench_filter, bench_search, bench_store);
+criterion_main!(benches);
@@ -1,24 +1,106 @@
+use regex::{Regex, RegexSet};
+
 use crate::models::{
     filter::{FilterAction, LogFilter},
-    log_line::LogLine,
+    log_line::{LineFields, LogLine},
+    severity::Severity,
 };
 
-/// Applies the given filter to a line deciding if the filtering requirements are satisfied
-/// and applying the filter color if needed
-fn filter_line<'a>(filtering: &'a LogFilter, log_line: &'a mut LogLine) -> bool {
+/// Color given to a line by the filters
+type Color = Option<(u8, u8, u8)>;
+
+/// Decide if the filtering requirements are satisfied by the line
+#[cfg(feature = "threads")]
+pub fn is_match(filtering: &LogFilter, log_line: &(impl LineFields + ?Sized)) -> bool {
     let mut is_match = false;
     for (key, re) in &filtering.filters {
-        is_match = re.is_match(log_line.get(key).unwrap());
+        is_match = re.is_match(log_line.field(key).unwrap());
         if !is_match {
             break;
         }
     }
 
-    if is_match {
-        log_line.color = filtering.color;
+    is_match
+}
+
+/// Enabled filters compiled for matching the lines against all of them at once
+///
+/// The regexes of every filter on the same field are joined in a `RegexSet`, so each field
+/// is scanned once per line instead of once per filter. A filter matches when the sets of
+/// all its fields matched its regexes, without running them one by one. The regexes of a field
+/// too large for a set are run one by one instead
+pub struct FilterSet {
+    filters: Vec<LogFilter>,
+    /// List of (log_line_key, regexes of the filters on the key, filter of each regex)
+    fields: Vec<(String, FieldRegexes, Vec<usize>)>,
+}
+
+/// Regexes of the filters on a field
+enum FieldRegexes {
+    Set(RegexSet),
+    /// Run one by one, their set exceeding the size limit
+    Each(Vec<Regex>),
+}
+
+impl FieldRegexes {
+    /// Positions of the regexes matching `value`
+    fn matches(&self, value: &str) -> Vec<usize> {
+        match self {
+            FieldRegexes::Set(set) => set.matches(value).into_iter().collect(),
+            FieldRegexes::Each(regexes) => (0..regexes.len())
+                .filter(|pattern| regexes[*pattern].is_match(value))
+                .collect(),
+        }
     }
+}
 
-    is_match
+impl FilterSet {
+    pub fn new(filters: Vec<LogFilter>) -> Self {
+        let mut patterns: Vec<(String, Vec<&Regex>, Vec<usize>)> = Vec::new();
+        for (position, filter) in filters.iter().enumerate() {
+            for (key, regex) in &filter.filters {
+                let field = match patterns.iter().position(|(field, _, _)| field == key) {
+                    Some(field) => &mut patterns[field],
+                    None => {
+                        patterns.push((key.clone(), Vec::new(), Vec::new()));
+                        patterns.last_mut().unwrap()
+                    }
+                };
+                field.1.push(regex);
+                field.2.push(position);
+            }
+        }
+
+        // The regexes are already valid, a set could only fail by exceeding the size limit
+        let fields = patterns
+            .into_iter()
+            .map(|(key, regexes, owners)| {
+                let regexes = match RegexSet::new(regexes.iter().map(|regex| regex.as_str())) {
+                    Ok(set) => FieldRegexes::Set(set),
+                    Err(_) => FieldRegexes::Each(regexes.into_iter().cloned().collect()),
+                };
+                (key, regexes, owners)
+            })
+            .collect();
+        Self { filters, fields }
+    }
+
+    /// Decide which filters are satisfied by the line, in the order of the filters
+    fn matches(&self, log_line: &(impl LineFields + ?Sized)) -> Vec<bool> {
+        let mut matched_fields = vec![0; self.filters.len()];
+        for (key, regexes, owners) in &self.fields {
+            let value = log_line.field(key).unwrap_or_default();
+            for pattern in regexes.matches(value) {
+                matched_fields[owners[pattern]] += 1;
+            }
+        }
+
+        self.filters
+            .iter()
+            .zip(matched_fields)
+            .map(|(filter, matched)| matched > 0 && matched == filter.filters.len())
+            .collect()
+    }
 }
 
 /// Apply a list of filters to a line
@@ -29,46 +111,65 @@ fn filter_line<'a>(filtering: &'a LogFilter, log_line: &'a mut LogLine) -> bool
 /// * If a line is to be included -> It is included
 /// * If a line is to be excluded (and it's not previously included) -> It is excluded
 /// * Marker filters are applied after to determine the final color
-pub fn apply_filters(filters: &[LogFilter], mut log_line: LogLine) -> Option<LogLine> {
-    let include_filters = filters
-        .iter()
-        .filter(|filter| filter.action == FilterAction::INCLUDE);
-    let exclude_filters = filters
-        .iter()
-        .filter(|filter| filter.action == FilterAction::EXCLUDE);
-    let marker_filters = filters
-        .iter()
-        .filter(|filter| filter.action == FilterAction::MARKER);
+pub fn apply_filters(filters: &FilterSet, mut log_line: LogLine) -> Option<LogLine> {
+    let color = filter_color(filters, &log_line)?;
+    if let Some(color) = color {
+        log_line.color = color;
+    }
+    Some(log_line)
+}
 
-    // If should be included check for any potential override of color with markers and return the line
-    for filter in include_filters.clone() {
-        if filter_line(filter, &mut log_line) {
-            for marker_filter in marker_filters {
-                filter_line(marker_filter, &mut log_line);
-            }
+/// Decide like `apply_filters` if the line is kept, reading its fields in place.
+///
+/// Returns `None` if the line is filtered out, otherwise the color the filters give it if any
+pub fn filter_color(
+    filters: &FilterSet,
+    log_line: &(impl LineFields + ?Sized),
+) -> Option<Option<Color>> {
+    let matches = filters.matches(log_line);
+    let matched = |action: FilterAction| {
+        filters
+            .filters
+            .iter()
+            .zip(&matches)
+            .filter(move |(filter, is_match)| filter.action == action && **is_match)
+            .map(|(filter, _)| filter)
+    };
+    let has_include_filters = filters
+        .filters
+        .iter()
+        .any(|filter| filter.action == FilterAction::INCLUDE);
 
-            return Some(log_line);
-        }
+    // If should be included check for any potential override of color with markers
+    if let Some(filter) = matched(FilterAction::INCLUDE).next() {
+        let color = matched(FilterAction::MARKER).next_back().unwrap_or(filter).color;
+        return Some(Some(color));
     }
 
     // If is not included and is excluded -> exclude it
-    for filter in exclude_filters {
-        if filter_line(filter, &mut log_line) {
-            return None;
-        }
+    if matched(FilterAction::EXCLUDE).next().is_some() {
+        return None;
     }
 
-    // If there are no including filters filter it just with markers and return the line
-    if include_filters.count() == 0 {
-        for filter in marker_filters {
-            filter_line(filter, &mut log_line);
-        }
-
-        return Some(log_line);
+    // If there are no including filters color it just with markers
+    if !has_include_filters {
+        return Some(matched(FilterAction::MARKER).next_back().map(|filter| filter.color));
     }
 
     // There was including filters but we didnt match. Line not to be included
-    return None
+    None
+}
+
+/// Apply the severity mask to a line, deciding if it's visible.
+/// Lines without a recognizable severity are always visible
+pub fn apply_severity_mask(
+    hidden_severities: &[Severity],
+    log_line: &(impl LineFields + ?Sized),
+) -> bool {
+    match Severity::parse(log_line.field("Severity").unwrap_or_default()) {
+        Some(severity) => !hidden_severities.contains(&severity),
+        None => true,
+    }
 }
 
 #[cfg(test)]
@@ -79,28 +180,30 @@ mod tests {
 
     #[test]
     fn match_found_on_every_individual_field() {
-        let run_test = |filter, mut line| {
-            let is_match = filter_line(&filter, &mut line);
+        let run_test = |filter: LogFilter, line| {
+            #[cfg(feature = "threads")]
+            assert!(is_match(&filter, &line));
+            let color = filter.color;
+            let line = apply_filters(&FilterSet::new(vec![filter]), line).unwrap();
 
-            assert_eq!(is_match, true);
-            assert_eq!(filter.color, line.color);
+            assert_eq!(color, line.color);
         };
 
         let line = LogLine {
-            log: "test".to_string(),
-            index: "0".to_string(),
-            date: "2022-01-02".to_string(),
-            timestamp: "200.05".to_string(),
-            app: "python".to_string(),
-            severity: "INFO".to_string(),
-            function: "call".to_string(),
-            payload: "some useful information".to_string(),
+            log: "test".into(),
+            index: 0,
+            date: "2022-01-02".into(),
+            timestamp: "200.05".into(),
+            app: "python".into(),
+            severity: "INFO".into(),
+            function: "call".into(),
+            payload: "some useful information".into(),
             ..Default::default()
         };
 
         let mut filter = LogFilter::from(Filter {
             filter: LogLine {
-                date: "2022-01-".to_string(),
+                date: "2022-01-".into(),
                 color: Some((255, 0, 0)),
                 ..Default::default()
             },
@@ -110,7 +213,7 @@ mod tests {
 
         filter = LogFilter::from(Filter {
             filter: LogLine {
-                timestamp: "200".to_string(),
+                timestamp: "200".into(),
                 color: Some((254, 0, 0)),
                 ..Default::default()
             },
@@ -120,7 +223,7 @@ mod tests {
 
         filter = LogFilter::from(Filter {
             filter: LogLine {
-                app: "python".to_string(),
+                app: "python".into(),
                 color: Some((253, 0, 0)),
                 ..Default::default()
             },
@@ -130,7 +233,7 @@ mod tests {
 
         filter = LogFilter::from(Filter {
             filter: LogLine {
-                severity: "INFO".to_string(),
+                severity: "INFO".into(),
                 color: Some((252, 0, 0)),
                 ..Default::default()
             },
@@ -140,7 +243,7 @@ mod tests {
 
         filter = LogFilter::from(Filter {
             filter: LogLine {
-                function: "call".to_string(),
+                function: "call".into(),
                 color: Some((251, 0, 0)),
                 ..Default::default()
             },
@@ -150,8 +253,8 @@ mod tests {
 
         filter = LogFilter::from(Filter {
             filter: LogLine {
-                index: "0".to_string(),
-                payload: "some use".to_string(),
+                index: 0,
+                payload: "some use".into(),
                 color: Some((250, 0, 0)),
                 ..Default::default()
             },
@@ -162,29 +265,104 @@ mod tests {
 
     #[test]
     fn dont_match_on_multiple_conditions_unsatisfied() {
-        let mut line = LogLine {
-            log: "test".to_string(),
-            index: "0".to_string(),
-            date: "2022-01-02".to_string(),
-            timestamp: "200.05".to_string(),
-            app: "python".to_string(),
-            severity: "INFO".to_string(),
-            function: "call".to_string(),
-            payload: "some useful information".to_string(),
+        let line = LogLine {
+            log: "test".into(),
+            index: 0,
+            date: "2022-01-02".into(),
+            timestamp: "200.05".into(),
+            app: "python".into(),
+            severity: "INFO".into(),
+            function: "call".into(),
+            payload: "some useful information".into(),
             ..Default::default()
         };
         let filter = LogFilter::from(Filter {
             filter: LogLine {
-                date: "2022-01-".to_string(),
-                timestamp: "100".to_string(),
+                date: "2022-01-".into(),
+                timestamp: "100".into(),
                 color: Some((255, 0, 0)),
                 ..Default::default()
             },
             ..Default::default()
         });
 
-        let is_match = filter_line(&filter, &mut line);
-        assert_eq!(is_match, false);
-        assert_ne!(filter.color, line.color);
+        #[cfg(feature = "threads")]
+        assert!(!is_match(&filter, &line));
+        let line = apply_filters(&FilterSet::new(vec![filter]), line).unwrap();
+        assert_eq!(line.color, None);
+    }
+
+    #[test]
+    fn filters_on_the_same_field_follow_the_action_priority() {
+        let filter = |action, payload: &str, color| {
+            LogFilter::from(Filter {
+                action,
+                filter: LogLine {
+                    payload: payload.into(),
+                    color: Some(color),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+        };
+        let line = |payload: &str| LogLine {
+            payload: payload.into(),
+            ..Default::default()
+        };
+        let filters = FilterSet::new(vec![
+            filter(FilterAction::EXCLUDE, "noise", (1, 0, 0)),
+            filter(FilterAction::INCLUDE, "ERROR", (2, 0, 0)),
+            filter(FilterAction::MARKER, "disk", (3, 0, 0)),
+            filter(FilterAction::INCLUDE, "WARN", (4, 0, 0)),
+        ]);
+
+        let included = apply_filters(&filters, line("ERROR noise")).unwrap();
+        assert_eq!(included.color, Some((2, 0, 0)));
+        let marked = apply_filters(&filters, line("WARN disk full")).unwrap();
+        assert_eq!(marked.color, Some((3, 0, 0)));
+        assert!(apply_filters(&filters, line("INFO noise")).is_none());
+        assert!(apply_filters(&filters, line("INFO disk")).is_none());
+
+        let markers = FilterSet::new(vec![filter(FilterAction::MARKER, "disk", (3, 0, 0))]);
+        assert_eq!(apply_filters(&markers, line("INFO disk")).unwrap().color, Some((3, 0, 0)));
+        assert_eq!(apply_filters(&markers, line("INFO")).unwrap().color, None);
+    }
+
+    #[test]
+    fn filters_too_large_for_a_set_are_matched_one_by_one() {
+        let filter = |payload: &str| {
+            LogFilter::from(Filter {
+                alias: payload.to_string(),
+                action: FilterAction::INCLUDE,
+                filter: LogLine {
+                    payload: payload.into(),
+                    ..Default::default()
+                },
+            })
+        };
+        // Each regex fits the size limit, not both of them
+        let filters = FilterSet::new(vec![filter(r"\w{50}x"), filter(r"\w{50}")]);
+        let payload = filters.fields.iter().find(|(key, _, _)| key == "Payload").unwrap();
+        assert!(matches!(payload.1, FieldRegexes::Each(_)));
+
+        let line = LogLine {
+            payload: "a".repeat(50).into(),
+            ..Default::default()
+        };
+        assert!(apply_filters(&filters, line).is_some());
+        assert!(apply_filters(&filters, LogLine::default()).is_none());
+    }
+
+    #[test]
+    fn severity_mask_hides_only_masked_severities() {
+        let line = |severity: &str| LogLine {
+            severity: severity.into(),
+            ..Default::default()
+        };
+        let hidden = [Severity::Debug];
+
+        assert!(!apply_severity_mask(&hidden, &line("DEBUG")));
+        assert!(apply_severity_mask(&hidden, &line("ERROR")));
+        assert!(apply_severity_mask(&hidden, &line("")));
     }
 }
@@ -1,50 +1,144 @@
-use regex::{Captures, Regex};
-
-use crate::models::log_line::LogLine;
-
-/// Creates a default log line assigning the line content to payload and the index
-fn default_log_line(line: &str, path: &str, index: usize) -> LogLine {
-    LogLine {
-        log: path.to_string(),
-        index: index.to_string(),
-        payload: line.to_string(),
-        color: None,
-        ..Default::default()
+use std::ops::Range;
+
+use regex::Regex;
+
+#[cfg(feature = "threads")]
+use crate::domain::parse_time::parse_time;
+use crate::models::log_line::{LineFields, LogLine, SmolStr};
+
+/// Keys of the fields captured by the formats, in the order of `FormattedLine::fields`
+const FIELDS: [&str; 6] = ["DATE", "TIMESTAMP", "APP", "SEVERITY", "FUNCTION", "PAYLOAD"];
+
+/// A raw line with the ranges of the fields captured by its format, read in place.
+///
+/// Filtering a line only needs some of its fields, so the `LogLine` copying them is only built
+/// for the lines kept
+pub struct FormattedLine<'a> {
+    path: &'a SmolStr,
+    line: &'a str,
+    index: usize,
+    /// Ranges in `line` of the date, timestamp, app, severity, function and payload
+    fields: [Range<usize>; 6],
+    /// Bytes cut from the end of the raw line, see `format_line_within`
+    truncated: usize,
+}
+
+impl<'a> FormattedLine<'a> {
+    /// Text of the field with the `FIELDS` key at `position`
+    fn text(&self, position: usize) -> &'a str {
+        &self.line[self.fields[position].clone()]
+    }
+
+    /// Get the time of this line in seconds, parsed from the date or the timestamp
+    #[cfg(feature = "threads")]
+    pub fn time(&self) -> Option<f64> {
+        parse_time(self.text(0), self.text(1))
+    }
+
+    /// Build the `LogLine` with a copy of the fields. The payload of a truncated line ends with
+    /// the `truncation_marker` of the bytes cut
+    pub fn to_log_line(&self) -> LogLine {
+        let payload = match self.truncated {
+            0 => self.text(5).into(),
+            truncated => format!("{}{}", self.text(5), truncation_marker(truncated)).into(),
+        };
+
+        LogLine {
+            log: self.path.clone(),
+            index: self.index,
+            raw_index: self.index,
+            date: self.text(0).into(),
+            timestamp: self.text(1).into(),
+            app: self.text(2).into(),
+            severity: self.text(3).into(),
+            function: self.text(4).into(),
+            payload,
+            color: None,
+        }
     }
 }
 
-/// Apply the given format (if any) to the given line
-pub fn apply_format(format: &Option<&Regex>, path: &str, line: &str, index: usize) -> LogLine {
-    match format {
-        Some(format) => match format.captures(line) {
-            Some(captures) => {
-                let unwrap_or_empty_string = |capture: &Captures, key: &str| -> String {
-                    let str = match capture.name(key) {
-                        Some(m) => m.as_str(),
-                        None => "",
-                    };
-
-                    str.to_string()
-                };
-
-                LogLine {
-                    log: path.to_string(),
-                    index: index.to_string(),
-                    date: unwrap_or_empty_string(&captures, "DATE"),
-                    timestamp: unwrap_or_empty_string(&captures, "TIMESTAMP"),
-                    app: unwrap_or_empty_string(&captures, "APP"),
-                    severity: unwrap_or_empty_string(&captures, "SEVERITY"),
-                    function: unwrap_or_empty_string(&captures, "FUNCTION"),
-                    payload: unwrap_or_empty_string(&captures, "PAYLOAD"),
-                    ..Default::default()
-                }
-            }
-            _ => default_log_line(line, path, index),
-        },
-        _ => default_log_line(line, path, index),
+impl LineFields for FormattedLine<'_> {
+    fn field(&self, key: &str) -> Option<&str> {
+        match key {
+            "Log" => Some(self.path.as_str()),
+            "Date" => Some(self.text(0)),
+            "Timestamp" => Some(self.text(1)),
+            "App" => Some(self.text(2)),
+            "Severity" => Some(self.text(3)),
+            "Function" => Some(self.text(4)),
+            "Payload" => Some(self.text(5)),
+            _ => None,
+        }
+    }
+
+    fn position(&self) -> usize {
+        self.index
     }
 }
 
+/// Find the fields of the given format (if any) in the given line, without copying them.
+/// A line not matching the format is all payload. The lines of a source share its `path`
+pub fn format_line<'a>(
+    format: &Option<&Regex>,
+    path: &'a SmolStr,
+    line: &'a str,
+    index: usize,
+) -> FormattedLine<'a> {
+    let mut fields = [0..0, 0..0, 0..0, 0..0, 0..0, 0..line.len()];
+    if let Some(captures) = format.and_then(|format| format.captures(line)) {
+        for (field, key) in fields.iter_mut().zip(FIELDS) {
+            *field = captures.name(key).map_or(0..0, |m| m.range());
+        }
+    }
+
+    FormattedLine {
+        path,
+        line,
+        index,
+        fields,
+        truncated: 0,
+    }
+}
+
+/// Indicator ending the payload of a line cut by `format_line_within`, with the `bytes` cut
+pub fn truncation_marker(bytes: usize) -> String {
+    format!(" …[+{} bytes]", bytes)
+}
+
+/// Like `format_line`, a line longer than `max_length` bytes being cut on the char boundary
+/// before, so a huge line neither stalls the regexes nor the rendering. The raw line is kept
+/// whole by the log store
+#[cfg(feature = "threads")]
+pub fn format_line_within<'a>(
+    format: &Option<&Regex>,
+    path: &'a SmolStr,
+    line: &'a str,
+    index: usize,
+    max_length: usize,
+) -> FormattedLine<'a> {
+    if line.len() <= max_length {
+        return format_line(format, path, line, index);
+    }
+    let mut end = max_length;
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    FormattedLine {
+        truncated: line.len() - end,
+        ..format_line(format, path, &line[..end], index)
+    }
+}
+
+/// Apply the given format (if any) to the given line. The lines of a source share its `path`
+pub fn apply_format(
+    format: &Option<&Regex>,
+    path: &SmolStr,
+    line: &str,
+    index: usize,
+) -> LogLine {
+    format_line(format, path, line, index).to_log_line()
+}
 
 #[cfg(test)]
 mod tests {
@@ -53,14 +147,15 @@ mod tests {
     #[test]
     fn assign_content_to_payload_if_no_format() {
         let line = "Test";
-        let log_line = apply_format(&None, "", line, 0);
+        let log_line = apply_format(&None, &SmolStr::default(), line, 0);
         assert_eq!(line, log_line.payload)
     }
 
     #[test]
     fn assign_content_to_payload_if_no_matches() {
         let line = "Test";
-        let log_line = apply_format(&Some(&Regex::new("\\d").unwrap()), "", line, 0);
+        let format = Regex::new("\\d").unwrap();
+        let log_line = apply_format(&Some(&format), &SmolStr::default(), line, 0);
         assert_eq!(line, log_line.payload)
     }
 
@@ -68,7 +163,7 @@ mod tests {
     fn test_format() {
         let line = "2022-05-27 [1234] test INFO assign_content_to_payload_if_no_matches testing if formatting works";
         let re = Regex::new("(?P<DATE>[\\d]{4}-[\\d]{2}-[\\d]{2}) \\[(?P<TIMESTAMP>[\\d]{4})\\] (?P<APP>[\\w]*) (?P<SEVERITY>[\\w]*) (?P<FUNCTION>[\\w_]*) (?P<PAYLOAD>.*)").unwrap();
-        let log_line = apply_format(&Some(&re), "", line, 0);
+        let log_line = apply_format(&Some(&re), &SmolStr::default(), line, 0);
         assert_eq!("2022-05-27", log_line.date);
         assert_eq!("1234", log_line.timestamp);
         assert_eq!("test", log_line.app);
@@ -76,4 +171,39 @@ mod tests {
         assert_eq!("assign_content_to_payload_if_no_matches", log_line.function);
         assert_eq!("testing if formatting works", log_line.payload);
     }
+
+    #[test]
+    fn fields_are_read_in_place() {
+        let path = SmolStr::new("app.log");
+        let format = Regex::new(r"(?P<DATE>\S+) (?P<SEVERITY>\w+) (?P<PAYLOAD>.*)").unwrap();
+        let line = format_line(&Some(&format), &path, "2022-05-27T10:00:01 WARN disk full", 3);
+
+        assert_eq!(line.field("Severity"), Some("WARN"));
+        assert_eq!(line.field("App"), Some(""));
+        assert_eq!(line.field("Log"), Some("app.log"));
+        assert_eq!(line.field("Index"), None);
+        #[cfg(feature = "threads")]
+        assert_eq!(line.time(), line.to_log_line().time());
+        let log_line = line.to_log_line();
+        assert_eq!((log_line.index, log_line.raw_index), (3, 3));
+        assert_eq!(log_line.payload, "disk full");
+
+        let unformatted = format_line(&Some(&format), &path, "garbage", 0);
+        assert_eq!(unformatted.field("Payload"), Some("garbage"));
+        assert_eq!(unformatted.field("Date"), Some(""));
+    }
+
+    #[test]
+    #[cfg(feature = "threads")]
+    fn long_lines_are_truncated_on_a_char_boundary() {
+        let path = SmolStr::new("app.log");
+        let format = Regex::new(r"(?P<SEVERITY>\w+) (?P<PAYLOAD>.*)").unwrap();
+        let line = format_line_within(&Some(&format), &path, "WARN dump: ééé", 0, 13);
+        assert_eq!(line.field("Severity"), Some("WARN"));
+        assert_eq!(line.field("Payload"), Some("dump: é"));
+        assert_eq!(line.to_log_line().payload, "dump: é …[+4 bytes]");
+
+        let line = format_line_within(&Some(&format), &path, "INFO short", 0, 10);
+        assert_eq!(line.to_log_line().payload, "short");
+    }
 }
\ No newline at end of file
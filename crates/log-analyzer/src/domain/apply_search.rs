@@ -1,82 +1,88 @@
+use std::ops::Range;
+
 use regex::Regex;
 
-use crate::models::{log_line::LogLine, log_line_styled::LogLineStyled};
+use crate::models::log_line::LogLine;
+use crate::models::log_line_styled::{Highlight, LogLineStyled};
 
 /// Tries to match the given search expression to all fields of the log
 pub fn apply_search(search: &Regex, log_line: &LogLine) -> bool {
     log_line.into_iter().rev().any(|str| search.is_match(str))
 }
 
-/// Embed group information in the log line fields.
-/// This is used to display formated text.
-///
-/// The string fields are serialized into json in the form of
-/// `[(Option<Group>, Content), ...]`. The group can be used to later be matched
-/// with a color in the Front End
-pub fn format_search(search: &Regex, log_line: &LogLine) -> LogLineStyled {
-    let mut columns: Vec<Vec<(Option<String>, String)>> = LogLine::columns()
-        .into_iter()
-        .map(|column| {
-            let s = log_line.get(&column).unwrap();
-            let mut groups = vec![];
-            if let Some(m) = search.captures(s) {
-                // Capture all matched groups
-                for group in search.capture_names().flatten() {
-                    if let Some(capture) = m.name(group) {
-                        groups.push((group, (capture.start(), capture.end())))
-                    }
-                }
+/// Tells if every line matched by the search `query` is matched by the `previous` one, so only
+/// the results of `previous` need to be searched again. Only known when both are plain text,
+/// `query` containing `previous`
+#[cfg(feature = "threads")]
+pub fn is_refinement(previous: &str, query: &str) -> bool {
+    let is_text = |search: &str| regex::escape(search) == search;
+    is_text(previous) && is_text(query) && query.contains(previous)
+}
 
-                let mut string_groups = vec![];
-
-                // If there are captured groups manage the splitting between unformatted and captured parts of the string
-                if !groups.is_empty() {
-                    let mut offset = 0;
-                    for (group, (start, end)) in groups {
-                        let unmatched = &s[offset..start];
-                        if !unmatched.is_empty() {
-                            string_groups.push((None, unmatched.to_string()));
-                        }
-                        string_groups.push((Some(group.to_string()), s[start..end].to_string()));
-                        offset = end;
-                    }
-
-                    if offset < (s.len().saturating_sub(1)) {
-                        string_groups.push((None, s[offset..].to_string()));
-                    }
-                }
-                // Otherwise just add the entire string without any format
-                else {
-                    string_groups.push((None, s.to_string()));
+/// Parts of the fields of the line matched by the named groups of the search, the first match
+/// of each field. The groups overlapping a previous one are left out. The index is not searched
+/// so is not highlighted, like `apply_search`
+pub fn find_highlights(search: &Regex, log_line: &LogLine) -> Vec<Highlight> {
+    let mut highlights = vec![];
+    if search.capture_names().flatten().next().is_none() {
+        return highlights;
+    }
+
+    for (column, key) in LogLine::columns().iter().enumerate() {
+        let Some(text) = log_line.get_text(key) else {
+            continue;
+        };
+        if let Some(captures) = search.captures(text) {
+            let mut groups: Vec<(&str, Range<usize>)> = search
+                .capture_names()
+                .flatten()
+                .filter_map(|group| captures.name(group).map(|m| (group, m.range())))
+                .collect();
+            groups.sort_by_key(|(_, range)| range.start);
+
+            let mut offset = 0;
+            for (group, range) in groups {
+                if range.start >= offset && !range.is_empty() {
+                    offset = range.end;
+                    highlights.push(Highlight {
+                        column,
+                        group: group.into(),
+                        range,
+                    });
                 }
-                return string_groups;
             }
-            return vec![(None, s.to_string())]
-        })
-        .collect();
-
-    LogLineStyled {
-        log: std::mem::take(&mut columns[0]),
-        index: std::mem::take(&mut columns[1]),
-        date: std::mem::take(&mut columns[2]),
-        timestamp: std::mem::take(&mut columns[3]),
-        app: std::mem::take(&mut columns[4]),
-        severity: std::mem::take(&mut columns[5]),
-        function: std::mem::take(&mut columns[6]),
-        payload: std::mem::take(&mut columns[7]),
-        color: log_line.color,
+        }
     }
+    highlights
+}
+
+/// Split the fields of the line in `(Option<Group>, Content)` groups, the groups matched by the
+/// search named after them. The group can be used to later be matched with a color in the Front
+/// End
+pub fn format_search(search: &Regex, log_line: &LogLine) -> LogLineStyled {
+    LogLineStyled::new(log_line, &find_highlights(search, log_line))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    #[cfg(feature = "threads")]
+    fn refinements_are_narrower_plain_text_searches() {
+        assert!(is_refinement("ERR", "ERROR"));
+        assert!(is_refinement("", "ERROR"));
+        assert!(is_refinement("ERROR", "ERROR"));
+        assert!(!is_refinement("ERROR", "ERR"));
+        assert!(!is_refinement("ERR", "ERR|INFO"));
+        assert!(!is_refinement("E.R", "E.ROR"));
+    }
+
     #[test]
     fn correct_formatting() {
         let line = LogLine {
             log: "test.log".into(),
-            index: "0".into(),
+            index: 0,
             date: "2022-06-02".into(),
             timestamp: "42".into(),
             app: "test".into(),
@@ -99,4 +105,24 @@ mod tests {
         assert!(formatted_line.payload[1].0 == Some("BLACK".to_string()));
         assert!(formatted_line.payload[1].1 == "awesome");
     }
+
+    #[test]
+    fn highlights_are_the_groups_matched_in_each_field() {
+        let line = LogLine {
+            severity: "ERROR".into(),
+            payload: "ERROR: disk full".into(),
+            ..Default::default()
+        };
+
+        let regex = Regex::new("(?P<RED>ERROR)(?P<BLUE>: )?").unwrap();
+        let highlights = find_highlights(&regex, &line);
+        assert_eq!(highlights.len(), 3);
+        assert_eq!((highlights[0].column, highlights[0].range.clone()), (5, 0..5));
+        assert_eq!(highlights[2].group, "BLUE");
+        assert_eq!((highlights[2].column, highlights[2].range.clone()), (7, 5..7));
+        // Nested groups overlap the outer one
+        let regex = Regex::new("(?P<RED>disk (?P<BLUE>full))").unwrap();
+        assert_eq!(find_highlights(&regex, &line).len(), 1);
+        assert!(find_highlights(&Regex::new("ERROR").unwrap(), &line).is_empty());
+    }
 }
@@ -0,0 +1,36 @@
+use regex::Regex;
+
+use crate::models::log_line::LogLine;
+
+/// Extract the correlation id of a line (e.g. a request id) with the `key` expression.
+///
+/// The fields are tried from the payload backwards and the first match wins. The id is the
+/// first capture group of the expression, or the whole match if it has no groups
+pub fn correlation_id(key: &Regex, log_line: &LogLine) -> Option<String> {
+    log_line.into_iter().rev().find_map(|field| {
+        let captures = key.captures(field)?;
+        let id = captures.get(1).or_else(|| captures.get(0))?.as_str();
+        (!id.is_empty()).then(|| id.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn correlation_id_is_the_first_group() {
+        let line = LogLine {
+            app: "req=app".into(),
+            payload: "handled req=42 in 3ms".into(),
+            ..Default::default()
+        };
+
+        let key = Regex::new(r"req=(\d+)").unwrap();
+        assert_eq!(correlation_id(&key, &line).as_deref(), Some("42"));
+        let key = Regex::new(r"req=\w+").unwrap();
+        assert_eq!(correlation_id(&key, &line).as_deref(), Some("req=42"));
+        let key = Regex::new(r"trace=(\w+)").unwrap();
+        assert_eq!(correlation_id(&key, &line), None);
+    }
+}
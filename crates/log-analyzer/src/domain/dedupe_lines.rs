@@ -0,0 +1,122 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+
+use smol_str::SmolStr;
+
+use crate::models::dedupe::DedupeConfig;
+use crate::models::log_line::LogLine;
+
+/// Hash of the payload of a line compared case insensitively and without the spaces around
+/// its words, so a line written to two files with a different padding is still the same
+fn content_key(payload: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for word in payload.split_whitespace() {
+        for c in word.chars() {
+            c.to_lowercase().for_each(|c| c.hash(&mut hasher));
+        }
+        ' '.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Hide the lines already read from another source around the same time, e.g. a daemon
+/// writing the same events to its own log and to syslog.
+///
+/// The payloads are remembered per interval of `window_secs` of their time, the first source
+/// to have a payload in an interval keeping it. Which source that is depends on the order the
+/// lines are given in, not on their time. Lines without time are never duplicates
+pub struct Deduplicator {
+    config: DedupeConfig,
+    /// First source and time of each payload per interval
+    seen: HashMap<(u64, i64), (SmolStr, f64)>,
+    /// Keys of `seen` in the order they were added, to forget the oldest ones
+    order: VecDeque<(u64, i64)>,
+}
+
+impl Deduplicator {
+    pub fn new(config: DedupeConfig) -> Self {
+        Self {
+            config,
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// `line` has the payload of a line of another source at most `window_secs` apart.
+    /// Otherwise the line is remembered
+    pub fn is_duplicate(&mut self, line: &LogLine) -> bool {
+        let time = match line.time() {
+            Some(time) => time,
+            None => return false,
+        };
+        let window = self.config.window_secs;
+        let key = content_key(&line.payload);
+        let interval = (time / window).floor() as i64;
+
+        let duplicate = (interval - 1..=interval + 1).any(|interval| {
+            self.seen.get(&(key, interval)).is_some_and(|(source, seen)| {
+                *source != line.log && (time - seen).abs() <= window
+            })
+        });
+        if !duplicate && !self.seen.contains_key(&(key, interval)) {
+            self.seen.insert((key, interval), (line.log.clone(), time));
+            self.order.push_back((key, interval));
+            while self.order.len() > self.config.max_keys {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.seen.remove(&oldest);
+                }
+            }
+        }
+        duplicate
+    }
+
+    /// Forget the lines seen, e.g. before filtering the log again
+    pub fn reset(&mut self) {
+        self.seen.clear();
+        self.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(log: &str, date: &str, payload: &str) -> LogLine {
+        LogLine {
+            log: log.into(),
+            date: date.into(),
+            payload: payload.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn lines_of_another_source_within_the_window_are_duplicates() {
+        let mut dedupe = Deduplicator::new(DedupeConfig::default());
+        assert!(!dedupe.is_duplicate(&line("daemon", "12:00:00.900", "Disk  FULL")));
+        assert!(dedupe.is_duplicate(&line("syslog", "12:00:01.200", "disk full")));
+        // Repeated in its own source, or too late
+        assert!(!dedupe.is_duplicate(&line("daemon", "12:00:01.000", "disk full")));
+        assert!(!dedupe.is_duplicate(&line("syslog", "12:00:03.000", "disk full")));
+        // Without time
+        assert!(!dedupe.is_duplicate(&line("daemon", "", "started")));
+        assert!(!dedupe.is_duplicate(&line("syslog", "", "started")));
+    }
+
+    #[test]
+    fn the_oldest_payloads_are_forgotten() {
+        let config = DedupeConfig {
+            max_keys: 1,
+            ..Default::default()
+        };
+        let mut dedupe = Deduplicator::new(config);
+        dedupe.is_duplicate(&line("daemon", "12:00:00", "first"));
+        dedupe.is_duplicate(&line("daemon", "12:00:00", "second"));
+        assert!(dedupe.is_duplicate(&line("syslog", "12:00:00", "second")));
+        assert!(!dedupe.is_duplicate(&line("syslog", "12:00:00", "first")));
+
+        dedupe.reset();
+        assert!(!dedupe.is_duplicate(&line("daemon", "12:00:00", "first")));
+    }
+}
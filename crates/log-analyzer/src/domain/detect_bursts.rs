@@ -0,0 +1,152 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::models::burst::{Burst, BurstConfig};
+use crate::models::log_line::LogLine;
+
+/// Lines per interval of all the lines (`None`) and of each severity
+type Series = BTreeMap<Option<String>, BTreeMap<i64, usize>>;
+
+/// Count the filtered lines per interval of their time and find the intervals exceeding the
+/// usual rate by the configured factor.
+///
+/// Each burst is found once, even if the lines are counted again after a refilter
+pub struct BurstDetector {
+    config: BurstConfig,
+    series: Series,
+    reported: HashSet<(Option<String>, i64)>,
+}
+
+impl BurstDetector {
+    pub fn new(config: BurstConfig) -> Self {
+        Self {
+            config,
+            series: BTreeMap::new(),
+            reported: HashSet::new(),
+        }
+    }
+
+    pub fn config(&self) -> BurstConfig {
+        self.config
+    }
+
+    /// Count the lines with a recognizable time in their interval
+    pub fn add_lines<L: Borrow<LogLine>>(&mut self, lines: &[L]) {
+        let interval = self.config.interval_secs.max(1) as i64;
+        for line in lines {
+            let line = line.borrow();
+            let time = match line.time() {
+                Some(time) => time.floor() as i64,
+                None => continue,
+            };
+            let start = time - time.rem_euclid(interval);
+            *self.series.entry(None).or_default().entry(start).or_default() += 1;
+            if !line.severity.is_empty() {
+                *self
+                    .series
+                    .entry(Some(line.severity.to_string()))
+                    .or_default()
+                    .entry(start)
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    /// Forget the counted lines, keeping the bursts already found
+    pub fn reset_counts(&mut self) {
+        self.series.clear();
+    }
+
+    /// Find the bursts not found yet, sorted by time
+    pub fn detect(&mut self) -> Vec<Burst> {
+        let interval = self.config.interval_secs.max(1) as i64;
+        let window = self.config.window.max(1);
+        let mut bursts = Vec::new();
+
+        // Intervals before the first line are unknown, not quiet
+        let first = match self.series.get(&None).and_then(|counts| counts.keys().next()) {
+            Some(first) => *first,
+            None => return bursts,
+        };
+        for (severity, counts) in &self.series {
+            for (start, lines) in counts {
+                let previous = (((start - first) / interval) as usize).min(window);
+                if previous == 0 || *lines < self.config.min_lines {
+                    continue;
+                }
+                let usual = counts
+                    .range(start - previous as i64 * interval..*start)
+                    .map(|(_, lines)| *lines)
+                    .sum::<usize>() as f64
+                    / previous as f64;
+                if *lines as f64 > self.config.factor * usual.max(1.0)
+                    && self.reported.insert((severity.clone(), *start))
+                {
+                    bursts.push(Burst {
+                        severity: severity.clone(),
+                        start: *start,
+                        end: start + interval,
+                        lines: *lines,
+                        usual: usual.round() as usize,
+                    });
+                }
+            }
+        }
+
+        bursts.sort_by_key(|burst| burst.start);
+        bursts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(second: i64, severity: &str, count: usize) -> Vec<LogLine> {
+        (0..count)
+            .map(|_| LogLine {
+                timestamp: second.to_string().into(),
+                severity: severity.into(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spikes_over_the_usual_rate_are_found_once() {
+        let mut detector = BurstDetector::new(BurstConfig {
+            interval_secs: 10,
+            window: 3,
+            factor: 5.0,
+            min_lines: 20,
+        });
+        for second in [100, 110, 120, 130] {
+            detector.add_lines(&lines(second, "INFO", 10));
+        }
+        detector.add_lines(&lines(140, "ERROR", 30));
+
+        let bursts = detector.detect();
+        assert_eq!(bursts.len(), 1);
+        assert_eq!(bursts[0].severity.as_deref(), Some("ERROR"));
+        assert_eq!((bursts[0].start, bursts[0].end, bursts[0].lines), (140, 150, 30));
+        assert!(bursts[0].contains(149.5));
+
+        // Bursts are found once and the overall rate only tripled, under the factor
+        detector.reset_counts();
+        for second in [100, 110, 120, 130] {
+            detector.add_lines(&lines(second, "INFO", 10));
+        }
+        detector.add_lines(&lines(140, "ERROR", 30));
+        assert!(detector.detect().is_empty());
+    }
+
+    #[test]
+    fn first_interval_and_small_counts_are_not_bursts() {
+        let mut detector = BurstDetector::new(BurstConfig::default());
+        detector.add_lines(&lines(0, "ERROR", 100));
+        detector.add_lines(&lines(10, "WARN", 10));
+        detector.add_lines(&lines(100, "WARN", 10));
+
+        assert!(detector.detect().is_empty());
+    }
+}
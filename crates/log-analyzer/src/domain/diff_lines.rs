@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::models::{diff::Diff, log_line::LogLine};
+
+/// Dates and clock times, e.g. `2023-07-22`, `12:03:45` or `12:03:45.120`
+const TIME_PATTERN: &str = r"\d{4}-\d{2}-\d{2}|\d{1,2}:\d{2}:\d{2}(?:[.,]\d+)?";
+
+/// Content compared by the diff: every field but the source, the date and the timestamp, with
+/// the times found in them masked
+fn masked(time: &Regex, line: &LogLine) -> String {
+    [&line.app, &line.severity, &line.function, &line.payload]
+        .iter()
+        .map(|field| time.replace_all(field, "<time>"))
+        .collect::<Vec<_>>()
+        .join("\u{1f}")
+}
+
+/// Keep the lines of `lines` missing from `other`. A line repeated more times on one side than
+/// on the other is kept as many times as it is in excess
+fn unique_lines(time: &Regex, lines: &[&LogLine], other: &[&LogLine]) -> Vec<LogLine> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for line in other {
+        *counts.entry(masked(time, line)).or_default() += 1;
+    }
+
+    lines
+        .iter()
+        .filter(|line| match counts.get_mut(&masked(time, line)) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .map(|line| (*line).clone())
+        .collect()
+}
+
+/// Find the lines of each side that are not on the other one, comparing them with their times masked
+pub fn diff_lines(left: &[&LogLine], right: &[&LogLine]) -> Diff {
+    static TIME: OnceLock<Regex> = OnceLock::new();
+    let time = TIME.get_or_init(|| Regex::new(TIME_PATTERN).unwrap());
+    let left_only = unique_lines(time, left, right);
+    let right_only = unique_lines(time, right, left);
+
+    Diff {
+        common: left.len() - left_only.len(),
+        left: left_only,
+        right: right_only,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(log: &str, timestamp: &str, payload: &str) -> LogLine {
+        LogLine {
+            log: log.into(),
+            timestamp: timestamp.into(),
+            payload: payload.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn lines_are_compared_without_their_times() {
+        let left = [
+            line("a.log", "1", "boot at 10:00:01.250"),
+            line("a.log", "2", "wifi connected"),
+            line("a.log", "3", "retry"),
+            line("a.log", "4", "retry"),
+        ];
+        let right = [
+            line("b.log", "7", "boot at 11:30:00.000"),
+            line("b.log", "8", "retry"),
+            line("b.log", "9", "wifi failed"),
+        ];
+
+        let diff = diff_lines(&left.iter().collect::<Vec<_>>(), &right.iter().collect::<Vec<_>>());
+
+        let payloads = |lines: &[LogLine]| -> Vec<String> {
+            lines.iter().map(|line| line.payload.to_string()).collect()
+        };
+        assert_eq!(diff.common, 2);
+        assert_eq!(payloads(&diff.left), vec!["wifi connected", "retry"]);
+        assert_eq!(diff.left[1].timestamp, "4");
+        assert_eq!(payloads(&diff.right), vec!["wifi failed"]);
+    }
+}
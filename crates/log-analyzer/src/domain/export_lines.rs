@@ -0,0 +1,286 @@
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use crate::error::{LogAnalyzerError, Result};
+use crate::models::{
+    export::ExportFormat,
+    log_line::LogLine,
+    log_line_styled::{LogLineStyled, StyledGroups},
+};
+
+/// Number of lines fetched at once when exporting them
+const EXPORT_BATCH_SIZE: usize = 10_000;
+
+/// Background of the highlight groups named after a color in the HTML report. Other groups
+/// take the one of `mark`
+const HTML_GROUP_COLORS: [(&str, &str); 16] = [
+    ("black", "#000000"),
+    ("white", "#ffffff"),
+    ("red", "#cd3131"),
+    ("green", "#0dbc79"),
+    ("yellow", "#e5e510"),
+    ("blue", "#2472c8"),
+    ("magenta", "#bc3fbc"),
+    ("cyan", "#11a8cd"),
+    ("gray", "#808080"),
+    ("darkgray", "#666666"),
+    ("lightred", "#f14c4c"),
+    ("lightgreen", "#23d18b"),
+    ("lightyellow", "#f5f543"),
+    ("lightblue", "#3b8eea"),
+    ("lightmagenta", "#d670d6"),
+    ("lightcyan", "#29b8db"),
+];
+
+/// Write the `total` lines fetched one batch after another with `fetch(from, to)` to the file at
+/// `path`, replacing it. Stops early if the lines are cleared or refiltered meanwhile. Returns
+/// the number of lines written
+pub fn export_to_file<F>(
+    path: &str,
+    format: ExportFormat,
+    title: &str,
+    total: usize,
+    fetch: F,
+) -> Result<usize>
+where
+    F: Fn(usize, usize) -> Vec<LogLineStyled>,
+{
+    let failed = |err: io::Error| LogAnalyzerError::ExportFailed {
+        path: path.to_string(),
+        reason: err.to_string(),
+    };
+    let file = BufWriter::new(File::create(path).map_err(failed)?);
+    let mut exporter = LineExporter::new(file, format, title).map_err(failed)?;
+
+    let mut exported = 0;
+    while exported < total {
+        let lines = fetch(exported, (exported + EXPORT_BATCH_SIZE).min(total));
+        if lines.is_empty() {
+            break;
+        }
+        exporter.write(&lines).map_err(failed)?;
+        exported += lines.len();
+    }
+    exporter.finish().map_err(failed)
+}
+
+/// Write lines to a file in the given format, one batch after another, so large logs are
+/// exported without copying them whole.
+///
+/// The highlight groups of the lines are kept by the JSON and HTML formats, the other ones write
+/// the unformatted content
+pub struct LineExporter<W: Write> {
+    writer: W,
+    format: ExportFormat,
+    lines: usize,
+}
+
+impl<W: Write> LineExporter<W> {
+    /// Start the export writing the header of the format. `title` names the lines in the report
+    pub fn new(mut writer: W, format: ExportFormat, title: &str) -> io::Result<Self> {
+        match format {
+            ExportFormat::Text | ExportFormat::Ndjson => {}
+            ExportFormat::Json => write!(writer, "[")?,
+            ExportFormat::Csv => writeln!(writer, "{}", csv_row(&LogLine::columns()))?,
+            ExportFormat::Html => write_html_header(&mut writer, title)?,
+        }
+        Ok(Self {
+            writer,
+            format,
+            lines: 0,
+        })
+    }
+
+    pub fn write(&mut self, lines: &[LogLineStyled]) -> io::Result<()> {
+        for line in lines {
+            match self.format {
+                ExportFormat::Text => writeln!(self.writer, "{}", line.unformat().text())?,
+                ExportFormat::Ndjson => {
+                    serde_json::to_writer(&mut self.writer, &line.unformat())?;
+                    writeln!(self.writer)?;
+                }
+                ExportFormat::Json => {
+                    if self.lines > 0 {
+                        write!(self.writer, ",")?;
+                    }
+                    writeln!(self.writer)?;
+                    serde_json::to_writer(&mut self.writer, line)?;
+                }
+                ExportFormat::Csv => {
+                    let line = line.unformat();
+                    let fields: Vec<Cow<str>> = LogLine::columns()
+                        .iter()
+                        .filter_map(|column| line.get(column))
+                        .collect();
+                    writeln!(self.writer, "{}", csv_row(&fields))?;
+                }
+                ExportFormat::Html => write_html_row(&mut self.writer, line)?,
+            }
+            self.lines += 1;
+        }
+        Ok(())
+    }
+
+    /// Write the end of the format and flush. Returns the number of lines written
+    pub fn finish(mut self) -> io::Result<usize> {
+        match self.format {
+            ExportFormat::Text | ExportFormat::Ndjson | ExportFormat::Csv => {}
+            ExportFormat::Json => writeln!(self.writer, "\n]")?,
+            ExportFormat::Html => writeln!(self.writer, "</table>\n</body>\n</html>")?,
+        }
+        self.writer.flush()?;
+        Ok(self.lines)
+    }
+}
+
+/// Join the fields with commas, quoting the ones with commas, quotes or line breaks
+fn csv_row<S: AsRef<str>>(fields: &[S]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            let field = field.as_ref();
+            match field.contains([',', '"', '\n', '\r']) {
+                true => format!("\"{}\"", field.replace('"', "\"\"")),
+                false => field.to_string(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn write_html_header<W: Write>(writer: &mut W, title: &str) -> io::Result<()> {
+    let title = escape_html(title);
+    writeln!(writer, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>{}</title>\n<style>", title)?;
+    writeln!(
+        writer,
+        "body {{ font-family: monospace; }}\n\
+         table {{ border-collapse: collapse; }}\n\
+         th, td {{ border: 1px solid #ccc; padding: 2px 6px; text-align: left; vertical-align: top; }}\n\
+         mark {{ background: #ffeb3b; }}"
+    )?;
+    for (group, color) in HTML_GROUP_COLORS {
+        writeln!(writer, "mark.{} {{ background: {}; }}", group, color)?;
+    }
+    writeln!(writer, "</style>\n</head>\n<body>\n<h1>{}</h1>\n<table>", title)?;
+
+    let header: String = LogLine::columns()
+        .iter()
+        .map(|column| format!("<th>{}</th>", column))
+        .collect();
+    writeln!(writer, "<tr>{}</tr>", header)
+}
+
+/// Highlighted content of a field, each group in a `mark` with its name as class
+fn html_groups(groups: &StyledGroups) -> String {
+    groups
+        .iter()
+        .map(|(group, content)| match group {
+            Some(group) => format!(
+                "<mark class=\"{}\">{}</mark>",
+                escape_html(&group.to_lowercase()),
+                escape_html(content)
+            ),
+            None => escape_html(content),
+        })
+        .collect()
+}
+
+fn write_html_row<W: Write>(writer: &mut W, line: &LogLineStyled) -> io::Result<()> {
+    let style = match line.color {
+        Some((r, g, b)) => format!(" style=\"color: rgb({}, {}, {})\"", r, g, b),
+        None => String::new(),
+    };
+    let cells: String = LogLineStyled::columns()
+        .iter()
+        .filter_map(|column| line.get(column))
+        .map(|groups| format!("<td>{}</td>", html_groups(groups)))
+        .collect();
+    writeln!(writer, "<tr{}>{}</tr>", style, cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(lines: &[LogLineStyled], format: ExportFormat) -> String {
+        let mut buffer = Vec::new();
+        let mut exporter = LineExporter::new(&mut buffer, format, "Search <ERROR>").unwrap();
+        exporter.write(&lines[..1]).unwrap();
+        exporter.write(&lines[1..]).unwrap();
+        assert_eq!(exporter.finish().unwrap(), lines.len());
+        String::from_utf8(buffer).unwrap()
+    }
+
+    fn lines() -> Vec<LogLineStyled> {
+        vec![
+            LogLineStyled {
+                severity: vec![(None, "ERROR".to_string())],
+                payload: vec![
+                    (None, "connection ".to_string()),
+                    (Some("RED".to_string()), "lost".to_string()),
+                ],
+                ..Default::default()
+            },
+            (&LogLine {
+                date: "2022-06-05".into(),
+                payload: "said \"ok\", <done>".into(),
+                ..Default::default()
+            })
+                .into(),
+        ]
+    }
+
+    #[test]
+    fn lines_are_exported_as_text_and_ndjson() {
+        let lines = lines();
+        assert_eq!(
+            export(&lines, ExportFormat::Text),
+            "ERROR connection lost\n2022-06-05 said \"ok\", <done>\n"
+        );
+
+        let exported: Vec<LogLine> = export(&lines, ExportFormat::Ndjson)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[0].payload, "connection lost");
+        assert_eq!(exported[1].date, "2022-06-05");
+    }
+
+    #[test]
+    fn json_keeps_the_highlight_groups() {
+        let exported: Vec<LogLineStyled> =
+            serde_json::from_str(&export(&lines(), ExportFormat::Json)).unwrap();
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[0].payload[1], (Some("RED".to_string()), "lost".to_string()));
+    }
+
+    #[test]
+    fn csv_quotes_the_fields_when_needed() {
+        let csv = export(&lines(), ExportFormat::Csv);
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows[0], "Log,Index,Date,Timestamp,App,Severity,Function,Payload");
+        assert_eq!(rows[1], ",0,,,,ERROR,,connection lost");
+        assert_eq!(rows[2], ",0,2022-06-05,,,,,\"said \"\"ok\"\", <done>\"");
+    }
+
+    #[test]
+    fn html_highlights_the_groups() {
+        let html = export(&lines(), ExportFormat::Html);
+        assert!(html.contains("<h1>Search &lt;ERROR&gt;</h1>"));
+        assert!(html.contains("<td>connection <mark class=\"red\">lost</mark></td>"));
+        assert!(html.contains("<td>said &quot;ok&quot;, &lt;done&gt;</td>"));
+        assert!(html.ends_with("</html>\n"));
+    }
+}
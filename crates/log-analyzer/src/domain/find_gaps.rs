@@ -0,0 +1,68 @@
+use std::{borrow::Borrow, collections::HashMap};
+
+use crate::models::{gap::Gap, log_line::LogLine};
+
+/// Find the gaps longer than `min_gap` seconds between the consecutive lines of each source,
+/// sorted by the position of the line after them.
+///
+/// Lines without a recognizable time are skipped, a gap spans to the next line with one
+pub fn find_gaps<L: Borrow<LogLine>>(lines: &[L], min_gap: f64) -> Vec<Gap> {
+    // K: source -> V: (position, time) of its last line
+    let mut last_lines: HashMap<&str, (usize, f64)> = HashMap::new();
+    let mut gaps = Vec::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        let line = line.borrow();
+        let time = match line.time() {
+            Some(time) => time,
+            None => continue,
+        };
+        if let Some((before, start)) = last_lines.insert(&line.log, (index, time)) {
+            if time - start > min_gap {
+                gaps.push(Gap {
+                    source: line.log.to_string(),
+                    before,
+                    after: index,
+                    start,
+                    end: time,
+                });
+            }
+        }
+    }
+
+    gaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(index: usize, log: &str, timestamp: &str) -> LogLine {
+        LogLine {
+            index,
+            log: log.into(),
+            timestamp: timestamp.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn gaps_are_found_per_source() {
+        let lines = vec![
+            line(0, "a.log", "10"),
+            line(1, "b.log", "11"),
+            line(2, "a.log", "15"),
+            line(3, "a.log", ""),
+            line(4, "b.log", "100"),
+            line(5, "a.log", "200"),
+        ];
+
+        let gaps = find_gaps(&lines, 60.0);
+
+        assert_eq!(gaps.len(), 2);
+        assert_eq!((gaps[0].source.as_str(), gaps[0].before, gaps[0].after), ("b.log", 1, 4));
+        assert_eq!(gaps[0].duration(), 89.0);
+        assert_eq!((gaps[1].source.as_str(), gaps[1].before, gaps[1].after), ("a.log", 2, 5));
+        assert!(find_gaps(&lines, 200.0).is_empty());
+    }
+}
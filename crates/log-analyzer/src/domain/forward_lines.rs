@@ -0,0 +1,128 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    net::UdpSocket,
+    time::Duration,
+};
+
+use crate::models::{log_line::LogLine, sink::Sink};
+
+/// Time given to a webhook to answer before the line is considered lost
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Syslog facility of the forwarded lines: user-level messages
+const SYSLOG_FACILITY: u8 = 1;
+
+/// Syslog severity of a line from its severity field, informational if unknown
+fn syslog_severity(severity: &str) -> u8 {
+    let severity = severity.to_uppercase();
+    if severity.contains("FATAL") || severity.contains("CRIT") {
+        2
+    } else if severity.contains("ERR") {
+        3
+    } else if severity.contains("WARN") {
+        4
+    } else if severity.contains("DEBUG") || severity.contains("TRACE") {
+        7
+    } else {
+        6
+    }
+}
+
+/// Syslog message of a line: `<PRI>lap[filter]: line`
+fn syslog_message(filter: &str, line: &LogLine) -> String {
+    let priority = SYSLOG_FACILITY * 8 + syslog_severity(&line.severity);
+    format!("<{}>lap[{}]: {}", priority, filter, line.text())
+}
+
+/// Write the live lines to their sinks, keeping the files and the socket open between lines
+pub struct SinkForwarder {
+    files: HashMap<String, File>,
+    socket: Option<UdpSocket>,
+    agent: ureq::Agent,
+}
+
+impl Default for SinkForwarder {
+    fn default() -> Self {
+        Self {
+            files: HashMap::new(),
+            socket: None,
+            agent: ureq::AgentBuilder::new().timeout(WEBHOOK_TIMEOUT).build(),
+        }
+    }
+}
+
+impl SinkForwarder {
+    /// Forward a line matched by `filter` to `sink`
+    pub fn forward(&mut self, filter: &str, sink: &Sink, line: &LogLine) -> io::Result<()> {
+        match sink {
+            Sink::Syslog { address } => {
+                let socket = match &mut self.socket {
+                    Some(socket) => socket,
+                    None => self.socket.insert(UdpSocket::bind("0.0.0.0:0")?),
+                };
+                socket.send_to(syslog_message(filter, line).as_bytes(), address.as_str())?;
+            }
+            Sink::Webhook { url } => {
+                let body = serde_json::json!({ "filter": filter, "line": line });
+                self.agent
+                    .post(url)
+                    .set("Content-Type", "application/json")
+                    .send_string(&body.to_string())
+                    .map_err(|err| io::Error::other(err.to_string()))?;
+            }
+            Sink::File { path } => {
+                let file = match self.files.entry(path.clone()) {
+                    Entry::Occupied(file) => file.into_mut(),
+                    Entry::Vacant(entry) => {
+                        entry.insert(OpenOptions::new().create(true).append(true).open(path)?)
+                    }
+                };
+                writeln!(file, "{}", line.text())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Close the files not used by `sinks` anymore
+    pub fn retain(&mut self, sinks: &[Sink]) {
+        self.files.retain(|path, _| {
+            sinks
+                .iter()
+                .any(|sink| matches!(sink, Sink::File { path: sink_path } if sink_path == path))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_sent_to_syslog_with_their_severity() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let sink = Sink::Syslog {
+            address: receiver.local_addr().unwrap().to_string(),
+        };
+        let line = LogLine {
+            severity: "ERROR".into(),
+            payload: "disk full".into(),
+            ..Default::default()
+        };
+
+        SinkForwarder::default()
+            .forward("Errors", &sink, &line)
+            .unwrap();
+
+        let mut buffer = [0; 128];
+        let received = receiver.recv(&mut buffer).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buffer[..received]).unwrap(),
+            "<11>lap[Errors]: ERROR disk full"
+        );
+    }
+}
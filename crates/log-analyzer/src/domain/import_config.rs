@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::error::{LogAnalyzerError, Result};
+use crate::models::{
+    filter::{Filter, FilterAction},
+    format::Format,
+    log_line::LogLine,
+};
+
+/// Formats and filters converted from the configuration of another log viewer
+#[derive(Debug, Default)]
+pub struct ImportedConfig {
+    pub formats: Vec<Format>,
+    pub filters: Vec<Filter>,
+    /// Entries that could not be converted, with the reason
+    pub skipped: Vec<String>,
+}
+
+/// Convert an lnav format file (JSON) or a glogg/klogg configuration (INI), detected from the
+/// content
+pub fn import_config(content: &str) -> Result<ImportedConfig> {
+    let imported = match serde_json::from_str::<Value>(content) {
+        Ok(json) => import_lnav(&json)?,
+        Err(_) => import_glogg(content),
+    };
+    if imported.formats.is_empty() && imported.filters.is_empty() && imported.skipped.is_empty() {
+        return Err(LogAnalyzerError::InvalidImport(
+            "No lnav format nor glogg/klogg filter found".to_string(),
+        ));
+    }
+    Ok(imported)
+}
+
+/// Convert the formats of an lnav format file. Every regex of a format becomes a format with the
+/// timestamp, level, module and body fields mapped to the DATE, SEVERITY, APP and PAYLOAD groups.
+/// The highlights of the formats become marker filters
+pub fn import_lnav(json: &Value) -> Result<ImportedConfig> {
+    let formats = json
+        .as_object()
+        .ok_or_else(|| {
+            LogAnalyzerError::InvalidImport("An lnav format file must be a JSON object".to_string())
+        })?;
+
+    let mut imported = ImportedConfig::default();
+    for (name, format) in formats {
+        let format = match format.as_object() {
+            Some(format) if !name.starts_with('$') => format,
+            _ => continue,
+        };
+        let field = |key: &str, default: &str| {
+            format
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or(default)
+                .to_string()
+        };
+        let groups = [
+            (field("timestamp-field", "timestamp"), "DATE"),
+            (field("level-field", "level"), "SEVERITY"),
+            (field("module-field", "module"), "APP"),
+            (field("body-field", "body"), "PAYLOAD"),
+        ];
+
+        let regexes = format.get("regex").and_then(Value::as_object);
+        match regexes {
+            Some(regexes) => {
+                for (regex_name, regex) in regexes {
+                    let alias = match regexes.len() {
+                        1 => name.clone(),
+                        _ => format!("{}/{}", name, regex_name),
+                    };
+                    let pattern = match regex.get("pattern").and_then(Value::as_str) {
+                        Some(pattern) => convert_groups(pattern, &groups),
+                        None => {
+                            imported
+                                .skipped
+                                .push(format!("Format {}: no pattern", alias));
+                            continue;
+                        }
+                    };
+                    match Format::new(&alias, &pattern) {
+                        Ok(format) => imported.formats.push(format),
+                        Err(err) => imported.skipped.push(format!("Format {}: {}", alias, err)),
+                    }
+                }
+            }
+            None => imported.skipped.push(format!(
+                "Format {}: no regex, JSON log formats are not supported",
+                name
+            )),
+        }
+
+        if let Some(highlights) = format.get("highlights").and_then(Value::as_object) {
+            import_lnav_highlights(highlights, &mut imported);
+        }
+    }
+    Ok(imported)
+}
+
+fn import_lnav_highlights(highlights: &Map<String, Value>, imported: &mut ImportedConfig) {
+    for (alias, highlight) in highlights {
+        let pattern = match highlight.get("pattern").and_then(Value::as_str) {
+            Some(pattern) => pattern,
+            None => {
+                imported
+                    .skipped
+                    .push(format!("Highlight {}: no pattern", alias));
+                continue;
+            }
+        };
+        let color = ["color", "background-color"]
+            .iter()
+            .filter_map(|key| highlight.get(*key).and_then(Value::as_str))
+            .find_map(parse_color);
+        match Regex::new(pattern) {
+            Ok(_) => imported.filters.push(marker(alias, pattern, color)),
+            Err(err) => imported
+                .skipped
+                .push(format!("Highlight {}: {}", alias, err)),
+        }
+    }
+}
+
+/// Convert the filters and highlighters of a glogg or klogg configuration into marker filters
+/// on the payload, named after their regex. Their color is the background unless it is white,
+/// otherwise the foreground
+pub fn import_glogg(ini: &str) -> ImportedConfig {
+    // Entries in the order they appear. K: entry key, e.g. `filters\1` -> V: its fields
+    let mut entries: Vec<(String, HashMap<String, String>)> = Vec::new();
+    for line in ini.lines() {
+        let line = line.trim();
+        if line.starts_with(['[', ';', '#']) {
+            continue;
+        }
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), unescape_ini(value.trim())),
+            None => continue,
+        };
+        let (entry, field) = match key.rsplit_once('\\') {
+            Some(entry) => entry,
+            None => continue,
+        };
+        match entries.iter_mut().find(|(key, _)| key == entry) {
+            Some((_, fields)) => {
+                fields.insert(field.to_string(), value);
+            }
+            None => entries.push((
+                entry.to_string(),
+                HashMap::from([(field.to_string(), value)]),
+            )),
+        }
+    }
+
+    let mut imported = ImportedConfig::default();
+    for (entry, fields) in entries {
+        let pattern = match fields.get("regexp").or_else(|| fields.get("pattern")) {
+            Some(pattern) if !pattern.is_empty() => pattern,
+            _ => continue,
+        };
+        let mut regex = match fields.get("use_regex").map(String::as_str) {
+            Some("false") => regex::escape(pattern),
+            _ => pattern.clone(),
+        };
+        if fields.get("ignore_case").map(String::as_str) == Some("true") {
+            regex = format!("(?i){}", regex);
+        }
+        let background = fields
+            .get("back_colour")
+            .and_then(|color| parse_color(color))
+            .filter(|color| *color != (255, 255, 255));
+        let color = background.or_else(|| {
+            fields
+                .get("fore_colour")
+                .and_then(|color| parse_color(color))
+        });
+
+        if let Err(err) = Regex::new(&regex) {
+            imported.skipped.push(format!("Filter {}: {}", entry, err));
+        } else if imported
+            .filters
+            .iter()
+            .all(|filter| filter.alias != *pattern)
+        {
+            imported.filters.push(marker(pattern, &regex, color));
+        }
+    }
+    imported
+}
+
+fn marker(alias: &str, payload: &str, color: Option<(u8, u8, u8)>) -> Filter {
+    Filter {
+        alias: alias.to_string(),
+        action: FilterAction::MARKER,
+        filter: LogLine {
+            payload: payload.into(),
+            color,
+            ..Default::default()
+        },
+    }
+}
+
+/// Rename the named groups of a PCRE pattern, `(?<name>` or `(?P<name>`, with the group of their
+/// field in `groups`. The groups of other fields become non capturing
+fn convert_groups(pattern: &str, groups: &[(String, &str)]) -> String {
+    let named_group = Regex::new(r"\(\?P?<([A-Za-z_][A-Za-z0-9_]*)>").unwrap();
+    let mut converted = String::new();
+    let mut last = 0;
+    for captures in named_group.captures_iter(pattern) {
+        let group = captures.get(0).unwrap();
+        // An escaped parenthesis doesn't open a group
+        let backslashes = pattern[..group.start()]
+            .chars()
+            .rev()
+            .take_while(|c| *c == '\\')
+            .count();
+        if backslashes % 2 == 1 {
+            continue;
+        }
+        converted.push_str(&pattern[last..group.start()]);
+        match groups.iter().find(|(field, _)| field == &captures[1]) {
+            Some((_, name)) => converted.push_str(&format!("(?P<{}>", name)),
+            None => converted.push_str("(?:"),
+        }
+        last = group.end();
+    }
+    converted.push_str(&pattern[last..]);
+    converted
+}
+
+/// Value of a Qt INI file without its quotes and escapes
+fn unescape_ini(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('t') => unescaped.push('\t'),
+                Some(c) => unescaped.push(c),
+                None => {}
+            },
+            '"' => {}
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Parse `#rgb`, `#rrggbb` or a basic color name
+fn parse_color(color: &str) -> Option<(u8, u8, u8)> {
+    let color = color.trim().to_lowercase();
+    if let Some(hex) = color.strip_prefix('#') {
+        let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+        return match hex.len() {
+            3 => Some((
+                channel(&hex[0..1].repeat(2))?,
+                channel(&hex[1..2].repeat(2))?,
+                channel(&hex[2..3].repeat(2))?,
+            )),
+            6 => Some((
+                channel(&hex[0..2])?,
+                channel(&hex[2..4])?,
+                channel(&hex[4..6])?,
+            )),
+            _ => None,
+        };
+    }
+    match color.as_str() {
+        "black" => Some((0, 0, 0)),
+        "white" => Some((255, 255, 255)),
+        "red" => Some((255, 0, 0)),
+        "green" => Some((0, 128, 0)),
+        "yellow" => Some((255, 255, 0)),
+        "blue" => Some((0, 0, 255)),
+        "magenta" | "fuchsia" => Some((255, 0, 255)),
+        "cyan" | "aqua" => Some((0, 255, 255)),
+        "orange" => Some((255, 165, 0)),
+        "gray" | "grey" => Some((128, 128, 128)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lnav_formats_are_converted() {
+        let json = r##"{
+            "$schema": "https://lnav.org/schemas/format-v1.schema.json",
+            "myapp_log": {
+                "title": "My app",
+                "level-field": "sev",
+                "regex": {
+                    "std": {
+                        "pattern": "^(?<timestamp>\\d+-\\d+-\\d+) \\(?<pid>\\d+\\) (?<sev>\\w+) (?<body>.*)$"
+                    },
+                    "lookbehind": { "pattern": "(?<=x)(?<body>.*)" }
+                },
+                "highlights": {
+                    "ids": { "pattern": "id=\\d+", "color": "#f00" }
+                }
+            },
+            "json_log": { "json": true }
+        }"##;
+
+        let imported = import_config(json).unwrap();
+        assert_eq!(imported.formats.len(), 1);
+        assert_eq!(imported.formats[0].alias, "myapp_log/std");
+        assert_eq!(
+            imported.formats[0].regex,
+            r"^(?P<DATE>\d+-\d+-\d+) \(?<pid>\d+\) (?P<SEVERITY>\w+) (?P<PAYLOAD>.*)$"
+        );
+        assert_eq!(imported.filters[0].alias, "ids");
+        assert_eq!(imported.filters[0].filter.color, Some((255, 0, 0)));
+        assert_eq!(imported.skipped.len(), 2);
+        assert!(imported.skipped[0].starts_with("Format json_log: no regex"));
+        assert!(imported.skipped[1].starts_with("Format myapp_log/lookbehind"));
+    }
+
+    #[test]
+    fn glogg_filters_become_markers() {
+        let ini = r#"
+[FilterSet]
+version=1
+filters\1\regexp="ERROR \\d+"
+filters\1\ignore_case=true
+filters\1\fore_colour=#000000
+filters\1\back_colour=#ff0000
+filters\2\regexp=warn
+filters\2\fore_colour=#ffa500
+filters\2\back_colour=#ffffff
+filters\size=2
+
+[HighlighterSets]
+sets\1\highlighters\1\regexp=a.b
+sets\1\highlighters\1\use_regex=false
+"#;
+
+        let imported = import_config(ini).unwrap();
+        let filters: Vec<_> = imported
+            .filters
+            .iter()
+            .map(|f| (f.alias.as_str(), f.filter.payload.as_str(), f.filter.color))
+            .collect();
+        assert_eq!(
+            filters,
+            vec![
+                (r"ERROR \d+", r"(?i)ERROR \d+", Some((255, 0, 0))),
+                ("warn", "warn", Some((255, 165, 0))),
+                ("a.b", r"a\.b", None),
+            ]
+        );
+        assert!(import_config("[General]\nfont=Mono").is_err());
+    }
+}
@@ -1,3 +1,28 @@
 pub mod apply_format;
 pub mod apply_filters;
-pub mod apply_search;
\ No newline at end of file
+pub mod apply_search;
+#[cfg(feature = "threads")]
+pub mod correlation_id;
+#[cfg(feature = "threads")]
+pub mod dedupe_lines;
+#[cfg(feature = "threads")]
+pub mod detect_bursts;
+#[cfg(feature = "threads")]
+pub mod diff_lines;
+pub mod export_lines;
+#[cfg(feature = "threads")]
+pub mod find_gaps;
+pub mod import_config;
+#[cfg(feature = "threads")]
+pub mod forward_lines;
+pub mod parse_time;
+#[cfg(feature = "threads")]
+pub mod redact_lines;
+#[cfg(feature = "threads")]
+pub mod size_chunks;
+#[cfg(feature = "threads")]
+pub mod snapshot_lines;
+#[cfg(feature = "threads")]
+pub mod transform_lines;
+#[cfg(feature = "threads")]
+pub mod watch_counter;
\ No newline at end of file
@@ -0,0 +1,110 @@
+/// Parse the time of a line in seconds.
+///
+/// The date is used when it contains a recognizable `YYYY-MM-DD` and/or `HH:MM:SS[.fraction]`,
+/// otherwise the timestamp is parsed as a number of seconds
+pub fn parse_time(date: &str, timestamp: &str) -> Option<f64> {
+    parse_date(date)
+        .or_else(|| timestamp.trim().parse::<f64>().ok())
+        .filter(|time| time.is_finite())
+}
+
+/// Parse a date into seconds since the unix epoch (or since midnight if there is only time)
+fn parse_date(date: &str) -> Option<f64> {
+    // Split into runs of digits together with the separator following them
+    let mut numbers: Vec<(&str, Option<char>)> = Vec::new();
+    let mut start = None;
+    for (i, c) in date.char_indices() {
+        match (c.is_ascii_digit(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                numbers.push((&date[s..i], Some(c)));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        numbers.push((&date[s..], None));
+    }
+
+    let mut days = None;
+    let mut i = 0;
+    if let Some(position) = numbers
+        .iter()
+        .position(|(n, sep)| n.len() == 4 && matches!(sep, Some('-') | Some('/')))
+    {
+        if let (Some((month, _)), Some((day, _))) =
+            (numbers.get(position + 1), numbers.get(position + 2))
+        {
+            days = Some(days_from_civil(
+                numbers[position].0.parse().ok()?,
+                month.parse().ok()?,
+                day.parse().ok()?,
+            ));
+            i = position + 3;
+        }
+    }
+
+    let mut seconds = None;
+    if let Some(position) = numbers[i.min(numbers.len())..]
+        .iter()
+        .position(|(_, sep)| *sep == Some(':'))
+        .map(|p| p + i)
+    {
+        if let (Some((minutes, Some(':'))), Some((secs, separator))) =
+            (numbers.get(position + 1), numbers.get(position + 2))
+        {
+            let hours: f64 = numbers[position].0.parse().ok()?;
+            let minutes: f64 = minutes.parse().ok()?;
+            let mut secs: f64 = secs.parse().ok()?;
+            if let (Some('.') | Some(','), Some((fraction, _))) =
+                (separator, numbers.get(position + 3))
+            {
+                secs += fraction.parse::<f64>().ok()? / 10_f64.powi(fraction.len() as i32);
+            }
+            seconds = Some(hours * 3600.0 + minutes * 60.0 + secs);
+        }
+    }
+
+    match (days, seconds) {
+        (None, None) => None,
+        (days, seconds) => {
+            Some(days.unwrap_or_default() as f64 * 86400.0 + seconds.unwrap_or_default())
+        }
+    }
+}
+
+/// Number of days since 1970-01-01 for the given civil date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_full_date() {
+        let time = parse_time("2022-06-02 10:20:30,500", "").unwrap();
+        assert_eq!(time, 1654165230.5);
+    }
+
+    #[test]
+    fn parse_time_only() {
+        let time = parse_time("10:20:30.25", "").unwrap();
+        assert_eq!(time, 37230.25);
+    }
+
+    #[test]
+    fn fallback_to_timestamp() {
+        assert_eq!(parse_time("", " 200.05"), Some(200.05));
+        assert_eq!(parse_time("not a date", "nor a timestamp"), None);
+        assert_eq!(parse_time("", "inf"), None);
+        assert_eq!(parse_time("", "NaN"), None);
+    }
+}
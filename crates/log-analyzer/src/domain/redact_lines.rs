@@ -0,0 +1,94 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+
+use crate::error::{LogAnalyzerError, Result};
+use crate::models::{log_line::LogLine, redaction::Redaction};
+
+/// Replace the matches of the redactions in the fields of the lines, in the order they are given
+#[derive(Default)]
+pub struct Redactor {
+    rules: Vec<(Regex, String)>,
+}
+
+impl Redactor {
+    pub fn new(redactions: &[Redaction]) -> Result<Self> {
+        let rules = redactions
+            .iter()
+            .map(|redaction| match redaction.regex.as_str() {
+                "" => Err(LogAnalyzerError::EmptyRegex),
+                regex => Regex::new(regex)
+                    .map(|re| (re, redaction.replacement.clone()))
+                    .map_err(|err| LogAnalyzerError::InvalidRegex {
+                        regex: regex.to_string(),
+                        reason: err.to_string(),
+                    }),
+            })
+            .collect::<Result<Vec<(Regex, String)>>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Redact the date, timestamp, app, severity, function and payload of the line
+    pub fn redact(&self, line: &mut LogLine) {
+        for field in [
+            &mut line.date,
+            &mut line.timestamp,
+            &mut line.app,
+            &mut line.severity,
+            &mut line.function,
+            &mut line.payload,
+        ] {
+            if let Cow::Owned(redacted) = self.redact_text(field) {
+                *field = redacted.into();
+            }
+        }
+    }
+
+    /// Redact a whole text, e.g. a raw line, borrowing it back if nothing matches
+    pub fn redact_text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for (regex, replacement) in &self.rules {
+            if let Cow::Owned(redacted) = regex.replace_all(&text, replacement.as_str()) {
+                text = Cow::Owned(redacted);
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_field_is_redacted_in_order() {
+        let redactor = Redactor::new(&[
+            Redaction {
+                regex: r"[\w.+-]+@(?P<domain>[\w-]+\.[\w.]+)".to_string(),
+                replacement: "<email at $domain>".to_string(),
+            },
+            Redaction::new(r"\b\d{1,3}(\.\d{1,3}){3}\b"),
+        ])
+        .unwrap();
+        let mut line = LogLine {
+            app: "10.0.0.1".into(),
+            payload: "login of jane.doe@example.com from 192.168.1.20".into(),
+            ..Default::default()
+        };
+
+        redactor.redact(&mut line);
+
+        assert_eq!(line.app, "[REDACTED]");
+        assert_eq!(line.payload, "login of <email at example.com> from [REDACTED]");
+        assert_eq!(redactor.redact_text("from 10.0.0.1"), "from [REDACTED]");
+        assert!(matches!(redactor.redact_text("nothing"), Cow::Borrowed("nothing")));
+        assert!(matches!(
+            Redactor::new(&[Redaction::new("(")]),
+            Err(LogAnalyzerError::InvalidRegex { .. })
+        ));
+    }
+}
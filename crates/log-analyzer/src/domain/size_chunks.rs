@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+/// Time a worker should spend on a chunk of lines while the pipeline keeps up with the sources
+pub const TARGET_CHUNK_LATENCY: Duration = Duration::from_millis(20);
+
+/// Fewest lines worth a chunk of their own, below which the scheduling costs more than it saves
+pub const MIN_CHUNK_SIZE: usize = 64;
+
+/// Weight of the latest chunk in the measured throughput
+const SMOOTHING: f64 = 0.2;
+
+/// Choose the lines processed at once by a worker from the latency observed on the previous
+/// chunks and the batches waiting to be processed.
+///
+/// While the pipeline keeps up, e.g. a followed file growing a few lines at a time, the chunks
+/// are kept within `TARGET_CHUNK_LATENCY` so the lines show up quickly. Once batches queue up,
+/// e.g. loading a large file, each worker gets one big chunk of the batch to spare the per-chunk
+/// overhead
+#[derive(Debug, Default)]
+pub struct ChunkSizer {
+    /// Lines processed per second by a worker, smoothed over the measured chunks
+    lines_per_sec: Option<f64>,
+}
+
+impl ChunkSizer {
+    /// Account for a chunk of `lines` processed by a worker in `elapsed`
+    pub fn record(&mut self, lines: usize, elapsed: Duration) {
+        if lines == 0 || elapsed.is_zero() {
+            return;
+        }
+        let measured = lines as f64 / elapsed.as_secs_f64();
+        self.lines_per_sec = Some(match self.lines_per_sec {
+            Some(average) => average + SMOOTHING * (measured - average),
+            None => measured,
+        });
+    }
+
+    /// Lines a worker processes within `TARGET_CHUNK_LATENCY`, once a chunk was measured
+    pub fn target_size(&self) -> Option<usize> {
+        self.lines_per_sec
+            .map(|lines_per_sec| (lines_per_sec * TARGET_CHUNK_LATENCY.as_secs_f64()) as usize)
+    }
+
+    /// Lines per chunk for a batch of `lines` shared by `workers`, with `queue_depth` batches
+    /// waiting behind it
+    pub fn chunk_size(&self, lines: usize, workers: usize, queue_depth: usize) -> usize {
+        let even = lines.div_ceil(workers.max(1)).max(1);
+        match self.target_size() {
+            Some(target) if queue_depth == 0 => even.min(target.max(MIN_CHUNK_SIZE)),
+            _ => even,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_shrink_while_the_pipeline_keeps_up() {
+        let mut sizer = ChunkSizer::default();
+        // Nothing measured yet: the batch is split evenly between the workers
+        assert_eq!(sizer.chunk_size(10_000, 4, 0), 2_500);
+
+        // 50 000 lines per second: 1 000 lines within the target latency
+        sizer.record(5_000, Duration::from_millis(100));
+        assert_eq!(sizer.target_size(), Some(1_000));
+        assert_eq!(sizer.chunk_size(10_000, 4, 0), 1_000);
+        assert_eq!(sizer.chunk_size(100, 4, 0), 25);
+
+        // Batches queued up: one big chunk per worker
+        assert_eq!(sizer.chunk_size(10_000, 4, 3), 2_500);
+
+        // Slower chunks weigh in gradually
+        sizer.record(500, Duration::from_millis(100));
+        assert_eq!(sizer.target_size(), Some(820));
+        sizer.record(10, Duration::from_secs(1));
+        assert_eq!(sizer.chunk_size(10_000, 4, 0), 656);
+    }
+}
@@ -0,0 +1,107 @@
+use std::io::{self, Read, Write};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::de::IoRead;
+
+use crate::models::snapshot::{SnapshotHeader, SNAPSHOT_VERSION};
+
+/// Write a snapshot as a sequence of JSON values: its header, then the lines it announces one
+/// batch after another, so the stores are saved without copying them whole
+pub struct SnapshotWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> SnapshotWriter<W> {
+    /// Start the snapshot with `header`, stamped with the current version
+    pub fn new(mut writer: W, header: &SnapshotHeader) -> io::Result<Self> {
+        let mut header = serde_json::to_value(header)?;
+        header["version"] = SNAPSHOT_VERSION.into();
+        serde_json::to_writer(&mut writer, &header)?;
+        Ok(Self { writer })
+    }
+
+    pub fn write<T: Serialize>(&mut self, batch: &[T]) -> io::Result<()> {
+        self.writer.write_all(b"\n")?;
+        serde_json::to_writer(&mut self.writer, batch)?;
+        Ok(())
+    }
+
+    /// Flush the snapshot, returning the writer to finish it, e.g. a compressor
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Read a snapshot written by `SnapshotWriter`, one batch after another
+pub struct SnapshotReader<R: Read> {
+    deserializer: serde_json::Deserializer<IoRead<R>>,
+}
+
+impl<R: Read> SnapshotReader<R> {
+    /// Read the header of the snapshot. Snapshots of newer versions are refused
+    pub fn new(reader: R) -> io::Result<(Self, SnapshotHeader)> {
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        let header = SnapshotHeader::deserialize(&mut deserializer)?;
+        if header.version > SNAPSHOT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "written by a newer version (snapshot format {}, supported up to {})",
+                    header.version, SNAPSHOT_VERSION
+                ),
+            ));
+        }
+        Ok((Self { deserializer }, header))
+    }
+
+    /// Read batches until `total` elements are read, passing each one to `add`
+    pub fn read_batches<T, F>(&mut self, total: usize, mut add: F) -> io::Result<()>
+    where
+        T: DeserializeOwned,
+        F: FnMut(Vec<T>),
+    {
+        let mut read = 0;
+        while read < total {
+            let batch = Vec::<T>::deserialize(&mut self.deserializer)?;
+            if batch.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated snapshot"));
+            }
+            read += batch.len();
+            add(batch);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches_are_read_back_after_the_header() {
+        let header = SnapshotHeader {
+            filtered_lines: 3,
+            ..Default::default()
+        };
+        let mut writer = SnapshotWriter::new(Vec::new(), &header).unwrap();
+        writer.write(&["one", "two"]).unwrap();
+        writer.write(&["three"]).unwrap();
+        let snapshot = writer.finish().unwrap();
+
+        let (mut reader, header) = SnapshotReader::new(snapshot.as_slice()).unwrap();
+        let mut lines: Vec<String> = Vec::new();
+        reader
+            .read_batches(header.filtered_lines, |batch| lines.extend(batch))
+            .unwrap();
+        assert_eq!(header.version, SNAPSHOT_VERSION);
+        assert_eq!(lines, ["one", "two", "three"]);
+
+        let (mut reader, _) = SnapshotReader::new(snapshot.as_slice()).unwrap();
+        assert!(reader.read_batches(4, |_: Vec<String>| {}).is_err());
+        let newer = br#"{"version": 99, "session": {}, "sources": [], "filtered_lines": 0,
+            "searched_lines": 0, "search_complete": true, "rate": []}"#;
+        let newer = SnapshotReader::new(newer.as_slice()).err().unwrap();
+        assert!(newer.to_string().contains("newer version"));
+    }
+}
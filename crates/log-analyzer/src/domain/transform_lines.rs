@@ -0,0 +1,179 @@
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+
+use crate::error::{LogAnalyzerError, Result};
+use crate::models::{
+    log_line::{LogLine, SmolStr},
+    transform::{transform_field, Transform, TransformRule},
+};
+
+/// Transform rule with its regex compiled and its fields resolved
+enum Rule {
+    Rewrite(Regex, String),
+    Extract(&'static str, Regex),
+    StripPrefix(String),
+    NormalizePaths,
+}
+
+/// Apply the transforms to the fields of the lines, in the order they are given
+#[derive(Default)]
+pub struct Transformer {
+    rules: Vec<(&'static str, Rule)>,
+}
+
+fn field(name: &str) -> Result<&'static str> {
+    transform_field(name).ok_or_else(|| LogAnalyzerError::UnknownColumn(name.to_string()))
+}
+
+fn compile(regex: &str) -> Result<Regex> {
+    Regex::new(regex).map_err(|err| LogAnalyzerError::InvalidRegex {
+        regex: regex.to_string(),
+        reason: err.to_string(),
+    })
+}
+
+impl Transformer {
+    pub fn new(transforms: &[Transform]) -> Result<Self> {
+        let rules = transforms
+            .iter()
+            .map(|transform| {
+                let rule = match &transform.rule {
+                    TransformRule::Rewrite { regex, replacement } => {
+                        Rule::Rewrite(compile(regex)?, replacement.clone())
+                    }
+                    TransformRule::Extract { from, regex } => {
+                        Rule::Extract(field(from)?, compile(regex)?)
+                    }
+                    TransformRule::StripPrefix { prefix } => Rule::StripPrefix(prefix.clone()),
+                    TransformRule::NormalizePaths => Rule::NormalizePaths,
+                };
+                Ok((field(&transform.field)?, rule))
+            })
+            .collect::<Result<Vec<(&'static str, Rule)>>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn transform(&self, line: &mut LogLine) {
+        for (field, rule) in &self.rules {
+            let value = match rule {
+                Rule::Rewrite(regex, replacement) => {
+                    let value = line.get_text(field).map(SmolStr::as_str).unwrap_or_default();
+                    regex.replace_all(value, replacement.as_str()).into_owned()
+                }
+                Rule::Extract(from, regex) => {
+                    let from = line.get_text(from).map(SmolStr::as_str).unwrap_or_default();
+                    match regex.captures(from) {
+                        Some(captures) => captures
+                            .get(1)
+                            .or_else(|| captures.get(0))
+                            .map(|capture| capture.as_str().to_string())
+                            .unwrap_or_default(),
+                        None => continue,
+                    }
+                }
+                Rule::StripPrefix(prefix) => match line
+                    .get_text(field)
+                    .and_then(|value| value.strip_prefix(prefix.as_str()))
+                {
+                    Some(value) => value.to_string(),
+                    None => continue,
+                },
+                Rule::NormalizePaths => {
+                    let value = line.get_text(field).map(SmolStr::as_str).unwrap_or_default();
+                    normalize_paths(value)
+                }
+            };
+            if let Some(field) = line.get_mut(field) {
+                *field = value.into();
+            }
+        }
+    }
+}
+
+/// Normalize the words of the text containing a path separator. URLs are left as they are
+fn normalize_paths(text: &str) -> String {
+    static WORD: OnceLock<Regex> = OnceLock::new();
+    let word = WORD.get_or_init(|| Regex::new(r"\S+").unwrap());
+    word.replace_all(text, |captures: &Captures| {
+        let word = &captures[0];
+        match word.contains(['/', '\\']) && !word.contains("://") {
+            true => normalize_path(word),
+            false => word.to_string(),
+        }
+    })
+    .into_owned()
+}
+
+fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let is_absolute = path.starts_with('/');
+    let mut components: Vec<&str> = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => match components.last() {
+                Some(last) if *last != ".." && !last.ends_with(':') => {
+                    components.pop();
+                }
+                _ if is_absolute => {}
+                _ => components.push(component),
+            },
+            component => components.push(component),
+        }
+    }
+    let normalized = components.join("/");
+    match is_absolute {
+        true => format!("/{}", normalized),
+        false => normalized,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_are_applied_in_order() {
+        let transforms: Vec<Transform> = serde_json::from_str(
+            r#"[
+                { "field": "Payload", "type": "strip_prefix", "prefix": "[main] " },
+                { "field": "App", "type": "extract", "from": "Payload", "regex": "^(\\w+):" },
+                { "field": "Payload", "type": "rewrite", "regex": "^\\w+: ", "replacement": "" },
+                { "field": "Payload", "type": "normalize_paths" }
+            ]"#,
+        )
+        .unwrap();
+        let transformer = Transformer::new(&transforms).unwrap();
+        let mut line = LogLine {
+            app: "unknown".into(),
+            payload: r"[main] loader: open C:\app\..\logs\.\a.log and /var//log/../tmp/b from http://host//x"
+                .into(),
+            ..Default::default()
+        };
+
+        transformer.transform(&mut line);
+
+        assert_eq!(line.app, "loader");
+        assert_eq!(
+            line.payload,
+            "open C:/logs/a.log and /var/tmp/b from http://host//x"
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let transform = Transform {
+            field: "Thread".to_string(),
+            rule: TransformRule::NormalizePaths,
+        };
+        assert!(matches!(
+            Transformer::new(&[transform]),
+            Err(LogAnalyzerError::UnknownColumn(_))
+        ));
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+
+use crate::models::log_line::LogLine;
+use crate::models::query::Query;
+use crate::models::watch::{Watch, WATCH_HISTORY_SECS};
+
+/// Count the lines matched by a watch expression in each second they are received
+pub struct WatchCounter {
+    expression: String,
+    query: Query,
+    total: usize,
+    /// Matches of the last seconds, the last one being `second`
+    counts: VecDeque<usize>,
+    second: u64,
+}
+
+/// Add the empty seconds elapsed since `from` until `to`, keeping the history length
+fn advance(counts: &mut VecDeque<usize>, from: u64, to: u64) {
+    let elapsed = to.saturating_sub(from).min(WATCH_HISTORY_SECS as u64);
+    for _ in 0..elapsed {
+        counts.push_back(0);
+    }
+    while counts.len() > WATCH_HISTORY_SECS {
+        counts.pop_front();
+    }
+}
+
+impl WatchCounter {
+    /// Start counting at `second`
+    pub fn new(expression: &str, query: Query, second: u64) -> Self {
+        Self {
+            expression: expression.to_string(),
+            query,
+            total: 0,
+            counts: VecDeque::from([0]),
+            second,
+        }
+    }
+
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    pub fn matches(&self, line: &LogLine) -> bool {
+        self.query.matches(line)
+    }
+
+    /// Count the matches received at `second`. Late matches are counted in the current second
+    pub fn add(&mut self, matches: usize, second: u64) {
+        if second > self.second {
+            advance(&mut self.counts, self.second, second);
+            self.second = second;
+        }
+        if let Some(current) = self.counts.back_mut() {
+            *current += matches;
+        }
+        self.total += matches;
+    }
+
+    /// Counts up to `second`, the seconds without matches included
+    pub fn watch(&self, second: u64) -> Watch {
+        let mut counts = self.counts.clone();
+        advance(&mut counts, self.second, second);
+        Watch {
+            expression: self.expression.clone(),
+            total: self.total,
+            per_second: counts.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_are_counted_per_second() {
+        let query = Query::parse("payload:retry", &[]).unwrap();
+        let mut counter = WatchCounter::new("payload:retry", query, 100);
+        let retry = LogLine {
+            payload: "retry in 5s".into(),
+            ..Default::default()
+        };
+        assert!(counter.matches(&retry));
+        assert!(!counter.matches(&LogLine::default()));
+
+        counter.add(3, 100);
+        counter.add(2, 102);
+        counter.add(1, 101);
+        let watch = counter.watch(104);
+        assert_eq!(watch.per_second, vec![3, 0, 3, 0, 0]);
+        assert_eq!(watch.total, 6);
+        assert_eq!(watch.rate(4), 1.5);
+
+        let watch = counter.watch(1000);
+        assert_eq!(watch.per_second.len(), WATCH_HISTORY_SECS);
+        assert!(watch.per_second.iter().all(|count| *count == 0));
+    }
+}
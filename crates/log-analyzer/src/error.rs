@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Errors returned by the `LogAnalyzer` API.
+///
+/// They are serializable so a remote analyzer can return the same kind of error as a local one
+#[derive(Error, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "kind", content = "details", rename_all = "snake_case")]
+pub enum LogAnalyzerError {
+    #[error("The alias is empty")]
+    EmptyAlias,
+    #[error("The regex is empty")]
+    EmptyRegex,
+    #[error("Could not compile regex {regex}: {reason}")]
+    InvalidRegex { regex: String, reason: String },
+    #[error("Unknown source type {0}")]
+    UnknownSourceType(usize),
+    #[error("{reason}")]
+    SourceUnavailable { address: String, reason: String },
+    #[error("Format {0} not found")]
+    FormatNotFound(String),
+    #[error("Format {0} already exists")]
+    FormatAlreadyExists(String),
+    #[error("Filter {0} not found")]
+    FilterNotFound(String),
+    #[error("Filter {0} already exists")]
+    FilterAlreadyExists(String),
+    #[error("Unknown column {0}")]
+    UnknownColumn(String),
+    #[error("Invalid query {expression}: {reason}")]
+    InvalidQuery { expression: String, reason: String },
+    #[error("Watch {0} not found")]
+    WatchNotFound(String),
+    #[error("Watch {0} already exists")]
+    WatchAlreadyExists(String),
+    #[error("Invalid sink: {0}")]
+    InvalidSink(String),
+    #[error("The replay speed must be greater than 0")]
+    InvalidReplaySpeed,
+    #[error("Could not export to {path}: {reason}")]
+    ExportFailed { path: String, reason: String },
+    #[error("Could not use the snapshot {path}: {reason}")]
+    SnapshotFailed { path: String, reason: String },
+    #[error("Could not open the database {path}: {reason}")]
+    DatabaseUnavailable { path: String, reason: String },
+    /// Refused by `lap serve`: the method writes files or runs commands on the server, which
+    /// anyone reaching it could otherwise do
+    #[error("{0} is only available to the local application, not through the API")]
+    LocalOnly(String),
+    #[error("Invalid request {method}: {reason}")]
+    InvalidRequest { method: String, reason: String },
+    #[error("Invalid settings: {0}")]
+    InvalidSettings(String),
+    #[error("Invalid session: {0}")]
+    InvalidSession(String),
+    #[error("Could not import the configuration: {0}")]
+    InvalidImport(String),
+    #[error("Invalid JSON: {0}")]
+    InvalidJson(String),
+    #[error("Could not use {path}: {reason}")]
+    FileUnavailable { path: String, reason: String },
+    /// The analyzer could not be reached or answered something else than an API error
+    #[error("{0}")]
+    Remote(String),
+}
+
+pub type Result<T, E = LogAnalyzerError> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_keep_their_kind_when_serialized() {
+        let error = LogAnalyzerError::InvalidRegex {
+            regex: "(".to_string(),
+            reason: "unclosed group".to_string(),
+        };
+
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["kind"], "invalid_regex");
+        assert_eq!(serde_json::from_value::<LogAnalyzerError>(json).unwrap(), error);
+
+        let json = serde_json::to_value(LogAnalyzerError::FormatNotFound("All".to_string()));
+        assert_eq!(json.unwrap()["kind"], "format_not_found");
+    }
+}
@@ -1,6 +1,22 @@
+//! Engine of log analyzer pro: reads logs from files, websockets or stdin, formats them with
+//! regexes, filters and searches them in the background.
+//!
+//! Start an engine with `LogAnalyzerBuilder` and drive it through the `LogAnalyzer` trait.
+//! Its `on_event` channel notifies when new lines are processed.
+//!
+//! The engine requires the `threads` feature (enabled by default). Without it, e.g. to build
+//! for wasm32, `LineProcessor` still formats, filters and searches the lines given to it
+pub mod error;
 pub mod models;
 mod domain;
 pub mod services;
+#[cfg(feature = "threads")]
 pub mod stores;
 
-
+pub use domain::export_lines::export_to_file;
+pub use error::{LogAnalyzerError, Result};
+pub use services::line_processor::LineProcessor;
+#[cfg(feature = "threads")]
+pub use services::builder::LogAnalyzerBuilder;
+#[cfg(feature = "threads")]
+pub use services::log_service::{Event, LogAnalyzer, LogService};
@@ -1,24 +1,19 @@
 use anyhow::{anyhow, Result};
-mod models;
-mod services;
-mod stores;
-mod domain;
 
 use log_source::source::log_source::SourceType;
+use log_source::source::runtime;
 
-use services::log_service::{LogAnalyzer, LogService};
+use log_analyzer::services::log_service::{LogAnalyzer, LogService};
+use log_analyzer::stores::analysis_store::InMemmoryAnalysisStore;
+use log_analyzer::stores::log_store::InMemmoryLogStore;
+use log_analyzer::stores::processing_store::{InMemmoryProcessingStore, ProcessingStore};
 use std::sync::Arc;
 use std::time::Duration;
-use stores::analysis_store::InMemmoryAnalysisStore;
-use stores::log_store::InMemmoryLogStore;
-use stores::processing_store::{ProcessingStore, InMemmoryProcessingStore};
-
-use async_std::task;
 
 fn get_filename() -> Option<String> {
-    let file = std::env::args().skip(1).next()?;
+    let file = std::env::args().nth(1)?;
     println!("file to stream: {:?}", file);
-    return Some(file);
+    Some(file)
 }
 
 async fn async_main() -> Result<()> {
@@ -38,12 +33,12 @@ async fn async_main() -> Result<()> {
     )?;
 
     loop {
-        async_std::task::sleep(Duration::from_secs(10)).await;
+        runtime::sleep(Duration::from_secs(10)).await;
     }
 }
 
 fn main() -> Result<()> {
-    task::block_on(async_main())?;
+    runtime::block_on(async_main())?;
 
     Ok(())
 }
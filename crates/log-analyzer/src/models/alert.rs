@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/// Seconds during which a filter doesn't alert again after alerting
+pub const DEFAULT_ALERT_COOLDOWN_SECS: u64 = 10;
+
+fn default_cooldown_secs() -> u64 {
+    DEFAULT_ALERT_COOLDOWN_SECS
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+/// What to do when a filter with an alert matches a live line
+pub enum AlertAction {
+    /// Ring the terminal bell
+    Bell,
+    /// Show a desktop notification with the line
+    Desktop,
+    /// Run a shell command with the line as its first argument
+    Command { command: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+/// Action triggered by a filter, e.g. `{"action": "command", "command": "./page.sh"}`
+pub struct Alert {
+    #[serde(flatten)]
+    pub action: AlertAction,
+    /// Seconds during which the filter doesn't alert again after alerting
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+}
+
+impl Alert {
+    pub fn new(action: AlertAction) -> Self {
+        Self {
+            action,
+            cooldown_secs: DEFAULT_ALERT_COOLDOWN_SECS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alerts_are_declared_by_action() {
+        let alert: Alert = serde_json::from_str(r#"{ "action": "bell" }"#).unwrap();
+        assert_eq!(alert, Alert::new(AlertAction::Bell));
+
+        let json = r#"{ "action": "command", "command": "./page.sh", "cooldown_secs": 60 }"#;
+        let alert: Alert = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            alert.action,
+            AlertAction::Command {
+                command: "./page.sh".to_string()
+            }
+        );
+        assert_eq!(alert.cooldown_secs, 60);
+    }
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+/// Factor over the usual rate from which an interval is a burst by default
+pub const DEFAULT_BURST_FACTOR: f64 = 5.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+/// Options of the burst detection. Missing options take their default
+pub struct BurstConfig {
+    /// Seconds of log time grouped in each interval
+    pub interval_secs: u64,
+    /// Intervals before an interval averaged as its usual rate
+    pub window: usize,
+    /// Factor over the usual rate from which an interval is a burst
+    pub factor: f64,
+    /// Lines an interval needs at least to be a burst, so a few lines in a quiet log are not one
+    pub min_lines: usize,
+}
+
+impl Default for BurstConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 10,
+            window: 30,
+            factor: DEFAULT_BURST_FACTOR,
+            min_lines: 20,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+/// Interval of log time with many more filtered lines than the intervals before it
+pub struct Burst {
+    /// Severity of the lines, `None` when counting all the lines
+    pub severity: Option<String>,
+    /// Start of the interval in seconds, parsed like the time of the lines
+    pub start: i64,
+    /// End of the interval in seconds, excluded
+    pub end: i64,
+    /// Lines in the interval
+    pub lines: usize,
+    /// Average lines of the intervals before it, rounded
+    pub usual: usize,
+}
+
+impl Burst {
+    /// The time in seconds is inside the interval of the burst
+    pub fn contains(&self, time: f64) -> bool {
+        self.start as f64 <= time && time < self.end as f64
+    }
+
+    /// How many times the usual rate the interval reached
+    pub fn ratio(&self) -> f64 {
+        self.lines as f64 / self.usual.max(1) as f64
+    }
+}
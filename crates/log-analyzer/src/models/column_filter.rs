@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+use super::log_line::LineFields;
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug, Default)]
+/// Ad-hoc filter on a single column, created from the table header.
+///
+/// A line matches when the column contains the value, ignoring case
+pub struct ColumnFilter {
+    /// Column name as used by `LogLine::get`
+    pub column: String,
+    pub value: String,
+}
+
+impl ColumnFilter {
+    pub fn new(column: &str, value: &str) -> Self {
+        Self {
+            column: column.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    pub fn is_match(&self, log_line: &(impl LineFields + ?Sized)) -> bool {
+        let matches = |content: &str| content.to_lowercase().contains(&self.value.to_lowercase());
+        match self.column.as_str() {
+            "Index" => matches(&log_line.position().to_string()),
+            column => log_line.field(column).is_some_and(matches),
+        }
+    }
+}
+
+impl std::fmt::Display for ColumnFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = \"{}\"", self.column, self.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::log_line::LogLine;
+
+    #[test]
+    fn matches_column_content_ignoring_case() {
+        let line = LogLine {
+            app: "NetD-service".into(),
+            ..Default::default()
+        };
+
+        assert!(ColumnFilter::new("App", "netd").is_match(&line));
+        assert!(!ColumnFilter::new("App", "wifi").is_match(&line));
+        assert!(!ColumnFilter::new("Unknown", "netd").is_match(&line));
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+/// Options of the duplicate suppression. Missing options take their default
+pub struct DedupeConfig {
+    /// Seconds of log time between two lines of different sources with the same payload for
+    /// the second one to be a duplicate
+    pub window_secs: f64,
+    /// Payloads remembered at most, the oldest ones being forgotten beyond it
+    pub max_keys: usize,
+}
+
+impl Default for DedupeConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: 1.0,
+            max_keys: 1_000_000,
+        }
+    }
+}
+
+impl DedupeConfig {
+    /// Describe the options that would never find a duplicate
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if !(self.window_secs.is_finite() && self.window_secs > 0.0) {
+            errors.push("window_secs: must be greater than 0".to_string());
+        }
+        if self.max_keys == 0 {
+            errors.push("max_keys: must be greater than 0".to_string());
+        }
+        errors
+    }
+}
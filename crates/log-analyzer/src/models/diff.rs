@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+use super::log_line::LogLine;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+/// Filtered lines compared on one side of a diff
+pub struct DiffSide {
+    /// Only take the lines of this source, every source if not set
+    pub source: Option<String>,
+    /// Only take the lines with a time (in seconds) in this inclusive range. Lines without a
+    /// recognizable time are skipped
+    pub time_range: Option<(f64, f64)>,
+}
+
+impl DiffSide {
+    /// The line belongs to this side
+    pub fn contains(&self, line: &LogLine) -> bool {
+        if self.source.as_ref().is_some_and(|source| *source != line.log) {
+            return false;
+        }
+        match self.time_range {
+            Some((start, end)) => line.time().is_some_and(|time| start <= time && time <= end),
+            None => true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+/// Compare the filtered lines of two sources or two time ranges, e.g. the log of a device where
+/// a feature works against the log of one where it fails
+pub struct DiffQuery {
+    pub left: DiffSide,
+    pub right: DiffSide,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+/// Lines found on one side only, once the times they contain are masked
+pub struct Diff {
+    /// Lines of the left side missing from the right one, sorted by index
+    pub left: Vec<LogLine>,
+    /// Lines of the right side missing from the left one, sorted by index
+    pub right: Vec<LogLine>,
+    /// Lines found on both sides
+    pub common: usize,
+}
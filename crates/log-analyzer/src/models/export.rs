@@ -0,0 +1,34 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// How the lines are written by an export
+pub enum ExportFormat {
+    #[default]
+    /// One line of text per log line, its non empty fields joined by spaces
+    Text,
+    /// One JSON object per log line with every field
+    Ndjson,
+    /// Array of the lines with every field split in its highlight groups
+    Json,
+    /// One row per log line with a column per field
+    Csv,
+    /// Report with a table of the lines and their highlight groups colored
+    Html,
+}
+
+impl ExportFormat {
+    /// Format matching the extension of the file, text if it is not a known one
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        match extension.map(|extension| extension.to_ascii_lowercase()).as_deref() {
+            Some("ndjson" | "jsonl") => ExportFormat::Ndjson,
+            Some("json") => ExportFormat::Json,
+            Some("csv") => ExportFormat::Csv,
+            Some("html" | "htm") => ExportFormat::Html,
+            _ => ExportFormat::Text,
+        }
+    }
+}
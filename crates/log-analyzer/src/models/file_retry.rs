@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+#[cfg(feature = "threads")]
+use log_source::source::log_source::Backoff;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Waits between the attempts to read again a followed file that can't be read, e.g. deleted or
+/// no longer readable. The wait doubles after each failed attempt, up to `max_ms`
+pub struct FileRetry {
+    /// Milliseconds waited after the first failed attempt. 1000 if not set
+    pub initial_ms: Option<u64>,
+    /// Longest wait between two attempts in milliseconds. 30000 if not set
+    pub max_ms: Option<u64>,
+}
+
+impl FileRetry {
+    /// Describe the waits set to 0 or the first wait longer than the longest one
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.initial_ms == Some(0) {
+            errors.push("initial_ms: must be greater than 0".to_string());
+        }
+        if self.max_ms == Some(0) {
+            errors.push("max_ms: must be greater than 0".to_string());
+        }
+        if self.initial() > self.max() {
+            errors.push("initial_ms: must not be greater than max_ms".to_string());
+        }
+        errors
+    }
+
+    /// Wait after the first failed attempt
+    pub fn initial(&self) -> Duration {
+        Duration::from_millis(self.initial_ms.unwrap_or(1000))
+    }
+
+    /// Longest wait between two attempts
+    pub fn max(&self) -> Duration {
+        Duration::from_millis(self.max_ms.unwrap_or(30_000))
+    }
+
+    /// Waits of the file sources
+    #[cfg(feature = "threads")]
+    pub fn backoff(&self) -> Backoff {
+        Backoff {
+            initial: self.initial(),
+            max: self.max(),
+        }
+    }
+}
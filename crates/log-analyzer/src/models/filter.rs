@@ -3,10 +3,12 @@ use super::log_line::LogLine;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug, Default)]
+#[allow(clippy::upper_case_acronyms)]
 /// Describe the action of a filter
 pub enum FilterAction {
     /// Just add a color marker
+    #[default]
     MARKER,
     /// Exclude what is not matched by this filter
     INCLUDE,
@@ -24,9 +26,9 @@ impl From<usize> for FilterAction {
     }
 }
 
-impl Into<usize> for FilterAction {
-    fn into(self) -> usize {
-        match self {
+impl From<FilterAction> for usize {
+    fn from(action: FilterAction) -> Self {
+        match action {
             FilterAction::INCLUDE => 0,
             FilterAction::EXCLUDE => 1,
             FilterAction::MARKER => 2,
@@ -34,12 +36,6 @@ impl Into<usize> for FilterAction {
     }
 }
 
-impl Default for FilterAction {
-    fn default() -> Self {
-        FilterAction::MARKER
-    }
-}
-
 
 #[derive(Default, Clone, Debug)]
 /// Struct with cached vector of log_line keys with their associated regex
@@ -93,7 +89,7 @@ mod tests {
             alias: "All".into(),
             action: FilterAction::MARKER,
             filter: LogLine {
-                index: "0".to_string(),
+                index: 0,
                 ..Default::default()
             },
         };
@@ -1,4 +1,4 @@
-use anyhow::{Result, anyhow};
+use crate::error::{LogAnalyzerError, Result};
 use regex::Regex;
 use serde::{Serialize, Deserialize};
 
@@ -13,14 +13,17 @@ pub struct Format {
 
 impl Format {
     pub fn new(alias: &str, regex: &str) -> Result<Self> {
-        if alias.is_empty() || regex.is_empty() {
-            return Err(anyhow!("Error when creating new format.\nPlease review alias and regex are not empty"));
+        if alias.is_empty() {
+            return Err(LogAnalyzerError::EmptyAlias);
+        }
+        if regex.is_empty() {
+            return Err(LogAnalyzerError::EmptyRegex);
         }
 
         let re = Regex::new(regex);
         match re {
             Ok(_) => Ok(Format{alias: alias.to_string(), regex : regex.to_string()}),
-            Err(_) => Err(anyhow!("Could not compile regex.\nPlease review regex syntax"))
+            Err(err) => Err(LogAnalyzerError::InvalidRegex { regex: regex.to_string(), reason: err.to_string() })
         }
     }
 }
@@ -31,7 +34,7 @@ mod tests {
 
     #[test]
     fn serialize() {
-        let format = Format::new(&"All".to_string(), &"(?P<PAYLOAD>.*)".to_string()).unwrap();
+        let format = Format::new("All", "(?P<PAYLOAD>.*)").unwrap();
         let json = serde_json::to_string(&format);
         assert!(json.is_ok())
     }
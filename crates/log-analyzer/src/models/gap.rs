@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+/// Silence between two consecutive filtered lines of a source, e.g. after a crash or a reboot
+pub struct Gap {
+    pub source: String,
+    /// Index in the filtered log of the last line before the gap
+    pub before: usize,
+    /// Index in the filtered log of the first line after the gap
+    pub after: usize,
+    /// Time in seconds of the line before the gap
+    pub start: f64,
+    /// Time in seconds of the line after the gap
+    pub end: f64,
+}
+
+impl Gap {
+    /// Seconds without lines
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
@@ -0,0 +1,69 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// What is done with the lines read that are not valid UTF-8, e.g. binary data written in a log
+pub enum InvalidUtf8 {
+    /// The invalid bytes are replaced by `�`
+    #[default]
+    Replace,
+    /// The line is discarded
+    Skip,
+    /// Each invalid byte is written as `\xNN`
+    HexEscape,
+}
+
+impl InvalidUtf8 {
+    /// Text of `line` following the policy, `None` if the line is skipped. Valid lines are
+    /// borrowed as they are
+    pub fn decode<'a>(&self, line: &'a [u8]) -> Option<Cow<'a, str>> {
+        if let Ok(text) = std::str::from_utf8(line) {
+            return Some(Cow::Borrowed(text));
+        }
+        match self {
+            InvalidUtf8::Replace => Some(String::from_utf8_lossy(line)),
+            InvalidUtf8::Skip => None,
+            InvalidUtf8::HexEscape => Some(Cow::Owned(hex_escape(line))),
+        }
+    }
+}
+
+fn hex_escape(line: &[u8]) -> String {
+    let mut text = String::with_capacity(line.len() + 8);
+    for chunk in line.utf8_chunks() {
+        text.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            write!(text, "\\x{:02X}", byte).unwrap();
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_bytes_follow_the_policy() {
+        let line = b"ok \xFF\xFEdone \xE2\x82";
+        assert_eq!(InvalidUtf8::Replace.decode(line).unwrap(), "ok ��done �");
+        assert_eq!(InvalidUtf8::Skip.decode(line), None);
+        assert_eq!(
+            InvalidUtf8::HexEscape.decode(line).unwrap(),
+            "ok \\xFF\\xFEdone \\xE2\\x82"
+        );
+        assert!(matches!(
+            InvalidUtf8::Skip.decode("valid ✓".as_bytes()),
+            Some(Cow::Borrowed("valid ✓"))
+        ));
+    }
+
+    #[test]
+    fn policies_are_named_in_snake_case() {
+        let policy: InvalidUtf8 = serde_json::from_str(r#""hex_escape""#).unwrap();
+        assert_eq!(policy, InvalidUtf8::HexEscape);
+    }
+}
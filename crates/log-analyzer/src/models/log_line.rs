@@ -1,20 +1,32 @@
+use std::borrow::Cow;
 use std::cmp::Ordering;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+pub use smol_str::SmolStr;
+
+use crate::domain::parse_time::parse_time;
 
 #[derive(Default, Serialize, Deserialize, Clone, Debug)]
 #[serde(default)]
 /// This struct contains a formated log with its info clasified
 /// in several fields
+///
+/// The fields are `SmolStr`: the short ones are kept inline and the long ones shared, so the
+/// copies of a line in the filtered and search logs don't copy its text
 pub struct LogLine {
-    pub log: String,
-    pub index: String,
-    pub date: String,
-    pub timestamp: String,
-    pub app: String,
-    pub severity: String,
-    pub function: String,
-    pub payload: String,
+    pub log: SmolStr,
+    /// Position in the filtered log
+    #[serde(deserialize_with = "deserialize_index")]
+    pub index: usize,
+    /// Position in the raw lines of its source
+    #[serde(deserialize_with = "deserialize_index")]
+    pub raw_index: usize,
+    pub date: SmolStr,
+    pub timestamp: SmolStr,
+    pub app: SmolStr,
+    pub severity: SmolStr,
+    pub function: SmolStr,
+    pub payload: SmolStr,
     pub color: Option<(u8, u8, u8)>,
 }
 
@@ -33,11 +45,18 @@ impl LogLine {
         ]
     }
 
-    /// Gets the field value with the `columns` returned key
-    pub fn get(&self, key: &str) -> Option<&String> {
+    /// Gets the field value with the `columns` returned key, the index as text
+    pub fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        match key {
+            "Index" => Some(Cow::Owned(self.index.to_string())),
+            key => self.get_text(key).map(|text| Cow::Borrowed(text.as_str())),
+        }
+    }
+
+    /// Gets the text field with the `columns` returned key, every column but the index
+    pub fn get_text(&self, key: &str) -> Option<&SmolStr> {
         match key {
             "Log" => Some(&self.log),
-            "Index" => Some(&self.index),
             "Date" => Some(&self.date),
             "Timestamp" => Some(&self.timestamp),
             "App" => Some(&self.app),
@@ -48,8 +67,22 @@ impl LogLine {
         }
     }
 
+    /// Gets the mutable text field with the `columns` returned key, every column but the index
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut SmolStr> {
+        match key {
+            "Log" => Some(&mut self.log),
+            "Date" => Some(&mut self.date),
+            "Timestamp" => Some(&mut self.timestamp),
+            "App" => Some(&mut self.app),
+            "Severity" => Some(&mut self.severity),
+            "Function" => Some(&mut self.function),
+            "Payload" => Some(&mut self.payload),
+            _ => None,
+        }
+    }
+
     /// Gets a (key, value) like representation of some fields
-    pub fn values(&self) -> Vec<(&str, &String)> {
+    pub fn values(&self) -> Vec<(&str, &SmolStr)> {
         vec![
             ("Log", &self.log),
             ("Date", &self.date),
@@ -61,40 +94,67 @@ impl LogLine {
         ]
     }
 
-    /// Check if the content of the lines is formatted
-    pub fn is_formated(&self) -> bool {
-        self.into_iter()
-        .any(|field| serde_json::from_str::<Vec<(Option<&str>, &str)>>(field).is_ok())
+    /// Get the time of this line in seconds, parsed from the date or the timestamp
+    pub fn time(&self) -> Option<f64> {
+        parse_time(&self.date, &self.timestamp)
     }
 
-    /// Return a copy of this line with unformatted content
-    pub fn unformat(&self) -> Self {
-        let unformat = |field: &str| {
-            let groups = serde_json::from_str::<Vec<(Option<&str>, &str)>>(field);
-
-            match groups {
-                Ok(groups) => groups.into_iter().fold(String::new(), |acc, g| acc + g.1),
-                _ => field.to_string(),
-            }
+    /// Estimation of the heap and inline memory used by this line in bytes. The text shared
+    /// with the copies of the line is counted for each of them
+    pub fn memory_size(&self) -> usize {
+        let heap_size = |field: &SmolStr| match field.is_heap_allocated() {
+            true => field.len(),
+            false => 0,
         };
+        std::mem::size_of::<Self>()
+            + heap_size(&self.log)
+            + heap_size(&self.date)
+            + heap_size(&self.timestamp)
+            + heap_size(&self.app)
+            + heap_size(&self.severity)
+            + heap_size(&self.function)
+            + heap_size(&self.payload)
+    }
 
-        LogLine {
-            log: unformat(&self.log),
-            index: unformat(&self.index),
-            date: unformat(&self.date),
-            timestamp: unformat(&self.timestamp),
-            app: unformat(&self.app),
-            severity: unformat(&self.severity),
-            function: unformat(&self.function),
-            payload: unformat(&self.payload),
-            color: self.color,
-        }
+    /// Text of the line, its non empty fields joined by spaces
+    pub fn text(&self) -> String {
+        [
+            &self.date,
+            &self.timestamp,
+            &self.app,
+            &self.severity,
+            &self.function,
+            &self.payload,
+        ]
+        .iter()
+        .filter(|field| !field.is_empty())
+        .map(|field| field.as_str())
+        .collect::<Vec<&str>>()
+        .join(" ")
+    }
+}
+
+/// Fields of a line read by the filters, either a `LogLine` or a raw line formatted in place
+pub trait LineFields {
+    /// Gets the text field with the `LogLine::columns` key, every column but the index
+    fn field(&self, key: &str) -> Option<&str>;
+    /// Position of the line, as `LogLine::index`
+    fn position(&self) -> usize;
+}
+
+impl LineFields for LogLine {
+    fn field(&self, key: &str) -> Option<&str> {
+        self.get_text(key).map(SmolStr::as_str)
+    }
+
+    fn position(&self) -> usize {
+        self.index
     }
 }
 
 impl IntoIterator for LogLine {
-    type Item = String;
-    type IntoIter = std::array::IntoIter<String, 7>;
+    type Item = SmolStr;
+    type IntoIter = std::array::IntoIter<SmolStr, 7>;
 
     fn into_iter(self) -> Self::IntoIter {
         IntoIterator::into_iter([
@@ -110,8 +170,8 @@ impl IntoIterator for LogLine {
 }
 
 impl<'a> IntoIterator for &'a LogLine {
-    type Item = &'a String;
-    type IntoIter = std::array::IntoIter<&'a String, 7>;
+    type Item = &'a SmolStr;
+    type IntoIter = std::array::IntoIter<&'a SmolStr, 7>;
 
     fn into_iter(self) -> Self::IntoIter {
         IntoIterator::into_iter([
@@ -127,8 +187,8 @@ impl<'a> IntoIterator for &'a LogLine {
 }
 
 impl<'a> IntoIterator for &'a mut LogLine {
-    type Item = &'a String;
-    type IntoIter = std::array::IntoIter<&'a String, 7>;
+    type Item = &'a SmolStr;
+    type IntoIter = std::array::IntoIter<&'a SmolStr, 7>;
 
     fn into_iter(self) -> Self::IntoIter {
         IntoIterator::into_iter([
@@ -144,8 +204,8 @@ impl<'a> IntoIterator for &'a mut LogLine {
 }
 
 impl<'a> IntoIterator for &'a &'a mut LogLine {
-    type Item = &'a String;
-    type IntoIter = std::array::IntoIter<&'a String, 7>;
+    type Item = &'a SmolStr;
+    type IntoIter = std::array::IntoIter<&'a SmolStr, 7>;
 
     fn into_iter(self) -> Self::IntoIter {
         IntoIterator::into_iter([
@@ -160,8 +220,8 @@ impl<'a> IntoIterator for &'a &'a mut LogLine {
     }
 }
 impl<'a> IntoIterator for &'a &'a LogLine {
-    type Item = &'a String;
-    type IntoIter = std::array::IntoIter<&'a String, 7>;
+    type Item = &'a SmolStr;
+    type IntoIter = std::array::IntoIter<&'a SmolStr, 7>;
 
     fn into_iter(self) -> Self::IntoIter {
         IntoIterator::into_iter([
@@ -178,27 +238,13 @@ impl<'a> IntoIterator for &'a &'a LogLine {
 
 impl Ord for LogLine {
     fn cmp(&self, other: &Self) -> Ordering {
-        match (self.index.parse::<usize>(), other.index.parse::<usize>()) {
-            (Ok(index), Ok(other)) => match (index, other) {
-                (index, other) if index < other => Ordering::Less,
-                (index, other) if index == other => Ordering::Equal,
-                _ => Ordering::Greater,
-            },
-            _ => Ordering::Equal,
-        }
+        self.index.cmp(&other.index)
     }
 }
 
 impl PartialOrd for LogLine {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (self.index.parse::<usize>(), other.index.parse::<usize>()) {
-            (Ok(index), Ok(other)) => match (index, other) {
-                (index, other) if index < other => Some(Ordering::Less),
-                (index, other) if index == other => Some(Ordering::Equal),
-                _ => Some(Ordering::Greater),
-            },
-            _ => None,
-        }
+        Some(self.cmp(other))
     }
 }
 
@@ -216,3 +262,34 @@ impl PartialEq for LogLine {
 }
 
 impl Eq for LogLine {}
+
+/// Read an index written as a number, or as text by the previous versions (empty for 0)
+fn deserialize_index<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Index {
+        Number(usize),
+        Text(String),
+    }
+
+    match Index::deserialize(deserializer)? {
+        Index::Number(index) => Ok(index),
+        Index::Text(text) if text.is_empty() => Ok(0),
+        Index::Text(text) => text.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indexes_are_read_as_numbers_or_text() {
+        let line: LogLine = serde_json::from_str(r#"{"index": 12, "raw_index": "7"}"#).unwrap();
+        assert_eq!((line.index, line.raw_index), (12, 7));
+        let line: LogLine = serde_json::from_str(r#"{"index": "", "payload": "x"}"#).unwrap();
+        assert_eq!((line.index, line.raw_index), (0, 0));
+        assert!(serde_json::from_str::<LogLine>(r#"{"index": "first"}"#).is_err());
+        assert_eq!(line.get("Index").as_deref(), Some("0"));
+    }
+}
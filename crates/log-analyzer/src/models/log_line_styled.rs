@@ -1,26 +1,86 @@
 use std::cmp::Ordering;
+use std::ops::Range;
 
 use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
 
 use super::log_line::LogLine;
 
+/// Content of a field split in groups of `(highlight group, content)`
+pub type StyledGroups = Vec<(Option<String>, String)>;
+
+/// Part of a field of a line matched by a named group of the search, kept with the searched
+/// line so its styled groups are built without searching it again
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Highlight {
+    /// Position of the field in `LogLine::columns`
+    pub column: usize,
+    /// Name of the matching group, the color it is displayed with
+    pub group: SmolStr,
+    /// Bytes of the field matched
+    pub range: Range<usize>,
+}
+
 #[derive(Default, Serialize, Deserialize, Clone, Debug)]
 #[serde(default)]
 /// This struct contains a formated log with its info clasified
 /// in several fields
 pub struct LogLineStyled {
-    pub log: Vec<(Option<String>, String)>,
-    pub index: Vec<(Option<String>, String)>,
-    pub date: Vec<(Option<String>, String)>,
-    pub timestamp: Vec<(Option<String>, String)>,
-    pub app: Vec<(Option<String>, String)>,
-    pub severity: Vec<(Option<String>, String)>,
-    pub function: Vec<(Option<String>, String)>,
-    pub payload: Vec<(Option<String>, String)>,
+    pub log: StyledGroups,
+    pub index: StyledGroups,
+    /// Position in the raw lines of its source
+    pub raw_index: usize,
+    pub date: StyledGroups,
+    pub timestamp: StyledGroups,
+    pub app: StyledGroups,
+    pub severity: StyledGroups,
+    pub function: StyledGroups,
+    pub payload: StyledGroups,
     pub color: Option<(u8, u8, u8)>,
 }
 
 impl LogLineStyled {
+    /// Split the fields of `line` in the groups of its `highlights`, sorted by position and
+    /// not overlapping. The rest of each field is a group without highlight
+    pub fn new(line: &LogLine, highlights: &[Highlight]) -> Self {
+        let mut columns: Vec<StyledGroups> = LogLine::columns()
+            .iter()
+            .enumerate()
+            .map(|(column, key)| {
+                let text = line.get(key).unwrap();
+                let mut groups = vec![];
+                let mut offset = 0;
+                for highlight in highlights.iter().filter(|h| h.column == column) {
+                    let Some(matched) = text.get(highlight.range.clone()) else {
+                        continue;
+                    };
+                    if offset < highlight.range.start {
+                        groups.push((None, text[offset..highlight.range.start].to_string()));
+                    }
+                    groups.push((Some(highlight.group.to_string()), matched.to_string()));
+                    offset = highlight.range.end;
+                }
+                if offset < text.len() || groups.is_empty() {
+                    groups.push((None, text[offset..].to_string()));
+                }
+                groups
+            })
+            .collect();
+
+        LogLineStyled {
+            log: std::mem::take(&mut columns[0]),
+            index: std::mem::take(&mut columns[1]),
+            raw_index: line.raw_index,
+            date: std::mem::take(&mut columns[2]),
+            timestamp: std::mem::take(&mut columns[3]),
+            app: std::mem::take(&mut columns[4]),
+            severity: std::mem::take(&mut columns[5]),
+            function: std::mem::take(&mut columns[6]),
+            payload: std::mem::take(&mut columns[7]),
+            color: line.color,
+        }
+    }
+
     /// Returns the available fields
     pub fn columns() -> Vec<String> {
         vec![
@@ -36,7 +96,7 @@ impl LogLineStyled {
     }
 
     /// Gets the field value with the `columns` returned key
-    pub fn get(&self, key: &str) -> Option<&Vec<(Option<String>, String)>> {
+    pub fn get(&self, key: &str) -> Option<&StyledGroups> {
         match key {
             "Log" => Some(&self.log),
             "Index" => Some(&self.index),
@@ -51,7 +111,7 @@ impl LogLineStyled {
     }
 
     /// Gets a (key, value) like representation of some fields
-    pub fn values(&self) -> Vec<(&str, &Vec<(Option<String>, String)>)> {
+    pub fn values(&self) -> Vec<(&str, &StyledGroups)> {
         vec![
             ("Log", &self.log),
             ("Date", &self.date),
@@ -66,13 +126,13 @@ impl LogLineStyled {
 
     /// Return a copy of this line with unformatted content
     pub fn unformat(&self) -> LogLine {
-        let unformat = |groups: &Vec<(Option<String>, String)>| {
-            groups.into_iter().fold(String::new(), |acc, g| acc + &g.1)
-        };
+        let text = |groups: &StyledGroups| groups.iter().fold(String::new(), |acc, g| acc + &g.1);
+        let unformat = |groups: &StyledGroups| text(groups).into();
 
         LogLine {
             log: unformat(&self.log),
-            index: unformat(&self.index),
+            index: text(&self.index).parse().unwrap_or_default(),
+            raw_index: self.raw_index,
             date: unformat(&self.date),
             timestamp: unformat(&self.timestamp),
             app: unformat(&self.app),
@@ -84,9 +144,16 @@ impl LogLineStyled {
     }
 }
 
+impl From<&LogLine> for LogLineStyled {
+    /// Every field is a single group without highlight
+    fn from(line: &LogLine) -> Self {
+        LogLineStyled::new(line, &[])
+    }
+}
+
 impl IntoIterator for LogLineStyled {
-    type Item = Vec<(Option<String>, String)>;
-    type IntoIter = std::array::IntoIter<Vec<(Option<String>, String)>, 7>;
+    type Item = StyledGroups;
+    type IntoIter = std::array::IntoIter<StyledGroups, 7>;
 
     fn into_iter(self) -> Self::IntoIter {
         IntoIterator::into_iter([
@@ -102,8 +169,8 @@ impl IntoIterator for LogLineStyled {
 }
 
 impl<'a> IntoIterator for &'a LogLineStyled {
-    type Item = &'a Vec<(Option<String>, String)>;
-    type IntoIter = std::array::IntoIter<&'a Vec<(Option<String>, String)>, 7>;
+    type Item = &'a StyledGroups;
+    type IntoIter = std::array::IntoIter<&'a StyledGroups, 7>;
 
     fn into_iter(self) -> Self::IntoIter {
         IntoIterator::into_iter([
@@ -119,8 +186,8 @@ impl<'a> IntoIterator for &'a LogLineStyled {
 }
 
 impl<'a> IntoIterator for &'a mut LogLineStyled {
-    type Item = &'a Vec<(Option<String>, String)>;
-    type IntoIter = std::array::IntoIter<&'a Vec<(Option<String>, String)>, 7>;
+    type Item = &'a StyledGroups;
+    type IntoIter = std::array::IntoIter<&'a StyledGroups, 7>;
 
     fn into_iter(self) -> Self::IntoIter {
         IntoIterator::into_iter([
@@ -136,8 +203,8 @@ impl<'a> IntoIterator for &'a mut LogLineStyled {
 }
 
 impl<'a> IntoIterator for &'a &'a mut LogLineStyled {
-    type Item = &'a Vec<(Option<String>, String)>;
-    type IntoIter = std::array::IntoIter<&'a Vec<(Option<String>, String)>, 7>;
+    type Item = &'a StyledGroups;
+    type IntoIter = std::array::IntoIter<&'a StyledGroups, 7>;
 
     fn into_iter(self) -> Self::IntoIter {
         IntoIterator::into_iter([
@@ -152,8 +219,8 @@ impl<'a> IntoIterator for &'a &'a mut LogLineStyled {
     }
 }
 impl<'a> IntoIterator for &'a &'a LogLineStyled {
-    type Item = &'a Vec<(Option<String>, String)>;
-    type IntoIter = std::array::IntoIter<&'a Vec<(Option<String>, String)>, 7>;
+    type Item = &'a StyledGroups;
+    type IntoIter = std::array::IntoIter<&'a StyledGroups, 7>;
 
     fn into_iter(self) -> Self::IntoIter {
         IntoIterator::into_iter([
@@ -170,27 +237,13 @@ impl<'a> IntoIterator for &'a &'a LogLineStyled {
 
 impl Ord for LogLineStyled {
     fn cmp(&self, other: &Self) -> Ordering {
-        match (self.unformat().index.parse::<usize>(), other.unformat().index.parse::<usize>()) {
-            (Ok(index), Ok(other)) => match (index, other) {
-                (index, other) if index < other => Ordering::Less,
-                (index, other) if index == other => Ordering::Equal,
-                _ => Ordering::Greater,
-            },
-            _ => Ordering::Equal,
-        }
+        self.unformat().index.cmp(&other.unformat().index)
     }
 }
 
 impl PartialOrd for LogLineStyled {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (self.unformat().index.parse::<usize>(), other.unformat().index.parse::<usize>()) {
-            (Ok(index), Ok(other)) => match (index, other) {
-                (index, other) if index < other => Some(Ordering::Less),
-                (index, other) if index == other => Some(Ordering::Equal),
-                _ => Some(Ordering::Greater),
-            },
-            _ => None,
-        }
+        Some(self.cmp(other))
     }
 }
 
@@ -208,3 +261,40 @@ impl PartialEq for LogLineStyled {
 }
 
 impl Eq for LogLineStyled {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_are_split_around_their_highlights() {
+        let line = LogLine {
+            index: 3,
+            severity: "ERROR".into(),
+            payload: "connection refused by peer".into(),
+            ..Default::default()
+        };
+        let highlight = |column: usize, group: &str, range: Range<usize>| Highlight {
+            column,
+            group: group.into(),
+            range,
+        };
+
+        let styled = LogLineStyled::new(
+            &line,
+            &[highlight(5, "RED", 0..5), highlight(7, "BLUE", 11..18)],
+        );
+        assert_eq!(styled.severity, vec![(Some("RED".to_string()), "ERROR".to_string())]);
+        assert_eq!(
+            styled.payload,
+            vec![
+                (None, "connection ".to_string()),
+                (Some("BLUE".to_string()), "refused".to_string()),
+                (None, " by peer".to_string()),
+            ]
+        );
+        assert_eq!(styled.index, vec![(None, "3".to_string())]);
+        assert_eq!(styled.app, vec![(None, String::new())]);
+        assert_eq!(styled.unformat().payload, line.payload);
+    }
+}
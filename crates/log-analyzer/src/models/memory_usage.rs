@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, Eq, PartialEq)]
+/// Estimated memory held by the stores, in bytes
+pub struct MemoryUsage {
+    /// Raw lines as received from the sources
+    pub raw: usize,
+    /// Processed lines in the filtered log
+    pub filtered: usize,
+    /// Processed lines in the search log
+    pub search: usize,
+}
+
+impl MemoryUsage {
+    /// Sum of the memory used by all the stores
+    pub fn total(&self) -> usize {
+        self.raw + self.filtered + self.search
+    }
+}
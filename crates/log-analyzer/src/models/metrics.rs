@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, Eq, PartialEq)]
+/// Time spent by the workers in every stage of the pipeline, in microseconds.
+/// Stages running in parallel add up, so they can exceed the measured period
+pub struct StageTimings {
+    /// Storing the raw lines and the processed ones
+    pub store: u64,
+    pub format: u64,
+    pub filter: u64,
+    pub search: u64,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, Eq, PartialEq)]
+/// Throughput of the processing pipeline over the last measured period
+pub struct PipelineMetrics {
+    /// Lines read by the sources per second
+    pub ingested_per_sec: u64,
+    /// Lines formatted, filtered and searched per second, including the re-filtered ones
+    pub processed_per_sec: u64,
+    /// Batches of lines waiting to be processed
+    pub queue_depth: usize,
+    /// Workers processing a chunk of lines when measured
+    pub active_workers: usize,
+    /// Workers available to the pipeline
+    pub workers: usize,
+    /// Lines per chunk of the last batch processed, adapted to the load unless configured
+    pub chunk_size: usize,
+    /// Time spent in every stage during the period
+    pub stage_timings: StageTimings,
+    /// Live lines not forwarded to their sinks during the period because the sinks were behind
+    #[serde(default)]
+    pub sink_dropped: u64,
+}
+
+impl PipelineMetrics {
+    /// The pipeline did nothing during the period
+    pub fn is_idle(&self) -> bool {
+        self.ingested_per_sec == 0
+            && self.processed_per_sec == 0
+            && self.queue_depth == 0
+            && self.active_workers == 0
+    }
+}
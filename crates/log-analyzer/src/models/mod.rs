@@ -1,5 +1,37 @@
+pub mod alert;
+pub mod burst;
+pub mod column_filter;
+pub mod dedupe;
+#[cfg(feature = "threads")]
+pub mod diff;
+pub mod export;
+pub mod file_retry;
 pub mod filter;
 pub mod format;
+#[cfg(feature = "threads")]
+pub mod gap;
+pub mod invalid_utf8;
 pub mod log_line;
 pub mod log_line_styled;
-pub mod settings;
\ No newline at end of file
+pub mod memory_usage;
+pub mod metrics;
+pub mod notification;
+pub mod overload_policy;
+pub mod pipeline;
+pub mod query;
+pub mod rate_bucket;
+pub mod redaction;
+pub mod recent_sources;
+pub mod retention;
+pub mod session;
+pub mod settings;
+pub mod severity;
+pub mod sink;
+#[cfg(feature = "threads")]
+pub mod snapshot;
+pub mod source_stats;
+pub mod statistics;
+pub mod top_values;
+pub mod transform;
+#[cfg(feature = "threads")]
+pub mod watch;
\ No newline at end of file
@@ -0,0 +1,34 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+/// Importance of a notification, sorted from the lowest
+pub enum NotificationLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+/// Runtime event worth reporting to the user
+pub struct Notification {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub level: NotificationLevel,
+    pub message: String,
+}
+
+impl Notification {
+    /// Create a notification stamped with the current time
+    pub fn new(level: NotificationLevel, message: &str) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+            level,
+            message: message.to_string(),
+        }
+    }
+}
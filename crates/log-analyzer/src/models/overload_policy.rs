@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+/// What is done with the lines read by the sources while the pipeline queue is full, e.g.
+/// `{"type": "sample", "every": 10}`
+pub enum OverloadPolicy {
+    /// The sources wait for room in the queue, no line is lost
+    #[default]
+    Block,
+    /// The oldest queued batch is discarded to make room for the new one
+    DropOldest,
+    /// Only one of every `every` lines of the new batches is queued, waiting for room
+    Sample { every: usize },
+}
+
+impl OverloadPolicy {
+    /// Problems preventing the policy from being applied, if any
+    pub fn validate(&self) -> Vec<String> {
+        match self {
+            OverloadPolicy::Sample { every: 0 } => {
+                vec!["every: must be greater than 0".to_string()]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Sizing of the processing pipeline, e.g. to cap the CPU used on a shared machine or to tune
+/// the throughput. The defaults of the engine are kept for the options not set
+pub struct PipelineConfig {
+    /// Threads processing the lines in parallel. One per CPU if not set
+    pub workers: Option<usize>,
+    /// Lines processed at once by a worker. Adapted to the latency of the chunks and the
+    /// batches queued if not set
+    pub chunk_size: Option<usize>,
+    /// Batches of lines held by each stage of the pipeline before applying the overload policy
+    pub queue_capacity: Option<usize>,
+    /// Events kept for slow subscribers and lines queued for the sinks
+    pub channel_capacity: Option<usize>,
+}
+
+impl PipelineConfig {
+    /// Describe the options set to 0, which would stall the pipeline
+    pub fn validate(&self) -> Vec<String> {
+        [
+            ("workers", self.workers),
+            ("chunk_size", self.chunk_size),
+            ("queue_capacity", self.queue_capacity),
+            ("channel_capacity", self.channel_capacity),
+        ]
+        .into_iter()
+        .filter(|(_, value)| *value == Some(0))
+        .map(|(name, _)| format!("{}: must be greater than 0", name))
+        .collect()
+    }
+}
@@ -0,0 +1,182 @@
+use regex::Regex;
+
+use crate::error::{LogAnalyzerError, Result};
+
+use super::{
+    filter::{Filter, FilterAction, LogFilter},
+    log_line::{LogLine, SmolStr},
+};
+
+#[derive(Debug)]
+enum Matcher {
+    /// Regex matched against every field of the line
+    Any(Regex),
+    /// Regex matched against the column with the given `LogLine::columns` key
+    Column(String, Regex),
+    /// Lines kept by the filter: the matching ones for INCLUDE and MARKER filters,
+    /// the not matching ones for EXCLUDE filters
+    Filter(LogFilter),
+}
+
+#[derive(Debug)]
+struct Term {
+    matcher: Matcher,
+    negated: bool,
+}
+
+#[derive(Debug)]
+/// Line matcher parsed from a query expression.
+///
+/// An expression is a list of terms separated by spaces, all of which must match a line:
+/// * `regex` matches any field of the line
+/// * `column:regex` matches a single column, e.g. `severity:ERROR|WARN`
+/// * `@alias` matches the lines kept by the filter with the given alias
+/// * `-term` negates the term
+///
+/// Terms containing spaces can be quoted: `payload:"connection lost"`
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+impl Query {
+    /// Parse the expression. `filters` are the ones that can be referenced with `@alias`
+    pub fn parse(expression: &str, filters: &[Filter]) -> Result<Self> {
+        let invalid = |reason: String| LogAnalyzerError::InvalidQuery {
+            expression: expression.to_string(),
+            reason,
+        };
+        let terms = split_terms(expression)
+            .map_err(invalid)?
+            .into_iter()
+            .map(|term| parse_term(&term, filters))
+            .collect::<Result<Vec<Term>, String>>()
+            .map_err(invalid)?;
+
+        match terms.is_empty() {
+            true => Err(invalid("The query is empty".to_string())),
+            false => Ok(Self { terms }),
+        }
+    }
+
+    pub fn matches(&self, log_line: &LogLine) -> bool {
+        self.terms.iter().all(|term| {
+            let is_match = match &term.matcher {
+                Matcher::Any(regex) => log_line.into_iter().any(|field| regex.is_match(field)),
+                Matcher::Column(column, regex) => {
+                    regex.is_match(&log_line.get(column).unwrap_or_default())
+                }
+                Matcher::Filter(filter) => {
+                    let filter_match = filter.filters.iter().all(|(key, regex)| {
+                        regex.is_match(log_line.get_text(key).map_or("", SmolStr::as_str))
+                    });
+                    filter_match != (filter.action == FilterAction::EXCLUDE)
+                }
+            };
+            is_match != term.negated
+        })
+    }
+}
+
+/// Split the expression by spaces, keeping together the text between double quotes
+fn split_terms(expression: &str) -> Result<Vec<String>, String> {
+    let mut terms = Vec::new();
+    let mut term = String::new();
+    let mut quoted = false;
+    for c in expression.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !term.is_empty() {
+                    terms.push(std::mem::take(&mut term));
+                }
+            }
+            c => term.push(c),
+        }
+    }
+    if quoted {
+        return Err("Unclosed quote in the query".to_string());
+    }
+    if !term.is_empty() {
+        terms.push(term);
+    }
+    Ok(terms)
+}
+
+fn parse_term(term: &str, filters: &[Filter]) -> Result<Term, String> {
+    let (negated, term) = match term.strip_prefix('-') {
+        Some(rest) if !rest.is_empty() => (true, rest),
+        _ => (false, term),
+    };
+    let compile = |regex: &str| {
+        Regex::new(regex).map_err(|err| format!("Invalid regex in query term {}: {}", term, err))
+    };
+
+    let matcher = if let Some(alias) = term.strip_prefix('@') {
+        let filter = filters
+            .iter()
+            .find(|filter| filter.alias == alias)
+            .ok_or_else(|| format!("Filter {} not found", alias))?;
+        Matcher::Filter(LogFilter {
+            action: filter.action,
+            filters: filter.get_filters(),
+            color: None,
+        })
+    } else {
+        let column = term.split_once(':').and_then(|(column, regex)| {
+            LogLine::columns()
+                .into_iter()
+                .find(|key| key.eq_ignore_ascii_case(column))
+                .map(|key| (key, regex))
+        });
+        match column {
+            Some((column, regex)) => Matcher::Column(column, compile(regex)?),
+            None => Matcher::Any(compile(term)?),
+        }
+    };
+
+    Ok(Term { matcher, negated })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(severity: &str, payload: &str) -> LogLine {
+        LogLine {
+            severity: severity.into(),
+            payload: payload.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn all_terms_must_match() {
+        let query = Query::parse(r#"severity:ERROR|WARN -payload:"retry later" 12:\d+"#, &[]).unwrap();
+
+        assert!(query.matches(&line("ERROR", "failed at 12:30")));
+        assert!(!query.matches(&line("INFO", "failed at 12:30")));
+        assert!(!query.matches(&line("WARN", "retry later at 12:30")));
+        assert!(!query.matches(&line("WARN", "failed")));
+    }
+
+    #[test]
+    fn filters_are_referenced_by_alias() {
+        let filters = vec![Filter {
+            alias: "No debug".into(),
+            action: FilterAction::EXCLUDE,
+            filter: LogLine {
+                severity: "DEBUG".into(),
+                ..Default::default()
+            },
+        }];
+        let query = Query::parse(r#""@No debug""#, &filters).unwrap();
+
+        assert!(query.matches(&line("INFO", "kept")));
+        assert!(!query.matches(&line("DEBUG", "excluded")));
+        assert!(matches!(
+            Query::parse("@Missing", &filters),
+            Err(LogAnalyzerError::InvalidQuery { .. })
+        ));
+        assert!(Query::parse("severity:[", &filters).is_err());
+    }
+}
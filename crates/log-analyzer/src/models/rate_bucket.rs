@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, Eq, PartialEq)]
+/// Lines of the raw, filtered and search logs whose time falls in a bucket of a histogram
+pub struct RateBucket {
+    /// Start of the bucket in seconds
+    pub start: i64,
+    pub raw: usize,
+    pub filtered: usize,
+    pub search: usize,
+}
+
+/// Buckets of a histogram above which the empty ones are left out, e.g. when time-only lines
+/// (seconds since midnight) are mixed with dated ones (seconds since the epoch)
+pub const MAX_CONTIGUOUS_BUCKETS: i64 = 100_000;
+
+impl RateBucket {
+    /// Group per second counts as (second, raw, filtered, search), sorted by second, into buckets
+    /// of `bucket_seconds` starting at multiples of it.
+    ///
+    /// The empty buckets between the first and the last line are kept so the buckets are
+    /// contiguous, unless there would be more than `MAX_CONTIGUOUS_BUCKETS`
+    pub fn histogram(per_second: &[(i64, usize, usize, usize)], bucket_seconds: i64) -> Vec<Self> {
+        let bucket_seconds = bucket_seconds.max(1);
+        let bucket_start =
+            |second: i64| second.div_euclid(bucket_seconds).saturating_mul(bucket_seconds);
+
+        let mut histogram: Vec<Self> = Vec::new();
+        for &(second, raw, filtered, search) in per_second {
+            let start = bucket_start(second);
+            match histogram.last_mut() {
+                Some(bucket) if bucket.start == start => {
+                    bucket.raw += raw;
+                    bucket.filtered += filtered;
+                    bucket.search += search;
+                }
+                _ => histogram.push(Self {
+                    start,
+                    raw,
+                    filtered,
+                    search,
+                }),
+            }
+        }
+
+        let buckets = match (histogram.first(), histogram.last()) {
+            (Some(first), Some(last)) => last
+                .start
+                .checked_sub(first.start)
+                .map(|span| span / bucket_seconds + 1),
+            _ => None,
+        };
+        match buckets {
+            Some(buckets) if buckets <= MAX_CONTIGUOUS_BUCKETS => {
+                let mut contiguous = Vec::with_capacity(buckets as usize);
+                for bucket in histogram {
+                    while let Some(previous) = contiguous.last().map(|b: &Self| b.start) {
+                        if previous + bucket_seconds >= bucket.start {
+                            break;
+                        }
+                        contiguous.push(Self {
+                            start: previous + bucket_seconds,
+                            ..Default::default()
+                        });
+                    }
+                    contiguous.push(bucket);
+                }
+                contiguous
+            }
+            _ => histogram,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_are_grouped_in_aligned_buckets() {
+        let per_second = vec![(61, 2, 1, 0), (119, 1, 1, 1), (250, 3, 0, 0)];
+
+        let histogram = RateBucket::histogram(&per_second, 60);
+
+        let bucket = |start, raw, filtered, search| RateBucket {
+            start,
+            raw,
+            filtered,
+            search,
+        };
+        assert_eq!(
+            histogram,
+            vec![bucket(60, 3, 2, 1), bucket(120, 0, 0, 0), bucket(180, 0, 0, 0), bucket(240, 3, 0, 0)]
+        );
+        assert_eq!(RateBucket::histogram(&per_second, 0).len(), 190);
+        assert!(RateBucket::histogram(&[], 60).is_empty());
+
+        // A time-only line mixed with a dated one
+        let mixed = vec![(37230, 1, 1, 0), (1_690_000_000, 1, 0, 0), (i64::MAX, 1, 0, 0)];
+        let histogram = RateBucket::histogram(&mixed, 1);
+        assert_eq!(
+            histogram,
+            vec![bucket(37230, 1, 1, 0), bucket(1_690_000_000, 1, 0, 0), bucket(i64::MAX, 1, 0, 0)]
+        );
+        let negative = RateBucket::histogram(&[(i64::MIN, 1, 0, 0), (0, 1, 0, 0)], 60);
+        assert_eq!(negative.len(), 2);
+    }
+}
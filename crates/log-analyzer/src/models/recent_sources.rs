@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+use super::format::Format;
+use crate::error::{LogAnalyzerError, Result};
+
+/// Maximum number of sources remembered
+pub const MAX_RECENT_SOURCES: usize = 10;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+/// Source added by the user. The format is kept complete so it can be recreated
+/// in a later run where it is not defined
+pub struct RecentSource {
+    /// Source type as accepted by `LogAnalyzer::add_log`
+    pub source_type: usize,
+    pub address: String,
+    pub format: Option<Format>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+/// Recently added sources, most recent first
+pub struct RecentSources {
+    #[serde(default)]
+    pub sources: Vec<RecentSource>,
+}
+
+impl RecentSources {
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|err| LogAnalyzerError::InvalidJson(err.to_string()))
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|err| LogAnalyzerError::InvalidJson(err.to_string()))
+    }
+
+    /// Move the source to the top of the list, forgetting the oldest one if the list is full
+    pub fn add(&mut self, source: RecentSource) {
+        self.sources.retain(|recent| {
+            recent.source_type != source.source_type || recent.address != source.address
+        });
+        self.sources.insert(0, source);
+        self.sources.truncate(MAX_RECENT_SOURCES);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(address: &str) -> RecentSource {
+        RecentSource {
+            address: address.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn most_recent_source_goes_first_without_duplicates() {
+        let mut recent = RecentSources::default();
+        for i in 0..MAX_RECENT_SOURCES + 2 {
+            recent.add(source(&format!("{}.log", i)));
+        }
+        recent.add(source("5.log"));
+
+        assert_eq!(recent.sources.len(), MAX_RECENT_SOURCES);
+        assert_eq!(recent.sources[0].address, "5.log");
+        assert_eq!(recent.sources[1].address, "11.log");
+        assert_eq!(recent.sources.iter().filter(|s| s.address == "5.log").count(), 1);
+
+        let json = recent.to_json().unwrap();
+        assert_eq!(RecentSources::from_json(&json).unwrap().sources.len(), MAX_RECENT_SOURCES);
+    }
+}
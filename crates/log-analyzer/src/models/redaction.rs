@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// Text replacing the matches of a redaction without replacement
+pub const DEFAULT_REDACTION_REPLACEMENT: &str = "[REDACTED]";
+
+fn default_replacement() -> String {
+    DEFAULT_REDACTION_REPLACEMENT.to_string()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+/// Text hidden in the fields of the lines, e.g. `{"regex": "[\\w.+-]+@[\\w-]+\\.[\\w.]+",
+/// "replacement": "<email>"}`. The replacement can refer to the capture groups of the regex
+/// with `$1` or `$name`
+pub struct Redaction {
+    pub regex: String,
+    #[serde(default = "default_replacement")]
+    pub replacement: String,
+}
+
+impl Redaction {
+    pub fn new(regex: &str) -> Self {
+        Self {
+            regex: regex.to_string(),
+            replacement: default_replacement(),
+        }
+    }
+}
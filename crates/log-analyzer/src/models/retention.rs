@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Limits of the lines kept by the stores, e.g. to tail a log for days keeping only the recent
+/// lines. Beyond them the oldest lines are discarded. No limit if not set
+pub struct Retention {
+    /// Raw lines kept per source, and lines kept in the filtered log
+    pub max_lines: Option<usize>,
+    /// Seconds the lines are kept after being received
+    pub max_age_secs: Option<u64>,
+}
+
+impl Retention {
+    /// Describe the limits set to 0, which would discard every line
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.max_lines == Some(0) {
+            errors.push("max_lines: must be greater than 0".to_string());
+        }
+        if self.max_age_secs == Some(0) {
+            errors.push("max_age_secs: must be greater than 0".to_string());
+        }
+        errors
+    }
+}
@@ -0,0 +1,217 @@
+#[cfg(feature = "fs")]
+use std::{fs, path::Path};
+
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+
+use crate::error::{LogAnalyzerError, Result};
+
+use super::{
+    alert::Alert, column_filter::ColumnFilter, filter::Filter, format::Format, log_line::LogLine,
+    severity::Severity, sink::Sink,
+};
+
+/// Extension of the files where an analysis is saved to be shared
+pub const SESSION_EXTENSION: &str = "lapsession";
+/// Version of the session files written by this version of the application
+pub const SESSION_VERSION: u32 = 1;
+
+/// Names of the source types accepted in settings files, in the order of their number
+pub const SOURCE_TYPE_NAMES: [&str; 4] = ["file", "ws", "stdin", "plugin"];
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+/// Source of a session
+pub struct SessionSource {
+    /// Source type as accepted by `LogAnalyzer::add_log`. Read from its number or its name
+    #[serde(alias = "type", deserialize_with = "source_type_from_name")]
+    pub source_type: usize,
+    /// Path of the file or address of the websocket
+    #[serde(alias = "path")]
+    pub address: String,
+    /// Alias of the format applied to the lines
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+fn source_type_from_name<'de, D>(deserializer: D) -> std::result::Result<usize, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NameOrNumber {
+        Number(usize),
+        Name(String),
+    }
+
+    match NameOrNumber::deserialize(deserializer)? {
+        NameOrNumber::Number(number) => Ok(number),
+        NameOrNumber::Name(name) => SOURCE_TYPE_NAMES
+            .iter()
+            .position(|known| known.eq_ignore_ascii_case(&name))
+            .ok_or_else(|| {
+                D::Error::custom(format!(
+                    "unknown source type '{}', expected one of {}",
+                    name,
+                    SOURCE_TYPE_NAMES.join(", ")
+                ))
+            }),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+/// Filter of a session together with its enabled state
+pub struct SessionFilter {
+    pub enabled: bool,
+    pub filter: Filter,
+    /// Action triggered when the filter matches a live line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alert: Option<Alert>,
+    /// Destination of the live lines matched by the filter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sink: Option<Sink>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+/// Columns of the log tables as arranged by the user
+pub struct ColumnLayout {
+    /// Every column in display order, with whether it is displayed
+    pub columns: Vec<(String, bool)>,
+    /// Columns kept on the left while scrolling horizontally
+    pub frozen: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+/// Snapshot of the analysis state, used to recover the work after the application is closed
+/// or to hand an investigation over in a `.lapsession` file
+pub struct Session {
+    /// Version of the application format the session was written with
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub sources: Vec<SessionSource>,
+    #[serde(default)]
+    pub formats: Vec<Format>,
+    #[serde(default)]
+    pub filters: Vec<SessionFilter>,
+    pub search: Option<String>,
+    /// Lines bookmarked by the user
+    #[serde(default)]
+    pub pinned_lines: Vec<LogLine>,
+    /// Index of the line selected in the log
+    pub log_position: Option<usize>,
+    #[serde(default)]
+    pub column_filters: Vec<ColumnFilter>,
+    #[serde(default)]
+    pub hidden_severities: Vec<Severity>,
+    /// Columns of the log tables, the default ones if not set
+    pub columns: Option<ColumnLayout>,
+}
+
+impl Session {
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|err| LogAnalyzerError::InvalidSession(err.to_string()))
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|err| LogAnalyzerError::InvalidJson(err.to_string()))
+    }
+
+    /// Write the session to a file, usually with the `SESSION_EXTENSION` extension
+    #[cfg(feature = "fs")]
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut session = serde_json::to_value(self)
+            .map_err(|err| LogAnalyzerError::InvalidJson(err.to_string()))?;
+        session["version"] = SESSION_VERSION.into();
+        let json = serde_json::to_string_pretty(&session)
+            .map_err(|err| LogAnalyzerError::InvalidJson(err.to_string()))?;
+        fs::write(path, json).map_err(|err| file_unavailable(path, err))
+    }
+
+    /// Read a session written by `save`. Sessions of newer versions are refused
+    #[cfg(feature = "fs")]
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path).map_err(|err| file_unavailable(path, err))?;
+        let session = Session::from_json(&json)?;
+        if session.version > SESSION_VERSION {
+            return Err(LogAnalyzerError::InvalidSession(format!(
+                "{} was written by a newer version (session format {}, supported up to {})",
+                path.display(),
+                session.version,
+                SESSION_VERSION
+            )));
+        }
+        Ok(session)
+    }
+
+    /// A session without sources, formats nor filters has nothing worth restoring
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty() && self.formats.is_empty() && self.filters.is_empty()
+    }
+}
+
+#[cfg(feature = "fs")]
+fn file_unavailable(path: &Path, err: std::io::Error) -> LogAnalyzerError {
+    LogAnalyzerError::FileUnavailable {
+        path: path.display().to_string(),
+        reason: err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_roundtrip() {
+        let session = Session {
+            sources: vec![SessionSource {
+                source_type: 0,
+                address: "test.log".into(),
+                format: Some("Default".into()),
+                enabled: true,
+            }],
+            search: Some("error".into()),
+            log_position: Some(42),
+            ..Default::default()
+        };
+
+        let restored = Session::from_json(&session.to_json().unwrap()).unwrap();
+        assert_eq!(restored.sources[0].address, "test.log");
+        assert_eq!(restored.search.as_deref(), Some("error"));
+        assert_eq!(restored.log_position, Some(42));
+        assert!(!restored.is_empty());
+        assert!(Session::from_json("{}").unwrap().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn session_files_keep_the_whole_analysis() {
+        let dir = test_utils::tempdir().unwrap();
+        let path = dir.path().join(format!("investigation.{}", SESSION_EXTENSION));
+        let session = Session {
+            hidden_severities: vec![Severity::Debug],
+            columns: Some(ColumnLayout {
+                columns: vec![("Payload".into(), true), ("Date".into(), false)],
+                frozen: vec!["Payload".into()],
+            }),
+            ..Default::default()
+        };
+
+        session.save(&path).unwrap();
+        let loaded = Session::load(&path).unwrap();
+        fs::write(&path, r#"{"version": 99}"#).unwrap();
+        let newer = Session::load(&path);
+
+        assert_eq!(loaded.version, SESSION_VERSION);
+        assert_eq!(loaded.hidden_severities, vec![Severity::Debug]);
+        assert_eq!(loaded.columns, session.columns);
+        assert!(newer.unwrap_err().to_string().contains("newer version"));
+    }
+}
@@ -1,24 +1,399 @@
-use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::domain::import_config::import_config;
+use crate::error::{LogAnalyzerError, Result};
+
+use super::{
+    alert::{Alert, AlertAction},
+    burst::BurstConfig,
+    dedupe::DedupeConfig,
+    file_retry::FileRetry,
+    filter::Filter,
+    format::Format,
+    invalid_utf8::InvalidUtf8,
+    overload_policy::OverloadPolicy,
+    pipeline::PipelineConfig,
+    query::Query,
+    redaction::Redaction,
+    retention::Retention,
+    session::{Session, SessionFilter, SessionSource, SOURCE_TYPE_NAMES},
+    sink::Sink,
+    transform::Transform,
+};
+
+/// Capture group names of a format assigned to the fields of the log lines
+pub const FORMAT_GROUPS: [&str; 6] = ["DATE", "TIMESTAMP", "APP", "SEVERITY", "FUNCTION", "PAYLOAD"];
 
-use super::{filter::Filter, format::Format};
+#[derive(Serialize, Deserialize, Debug, Default)]
+/// Filter of the settings together with the state it is loaded with
+pub struct SettingsFilter {
+    #[serde(flatten)]
+    pub filter: Filter,
+    /// Filters are loaded disabled if not set
+    #[serde(default)]
+    pub enabled: bool,
+    /// Action triggered when the filter matches a live line, enabled or not
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alert: Option<Alert>,
+    /// Destination of the live lines matched by the filter, enabled or not
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sink: Option<Sink>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+/// Outcome of importing the configuration of another log viewer into the settings
+pub struct ImportReport {
+    pub formats: usize,
+    pub filters: usize,
+    /// Entries left out, with the reason
+    pub skipped: Vec<String>,
+}
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Settings {
     pub formats: Option<Vec<Format>>,
-    pub filters: Option<Vec<Filter>>,
+    pub filters: Option<Vec<SettingsFilter>>,
+    /// Sources opened at startup
+    pub sources: Option<Vec<SessionSource>>,
     pub primary_color: Option<(u8, u8, u8)>,
+    /// Background color of every other row of the log tables. Rows are not striped if not set
+    pub stripe_color: Option<(u8, u8, u8)>,
+    /// Memory used by the stores (in MB) from which the status bar displays a warning
+    pub memory_warning_mb: Option<usize>,
+    /// Memory used by the stores (in MB) from which the status bar displays a critical alert
+    pub memory_critical_mb: Option<usize>,
+    /// Time between two refreshes of the interface in milliseconds
+    pub tick_rate_ms: Option<u64>,
+    /// Named colors that can be used instead of RGB tuples in the colors and filters of the
+    /// settings, and as group names in searches
+    pub palette: Option<BTreeMap<String, (u8, u8, u8)>>,
+    /// Look for bursts of filtered lines with these options. Disabled if not set
+    pub burst_detection: Option<BurstConfig>,
+    /// Expression extracting the id grouping the lines of a transaction (e.g. `req=(\w+)`).
+    /// The first capture group is the id, or the whole match if it has no groups
+    pub correlation_key: Option<String>,
+    /// Query expressions whose live matches are counted per second, e.g. `payload:retry`
+    pub watches: Option<Vec<String>>,
+    /// Rules cleaning up the fields of the lines once formatted, e.g. stripping a prefix
+    pub transforms: Option<Vec<Transform>>,
+    /// Text hidden in the lines before they are filtered and stored, e.g. emails or tokens
+    pub redactions: Option<Vec<Redaction>>,
+    /// Limits of the lines kept, the oldest lines being discarded beyond them. Every line is
+    /// kept if not set
+    pub retention: Option<Retention>,
+    /// What is done with the lines read while the pipeline is full. The sources are slowed down
+    /// if not set, no line being lost
+    pub overload_policy: Option<OverloadPolicy>,
+    /// Workers, chunk size and capacities of the processing pipeline. The defaults of the engine
+    /// are kept for the options not set
+    pub pipeline: Option<PipelineConfig>,
+    /// Bytes of the lines processed and displayed, the longer lines being cut with an indicator.
+    /// No limit if not set
+    pub max_line_length: Option<usize>,
+    /// What is done with the lines that are not valid UTF-8. The invalid bytes are replaced by
+    /// `�` if not set
+    pub invalid_utf8: Option<InvalidUtf8>,
+    /// Waits between the attempts to read again a followed file that can't be read. From 1 to
+    /// 30 seconds if not set
+    pub file_retry: Option<FileRetry>,
+    /// Hide the lines with the payload of a line of another source read around the same time,
+    /// e.g. a daemon log collected with syslog. Every line is shown if not set
+    pub dedupe: Option<DedupeConfig>,
+    /// Directory of the libraries read as source plugins. No plugin is loaded if not set
+    pub plugins_dir: Option<String>,
 }
 
 impl Settings {
     pub fn from_json(json: &str) -> Result<Self> {
-        let settings: Result<Settings, _> = serde_json::from_str(json);
+        serde_json::from_str(json)
+            .map_err(|err| err.to_string())
+            .and_then(|mut settings| {
+                resolve_palette(&mut settings)?;
+                serde_json::from_value(settings).map_err(|err| err.to_string())
+            })
+            .map_err(LogAnalyzerError::InvalidSettings)
+    }
+
+    /// Check the formats, filters and options, returning a description of every problem found.
+    ///
+    /// Entries with errors are otherwise skipped or partially applied when the settings are loaded
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        for (i, format) in self.formats.iter().flatten().enumerate() {
+            let name = format!("Format #{} '{}'", i + 1, format.alias);
+            if format.alias.is_empty() {
+                errors.push(format!("{}: alias is empty", name));
+            }
+            if self.formats.iter().flatten().take(i).any(|f| f.alias == format.alias) {
+                errors.push(format!("{}: alias is already used by another format", name));
+            }
+            match Regex::new(&format.regex) {
+                Ok(regex) => {
+                    for group in regex.capture_names().flatten() {
+                        if !FORMAT_GROUPS.contains(&group) {
+                            errors.push(format!(
+                                "{}: unknown capture group '{}', expected one of {}",
+                                name,
+                                group,
+                                FORMAT_GROUPS.join(", ")
+                            ));
+                        }
+                    }
+                }
+                Err(err) => errors.push(format!("{}: invalid regex: {}", name, err)),
+            }
+        }
+
+        for (i, SettingsFilter { filter, alert, sink, .. }) in self.filters.iter().flatten().enumerate() {
+            let name = format!("Filter #{} '{}'", i + 1, filter.alias);
+            if filter.alias.is_empty() {
+                errors.push(format!("{}: alias is empty", name));
+            }
+            if let Some(AlertAction::Command { command }) = alert.as_ref().map(|alert| &alert.action) {
+                if command.trim().is_empty() {
+                    errors.push(format!("{}: alert command is empty", name));
+                }
+            }
+            if let Some(Err(err)) = sink.as_ref().map(Sink::validate) {
+                errors.push(format!("{}: {}", name, err));
+            }
+            for (field, value) in filter.filter.values() {
+                if let Err(err) = Regex::new(value) {
+                    errors.push(format!("{}: invalid regex in field {}: {}", name, field, err));
+                }
+            }
+        }
+
+        for (i, source) in self.sources.iter().flatten().enumerate() {
+            let name = format!("Source #{} '{}'", i + 1, source.address);
+            if source.source_type >= SOURCE_TYPE_NAMES.len() {
+                errors.push(format!(
+                    "{}: unknown source type {}, expected one of {}",
+                    name,
+                    source.source_type,
+                    SOURCE_TYPE_NAMES.join(", ")
+                ));
+            }
+            if source.address.is_empty() {
+                errors.push(format!("{}: address is empty", name));
+            }
+            if let Some(alias) = &source.format {
+                if !self.formats.iter().flatten().any(|format| &format.alias == alias) {
+                    errors.push(format!("{}: format '{}' is not declared", name, alias));
+                }
+            }
+        }
+
+        if let Some(burst_detection) = &self.burst_detection {
+            if burst_detection.interval_secs == 0 {
+                errors.push("burst_detection.interval_secs: must be greater than 0".to_string());
+            }
+            if burst_detection.factor <= 1.0 {
+                errors.push("burst_detection.factor: must be greater than 1".to_string());
+            }
+        }
+        if let Some(correlation_key) = &self.correlation_key {
+            if let Err(err) = Regex::new(correlation_key) {
+                errors.push(format!("correlation_key: invalid regex: {}", err));
+            }
+        }
+        let filters: Vec<Filter> = self
+            .filters
+            .iter()
+            .flatten()
+            .map(|SettingsFilter { filter, .. }| Filter {
+                alias: filter.alias.clone(),
+                action: filter.action,
+                filter: filter.filter.clone(),
+            })
+            .collect();
+        for (i, watch) in self.watches.iter().flatten().enumerate() {
+            if let Err(err) = Query::parse(watch, &filters) {
+                errors.push(format!("Watch #{} '{}': {}", i + 1, watch, err));
+            }
+        }
+        for (i, transform) in self.transforms.iter().flatten().enumerate() {
+            if let Err(err) = transform.validate() {
+                errors.push(format!("Transform #{} ({}): {}", i + 1, transform.field, err));
+            }
+        }
+        for (i, redaction) in self.redactions.iter().flatten().enumerate() {
+            if let Err(err) = Regex::new(&redaction.regex) {
+                errors.push(format!("Redaction #{}: invalid regex: {}", i + 1, err));
+            }
+        }
+        for err in self.retention.iter().flat_map(Retention::validate) {
+            errors.push(format!("retention.{}", err));
+        }
+        for err in self.overload_policy.iter().flat_map(OverloadPolicy::validate) {
+            errors.push(format!("overload_policy.{}", err));
+        }
+        for err in self.pipeline.iter().flat_map(PipelineConfig::validate) {
+            errors.push(format!("pipeline.{}", err));
+        }
+        for err in self.dedupe.iter().flat_map(DedupeConfig::validate) {
+            errors.push(format!("dedupe.{}", err));
+        }
+        for err in self.file_retry.iter().flat_map(FileRetry::validate) {
+            errors.push(format!("file_retry.{}", err));
+        }
+        if self.max_line_length == Some(0) {
+            errors.push("max_line_length: must be greater than 0".to_string());
+        }
+        if self.tick_rate_ms == Some(0) {
+            errors.push("tick_rate_ms: must be greater than 0".to_string());
+        }
+        if let (Some(warning), Some(critical)) = (self.memory_warning_mb, self.memory_critical_mb) {
+            if warning >= critical {
+                errors.push(
+                    "memory_warning_mb: must be lower than memory_critical_mb".to_string(),
+                );
+            }
+        }
+
+        errors
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|err| LogAnalyzerError::InvalidJson(err.to_string()))
+    }
+
+    /// Take the sources, formats and filters out of the settings as a session to be restored,
+    /// leaving only the display options
+    pub fn take_session(&mut self) -> Session {
+        Session {
+            sources: self.sources.take().unwrap_or_default(),
+            formats: self.formats.take().unwrap_or_default(),
+            filters: self
+                .filters
+                .take()
+                .unwrap_or_default()
+                .into_iter()
+                .map(
+                    |SettingsFilter {
+                         filter,
+                         enabled,
+                         alert,
+                         sink,
+                     }| SessionFilter {
+                        enabled,
+                        filter,
+                        alert,
+                        sink,
+                    },
+                )
+                .collect(),
+            ..Default::default()
+        }
+    }
 
-        match settings {
-            Ok(settings) => Ok(settings),
-            _ => Err(anyhow!("Unable to decode settings from file")),
+    /// Replace the sources, formats and filters with the ones of the session.
+    /// The search, pinned lines and position of the session are not part of the settings
+    pub fn with_session(self, session: Session) -> Self {
+        Self {
+            sources: Some(session.sources),
+            formats: Some(session.formats),
+            filters: Some(
+                session
+                    .filters
+                    .into_iter()
+                    .map(
+                        |SessionFilter {
+                             enabled,
+                             filter,
+                             alert,
+                             sink,
+                         }| SettingsFilter {
+                            filter,
+                            enabled,
+                            alert,
+                            sink,
+                        },
+                    )
+                    .collect(),
+            ),
+            ..self
         }
     }
+
+    /// Add the formats and filters converted from an lnav format file or a glogg/klogg
+    /// configuration. The imported filters are enabled markers. Entries with an alias already
+    /// in the settings are left out
+    pub fn import(&mut self, content: &str) -> Result<ImportReport> {
+        let imported = import_config(content)?;
+        let mut report = ImportReport {
+            skipped: imported.skipped,
+            ..Default::default()
+        };
+
+        let formats = self.formats.get_or_insert_with(Vec::new);
+        for format in imported.formats {
+            match formats.iter().any(|f| f.alias == format.alias) {
+                true => report
+                    .skipped
+                    .push(format!("Format {}: already in the settings", format.alias)),
+                false => {
+                    formats.push(format);
+                    report.formats += 1;
+                }
+            }
+        }
+
+        let filters = self.filters.get_or_insert_with(Vec::new);
+        for filter in imported.filters {
+            match filters.iter().any(|f| f.filter.alias == filter.alias) {
+                true => report
+                    .skipped
+                    .push(format!("Filter {}: already in the settings", filter.alias)),
+                false => {
+                    filters.push(SettingsFilter {
+                        filter,
+                        enabled: true,
+                        ..Default::default()
+                    });
+                    report.filters += 1;
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Replace the color names used by the primary and stripe colors and by the filters with their
+/// RGB tuple in the palette
+fn resolve_palette(settings: &mut Value) -> Result<(), String> {
+    let palette: BTreeMap<String, (u8, u8, u8)> = match settings.get("palette") {
+        Some(palette) if !palette.is_null() => serde_json::from_value(palette.clone())
+            .map_err(|err| format!("invalid palette: {}", err))?,
+        _ => BTreeMap::new(),
+    };
+    let resolve = |color: &mut Value| -> Result<(), String> {
+        if let Some(name) = color.as_str() {
+            let rgb = palette
+                .get(name)
+                .ok_or_else(|| format!("color '{}' is not defined in the palette", name))?;
+            *color = json!(rgb);
+        }
+        Ok(())
+    };
+
+    for key in ["primary_color", "stripe_color"] {
+        if let Some(color) = settings.get_mut(key) {
+            resolve(color)?;
+        }
+    }
+    if let Some(filters) = settings.get_mut("filters").and_then(Value::as_array_mut) {
+        for color in filters.iter_mut().filter_map(|filter| filter.pointer_mut("/filter/color")) {
+            resolve(color)?;
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -31,6 +406,7 @@ mod tests {
     fn test_load_settings() {
         let json = r#"{
             "primary_color": [200, 200, 0],
+            "stripe_color": [30, 30, 30],
             "formats": [
                 {
                     "alias": "Default",
@@ -64,18 +440,199 @@ mod tests {
     fn test_serialize_settings() {
         let settings = Settings {
             formats: None,
-            filters: Some(vec![Filter {
-                alias: "test".into(),
-                action: crate::models::filter::FilterAction::INCLUDE,
-                filter: LogLine {
-                    payload: "test".into(),
-                    color: Some((200, 200, 0)),
-                    ..Default::default()
+            filters: Some(vec![SettingsFilter {
+                filter: Filter {
+                    alias: "test".into(),
+                    action: crate::models::filter::FilterAction::INCLUDE,
+                    filter: LogLine {
+                        payload: "test".into(),
+                        color: Some((200, 200, 0)),
+                        ..Default::default()
+                    },
                 },
+                enabled: true,
+                alert: None,
+                sink: None,
             }]),
+            sources: None,
             primary_color: None,
+            stripe_color: Some((30, 30, 30)),
+            memory_warning_mb: None,
+            memory_critical_mb: None,
+            tick_rate_ms: Some(100),
+            palette: None,
+            burst_detection: None,
+            correlation_key: None,
+            watches: None,
+            transforms: None,
+            redactions: None,
+            retention: None,
+            overload_policy: None,
+            pipeline: None,
+            max_line_length: None,
+            invalid_utf8: None,
+            file_retry: None,
+            dedupe: None,
+            plugins_dir: None,
         };
         let json = serde_json::to_string(&settings);
         assert!(json.is_ok());
     }
+
+    #[test]
+    fn imported_entries_keep_the_existing_aliases() {
+        let mut settings = Settings::from_json(
+            r#"{ "filters": [{ "alias": "warn", "action": "EXCLUDE", "filter": {} }] }"#,
+        )
+        .unwrap();
+        let ini = "[FilterSet]\nfilters\\1\\regexp=warn\nfilters\\2\\regexp=error\n";
+
+        let report = settings.import(ini).unwrap();
+
+        assert_eq!(
+            report,
+            ImportReport {
+                formats: 0,
+                filters: 1,
+                skipped: vec!["Filter warn: already in the settings".to_string()],
+            }
+        );
+        let filters = settings.filters.unwrap();
+        assert_eq!(filters[0].filter.action, crate::models::filter::FilterAction::EXCLUDE);
+        assert_eq!(filters[1].filter.alias, "error");
+        assert!(filters[1].enabled);
+    }
+
+    #[test]
+    fn validation_reports_every_broken_entry() {
+        let json = r#"{
+            "tick_rate_ms": 0,
+            "correlation_key": "req=(\\w+",
+            "watches": ["payload:retry", "@Missing"],
+            "transforms": [{ "field": "Thread", "type": "normalize_paths" }],
+            "redactions": [{ "regex": "token=(\\w+" }],
+            "retention": { "max_lines": 0, "max_age_secs": 3600 },
+            "overload_policy": { "type": "sample", "every": 0 },
+            "pipeline": { "workers": 2, "chunk_size": 0 },
+            "dedupe": { "window_secs": 0 },
+            "file_retry": { "initial_ms": 5000, "max_ms": 1000 },
+            "max_line_length": 0,
+            "formats": [
+                { "alias": "Default", "regex": "(?P<PAYLOAD>.*)" },
+                { "alias": "Broken", "regex": "(?P<PAYLOAD>.*" },
+                { "alias": "Typo", "regex": "(?P<SEVERTY>\\w+)" }
+            ],
+            "filters": [
+                { "alias": "Ok", "action": "INCLUDE", "filter": { "payload": ".*" } },
+                { "alias": "Bad", "action": "EXCLUDE", "filter": { "app": "[a-" } },
+                {
+                    "alias": "Hook", "action": "INCLUDE", "filter": { "payload": "x" },
+                    "sink": { "type": "webhook", "url": "ftp://host" }
+                }
+            ]
+        }"#;
+
+        let errors = Settings::from_json(json).unwrap().validate();
+
+        assert_eq!(errors.len(), 15);
+        assert!(errors[0].starts_with("Format #2 'Broken': invalid regex"));
+        assert!(errors[1].starts_with("Format #3 'Typo': unknown capture group 'SEVERTY'"));
+        assert!(errors[2].starts_with("Filter #2 'Bad': invalid regex in field App"));
+        assert!(errors[3].starts_with("Filter #3 'Hook': Webhook url `ftp://host`"));
+        assert!(errors[4].starts_with("correlation_key: invalid regex"));
+        assert!(errors[5].starts_with("Watch #2 '@Missing'"));
+        assert!(errors[6].starts_with("Transform #1 (Thread): unknown field"));
+        assert!(errors[7].starts_with("Redaction #1: invalid regex"));
+        assert_eq!(errors[8], "retention.max_lines: must be greater than 0");
+        assert_eq!(errors[9], "overload_policy.every: must be greater than 0");
+        assert_eq!(errors[10], "pipeline.chunk_size: must be greater than 0");
+        assert_eq!(errors[11], "dedupe.window_secs: must be greater than 0");
+        assert_eq!(errors[12], "file_retry.initial_ms: must not be greater than max_ms");
+        assert_eq!(errors[13], "max_line_length: must be greater than 0");
+        assert!(errors[14].starts_with("tick_rate_ms"));
+        assert!(Settings::default().validate().is_empty());
+    }
+
+    #[test]
+    fn session_round_trips_through_the_settings() {
+        let json = r#"{
+            "primary_color": [200, 200, 0],
+            "sources": [{ "source_type": 0, "address": "app.log", "format": "Default", "enabled": true }],
+            "formats": [{ "alias": "Default", "regex": "(?P<PAYLOAD>.*)" }],
+            "filters": [
+                { "alias": "On", "action": "EXCLUDE", "filter": { "payload": "noise" }, "enabled": true },
+                { "alias": "Off", "action": "INCLUDE", "filter": { "payload": ".*" } }
+            ]
+        }"#;
+        let mut settings = Settings::from_json(json).unwrap();
+
+        let session = settings.take_session();
+        assert!(settings.formats.is_none() && settings.filters.is_none());
+        assert_eq!(session.sources[0].address, "app.log");
+        assert!(session.filters[0].enabled);
+        assert!(!session.filters[1].enabled);
+
+        let settings = settings.with_session(session);
+        let saved = Settings::from_json(&settings.to_json().unwrap()).unwrap();
+        assert_eq!(saved.primary_color, Some((200, 200, 0)));
+        assert_eq!(saved.sources.unwrap().len(), 1);
+        assert!(saved.filters.unwrap()[0].enabled);
+    }
+
+    #[test]
+    fn burst_detection_options_take_their_default() {
+        let settings = Settings::from_json(r#"{ "burst_detection": { "factor": 1 } }"#).unwrap();
+
+        let burst_detection = settings.burst_detection.unwrap();
+        assert_eq!(burst_detection.interval_secs, BurstConfig::default().interval_secs);
+        assert_eq!(
+            settings.validate(),
+            vec!["burst_detection.factor: must be greater than 1".to_string()]
+        );
+    }
+
+    #[test]
+    fn sources_are_declared_by_type_name() {
+        let json = r#"{
+            "formats": [{ "alias": "Default", "regex": "(?P<PAYLOAD>.*)" }],
+            "sources": [
+                { "type": "file", "path": "app.log", "format": "Default" },
+                { "type": "ws", "address": "ws://127.0.0.1:9000", "enabled": false },
+                { "source_type": 7, "address": "", "format": "Missing" }
+            ]
+        }"#;
+        let settings = Settings::from_json(json).unwrap();
+
+        let errors = settings.validate();
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("Source #3 '': unknown source type 7"));
+        assert!(errors[1].ends_with("address is empty"));
+        assert!(errors[2].ends_with("format 'Missing' is not declared"));
+
+        let sources = settings.sources.unwrap();
+        assert_eq!((sources[0].source_type, sources[0].enabled), (0, true));
+        assert_eq!((sources[1].source_type, sources[1].enabled), (1, false));
+        assert!(Settings::from_json(r#"{"sources": [{"type": "ftp", "path": "x"}]}"#).is_err());
+    }
+
+    #[test]
+    fn palette_colors_are_referenced_by_name() {
+        let json = r#"{
+            "palette": { "critical": [255, 0, 0], "brand": [0, 225, 255] },
+            "primary_color": "brand",
+            "filters": [
+                { "alias": "Errors", "action": "MARKER", "filter": { "severity": "ERROR", "color": "critical" } },
+                { "alias": "Rgb", "action": "MARKER", "filter": { "payload": "x", "color": [1, 2, 3] } }
+            ]
+        }"#;
+
+        let settings = Settings::from_json(json).unwrap();
+
+        assert_eq!(settings.primary_color, Some((0, 225, 255)));
+        let filters = settings.filters.unwrap();
+        assert_eq!(filters[0].filter.filter.color, Some((255, 0, 0)));
+        assert_eq!(filters[1].filter.filter.color, Some((1, 2, 3)));
+        let err = Settings::from_json(r#"{"stripe_color": "missing"}"#).unwrap_err();
+        assert!(err.to_string().contains("color 'missing' is not defined in the palette"));
+    }
 }
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, Debug)]
+/// Severity levels that can be quickly hidden or shown
+pub enum Severity {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Severity {
+    /// Returns the available severities sorted by level
+    pub fn all() -> [Severity; 4] {
+        [
+            Severity::Debug,
+            Severity::Info,
+            Severity::Warn,
+            Severity::Error,
+        ]
+    }
+
+    /// Classify the content of a `LogLine` severity field
+    pub fn parse(severity: &str) -> Option<Self> {
+        match severity.trim().to_uppercase().as_str() {
+            "DEBUG" | "DBG" | "D" | "TRACE" | "VERBOSE" | "V" => Some(Severity::Debug),
+            "INFO" | "INF" | "I" | "NOTICE" => Some(Severity::Info),
+            "WARN" | "WARNING" | "WRN" | "W" => Some(Severity::Warn),
+            "ERROR" | "ERR" | "E" | "FATAL" | "CRITICAL" | "CRIT" | "F" => Some(Severity::Error),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Debug => "DEBUG",
+            Severity::Info => "INFO",
+            Severity::Warn => "WARN",
+            Severity::Error => "ERROR",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_common_spellings() {
+        assert_eq!(Severity::parse("info"), Some(Severity::Info));
+        assert_eq!(Severity::parse(" WARNING "), Some(Severity::Warn));
+        assert_eq!(Severity::parse("E"), Some(Severity::Error));
+        assert_eq!(Severity::parse("whatever"), None);
+    }
+}
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+/// Destination of the live lines matched by a filter, e.g.
+/// `{"type": "webhook", "url": "http://localhost:8080/logs"}`
+pub enum Sink {
+    /// Send each line as a syslog message over UDP to `address`, e.g. `127.0.0.1:514`
+    Syslog { address: String },
+    /// POST each line as JSON to an `http://` url
+    Webhook { url: String },
+    /// Append each line to a file
+    File { path: String },
+}
+
+impl Sink {
+    /// Check the sink can be used, returning the reason otherwise
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Sink::Syslog { address } if address.trim().is_empty() => {
+                Err("The syslog address is empty".to_string())
+            }
+            Sink::Webhook { url } if !url.starts_with("http://") => {
+                Err(format!("Webhook url `{}` is not an http:// url", url))
+            }
+            Sink::File { path } if path.trim().is_empty() => {
+                Err("The file path is empty".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sinks_are_declared_by_type() {
+        let sink: Sink =
+            serde_json::from_str(r#"{ "type": "syslog", "address": "127.0.0.1:514" }"#).unwrap();
+        assert_eq!(
+            sink,
+            Sink::Syslog {
+                address: "127.0.0.1:514".to_string()
+            }
+        );
+
+        let sink: Sink =
+            serde_json::from_str(r#"{ "type": "webhook", "url": "https://hooks/logs" }"#).unwrap();
+        assert!(sink.validate().is_err());
+        assert!(Sink::File {
+            path: "errors.log".to_string()
+        }
+        .validate()
+        .is_ok());
+    }
+}
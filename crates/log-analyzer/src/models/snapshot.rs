@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+use super::session::{Session, SessionSource};
+
+/// Extension of the files where the processed lines of an analysis are saved
+pub const SNAPSHOT_EXTENSION: &str = "lapsnapshot";
+/// Version of the snapshot files written by this version of the application
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+/// Source of a snapshot with the number of its raw lines saved
+pub struct SnapshotSource {
+    pub source: SessionSource,
+    pub lines: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+/// Start of a snapshot file, telling the lines written after it: the raw lines of each source,
+/// then the filtered lines and the search results
+pub struct SnapshotHeader {
+    /// Version of the application format the snapshot was written with
+    #[serde(default)]
+    pub version: u32,
+    /// Formats, filters, search and view of the analysis, restored without filtering again
+    pub session: Session,
+    /// Every source in the order its raw lines are written, piped input included
+    pub sources: Vec<SnapshotSource>,
+    pub filtered_lines: usize,
+    pub searched_lines: usize,
+    /// Whether the search results hold every line matching the search. A search still running
+    /// when saved is run again once restored
+    pub search_complete: bool,
+    /// Lines received per second as (second, raw lines, filtered lines)
+    pub rate: Vec<(i64, usize, usize)>,
+}
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug, Eq, PartialEq)]
+#[serde(default)]
+/// Lines of a source at every stage of the analysis
+pub struct SourceStats {
+    /// Log id of the source
+    pub source: String,
+    /// Lines read from the source
+    pub raw: usize,
+    /// Lines kept in the filtered log
+    pub filtered: usize,
+    /// Lines found by the search
+    pub search: usize,
+    /// Lines discarded without being processed, by the overload policy or the memory limit
+    pub dropped: usize,
+    /// Lines that waited for room in the full pipeline queue before being processed
+    pub deferred: usize,
+    /// The last lines of the source waited or were dropped, the pipeline not keeping up
+    pub lagging: bool,
+    /// Reason why the source can't be read at the moment. Followed files are tried again meanwhile
+    pub error: Option<String>,
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+/// Aggregated view of the processed log
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Statistics {
+    /// Lines per time bucket as (bucket start in seconds, raw lines, filtered lines)
+    pub rate: Vec<(i64, usize, usize)>,
+    /// Seconds covered by each bucket of the rate
+    pub bucket_seconds: i64,
+    /// Filtered lines per severity, most frequent first
+    pub severities: Vec<(String, usize)>,
+    /// Apps with the most filtered lines, most frequent first
+    pub top_apps: Vec<(String, usize)>,
+}
+
+impl Statistics {
+    /// Group a per second rate, sorted by second, into at most `buckets` buckets of equal duration
+    ///
+    /// Returns the buckets and the seconds covered by each of them
+    pub fn bucket_rate(
+        per_second: &[(i64, usize, usize)],
+        buckets: usize,
+    ) -> (Vec<(i64, usize, usize)>, i64) {
+        let (first, last) = match (per_second.first(), per_second.last()) {
+            (Some(first), Some(last)) if buckets > 0 => (first.0, last.0),
+            _ => return (Vec::new(), 0),
+        };
+
+        let span = last - first + 1;
+        let bucket_seconds = (span + buckets as i64 - 1) / buckets as i64;
+        let mut rate: Vec<(i64, usize, usize)> = (0..(span + bucket_seconds - 1) / bucket_seconds)
+            .map(|i| (first + i * bucket_seconds, 0, 0))
+            .collect();
+        for (second, raw, filtered) in per_second {
+            let bucket = &mut rate[((second - first) / bucket_seconds) as usize];
+            bucket.1 += raw;
+            bucket.2 += filtered;
+        }
+
+        (rate, bucket_seconds)
+    }
+
+    /// Sort counts by frequency (then by name) keeping the first `top` ones
+    pub fn top(counts: Vec<(String, usize)>, top: usize) -> Vec<(String, usize)> {
+        let mut counts = counts;
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts.truncate(top);
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_is_grouped_in_buckets() {
+        let per_second = vec![(100, 5, 1), (101, 5, 2), (105, 1, 1), (109, 2, 0)];
+
+        let (rate, bucket_seconds) = Statistics::bucket_rate(&per_second, 5);
+
+        assert_eq!(bucket_seconds, 2);
+        assert_eq!(rate, vec![(100, 10, 3), (102, 0, 0), (104, 1, 1), (106, 0, 0), (108, 2, 0)]);
+
+        let (rate, bucket_seconds) = Statistics::bucket_rate(&per_second, 100);
+        assert_eq!(bucket_seconds, 1);
+        assert_eq!(rate.len(), 10);
+    }
+
+    #[test]
+    fn top_keeps_the_most_frequent() {
+        let counts = vec![("a".into(), 1), ("b".into(), 5), ("c".into(), 5), ("d".into(), 3)];
+
+        let top = Statistics::top(counts, 3);
+
+        assert_eq!(top, vec![("b".into(), 5), ("c".into(), 5), ("d".into(), 3)]);
+    }
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+/// Most frequent values of a column, e.g. the top 10 functions among the searched ERROR lines
+pub struct TopValuesQuery {
+    /// Column name as used by `LogLine::get`
+    pub column: String,
+    /// Values to keep, most frequent first
+    pub top: usize,
+    /// Only count the lines with a time (in seconds) in this inclusive range. Lines without a
+    /// recognizable time are skipped
+    #[serde(default)]
+    pub time_range: Option<(f64, f64)>,
+    /// Count the search results instead of the filtered log
+    #[serde(default)]
+    pub search: bool,
+}
+
+impl TopValuesQuery {
+    pub fn new(column: &str, top: usize) -> Self {
+        Self {
+            column: column.to_string(),
+            top,
+            ..Default::default()
+        }
+    }
+}
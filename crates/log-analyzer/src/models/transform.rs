@@ -0,0 +1,106 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Fields of the formatted lines that can be transformed
+pub const TRANSFORM_FIELDS: [&str; 6] = [
+    "Date",
+    "Timestamp",
+    "App",
+    "Severity",
+    "Function",
+    "Payload",
+];
+
+/// Field of `TRANSFORM_FIELDS` named `name`, ignoring the case
+pub fn transform_field(name: &str) -> Option<&'static str> {
+    TRANSFORM_FIELDS
+        .iter()
+        .find(|field| field.eq_ignore_ascii_case(name))
+        .copied()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+/// Change made to a field of the formatted lines
+pub enum TransformRule {
+    /// Replace the matches of `regex` with `replacement`, which can refer to the capture groups
+    /// with `$1` or `$name`
+    Rewrite {
+        regex: String,
+        #[serde(default)]
+        replacement: String,
+    },
+    /// Set the field to the first capture group of `regex` (or its whole match) in the `from`
+    /// field. The field is left as is if nothing matches
+    Extract { from: String, regex: String },
+    /// Remove `prefix` from the start of the field
+    StripPrefix { prefix: String },
+    /// Write the paths of the field with `/` separators, without `.` components nor the
+    /// directories followed by `..`, e.g. `C:\app\..\logs\.\a.log` becomes `C:/logs/a.log`
+    NormalizePaths,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+/// Rule applied to a field of the lines once formatted and before they are filtered, e.g.
+/// `{"field": "payload", "type": "strip_prefix", "prefix": "[main] "}`
+pub struct Transform {
+    /// Column of the field, e.g. `Payload`
+    pub field: String,
+    #[serde(flatten)]
+    pub rule: TransformRule,
+}
+
+impl Transform {
+    /// Check the fields and regexes of the transform, returning the problem otherwise
+    pub fn validate(&self) -> Result<(), String> {
+        let fields = match &self.rule {
+            TransformRule::Extract { from, .. } => vec![&self.field, from],
+            _ => vec![&self.field],
+        };
+        if let Some(field) = fields
+            .into_iter()
+            .find(|field| transform_field(field).is_none())
+        {
+            return Err(format!(
+                "unknown field '{}', expected one of {}",
+                field,
+                TRANSFORM_FIELDS.join(", ")
+            ));
+        }
+        match &self.rule {
+            TransformRule::Rewrite { regex, .. } | TransformRule::Extract { regex, .. } => {
+                Regex::new(regex)
+                    .map(|_| ())
+                    .map_err(|err| format!("invalid regex: {}", err))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transforms_are_declared_by_type() {
+        let json =
+            r#"{ "field": "app", "type": "extract", "from": "Payload", "regex": "\\[(\\w+)\\]" }"#;
+        let transform: Transform = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            transform.rule,
+            TransformRule::Extract {
+                from: "Payload".to_string(),
+                regex: r"\[(\w+)\]".to_string()
+            }
+        );
+        assert!(transform.validate().is_ok());
+
+        let json = r#"{ "field": "Thread", "type": "normalize_paths" }"#;
+        let transform: Transform = serde_json::from_str(json).unwrap();
+        assert!(transform
+            .validate()
+            .unwrap_err()
+            .starts_with("unknown field 'Thread'"));
+    }
+}
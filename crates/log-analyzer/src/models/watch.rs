@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+/// Seconds of matches kept by each watch
+pub const WATCH_HISTORY_SECS: usize = 60;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+/// Live lines matched by a watch expression, e.g. `payload:retry` to follow the retries per second
+pub struct Watch {
+    /// Query expression, like the ones of `lap query`
+    pub expression: String,
+    /// Lines matched since the watch was added
+    pub total: usize,
+    /// Lines matched in each of the last seconds, oldest first. The last one is the current
+    /// second, still being counted
+    pub per_second: Vec<usize>,
+}
+
+impl Watch {
+    /// Average matches per second over the last complete seconds, up to `seconds` of them
+    pub fn rate(&self, seconds: usize) -> f64 {
+        let complete = &self.per_second[..self.per_second.len().saturating_sub(1)];
+        let last = &complete[complete.len().saturating_sub(seconds)..];
+        match last.len() {
+            0 => 0.0,
+            n => last.iter().sum::<usize>() as f64 / n as f64,
+        }
+    }
+}
@@ -0,0 +1,489 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::error::{self, LogAnalyzerError};
+use crate::models::{
+    alert::{Alert, AlertAction}, column_filter::ColumnFilter, diff::DiffQuery, export::ExportFormat,
+    filter::Filter, redaction::Redaction, session::Session, severity::Severity, sink::Sink,
+    top_values::TopValuesQuery, transform::Transform,
+};
+
+use super::log_service::{Event, LogAnalyzer};
+
+/// Number of events kept for the clients polling them
+const MAX_EVENTS: usize = 10_000;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+/// Call to a method of `LogAnalyzer` that can be sent to a remote analyzer.
+///
+/// Serialized as `{"method": "get_log_lines", "params": {"from": 0, "to": 100}}`.
+/// The server exposes each method as `POST /api/<method>` with the params as body
+pub enum ApiRequest {
+    AddLog {
+        source_type: usize,
+        address: String,
+        format: Option<String>,
+    },
+    ReplayLog {
+        path: String,
+        format: Option<String>,
+        speed: f64,
+    },
+    AddFormat {
+        alias: String,
+        regex: String,
+    },
+    AddSearch {
+        regex: String,
+    },
+    AddFilter {
+        filter: Filter,
+    },
+    RemoveFormat {
+        alias: String,
+    },
+    RenameFormat {
+        alias: String,
+        new_alias: String,
+    },
+    RemoveFilter {
+        alias: String,
+    },
+    RenameFilter {
+        alias: String,
+        new_alias: String,
+    },
+    GetLogLines {
+        from: usize,
+        to: usize,
+    },
+    GetSearchLines {
+        from: usize,
+        to: usize,
+    },
+    GetLogLinesContaining {
+        index: usize,
+        elements: usize,
+    },
+    GetSearchLinesContaining {
+        index: usize,
+        elements: usize,
+    },
+    GetStatistics {
+        buckets: usize,
+        top: usize,
+    },
+    GetColumnValues {
+        column: String,
+        top: usize,
+    },
+    GetRateHistogram {
+        bucket: Duration,
+    },
+    GetTopValues {
+        query: TopValuesQuery,
+    },
+    GetGaps {
+        min_gap: Duration,
+    },
+    GetIndexAtTime {
+        time: f64,
+    },
+    SetCorrelationKey {
+        #[serde(default)]
+        key: Option<String>,
+    },
+    GetCorrelationId {
+        index: usize,
+    },
+    GetCorrelatedLines {
+        id: String,
+    },
+    GetDiff {
+        query: DiffQuery,
+    },
+    SetAlert {
+        filter: String,
+        #[serde(default)]
+        alert: Option<Alert>,
+    },
+    GetAlerts,
+    SetSink {
+        filter: String,
+        #[serde(default)]
+        sink: Option<Sink>,
+    },
+    GetSinks,
+    AddWatch {
+        expression: String,
+    },
+    RemoveWatch {
+        expression: String,
+    },
+    GetWatches,
+    SetTransforms {
+        transforms: Vec<Transform>,
+    },
+    GetTransforms,
+    SetRedactions {
+        redactions: Vec<Redaction>,
+    },
+    GetRedactions,
+    SetShowOriginals {
+        show: bool,
+    },
+    IsShowingOriginals,
+    /// Refused by `dispatch`, the server doesn't write files for its clients
+    ExportFiltered {
+        path: String,
+        #[serde(default)]
+        format: ExportFormat,
+    },
+    ExportSearch {
+        path: String,
+        #[serde(default)]
+        format: ExportFormat,
+    },
+    /// Refused by `dispatch` as the exports, along with `LoadSnapshot`
+    SaveSnapshot {
+        path: String,
+        #[serde(default)]
+        session: Session,
+    },
+    LoadSnapshot {
+        path: String,
+    },
+    ResolveRawIndex {
+        #[serde(default)]
+        source: Option<String>,
+        raw_index: usize,
+    },
+    SetViewport {
+        source: String,
+        raw_index: usize,
+    },
+    GetLogs,
+    GetSourcePlugins,
+    GetFormats,
+    GetFilters,
+    GetTotalRawLines,
+    GetTotalFilteredLines,
+    GetTotalSearchedLines,
+    GetFilteredLinesPerSource,
+    GetSourceLogLines {
+        source: String,
+        limit: usize,
+    },
+    GetRawLine {
+        source: String,
+        raw_index: usize,
+    },
+    GetMemoryUsage,
+    GetMetrics,
+    GetSourceStats,
+    ToggleSource {
+        id: String,
+    },
+    ToggleFilter {
+        id: String,
+    },
+    ToggleSeverity {
+        severity: Severity,
+    },
+    GetHiddenSeverities,
+    AddColumnFilter {
+        column_filter: ColumnFilter,
+    },
+    RemoveColumnFilter {
+        column: String,
+    },
+    GetColumnFilters,
+    GetSession,
+    RestoreSession {
+        session: Session,
+    },
+    ClearAll,
+}
+
+impl ApiRequest {
+    /// Build the request of the method with the given name, e.g. `get_log_lines`.
+    /// `params` is `None` for the methods without arguments
+    pub fn from_method(method: &str, params: Option<Value>) -> error::Result<Self> {
+        let request = match params {
+            Some(params) => json!({ "method": method, "params": params }),
+            None => json!({ "method": method }),
+        };
+        serde_json::from_value(request).map_err(|err| LogAnalyzerError::InvalidRequest {
+            method: method.to_string(),
+            reason: err.to_string(),
+        })
+    }
+
+    /// Name and params of the request as used by `from_method`
+    pub fn to_method(&self) -> error::Result<(String, Option<Value>)> {
+        let mut request = serde_json::to_value(self)
+            .map_err(|err| LogAnalyzerError::InvalidJson(err.to_string()))?;
+        let method = request["method"].as_str().unwrap_or_default().to_string();
+        let params = request.get_mut("params").map(Value::take);
+        Ok((method, params))
+    }
+}
+
+/// Refuse the alerts running commands: the server is not authenticated, so anyone reaching it
+/// could run commands on it and on the terminal UIs connected to it
+fn check_alert(alert: Option<&Alert>) -> error::Result<()> {
+    match alert.map(|alert| &alert.action) {
+        Some(AlertAction::Command { .. }) => {
+            Err(LogAnalyzerError::LocalOnly("A command alert".to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Refuse the sinks writing files, which would let anyone reaching the server write anywhere
+/// on it
+fn check_sink(sink: Option<&Sink>) -> error::Result<()> {
+    match sink {
+        Some(Sink::File { .. }) => Err(LogAnalyzerError::LocalOnly("A file sink".to_string())),
+        _ => Ok(()),
+    }
+}
+
+/// Run the request on the analyzer, returning the result of the method as JSON
+pub fn dispatch(analyzer: &dyn LogAnalyzer, request: ApiRequest) -> error::Result<Value> {
+    let value = match request {
+        ApiRequest::AddLog {
+            source_type,
+            address,
+            format,
+        } => json!(analyzer.add_log(source_type, &address, format.as_ref())?),
+        ApiRequest::ReplayLog {
+            path,
+            format,
+            speed,
+        } => json!(analyzer.replay_log(&path, format.as_ref(), speed)?),
+        ApiRequest::AddFormat { alias, regex } => json!(analyzer.add_format(&alias, &regex)?),
+        ApiRequest::AddSearch { regex } => json!(analyzer.add_search(&regex)),
+        ApiRequest::AddFilter { filter } => json!(analyzer.add_filter(filter)),
+        ApiRequest::RemoveFormat { alias } => json!(analyzer.remove_format(&alias)?),
+        ApiRequest::RenameFormat { alias, new_alias } => {
+            json!(analyzer.rename_format(&alias, &new_alias)?)
+        }
+        ApiRequest::RemoveFilter { alias } => json!(analyzer.remove_filter(&alias)?),
+        ApiRequest::RenameFilter { alias, new_alias } => {
+            json!(analyzer.rename_filter(&alias, &new_alias)?)
+        }
+        ApiRequest::GetLogLines { from, to } => json!(analyzer.get_log_lines(from, to)),
+        ApiRequest::GetSearchLines { from, to } => json!(analyzer.get_search_lines(from, to)),
+        ApiRequest::GetLogLinesContaining { index, elements } => {
+            json!(analyzer.get_log_lines_containing(index, elements))
+        }
+        ApiRequest::GetSearchLinesContaining { index, elements } => {
+            json!(analyzer.get_search_lines_containing(index, elements))
+        }
+        ApiRequest::GetStatistics { buckets, top } => json!(analyzer.get_statistics(buckets, top)),
+        ApiRequest::GetColumnValues { column, top } => {
+            json!(analyzer.get_column_values(&column, top)?)
+        }
+        ApiRequest::GetRateHistogram { bucket } => json!(analyzer.get_rate_histogram(bucket)),
+        ApiRequest::GetTopValues { query } => json!(analyzer.get_top_values(&query)?),
+        ApiRequest::GetGaps { min_gap } => json!(analyzer.get_gaps(min_gap)),
+        ApiRequest::GetIndexAtTime { time } => json!(analyzer.get_index_at_time(time)),
+        ApiRequest::SetCorrelationKey { key } => {
+            json!(analyzer.set_correlation_key(key.as_deref())?)
+        }
+        ApiRequest::GetCorrelationId { index } => json!(analyzer.get_correlation_id(index)),
+        ApiRequest::GetCorrelatedLines { id } => json!(analyzer.get_correlated_lines(&id)),
+        ApiRequest::GetDiff { query } => json!(analyzer.get_diff(&query)),
+        ApiRequest::SetAlert { filter, alert } => {
+            check_alert(alert.as_ref())?;
+            json!(analyzer.set_alert(&filter, alert)?)
+        }
+        ApiRequest::GetAlerts => json!(analyzer.get_alerts()),
+        ApiRequest::SetSink { filter, sink } => {
+            check_sink(sink.as_ref())?;
+            json!(analyzer.set_sink(&filter, sink)?)
+        }
+        ApiRequest::GetSinks => json!(analyzer.get_sinks()),
+        ApiRequest::AddWatch { expression } => json!(analyzer.add_watch(&expression)?),
+        ApiRequest::RemoveWatch { expression } => json!(analyzer.remove_watch(&expression)?),
+        ApiRequest::GetWatches => json!(analyzer.get_watches()),
+        ApiRequest::SetTransforms { transforms } => json!(analyzer.set_transforms(transforms)?),
+        ApiRequest::GetTransforms => json!(analyzer.get_transforms()),
+        ApiRequest::SetRedactions { redactions } => json!(analyzer.set_redactions(redactions)?),
+        ApiRequest::GetRedactions => json!(analyzer.get_redactions()),
+        ApiRequest::SetShowOriginals { show } => json!(analyzer.set_show_originals(show)),
+        ApiRequest::IsShowingOriginals => json!(analyzer.is_showing_originals()),
+        // Clients export to their own files with the lines they fetch
+        ApiRequest::ExportFiltered { .. } | ApiRequest::ExportSearch { .. } => {
+            return Err(LogAnalyzerError::LocalOnly("Exporting to a file".to_string()))
+        }
+        ApiRequest::SaveSnapshot { .. } | ApiRequest::LoadSnapshot { .. } => {
+            return Err(LogAnalyzerError::LocalOnly("A snapshot".to_string()))
+        }
+        ApiRequest::ResolveRawIndex { source, raw_index } => {
+            json!(analyzer.resolve_raw_index(source.as_deref(), raw_index))
+        }
+        ApiRequest::SetViewport { source, raw_index } => {
+            json!(analyzer.set_viewport(&source, raw_index))
+        }
+        ApiRequest::GetLogs => json!(analyzer.get_logs()),
+        ApiRequest::GetSourcePlugins => json!(analyzer.get_source_plugins()),
+        ApiRequest::GetFormats => json!(analyzer.get_formats()),
+        ApiRequest::GetFilters => json!(analyzer.get_filters()),
+        ApiRequest::GetTotalRawLines => json!(analyzer.get_total_raw_lines()),
+        ApiRequest::GetTotalFilteredLines => json!(analyzer.get_total_filtered_lines()),
+        ApiRequest::GetTotalSearchedLines => json!(analyzer.get_total_searched_lines()),
+        ApiRequest::GetFilteredLinesPerSource => json!(analyzer.get_filtered_lines_per_source()),
+        ApiRequest::GetSourceLogLines { source, limit } => {
+            json!(analyzer.get_source_log_lines(&source, limit))
+        }
+        ApiRequest::GetRawLine { source, raw_index } => {
+            json!(analyzer.get_raw_line(&source, raw_index))
+        }
+        ApiRequest::GetMemoryUsage => json!(analyzer.get_memory_usage()),
+        ApiRequest::GetMetrics => json!(analyzer.get_metrics()),
+        ApiRequest::GetSourceStats => json!(analyzer.get_source_stats()),
+        ApiRequest::ToggleSource { id } => json!(analyzer.toggle_source(&id)),
+        ApiRequest::ToggleFilter { id } => json!(analyzer.toggle_filter(&id)),
+        ApiRequest::ToggleSeverity { severity } => json!(analyzer.toggle_severity(severity)),
+        ApiRequest::GetHiddenSeverities => json!(analyzer.get_hidden_severities()),
+        ApiRequest::AddColumnFilter { column_filter } => {
+            json!(analyzer.add_column_filter(column_filter))
+        }
+        ApiRequest::RemoveColumnFilter { column } => json!(analyzer.remove_column_filter(&column)),
+        ApiRequest::GetColumnFilters => json!(analyzer.get_column_filters()),
+        ApiRequest::GetSession => json!(analyzer.get_session()),
+        ApiRequest::RestoreSession { session } => {
+            for filter in &session.filters {
+                check_alert(filter.alert.as_ref())?;
+                check_sink(filter.sink.as_ref())?;
+            }
+            json!(analyzer.restore_session(&session)?)
+        }
+        ApiRequest::ClearAll => json!(analyzer.clear_all()),
+    };
+
+    Ok(value)
+}
+
+#[derive(Default)]
+/// Latest events of an analyzer numbered in order, so remote clients can poll the ones they missed
+pub struct EventLog {
+    events: Mutex<(u64, VecDeque<(u64, Event)>)>,
+}
+
+impl EventLog {
+    /// Number the event and keep it, forgetting the oldest one when full
+    pub fn record(&self, event: Event) {
+        let mut events = self.events.lock();
+        events.0 += 1;
+        let id = events.0;
+        events.1.push_back((id, event));
+        if events.1.len() > MAX_EVENTS {
+            events.1.pop_front();
+        }
+    }
+
+    /// Events numbered after `after`, oldest first
+    pub fn since(&self, after: u64) -> Vec<(u64, Event)> {
+        let events = self.events.lock();
+        events
+            .1
+            .iter()
+            .filter(|(id, _)| *id > after)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_built_from_the_method_name() {
+        let request = ApiRequest::from_method("get_log_lines", Some(json!({"from": 1, "to": 5})))
+            .unwrap();
+        assert!(matches!(request, ApiRequest::GetLogLines { from: 1, to: 5 }));
+        assert!(matches!(
+            ApiRequest::from_method("get_logs", None).unwrap(),
+            ApiRequest::GetLogs
+        ));
+        assert!(ApiRequest::from_method("drop_database", None).is_err());
+
+        let (method, params) = ApiRequest::ToggleFilter { id: "x".into() }.to_method().unwrap();
+        assert_eq!(method, "toggle_filter");
+        assert_eq!(params, Some(json!({"id": "x"})));
+        assert_eq!(ApiRequest::GetLogs.to_method().unwrap(), ("get_logs".into(), None));
+    }
+
+    #[test]
+    fn command_alerts_are_refused() {
+        let analyzer = crate::LogAnalyzerBuilder::new().workers(1).build();
+        analyzer.add_filter(Filter {
+            alias: "Errors".into(),
+            ..Default::default()
+        });
+        let alert = |action| {
+            let request = json!({"filter": "Errors", "alert": action});
+            ApiRequest::from_method("set_alert", Some(request)).unwrap()
+        };
+
+        let command = alert(json!({"action": "command", "command": "touch /tmp/owned"}));
+        assert!(matches!(
+            dispatch(analyzer.as_ref(), command),
+            Err(LogAnalyzerError::LocalOnly(_))
+        ));
+        assert!(analyzer.get_alerts().is_empty());
+        dispatch(analyzer.as_ref(), alert(json!({"action": "bell"}))).unwrap();
+        assert_eq!(analyzer.get_alerts().len(), 1);
+    }
+
+    #[test]
+    fn files_are_not_written_for_the_clients() {
+        let analyzer = crate::LogAnalyzerBuilder::new().workers(1).build();
+        let dir = test_utils::tempdir().unwrap();
+        let path = dir.path().join("api.log");
+        let path = json!({ "path": path });
+
+        for method in ["export_filtered", "export_search", "save_snapshot", "load_snapshot"] {
+            let request = ApiRequest::from_method(method, Some(path.clone())).unwrap();
+            assert!(matches!(
+                dispatch(analyzer.as_ref(), request),
+                Err(LogAnalyzerError::LocalOnly(_))
+            ));
+        }
+
+        analyzer.add_filter(Filter {
+            alias: "Errors".into(),
+            ..Default::default()
+        });
+        let sink = json!({"filter": "Errors", "sink": {"type": "file", "path": path["path"]}});
+        let request = ApiRequest::from_method("set_sink", Some(sink)).unwrap();
+        assert!(matches!(
+            dispatch(analyzer.as_ref(), request),
+            Err(LogAnalyzerError::LocalOnly(_))
+        ));
+        assert!(analyzer.get_sinks().is_empty());
+        assert!(!std::path::Path::new(path["path"].as_str().unwrap()).exists());
+    }
+
+    #[test]
+    fn events_are_polled_in_order() {
+        let log = EventLog::default();
+        log.record(Event::Filtering);
+        log.record(Event::NewLines(0, 10));
+        log.record(Event::FilterFinished);
+
+        let events = log.since(1);
+
+        assert_eq!(events, vec![(2, Event::NewLines(0, 10)), (3, Event::FilterFinished)]);
+        assert!(log.since(3).is_empty());
+    }
+}
@@ -0,0 +1,293 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use flume::{Receiver, Sender, TrySendError};
+use log_source::source::line_batch::LineBatch;
+use parking_lot::RwLock;
+use rustc_hash::FxHashMap as HashMap;
+use tokio::sync::broadcast;
+use tracing::debug;
+
+use crate::models::notification::{Notification, NotificationLevel};
+use crate::models::overload_policy::OverloadPolicy;
+
+use super::log_service::Event;
+
+/// Batch of lines read by a source: (source, lines)
+pub type Batch = (String, LineBatch);
+
+/// Lines of a source the pipeline didn't keep up with
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SourceLag {
+    /// Lines discarded without being processed
+    pub dropped: usize,
+    /// Lines that waited for room in the full queue
+    pub deferred: usize,
+    /// The last lines of the source waited or were dropped
+    pub lagging: bool,
+}
+
+/// Bounded queue of the batches read by the sources, waiting to enter the pipeline.
+///
+/// Once full, the `OverloadPolicy` decides what is done with the new batches. The lines dropped
+/// are sent as `Event::LinesDropped`, the first ones of an overload being notified. The lines
+/// dropped or deferred are counted per source
+pub struct BatchQueue {
+    sender: Sender<Batch>,
+    /// Kept to discard the oldest batches
+    receiver: Receiver<Batch>,
+    policy: RwLock<OverloadPolicy>,
+    /// Lines were dropped since the queue last had room
+    overloaded: AtomicBool,
+    /// K: source -> V: lines of the source the pipeline didn't keep up with
+    lag: RwLock<HashMap<String, SourceLag>>,
+    events: broadcast::Sender<Event>,
+}
+
+impl BatchQueue {
+    pub fn new(capacity: usize, events: broadcast::Sender<Event>) -> Self {
+        let (sender, receiver) = flume::bounded(capacity.max(1));
+        Self {
+            sender,
+            receiver,
+            policy: RwLock::new(OverloadPolicy::default()),
+            overloaded: AtomicBool::new(false),
+            lag: RwLock::new(HashMap::default()),
+            events,
+        }
+    }
+
+    pub fn set_policy(&self, policy: OverloadPolicy) {
+        *self.policy.write() = policy;
+    }
+
+    pub fn policy(&self) -> OverloadPolicy {
+        *self.policy.read()
+    }
+
+    /// Receiving side of the queue, for the next stage of the pipeline
+    pub fn receiver(&self) -> Receiver<Batch> {
+        self.receiver.clone()
+    }
+
+    /// Batches queued
+    pub fn len(&self) -> usize {
+        self.sender.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sender.is_empty()
+    }
+
+    /// Lines of `source` the pipeline didn't keep up with
+    pub fn lag(&self, source: &str) -> SourceLag {
+        self.lag.read().get(source).copied().unwrap_or_default()
+    }
+
+    /// Count the `lines` of `source` dropped out of the queue, e.g. over the memory limit
+    pub fn add_dropped(&self, source: &str, lines: usize) {
+        let mut lag = self.lag.write();
+        let lag = lag.entry(source.to_string()).or_default();
+        lag.dropped += lines;
+        lag.lagging = true;
+    }
+
+    /// Mark every source as kept up with once the queue is drained, as a source that stopped
+    /// sending, e.g. stdin closed, never pushes the batch clearing its lag
+    pub fn caught_up(&self) {
+        if self.sender.is_empty() {
+            self.lag
+                .write()
+                .values_mut()
+                .for_each(|lag| lag.lagging = false);
+        }
+    }
+
+    /// Forget the lines dropped and deferred of every source
+    pub fn clear_lag(&self) {
+        self.lag.write().clear();
+    }
+
+    /// Queue the `lines` read by `source`, applying the overload policy if the queue is full.
+    /// Waits for room with `OverloadPolicy::Block`, without blocking the thread of the source
+    pub async fn push(&self, source: String, lines: LineBatch) {
+        let full = self.sender.is_full();
+        // Followed files send empty batches while waiting for new lines, not worth any room
+        if lines.is_empty() {
+            if !full {
+                self.set_lagging(&source, false);
+            }
+            self.sender.try_send((source, lines)).unwrap_or_default();
+            return;
+        }
+
+        let mut dropped: Vec<(String, usize)> = Vec::new();
+        match self.policy() {
+            OverloadPolicy::Block => {
+                if full {
+                    self.lag.write().entry(source.clone()).or_default().deferred += lines.len();
+                }
+                self.set_lagging(&source, full);
+                self.sender.send_async((source, lines)).await.unwrap_or_default()
+            }
+            OverloadPolicy::DropOldest => {
+                self.set_lagging(&source, false);
+                let mut batch = (source, lines);
+                while let Err(TrySendError::Full(full)) = self.sender.try_send(batch) {
+                    batch = full;
+                    if let Ok((oldest, lines)) = self.receiver.try_recv() {
+                        if !lines.is_empty() {
+                            dropped.push((oldest, lines.len()));
+                        }
+                    }
+                }
+            }
+            OverloadPolicy::Sample { every } => {
+                self.set_lagging(&source, false);
+                let mut lines = lines;
+                if full && every > 1 {
+                    let total = lines.len();
+                    lines = lines.step_by(every);
+                    dropped.push((source.clone(), total - lines.len()));
+                }
+                self.sender.send_async((source, lines)).await.unwrap_or_default();
+            }
+        }
+        self.report(dropped);
+    }
+
+    /// Mark whether the pipeline keeps up with `source`, only counted once it lagged
+    fn set_lagging(&self, source: &str, lagging: bool) {
+        let mut lag = self.lag.write();
+        if lagging || lag.contains_key(source) {
+            lag.entry(source.to_string()).or_default().lagging = lagging;
+        }
+    }
+
+    /// Count and send the `dropped` lines of each source, notifying the start of an overload
+    fn report(&self, dropped: Vec<(String, usize)>) {
+        if dropped.is_empty() {
+            self.overloaded.store(false, Ordering::Relaxed);
+            return;
+        }
+
+        let reason = "Pipeline queue full".to_string();
+        if !self.overloaded.swap(true, Ordering::Relaxed) {
+            let notification = Notification::new(
+                NotificationLevel::Warning,
+                &format!("{}, lines are dropped ({:?})", reason, self.policy()),
+            );
+            self.events
+                .send(Event::Notification(notification))
+                .unwrap_or_default();
+        }
+        for (source, lines) in dropped {
+            debug!(source = %source, lines, "Lines dropped by the overload policy");
+            self.add_dropped(&source, lines);
+            self.events
+                .send(Event::LinesDropped {
+                    source,
+                    lines,
+                    reason: reason.clone(),
+                })
+                .unwrap_or_default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::{pin, Pin};
+    use std::task::{Context, Waker};
+
+    use log_source::source::runtime::block_on;
+
+    use super::*;
+
+    fn batch(lines: usize) -> LineBatch {
+        (0..lines).map(|line| line.to_string()).collect()
+    }
+
+    /// Run `push` until it waits for room in the queue, returning whether it does
+    fn waits_for_room(push: Pin<&mut impl Future<Output = ()>>) -> bool {
+        push.poll(&mut Context::from_waker(Waker::noop())).is_pending()
+    }
+
+    #[test]
+    fn full_queue_drops_the_oldest_batches() {
+        let (events, mut receiver) = broadcast::channel(16);
+        let queue = BatchQueue::new(2, events);
+        queue.set_policy(OverloadPolicy::DropOldest);
+
+        block_on(async {
+            queue.push("a.log".to_string(), batch(1)).await;
+            queue.push("a.log".to_string(), batch(2)).await;
+            queue.push("b.log".to_string(), batch(3)).await;
+            queue.push("b.log".to_string(), LineBatch::default()).await;
+        });
+
+        let queued: Vec<usize> = queue.receiver().drain().map(|(_, lines)| lines.len()).collect();
+        assert_eq!(queued, vec![2, 3]);
+        assert!(matches!(receiver.try_recv(), Ok(Event::Notification(_))));
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            Event::LinesDropped {
+                source: "a.log".to_string(),
+                lines: 1,
+                reason: "Pipeline queue full".to_string()
+            }
+        );
+        let lag = queue.lag("a.log");
+        assert_eq!((lag.dropped, lag.lagging), (1, true));
+        assert_eq!(queue.lag("b.log"), SourceLag::default());
+    }
+
+    #[test]
+    fn full_queue_samples_the_new_batches() {
+        let (events, mut receiver) = broadcast::channel(16);
+        let queue = BatchQueue::new(2, events);
+        queue.set_policy(OverloadPolicy::Sample { every: 4 });
+
+        block_on(queue.push("a.log".to_string(), batch(8)));
+        block_on(queue.push("a.log".to_string(), batch(8)));
+        let mut sampled = pin!(queue.push("a.log".to_string(), batch(8)));
+        assert!(waits_for_room(sampled.as_mut()));
+        assert_eq!(queue.receiver().recv().unwrap().1.len(), 8);
+        block_on(sampled);
+
+        let queued: Vec<LineBatch> = queue.receiver().drain().map(|(_, lines)| lines).collect();
+        assert_eq!(queued, vec![batch(8), LineBatch::from_iter(["0", "4"])]);
+        assert!(matches!(receiver.try_recv(), Ok(Event::Notification(_))));
+        assert!(matches!(receiver.try_recv(), Ok(Event::LinesDropped { lines: 6, .. })));
+        assert_eq!(queue.lag("a.log").dropped, 6);
+    }
+
+    #[test]
+    fn lines_waiting_for_room_are_deferred() {
+        let (events, _receiver) = broadcast::channel(16);
+        let queue = BatchQueue::new(1, events);
+
+        block_on(queue.push("a.log".to_string(), batch(2)));
+        assert_eq!(queue.lag("a.log"), SourceLag::default());
+        let mut deferred = pin!(queue.push("a.log".to_string(), batch(3)));
+        assert!(waits_for_room(deferred.as_mut()));
+        queue.receiver().recv().unwrap();
+        block_on(deferred);
+        let lag = queue.lag("a.log");
+        assert_eq!((lag.dropped, lag.deferred, lag.lagging), (0, 3, true));
+
+        queue.receiver().drain().for_each(drop);
+        block_on(queue.push("a.log".to_string(), LineBatch::default()));
+        assert!(!queue.lag("a.log").lagging);
+        queue.add_dropped("b.log", 4);
+        assert_eq!(queue.lag("b.log").dropped, 4);
+        // Lagging until the queue is drained, even without any other batch of the source
+        queue.caught_up();
+        assert!(queue.lag("b.log").lagging);
+        queue.receiver().drain().for_each(drop);
+        queue.caught_up();
+        assert_eq!((queue.lag("b.log").dropped, queue.lag("b.log").lagging), (4, false));
+        queue.clear_lag();
+        assert_eq!(queue.lag("a.log"), SourceLag::default());
+    }
+}
@@ -0,0 +1,272 @@
+use std::sync::Arc;
+
+use log_source::source::log_source::Backoff;
+#[cfg(feature = "plugins")]
+use log_source::source::plugin_source::SourcePlugins;
+
+use crate::models::burst::BurstConfig;
+use crate::models::dedupe::DedupeConfig;
+use crate::models::invalid_utf8::InvalidUtf8;
+use crate::models::overload_policy::OverloadPolicy;
+use crate::models::pipeline::PipelineConfig;
+use crate::models::retention::Retention;
+use crate::stores::analysis_store::{AnalysisStore, InMemmoryAnalysisStore};
+use crate::stores::log_store::{InMemmoryLogStore, LogStore};
+use crate::stores::processing_store::{InMemmoryProcessingStore, ProcessingStore};
+
+use super::log_service::{LogService, DEFAULT_CHANNEL_CAPACITY, DEFAULT_QUEUE_CAPACITY};
+
+/// Configure and start a `LogService`, the analysis engine behind the terminal UI.
+///
+/// Every option has a default, so `LogAnalyzerBuilder::new().build()` gives the same engine
+/// as `lap`: in memory stores, one worker per CPU and file sources following their files.
+///
+/// ```no_run
+/// use log_analyzer::{LogAnalyzer, LogAnalyzerBuilder};
+///
+/// let analyzer = LogAnalyzerBuilder::new()
+///     .workers(2)
+///     .max_memory(Some(512))
+///     .build();
+/// analyzer.add_format("Default", r"(?P<SEVERITY>\w+) (?P<PAYLOAD>.*)")?;
+/// analyzer.add_log(0, "/var/log/syslog", Some(&"Default".to_string()))?;
+/// # Ok::<(), log_analyzer::LogAnalyzerError>(())
+/// ```
+pub struct LogAnalyzerBuilder {
+    log_store: Option<Arc<dyn LogStore + Sync + Send>>,
+    processing_store: Option<Arc<dyn ProcessingStore + Sync + Send>>,
+    analysis_store: Option<Arc<dyn AnalysisStore + Sync + Send>>,
+    workers: Option<usize>,
+    chunk_size: Option<usize>,
+    queue_capacity: usize,
+    overload_policy: OverloadPolicy,
+    channel_capacity: usize,
+    follow: bool,
+    max_memory: Option<usize>,
+    burst_detection: Option<BurstConfig>,
+    retention: Option<Retention>,
+    max_line_length: Option<usize>,
+    invalid_utf8: InvalidUtf8,
+    file_retry: Backoff,
+    dedupe: Option<DedupeConfig>,
+    #[cfg(feature = "plugins")]
+    plugins: Option<SourcePlugins>,
+}
+
+impl LogAnalyzerBuilder {
+    pub fn new() -> Self {
+        Self {
+            log_store: None,
+            processing_store: None,
+            analysis_store: None,
+            workers: None,
+            chunk_size: None,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            overload_policy: OverloadPolicy::default(),
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            follow: true,
+            max_memory: None,
+            burst_detection: None,
+            retention: None,
+            max_line_length: None,
+            invalid_utf8: InvalidUtf8::default(),
+            file_retry: Backoff::default(),
+            dedupe: None,
+            #[cfg(feature = "plugins")]
+            plugins: None,
+        }
+    }
+
+    /// Store of the raw lines and the sources. In memory by default, `SqliteLogStore` keeps them
+    /// in a database with the `sqlite` feature
+    pub fn log_store(mut self, log_store: Arc<dyn LogStore + Sync + Send>) -> Self {
+        self.log_store = Some(log_store);
+        self
+    }
+
+    /// Store of the formats and filters. In memory by default
+    pub fn processing_store(
+        mut self,
+        processing_store: Arc<dyn ProcessingStore + Sync + Send>,
+    ) -> Self {
+        self.processing_store = Some(processing_store);
+        self
+    }
+
+    /// Store of the filtered and searched lines. In memory by default, `SqliteAnalysisStore`
+    /// also keeps the filtered log in a database with the `sqlite` feature
+    pub fn analysis_store(mut self, analysis_store: Arc<dyn AnalysisStore + Sync + Send>) -> Self {
+        self.analysis_store = Some(analysis_store);
+        self
+    }
+
+    /// Threads processing the lines and searches in parallel. One per CPU by default
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = Some(workers);
+        self
+    }
+
+    /// Lines processed at once by a worker. By default the chunks are sized from their latency
+    /// and the batches queued, see `ChunkSizer`
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = Some(chunk_size.max(1));
+        self
+    }
+
+    /// Batches of lines held by each stage of the pipeline before applying the overload policy.
+    /// `DEFAULT_QUEUE_CAPACITY` by default
+    pub fn queue_capacity(mut self, queue_capacity: usize) -> Self {
+        self.queue_capacity = queue_capacity.max(1);
+        self
+    }
+
+    /// What is done with the lines read while the pipeline is full. `OverloadPolicy::Block` by
+    /// default, so no line is lost but the sources are slowed down
+    pub fn overload_policy(mut self, overload_policy: OverloadPolicy) -> Self {
+        self.overload_policy = overload_policy;
+        self
+    }
+
+    /// Events kept for the subscribers of `on_event` and lines queued for the sinks.
+    /// `DEFAULT_CHANNEL_CAPACITY` by default
+    pub fn channel_capacity(mut self, channel_capacity: usize) -> Self {
+        self.channel_capacity = channel_capacity.max(1);
+        self
+    }
+
+    /// Apply the options set in `pipeline`, keeping the current ones for the others
+    pub fn pipeline(mut self, pipeline: PipelineConfig) -> Self {
+        if let Some(workers) = pipeline.workers {
+            self = self.workers(workers);
+        }
+        if let Some(chunk_size) = pipeline.chunk_size {
+            self = self.chunk_size(chunk_size);
+        }
+        if let Some(queue_capacity) = pipeline.queue_capacity {
+            self = self.queue_capacity(queue_capacity);
+        }
+        if let Some(channel_capacity) = pipeline.channel_capacity {
+            self = self.channel_capacity(channel_capacity);
+        }
+        self
+    }
+
+    /// Keep reading the lines appended to the file sources (the default) or load them once
+    pub fn follow(mut self, follow: bool) -> Self {
+        self.follow = follow;
+        self
+    }
+
+    /// Limit the memory used by the stored lines in MB, `None` for no limit (the default)
+    pub fn max_memory(mut self, max_memory_mb: Option<usize>) -> Self {
+        self.max_memory = max_memory_mb;
+        self
+    }
+
+    /// Send `Event::Burst` events for the intervals with many more filtered lines than usual,
+    /// `None` to disable the detection (the default)
+    pub fn burst_detection(mut self, config: Option<BurstConfig>) -> Self {
+        self.burst_detection = config;
+        self
+    }
+
+    /// Discard the oldest lines beyond these limits, `None` to keep every line (the default)
+    pub fn retention(mut self, retention: Option<Retention>) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Cut the lines longer than this many bytes before processing them, `None` for no limit
+    /// (the default). The raw lines are kept whole
+    pub fn max_line_length(mut self, max_line_length: Option<usize>) -> Self {
+        self.max_line_length = max_line_length;
+        self
+    }
+
+    /// What is done with the lines read that are not valid UTF-8. `InvalidUtf8::Replace` by
+    /// default, so the invalid bytes show up as `�`
+    pub fn invalid_utf8(mut self, invalid_utf8: InvalidUtf8) -> Self {
+        self.invalid_utf8 = invalid_utf8;
+        self
+    }
+
+    /// Waits between the attempts to read again a followed file that can't be read, from 1 to
+    /// 30 seconds by default
+    pub fn file_retry(mut self, file_retry: Backoff) -> Self {
+        self.file_retry = file_retry;
+        self
+    }
+
+    /// Hide the lines with the payload of a line of another source read around the same time.
+    /// Disabled by default
+    pub fn dedupe(mut self, dedupe: Option<DedupeConfig>) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+
+    /// Read the sources of type `PLUGIN` with `plugins`. None are read by default
+    #[cfg(feature = "plugins")]
+    pub fn plugins(mut self, plugins: SourcePlugins) -> Self {
+        self.plugins = Some(plugins);
+        self
+    }
+
+    /// Start the service with its background threads
+    pub fn build(self) -> Arc<LogService> {
+        let log_service = LogService::start(
+            self.log_store
+                .unwrap_or_else(|| Arc::new(InMemmoryLogStore::new())),
+            self.processing_store
+                .unwrap_or_else(|| Arc::new(InMemmoryProcessingStore::new())),
+            self.analysis_store
+                .unwrap_or_else(|| Arc::new(InMemmoryAnalysisStore::new())),
+            self.workers.unwrap_or_else(num_cpus::get),
+            self.chunk_size,
+            self.queue_capacity,
+            self.channel_capacity,
+        );
+        log_service.set_follow(self.follow);
+        log_service.set_max_memory(self.max_memory);
+        log_service.set_burst_detection(self.burst_detection);
+        log_service.set_retention(self.retention);
+        log_service.set_overload_policy(self.overload_policy);
+        log_service.set_max_line_length(self.max_line_length);
+        log_service.set_invalid_utf8(self.invalid_utf8);
+        log_service.set_file_retry(self.file_retry);
+        log_service.set_dedupe(self.dedupe);
+        #[cfg(feature = "plugins")]
+        if let Some(plugins) = self.plugins {
+            log_service.set_plugins(plugins);
+        }
+        log_service
+    }
+}
+
+impl Default for LogAnalyzerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::log_service::LogAnalyzer;
+
+    #[test]
+    fn built_analyzer_uses_the_given_stores() {
+        let processing_store = Arc::new(InMemmoryProcessingStore::new());
+        let analyzer = LogAnalyzerBuilder::new()
+            .processing_store(processing_store.clone())
+            .workers(1)
+            .pipeline(PipelineConfig {
+                chunk_size: Some(100),
+                channel_capacity: Some(16),
+                ..Default::default()
+            })
+            .build();
+
+        analyzer.add_format("All", "(?P<PAYLOAD>.*)").unwrap();
+        assert_eq!(processing_store.get_format("All").as_deref(), Some("(?P<PAYLOAD>.*)"));
+    }
+}
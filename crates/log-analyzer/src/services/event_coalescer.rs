@@ -0,0 +1,78 @@
+use super::log_service::Event;
+
+/// Merge the ranges of the frequent pipeline events, so thousands of batches processed in a
+/// second notify a single range per kind of event
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct EventCoalescer {
+    processing: Option<(usize, usize)>,
+    new_lines: Option<(usize, usize)>,
+    new_search_lines: Option<(usize, usize)>,
+}
+
+/// Smallest range covering `range` and `(from, to)`
+fn merge(range: &mut Option<(usize, usize)>, from: usize, to: usize) {
+    *range = Some(match *range {
+        Some((first, last)) => (first.min(from), last.max(to)),
+        None => (from, to),
+    });
+}
+
+impl EventCoalescer {
+    /// Merge `event` with the pending ones of its kind, returning it back if it is not merged
+    pub fn add(&mut self, event: Event) -> Option<Event> {
+        match event {
+            Event::Processing(from, to) => merge(&mut self.processing, from, to),
+            Event::NewLines(from, to) => merge(&mut self.new_lines, from, to),
+            Event::NewSearchLines(from, to) => merge(&mut self.new_search_lines, from, to),
+            event => return Some(event),
+        }
+        None
+    }
+
+    /// Range of the new lines pending, if any
+    pub fn new_lines(&self) -> Option<(usize, usize)> {
+        self.new_lines
+    }
+
+    /// Range of the new search lines pending, if any
+    pub fn new_search_lines(&self) -> Option<(usize, usize)> {
+        self.new_search_lines
+    }
+
+    /// Merged events pending, in the order they are emitted by the pipeline
+    pub fn take(&mut self) -> Vec<Event> {
+        let Self {
+            processing,
+            new_lines,
+            new_search_lines,
+        } = std::mem::take(self);
+        processing
+            .map(|(from, to)| Event::Processing(from, to))
+            .into_iter()
+            .chain(new_lines.map(|(from, to)| Event::NewLines(from, to)))
+            .chain(new_search_lines.map(|(from, to)| Event::NewSearchLines(from, to)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_of_the_same_kind_are_merged() {
+        let mut coalescer = EventCoalescer::default();
+
+        assert_eq!(coalescer.add(Event::NewLines(10, 19)), None);
+        assert_eq!(coalescer.add(Event::NewLines(0, 9)), None);
+        assert_eq!(coalescer.add(Event::NewSearchLines(20, 29)), None);
+        assert_eq!(coalescer.add(Event::FilterFinished), Some(Event::FilterFinished));
+        assert_eq!(coalescer.new_lines(), Some((0, 19)));
+
+        assert_eq!(
+            coalescer.take(),
+            vec![Event::NewLines(0, 19), Event::NewSearchLines(20, 29)]
+        );
+        assert!(coalescer.take().is_empty());
+    }
+}
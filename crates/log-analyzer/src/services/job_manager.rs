@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap as HashMap;
+
+/// Background work of the service. Starting a job cancels the previous one of the same kind
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Job {
+    /// Filtering the raw lines again
+    Refilter,
+    /// Searching the processed lines
+    Search,
+    /// Reading the source with the given address
+    Source(String),
+}
+
+/// Tells a job it was superseded. Clones share the same state
+#[derive(Clone, Default, Debug)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Tokens of the jobs started and not finished yet
+#[derive(Default)]
+pub struct JobManager {
+    /// K: job -> V: token of its latest start
+    tokens: Mutex<HashMap<Job, CancellationToken>>,
+}
+
+impl JobManager {
+    /// Start `job`, cancelling its previous start if still running
+    pub fn start(&self, job: Job) -> CancellationToken {
+        let token = CancellationToken::default();
+        if let Some(previous) = self.tokens.lock().insert(job, token.clone()) {
+            previous.cancel();
+        }
+        token
+    }
+
+    /// Token of the running `job`, starting it if not running
+    pub fn current(&self, job: Job) -> CancellationToken {
+        self.tokens.lock().entry(job).or_default().clone()
+    }
+
+    /// Forget `job` once finished, unless started again meanwhile
+    pub fn finish(&self, job: &Job, token: &CancellationToken) {
+        let mut tokens = self.tokens.lock();
+        if tokens
+            .get(job)
+            .is_some_and(|current| Arc::ptr_eq(&current.0, &token.0))
+        {
+            tokens.remove(job);
+        }
+    }
+
+    /// Tells if `job` was started and not finished yet
+    pub fn is_running(&self, job: &Job) -> bool {
+        self.tokens.lock().contains_key(job)
+    }
+
+    /// Cancel every job
+    pub fn cancel_all(&self) {
+        for (_, token) in self.tokens.lock().drain() {
+            token.cancel();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starting_a_job_cancels_its_previous_start() {
+        let jobs = JobManager::default();
+        let first = jobs.start(Job::Search);
+        let refilter = jobs.start(Job::Refilter);
+        let second = jobs.start(Job::Search);
+
+        assert!(first.is_cancelled());
+        assert!(!second.is_cancelled());
+        assert!(!refilter.is_cancelled());
+
+        // A superseded job finishing leaves the latest one running
+        jobs.finish(&Job::Search, &first);
+        assert!(jobs.is_running(&Job::Search));
+        jobs.finish(&Job::Search, &second);
+        assert!(!jobs.is_running(&Job::Search));
+
+        let source = jobs.current(Job::Source("a.log".to_string()));
+        jobs.cancel_all();
+        assert!(refilter.is_cancelled());
+        assert!(source.is_cancelled());
+        assert!(!jobs.is_running(&Job::Refilter));
+    }
+}
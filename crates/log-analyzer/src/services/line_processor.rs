@@ -0,0 +1,139 @@
+use regex::Regex;
+
+use crate::domain::apply_filters::{apply_filters, apply_severity_mask, filter_color, FilterSet};
+use crate::domain::apply_format::{apply_format, format_line};
+use crate::domain::apply_search::{apply_search, format_search};
+use crate::error::{LogAnalyzerError, Result};
+use crate::models::filter::Filter;
+use crate::models::log_line_styled::LogLineStyled;
+use crate::models::severity::Severity;
+use crate::models::{
+    format::Format,
+    log_line::{LogLine, SmolStr},
+};
+
+/// Formats and filters lines one by one, without stores nor background processing.
+///
+/// Used to process logs from scripts, where the whole interactive pipeline is not needed,
+/// and where threads are not available, e.g. in a browser
+pub struct LineProcessor {
+    format: Option<Regex>,
+    filters: FilterSet,
+    hidden_severities: Vec<Severity>,
+}
+
+impl LineProcessor {
+    pub fn new(format: Option<&Format>, filters: Vec<Filter>) -> Result<Self> {
+        let format = match format {
+            Some(format) => Some(
+                Regex::new(&format.regex).map_err(|err| LogAnalyzerError::InvalidRegex {
+                    regex: format.regex.clone(),
+                    reason: err.to_string(),
+                })?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            format,
+            filters: FilterSet::new(filters.into_iter().map(|filter| filter.into()).collect()),
+            hidden_severities: Vec::new(),
+        })
+    }
+
+    /// Filter out the lines of these severities after applying the filters
+    pub fn hide_severities(mut self, severities: Vec<Severity>) -> Self {
+        self.hidden_severities = severities;
+        self
+    }
+
+    /// Process every line of `text`, e.g. the content of a whole file of `source`.
+    /// The lines keep their position in `text` as raw index
+    pub fn process_text(&self, source: &str, text: &str) -> Vec<LogLine> {
+        let source = SmolStr::from(source);
+        text.lines()
+            .enumerate()
+            .filter_map(|(index, line)| self.process_line(&source, line, index))
+            .collect()
+    }
+
+    /// Format the line of `source` at `index` and apply the filters.
+    /// Returns `None` if the line is filtered out
+    pub fn process(&self, source: &str, line: &str, index: usize) -> Option<LogLine> {
+        self.process_line(&source.into(), line, index)
+    }
+
+    /// Apply the filters to the fields of the line read in place, only building the `LogLine`
+    /// if it is kept
+    fn process_line(&self, source: &SmolStr, line: &str, index: usize) -> Option<LogLine> {
+        let formatted = format_line(&self.format.as_ref(), source, line, index);
+        let color = filter_color(&self.filters, &formatted)?;
+        if !apply_severity_mask(&self.hidden_severities, &formatted) {
+            return None;
+        }
+        let mut log_line = formatted.to_log_line();
+        log_line.color = color.flatten();
+        Some(log_line)
+    }
+
+    /// Format the line of `source` at `index`
+    pub fn format(&self, source: &str, line: &str, index: usize) -> LogLine {
+        apply_format(&self.format.as_ref(), &source.into(), line, index)
+    }
+
+    /// Apply the filters to a formatted line. Returns `None` if the line is filtered out
+    pub fn filter(&self, log_line: LogLine) -> Option<LogLine> {
+        apply_filters(&self.filters, log_line)
+            .filter(|log_line| apply_severity_mask(&self.hidden_severities, log_line))
+    }
+
+    /// Check if any field of the line matches the search, as the search of the analyzer does
+    pub fn search(search: &Regex, log_line: &LogLine) -> bool {
+        apply_search(search, log_line)
+    }
+
+    /// Split the fields of a line matching the search into the groups of the search regex,
+    /// as displayed in the search results
+    pub fn highlight(search: &Regex, log_line: &LogLine) -> LogLineStyled {
+        format_search(search, log_line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::filter::FilterAction;
+
+    use super::*;
+
+    #[test]
+    fn lines_are_formatted_and_filtered() {
+        let format = Format::new("Default", r"(?P<SEVERITY>\w+) (?P<PAYLOAD>.*)").unwrap();
+        let filters = vec![Filter {
+            alias: "No debug".into(),
+            action: FilterAction::EXCLUDE,
+            filter: LogLine {
+                severity: "DEBUG".into(),
+                ..Default::default()
+            },
+        }];
+        let processor = LineProcessor::new(Some(&format), filters).unwrap();
+
+        let line = processor.process("app.log", "INFO started", 0).unwrap();
+        assert_eq!(line.severity, "INFO");
+        assert_eq!(line.payload, "started");
+        assert!(processor.process("app.log", "DEBUG noise", 1).is_none());
+    }
+
+    #[test]
+    fn whole_texts_are_processed_with_hidden_severities() {
+        let format = Format::new("Default", r"(?P<SEVERITY>\w+) (?P<PAYLOAD>.*)").unwrap();
+        let processor = LineProcessor::new(Some(&format), Vec::new())
+            .unwrap()
+            .hide_severities(vec![Severity::Debug]);
+
+        let lines = processor.process_text("dropped.log", "INFO a\nDEBUG b\nERROR c");
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].payload, "c");
+        assert_eq!(lines[1].raw_index, 2);
+    }
+}
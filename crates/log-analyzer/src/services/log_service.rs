@@ -1,26 +1,221 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::any::Any;
 use std::ops::Range;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
-
-use anyhow::Result;
-use flume::Sender;
-use log_source::source::log_source::{create_source, LogSource, SourceType};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use flume::{Receiver, Sender, TrySendError};
+use parking_lot::{Mutex, RwLock};
+use log_source::source::line_batch::LineBatch;
+use log_source::source::log_source::{
+    create_source, Backoff, LogSource, ReplaySource, RestoredSource, SourceStatus, SourceType,
+};
+#[cfg(feature = "plugins")]
+use log_source::source::plugin_source::SourcePlugins;
+use log_source::source::runtime;
+use lz4_flex::frame::{FrameDecoder, FrameEncoder};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
-
-use pariter::{scope, IteratorExt as _};
-
-use crate::domain::apply_filters::apply_filters;
-use crate::domain::apply_format::apply_format;
-use crate::domain::apply_search::{apply_search, format_search};
+use tracing::{debug, debug_span, error, field, instrument, warn, Span};
+
+use crate::domain::apply_filters::{apply_severity_mask, filter_color, is_match, FilterSet};
+use crate::domain::apply_format::{format_line, format_line_within, FormattedLine};
+use crate::domain::apply_search::{apply_search, find_highlights, is_refinement};
+use crate::domain::dedupe_lines::Deduplicator;
+use crate::domain::detect_bursts::BurstDetector;
+use crate::domain::diff_lines::diff_lines;
+use crate::domain::export_lines::export_to_file;
+use crate::domain::find_gaps::find_gaps;
+use crate::domain::forward_lines::SinkForwarder;
+use crate::domain::redact_lines::Redactor;
+use crate::domain::size_chunks::ChunkSizer;
+use crate::domain::snapshot_lines::{SnapshotReader, SnapshotWriter};
+use crate::domain::transform_lines::Transformer;
+use crate::domain::watch_counter::WatchCounter;
+use crate::error::{LogAnalyzerError, Result};
+use crate::models::alert::Alert;
+use crate::models::burst::{Burst, BurstConfig};
+use crate::models::column_filter::ColumnFilter;
+use crate::models::dedupe::DedupeConfig;
+use crate::models::diff::{Diff, DiffQuery, DiffSide};
+use crate::models::export::ExportFormat;
 use crate::models::filter::LogFilter;
-use crate::models::log_line_styled::LogLineStyled;
-use crate::models::{filter::Filter, format::Format, log_line::LogLine};
-use crate::stores::analysis_store::AnalysisStore;
+use crate::models::gap::Gap;
+use crate::models::invalid_utf8::InvalidUtf8;
+use crate::models::log_line_styled::{Highlight, LogLineStyled};
+use crate::models::memory_usage::MemoryUsage;
+use crate::models::metrics::{PipelineMetrics, StageTimings};
+use crate::models::notification::{Notification, NotificationLevel};
+use crate::models::overload_policy::OverloadPolicy;
+use crate::models::session::{Session, SessionFilter, SessionSource};
+use crate::models::rate_bucket::RateBucket;
+use crate::models::redaction::Redaction;
+use crate::models::retention::Retention;
+use crate::models::severity::Severity;
+use crate::models::sink::Sink;
+use crate::models::snapshot::{SnapshotHeader, SnapshotSource};
+use crate::models::source_stats::SourceStats;
+use crate::models::statistics::Statistics;
+use crate::models::query::Query;
+use crate::models::top_values::TopValuesQuery;
+use crate::models::transform::Transform;
+use crate::models::watch::Watch;
+use crate::models::{
+    filter::Filter,
+    format::Format,
+    log_line::{LineFields, LogLine, SmolStr},
+};
+use crate::stores::analysis_store::{AnalysisStore, InMemmoryAnalysisStore};
 use crate::stores::log_store::LogStore;
 use crate::stores::processing_store::ProcessingStore;
+use crate::stores::regex_cache::RegexCache;
+
+use super::batch_queue::BatchQueue;
+use super::event_coalescer::EventCoalescer;
+use super::job_manager::{CancellationToken, Job, JobManager};
+use super::worker_pool::WorkerPool;
+
+/// Number of lines copied at once from the stores when saving a snapshot
+const SNAPSHOT_BATCH_SIZE: usize = 10_000;
+
+/// Number of raw lines read at once from the store when re-filtering.
+/// A newer refilter cancels the current one between two batches
+const REFILTER_BATCH_SIZE: usize = 100_000;
+
+/// Events kept for slow subscribers and lines queued for the sinks
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 1_000_000;
+
+/// Batches of lines held by each stage of the pipeline before applying the overload policy
+pub const DEFAULT_QUEUE_CAPACITY: usize = 64;
+
+/// Period over which the processed lines are notified, their ranges being merged meanwhile
+const EVENT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Period over which the pipeline metrics are measured and emitted
+const METRICS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Period between two checks of the retention limits. Discarding lines moves the lines left,
+/// so it is not done for every batch
+const RETENTION_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Positions of the search lines in a chunk of filtered lines, with their highlights
+type SearchPositions = Vec<(usize, Vec<Highlight>)>;
+
+/// Output of a worker for a chunk of lines: filtered lines, search lines, time of the raw lines
+/// and the matches of the live lines
+type ProcessedChunk = (Vec<LogLine>, SearchPositions, Vec<i64>, LiveMatches);
+
+/// Lines of a chunk once filtered
+struct FilteredChunk {
+    /// Lines kept by the filters
+    filtered: Vec<LogLine>,
+    /// Time of the lines of the chunk truncated to seconds, filtered or not
+    raw_times: Vec<i64>,
+    /// Lines of the chunk read while their source is live, filtered or not
+    live: Vec<LogLine>,
+}
+
+#[derive(Default)]
+/// Matches of the live lines of a chunk
+struct LiveMatches {
+    /// (filter alias, line) of the filters with an alert
+    alerts: Vec<(String, LogLine)>,
+    /// (filter alias, line) of the filters with a sink
+    sinks: Vec<(String, LogLine)>,
+    /// (expression, matched lines) of the watches
+    watches: Vec<(String, usize)>,
+}
+
+/// Seconds since the epoch, the time at which the live lines are counted by the watches
+fn current_second() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Search lines at the `search` positions of the `filtered` lines with their highlights, with
+/// the index assigned to their line in the processed log
+fn search_lines(
+    filtered: &[LogLine],
+    search: SearchPositions,
+    indexes: Range<usize>,
+) -> Vec<(LogLine, Vec<Highlight>)> {
+    search
+        .into_iter()
+        .map(|(position, highlights)| {
+            let mut line = filtered[position].clone();
+            line.index = indexes.start + position;
+            (line, highlights)
+        })
+        .collect()
+}
+
+/// The `lines` matched by the `search`, with their highlights
+fn matching_lines<'a>(
+    search: &'a Regex,
+    lines: impl IntoIterator<Item = &'a Arc<LogLine>>,
+) -> Vec<(LogLine, Vec<Highlight>)> {
+    lines
+        .into_iter()
+        .filter(|line| apply_search(search, line))
+        .map(|line| (LogLine::clone(line), find_highlights(search, line)))
+        .collect()
+}
+
+/// Ranges of the raw lines of the `sources` filtered first by a refilter: the `batch` lines
+/// around the `viewport` line and, when following the sources, the last `batch` lines of each.
+/// Empty if they hold half the lines or more, a preview not being worth it then
+fn priority_ranges(
+    sources: &[(String, usize)],
+    viewport: Option<&(String, usize)>,
+    follow: bool,
+    batch: usize,
+) -> Vec<(String, Range<usize>)> {
+    let mut ranges = Vec::new();
+    for (source, lines) in sources {
+        let around = viewport
+            .filter(|(viewport, raw_index)| viewport == source && raw_index < lines)
+            .map(|(_, raw_index)| {
+                let start = raw_index / batch * batch;
+                start..(start + batch).min(*lines)
+            });
+        let tail = (follow && *lines > 0).then(|| lines.saturating_sub(batch)..*lines);
+        match (around, tail) {
+            (Some(around), Some(tail)) if around.end >= tail.start => {
+                ranges.push((source.clone(), around.start.min(tail.start)..tail.end))
+            }
+            (around, tail) => {
+                ranges.extend(around.into_iter().chain(tail).map(|range| (source.clone(), range)))
+            }
+        }
+    }
+
+    let total: usize = sources.iter().map(|(_, lines)| lines).sum();
+    let prioritized: usize = ranges.iter().map(|(_, range)| range.len()).sum();
+    match prioritized * 2 >= total {
+        true => Vec::new(),
+        false => ranges,
+    }
+}
+
+/// Time of the lines truncated to seconds. Lines without a recognizable time are skipped
+fn line_seconds(lines: &[LogLine]) -> Vec<i64> {
+    lines
+        .iter()
+        .filter_map(|line| line.time())
+        .map(|time| time.floor() as i64)
+        .collect()
+}
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 /// Notify of state changes
 pub enum Event {
     // Currently processing lines (from, to)
@@ -31,12 +226,90 @@ pub enum Event {
     NewSearchLines(usize, usize),
     // Currently busy filtering
     Filtering,
+    // Lines around the viewport filtered first, swapped in while the rest is being filtered
+    FilterPreview,
     // Finished filtering
     FilterFinished,
     // Finished busy searching
     Searching,
     // Finished search
     SearchFinished,
+    // Runtime event worth reporting to the user
+    Notification(Notification),
+    // Throughput of the pipeline, emitted every second while it is busy
+    Metrics(PipelineMetrics),
+    // A source could not be read or stopped on an error
+    SourceError { source: String, error: String },
+    // A source read all its lines and stopped, e.g. a file loaded without following it
+    SourceEof { source: String },
+    // A followed file that could not be read is read again, from where it stopped
+    SourceResumed { source: String },
+    // Lines of a source discarded without being processed
+    LinesDropped {
+        source: String,
+        lines: usize,
+        reason: String,
+    },
+    // Oldest lines discarded by the retention: (source, raw lines) and filtered lines. The
+    // filtered lines left moved back by `filtered` positions, the raw ones by their source's
+    LinesDiscarded {
+        raw: Vec<(String, usize)>,
+        filtered: usize,
+    },
+    // Interval with many more filtered lines than usual, found by the burst detection
+    Burst(Burst),
+    // Live line matched by a filter with an alert
+    Alert {
+        filter: String,
+        alert: Alert,
+        line: Box<LogLine>,
+    },
+}
+
+impl Event {
+    /// Notification to display to the user for this event, if it is worth one.
+    /// Dropped lines are notified once by the analyzer when it starts dropping them
+    pub fn notification(&self) -> Option<Notification> {
+        match self {
+            Event::Notification(notification) => Some(notification.clone()),
+            Event::SourceError { source, error } => Some(Notification::new(
+                NotificationLevel::Error,
+                &format!("Unable to read {}: {}", source, error),
+            )),
+            Event::SourceEof { source } => Some(Notification::new(
+                NotificationLevel::Info,
+                &format!("Finished reading {}", source),
+            )),
+            Event::SourceResumed { source } => Some(Notification::new(
+                NotificationLevel::Info,
+                &format!("Reading {} again", source),
+            )),
+            Event::Burst(burst) => {
+                let seconds = burst.start.rem_euclid(86_400);
+                Some(Notification::new(
+                    NotificationLevel::Warning,
+                    &format!(
+                        "Burst of {} {}lines at {:02}:{:02}:{:02}, {:.1}x the usual rate",
+                        burst.lines,
+                        burst
+                            .severity
+                            .as_ref()
+                            .map(|severity| format!("{} ", severity))
+                            .unwrap_or_default(),
+                        seconds / 3600,
+                        seconds % 3600 / 60,
+                        seconds % 60,
+                        burst.ratio()
+                    ),
+                ))
+            }
+            Event::Alert { filter, line, .. } => Some(Notification::new(
+                NotificationLevel::Warning,
+                &format!("Alert {}: {}", filter, line.payload),
+            )),
+            _ => None,
+        }
+    }
 }
 
 /// Main API of this crate
@@ -48,14 +321,27 @@ pub trait LogAnalyzer {
         source_address: &str,
         format: Option<&String>,
     ) -> Result<()>;
+    /// Replay the file into the log as if it was being written: each line waits the time elapsed
+    /// since the previous one, parsed from its date or timestamp with the format, divided by
+    /// `speed`. Every replayed line is live, looked for alerts, watches and sinks
+    fn replay_log(&self, path: &str, format: Option<&String>, speed: f64) -> Result<()>;
     /// Add a new format to the list of available formats
     fn add_format(&self, alias: &str, regex: &str) -> Result<()>;
-    /// Start a new search
+    /// Start a new search. Its results are kept up to date as lines are processed, a search
+    /// narrowing the current one only looking through its results
     fn add_search(&self, regex: &str);
     /// Add a new filter to the list of available filters
     fn add_filter(&self, filter: Filter);
-    /// Get log lines between the range [from, to]
-    fn get_log_lines(&self, from: usize, to: usize) -> Vec<LogLine>;
+    /// Remove the format. The sources using it are processed again without format
+    fn remove_format(&self, alias: &str) -> Result<()>;
+    /// Change the alias of the format. The sources using it keep it under the new alias
+    fn rename_format(&self, alias: &str, new_alias: &str) -> Result<()>;
+    /// Remove the filter, filtering the log again if it was enabled
+    fn remove_filter(&self, alias: &str) -> Result<()>;
+    /// Change the alias of the filter, keeping its definition and enabled state
+    fn rename_filter(&self, alias: &str, new_alias: &str) -> Result<()>;
+    /// Get log lines between the range [from, to], shared with the store without copying them
+    fn get_log_lines(&self, from: usize, to: usize) -> Vec<Arc<LogLine>>;
     /// Get search lines between the range [from, to]
     fn get_search_lines(&self, from: usize, to: usize) -> Vec<LogLineStyled>;
     /// Get a list of log lines of `elements` size centered on the `line` element or the closest
@@ -64,7 +350,108 @@ pub trait LogAnalyzer {
         &self,
         index: usize,
         elements: usize,
-    ) -> (Vec<LogLine>, usize, usize);
+    ) -> (Vec<Arc<LogLine>>, usize, usize);
+
+    /// Aggregate the log: lines per time bucket (raw vs filtered) in up to `buckets` buckets,
+    /// filtered lines per severity and the `top` apps with the most filtered lines
+    fn get_statistics(&self, buckets: usize, top: usize) -> Statistics;
+
+    /// Count the filtered lines per distinct value of a column (e.g. Severity, App or Function),
+    /// keeping the `top` most frequent ones first. Lines with the column empty are not counted
+    fn get_column_values(&self, column: &str, top: usize) -> Result<Vec<(String, usize)>>;
+
+    /// Count the raw, filtered and search lines per `bucket` of time, parsed from their date or
+    /// timestamp. Buckets are rounded to whole seconds (one at least) and lines without time are not counted
+    fn get_rate_histogram(&self, bucket: Duration) -> Vec<RateBucket>;
+
+    /// Find the most frequent values of a column in the filtered log or the search results,
+    /// optionally restricted to a time range
+    fn get_top_values(&self, query: &TopValuesQuery) -> Result<Vec<(String, usize)>>;
+
+    /// Find the gaps longer than `min_gap` between the consecutive filtered lines of each source,
+    /// sorted by the position of the line after them. The time of the lines is parsed from their
+    /// date or timestamp
+    fn get_gaps(&self, min_gap: Duration) -> Vec<Gap>;
+
+    /// Find the filtered line to go to for a `time` in seconds, parsed like the date or timestamp
+    /// of the lines: the first one at or after it, or the last one if they are all earlier.
+    /// `None` if no filtered line has a recognizable time
+    fn get_index_at_time(&self, time: f64) -> Option<usize>;
+
+    /// Group the filtered lines by the id extracted with the `key` expression (e.g. `req=(\w+)`
+    /// for a request id), `None` to stop grouping them. The id is the first capture group of the
+    /// expression, or the whole match if it has no groups
+    fn set_correlation_key(&self, key: Option<&str>) -> Result<()>;
+    /// Get the correlation id of the filtered line at `index`
+    fn get_correlation_id(&self, index: usize) -> Option<String>;
+    /// Get every filtered line with the correlation id `id`, sorted by index
+    fn get_correlated_lines(&self, id: &str) -> Vec<LogLine>;
+
+    /// Compare the filtered lines of two sources or two time ranges, masking the times they
+    /// contain, and return the lines found on one side only
+    fn get_diff(&self, query: &DiffQuery) -> Diff;
+
+    /// Trigger `alert` when the filter matches a live line, enabled or not, `None` to remove its
+    /// alert. Live lines are the ones appended to followed files once read and every line of
+    /// the other sources
+    fn set_alert(&self, filter: &str, alert: Option<Alert>) -> Result<()>;
+    /// Get the alerts of the filters as a list of (filter alias, alert) sorted by alias
+    fn get_alerts(&self) -> Vec<(String, Alert)>;
+
+    /// Forward the live lines matched by the filter, enabled or not, to `sink` as they are read,
+    /// `None` to stop forwarding them
+    fn set_sink(&self, filter: &str, sink: Option<Sink>) -> Result<()>;
+    /// Get the sinks of the filters as a list of (filter alias, sink) sorted by alias
+    fn get_sinks(&self) -> Vec<(String, Sink)>;
+
+    /// Count the live lines matched by a query expression (e.g. `payload:retry`) in each second.
+    /// A plain regex matches any field of the lines
+    fn add_watch(&self, expression: &str) -> Result<()>;
+    /// Stop counting the lines matched by the expression
+    fn remove_watch(&self, expression: &str) -> Result<()>;
+    /// Get the counts of the watches, in the order they were added
+    fn get_watches(&self) -> Vec<Watch>;
+
+    /// Apply the transforms to the fields of the lines once formatted, before they are
+    /// redacted and filtered. The log is refiltered to transform the lines already read
+    fn set_transforms(&self, transforms: Vec<Transform>) -> Result<()>;
+    /// Get the transforms, in the order they are applied
+    fn get_transforms(&self) -> Vec<Transform>;
+
+    /// Replace the matches of the redaction regexes in the fields of the lines once formatted,
+    /// before they are filtered, stored, exported or forwarded. The raw lines are kept, the log
+    /// is refiltered to redact the lines already read
+    fn set_redactions(&self, redactions: Vec<Redaction>) -> Result<()>;
+    /// Get the redactions, in the order they are applied
+    fn get_redactions(&self) -> Vec<Redaction>;
+    /// Show the lines as they were read (`true`) or redacted again (`false`), refiltering the log
+    fn set_show_originals(&self, show: bool);
+    /// Whether the lines are shown as they were read, without redaction
+    fn is_showing_originals(&self) -> bool;
+
+    /// Write the filtered lines to the file at `path`, replacing it. The lines are copied from
+    /// the store in batches, so the lines filtered meanwhile are not exported.
+    /// Returns the number of lines written
+    fn export_filtered(&self, path: &str, format: ExportFormat) -> Result<usize>;
+    /// Write the search results to the file at `path` like `export_filtered`. The JSON and HTML
+    /// formats keep the groups highlighted by the search
+    fn export_search(&self, path: &str, format: ExportFormat) -> Result<usize>;
+    /// Save the raw, filtered and searched lines to the compressed file at `path`, replacing it,
+    /// together with `session`, usually `get_session` with the state of the view.
+    /// Returns the number of raw lines saved
+    fn save_snapshot(&self, path: &str, session: Session) -> Result<usize>;
+    /// Replace the analysis with the one saved by `save_snapshot` at `path`, without reading nor
+    /// filtering its sources again. The lines read before an error, e.g. a truncated file, are
+    /// kept. Returns the session saved with the lines, already restored
+    fn load_snapshot(&self, path: &str) -> Result<Session>;
+
+    /// Find the position in the filtered log of the line of `source` with the given raw index or
+    /// the closest one. Raw indexes are counted per source, the closest line of any source is
+    /// taken without one
+    fn resolve_raw_index(&self, source: Option<&str>, raw_index: usize) -> Option<usize>;
+    /// Tell the raw line of `source` the user is looking at. A refilter of a large log filters
+    /// the lines around it first, swapping them in as a preview until the whole log is done
+    fn set_viewport(&self, source: &str, raw_index: usize);
 
     /// Get a list of log lines of `elements` size centered on the `line` element or the closest
     /// Returns (elements, offset, index)
@@ -77,6 +464,8 @@ pub trait LogAnalyzer {
     /// Get the current managed logs
     /// Returns a vector of (enabled, log_path, Option<format>)
     fn get_logs(&self) -> Vec<(bool, String, Option<String>)>;
+    /// Names of the source plugins loaded, the schemes of the addresses of the `PLUGIN` sources
+    fn get_source_plugins(&self) -> Vec<String>;
 
     /// Get all the available formats
     fn get_formats(&self) -> Vec<Format>;
@@ -88,10 +477,45 @@ pub trait LogAnalyzer {
     fn get_total_filtered_lines(&self) -> usize;
     /// Get how many lines are in the search log
     fn get_total_searched_lines(&self) -> usize;
+    /// Count the filtered lines contributed by each source
+    /// Returns a vector of (source, lines) sorted by source
+    fn get_filtered_lines_per_source(&self) -> Vec<(String, usize)>;
+    /// Count the raw, filtered and searched lines of each source, sorted by source
+    fn get_source_stats(&self) -> Vec<SourceStats>;
+    /// Get the first `limit` filtered lines of the given source
+    fn get_source_log_lines(&self, source: &str, limit: usize) -> Vec<LogLine>;
+    /// Get the whole raw line of `source` at `raw_index`, e.g. a line cut by the max line length.
+    /// It is redacted as the lines shown, unless the originals are shown
+    fn get_raw_line(&self, source: &str, raw_index: usize) -> Option<String>;
+    /// Get an estimation of the memory held by the raw, filtered and search stores
+    fn get_memory_usage(&self) -> MemoryUsage;
+    /// Throughput of the processing pipeline measured over the last second
+    fn get_metrics(&self) -> PipelineMetrics;
     /// Enable or disable the given source
     fn toggle_source(&self, id: &str);
     /// Enable or disable the given filter
     fn toggle_filter(&self, id: &str);
+    /// Hide or show the lines with the given severity
+    fn toggle_severity(&self, severity: Severity);
+    /// Get the severities currently hidden
+    fn get_hidden_severities(&self) -> Vec<Severity>;
+    /// Filter the lines by the content of a column, replacing any previous filter on it
+    fn add_column_filter(&self, column_filter: ColumnFilter);
+    /// Remove the column filter of the given column
+    fn remove_column_filter(&self, column: &str);
+    /// Get the column filters in creation order
+    fn get_column_filters(&self) -> Vec<ColumnFilter>;
+    /// Get a snapshot of the sources, formats, filters, column filters, hidden severities and
+    /// search of the analysis
+    fn get_session(&self) -> Session;
+    /// Load the sources, formats, filters, column filters, hidden severities and search of a
+    /// session, in addition to the current ones.
+    /// Every format, filter and source is attempted, returning the first error found if any, a
+    /// filter with an invalid sink being added without it. Sources already loaded, e.g. from a
+    /// reopened database, are not read again
+    fn restore_session(&self, session: &Session) -> Result<()>;
+    /// Stop every source and remove all the sources, formats, filters, lines and the search
+    fn clear_all(&self);
     fn on_event(&self) -> broadcast::Receiver<Event>;
 }
 
@@ -99,8 +523,140 @@ pub struct LogService {
     log_store: Arc<dyn LogStore + Sync + Send>,
     processing_store: Arc<dyn ProcessingStore + Sync + Send>,
     analysis_store: Arc<dyn AnalysisStore + Sync + Send>,
-    log_sender: Sender<(String, Vec<String>)>,
+    log_sender: Sender<(String, LineBatch)>,
+    /// Batches read by the sources, waiting for the memory budget
+    source_queue: Arc<BatchQueue>,
+    status_sender: Sender<(String, SourceStatus)>,
+    /// Lines to write to a sink, by the filter that matched them
+    sink_sender: Sender<(String, Sink, LogLine)>,
     event_channel: broadcast::Sender<Event>,
+    /// Ranges of the lines processed since the last `EVENT_INTERVAL`
+    pending_events: Mutex<EventCoalescer>,
+    follow: AtomicBool,
+    /// Waits between the attempts to read again the followed files that can't be read
+    file_retry: Mutex<Backoff>,
+    /// Plugins reading the sources of type `PLUGIN`
+    #[cfg(feature = "plugins")]
+    plugins: RwLock<SourcePlugins>,
+    /// Reason why each source that failed can't be read, until it is read again
+    source_errors: Arc<Mutex<HashMap<String, String>>>,
+    /// (source, raw index) of the line the user is looking at, filtered first by the refilters
+    viewport: Mutex<Option<(String, usize)>>,
+    max_memory: AtomicUsize,
+    /// Bytes of the raw lines processed, the rest being cut. `usize::MAX` for no limit
+    max_line_length: AtomicUsize,
+    /// What is done with the lines read that are not valid UTF-8
+    invalid_utf8: Mutex<InvalidUtf8>,
+    /// Threads processing the lines in parallel
+    pool: WorkerPool,
+    /// Lines processed at once by a worker, `None` to size the chunks with `chunk_sizer`
+    chunk_size: Option<usize>,
+    /// Latency of the chunks processed, sizing the next ones
+    chunk_sizer: Mutex<ChunkSizer>,
+    counters: PipelineCounters,
+    /// Last measured metrics
+    metrics: RwLock<PipelineMetrics>,
+    /// Counts of the filtered lines looked for bursts, when enabled
+    burst_detector: Mutex<Option<BurstDetector>>,
+    /// Hides the filtered lines already read from another source, if enabled
+    dedupe: Mutex<Option<Deduplicator>>,
+    /// K: filter alias -> V: alert triggered when the filter matches a live line
+    alerts: RwLock<HashMap<String, Alert>>,
+    /// K: source -> V: first raw index not looked for alerts and watches yet. The lines before
+    /// it were read before the source was live
+    live_positions: Mutex<HashMap<String, usize>>,
+    /// K: source -> V: lines received, the ones skipped for their invalid UTF-8 included
+    received_lines: Mutex<HashMap<String, usize>>,
+    /// K: followed file -> V: lines received once it was read up to its end, until the batch
+    /// holding them is stored and the file made live from the raw index following them
+    caught_up: Mutex<HashMap<String, usize>>,
+    /// K: filter alias -> V: last time its alert was triggered
+    last_alerts: Mutex<HashMap<String, Instant>>,
+    /// K: filter alias -> V: sink receiving the live lines matched by the filter
+    sinks: RwLock<HashMap<String, Sink>>,
+    /// Counters of the live lines matched by the watch expressions, in the order they were added
+    watches: RwLock<Vec<WatchCounter>>,
+    /// Enabled filters compiled, with the `filters_version` they were compiled at
+    filter_set: RwLock<(u64, Arc<FilterSet>)>,
+    /// Incremented whenever the filters change, compiled again for the next chunk
+    filters_version: AtomicU64,
+    /// Transforms applied to the formatted lines, with the regexes compiled
+    transforms: RwLock<(Vec<Transform>, Arc<Transformer>)>,
+    /// Redactions applied to the formatted lines, with the regexes compiled
+    redactions: RwLock<(Vec<Redaction>, Arc<Redactor>)>,
+    /// The lines are not redacted while set
+    show_originals: AtomicBool,
+    /// The stores held the lines of a previous analysis when started, e.g. a reopened SQLite
+    /// database. They are already filtered with the session it is restored with
+    reopened: AtomicBool,
+    /// Limits of the lines kept by the stores, if any
+    retention: Mutex<Option<Retention>>,
+    /// Refilters, searches and sources running in the background
+    jobs: Arc<JobManager>,
+    /// Tokens of the refilters requested, run one after the other
+    refilter_sender: Sender<CancellationToken>,
+    /// Held while a batch of lines is stored and processed, so a refilter or a search catches
+    /// up with every stored line before swapping its results in
+    batch_lock: Mutex<()>,
+    /// Times the processed log was replaced or cleared, changing the lines at each index
+    log_version: AtomicUsize,
+    /// The search log holds every result of the query, no search being requested since the
+    /// last one was swapped in
+    search_complete: AtomicBool,
+    /// (token, query, refinement of the previous query) of the searches requested, run one
+    /// after the other
+    search_sender: Sender<(CancellationToken, String, bool)>,
+    /// Regexes of the formats and searches compiled once for every chunk of lines
+    regex_cache: RegexCache,
+    /// Never sent to, its receivers are disconnected once the service is dropped. Stops the
+    /// threads waiting on the channels of the sources, which may outlive the service
+    _shutdown: Sender<()>,
+    /// Lines holding this text make the workers panic, standing for a bug in the tests
+    #[cfg(test)]
+    panic_on: RwLock<Option<String>>,
+    /// Pipeline threads. They hold the service weakly, upgrading it for each message, so they
+    /// exit once it is dropped and the channels it holds are disconnected
+    threads: Mutex<Vec<JoinHandle<()>>>,
+}
+
+#[derive(Default)]
+/// Activity of the pipeline accumulated since the last measure of the metrics
+struct PipelineCounters {
+    ingested: AtomicU64,
+    processed: AtomicU64,
+    active_workers: AtomicUsize,
+    /// Lines per chunk of the last batch processed
+    chunk_size: AtomicUsize,
+    store_us: AtomicU64,
+    format_us: AtomicU64,
+    filter_us: AtomicU64,
+    search_us: AtomicU64,
+    /// Live lines dropped because the queue of the sinks was full
+    sink_dropped: AtomicU64,
+}
+
+/// Run `f` adding the microseconds it takes to `counter`
+fn timed<T>(counter: &AtomicU64, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    counter.fetch_add(start.elapsed().as_micros() as u64, Ordering::Relaxed);
+    result
+}
+
+/// Next message of `receiver`, or None once it is disconnected or the service is dropped
+fn recv_until_shutdown<T>(receiver: &Receiver<T>, shutdown: &Receiver<()>) -> Option<T> {
+    flume::Selector::new()
+        .recv(receiver, Result::ok)
+        .recv(shutdown, |_| None)
+        .wait()
+}
+
+/// Message of a caught panic, as given to `panic!`
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    match panic.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => panic.downcast_ref::<String>().cloned().unwrap_or_default(),
+    }
 }
 
 impl LogService {
@@ -111,161 +667,1008 @@ impl LogService {
     /// * apply format
     /// * apply filters
     /// * apply search
+    ///
+    /// Uses one worker per CPU, stages of `DEFAULT_QUEUE_CAPACITY` batches blocking the sources
+    /// when full and channels of `DEFAULT_CHANNEL_CAPACITY` events.
+    /// See `LogAnalyzerBuilder` to configure them
     pub fn new(
         log_store: Arc<dyn LogStore + Sync + Send>,
         processing_store: Arc<dyn ProcessingStore + Sync + Send>,
         analysis_store: Arc<dyn AnalysisStore + Sync + Send>,
     ) -> Arc<Self> {
-        let (sender, receiver) = flume::bounded(1_000_000_usize);
-        let (broadcast_sender, _broadcast_receiver) = broadcast::channel(1_000_000_usize);
+        Self::start(
+            log_store,
+            processing_store,
+            analysis_store,
+            num_cpus::get(),
+            None,
+            DEFAULT_QUEUE_CAPACITY,
+            DEFAULT_CHANNEL_CAPACITY,
+        )
+    }
+
+    /// Instantiates the service with `workers` processing threads handling `chunk_size` lines at
+    /// once, pipeline stages holding up to `queue_capacity` batches of lines and channels holding
+    /// up to `channel_capacity` events or lines to forward
+    pub(crate) fn start(
+        log_store: Arc<dyn LogStore + Sync + Send>,
+        processing_store: Arc<dyn ProcessingStore + Sync + Send>,
+        analysis_store: Arc<dyn AnalysisStore + Sync + Send>,
+        workers: usize,
+        chunk_size: Option<usize>,
+        queue_capacity: usize,
+        channel_capacity: usize,
+    ) -> Arc<Self> {
+        let (sender, receiver) = flume::bounded(queue_capacity.max(1));
+        let (status_sender, status_receiver) = flume::unbounded();
+        let (sink_sender, sink_receiver) = flume::bounded(channel_capacity);
+        let (refilter_sender, refilter_receiver) = flume::unbounded();
+        let (search_sender, search_receiver) = flume::unbounded();
+        let (shutdown_sender, shutdown_receiver) = flume::bounded(0);
+        let (broadcast_sender, _broadcast_receiver) = broadcast::channel(channel_capacity);
+        let source_queue = Arc::new(BatchQueue::new(queue_capacity, broadcast_sender.clone()));
+        let source_receiver = source_queue.receiver();
+        let reopened = log_store.get_total_lines() > 0;
 
         let log_service = Arc::new(Self {
             log_store,
             processing_store,
             analysis_store,
             log_sender: sender,
+            source_queue,
+            status_sender,
+            sink_sender,
             event_channel: broadcast_sender,
+            pending_events: Mutex::new(EventCoalescer::default()),
+            follow: AtomicBool::new(true),
+            file_retry: Mutex::new(Backoff::default()),
+            #[cfg(feature = "plugins")]
+            plugins: RwLock::new(SourcePlugins::default()),
+            source_errors: Arc::new(Mutex::new(HashMap::new())),
+            viewport: Mutex::new(None),
+            max_memory: AtomicUsize::new(0),
+            max_line_length: AtomicUsize::new(usize::MAX),
+            invalid_utf8: Mutex::new(InvalidUtf8::default()),
+            pool: WorkerPool::new(workers),
+            chunk_size,
+            chunk_sizer: Mutex::new(ChunkSizer::default()),
+            counters: PipelineCounters::default(),
+            metrics: RwLock::new(PipelineMetrics::default()),
+            burst_detector: Mutex::new(None),
+            dedupe: Mutex::new(None),
+            alerts: RwLock::new(HashMap::new()),
+            live_positions: Mutex::new(HashMap::new()),
+            received_lines: Mutex::new(HashMap::new()),
+            caught_up: Mutex::new(HashMap::new()),
+            last_alerts: Mutex::new(HashMap::new()),
+            sinks: RwLock::new(HashMap::new()),
+            watches: RwLock::new(Vec::new()),
+            filter_set: RwLock::new((0, Arc::new(FilterSet::new(Vec::new())))),
+            filters_version: AtomicU64::new(1),
+            transforms: RwLock::new((Vec::new(), Arc::new(Transformer::default()))),
+            redactions: RwLock::new((Vec::new(), Arc::new(Redactor::default()))),
+            show_originals: AtomicBool::new(false),
+            reopened: AtomicBool::new(reopened),
+            retention: Mutex::new(None),
+            jobs: Arc::new(JobManager::default()),
+            refilter_sender,
+            batch_lock: Mutex::new(()),
+            log_version: AtomicUsize::new(0),
+            search_complete: AtomicBool::new(true),
+            search_sender,
+            regex_cache: RegexCache::default(),
+            _shutdown: shutdown_sender,
+            #[cfg(test)]
+            panic_on: RwLock::new(None),
+            threads: Mutex::new(Vec::new()),
         });
 
-        let log = log_service.clone();
-        let event_sender = log_service.event_channel.clone();
-        std::thread::Builder::new()
+        // Lines read by the sources go through the memory budget before being processed.
+        let log = Arc::downgrade(&log_service);
+        let shutdown = shutdown_receiver.clone();
+        let ingestion = std::thread::Builder::new()
+            .name("Ingestion".to_string())
+            .spawn(move || {
+                let mut refusing = false;
+                while let Some((path, lines)) =
+                    recv_until_shutdown(&source_receiver, &shutdown)
+                {
+                    let Some(log) = log.upgrade() else {
+                        break;
+                    };
+                    // Lines still in flight from a source removed by `clear_all`
+                    if log.log_store.get_source(&path).is_none() {
+                        continue;
+                    }
+                    let max_memory = log.max_memory.load(Ordering::Relaxed);
+                    if max_memory > 0 && log.get_memory_usage().total() >= max_memory {
+                        debug!(source = %path, lines = lines.len(), "Batch discarded by the memory limit");
+                        let reason = format!(
+                            "Memory limit of {} MB reached",
+                            max_memory / (1024 * 1024)
+                        );
+                        if !refusing {
+                            refusing = true;
+                            let notification = Notification::new(
+                                NotificationLevel::Error,
+                                &format!("{}, new lines are discarded", reason),
+                            );
+                            log.event_channel
+                                .send(Event::Notification(notification))
+                                .unwrap_or_default();
+                        }
+                        if !lines.is_empty() {
+                            log.source_queue.add_dropped(&path, lines.len());
+                            log.event_channel
+                                .send(Event::LinesDropped {
+                                    source: path,
+                                    lines: lines.len(),
+                                    reason,
+                                })
+                                .unwrap_or_default();
+                        }
+                        continue;
+                    }
+                    refusing = false;
+                    log.counters
+                        .ingested
+                        .fetch_add(lines.len() as u64, Ordering::Relaxed);
+                    log.log_sender.send((path, lines)).unwrap_or_default();
+                    log.source_queue.caught_up();
+                }
+            })
+            .unwrap();
+
+        // Sinks are written apart from the pipeline so a slow webhook doesn't hold the
+        // processing. A failing sink is notified once until it works again
+        let log = Arc::downgrade(&log_service);
+        let forwarder = std::thread::Builder::new()
+            .name("Forwarder".to_string())
+            .spawn(move || {
+                let mut forwarder = SinkForwarder::default();
+                let mut failing: Vec<Sink> = Vec::new();
+                while let Ok((filter, sink, line)) = sink_receiver.recv() {
+                    let Some(log) = log.upgrade() else {
+                        break;
+                    };
+                    match forwarder.forward(&filter, &sink, &line) {
+                        Ok(()) => failing.retain(|failed| failed != &sink),
+                        Err(err) if !failing.contains(&sink) => {
+                            debug!(filter = %filter, error = %err, "Line not forwarded");
+                            let notification = Notification::new(
+                                NotificationLevel::Error,
+                                &format!("Sink of filter {} failed: {}", filter, err),
+                            );
+                            log.event_channel
+                                .send(Event::Notification(notification))
+                                .unwrap_or_default();
+                            failing.push(sink);
+                        }
+                        Err(_) => {}
+                    }
+                    if sink_receiver.is_empty() {
+                        let sinks: Vec<Sink> = log.sinks.read().values().cloned().collect();
+                        forwarder.retain(&sinks);
+                    }
+                }
+            })
+            .unwrap();
+
+        let log = Arc::downgrade(&log_service);
+        let notifier = std::thread::Builder::new()
+            .name("Notifier".to_string())
+            .spawn(move || {
+                while let Some((address, status)) =
+                    recv_until_shutdown(&status_receiver, &shutdown_receiver)
+                {
+                    let Some(log) = log.upgrade() else {
+                        break;
+                    };
+                    let event = match status {
+                        // The lines appended from now on are live, looked for alerts and watches
+                        SourceStatus::CaughtUp(lines) => {
+                            log.caught_up.lock().insert(address, lines);
+                            continue;
+                        }
+                        SourceStatus::Connected => {
+                            log.source_errors.lock().remove(&address);
+                            Event::Notification(Notification::new(
+                                NotificationLevel::Info,
+                                &format!("Connected to {}", address),
+                            ))
+                        }
+                        SourceStatus::Disconnected => Event::Notification(Notification::new(
+                            NotificationLevel::Warning,
+                            &format!("Disconnected from {}, reconnecting", address),
+                        )),
+                        SourceStatus::Unavailable(reason) => {
+                            log.source_errors.lock().insert(address.clone(), reason.clone());
+                            Event::SourceError {
+                                source: address,
+                                error: reason,
+                            }
+                        }
+                        SourceStatus::Resumed => {
+                            log.source_errors.lock().remove(&address);
+                            Event::SourceResumed { source: address }
+                        }
+                        SourceStatus::Eof => Event::SourceEof { source: address },
+                    };
+                    log.event_channel.send(event).unwrap_or_default();
+                }
+            })
+            .unwrap();
+
+        let log = Arc::downgrade(&log_service);
+        let consumer = std::thread::Builder::new()
             .name("Consumer".to_string())
-            .spawn(move || loop {
-                let num_cpus = num_cpus::get();
-                while let Ok((path, lines)) = receiver.recv() {
-                    let (format, indexes, lines) = log.process_raw_lines(&path, lines);
+            .spawn(move || {
+                let mut last_retention = Instant::now();
+                while let Ok((path, line_batch)) = receiver.recv() {
+                    let Some(log) = log.upgrade() else {
+                        break;
+                    };
+                    // Followed files send empty batches while waiting for new lines
+                    let batch = match line_batch.is_empty() {
+                        true => Span::none(),
+                        false => {
+                            debug_span!("ingest_batch", source = %path, lines = line_batch.len())
+                        }
+                    };
+                    let _batch = batch.enter();
+                    let batch_lock = log.batch_lock.lock();
+                    let counters = &log.counters;
+                    // The bytes read are only split and decoded here, following the policy
+                    let invalid_utf8 = *log.invalid_utf8.lock();
+                    let decoded: Vec<Cow<str>> =
+                        line_batch.lines().filter_map(|line| invalid_utf8.decode(line)).collect();
+                    let skipped = line_batch.len() - decoded.len();
+                    if skipped > 0 {
+                        warn!(source = %path, lines = skipped, "Skipped lines with invalid UTF-8");
+                    }
+                    let lines: Vec<&str> = decoded.iter().map(|line| line.as_ref()).collect();
+                    let (format, indexes) =
+                        timed(&counters.store_us, || log.process_raw_lines(&path, &lines));
+                    log.receive_lines(&path, &line_batch, invalid_utf8, indexes.start);
 
                     if !lines.is_empty() {
-                        let chunk_size = lines.len() / num_cpus;
+                        let queue_depth = log.source_queue.len() + receiver.len();
+                        let chunk_size = log.chunk_size(lines.len(), queue_depth);
+                        counters.chunk_size.store(chunk_size, Ordering::Relaxed);
 
-                        let elements: Vec<(String, usize)> = lines
-                            .into_iter()
-                            .zip(indexes)
-                            .map(|(line, index)| (line, index))
-                            .collect();
+                        let elements: Vec<(&str, usize)> = lines.into_iter().zip(indexes).collect();
 
                         let first_index = elements[0].1;
                         let last_index = elements.last().unwrap().1;
-                        event_sender
-                            .send(Event::Processing(first_index, last_index))
-                            .unwrap_or_default();
+                        log.pending_events
+                            .lock()
+                            .add(Event::Processing(first_index, last_index));
+
+                        // Split the lines to process in equal chunks to be processed in parallel.
+                        // A chunk panicking on a line fails the batch instead of the thread
+                        let processed = log.pool.map_chunks(&elements, chunk_size, |chunk| {
+                            let started = Instant::now();
+                            counters.active_workers.fetch_add(1, Ordering::Relaxed);
+                            let processed = catch_unwind(AssertUnwindSafe(|| {
+                                // Trace the chunks of the workers as part of the batch
+                                let _batch = batch.enter();
+                                let source = SmolStr::from(path.as_str());
+                                let lines = timed(&counters.format_us, || {
+                                    log.apply_format(&format, &source, chunk)
+                                });
+                                let live_position = log.live_position(&path);
+                                let FilteredChunk { filtered, raw_times, live } =
+                                    timed(&counters.filter_us, || {
+                                        log.apply_filters(lines, live_position)
+                                    });
+                                let live_matches = LiveMatches {
+                                    alerts: log.match_filters(&live, &log.alerts),
+                                    sinks: log.match_filters(&live, &log.sinks),
+                                    watches: log.match_watches(&live),
+                                };
+                                let (filtered, search) = timed(&counters.search_us, || {
+                                    log.apply_search(filtered)
+                                });
+                                (filtered, search, raw_times, live_matches)
+                            }));
+                            log.chunk_sizer.lock().record(chunk.len(), started.elapsed());
+                            counters.active_workers.fetch_sub(1, Ordering::Relaxed);
+                            counters.processed.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+                            processed
+                        });
+                        let processed: Vec<ProcessedChunk> =
+                            match processed.into_iter().collect::<Result<_, _>>() {
+                                Ok(processed) => processed,
+                                Err(panic) => {
+                                    let lines = (first_index, last_index);
+                                    log.report_panic(&path, "processed", lines, panic.as_ref());
+                                    continue;
+                                }
+                            };
+
+                        // Store the processed lines in the analysis store. Search lines
+                        // take the index assigned to their line in the processed log
+                        timed(&counters.store_us, || {
+                            let mut live_matches = LiveMatches::default();
+                            for (filtered, search, raw_times, chunk_matches) in processed {
+                                live_matches.alerts.extend(chunk_matches.alerts);
+                                live_matches.sinks.extend(chunk_matches.sinks);
+                                live_matches.watches.extend(chunk_matches.watches);
+                                log.analysis_store
+                                    .add_line_times(&raw_times, &line_seconds(&filtered));
+                                if let Some(detector) = log.burst_detector.lock().as_mut() {
+                                    detector.add_lines(&filtered);
+                                }
+                                let indexes = log.analysis_store.add_lines(&filtered);
+                                log.analysis_store.add_search_lines(&search_lines(
+                                    &filtered, search, indexes,
+                                ));
+                            }
+                            log.advance_live_position(&path, last_index + 1);
+                            log.trigger_alerts(live_matches.alerts);
+                            log.forward_lines(live_matches.sinks);
+                            log.count_watches(live_matches.watches);
+                        });
+
+                        // Notified with the next batches processed before `EVENT_INTERVAL`
+                        let mut pending_events = log.pending_events.lock();
+                        pending_events.add(Event::NewLines(first_index, last_index));
+                        pending_events.add(Event::NewSearchLines(first_index, last_index));
+                    }
 
-                        scope(|scope| {
-                            // Split the lines to process in equal chunks to be processed in parallel
-                            let processed: Vec<(Vec<LogLine>, Vec<LogLine>)> = elements
-                                .chunks(chunk_size.max(num_cpus))
-                                .parallel_map_scoped(scope, |chunk| {
-                                    let lines = log.apply_format(&format, &path, chunk);
-                                    let filtered_lines = log.apply_filters(lines);
-                                    let (filtered, search) = log.apply_search(filtered_lines);
-                                    (filtered, search)
-                                })
-                                .collect();
+                    // Processed lines keep their index until the next batch is stored
+                    if last_retention.elapsed() >= RETENTION_INTERVAL {
+                        log.apply_retention();
+                        last_retention = Instant::now();
+                    }
+                    drop(batch_lock);
+                }
+            })
+            .unwrap();
 
-                            // Store the processed lines in the analysis store
-                            for (filtered, search) in processed {
-                                log.analysis_store.add_lines(&filtered);
-                                log.analysis_store.add_search_lines(&search);
-                            }
+        // Only the latest refilter requested is worth running, the previous ones are outdated
+        let log = Arc::downgrade(&log_service);
+        let refilter = std::thread::Builder::new()
+            .name("Refilter".to_string())
+            .spawn(move || {
+                while let Ok(mut token) = refilter_receiver.recv() {
+                    let Some(log) = log.upgrade() else {
+                        break;
+                    };
+                    if let Some(newer) = refilter_receiver.try_iter().last() {
+                        token = newer;
+                    }
+                    // The job is finished even if refiltering panicked out of the workers
+                    let refiltered = catch_unwind(AssertUnwindSafe(|| log.run_refilter(&token)));
+                    log.jobs.finish(&Job::Refilter, &token);
+                    if let Err(panic) = refiltered {
+                        error!(panic = %panic_message(panic.as_ref()), "Refilter panicked");
+                    }
+                }
+            })
+            .unwrap();
 
-                            // Notify of the processed lines
-                            event_sender
-                                .send(Event::NewLines(first_index, last_index))
-                                .unwrap_or_default();
-                            event_sender
-                                .send(Event::NewSearchLines(first_index, last_index))
-                                .unwrap_or_default();
-                        })
-                        .unwrap();
+        // Same for the searches
+        let log = Arc::downgrade(&log_service);
+        let search = std::thread::Builder::new()
+            .name("Search".to_string())
+            .spawn(move || {
+                while let Ok(mut request) = search_receiver.recv() {
+                    let Some(log) = log.upgrade() else {
+                        break;
+                    };
+                    if let Some(newer) = search_receiver.try_iter().last() {
+                        request = newer;
+                    }
+                    let (token, query, refinement) = request;
+                    let searched = catch_unwind(AssertUnwindSafe(|| {
+                        log.run_search(&token, &query, refinement)
+                    }));
+                    log.jobs.finish(&Job::Search, &token);
+                    if let Err(panic) = searched {
+                        error!(panic = %panic_message(panic.as_ref()), "Search panicked");
+                    }
+                }
+            })
+            .unwrap();
+
+        // A single event per kind notifies the lines processed meanwhile, sparing the
+        // subscribers a reload per batch
+        let log = Arc::downgrade(&log_service);
+        let events = std::thread::Builder::new()
+            .name("Events".to_string())
+            .spawn(move || loop {
+                std::thread::sleep(EVENT_INTERVAL);
+                let Some(log) = log.upgrade() else {
+                    break;
+                };
+                let events = log.pending_events.lock().take();
+                for event in events {
+                    log.event_channel.send(event).unwrap_or_default();
+                }
+            })
+            .unwrap();
+
+        let log = Arc::downgrade(&log_service);
+        let metrics = std::thread::Builder::new()
+            .name("Metrics".to_string())
+            .spawn(move || {
+                let mut last_measure = Instant::now();
+                loop {
+                    std::thread::sleep(METRICS_INTERVAL);
+                    let Some(log) = log.upgrade() else {
+                        break;
+                    };
+                    let metrics = log.measure_metrics(last_measure.elapsed());
+                    last_measure = Instant::now();
+
+                    let previous = std::mem::replace(&mut *log.metrics.write(), metrics);
+                    // An idle pipeline is only notified once
+                    if !(metrics.is_idle() && previous.is_idle()) {
+                        log.event_channel
+                            .send(Event::Metrics(metrics))
+                            .unwrap_or_default();
+                    }
+
+                    let bursts = match log.burst_detector.lock().as_mut() {
+                        Some(detector) => detector.detect(),
+                        None => Vec::new(),
+                    };
+                    for burst in bursts {
+                        log.event_channel
+                            .send(Event::Burst(burst))
+                            .unwrap_or_default();
                     }
                 }
             })
             .unwrap();
 
+        log_service.threads.lock().extend([
+            ingestion, forwarder, notifier, consumer, refilter, search, events, metrics,
+        ]);
         log_service
     }
 
+    /// Drop `service` and wait for its pipeline threads to exit, which they do once every other
+    /// reference to the service is dropped too
+    pub fn shutdown(service: Arc<Self>) {
+        let threads = std::mem::take(&mut *service.threads.lock());
+        drop(service);
+        for thread in threads {
+            // A thread that panicked exited all the same
+            thread.join().unwrap_or_default();
+        }
+    }
+
+    /// Lines processed at once by a worker out of a batch of `lines` with `queue_depth` batches
+    /// waiting behind it, the configured size if any
+    fn chunk_size(&self, lines: usize, queue_depth: usize) -> usize {
+        self.chunk_size.unwrap_or_else(|| {
+            self.chunk_sizer
+                .lock()
+                .chunk_size(lines, self.pool.workers(), queue_depth)
+        })
+    }
+
+    /// Redactor applied to what is shown, none if the originals are shown or nothing is redacted
+    fn active_redactor(&self) -> Option<Arc<Redactor>> {
+        match self.show_originals.load(Ordering::Relaxed) {
+            true => None,
+            false => Some(self.redactions.read().1.clone()).filter(|redactor| !redactor.is_empty()),
+        }
+    }
+
+    /// Metrics of the activity accumulated during `elapsed`, resetting the counters
+    fn measure_metrics(&self, elapsed: Duration) -> PipelineMetrics {
+        let counters = &self.counters;
+        let elapsed_ms = elapsed.as_millis().max(1) as u64;
+        let per_sec = |counter: &AtomicU64| counter.swap(0, Ordering::Relaxed) * 1000 / elapsed_ms;
+
+        PipelineMetrics {
+            ingested_per_sec: per_sec(&counters.ingested),
+            processed_per_sec: per_sec(&counters.processed),
+            queue_depth: self.source_queue.len() + self.log_sender.len(),
+            active_workers: counters.active_workers.load(Ordering::Relaxed),
+            workers: self.pool.workers(),
+            chunk_size: counters.chunk_size.load(Ordering::Relaxed),
+            stage_timings: StageTimings {
+                store: counters.store_us.swap(0, Ordering::Relaxed),
+                format: counters.format_us.swap(0, Ordering::Relaxed),
+                filter: counters.filter_us.swap(0, Ordering::Relaxed),
+                search: counters.search_us.swap(0, Ordering::Relaxed),
+            },
+            sink_dropped: counters.sink_dropped.swap(0, Ordering::Relaxed),
+        }
+    }
+
+    /// Set if the file sources added from now on keep reading the lines appended to the file
+    /// (the default) or stop after loading it once
+    pub fn set_follow(&self, follow: bool) {
+        self.follow.store(follow, Ordering::Relaxed);
+    }
+
+    /// Waits between the attempts to read again a followed file that can't be read, e.g.
+    /// deleted or no longer readable, for the file sources added from now on. The first failure
+    /// is sent as an `Event::SourceError` and the file is read from where it stopped once it
+    /// is back, sending an `Event::SourceResumed`
+    pub fn set_file_retry(&self, retry: Backoff) {
+        *self.file_retry.lock() = retry;
+    }
+
+    /// Read the sources of type `PLUGIN` added from now on with `plugins`, usually the ones of
+    /// `SourcePlugins::load`
+    #[cfg(feature = "plugins")]
+    pub fn set_plugins(&self, plugins: SourcePlugins) {
+        *self.plugins.write() = plugins;
+    }
+
+    /// Limit the memory used by the stored lines to `max_memory_mb` megabytes, `None` for no limit.
+    /// Once reached, the lines read by the sources are discarded until the usage drops again.
+    /// The limit is checked for every batch of lines, so loading a large file can exceed it
+    pub fn set_max_memory(&self, max_memory_mb: Option<usize>) {
+        let max_memory = max_memory_mb.map(|mb| mb * 1024 * 1024).unwrap_or_default();
+        self.max_memory.store(max_memory, Ordering::Relaxed);
+    }
+
+    /// Cut the lines longer than `max_line_length` bytes before formatting them, `None` for no
+    /// limit (the default). Their payload ends with a `truncation_marker` and their whole text
+    /// is kept in the raw lines, see `LogAnalyzer::get_raw_line`. Applies to the lines
+    /// processed from now on, and to every line once refiltered
+    pub fn set_max_line_length(&self, max_line_length: Option<usize>) {
+        self.max_line_length
+            .store(max_line_length.unwrap_or(usize::MAX), Ordering::Relaxed);
+    }
+
+    /// What is done with the lines read that are not valid UTF-8, `InvalidUtf8::Replace` by
+    /// default. The skipped lines are not stored, so they take no raw index
+    pub fn set_invalid_utf8(&self, policy: InvalidUtf8) {
+        *self.invalid_utf8.lock() = policy;
+    }
+
+    /// Look for bursts in the filtered lines with the given options, `None` to stop (the default).
+    /// Every second, the intervals of log time with many more lines than usual, overall or of a
+    /// severity, are sent as `Event::Burst` events
+    pub fn set_burst_detection(&self, config: Option<BurstConfig>) {
+        *self.burst_detector.lock() = config.map(|config| {
+            let mut detector = BurstDetector::new(config);
+            detector.add_lines(&self.analysis_store.fetch_log());
+            detector
+        });
+    }
+
+    /// Hide the filtered lines with the payload of a line of another source read around the same
+    /// time, `None` to show them all (the default). The log is refiltered to hide the duplicates
+    /// already read. While reading, the source whose line is read first keeps it. A refilter
+    /// goes through the sources in the order they were added, so the first of them keeps it
+    pub fn set_dedupe(&self, config: Option<DedupeConfig>) {
+        let mut dedupe = self.dedupe.lock();
+        // Nothing to hide nor to show again while it stays disabled, e.g. in a reopened database
+        let changed = dedupe.is_some() || config.is_some();
+        *dedupe = config.map(Deduplicator::new);
+        drop(dedupe);
+        if changed && self.log_store.get_total_lines() > 0 {
+            self.refilter();
+        }
+    }
+
+    /// Keep the stored lines within the limits of `retention`, `None` for no limit (the default).
+    /// The oldest raw lines of each source and the oldest filtered lines beyond them are discarded,
+    /// the filtered lines left being indexed again from 0 and an `Event::LinesDiscarded` sent.
+    /// The limits are checked every second as lines are received, so they can be exceeded until
+    /// then
+    pub fn set_retention(&self, retention: Option<Retention>) {
+        *self.retention.lock() = retention;
+    }
+
+    /// What is done with the lines read while the pipeline is full, `OverloadPolicy::Block` by
+    /// default. The lines dropped are sent as `Event::LinesDropped` events
+    pub fn set_overload_policy(&self, policy: OverloadPolicy) {
+        self.source_queue.set_policy(policy);
+    }
+
+    /// Discard the stored lines beyond the retention limits, if any. Skipped during a refilter
+    /// or a search, which keep the indexes of the lines they read
+    fn apply_retention(&self) {
+        let busy = self.jobs.is_running(&Job::Refilter) || self.jobs.is_running(&Job::Search);
+        let retention = match *self.retention.lock() {
+            Some(retention) if !busy => retention,
+            _ => return,
+        };
+        let discarded = self.log_store.apply_retention(&retention);
+        let mut live_positions = self.live_positions.lock();
+        for (source, lines) in &discarded {
+            if let Some(position) = live_positions.get_mut(source) {
+                *position = position.saturating_sub(*lines);
+            }
+        }
+        drop(live_positions);
+        let filtered = self.analysis_store.apply_retention(&retention, &discarded);
+        if !discarded.is_empty() || filtered > 0 {
+            debug!(raw = ?discarded, filtered, "Discarded the lines beyond the retention");
+            self.event_channel
+                .send(Event::LinesDiscarded {
+                    raw: discarded,
+                    filtered,
+                })
+                .unwrap_or_default();
+        }
+    }
+
     /// Store the raw received lines in memory and retrieve if there is a format for this log
-    fn process_raw_lines(
-        &self,
-        path: &str,
-        lines: Vec<String>,
-    ) -> (Option<String>, Range<usize>, Vec<String>) {
-        let indexes = self.log_store.add_lines(path, &lines);
+    fn process_raw_lines(&self, path: &str, lines: &[&str]) -> (Option<String>, Range<usize>) {
+        let indexes = self.log_store.add_lines(path, lines);
         let format = self.log_store.get_format(path);
-        (format, indexes, lines)
+        (format, indexes)
     }
 
-    /// Apply formatting (if any) to a list of lines and return the formated `LogLine`
-    fn apply_format(
+    /// Apply formatting (if any) to a list of lines, finding their fields in place
+    #[instrument(name = "format_chunk", level = "debug", skip_all, fields(lines = line_index.len()))]
+    fn apply_format<'a>(
         &self,
         format: &Option<String>,
-        path: &str,
-        line_index: &[(String, usize)],
-    ) -> Vec<LogLine> {
+        path: &'a SmolStr,
+        line_index: &'a [(&'a str, usize)],
+    ) -> Vec<FormattedLine<'a>> {
         let mut format_regex = None;
 
         if let Some(format) = format {
             let format = self.processing_store.get_format(format);
-            format_regex = format.map(|format| Regex::new(&format).unwrap());
+            format_regex = format.and_then(|format| self.regex_cache.get(&format).ok());
         }
 
-        let mut log_lines: Vec<LogLine> = Vec::with_capacity(line_index.len());
-        for (line, index) in line_index {
-            let log_line = apply_format(&format_regex.as_ref(), path, line, *index);
-            log_lines.push(log_line);
+        let max_length = self.max_line_length.load(Ordering::Relaxed);
+        line_index
+            .iter()
+            .map(|(line, index)| {
+                #[cfg(test)]
+                self.panic_on(line);
+                format_line_within(&format_regex.as_ref(), path, line, *index, max_length)
+            })
+            .collect()
+    }
+
+    /// Count the lines received from a source, the first of them stored at `first_index`. A
+    /// followed file that caught up with the lines of the batch is live from the raw index
+    /// following them, the skipped lines taking none
+    fn receive_lines(
+        &self,
+        path: &str,
+        batch: &LineBatch,
+        policy: InvalidUtf8,
+        first_index: usize,
+    ) {
+        let mut received_lines = self.received_lines.lock();
+        let received = received_lines.entry(path.to_string()).or_default();
+        let before = *received;
+        *received += batch.len();
+        let mut caught_up = self.caught_up.lock();
+        let lines = match caught_up.get(path) {
+            Some(lines) if *lines <= *received => *lines,
+            _ => return,
+        };
+        caught_up.remove(path);
+        // Caught up after the lines already stored are only live from this batch on
+        let stored = batch
+            .lines()
+            .take(lines.saturating_sub(before))
+            .filter(|line| policy.decode(line).is_some())
+            .count();
+        let mut positions = self.live_positions.lock();
+        let position = positions.entry(path.to_string()).or_default();
+        *position = (*position).max(first_index + stored);
+    }
+
+    /// First raw index of a source not looked for alerts and watches yet, if it is live
+    fn live_position(&self, path: &str) -> Option<usize> {
+        self.live_positions.lock().get(path).copied()
+    }
+
+    /// Mark the lines of a live source up to `end` as looked for alerts and watches
+    fn advance_live_position(&self, path: &str, end: usize) {
+        if let Some(position) = self.live_positions.lock().get_mut(path) {
+            *position = (*position).max(end);
         }
-        log_lines
     }
 
-    /// Apply filters (if any) to a list of `LogLine` and return the filtered list of `LogLine`
-    fn apply_filters(&self, lines: Vec<LogLine>) -> Vec<LogLine> {
-        let filters: Vec<LogFilter> = self
+    /// Find the live lines matched by the filters with an alert or a sink in `targets`, enabled
+    /// or not. Returns a list of (filter alias, line)
+    fn match_filters<T>(
+        &self,
+        lines: &[LogLine],
+        targets: &RwLock<HashMap<String, T>>,
+    ) -> Vec<(String, LogLine)> {
+        let targets = targets.read();
+        if targets.is_empty() || lines.is_empty() {
+            return Vec::new();
+        }
+        let filters: Vec<(String, LogFilter)> = self
             .processing_store
             .get_filters()
             .into_iter()
-            .filter(|(enabled, _)| *enabled)
-            .map(|(_, filter)| filter.into())
+            .filter(|(_, filter)| targets.contains_key(&filter.alias))
+            .map(|(_, filter)| (filter.alias.clone(), filter.into()))
             .collect();
 
-        let mut filtered_lines: Vec<LogLine> = Vec::with_capacity(lines.len());
+        let mut matches = Vec::new();
+        for line in lines {
+            for (alias, filter) in &filters {
+                if is_match(filter, line) {
+                    matches.push((alias.clone(), line.clone()));
+                }
+            }
+        }
+        matches
+    }
+
+    /// Count the live lines matched by each watch. Returns a list of (expression, lines)
+    fn match_watches(&self, lines: &[LogLine]) -> Vec<(String, usize)> {
+        if lines.is_empty() {
+            return Vec::new();
+        }
+        self.watches
+            .read()
+            .iter()
+            .map(|watch| {
+                let matches = lines.iter().filter(|line| watch.matches(line)).count();
+                (watch.expression().to_string(), matches)
+            })
+            .filter(|(_, matches)| *matches > 0)
+            .collect()
+    }
+
+    /// Add the matches of the watches to the current second
+    fn count_watches(&self, matches: Vec<(String, usize)>) {
+        if matches.is_empty() {
+            return;
+        }
+        let second = current_second();
+        let mut watches = self.watches.write();
+        for (expression, lines) in matches {
+            if let Some(watch) = watches.iter_mut().find(|watch| watch.expression() == expression) {
+                watch.add(lines, second);
+            }
+        }
+    }
+
+    /// Queue the matched lines to be written to the sinks of their filters. The lines that
+    /// don't fit in the queue are dropped and counted instead of holding the pipeline behind a
+    /// slow sink
+    fn forward_lines(&self, matches: Vec<(String, LogLine)>) {
+        let sinks = self.sinks.read();
+        for (filter, line) in matches {
+            if let Some(sink) = sinks.get(&filter) {
+                if let Err(TrySendError::Full(_)) =
+                    self.sink_sender.try_send((filter, sink.clone(), line))
+                {
+                    self.counters.sink_dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// Send the alerts of the matched lines, skipping the filters that alerted less than their
+    /// cooldown ago
+    fn trigger_alerts(&self, matches: Vec<(String, LogLine)>) {
+        let alerts = self.alerts.read();
+        let mut last_alerts = self.last_alerts.lock();
+        for (filter, line) in matches {
+            let alert = match alerts.get(&filter) {
+                Some(alert) => alert.clone(),
+                None => continue,
+            };
+            let now = Instant::now();
+            let cooldown = Duration::from_secs(alert.cooldown_secs);
+            if last_alerts
+                .get(&filter)
+                .is_some_and(|last| now.duration_since(*last) < cooldown)
+            {
+                continue;
+            }
+            last_alerts.insert(filter.clone(), now);
+            self.event_channel
+                .send(Event::Alert {
+                    filter,
+                    alert,
+                    line: Box::new(line),
+                })
+                .unwrap_or_default();
+        }
+    }
+
+    /// Enabled filters, compiled again only once they changed
+    fn filter_set(&self) -> Arc<FilterSet> {
+        let version = self.filters_version.load(Ordering::Acquire);
+        {
+            let (compiled, filters) = &*self.filter_set.read();
+            if *compiled == version {
+                return filters.clone();
+            }
+        }
+
+        let filters = Arc::new(FilterSet::new(
+            self.processing_store
+                .get_filters()
+                .into_iter()
+                .filter(|(enabled, _)| *enabled)
+                .map(|(_, filter)| filter.into())
+                .collect(),
+        ));
+        let mut filter_set = self.filter_set.write();
+        if filter_set.0 < version {
+            *filter_set = (version, filters.clone());
+        }
+        filters
+    }
+
+    /// Compile the filters again for the next chunks
+    fn filters_changed(&self) {
+        self.filters_version.fetch_add(1, Ordering::Release);
+    }
+
+    /// Apply filters (if any) to a list of formatted lines and return the `LogLine` of the ones
+    /// kept, together with the time of every line and the lines from `live_position` on.
+    ///
+    /// The fields are filtered in place, a `LogLine` is only built for the lines kept and the
+    /// live ones, unless transforms or redactions rewrite the fields of every line
+    #[instrument(
+        name = "filter_chunk",
+        level = "debug",
+        skip_all,
+        fields(lines = lines.len(), kept = field::Empty)
+    )]
+    fn apply_filters(
+        &self,
+        lines: Vec<FormattedLine>,
+        live_position: Option<usize>,
+    ) -> FilteredChunk {
+        let filters = self.filter_set();
+        let hidden_severities = self.processing_store.get_hidden_severities();
+        let column_filters = self.processing_store.get_column_filters();
+        let keep = |line: &dyn LineFields| {
+            filter_color(&filters, line).filter(|_| {
+                apply_severity_mask(&hidden_severities, line)
+                    && column_filters
+                        .iter()
+                        .all(|column_filter| column_filter.is_match(line))
+            })
+        };
+
+        let transformer = Some(self.transforms.read().1.clone())
+            .filter(|transformer| !transformer.is_empty());
+        let redactor = self.active_redactor();
+        let rewritten = transformer.is_some() || redactor.is_some();
+
+        let mut chunk = FilteredChunk {
+            filtered: Vec::with_capacity(lines.len()),
+            raw_times: Vec::with_capacity(lines.len()),
+            live: Vec::new(),
+        };
         for line in lines {
-            if let Some(filtered_line) = apply_filters(&filters, line) {
-                filtered_lines.push(filtered_line);
+            let live = live_position.is_some_and(|position| line.position() >= position);
+            if !(rewritten || live) {
+                chunk.raw_times.extend(line.time().map(|time| time.floor() as i64));
+                if let Some(color) = keep(&line) {
+                    let mut log_line = line.to_log_line();
+                    log_line.color = color.flatten();
+                    chunk.filtered.push(log_line);
+                }
+                continue;
+            }
+
+            let mut log_line = line.to_log_line();
+            if let Some(transformer) = &transformer {
+                transformer.transform(&mut log_line);
+            }
+            if let Some(redactor) = &redactor {
+                redactor.redact(&mut log_line);
+            }
+            chunk.raw_times.extend(log_line.time().map(|time| time.floor() as i64));
+            if live {
+                chunk.live.push(log_line.clone());
+            }
+            if let Some(color) = keep(&log_line) {
+                if let Some(color) = color {
+                    log_line.color = color;
+                }
+                chunk.filtered.push(log_line);
             }
         }
-        filtered_lines
+        // The chunks of a batch come from the same source, so they don't hide each other's lines
+        if let Some(dedupe) = self.dedupe.lock().as_mut() {
+            chunk.filtered.retain(|line| !dedupe.is_duplicate(line));
+        }
+        Span::current().record("kept", chunk.filtered.len());
+        chunk
     }
 
-    /// Apply the search query (if any) to a list of `LogLine` and return both the received lines and the searched ones
-    fn apply_search(&self, lines: Vec<LogLine>) -> (Vec<LogLine>, Vec<LogLine>) {
-        let mut search_lines: Vec<LogLine> = Vec::with_capacity(lines.len());
+    /// Apply the search query (if any) to a list of `LogLine` and return both the received lines
+    /// and the positions of the searched ones
+    #[instrument(
+        name = "search_chunk",
+        level = "debug",
+        skip_all,
+        fields(lines = lines.len(), matches = field::Empty)
+    )]
+    fn apply_search(&self, lines: Vec<LogLine>) -> (Vec<LogLine>, SearchPositions) {
+        let mut search_positions = Vec::new();
         if let Some(search_query) = self.analysis_store.get_search_query() {
-            if let Ok(search_regex) = Regex::new(&search_query) {
-                for line in &lines {
+            if let Ok(search_regex) = self.regex_cache.get(&search_query) {
+                for (position, line) in lines.iter().enumerate() {
                     if apply_search(&search_regex, line) {
-                        search_lines.push(line.clone());
+                        search_positions.push((position, find_highlights(&search_regex, line)));
                     }
                 }
             }
         }
 
-        (lines, search_lines)
+        Span::current().record("matches", search_positions.len());
+        (lines, search_positions)
+    }
+
+    /// Helper function to run log sources. The lines of the run are dropped once its `token` is
+    /// cancelled, e.g. by another source added with the same address, which stops the source.
+    /// The job of the source is finished when it stops, at the end of its lines or on an error
+    fn run_log_source(
+        &self,
+        log_source: Arc<Box<dyn LogSource + Send + Sync>>,
+        token: CancellationToken,
+    ) {
+        let (sender, receiver) = flume::bounded(1);
+        let source_queue = self.source_queue.clone();
+        let status_sender = self.status_sender.clone();
+        let event_sender = self.event_channel.clone();
+        let source_errors = self.source_errors.clone();
+        let jobs = self.jobs.clone();
+
+        // The source and the queueing of its batches are tasks of the async runtime, the one of
+        // the caller with tokio if any, rather than threads of their own
+        let source_token = token.clone();
+        runtime::spawn(async move {
+            if let Err(err) = log_source.run(sender, status_sender).await {
+                if !source_token.is_cancelled() {
+                    let error = format!("stopped on {}", err);
+                    source_errors
+                        .lock()
+                        .insert(log_source.get_address(), error.clone());
+                    event_sender
+                        .send(Event::SourceError {
+                            source: log_source.get_address(),
+                            error,
+                        })
+                        .unwrap_or_default();
+                }
+            }
+            jobs.finish(&Job::Source(log_source.get_address()), &source_token);
+        });
+        runtime::spawn(async move {
+            while let Ok((path, lines)) = receiver.recv_async().await {
+                if token.is_cancelled() {
+                    break;
+                }
+                source_queue.push(path, lines).await;
+            }
+        });
     }
 
-    /// Helper function to run log sources
-    fn run_log_source(&self, log_source: Arc<Box<dyn LogSource + Send + Sync>>) {
-        let sender = self.log_sender.clone();
+    /// Start a new run of the source at `address`, stopping the previous source added with it
+    fn start_source(&self, address: &str) -> CancellationToken {
+        if let Some(previous) = self.log_store.get_source(address) {
+            previous.stop();
+        }
+        self.source_errors.lock().remove(address);
+        self.jobs.start(Job::Source(address.to_string()))
+    }
+}
 
-        std::thread::Builder::new()
-            .name(log_source.get_address())
-            .spawn(|| {
-                async_std::task::spawn(async move {
-                    log_source.run(sender).await.unwrap();
-                });
-            })
-            .unwrap();
+impl Drop for LogService {
+    /// Stop the sources, closing their files and connections, and the running jobs
+    fn drop(&mut self) {
+        self.jobs.cancel_all();
+        for (_, id, _) in self.log_store.get_logs() {
+            if let Some(source) = self.log_store.get_source(&id) {
+                source.stop();
+            }
+        }
     }
 }
 
@@ -278,14 +1681,62 @@ impl LogAnalyzer for LogService {
     ) -> Result<()> {
         let log_store = self.log_store.clone();
 
-        let source_type = SourceType::try_from(source_type).unwrap();
-
-        let log_source = Arc::new(async_std::task::block_on(create_source(
-            source_type,
-            source_address.to_string(),
-        ))?);
+        let source_type = SourceType::try_from(source_type)
+            .map_err(|_| LogAnalyzerError::UnknownSourceType(source_type))?;
+
+        let log_source = match source_type {
+            #[cfg(feature = "plugins")]
+            SourceType::PLUGIN => self.plugins.read().create_source(source_address),
+            _ => create_source(
+                source_type,
+                source_address.to_string(),
+                self.follow.load(Ordering::Relaxed),
+                *self.file_retry.lock(),
+            ),
+        };
+        let log_source = Arc::new(log_source.map_err(|err| {
+            LogAnalyzerError::SourceUnavailable {
+                address: source_address.to_string(),
+                reason: err.to_string(),
+            }
+        })?);
+        let token = self.start_source(source_address);
         log_store.add_log(source_address, log_source.clone(), format, true);
-        self.run_log_source(log_source);
+        // Files are live once their current lines are read, other sources right away
+        self.received_lines.lock().remove(source_address);
+        if source_type != SourceType::FILE {
+            self.live_positions
+                .lock()
+                .entry(source_address.to_string())
+                .or_default();
+        }
+        self.run_log_source(log_source, token);
+
+        Ok(())
+    }
+
+    fn replay_log(&self, path: &str, format: Option<&String>, speed: f64) -> Result<()> {
+        if !(speed.is_finite() && speed > 0.0) {
+            return Err(LogAnalyzerError::InvalidReplaySpeed);
+        }
+        let format_regex = format
+            .and_then(|format| self.processing_store.get_format(format))
+            .and_then(|format| self.regex_cache.get(&format).ok());
+        let time_of = Box::new(move |line: &str| {
+            format_line(&format_regex.as_ref(), &SmolStr::default(), line, 0).time()
+        });
+
+        let source = ReplaySource::new(path.to_string(), speed, time_of).map_err(|err| {
+            LogAnalyzerError::SourceUnavailable {
+                address: path.to_string(),
+                reason: err.to_string(),
+            }
+        })?;
+        let log_source: Arc<Box<dyn LogSource + Send + Sync>> = Arc::new(Box::new(source));
+        let token = self.start_source(path);
+        self.log_store.add_log(path, log_source.clone(), format, true);
+        self.live_positions.lock().insert(path.to_string(), 0);
+        self.run_log_source(log_source, token);
 
         Ok(())
     }
@@ -298,116 +1749,564 @@ impl LogAnalyzer for LogService {
     }
 
     fn add_search(&self, regex: &str) {
-        let re = Regex::new(regex);
-        self.analysis_store.reset_search();
-
-        if re.is_ok() {
-            self.analysis_store.add_search_query(regex);
-
-            let analysis_store = self.analysis_store.clone();
-            let regex_str = regex.to_string();
-            let sender = self.event_channel.clone();
-
-            std::thread::Builder::new()
-                .name("Search".to_string())
-                .spawn(move || {
-                    let log = analysis_store.fetch_log();
-
-                    if !log.is_empty() {
-                        sender.send(Event::Searching).unwrap_or_default();
-                        scope(|scope| {
-                            let num_cpus = num_cpus::get();
-                            let chunk_size = log.len() / num_cpus;
-                            let search_lines: Vec<LogLine> = log
-                                .chunks(chunk_size.max(num_cpus))
-                                .parallel_map_scoped(scope, move |chunk| {
-                                    let lines = chunk.to_owned();
-                                    let r = Regex::new(&regex_str).unwrap();
-                                    let mut v: Vec<LogLine> = Vec::with_capacity(lines.len());
-
-                                    for log_line in lines {
-                                        if apply_search(&r, &log_line) {
-                                            v.push(log_line);
-                                        };
-                                    }
-
-                                    v
-                                })
-                                .flatten()
-                                .collect::<Vec<LogLine>>();
-                            analysis_store.add_search_lines(&search_lines);
-                        })
-                        .unwrap();
-                        sender.send(Event::SearchFinished).unwrap_or_default();
-                    }
-                })
-                .unwrap();
+        if let Err(err) = self.regex_cache.get(regex) {
+            // The search log no longer holds every result of the query
+            self.analysis_store.reset_search();
+            self.search_complete.store(false, Ordering::Relaxed);
+            let notification = Notification::new(
+                NotificationLevel::Warning,
+                &format!("Invalid search \"{}\": {}", regex, err),
+            );
+            self.event_channel
+                .send(Event::Notification(notification))
+                .unwrap_or_default();
+            return;
+        }
+
+        // The results of the previous query are kept up to date as lines are processed, unless
+        // still being searched
+        let previous = self.analysis_store.get_search_query();
+        let complete = self.search_complete.load(Ordering::Relaxed);
+        if complete && previous.as_deref() == Some(regex) {
+            return;
         }
+        let refinement =
+            complete && previous.is_some_and(|previous| is_refinement(&previous, regex));
+
+        self.analysis_store.add_search_query(regex);
+        let token = self.jobs.start(Job::Search);
+        self.search_complete.store(false, Ordering::Relaxed);
+        self.search_sender
+            .send((token, regex.to_string(), refinement))
+            .unwrap_or_default();
     }
 
     fn add_filter(&self, filter: Filter) {
         self.processing_store
             .add_filter(filter.alias, filter.filter, filter.action, false);
+        self.filters_changed();
     }
 
-    fn get_log_lines(&self, from: usize, to: usize) -> Vec<LogLine> {
-        self.analysis_store.get_log_lines(from, to)
+    fn remove_format(&self, alias: &str) -> Result<()> {
+        if !self.processing_store.remove_format(alias) {
+            return Err(LogAnalyzerError::FormatNotFound(alias.to_string()));
+        }
+
+        let mut formatted_sources = false;
+        for (_, source, format) in self.log_store.get_logs() {
+            if format.as_deref() == Some(alias) {
+                self.log_store.set_format(&source, None);
+                formatted_sources = true;
+            }
+        }
+        if formatted_sources {
+            self.refilter();
+        }
+        Ok(())
     }
 
-    fn get_search_lines(&self, from: usize, to: usize) -> Vec<LogLineStyled> {
-        let search_lines_containing = self.analysis_store.get_search_lines(from, to);
-        let mut styled_search_lines = vec![];
+    fn rename_format(&self, alias: &str, new_alias: &str) -> Result<()> {
+        if new_alias.is_empty() {
+            return Err(LogAnalyzerError::EmptyAlias);
+        }
+        if alias != new_alias && self.processing_store.get_format(new_alias).is_some() {
+            return Err(LogAnalyzerError::FormatAlreadyExists(new_alias.to_string()));
+        }
+        if !self.processing_store.rename_format(alias, new_alias) {
+            return Err(LogAnalyzerError::FormatNotFound(alias.to_string()));
+        }
 
-        if !search_lines_containing.is_empty() {
-            // If there are search lines we are sure that there is a valid search query
-            let query = Regex::new(&self.analysis_store.get_search_query().unwrap()).unwrap();
-            styled_search_lines = search_lines_containing
-                .into_iter()
-                .map(|l| format_search(&query, &l))
-                .collect();
+        let new_alias = new_alias.to_string();
+        for (_, source, format) in self.log_store.get_logs() {
+            if format.as_deref() == Some(alias) {
+                self.log_store.set_format(&source, Some(&new_alias));
+            }
         }
+        Ok(())
+    }
 
-        styled_search_lines
+    fn remove_filter(&self, alias: &str) -> Result<()> {
+        self.alerts.write().remove(alias);
+        self.sinks.write().remove(alias);
+        match self.processing_store.remove_filter(alias) {
+            Some(true) => {
+                self.filters_changed();
+                self.refilter()
+            }
+            Some(false) => {}
+            None => return Err(LogAnalyzerError::FilterNotFound(alias.to_string())),
+        }
+        Ok(())
+    }
+
+    fn rename_filter(&self, alias: &str, new_alias: &str) -> Result<()> {
+        if new_alias.is_empty() {
+            return Err(LogAnalyzerError::EmptyAlias);
+        }
+        let exists = self
+            .processing_store
+            .get_filters()
+            .iter()
+            .any(|(_, filter)| filter.alias == new_alias);
+        if alias != new_alias && exists {
+            return Err(LogAnalyzerError::FilterAlreadyExists(new_alias.to_string()));
+        }
+        if !self.processing_store.rename_filter(alias, new_alias) {
+            return Err(LogAnalyzerError::FilterNotFound(alias.to_string()));
+        }
+        let mut alerts = self.alerts.write();
+        if let Some(alert) = alerts.remove(alias) {
+            alerts.insert(new_alias.to_string(), alert);
+        }
+        let mut sinks = self.sinks.write();
+        if let Some(sink) = sinks.remove(alias) {
+            sinks.insert(new_alias.to_string(), sink);
+        }
+        Ok(())
+    }
+
+    fn get_log_lines(&self, from: usize, to: usize) -> Vec<Arc<LogLine>> {
+        self.analysis_store.get_log_lines(from, to)
+    }
+
+    fn get_search_lines(&self, from: usize, to: usize) -> Vec<LogLineStyled> {
+        self.analysis_store.get_search_lines(from, to)
     }
 
     fn get_log_lines_containing(
         &self,
         index: usize,
         elements: usize,
-    ) -> (Vec<LogLine>, usize, usize) {
+    ) -> (Vec<Arc<LogLine>>, usize, usize) {
         self.analysis_store
             .get_log_lines_containing(index, elements)
     }
 
-    fn get_search_lines_containing(
-        &self,
-        index: usize,
-        elements: usize,
-    ) -> (Vec<LogLineStyled>, usize, usize) {
-        let search_lines_containing = self
+    fn get_statistics(&self, buckets: usize, top: usize) -> Statistics {
+        let (rate, bucket_seconds) =
+            Statistics::bucket_rate(&self.analysis_store.get_rate(), buckets);
+
+        Statistics {
+            rate,
+            bucket_seconds,
+            severities: Statistics::top(self.analysis_store.get_lines_per_severity(), usize::MAX),
+            top_apps: Statistics::top(self.analysis_store.get_lines_per_app(), top),
+        }
+    }
+
+    fn get_column_values(&self, column: &str, top: usize) -> Result<Vec<(String, usize)>> {
+        self.get_top_values(&TopValuesQuery::new(column, top))
+    }
+
+    fn get_rate_histogram(&self, bucket: Duration) -> Vec<RateBucket> {
+        let mut per_second: BTreeMap<i64, (usize, usize, usize)> = BTreeMap::new();
+        for (second, raw, filtered) in self.analysis_store.get_rate() {
+            let counts = per_second.entry(second).or_default();
+            counts.0 += raw;
+            counts.1 += filtered;
+        }
+        for (second, search) in self.analysis_store.get_search_rate() {
+            per_second.entry(second).or_default().2 += search;
+        }
+
+        let per_second: Vec<(i64, usize, usize, usize)> = per_second
+            .into_iter()
+            .map(|(second, (raw, filtered, search))| (second, raw, filtered, search))
+            .collect();
+        RateBucket::histogram(&per_second, bucket.as_secs() as i64)
+    }
+
+    fn get_top_values(&self, query: &TopValuesQuery) -> Result<Vec<(String, usize)>> {
+        match self
             .analysis_store
-            .get_search_lines_containing(index, elements);
+            .get_lines_per_value(&query.column, query.search, query.time_range)
+        {
+            Some(counts) => Ok(Statistics::top(counts, query.top)),
+            None => Err(LogAnalyzerError::UnknownColumn(query.column.clone())),
+        }
+    }
 
-        let mut styled_search_lines: (Vec<LogLineStyled>, usize, usize) =
-            (vec![], search_lines_containing.1, search_lines_containing.2);
+    fn get_gaps(&self, min_gap: Duration) -> Vec<Gap> {
+        find_gaps(&self.analysis_store.fetch_log(), min_gap.as_secs_f64())
+    }
 
-        if !search_lines_containing.0.is_empty() {
-            // If there are search lines we are sure that there is a valid search query
-            let query = Regex::new(&self.analysis_store.get_search_query().unwrap()).unwrap();
-            styled_search_lines.0 = search_lines_containing
-                .0
-                .into_iter()
-                .map(|l| format_search(&query, &l))
-                .collect();
+    fn get_index_at_time(&self, time: f64) -> Option<usize> {
+        self.analysis_store.get_index_at_time(time)
+    }
+
+    fn set_correlation_key(&self, key: Option<&str>) -> Result<()> {
+        let key = match key {
+            Some("") => return Err(LogAnalyzerError::EmptyRegex),
+            Some(key) => Some(Regex::new(key).map_err(|err| LogAnalyzerError::InvalidRegex {
+                regex: key.to_string(),
+                reason: err.to_string(),
+            })?),
+            None => None,
+        };
+        self.analysis_store.set_correlation_key(key);
+        Ok(())
+    }
+
+    fn get_correlation_id(&self, index: usize) -> Option<String> {
+        self.analysis_store.get_correlation_id(index)
+    }
+
+    fn get_correlated_lines(&self, id: &str) -> Vec<LogLine> {
+        self.analysis_store.get_correlated_lines(id)
+    }
+
+    fn get_diff(&self, query: &DiffQuery) -> Diff {
+        let log = self.analysis_store.fetch_log();
+        let side = |side: &DiffSide| -> Vec<&LogLine> {
+            match side.time_range {
+                // The lines in the range are looked up in the time index
+                Some((start, end)) => self
+                    .analysis_store
+                    .get_indexes_in_time_range(start, end)
+                    .into_iter()
+                    .filter_map(|index| log.get(index))
+                    .filter(|line| side.contains(line))
+                    .map(|line| &**line)
+                    .collect(),
+                None => log
+                    .iter()
+                    .filter(|line| side.contains(line))
+                    .map(|line| &**line)
+                    .collect(),
+            }
+        };
+        let mut diff = diff_lines(&side(&query.left), &side(&query.right));
+        for line in diff.left.iter_mut().chain(diff.right.iter_mut()) {
+            *line = self.analysis_store.show_line(line);
+        }
+        diff
+    }
+
+    fn set_alert(&self, filter: &str, alert: Option<Alert>) -> Result<()> {
+        let exists = self
+            .processing_store
+            .get_filters()
+            .iter()
+            .any(|(_, f)| f.alias == filter);
+        if !exists {
+            return Err(LogAnalyzerError::FilterNotFound(filter.to_string()));
         }
 
-        styled_search_lines
+        let mut alerts = self.alerts.write();
+        match alert {
+            Some(alert) => alerts.insert(filter.to_string(), alert),
+            None => alerts.remove(filter),
+        };
+        Ok(())
+    }
+
+    fn get_alerts(&self) -> Vec<(String, Alert)> {
+        let mut alerts: Vec<(String, Alert)> = self
+            .alerts
+            .read()
+            .iter()
+            .map(|(filter, alert)| (filter.clone(), alert.clone()))
+            .collect();
+        alerts.sort_by(|a, b| a.0.cmp(&b.0));
+        alerts
+    }
+
+    fn set_sink(&self, filter: &str, sink: Option<Sink>) -> Result<()> {
+        let exists = self
+            .processing_store
+            .get_filters()
+            .iter()
+            .any(|(_, f)| f.alias == filter);
+        if !exists {
+            return Err(LogAnalyzerError::FilterNotFound(filter.to_string()));
+        }
+        if let Some(sink) = &sink {
+            sink.validate().map_err(LogAnalyzerError::InvalidSink)?;
+        }
+
+        let mut sinks = self.sinks.write();
+        match sink {
+            Some(sink) => sinks.insert(filter.to_string(), sink),
+            None => sinks.remove(filter),
+        };
+        Ok(())
+    }
+
+    fn get_sinks(&self) -> Vec<(String, Sink)> {
+        let mut sinks: Vec<(String, Sink)> = self
+            .sinks
+            .read()
+            .iter()
+            .map(|(filter, sink)| (filter.clone(), sink.clone()))
+            .collect();
+        sinks.sort_by(|a, b| a.0.cmp(&b.0));
+        sinks
+    }
+
+    fn add_watch(&self, expression: &str) -> Result<()> {
+        let filters: Vec<Filter> = self
+            .processing_store
+            .get_filters()
+            .into_iter()
+            .map(|(_, filter)| filter)
+            .collect();
+        let query = Query::parse(expression, &filters)?;
+
+        let mut watches = self.watches.write();
+        if watches.iter().any(|watch| watch.expression() == expression) {
+            return Err(LogAnalyzerError::WatchAlreadyExists(expression.to_string()));
+        }
+        watches.push(WatchCounter::new(expression, query, current_second()));
+        Ok(())
+    }
+
+    fn remove_watch(&self, expression: &str) -> Result<()> {
+        let mut watches = self.watches.write();
+        match watches.iter().position(|watch| watch.expression() == expression) {
+            Some(position) => {
+                watches.remove(position);
+                Ok(())
+            }
+            None => Err(LogAnalyzerError::WatchNotFound(expression.to_string())),
+        }
+    }
+
+    fn get_watches(&self) -> Vec<Watch> {
+        let second = current_second();
+        self.watches.read().iter().map(|watch| watch.watch(second)).collect()
+    }
+
+    fn set_transforms(&self, transforms: Vec<Transform>) -> Result<()> {
+        let transformer = Transformer::new(&transforms)?;
+        *self.transforms.write() = (transforms, Arc::new(transformer));
+        if self.log_store.get_total_lines() > 0 {
+            self.refilter();
+        }
+        Ok(())
+    }
+
+    fn get_transforms(&self) -> Vec<Transform> {
+        self.transforms.read().0.clone()
+    }
+
+    fn set_redactions(&self, redactions: Vec<Redaction>) -> Result<()> {
+        let redactor = Redactor::new(&redactions)?;
+        *self.redactions.write() = (redactions, Arc::new(redactor));
+        if self.log_store.get_total_lines() > 0 && !self.show_originals.load(Ordering::Relaxed) {
+            self.refilter();
+        }
+        Ok(())
+    }
+
+    fn get_redactions(&self) -> Vec<Redaction> {
+        self.redactions.read().0.clone()
+    }
+
+    fn set_show_originals(&self, show: bool) {
+        let changed = self.show_originals.swap(show, Ordering::Relaxed) != show;
+        if changed && !self.redactions.read().0.is_empty() && self.log_store.get_total_lines() > 0 {
+            self.refilter();
+        }
+    }
+
+    fn is_showing_originals(&self) -> bool {
+        self.show_originals.load(Ordering::Relaxed)
+    }
+
+    fn export_filtered(&self, path: &str, format: ExportFormat) -> Result<usize> {
+        let total = self.analysis_store.get_total_filtered_lines();
+        export_to_file(path, format, "Filtered log", total, |from, to| {
+            self.analysis_store
+                .get_log_lines(from, to)
+                .iter()
+                .map(|line| LogLineStyled::from(&**line))
+                .collect()
+        })
+    }
+
+    fn export_search(&self, path: &str, format: ExportFormat) -> Result<usize> {
+        let title = match self.analysis_store.get_search_query() {
+            Some(query) => format!("Search results for {}", query),
+            None => "Search results".to_string(),
+        };
+        let total = self.analysis_store.get_total_searched_lines();
+        export_to_file(path, format, &title, total, |from, to| {
+            self.get_search_lines(from, to)
+        })
+    }
+
+    fn save_snapshot(&self, path: &str, session: Session) -> Result<usize> {
+        let failed = |err: std::io::Error| LogAnalyzerError::SnapshotFailed {
+            path: path.to_string(),
+            reason: err.to_string(),
+        };
+        // The lines are not processed meanwhile, so the stores saved agree with each other
+        let _batch_lock = self.batch_lock.lock();
+        let lines_per_source = self.log_store.get_lines_per_source();
+        let sources: Vec<SnapshotSource> = self
+            .log_store
+            .get_logs()
+            .into_iter()
+            .filter_map(|(enabled, address, format)| {
+                let source_type = self.log_store.get_source(&address)?.get_type().into();
+                let lines = lines_per_source
+                    .iter()
+                    .find(|(id, _)| *id == address)
+                    .map(|(_, lines)| *lines)
+                    .unwrap_or_default();
+                Some(SnapshotSource {
+                    source: SessionSource {
+                        source_type,
+                        address,
+                        format,
+                        enabled,
+                    },
+                    lines,
+                })
+            })
+            .collect();
+        let search_complete = self.search_complete.load(Ordering::Relaxed);
+        let header = SnapshotHeader {
+            session,
+            sources,
+            filtered_lines: self.analysis_store.get_total_filtered_lines(),
+            searched_lines: match search_complete {
+                true => self.analysis_store.get_total_searched_lines(),
+                false => 0,
+            },
+            search_complete,
+            rate: self.analysis_store.get_rate(),
+            ..Default::default()
+        };
+
+        let file = BufWriter::new(File::create(path).map_err(failed)?);
+        let mut writer = SnapshotWriter::new(FrameEncoder::new(file), &header).map_err(failed)?;
+        // The raw lines are saved as shown, so the redacted values don't leak through the file
+        let redactor = self.active_redactor();
+        for SnapshotSource { source, lines } in &header.sources {
+            for from in (0..*lines).step_by(SNAPSHOT_BATCH_SIZE) {
+                let to = (from + SNAPSHOT_BATCH_SIZE).min(*lines);
+                let mut batch = self.log_store.get_lines_range(&source.address, from..to);
+                if let Some(redactor) = &redactor {
+                    for line in &mut batch {
+                        if let Cow::Owned(redacted) = redactor.redact_text(line) {
+                            *line = redacted;
+                        }
+                    }
+                }
+                writer.write(&batch).map_err(failed)?;
+            }
+        }
+        for from in (0..header.filtered_lines).step_by(SNAPSHOT_BATCH_SIZE) {
+            let batch = self
+                .analysis_store
+                .get_log_lines(from, from + SNAPSHOT_BATCH_SIZE);
+            writer.write(&batch).map_err(failed)?;
+        }
+        for from in (0..header.searched_lines).step_by(SNAPSHOT_BATCH_SIZE) {
+            let batch = self
+                .analysis_store
+                .get_search_highlights(from, from + SNAPSHOT_BATCH_SIZE);
+            writer.write(&batch).map_err(failed)?;
+        }
+        let mut file = writer
+            .finish()
+            .and_then(|encoder| encoder.finish().map_err(std::io::Error::from))
+            .map_err(failed)?;
+        file.flush().map_err(failed)?;
+
+        Ok(header.sources.iter().map(|source| source.lines).sum())
+    }
+
+    fn load_snapshot(&self, path: &str) -> Result<Session> {
+        let failed = |err: std::io::Error| LogAnalyzerError::SnapshotFailed {
+            path: path.to_string(),
+            reason: err.to_string(),
+        };
+        let file = BufReader::new(File::open(path).map_err(failed)?);
+        let (mut reader, header) = SnapshotReader::new(FrameDecoder::new(file)).map_err(failed)?;
+
+        self.clear_all();
+        let batch_lock = self.batch_lock.lock();
+        for SnapshotSource { source, lines } in &header.sources {
+            let source_type = SourceType::try_from(source.source_type)
+                .map_err(|_| LogAnalyzerError::UnknownSourceType(source.source_type))?;
+            let restored = RestoredSource::new(source.address.clone(), source_type);
+            let restored: Arc<Box<dyn LogSource + Send + Sync>> = Arc::new(Box::new(restored));
+            self.log_store
+                .add_log(&source.address, restored, source.format.as_ref(), source.enabled);
+            reader
+                .read_batches(*lines, |batch: Vec<String>| {
+                    let batch: Vec<&str> = batch.iter().map(String::as_str).collect();
+                    self.log_store.add_lines(&source.address, &batch);
+                })
+                .map_err(failed)?;
+        }
+
+        let staged = InMemmoryAnalysisStore::new();
+        let analysis = reader
+            .read_batches(header.filtered_lines, |batch: Vec<LogLine>| {
+                staged.add_lines(&batch);
+            })
+            .and_then(|_| {
+                reader.read_batches(header.searched_lines, |batch| staged.add_search_lines(&batch))
+            });
+        for (second, raw, filtered) in &header.rate {
+            staged.add_line_times(&vec![*second; *raw], &vec![*second; *filtered]);
+        }
+        self.analysis_store.replace_log(staged);
+        if let Some(query) = &header.session.search {
+            self.analysis_store.add_search_query(query);
+        }
+        self.search_complete
+            .store(header.search_complete && analysis.is_ok(), Ordering::Relaxed);
+        self.log_version.fetch_add(1, Ordering::Relaxed);
+        if let Some(detector) = self.burst_detector.lock().as_mut() {
+            detector.add_lines(&self.analysis_store.fetch_log());
+        }
+        drop(batch_lock);
+
+        // The restored lines are already filtered and searched with the session, unless the
+        // search was still running when saved
+        self.reopened.store(true, Ordering::Relaxed);
+        let restored = self.restore_session(&header.session);
+        self.event_channel
+            .send(Event::FilterFinished)
+            .unwrap_or_default();
+        analysis.map_err(failed)?;
+        restored?;
+
+        Ok(header.session)
+    }
+
+    fn resolve_raw_index(&self, source: Option<&str>, raw_index: usize) -> Option<usize> {
+        self.analysis_store.find_raw_line(source, raw_index)
+    }
+
+    fn set_viewport(&self, source: &str, raw_index: usize) {
+        *self.viewport.lock() = Some((source.to_string(), raw_index));
+    }
+
+    fn get_search_lines_containing(
+        &self,
+        index: usize,
+        elements: usize,
+    ) -> (Vec<LogLineStyled>, usize, usize) {
+        self.analysis_store
+            .get_search_lines_containing(index, elements)
     }
 
     fn get_logs(&self) -> Vec<(bool, String, Option<String>)> {
         self.log_store.get_logs()
     }
 
+    fn get_source_plugins(&self) -> Vec<String> {
+        #[cfg(feature = "plugins")]
+        return self.plugins.read().names();
+        #[cfg(not(feature = "plugins"))]
+        Vec::new()
+    }
+
     fn get_formats(&self) -> Vec<Format> {
         self.processing_store.get_formats()
     }
@@ -428,6 +2327,69 @@ impl LogAnalyzer for LogService {
         self.analysis_store.get_total_searched_lines()
     }
 
+    fn get_filtered_lines_per_source(&self) -> Vec<(String, usize)> {
+        self.analysis_store.get_lines_per_source()
+    }
+
+    fn get_source_stats(&self) -> Vec<SourceStats> {
+        let raw: HashMap<String, usize> = self.log_store.get_lines_per_source().into_iter().collect();
+        let filtered: HashMap<String, usize> =
+            self.analysis_store.get_lines_per_source().into_iter().collect();
+        let search: HashMap<String, usize> = self
+            .analysis_store
+            .get_search_lines_per_source()
+            .into_iter()
+            .collect();
+
+        let mut stats: Vec<SourceStats> = self
+            .log_store
+            .get_logs()
+            .into_iter()
+            .map(|(_, source, _)| {
+                let lag = self.source_queue.lag(&source);
+                SourceStats {
+                    raw: raw.get(&source).copied().unwrap_or_default(),
+                    filtered: filtered.get(&source).copied().unwrap_or_default(),
+                    search: search.get(&source).copied().unwrap_or_default(),
+                    dropped: lag.dropped,
+                    deferred: lag.deferred,
+                    lagging: lag.lagging,
+                    error: self.source_errors.lock().get(&source).cloned(),
+                    source,
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| a.source.cmp(&b.source));
+        stats
+    }
+
+    fn get_source_log_lines(&self, source: &str, limit: usize) -> Vec<LogLine> {
+        self.analysis_store.get_source_lines(source, limit)
+    }
+
+    fn get_raw_line(&self, source: &str, raw_index: usize) -> Option<String> {
+        let line = self
+            .log_store
+            .get_lines_range(source, raw_index..raw_index + 1)
+            .pop()?;
+        match self.active_redactor() {
+            Some(redactor) => Some(redactor.redact_text(&line).into_owned()),
+            None => Some(line),
+        }
+    }
+
+    fn get_memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            raw: self.log_store.get_memory_usage(),
+            filtered: self.analysis_store.get_log_memory_usage(),
+            search: self.analysis_store.get_search_memory_usage(),
+        }
+    }
+
+    fn get_metrics(&self) -> PipelineMetrics {
+        *self.metrics.read()
+    }
+
     fn toggle_source(&self, id: &str) {
         if let Some((enabled, _log, _format)) = self
             .log_store
@@ -441,7 +2403,7 @@ impl LogAnalyzer for LogService {
                 if enabled {
                     source.stop();
                 } else {
-                    self.run_log_source(source);
+                    self.run_log_source(source, self.jobs.current(Job::Source(id.to_string())));
                 }
             }
         }
@@ -449,13 +2411,395 @@ impl LogAnalyzer for LogService {
 
     fn toggle_filter(&self, id: &str) {
         self.processing_store.toggle_filter(id);
+        self.filters_changed();
+        self.refilter();
+    }
+
+    fn toggle_severity(&self, severity: Severity) {
+        self.processing_store.toggle_severity(severity);
+        self.refilter();
+    }
 
-        // Reset everything because we need to recompute the log from the raw lines
-        self.analysis_store.reset_log();
-        self.analysis_store.reset_search();
+    fn get_hidden_severities(&self) -> Vec<Severity> {
+        self.processing_store.get_hidden_severities()
+    }
+
+    fn add_column_filter(&self, column_filter: ColumnFilter) {
+        self.processing_store.add_column_filter(column_filter);
+        self.refilter();
+    }
 
-        let mut receiver = self.event_channel.subscribe();
+    fn remove_column_filter(&self, column: &str) {
+        self.processing_store.remove_column_filter(column);
+        self.refilter();
+    }
+
+    fn get_column_filters(&self) -> Vec<ColumnFilter> {
+        self.processing_store.get_column_filters()
+    }
 
+    fn get_session(&self) -> Session {
+        let sources = self
+            .log_store
+            .get_logs()
+            .into_iter()
+            .filter_map(|(enabled, address, format)| {
+                let source = self.log_store.get_source(&address)?;
+                // Piped input can't be read again
+                if source.get_type() == SourceType::STDIN {
+                    return None;
+                }
+                Some(SessionSource {
+                    source_type: source.get_type().into(),
+                    address,
+                    format,
+                    enabled,
+                })
+            })
+            .collect();
+
+        let filters = self
+            .processing_store
+            .get_filters()
+            .into_iter()
+            .map(|(enabled, filter)| SessionFilter {
+                alert: self.alerts.read().get(&filter.alias).cloned(),
+                sink: self.sinks.read().get(&filter.alias).cloned(),
+                enabled,
+                filter,
+            })
+            .collect();
+
+        Session {
+            sources,
+            formats: self.processing_store.get_formats(),
+            filters,
+            search: self.analysis_store.get_search_query(),
+            column_filters: self.processing_store.get_column_filters(),
+            hidden_severities: self.processing_store.get_hidden_severities(),
+            ..Default::default()
+        }
+    }
+
+    fn restore_session(&self, session: &Session) -> Result<()> {
+        let mut result = Ok(());
+        for format in &session.formats {
+            result = result.and(self.add_format(&format.alias, &format.regex));
+        }
+
+        for SessionFilter {
+            enabled,
+            filter,
+            alert,
+            sink,
+        } in &session.filters
+        {
+            let sink = match sink.as_ref().map(Sink::validate) {
+                Some(Err(err)) => {
+                    result = result.and(Err(LogAnalyzerError::InvalidSink(err)));
+                    None
+                }
+                _ => sink.as_ref(),
+            };
+            self.processing_store.add_filter(
+                filter.alias.clone(),
+                filter.filter.clone(),
+                filter.action,
+                *enabled,
+            );
+            self.filters_changed();
+            if let Some(alert) = alert {
+                self.alerts.write().insert(filter.alias.clone(), alert.clone());
+            }
+            if let Some(sink) = sink {
+                self.sinks.write().insert(filter.alias.clone(), sink.clone());
+            }
+        }
+
+        for column_filter in &session.column_filters {
+            self.processing_store.add_column_filter(column_filter.clone());
+        }
+        let hidden_severities = self.processing_store.get_hidden_severities();
+        for severity in &session.hidden_severities {
+            if !hidden_severities.contains(severity) {
+                self.processing_store.toggle_severity(*severity);
+            }
+        }
+
+        // Lines already loaded are filtered again with the restored filters, unless they were
+        // reopened already filtered
+        let has_filters = !session.filters.is_empty()
+            || !session.column_filters.is_empty()
+            || !session.hidden_severities.is_empty();
+        let reopened = self.reopened.swap(false, Ordering::Relaxed);
+        if has_filters && self.log_store.get_total_lines() > 0 && !reopened {
+            self.refilter();
+        }
+
+        // The sources of a reopened analysis are not read again
+        let loaded: Vec<String> = self
+            .log_store
+            .get_logs()
+            .into_iter()
+            .map(|(_, id, _)| id)
+            .collect();
+        let sources = session
+            .sources
+            .iter()
+            .filter(|source| !loaded.contains(&source.address));
+        for source in sources {
+            match self.add_log(source.source_type, &source.address, source.format.as_ref()) {
+                Ok(()) if !source.enabled => self.toggle_source(&source.address),
+                Ok(()) => {}
+                Err(err) => result = result.and(Err(err)),
+            }
+        }
+
+        if let Some(search) = &session.search {
+            self.add_search(search);
+        }
+
+        result
+    }
+
+    fn clear_all(&self) {
+        // A running refilter would swap the cleared lines back in
+        self.jobs.cancel_all();
+        let _batch_lock = self.batch_lock.lock();
+        self.log_version.fetch_add(1, Ordering::Relaxed);
+        self.pending_events.lock().take();
+        for (_, id, _) in self.log_store.get_logs() {
+            if let Some(source) = self.log_store.get_source(&id) {
+                source.stop();
+            }
+        }
+
+        self.log_store.clear();
+        self.processing_store.clear();
+        self.filters_changed();
+        self.analysis_store.clear();
+        self.source_queue.clear_lag();
+        self.source_errors.lock().clear();
+        if let Some(dedupe) = self.dedupe.lock().as_mut() {
+            dedupe.reset();
+        }
+        if let Some(detector) = self.burst_detector.lock().as_mut() {
+            *detector = BurstDetector::new(detector.config());
+        }
+        self.alerts.write().clear();
+        self.live_positions.lock().clear();
+        self.received_lines.lock().clear();
+        self.caught_up.lock().clear();
+        self.last_alerts.lock().clear();
+        self.sinks.write().clear();
+        self.watches.write().clear();
+        *self.viewport.lock() = None;
+
+        // Let the views drop the lines they display
+        self.event_channel
+            .send(Event::Filtering)
+            .unwrap_or_default();
+        self.event_channel
+            .send(Event::FilterFinished)
+            .unwrap_or_default();
+    }
+
+    fn on_event(&self) -> broadcast::Receiver<Event> {
+        self.event_channel.subscribe()
+    }
+}
+
+impl LogService {
+    /// Recompute the filtered and search logs from the raw lines, in the background. The
+    /// current logs are displayed until replaced by the new ones
+    fn refilter(&self) {
+        self.reopened.store(false, Ordering::Relaxed);
+        let token = self.jobs.start(Job::Refilter);
+        self.refilter_sender.send(token).unwrap_or_default();
+    }
+
+    /// Filter the raw lines of the enabled logs into a staged store, left in place in the log
+    /// store, then swap it in. The lines around the viewport are swapped in first as a preview
+    /// on large logs. Gives up if a newer refilter is requested meanwhile
+    fn run_refilter(&self, token: &CancellationToken) {
+        if token.is_cancelled() {
+            return;
+        }
+        let span = debug_span!("refilter", lines = field::Empty);
+        let _span = span.enter();
+        self.event_channel
+            .send(Event::Filtering)
+            .unwrap_or_default();
+        if let Some(dedupe) = self.dedupe.lock().as_mut() {
+            dedupe.reset();
+        }
+        if !self.refilter_preview(token) {
+            return;
+        }
+        // The lines of the preview are filtered again, in the order of the sources
+        if let Some(dedupe) = self.dedupe.lock().as_mut() {
+            dedupe.reset();
+        }
+
+        let staged = InMemmoryAnalysisStore::new();
+        let query = self.analysis_store.get_search_query();
+        let mut positions = HashMap::new();
+        if !self.refilter_pending(&staged, &mut positions, token) {
+            return;
+        }
+
+        // The lines stored meanwhile are filtered while the pipeline waits
+        let _batch_lock = self.batch_lock.lock();
+        if !self.refilter_pending(&staged, &mut positions, token) {
+            return;
+        }
+        // The batches were searched with the query of their time, searched again if it changed
+        let current_query = self.analysis_store.get_search_query();
+        if current_query != query {
+            staged.reset_search();
+            let search_regex = current_query.and_then(|query| self.regex_cache.get(&query).ok());
+            if let Some(search_regex) = search_regex {
+                let search_lines = matching_lines(&search_regex, staged.fetch_log().iter());
+                staged.add_search_lines(&search_lines);
+            }
+        }
+        span.record("lines", staged.get_total_filtered_lines());
+
+        self.analysis_store.replace_log(staged);
+        self.log_version.fetch_add(1, Ordering::Relaxed);
+        if let Some(detector) = self.burst_detector.lock().as_mut() {
+            detector.reset_counts();
+            detector.add_lines(&self.analysis_store.fetch_log());
+        }
+        self.event_channel
+            .send(Event::FilterFinished)
+            .unwrap_or_default();
+    }
+
+    /// Search the processed lines with `query`, only the current results if it is a `refinement`
+    /// of the previous query, then the lines processed meanwhile, and swap the results in.
+    /// Gives up if a newer search is requested meanwhile
+    fn run_search(&self, token: &CancellationToken, query: &str, refinement: bool) {
+        let search_regex = match self.regex_cache.get(query) {
+            Ok(search_regex) => search_regex,
+            Err(_) => return,
+        };
+        let (lines, searched, log_version) = {
+            let _batch_lock = self.batch_lock.lock();
+            let log = self.analysis_store.fetch_log();
+            let lines = match refinement {
+                true => self.analysis_store.fetch_search().clone(),
+                false => log.clone(),
+            };
+            (lines, log.len(), self.log_version.load(Ordering::Relaxed))
+        };
+        let span = debug_span!(
+            "search",
+            regex = %query,
+            lines = lines.len(),
+            refinement,
+            matches = field::Empty
+        );
+        let _span = span.enter();
+        if searched > 0 {
+            self.event_channel
+                .send(Event::Searching)
+                .unwrap_or_default();
+        }
+
+        // Refilters and searches go through every line at once, like a bulk load
+        let chunk_size = self.chunk_size(lines.len(), usize::MAX);
+        let searched_chunks = self.pool.map_chunks(&lines, chunk_size, |chunk| {
+            catch_unwind(AssertUnwindSafe(|| {
+                #[cfg(test)]
+                chunk.iter().for_each(|line| self.panic_on(&line.payload));
+                matching_lines(&search_regex, chunk)
+            }))
+            .map_err(|panic| {
+                let (first, last) = (&chunk[0], &chunk[chunk.len() - 1]);
+                (first.log.clone(), (first.index, last.index), panic)
+            })
+        });
+        let mut search_lines: Vec<(LogLine, Vec<Highlight>)> =
+            match searched_chunks.into_iter().collect::<std::result::Result<Vec<_>, _>>() {
+                Ok(chunks) => chunks.into_iter().flatten().collect(),
+                // The search log is left incomplete, searched again with the next request
+                Err((source, lines, panic)) => {
+                    self.report_panic(&source, "searched", lines, panic.as_ref());
+                    if searched > 0 {
+                        self.event_channel
+                            .send(Event::SearchFinished)
+                            .unwrap_or_default();
+                    }
+                    return;
+                }
+            };
+
+        let batch_lock = self.batch_lock.lock();
+        if token.is_cancelled() {
+            if searched > 0 {
+                self.event_channel
+                    .send(Event::SearchFinished)
+                    .unwrap_or_default();
+            }
+            return;
+        }
+        // A refilter searched the log it swapped in with the query, a cleared log has no query
+        if self.log_version.load(Ordering::Relaxed) == log_version {
+            // Taken as stored, like the lines searched before
+            let log = self.analysis_store.fetch_log();
+            search_lines.extend(matching_lines(&search_regex, &log[searched.min(log.len())..]));
+            drop(log);
+            let staged = InMemmoryAnalysisStore::new();
+            staged.add_search_lines(&search_lines);
+            self.analysis_store.replace_search(staged);
+        }
+        self.search_complete.store(true, Ordering::Relaxed);
+        drop(batch_lock);
+
+        if searched > 0 {
+            let matches = self.analysis_store.get_total_searched_lines();
+            span.record("matches", matches);
+            let notification = Notification::new(
+                NotificationLevel::Info,
+                &format!("Search \"{}\" finished with {} matches", query, matches),
+            );
+            self.event_channel
+                .send(Event::Notification(notification))
+                .unwrap_or_default();
+            self.event_channel
+                .send(Event::SearchFinished)
+                .unwrap_or_default();
+        }
+    }
+
+    /// Filter into `staged` the raw lines of the enabled logs past their `positions`, in batches
+    ///
+    /// Returns `false` if the refilter was cancelled meanwhile
+    fn refilter_pending(
+        &self,
+        staged: &InMemmoryAnalysisStore,
+        positions: &mut HashMap<String, usize>,
+        token: &CancellationToken,
+    ) -> bool {
+        for (log, lines) in self.enabled_lines_per_source() {
+            let format = self.log_store.get_format(&log);
+            let position = positions.entry(log.clone()).or_default();
+            while *position < lines {
+                if token.is_cancelled() {
+                    return false;
+                }
+                let end = lines.min(*position + REFILTER_BATCH_SIZE);
+                let batch = self.log_store.get_lines_range(&log, *position..end);
+                self.refilter_batch(staged, &format, &log, *position, batch, token);
+                *position = end;
+            }
+        }
+        true
+    }
+
+    /// Raw lines stored for each enabled source
+    fn enabled_lines_per_source(&self) -> Vec<(String, usize)> {
         let enabled_logs: Vec<String> = self
             .log_store
             .get_logs()
@@ -464,37 +2808,1000 @@ impl LogAnalyzer for LogService {
             .map(|(_, id, _)| id)
             .collect();
 
-        let log_store = self.log_store.clone();
-        let sender = self.log_sender.clone();
-        let event_sender = self.event_channel.clone();
+        self.log_store
+            .get_lines_per_source()
+            .into_iter()
+            .filter(|(log, _)| enabled_logs.contains(log))
+            .collect()
+    }
 
-        std::thread::Builder::new()
-            .name("Toggle filter".to_string())
-            .spawn(move || {
-                for log in enabled_logs {
-                    let lines = log_store.extract_lines(&log);
+    /// Filter the lines around the viewport and the tails of the followed sources, then swap
+    /// them in so the view is usable before the whole log is refiltered. The lines filtered
+    /// meanwhile are appended to the preview until the refilter is done.
+    /// Returns `false` if the refilter was cancelled
+    fn refilter_preview(&self, token: &CancellationToken) -> bool {
+        let viewport = self.viewport.lock().clone();
+        let ranges = priority_ranges(
+            &self.enabled_lines_per_source(),
+            viewport.as_ref(),
+            self.follow.load(Ordering::Relaxed),
+            REFILTER_BATCH_SIZE,
+        );
+        if ranges.is_empty() {
+            return true;
+        }
 
-                    if lines.is_empty() {
-                        event_sender.send(Event::FilterFinished).unwrap();
-                        continue;
-                    }
+        let preview = InMemmoryAnalysisStore::new();
+        for (log, range) in ranges {
+            if token.is_cancelled() {
+                return false;
+            }
+            let format = self.log_store.get_format(&log);
+            let lines = self.log_store.get_lines_range(&log, range.clone());
+            self.refilter_batch(&preview, &format, &log, range.start, lines, token);
+        }
 
-                    event_sender.send(Event::Filtering).unwrap();
-                    sender.send((log.clone(), lines.to_vec())).unwrap();
+        let _batch_lock = self.batch_lock.lock();
+        if token.is_cancelled() {
+            return false;
+        }
+        debug!(lines = preview.get_total_filtered_lines(), "Refilter preview swapped in");
+        self.analysis_store.replace_log(preview);
+        self.log_version.fetch_add(1, Ordering::Relaxed);
+        self.event_channel
+            .send(Event::FilterPreview)
+            .unwrap_or_default();
+        true
+    }
 
-                    while !matches!(
-                        async_std::task::block_on(receiver.recv()).unwrap_or(Event::Filtering),
-                        Event::NewLines(_, last) if last == (lines.len() - 1)
-                    ) {
-                        std::thread::sleep(Duration::from_millis(100));
-                    }
-                    event_sender.send(Event::FilterFinished).unwrap();
+    /// Format, filter and search the raw `lines` of `path`, starting at the raw index `first`,
+    /// into `staged`. A chunk panicking on a line cancels the refilter of `token`, the filtered
+    /// log being left as it was
+    fn refilter_batch(
+        &self,
+        staged: &InMemmoryAnalysisStore,
+        format: &Option<String>,
+        path: &str,
+        first: usize,
+        lines: Vec<String>,
+        token: &CancellationToken,
+    ) {
+        let counters = &self.counters;
+        // Refilters and searches go through every line at once, like a bulk load
+        let chunk_size = self.chunk_size(lines.len(), usize::MAX);
+        let elements: Vec<(&str, usize)> = lines.iter().map(String::as_str).zip(first..).collect();
+
+        let processed = self.pool.map_chunks(&elements, chunk_size, |chunk| {
+            counters.active_workers.fetch_add(1, Ordering::Relaxed);
+            let processed = catch_unwind(AssertUnwindSafe(|| {
+                let source = SmolStr::from(path);
+                let lines =
+                    timed(&counters.format_us, || self.apply_format(format, &source, chunk));
+                let FilteredChunk { filtered, raw_times, .. } =
+                    timed(&counters.filter_us, || self.apply_filters(lines, None));
+                let (filtered, search) =
+                    timed(&counters.search_us, || self.apply_search(filtered));
+                (filtered, search, raw_times)
+            }));
+            counters.active_workers.fetch_sub(1, Ordering::Relaxed);
+            counters
+                .processed
+                .fetch_add(chunk.len() as u64, Ordering::Relaxed);
+            processed
+        });
+        let processed: Vec<(Vec<LogLine>, SearchPositions, Vec<i64>)> =
+            match processed.into_iter().collect::<std::result::Result<_, _>>() {
+                Ok(processed) => processed,
+                Err(panic) => {
+                    token.cancel();
+                    let lines = (first, first + elements.len() - 1);
+                    self.report_panic(path, "refiltered", lines, panic.as_ref());
+                    self.event_channel
+                        .send(Event::FilterFinished)
+                        .unwrap_or_default();
+                    return;
                 }
+            };
+
+        for (filtered, search, raw_times) in processed {
+            staged.add_line_times(&raw_times, &line_seconds(&filtered));
+            let indexes = staged.add_lines(&filtered);
+            staged.add_search_lines(&search_lines(&filtered, search, indexes));
+        }
+    }
+
+    /// Notify the `lines` (first, last) of `source` that could not be `action` as a worker
+    /// panicked on them
+    fn report_panic(
+        &self,
+        source: &str,
+        action: &str,
+        lines: (usize, usize),
+        panic: &(dyn Any + Send),
+    ) {
+        let message = panic_message(panic);
+        error!(source = %source, panic = %message, "Lines not {}", action);
+        self.event_channel
+            .send(Event::SourceError {
+                source: source.to_string(),
+                error: format!(
+                    "Lines {} to {} could not be {}: {}",
+                    lines.0, lines.1, action, message
+                ),
             })
+            .unwrap_or_default();
+    }
+
+    /// Panic on a `line` holding the text of `panic_on`, if set
+    #[cfg(test)]
+    fn panic_on(&self, line: &str) {
+        if let Some(text) = self.panic_on.read().as_deref() {
+            if line.contains(text) {
+                panic!("{} can't be handled", line);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::alert::AlertAction;
+    use crate::models::filter::FilterAction;
+    use crate::services::builder::LogAnalyzerBuilder;
+    use std::io::Write;
+    use test_utils::{tempdir, wait_until, DEADLINE};
+
+    /// Next event of the service, failing the test if none comes
+    fn next_event(events: &mut broadcast::Receiver<Event>) -> Event {
+        runtime::block_on(runtime::timeout(DEADLINE, events.recv()))
+            .expect("No event received")
+            .unwrap()
+    }
+
+    fn wait_event(events: &mut broadcast::Receiver<Event>, event: Event) {
+        while next_event(events) != event {}
+    }
+
+    /// Wait until `lines` are processed and stored by the consumer thread
+    fn wait_processed(analyzer: &LogService, lines: u64) {
+        wait_until(DEADLINE, || analyzer.counters.processed.load(Ordering::Relaxed) >= lines);
+        // The lines of the batch processed are stored before its lock is released
+        drop(analyzer.batch_lock.lock());
+    }
+
+    #[test]
+    fn dropping_the_service_stops_its_threads() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("followed.log");
+        std::fs::write(&path, "INFO started\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).build();
+        analyzer.add_log(0, &source, None).unwrap();
+        analyzer.add_search("started");
+        analyzer.refilter();
+        wait_until(DEADLINE, || analyzer.live_position(&source).is_some());
+        let threads = std::mem::take(&mut *analyzer.threads.lock());
+        assert_eq!(threads.len(), 8);
+
+        // The followed file keeps its source running until the service stops it
+        drop(analyzer);
+        wait_until(DEADLINE, || threads.iter().all(|thread| thread.is_finished()));
+    }
+
+    #[test]
+    fn metrics_report_the_processed_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("metrics.log");
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(file, "INFO started\nERROR failed\nINFO stopped").unwrap();
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).follow(false).build();
+        let mut events = analyzer.on_event();
+        analyzer.add_log(0, path.to_str().unwrap(), None).unwrap();
+
+        // The lines can be read and processed in different periods
+        let mut ingested = 0;
+        let metrics = loop {
+            if let Event::Metrics(metrics) = next_event(&mut events) {
+                ingested += metrics.ingested_per_sec;
+                if metrics.processed_per_sec > 0 {
+                    break metrics;
+                }
+            }
+        };
+        assert!(ingested > 0);
+        assert_eq!(metrics.workers, 1);
+        assert_eq!(analyzer.get_metrics(), metrics);
+    }
+
+    #[test]
+    fn sources_report_their_end_and_errors() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("events.log");
+        std::fs::write(&path, "INFO started\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+
+        let retry = Backoff {
+            initial: Duration::from_millis(10),
+            max: Duration::from_millis(20),
+        };
+        let analyzer = LogAnalyzerBuilder::new()
+            .workers(1)
+            .follow(false)
+            .file_retry(retry)
+            .build();
+        let mut events = analyzer.on_event();
+        analyzer.add_log(0, &source, None).unwrap();
+        let mut next_source_event = || loop {
+            match next_event(&mut events) {
+                event @ (Event::SourceEof { .. }
+                | Event::SourceError { .. }
+                | Event::SourceResumed { .. }) => break event,
+                _ => continue,
+            }
+        };
+
+        let eof = next_source_event();
+        assert_eq!(eof, Event::SourceEof { source: source.clone() });
+        let job = Job::Source(source.clone());
+        wait_until(DEADLINE, || !analyzer.jobs.is_running(&job));
+        assert_eq!(
+            eof.notification().unwrap().message,
+            format!("Finished reading {}", source)
+        );
+
+        let wait_raw_lines = |lines: usize| {
+            wait_until(DEADLINE, || analyzer.get_total_raw_lines() >= lines);
+        };
+        wait_raw_lines(1);
+
+        // A followed file removed while being read is retried until it is back
+        analyzer.set_follow(true);
+        analyzer.clear_all();
+        analyzer.add_log(0, &source, None).unwrap();
+        wait_raw_lines(1);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            next_source_event(),
+            Event::SourceError { source: error_source, .. } if error_source == source
+        ));
+        assert!(analyzer.get_source_stats()[0].error.is_some());
+
+        // Moved in place at once, as the file would be seen empty while being written
+        let restored = path.with_extension("restored");
+        std::fs::write(&restored, "INFO started\nINFO back\n").unwrap();
+        std::fs::rename(&restored, &path).unwrap();
+        assert_eq!(next_source_event(), Event::SourceResumed { source: source.clone() });
+        assert_eq!(analyzer.get_source_stats()[0].error, None);
+        // The lines read before the removal are not read again, the next line follows them
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "INFO next").unwrap();
+        wait_raw_lines(3);
+        assert_eq!(analyzer.get_total_raw_lines(), 3);
+        assert_eq!(analyzer.get_raw_line(&source, 1).unwrap(), "INFO back");
+        assert_eq!(analyzer.get_raw_line(&source, 2).unwrap(), "INFO next");
+    }
+
+    #[test]
+    fn plugin_sources_need_a_plugin_named_after_their_scheme() {
+        let analyzer = LogAnalyzerBuilder::new().workers(1).build();
+        assert!(analyzer.get_source_plugins().is_empty());
+
+        let err = analyzer
+            .add_log(SourceType::PLUGIN.into(), "kafka://broker/topic", None)
+            .unwrap_err();
+        assert!(matches!(err, LogAnalyzerError::SourceUnavailable { .. }));
+        assert!(err.to_string().contains("No plugin reads kafka://broker/topic"));
+        assert!(analyzer.get_logs().is_empty());
+    }
+
+    #[test]
+    fn source_stats_count_every_stage() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("stats.log");
+        std::fs::write(&path, "INFO started\nERROR failed\nINFO stopped\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).follow(false).build();
+        let mut events = analyzer.on_event();
+        analyzer.add_log(0, &source, None).unwrap();
+        wait_until(DEADLINE, || analyzer.get_total_filtered_lines() >= 3);
+        analyzer.add_search("ERROR");
+        wait_event(&mut events, Event::SearchFinished);
+
+        assert_eq!(
+            analyzer.get_source_stats(),
+            vec![SourceStats {
+                source,
+                raw: 3,
+                filtered: 3,
+                search: 1,
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn filtered_lines_and_search_results_are_exported() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("export.log");
+        std::fs::write(&path, "INFO started\nDEBUG noise\nERROR failed\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+        let output = dir.path().join("export.ndjson");
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).follow(false).build();
+        let mut events = analyzer.on_event();
+        analyzer.add_filter(Filter {
+            alias: "No debug".to_string(),
+            action: FilterAction::EXCLUDE,
+            filter: LogLine {
+                payload: "DEBUG".into(),
+                ..Default::default()
+            },
+        });
+        analyzer.toggle_filter("No debug");
+        analyzer.add_log(0, &source, None).unwrap();
+        wait_until(DEADLINE, || analyzer.get_total_filtered_lines() >= 2);
+
+        let exported = analyzer.export_filtered(output.to_str().unwrap(), ExportFormat::Ndjson);
+        assert_eq!(exported, Ok(2));
+        let lines: Vec<LogLine> = std::fs::read_to_string(&output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines[0].payload, "INFO started");
+        assert_eq!(lines[1].payload, "ERROR failed");
+
+        assert!(matches!(
+            analyzer.export_filtered(dir.path().to_str().unwrap(), ExportFormat::Text),
+            Err(LogAnalyzerError::ExportFailed { .. })
+        ));
+
+        analyzer.add_search("(?P<RED>ERROR)");
+        wait_event(&mut events, Event::SearchFinished);
+        let exported = analyzer.export_search(output.to_str().unwrap(), ExportFormat::Json);
+        assert_eq!(exported, Ok(1));
+        let lines: Vec<LogLineStyled> =
+            serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(lines[0].payload[0], (Some("RED".to_string()), "ERROR".to_string()));
+    }
+
+    #[test]
+    fn refilters_start_around_the_viewport_and_the_tails() {
+        let sources = vec![("a.log".to_string(), 1_000), ("b.log".to_string(), 250)];
+        let viewport = ("a.log".to_string(), 420);
+
+        assert_eq!(
+            priority_ranges(&sources, Some(&viewport), false, 100),
+            vec![("a.log".to_string(), 400..500)]
+        );
+        assert_eq!(
+            priority_ranges(&sources, Some(&viewport), true, 100),
+            vec![
+                ("a.log".to_string(), 400..500),
+                ("a.log".to_string(), 900..1_000),
+                ("b.log".to_string(), 150..250)
+            ]
+        );
+        // The viewport batch reaching the tail is merged with it
+        let viewport = ("a.log".to_string(), 950);
+        assert_eq!(
+            priority_ranges(&sources, Some(&viewport), true, 100),
+            vec![("a.log".to_string(), 900..1_000), ("b.log".to_string(), 150..250)]
+        );
+        // Not worth a preview if most of the lines are prioritized anyway
+        assert!(priority_ranges(&sources, None, true, 500).is_empty());
+        assert!(priority_ranges(&sources, None, false, 100).is_empty());
+    }
+
+    #[test]
+    fn discarded_lines_are_notified() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("retention.log");
+        std::fs::write(&path, "one\ntwo\nthree\nfour\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).build();
+        let mut events = analyzer.on_event();
+        analyzer.set_retention(Some(Retention {
+            max_lines: Some(2),
+            ..Default::default()
+        }));
+        analyzer.add_log(0, &source, None).unwrap();
+        let discarded = loop {
+            if let Event::LinesDiscarded { raw, filtered } = next_event(&mut events) {
+                break (raw, filtered);
+            }
+        };
+        assert_eq!(discarded, (vec![(source.clone(), 2)], 2));
+        let lines = analyzer.get_log_lines(0, 2);
+        assert_eq!((lines[0].index, lines[0].raw_index), (0, 0));
+        assert_eq!(lines[0].payload, "three");
+    }
+
+    #[test]
+    fn toggled_filters_refilter_the_raw_lines_in_place() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("refilter.log");
+        std::fs::write(&path, "INFO started\nDEBUG noise\nERROR failed\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).follow(false).build();
+        let mut events = analyzer.on_event();
+        analyzer.add_filter(Filter {
+            alias: "No debug".to_string(),
+            action: FilterAction::EXCLUDE,
+            filter: LogLine {
+                payload: "DEBUG".into(),
+                ..Default::default()
+            },
+        });
+        analyzer.add_log(0, &source, None).unwrap();
+        wait_until(DEADLINE, || analyzer.get_total_filtered_lines() >= 3);
+        analyzer.add_search("ERROR");
+        wait_event(&mut events, Event::SearchFinished);
+
+        // The lines are displayed until the refiltered ones replace them
+        let previous = analyzer.get_log_lines(0, 3);
+        analyzer.toggle_filter("No debug");
+        wait_event(&mut events, Event::FilterFinished);
+        assert_eq!(previous[1].payload, "DEBUG noise");
+        assert_eq!(analyzer.get_total_raw_lines(), 3);
+        assert_eq!(analyzer.get_total_filtered_lines(), 2);
+        assert_eq!(analyzer.get_log_lines(0, 2)[1].raw_index, 2);
+        assert_eq!(analyzer.get_total_searched_lines(), 1);
+        assert_eq!(analyzer.get_search_lines(0, 1)[0].unformat().index, 1);
+
+        analyzer.toggle_filter("No debug");
+        wait_event(&mut events, Event::FilterFinished);
+        assert_eq!(analyzer.get_total_filtered_lines(), 3);
+    }
+
+    #[test]
+    fn narrower_searches_look_through_the_current_results() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("refine.log");
+        std::fs::write(&path, "ERROR one\nERR two\nINFO three\nERROR four\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).follow(false).build();
+        let mut events = analyzer.on_event();
+        analyzer.add_log(0, &source, None).unwrap();
+        wait_until(DEADLINE, || analyzer.get_total_filtered_lines() >= 4);
+        let mut search = |query: &str| {
+            analyzer.add_search(query);
+            wait_event(&mut events, Event::SearchFinished);
+            analyzer.get_total_searched_lines()
+        };
+
+        assert_eq!(search("ERR"), 3);
+        assert_eq!(search("ERROR"), 2);
+        assert_eq!(analyzer.get_search_lines(0, 2)[1].unformat().index, 3);
+        assert_eq!(search("ERROR f"), 1);
+        assert_eq!(search("ERR"), 3);
+        // The same search is not run again
+        analyzer.add_search("ERR");
+        assert_eq!(analyzer.get_total_searched_lines(), 3);
+    }
+
+    #[test]
+    fn alerts_trigger_on_live_lines_only() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("alerts.log");
+        std::fs::write(&path, "ERROR before\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).follow(true).build();
+        let mut events = analyzer.on_event();
+        let alert = Alert {
+            cooldown_secs: 0,
+            ..Alert::new(AlertAction::Bell)
+        };
+        assert!(matches!(
+            analyzer.set_alert("Errors", Some(alert.clone())),
+            Err(LogAnalyzerError::FilterNotFound(_))
+        ));
+        analyzer.add_filter(Filter {
+            alias: "Errors".to_string(),
+            action: FilterAction::MARKER,
+            filter: LogLine {
+                payload: "ERROR".into(),
+                ..Default::default()
+            },
+        });
+        analyzer.set_alert("Errors", Some(alert.clone())).unwrap();
+        assert_eq!(analyzer.get_alerts(), vec![("Errors".to_string(), alert.clone())]);
+
+        analyzer.add_log(0, &source, None).unwrap();
+        wait_until(DEADLINE, || analyzer.get_total_filtered_lines() >= 1);
+        // The lines appended once the source caught up with the file are live
+        wait_until(DEADLINE, || analyzer.live_position(&source).is_some());
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "INFO live\nERROR live").unwrap();
+
+        let (filter, line) = loop {
+            if let Event::Alert { filter, line, .. } = next_event(&mut events) {
+                break (filter, line);
+            }
+        };
+        assert_eq!(filter, "Errors");
+        assert_eq!(line.payload, "ERROR live");
+        assert_eq!(line.raw_index, 2);
+    }
+
+    #[test]
+    fn live_matches_are_forwarded_to_the_sink() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("sink-in.log");
+        let output = dir.path().join("sink.log");
+        std::fs::write(&path, "ERROR before\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).follow(true).build();
+        let sink = Sink::File {
+            path: output.to_str().unwrap().to_string(),
+        };
+        analyzer.add_filter(Filter {
+            alias: "Errors".to_string(),
+            action: FilterAction::MARKER,
+            filter: LogLine {
+                payload: "ERROR".into(),
+                ..Default::default()
+            },
+        });
+        let invalid = Sink::Webhook {
+            url: "ftp://example.com".to_string(),
+        };
+        assert!(matches!(
+            analyzer.set_sink("Errors", Some(invalid)),
+            Err(LogAnalyzerError::InvalidSink(_))
+        ));
+        analyzer.set_sink("Errors", Some(sink.clone())).unwrap();
+        assert_eq!(analyzer.get_sinks(), vec![("Errors".to_string(), sink)]);
+
+        analyzer.add_log(0, &source, None).unwrap();
+        wait_until(DEADLINE, || analyzer.get_total_filtered_lines() >= 1);
+        // The lines appended once the source caught up with the file are live
+        wait_until(DEADLINE, || analyzer.live_position(&source).is_some());
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "INFO live\nERROR live").unwrap();
+
+        let forwarded = || std::fs::read_to_string(&output).unwrap_or_default();
+        wait_until(DEADLINE, || !forwarded().is_empty());
+        assert_eq!(forwarded(), "ERROR live\n");
+
+        analyzer.rename_filter("Errors", "Failures").unwrap();
+        assert_eq!(analyzer.get_sinks()[0].0, "Failures");
+        analyzer.remove_filter("Failures").unwrap();
+        assert!(analyzer.get_sinks().is_empty());
+    }
+
+    #[test]
+    fn replayed_lines_are_live() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("replay-in.log");
+        let output = dir.path().join("replay.log");
+        std::fs::write(&path, "0 ERROR first\n1 INFO second\n1.5 ERROR third\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).build();
+        analyzer
+            .add_format("Seconds", r"^(?P<TIMESTAMP>[\d.]+) (?P<PAYLOAD>.*)$")
             .unwrap();
+        analyzer.add_filter(Filter {
+            alias: "Errors".to_string(),
+            action: FilterAction::MARKER,
+            filter: LogLine {
+                payload: "ERROR".into(),
+                ..Default::default()
+            },
+        });
+        let sink = Sink::File {
+            path: output.to_str().unwrap().to_string(),
+        };
+        analyzer.set_sink("Errors", Some(sink)).unwrap();
+
+        let format = "Seconds".to_string();
+        assert!(matches!(
+            analyzer.replay_log(&source, Some(&format), 0.),
+            Err(LogAnalyzerError::InvalidReplaySpeed)
+        ));
+        analyzer.replay_log(&source, Some(&format), 10.).unwrap();
+
+        let forwarded = || std::fs::read_to_string(&output).unwrap_or_default();
+        wait_until(DEADLINE, || forwarded().lines().count() == 2);
+        assert_eq!(forwarded(), "0 ERROR first\n1.5 ERROR third\n");
+        assert_eq!(analyzer.get_total_raw_lines(), 3);
     }
 
-    fn on_event(&self) -> broadcast::Receiver<Event> {
-        self.event_channel.subscribe()
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn reopened_database_is_not_filtered_again() {
+        use crate::stores::sqlite_store::{SqliteAnalysisStore, SqliteLogStore};
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("reopen.log");
+        let database = dir.path().join("reopen.db");
+        std::fs::write(&path, "ERROR one\nINFO two\nERROR three\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+        let database = database.to_str().unwrap();
+        let open = || {
+            LogAnalyzerBuilder::new()
+                .log_store(Arc::new(SqliteLogStore::open(database).unwrap()))
+                .analysis_store(Arc::new(SqliteAnalysisStore::open(database).unwrap()))
+                .workers(1)
+                .follow(false)
+                .build()
+        };
+
+        let analyzer = open();
+        analyzer.add_filter(Filter {
+            alias: "Errors".to_string(),
+            action: FilterAction::INCLUDE,
+            filter: LogLine {
+                payload: "ERROR".into(),
+                ..Default::default()
+            },
+        });
+        analyzer.toggle_filter("Errors");
+        analyzer.add_log(0, &source, None).unwrap();
+        wait_until(DEADLINE, || analyzer.get_total_filtered_lines() >= 2);
+        let session = analyzer.get_session();
+
+        let reopened = open();
+        assert_eq!(reopened.get_total_raw_lines(), 3);
+        assert_eq!(reopened.get_total_filtered_lines(), 2);
+        reopened.restore_session(&session).unwrap();
+        // The jobs are started by the restore itself
+        assert!(!reopened.jobs.is_running(&Job::Refilter));
+        assert!(!reopened.jobs.is_running(&Job::Source(source.clone())));
+        assert_eq!(reopened.get_total_raw_lines(), 3);
+        assert_eq!(reopened.get_log_lines(0, 2)[1].payload, "ERROR three");
+    }
+
+    #[test]
+    fn restoring_a_session_attempts_every_item() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("restore.log");
+        std::fs::write(&path, "ERROR one\nINFO two\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+        let format = |alias: &str, regex: &str| Format {
+            alias: alias.to_string(),
+            regex: regex.to_string(),
+        };
+        let session = Session {
+            sources: vec![SessionSource {
+                source_type: 0,
+                address: source.clone(),
+                format: None,
+                enabled: true,
+            }],
+            formats: vec![format("Broken", "(?P<PAYLOAD>"), format("Default", "(?P<PAYLOAD>.*)")],
+            filters: vec![SessionFilter {
+                enabled: true,
+                filter: Filter {
+                    alias: "Errors".to_string(),
+                    action: FilterAction::INCLUDE,
+                    filter: LogLine {
+                        payload: "ERROR".into(),
+                        ..Default::default()
+                    },
+                },
+                alert: None,
+                sink: Some(Sink::File { path: " ".to_string() }),
+            }],
+            ..Default::default()
+        };
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).follow(false).build();
+        let restored = analyzer.restore_session(&session);
+        assert!(matches!(restored, Err(LogAnalyzerError::InvalidRegex { .. })));
+
+        // The items after the failing ones are restored all the same
+        let formats: Vec<String> =
+            analyzer.get_formats().into_iter().map(|format| format.alias).collect();
+        assert_eq!(formats, ["Default"]);
+        assert_eq!(analyzer.get_filters().len(), 1);
+        assert!(analyzer.get_sinks().is_empty());
+        wait_until(DEADLINE, || analyzer.get_total_filtered_lines() == 1);
+        assert_eq!(analyzer.get_log_lines(0, 1)[0].payload, "ERROR one");
+    }
+
+    #[test]
+    fn snapshots_restore_the_lines_without_reading_the_sources() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("snapshot.log");
+        let snapshot = path.with_extension("lapsnapshot");
+        std::fs::write(&path, "ERROR one\nINFO two\nERROR three\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+        let snapshot = snapshot.to_str().unwrap();
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).follow(false).build();
+        analyzer.add_filter(Filter {
+            alias: "Errors".to_string(),
+            action: FilterAction::INCLUDE,
+            filter: LogLine {
+                payload: "ERROR".into(),
+                ..Default::default()
+            },
+        });
+        analyzer.toggle_filter("Errors");
+        analyzer.set_redactions(vec![Redaction::new("two")]).unwrap();
+        analyzer.add_log(0, &source, None).unwrap();
+        analyzer.add_search("(?P<RED>three)");
+        wait_until(DEADLINE, || {
+            analyzer.get_total_filtered_lines() >= 2 && analyzer.get_total_searched_lines() >= 1
+        });
+        let saved = analyzer.save_snapshot(snapshot, analyzer.get_session()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let restored = LogAnalyzerBuilder::new().workers(1).follow(false).build();
+        let session = restored.load_snapshot(snapshot).unwrap();
+        // The jobs are started by the restore itself
+        assert!(!restored.jobs.is_running(&Job::Refilter));
+        assert!(!restored.jobs.is_running(&Job::Search));
+
+        assert_eq!(saved, 3);
+        assert_eq!(session.search.as_deref(), Some("(?P<RED>three)"));
+        assert_eq!(restored.get_logs(), [(true, source.clone(), None)]);
+        assert_eq!(restored.get_session().sources[0].address, source);
+        assert_eq!(restored.get_filters().len(), 1);
+        assert_eq!(restored.get_total_raw_lines(), 3);
+        assert_eq!(restored.get_raw_line(&source, 1).as_deref(), Some("INFO [REDACTED]"));
+        assert_eq!(restored.get_log_lines(0, 2)[1].payload, "ERROR three");
+        let search = restored.get_search_lines(0, 1);
+        assert_eq!(search[0].payload[1], (Some("RED".to_string()), "three".to_string()));
+        assert!(restored.load_snapshot(&source).is_err());
+    }
+
+    #[test]
+    fn lines_are_transformed_before_being_filtered() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("transform.log");
+        std::fs::write(&path, "[main] loader: started\n[main] other: started\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).follow(false).build();
+        let transforms: Vec<Transform> = serde_json::from_str(
+            r#"[
+                { "field": "Payload", "type": "strip_prefix", "prefix": "[main] " },
+                { "field": "App", "type": "extract", "from": "Payload", "regex": "^(\\w+):" }
+            ]"#,
+        )
+        .unwrap();
+        analyzer.set_transforms(transforms.clone()).unwrap();
+        assert_eq!(analyzer.get_transforms(), transforms);
+        analyzer.add_filter(Filter {
+            alias: "Loader".to_string(),
+            action: FilterAction::INCLUDE,
+            filter: LogLine {
+                app: "^loader$".into(),
+                ..Default::default()
+            },
+        });
+        analyzer.toggle_filter("Loader");
+
+        analyzer.add_log(0, &source, None).unwrap();
+        wait_processed(&analyzer, 2);
+        let lines = analyzer.get_log_lines(0, 2);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].payload, "loader: started");
+    }
+
+    #[test]
+    fn lines_are_redacted_unless_the_originals_are_shown() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("redaction.log");
+        std::fs::write(&path, "login of jane@example.com\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).follow(false).build();
+        assert!(matches!(
+            analyzer.set_redactions(vec![Redaction::new("[")]),
+            Err(LogAnalyzerError::InvalidRegex { .. })
+        ));
+        let redactions = vec![Redaction {
+            regex: r"\w+@[\w.]+".to_string(),
+            replacement: "<email>".to_string(),
+        }];
+        analyzer.set_redactions(redactions.clone()).unwrap();
+        assert_eq!(analyzer.get_redactions(), redactions);
+
+        let payload = |expected: &str| {
+            wait_until(DEADLINE, || {
+                let lines = analyzer.get_log_lines(0, 1);
+                lines.first().map(|line| line.payload.as_str()) == Some(expected)
+            })
+        };
+        analyzer.add_log(0, &source, None).unwrap();
+        payload("login of <email>");
+        let raw_line = || analyzer.get_raw_line(&source, 0).unwrap();
+        assert!(raw_line().ends_with("login of <email>"));
+
+        analyzer.set_show_originals(true);
+        assert!(analyzer.is_showing_originals());
+        payload("login of jane@example.com");
+        assert!(raw_line().ends_with("login of jane@example.com"));
+        analyzer.set_show_originals(false);
+        payload("login of <email>");
+    }
+
+    #[test]
+    fn lines_of_another_source_are_deduplicated() {
+        let dir = tempdir().unwrap();
+        let daemon = dir.path().join("dedupe-daemon.log");
+        let syslog = dir.path().join("dedupe-syslog.log");
+        std::fs::write(&daemon, "100.0 disk full\n101.0 retrying\n").unwrap();
+        std::fs::write(&syslog, "100.4 Disk full\n105.0 retrying\n").unwrap();
+        let seconds = "Seconds".to_string();
+
+        let analyzer = LogAnalyzerBuilder::new()
+            .workers(1)
+            .follow(false)
+            .dedupe(Some(DedupeConfig::default()))
+            .build();
+        analyzer
+            .add_format(&seconds, r"^(?P<TIMESTAMP>[\d.]+) (?P<PAYLOAD>.*)$")
+            .unwrap();
+        analyzer.add_log(0, daemon.to_str().unwrap(), Some(&seconds)).unwrap();
+        wait_until(DEADLINE, || analyzer.get_total_filtered_lines() >= 2);
+        analyzer.add_log(0, syslog.to_str().unwrap(), Some(&seconds)).unwrap();
+        wait_processed(&analyzer, 4);
+
+        let payloads: Vec<String> = analyzer
+            .get_log_lines(0, 4)
+            .iter()
+            .map(|line| line.payload.to_string())
+            .collect();
+        assert_eq!(payloads, ["disk full", "retrying", "retrying"]);
+
+        // A refilter keeps the line of the first source added
+        let mut events = analyzer.on_event();
+        analyzer.set_dedupe(Some(DedupeConfig::default()));
+        wait_event(&mut events, Event::FilterFinished);
+        let lines = analyzer.get_log_lines(0, 4);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].log, daemon.to_str().unwrap());
+    }
+
+    #[test]
+    fn invalid_utf8_lines_follow_the_policy() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("utf8.log");
+        std::fs::write(&path, b"bad \xFF byte\nvalid\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+
+        let payloads = |policy: InvalidUtf8, expected: usize| {
+            let analyzer = LogAnalyzerBuilder::new()
+                .workers(1)
+                .follow(false)
+                .invalid_utf8(policy)
+                .build();
+            analyzer.add_log(0, &source, None).unwrap();
+            wait_until(DEADLINE, || analyzer.get_log_lines(0, 2).len() == expected);
+            let lines = analyzer.get_log_lines(0, 2);
+            lines.iter().map(|line| line.payload.to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(payloads(InvalidUtf8::Replace, 2), ["bad � byte", "valid"]);
+        assert_eq!(payloads(InvalidUtf8::HexEscape, 2), ["bad \\xFF byte", "valid"]);
+        assert_eq!(payloads(InvalidUtf8::Skip, 1), ["valid"]);
+    }
+
+    #[test]
+    fn skipped_lines_do_not_delay_the_live_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("skipped.log");
+        std::fs::write(&path, b"bad \xFF byte\nretry before\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+
+        let analyzer = LogAnalyzerBuilder::new()
+            .workers(1)
+            .follow(true)
+            .invalid_utf8(InvalidUtf8::Skip)
+            .build();
+        analyzer.add_watch("retry").unwrap();
+        analyzer.add_log(0, &source, None).unwrap();
+        wait_until(DEADLINE, || analyzer.get_total_filtered_lines() >= 1);
+        // The lines appended once the source caught up with the file are live
+        wait_until(DEADLINE, || analyzer.live_position(&source).is_some());
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "retry live").unwrap();
+
+        // The live line follows the stored line, not the 2 lines read
+        wait_until(DEADLINE, || analyzer.get_watches()[0].total >= 1);
+        assert_eq!(analyzer.get_total_filtered_lines(), 2);
+    }
+
+    #[test]
+    fn watches_count_live_lines_only() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("watches.log");
+        std::fs::write(&path, "retry before\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).follow(true).build();
+        assert!(matches!(
+            analyzer.add_watch("payload:["),
+            Err(LogAnalyzerError::InvalidQuery { .. })
+        ));
+        analyzer.add_watch("retry").unwrap();
+        assert!(matches!(
+            analyzer.add_watch("retry"),
+            Err(LogAnalyzerError::WatchAlreadyExists(_))
+        ));
+        analyzer.add_watch("payload:timeout").unwrap();
+
+        analyzer.add_log(0, &source, None).unwrap();
+        wait_until(DEADLINE, || analyzer.get_total_filtered_lines() >= 1);
+        // The lines appended once the source caught up with the file are live
+        wait_until(DEADLINE, || analyzer.live_position(&source).is_some());
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "retry live\nretry again\nINFO live").unwrap();
+        // The watches are counted right after the lines are stored
+        wait_until(DEADLINE, || {
+            analyzer.get_total_filtered_lines() >= 4 && analyzer.get_watches()[0].total >= 2
+        });
+
+        let watches = analyzer.get_watches();
+        assert_eq!(watches.len(), 2);
+        assert_eq!((watches[0].expression.as_str(), watches[0].total), ("retry", 2));
+        assert_eq!(watches[0].per_second.iter().sum::<usize>(), 2);
+        assert_eq!(watches[1].total, 0);
+
+        analyzer.remove_watch("retry").unwrap();
+        assert!(matches!(
+            analyzer.remove_watch("retry"),
+            Err(LogAnalyzerError::WatchNotFound(_))
+        ));
+        assert_eq!(analyzer.get_watches().len(), 1);
+    }
+
+    #[test]
+    fn panicking_workers_report_the_lines_and_keep_running() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("panics.log");
+        std::fs::write(&path, "INFO started\nERROR failed\nINFO stopped\n").unwrap();
+        let source = path.to_str().unwrap().to_string();
+
+        let analyzer = LogAnalyzerBuilder::new().workers(1).follow(false).build();
+        let mut events = analyzer.on_event();
+        let mut next_error = || loop {
+            if let Event::SourceError { source, error } = next_event(&mut events) {
+                break (source, error);
+            }
+        };
+        let panic_on = |text: Option<&str>| *analyzer.panic_on.write() = text.map(String::from);
+
+        panic_on(Some("ERROR"));
+        analyzer.add_log(0, &source, None).unwrap();
+        let panicked = "ERROR failed can't be handled";
+        let processed = format!("Lines 0 to 2 could not be processed: {}", panicked);
+        assert_eq!(next_error(), (source.clone(), processed));
+        assert_eq!(analyzer.get_total_filtered_lines(), 0);
+
+        analyzer.refilter();
+        let refiltered = format!("Lines 0 to 2 could not be refiltered: {}", panicked);
+        assert_eq!(next_error(), (source.clone(), refiltered));
+        wait_until(DEADLINE, || !analyzer.jobs.is_running(&Job::Refilter));
+        assert_eq!(analyzer.get_total_filtered_lines(), 0);
+
+        // The next refilter runs on the same thread
+        panic_on(None);
+        analyzer.refilter();
+        wait_until(DEADLINE, || analyzer.get_total_filtered_lines() == 3);
+
+        panic_on(Some("ERROR"));
+        analyzer.add_search("failed");
+        let searched = format!("Lines 0 to 2 could not be searched: {}", panicked);
+        assert_eq!(next_error(), (source.clone(), searched));
+        wait_until(DEADLINE, || !analyzer.jobs.is_running(&Job::Search));
+        assert_eq!(analyzer.get_total_searched_lines(), 0);
+
+        panic_on(None);
+        analyzer.add_search("failed");
+        wait_until(DEADLINE, || analyzer.get_total_searched_lines() == 1);
     }
 }
@@ -1 +1,15 @@
-pub mod log_service;
\ No newline at end of file
+#[cfg(feature = "threads")]
+pub mod api;
+#[cfg(feature = "threads")]
+pub mod batch_queue;
+#[cfg(feature = "threads")]
+pub mod builder;
+#[cfg(feature = "threads")]
+pub mod event_coalescer;
+#[cfg(feature = "threads")]
+pub mod job_manager;
+pub mod line_processor;
+#[cfg(feature = "threads")]
+pub mod log_service;
+#[cfg(feature = "threads")]
+pub mod worker_pool;
@@ -0,0 +1,57 @@
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+/// Threads processing the chunks of lines of the batches, the refilters and the searches.
+///
+/// They are shared by every stage, so the threads working in parallel are bounded by the
+/// workers of the service however many batches, refilters or searches run at once, and none
+/// is spawned for each of them
+pub struct WorkerPool {
+    pool: ThreadPool,
+    workers: usize,
+}
+
+impl WorkerPool {
+    pub fn new(workers: usize) -> Self {
+        let workers = workers.max(1);
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(workers)
+            .thread_name(|index| format!("Worker {}", index))
+            .build()
+            .expect("Unable to start the worker threads");
+        Self { pool, workers }
+    }
+
+    /// Threads of the pool
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Apply `map` to the chunks of up to `chunk_size` items of `items` in parallel, returning
+    /// the results in the order of the chunks. Blocks until every chunk is mapped
+    pub fn map_chunks<T, R, F>(&self, items: &[T], chunk_size: usize, map: F) -> Vec<R>
+    where
+        T: Sync,
+        R: Send,
+        F: Fn(&[T]) -> R + Sync + Send,
+    {
+        self.pool
+            .install(|| items.par_chunks(chunk_size.max(1)).map(map).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_are_mapped_in_order() {
+        let pool = WorkerPool::new(3);
+        let items: Vec<usize> = (0..10).collect();
+        let sums = pool.map_chunks(&items, 4, |chunk| chunk.iter().sum::<usize>());
+        assert_eq!(sums, vec![6, 22, 17]);
+
+        let names = pool.map_chunks(&items, 1, |_| std::thread::current().name().map(String::from));
+        assert!(names.iter().all(|name| name.as_deref().unwrap().starts_with("Worker ")));
+    }
+}
@@ -1,14 +1,32 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, VecDeque};
+use std::ops::Range;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use crate::domain::correlation_id::correlation_id;
 use crate::models::log_line::LogLine;
+use crate::models::log_line_styled::{Highlight, LogLineStyled};
+use crate::models::retention::Retention;
 use parking_lot::{lock_api::RwLockReadGuard, RawRwLock, RwLock};
+use regex::Regex;
+use rustc_hash::FxHashMap as HashMap;
+use tracing::instrument;
+
+use super::arrivals::Arrivals;
 
 /// Store for managing processed logs.
 ///
 /// Stores both the combined filtered log and the search log
 pub trait AnalysisStore {
-    /// Add a list of processed lines
-    fn add_lines(&self, lines: &[LogLine]);
-    /// Add a list of searched lines
-    fn add_search_lines(&self, lines: &[LogLine]);
+    /// Add a list of processed lines, assigning them their index in the processed log
+    ///
+    /// Returns the range of assigned indexes
+    fn add_lines(&self, lines: &[LogLine]) -> Range<usize>;
+    /// Add a list of searched lines with the parts of their fields highlighted by the search
+    fn add_search_lines(&self, lines: &[(LogLine, Vec<Highlight>)]);
     /// Change the search query
     fn add_search_query(&self, query: &str);
     /// Get the current search query
@@ -17,14 +35,27 @@ pub trait AnalysisStore {
     fn reset_log(&self);
     /// Clear the searched log
     fn reset_search(&self);
-    /// Get a RwLock to the current processed log to avoid copying
-    fn fetch_log(&self) -> RwLockReadGuard<RawRwLock, Vec<LogLine>>;
-    /// Get a RwLock to the current searched log to avoid copying
-    fn fetch_search(&self) -> RwLockReadGuard<RawRwLock, Vec<LogLine>>;
-    /// Get a copy of a window of lines. Is safe to query out of bounds
-    fn get_log_lines(&self, from: usize, to: usize) -> Vec<LogLine>;
-    /// Get a copy of a window of search lines. Is safe to query out of bounds
-    fn get_search_lines(&self, from: usize, to: usize) -> Vec<LogLine>;
+    /// Get a RwLock to the current processed log to avoid copying. The lines keep the index and
+    /// raw index they were added with once older lines are discarded, see `show_line`
+    fn fetch_log(&self) -> RwLockReadGuard<'_, RawRwLock, Vec<Arc<LogLine>>>;
+    /// Get a RwLock to the current searched log to avoid copying, its lines stored as the ones
+    /// of `fetch_log`
+    fn fetch_search(&self) -> RwLockReadGuard<'_, RawRwLock, Vec<Arc<LogLine>>>;
+    /// Copy of a line of `fetch_log` or `fetch_search` with the index and raw index it is shown
+    /// with, its position in the processed log and in the raw lines kept of its source
+    fn show_line(&self, line: &LogLine) -> LogLine;
+    /// Get a window of lines, shared with the store. Is safe to query out of bounds
+    fn get_log_lines(&self, from: usize, to: usize) -> Vec<Arc<LogLine>>;
+    /// Get a window of search lines, styled with their highlights. Is safe to query out of
+    /// bounds
+    fn get_search_lines(&self, from: usize, to: usize) -> Vec<LogLineStyled>;
+    /// Get a window of search lines with the parts of their fields highlighted, as added. Is
+    /// safe to query out of bounds
+    fn get_search_highlights(
+        &self,
+        from: usize,
+        to: usize,
+    ) -> Vec<(Arc<LogLine>, Vec<Highlight>)>;
     /// Get a window of `elements` number of lines centered around the target `line`
     ///
     /// Returns (list of lines, offset from start, index of target)
@@ -32,7 +63,7 @@ pub trait AnalysisStore {
         &self,
         index: usize,
         elements: usize,
-    ) -> (Vec<LogLine>, usize, usize);
+    ) -> (Vec<Arc<LogLine>>, usize, usize);
     /// Get a window of `elements` number of lines centered around the target `line`
     ///
     /// Returns (list of lines, offset from start, index of target)
@@ -40,16 +71,217 @@ pub trait AnalysisStore {
         &self,
         index: usize,
         elements: usize,
-    ) -> (Vec<LogLine>, usize, usize);
+    ) -> (Vec<LogLineStyled>, usize, usize);
     /// Count the total number of lines
     fn get_total_filtered_lines(&self) -> usize;
     /// Count the total number of search lines
     fn get_total_searched_lines(&self) -> usize;
+    /// Get an estimation of the memory used by the processed log in bytes
+    fn get_log_memory_usage(&self) -> usize;
+    /// Get an estimation of the memory used by the searched log in bytes
+    fn get_search_memory_usage(&self) -> usize;
+    /// Count the processed lines contributed by each source
+    ///
+    /// Returns a list of (source, lines) sorted by source
+    fn get_lines_per_source(&self) -> Vec<(String, usize)>;
+    /// Count the searched lines contributed by each source
+    ///
+    /// Returns a list of (source, lines) sorted by source
+    fn get_search_lines_per_source(&self) -> Vec<(String, usize)>;
+    /// Get the first `limit` processed lines of the given source
+    fn get_source_lines(&self, source: &str, limit: usize) -> Vec<LogLine>;
+    /// Find the position in the processed log of the line of `source` with the given raw index,
+    /// or of the closest line of the source. The closest line of any source if `None`
+    fn find_raw_line(&self, source: Option<&str>, raw_index: usize) -> Option<usize>;
+    /// Count the time (in seconds) of the received raw lines and the filtered ones
+    fn add_line_times(&self, raw: &[i64], filtered: &[i64]);
+    /// Get the lines received per second as a list of (second, raw lines, filtered lines) sorted by second
+    fn get_rate(&self) -> Vec<(i64, usize, usize)>;
+    /// Get the searched lines per second, parsed from their time, as a list of (second, lines) sorted by second
+    fn get_search_rate(&self) -> Vec<(i64, usize)>;
+    /// Count the processed lines per severity. Lines without severity are not counted
+    fn get_lines_per_severity(&self) -> Vec<(String, usize)>;
+    /// Count the processed lines per app. Lines without app are not counted
+    fn get_lines_per_app(&self) -> Vec<(String, usize)>;
+    /// Count the processed lines, or the searched ones, per distinct value of `column`.
+    /// Lines with the column empty or outside the inclusive `time_range` (in seconds) are not counted
+    ///
+    /// Returns `None` if the column doesn't exist
+    fn get_lines_per_value(
+        &self,
+        column: &str,
+        search: bool,
+        time_range: Option<(f64, f64)>,
+    ) -> Option<Vec<(String, usize)>>;
+    /// Change the expression extracting the correlation id of the processed lines, `None` to stop
+    /// tracking them. The processed log is indexed again with the new key
+    fn set_correlation_key(&self, key: Option<Regex>);
+    /// Get the correlation id of the processed line at `index`
+    fn get_correlation_id(&self, index: usize) -> Option<String>;
+    /// Get every processed line with the correlation id `id`, sorted by index
+    fn get_correlated_lines(&self, id: &str) -> Vec<LogLine>;
+    /// Get the indexes of the processed lines with a time (in seconds) in the inclusive range,
+    /// sorted by index. Lines without a recognizable time are skipped
+    fn get_indexes_in_time_range(&self, from: f64, to: f64) -> Vec<usize>;
+    /// Get the index of the first processed line with a time (in seconds) at or after `time`,
+    /// or of the last one if they are all earlier
+    ///
+    /// Returns `None` if no processed line has a recognizable time
+    fn get_index_at_time(&self, time: f64) -> Option<usize>;
+    /// Discard the oldest processed lines beyond the limits of `retention` with their search
+    /// lines, and the ones whose raw line is among the `raw_discarded` lines of their source, a
+    /// list of (source, discarded raw lines), with the lines before them. The lines left are
+    /// shown indexed again from 0 and with their raw index moved back by the raw lines
+    /// discarded, without changing the stored lines. The rates keep counting the discarded lines
+    ///
+    /// Returns the number of discarded lines
+    fn apply_retention(&self, retention: &Retention, raw_discarded: &[(String, usize)]) -> usize;
+    /// Replace the processed log and the search log with the ones built in `staged`, at once.
+    /// The search query and the correlation key are kept, the lines of `staged` being indexed
+    /// again with the key
+    fn replace_log(&self, staged: InMemmoryAnalysisStore);
+    /// Replace the search log with the one built in `staged`, at once
+    fn replace_search(&self, staged: InMemmoryAnalysisStore);
+    /// Reset the log and the search, forgetting the search query
+    fn clear(&self);
+}
+/// Add one to the counter of `key`
+fn increment(counts: &mut HashMap<String, usize>, key: &str) {
+    match counts.get_mut(key) {
+        Some(count) => *count += 1,
+        None => {
+            counts.insert(key.to_string(), 1);
+        }
+    }
 }
+
+/// Key of a time (in seconds) in the time index, in microseconds
+fn time_key(time: f64) -> i64 {
+    (time * 1_000_000.0).round() as i64
+}
+
+/// Remove one from the counter of `key`, forgetting it at 0
+fn decrement(counts: &mut HashMap<String, usize>, key: &str) {
+    if let Some(count) = counts.get_mut(key) {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(key);
+        }
+    }
+}
+
+/// Number of the oldest processed lines to discard so that no line is left whose raw line is
+/// gone: the lines of the `raw_discarded` sources before their first raw index kept, and the
+/// lines before them
+fn lines_without_raw_line(
+    log: &[Arc<LogLine>],
+    raw_first: &HashMap<String, usize>,
+    source_lines: &HashMap<String, usize>,
+    raw_discarded: &[(String, usize)],
+) -> usize {
+    // K: source not found with a raw line kept yet -> V: lines of the source not seen yet
+    let mut pending: HashMap<&str, usize> = raw_discarded
+        .iter()
+        .filter(|(_, discarded)| *discarded > 0)
+        .filter_map(|(source, _)| Some((source.as_str(), *source_lines.get(source)?)))
+        .collect();
+    let mut excess = 0;
+    for (position, line) in log.iter().enumerate() {
+        if pending.is_empty() {
+            break;
+        }
+        let Some(left) = pending.get_mut(&*line.log) else {
+            continue;
+        };
+        if line.raw_index >= raw_first.get(&*line.log).copied().unwrap_or_default() {
+            pending.remove(&*line.log);
+            continue;
+        }
+        excess = position + 1;
+        *left -= 1;
+        if *left == 0 {
+            pending.remove(&*line.log);
+        }
+    }
+    excess
+}
+
 pub struct InMemmoryAnalysisStore {
-    log: RwLock<Vec<LogLine>>,
+    /// Processed lines, shared with the windows of lines handed out
+    log: RwLock<Vec<Arc<LogLine>>>,
     search_query: RwLock<Option<String>>,
-    search_log: RwLock<Vec<LogLine>>,
+    search_log: RwLock<Vec<Arc<LogLine>>>,
+    /// Highlights of each searched line, locked after the search log
+    search_highlights: RwLock<Vec<Vec<Highlight>>>,
+    /// Estimated bytes used by the processed log
+    log_memory: AtomicUsize,
+    /// Estimated bytes used by the searched log
+    search_memory: AtomicUsize,
+    /// Processed lines, read without locking the log
+    log_lines: AtomicUsize,
+    /// Searched lines, read without locking the search log
+    search_lines: AtomicUsize,
+    /// K: source -> V: processed lines
+    source_lines: RwLock<HashMap<String, usize>>,
+    /// K: source -> V: indexes of its processed lines, locked after the offsets
+    source_indexes: RwLock<HashMap<String, VecDeque<usize>>>,
+    /// K: source -> V: searched lines
+    search_source_lines: RwLock<HashMap<String, usize>>,
+    /// K: severity -> V: processed lines
+    severity_lines: RwLock<HashMap<String, usize>>,
+    /// K: app -> V: processed lines
+    app_lines: RwLock<HashMap<String, usize>>,
+    /// K: second -> V: (raw lines, processed lines)
+    rate: RwLock<BTreeMap<i64, (usize, usize)>>,
+    /// K: second -> V: searched lines
+    search_rate: RwLock<BTreeMap<i64, usize>>,
+    /// Expression extracting the correlation id of the processed lines
+    correlation_key: RwLock<Option<Regex>>,
+    /// K: correlation id -> V: indexes of its processed lines
+    correlated_lines: RwLock<HashMap<String, Vec<usize>>>,
+    /// K: time of the processed lines (see `time_key`) -> V: their indexes
+    time_index: RwLock<BTreeMap<i64, Vec<usize>>>,
+    /// When the processed lines were added
+    arrivals: RwLock<Arrivals>,
+    /// Processed lines discarded since the log was reset. The lines keep the index they were
+    /// added with, this many more than their position. Locked after the log
+    first: AtomicUsize,
+    /// K: source -> V: raw lines of the source discarded since the log was reset. The lines keep
+    /// the raw index they were added with, this many more than the one they are shown with
+    raw_first: RwLock<HashMap<String, usize>>,
+}
+
+/// Offsets between the indexes the lines are stored with and the ones they are shown with
+struct Offsets<'a> {
+    first: usize,
+    raw_first: RwLockReadGuard<'a, RawRwLock, HashMap<String, usize>>,
+}
+
+impl Offsets<'_> {
+    fn raw_first(&self, source: &str) -> usize {
+        self.raw_first.get(source).copied().unwrap_or_default()
+    }
+
+    /// The stored `line` as shown, copied only if its indexes moved
+    fn show(&self, line: &Arc<LogLine>) -> Arc<LogLine> {
+        match (self.first, self.raw_first(&line.log)) {
+            (0, 0) => line.clone(),
+            _ => Arc::new(self.show_line(line)),
+        }
+    }
+
+    fn show_line(&self, line: &LogLine) -> LogLine {
+        let mut shown = line.clone();
+        shown.index = shown.index.saturating_sub(self.first);
+        shown.raw_index = shown.raw_index.saturating_sub(self.raw_first(&line.log));
+        shown
+    }
+
+    /// The shown `line` as stored
+    fn store(&self, line: &mut LogLine) {
+        line.index += self.first;
+        line.raw_index += self.raw_first(&line.log);
+    }
 }
 
 impl InMemmoryAnalysisStore {
@@ -58,6 +290,24 @@ impl InMemmoryAnalysisStore {
             log: RwLock::new(Vec::new()),
             search_query: RwLock::new(None),
             search_log: RwLock::new(Vec::new()),
+            search_highlights: RwLock::new(Vec::new()),
+            log_memory: AtomicUsize::new(0),
+            search_memory: AtomicUsize::new(0),
+            log_lines: AtomicUsize::new(0),
+            search_lines: AtomicUsize::new(0),
+            source_lines: RwLock::new(HashMap::default()),
+            source_indexes: RwLock::new(HashMap::default()),
+            search_source_lines: RwLock::new(HashMap::default()),
+            severity_lines: RwLock::new(HashMap::default()),
+            app_lines: RwLock::new(HashMap::default()),
+            rate: RwLock::new(BTreeMap::new()),
+            search_rate: RwLock::new(BTreeMap::new()),
+            correlation_key: RwLock::new(None),
+            correlated_lines: RwLock::new(HashMap::default()),
+            time_index: RwLock::new(BTreeMap::new()),
+            arrivals: RwLock::new(Arrivals::default()),
+            first: AtomicUsize::new(0),
+            raw_first: RwLock::new(HashMap::default()),
         }
     }
 }
@@ -69,23 +319,74 @@ impl Default for InMemmoryAnalysisStore {
 }
 
 impl AnalysisStore for InMemmoryAnalysisStore {
-    fn add_lines(&self, lines: &[LogLine]) {
+    #[instrument(level = "trace", skip_all, fields(lines = lines.len()))]
+    fn add_lines(&self, lines: &[LogLine]) -> Range<usize> {
         let mut w = self.log.write();
+        let first_index = w.len();
+        let offsets = self.offsets();
+        let mut source_lines = self.source_lines.write();
+        let (mut severity_lines, mut app_lines) =
+            (self.severity_lines.write(), self.app_lines.write());
+        let correlation_key = self.correlation_key.read();
+        let mut correlated_lines = self.correlated_lines.write();
+        let mut time_index = self.time_index.write();
+        let mut source_indexes = self.source_indexes.write();
         for line in lines {
-            let index = w.len();
+            increment(&mut source_lines, &line.log);
+            if !line.severity.is_empty() {
+                increment(&mut severity_lines, &line.severity);
+            }
+            if !line.app.is_empty() {
+                increment(&mut app_lines, &line.app);
+            }
 
             let mut line = line.clone();
-            line.index = index.to_string();
+            line.index = w.len();
+            offsets.store(&mut line);
+            let index = line.index;
+            if let Some(id) = correlation_key.as_ref().and_then(|key| correlation_id(key, &line)) {
+                correlated_lines.entry(id).or_default().push(index);
+            }
+            if let Some(time) = line.time() {
+                time_index.entry(time_key(time)).or_default().push(index);
+            }
+            match source_indexes.get_mut(&*line.log) {
+                Some(indexes) => indexes.push_back(index),
+                None => {
+                    source_indexes.insert(line.log.to_string(), VecDeque::from([index]));
+                }
+            }
 
-            w.push(line);
+            self.log_memory
+                .fetch_add(line.memory_size(), Ordering::Relaxed);
+            w.push(Arc::new(line));
         }
+        self.arrivals.write().add(lines.len());
+        self.log_lines.store(w.len(), Ordering::Relaxed);
+
+        first_index..w.len()
     }
 
-    fn add_search_lines(&self, lines: &[LogLine]) {
+    #[instrument(level = "trace", skip_all, fields(lines = lines.len()))]
+    fn add_search_lines(&self, lines: &[(LogLine, Vec<Highlight>)]) {
         let mut w = self.search_log.write();
-        for line in lines {
-            w.push(line.clone());
+        let mut search_highlights = self.search_highlights.write();
+        let mut search_source_lines = self.search_source_lines.write();
+        let mut search_rate = self.search_rate.write();
+        let offsets = self.offsets();
+        for (line, highlights) in lines {
+            increment(&mut search_source_lines, &line.log);
+            if let Some(time) = line.time() {
+                *search_rate.entry(time.floor() as i64).or_default() += 1;
+            }
+            self.search_memory
+                .fetch_add(line.memory_size(), Ordering::Relaxed);
+            let mut line = line.clone();
+            offsets.store(&mut line);
+            w.push(Arc::new(line));
+            search_highlights.push(highlights.clone());
         }
+        self.search_lines.store(w.len(), Ordering::Relaxed);
     }
 
     fn add_search_query(&self, query: &str) {
@@ -98,87 +399,471 @@ impl AnalysisStore for InMemmoryAnalysisStore {
         r.clone()
     }
 
-    fn fetch_log(&self) -> RwLockReadGuard<RawRwLock, Vec<LogLine>> {
+    fn fetch_log(&self) -> RwLockReadGuard<'_, RawRwLock, Vec<Arc<LogLine>>> {
         self.log.read()
     }
 
-    fn fetch_search(&self) -> RwLockReadGuard<RawRwLock, Vec<LogLine>> {
+    fn fetch_search(&self) -> RwLockReadGuard<'_, RawRwLock, Vec<Arc<LogLine>>> {
         self.search_log.read()
     }
 
-    fn get_log_lines(&self, from: usize, to: usize) -> Vec<LogLine> {
+    fn show_line(&self, line: &LogLine) -> LogLine {
+        self.offsets().show_line(line)
+    }
+
+    fn get_log_lines(&self, from: usize, to: usize) -> Vec<Arc<LogLine>> {
         let log = self.log.read();
-        log[from.min(log.len())..to.min(log.len())].to_vec()
+        let offsets = self.offsets();
+        log[from.min(log.len())..to.min(log.len())]
+            .iter()
+            .map(|line| offsets.show(line))
+            .collect()
+    }
+
+    fn get_search_lines(&self, from: usize, to: usize) -> Vec<LogLineStyled> {
+        let log = self.search_log.read();
+        self.styled_search_lines(&log, from.min(log.len())..to.min(log.len()))
     }
 
-    fn get_search_lines(&self, from: usize, to: usize) -> Vec<LogLine> {
+    fn get_search_highlights(
+        &self,
+        from: usize,
+        to: usize,
+    ) -> Vec<(Arc<LogLine>, Vec<Highlight>)> {
         let log = self.search_log.read();
-        log[from.min(log.len())..to.min(log.len())].to_vec()
+        let offsets = self.offsets();
+        let positions = from.min(log.len())..to.min(log.len());
+        log[positions.clone()]
+            .iter()
+            .map(|line| offsets.show(line))
+            .zip(self.search_highlights.read()[positions].iter().cloned())
+            .collect()
     }
 
     fn get_log_lines_containing(
         &self,
         index: usize,
         elements: usize,
-    ) -> (Vec<LogLine>, usize, usize) {
+    ) -> (Vec<Arc<LogLine>>, usize, usize) {
         let log = self.log.read();
-        InMemmoryAnalysisStore::find_rolling_window(&log, index, elements)
+        let offsets = self.offsets();
+        let (lines, from, index) =
+            InMemmoryAnalysisStore::find_rolling_window(&log, index + offsets.first, elements);
+        (lines.iter().map(|line| offsets.show(line)).collect(), from, index)
     }
 
     fn get_search_lines_containing(
         &self,
         index: usize,
         elements: usize,
-    ) -> (Vec<LogLine>, usize, usize) {
+    ) -> (Vec<LogLineStyled>, usize, usize) {
         let search_log = self.search_log.read();
-        InMemmoryAnalysisStore::find_rolling_window(&search_log, index, elements)
+        let first = self.first.load(Ordering::Relaxed);
+        let (lines, from, index) =
+            InMemmoryAnalysisStore::find_rolling_window(&search_log, index + first, elements);
+        let lines = self.styled_search_lines(&search_log, from..from + lines.len());
+        (lines, from, index)
     }
 
     fn reset_log(&self) {
         let mut w = self.log.write();
         w.clear();
+        self.source_lines.write().clear();
+        self.source_indexes.write().clear();
+        self.severity_lines.write().clear();
+        self.app_lines.write().clear();
+        self.rate.write().clear();
+        self.correlated_lines.write().clear();
+        self.time_index.write().clear();
+        self.arrivals.write().clear();
+        self.first.store(0, Ordering::Relaxed);
+        self.raw_first.write().clear();
+        self.log_memory.store(0, Ordering::Relaxed);
+        self.log_lines.store(0, Ordering::Relaxed);
     }
 
     fn reset_search(&self) {
         let mut w = self.search_log.write();
         w.clear();
+        self.search_highlights.write().clear();
+        self.search_source_lines.write().clear();
+        self.search_rate.write().clear();
+        self.search_memory.store(0, Ordering::Relaxed);
+        self.search_lines.store(0, Ordering::Relaxed);
     }
 
     fn get_total_filtered_lines(&self) -> usize {
-        self.log.read().len()
+        self.log_lines.load(Ordering::Relaxed)
     }
 
     fn get_total_searched_lines(&self) -> usize {
-        self.search_log.read().len()
+        self.search_lines.load(Ordering::Relaxed)
+    }
+
+    fn get_log_memory_usage(&self) -> usize {
+        self.log_memory.load(Ordering::Relaxed)
+    }
+
+    fn get_search_memory_usage(&self) -> usize {
+        self.search_memory.load(Ordering::Relaxed)
+    }
+
+    fn get_lines_per_source(&self) -> Vec<(String, usize)> {
+        let mut lines_per_source: Vec<(String, usize)> = self
+            .source_lines
+            .read()
+            .iter()
+            .map(|(source, lines)| (source.clone(), *lines))
+            .collect();
+        lines_per_source.sort();
+        lines_per_source
+    }
+
+    fn get_search_lines_per_source(&self) -> Vec<(String, usize)> {
+        let mut lines_per_source: Vec<(String, usize)> = self
+            .search_source_lines
+            .read()
+            .iter()
+            .map(|(source, lines)| (source.clone(), *lines))
+            .collect();
+        lines_per_source.sort();
+        lines_per_source
+    }
+
+    fn get_source_lines(&self, source: &str, limit: usize) -> Vec<LogLine> {
+        let log = self.log.read();
+        let offsets = self.offsets();
+        log.iter()
+            .filter(|line| line.log == source)
+            .take(limit)
+            .map(|line| offsets.show_line(line))
+            .collect()
+    }
+
+    fn find_raw_line(&self, source: Option<&str>, raw_index: usize) -> Option<usize> {
+        let log = self.log.read();
+        let offsets = self.offsets();
+        let source_indexes = self.source_indexes.read();
+        // The lines of a source are sorted by raw index, the closest ones surround the target
+        let closest = |(source, indexes): (&String, &VecDeque<usize>)| {
+            let raw_index = raw_index + offsets.raw_first(source);
+            let raw_line = |index: &usize| log[index - offsets.first].raw_index;
+            let after = indexes.partition_point(|index| raw_line(index) < raw_index);
+            [after.checked_sub(1), Some(after)]
+                .into_iter()
+                .flatten()
+                .filter_map(|i| indexes.get(i))
+                .map(|index| (raw_line(index).abs_diff(raw_index), index - offsets.first))
+                .min()
+        };
+        match source {
+            Some(source) => source_indexes.get_key_value(source).and_then(closest),
+            None => source_indexes.iter().filter_map(closest).min(),
+        }
+        .map(|(_, position)| position)
+    }
+
+    fn add_line_times(&self, raw: &[i64], filtered: &[i64]) {
+        let mut rate = self.rate.write();
+        for second in raw {
+            rate.entry(*second).or_default().0 += 1;
+        }
+        for second in filtered {
+            rate.entry(*second).or_default().1 += 1;
+        }
+    }
+
+    fn get_rate(&self) -> Vec<(i64, usize, usize)> {
+        self.rate
+            .read()
+            .iter()
+            .map(|(second, (raw, filtered))| (*second, *raw, *filtered))
+            .collect()
+    }
+
+    fn get_search_rate(&self) -> Vec<(i64, usize)> {
+        self.search_rate
+            .read()
+            .iter()
+            .map(|(second, lines)| (*second, *lines))
+            .collect()
+    }
+
+    fn get_lines_per_severity(&self) -> Vec<(String, usize)> {
+        self.severity_lines
+            .read()
+            .iter()
+            .map(|(severity, lines)| (severity.clone(), *lines))
+            .collect()
+    }
+
+    fn get_lines_per_app(&self) -> Vec<(String, usize)> {
+        self.app_lines
+            .read()
+            .iter()
+            .map(|(app, lines)| (app.clone(), *lines))
+            .collect()
+    }
+
+    fn get_lines_per_value(
+        &self,
+        column: &str,
+        search: bool,
+        time_range: Option<(f64, f64)>,
+    ) -> Option<Vec<(String, usize)>> {
+        LogLine::default().get(column)?;
+
+        let in_range = |line: &LogLine| match time_range {
+            Some((from, to)) => line.time().is_some_and(|time| from <= time && time <= to),
+            None => true,
+        };
+        let mut counts: HashMap<Cow<str>, usize> = HashMap::default();
+        let log = match search {
+            true => self.search_log.read(),
+            false => self.log.read(),
+        };
+        let log: &[Arc<LogLine>] = &log;
+        let lines: Box<dyn Iterator<Item = &Arc<LogLine>>> = match (search, time_range) {
+            // The processed lines in the range are looked up in the time index
+            (false, Some((from, to))) => Box::new(
+                self.get_indexes_in_time_range(from, to)
+                    .into_iter()
+                    .filter_map(move |index| log.get(index)),
+            ),
+            _ => Box::new(log.iter().filter(|line| in_range(line))),
+        };
+        for value in lines.filter_map(|line| line.get(column)) {
+            if !value.is_empty() {
+                *counts.entry(value).or_default() += 1;
+            }
+        }
+        Some(
+            counts
+                .into_iter()
+                .map(|(value, lines)| (value.into_owned(), lines))
+                .collect(),
+        )
+    }
+
+    fn set_correlation_key(&self, key: Option<Regex>) {
+        let log = self.log.read();
+        let mut correlated_lines = self.correlated_lines.write();
+        correlated_lines.clear();
+        if let Some(key) = &key {
+            for line in log.iter() {
+                if let Some(id) = correlation_id(key, line) {
+                    correlated_lines.entry(id).or_default().push(line.index);
+                }
+            }
+        }
+        *self.correlation_key.write() = key;
+    }
+
+    fn get_correlation_id(&self, index: usize) -> Option<String> {
+        let log = self.log.read();
+        let key = self.correlation_key.read();
+        correlation_id(key.as_ref()?, log.get(index)?)
+    }
+
+    fn get_correlated_lines(&self, id: &str) -> Vec<LogLine> {
+        let log = self.log.read();
+        let offsets = self.offsets();
+        self.correlated_lines
+            .read()
+            .get(id)
+            .map(|indexes| {
+                indexes
+                    .iter()
+                    .filter_map(|i| log.get(i - offsets.first))
+                    .map(|line| offsets.show_line(line))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn get_indexes_in_time_range(&self, from: f64, to: f64) -> Vec<usize> {
+        if from > to {
+            return Vec::new();
+        }
+        let first = self.first.load(Ordering::Relaxed);
+        let mut indexes: Vec<usize> = self
+            .time_index
+            .read()
+            .range(time_key(from)..=time_key(to))
+            .flat_map(|(_, indexes)| indexes.iter().map(|index| index - first))
+            .collect();
+        indexes.sort_unstable();
+        indexes
+    }
+
+    fn get_index_at_time(&self, time: f64) -> Option<usize> {
+        let time_index = self.time_index.read();
+        let (_, indexes) = time_index
+            .range(time_key(time)..)
+            .next()
+            .or_else(|| time_index.iter().next_back())?;
+        indexes
+            .first()
+            .map(|index| index - self.first.load(Ordering::Relaxed))
+    }
+
+    #[instrument(level = "trace", skip_all)]
+    fn apply_retention(&self, retention: &Retention, raw_discarded: &[(String, usize)]) -> usize {
+        let mut log = self.log.write();
+        let mut raw_first = self.raw_first.write();
+        for (source, discarded) in raw_discarded.iter().filter(|(_, discarded)| *discarded > 0) {
+            *raw_first.entry(source.clone()).or_default() += discarded;
+        }
+        let mut source_lines = self.source_lines.write();
+        let mut arrivals = self.arrivals.write();
+        let excess = arrivals.excess(log.len(), retention).max(lines_without_raw_line(
+            &log,
+            &raw_first,
+            &source_lines,
+            raw_discarded,
+        ));
+        if excess == 0 {
+            return 0;
+        }
+        arrivals.discard(excess);
+
+        let first = self.first.fetch_add(excess, Ordering::Relaxed) + excess;
+        let (mut severity_lines, mut app_lines) =
+            (self.severity_lines.write(), self.app_lines.write());
+        let correlation_key = self.correlation_key.read();
+        let (mut correlated_lines, mut time_index) =
+            (self.correlated_lines.write(), self.time_index.write());
+        // The indexes of the lines left are kept, only the discarded ones are unindexed
+        let unindex = |indexes: Option<&mut Vec<usize>>| match indexes {
+            Some(indexes) => {
+                indexes.retain(|index| *index >= first);
+                indexes.is_empty()
+            }
+            None => false,
+        };
+        let mut source_indexes = self.source_indexes.write();
+        for line in log.drain(..excess) {
+            decrement(&mut source_lines, &line.log);
+            if let Some(indexes) = source_indexes.get_mut(&*line.log) {
+                indexes.pop_front();
+                if indexes.is_empty() {
+                    source_indexes.remove(&*line.log);
+                }
+            }
+            decrement(&mut severity_lines, &line.severity);
+            decrement(&mut app_lines, &line.app);
+            self.log_memory
+                .fetch_sub(line.memory_size(), Ordering::Relaxed);
+            if let Some(id) = correlation_key.as_ref().and_then(|key| correlation_id(key, &line)) {
+                if unindex(correlated_lines.get_mut(&id)) {
+                    correlated_lines.remove(&id);
+                }
+            }
+            if let Some(key) = line.time().map(time_key) {
+                if unindex(time_index.get_mut(&key)) {
+                    time_index.remove(&key);
+                }
+            }
+        }
+        self.log_lines.store(log.len(), Ordering::Relaxed);
+
+        let mut search_log = self.search_log.write();
+        let mut search_source_lines = self.search_source_lines.write();
+        let discarded_search = search_log.partition_point(|line| line.index < first);
+        self.search_highlights.write().drain(..discarded_search);
+        for line in search_log.drain(..discarded_search) {
+            decrement(&mut search_source_lines, &line.log);
+            self.search_memory
+                .fetch_sub(line.memory_size(), Ordering::Relaxed);
+        }
+        self.search_lines.store(search_log.len(), Ordering::Relaxed);
+        excess
+    }
+
+    fn replace_log(&self, staged: InMemmoryAnalysisStore) {
+        staged.set_correlation_key(self.correlation_key.read().clone());
+        let (mut log, mut search_log) = (self.log.write(), self.search_log.write());
+        *log = staged.log.into_inner();
+        *search_log = staged.search_log.into_inner();
+        *self.search_highlights.write() = staged.search_highlights.into_inner();
+        self.log_memory
+            .store(staged.log_memory.into_inner(), Ordering::Relaxed);
+        self.search_memory
+            .store(staged.search_memory.into_inner(), Ordering::Relaxed);
+        self.log_lines.store(log.len(), Ordering::Relaxed);
+        self.search_lines.store(search_log.len(), Ordering::Relaxed);
+        *self.source_lines.write() = staged.source_lines.into_inner();
+        *self.source_indexes.write() = staged.source_indexes.into_inner();
+        *self.search_source_lines.write() = staged.search_source_lines.into_inner();
+        *self.severity_lines.write() = staged.severity_lines.into_inner();
+        *self.app_lines.write() = staged.app_lines.into_inner();
+        *self.rate.write() = staged.rate.into_inner();
+        *self.search_rate.write() = staged.search_rate.into_inner();
+        *self.correlated_lines.write() = staged.correlated_lines.into_inner();
+        *self.time_index.write() = staged.time_index.into_inner();
+        *self.arrivals.write() = staged.arrivals.into_inner();
+        self.first
+            .store(staged.first.into_inner(), Ordering::Relaxed);
+        *self.raw_first.write() = staged.raw_first.into_inner();
+    }
+
+    fn replace_search(&self, staged: InMemmoryAnalysisStore) {
+        let mut search_log = self.search_log.write();
+        *search_log = staged.search_log.into_inner();
+        *self.search_highlights.write() = staged.search_highlights.into_inner();
+        self.search_memory
+            .store(staged.search_memory.into_inner(), Ordering::Relaxed);
+        self.search_lines.store(search_log.len(), Ordering::Relaxed);
+        *self.search_source_lines.write() = staged.search_source_lines.into_inner();
+        *self.search_rate.write() = staged.search_rate.into_inner();
+    }
+
+    fn clear(&self) {
+        self.reset_log();
+        self.reset_search();
+        *self.search_query.write() = None;
     }
 }
 
 impl InMemmoryAnalysisStore {
-    fn find_sorted_index(source: &[LogLine], index: usize) -> usize {
-        match source.binary_search_by(|e| {
-            e.index
-                .parse::<usize>()
-                .unwrap()
-                .cmp(&index)
-        }) {
-            Ok(i) => i,
-            Err(i) => i,
+    /// Offsets of the lines stored, to be locked after the log
+    fn offsets(&self) -> Offsets<'_> {
+        Offsets {
+            first: self.first.load(Ordering::Relaxed),
+            raw_first: self.raw_first.read(),
         }
     }
 
+    /// The search lines of `search_log` at `positions` styled with their highlights
+    fn styled_search_lines(
+        &self,
+        search_log: &[Arc<LogLine>],
+        positions: Range<usize>,
+    ) -> Vec<LogLineStyled> {
+        let search_highlights = self.search_highlights.read();
+        let offsets = self.offsets();
+        search_log[positions.clone()]
+            .iter()
+            .zip(&search_highlights[positions])
+            .map(|(line, highlights)| LogLineStyled::new(&offsets.show(line), highlights))
+            .collect()
+    }
+
+    fn find_sorted_index(source: &[Arc<LogLine>], index: usize) -> usize {
+        source
+            .binary_search_by_key(&index, |e| e.index)
+            .unwrap_or_else(|i| i)
+    }
+
     /// Find a window of elements containing the target in the middle
     /// Returns (elements, offset, index)
     fn find_rolling_window(
-        source: &[LogLine],
+        source: &[Arc<LogLine>],
         index: usize,
         elements: usize,
-    ) -> (Vec<LogLine>, usize, usize) {
+    ) -> (Vec<Arc<LogLine>>, usize, usize) {
         let closest = InMemmoryAnalysisStore::find_sorted_index(source, index);
-        let from = if (elements / 2) < closest {
-            closest - elements / 2
-        } else {
-            0
-        };
+        let from = closest.saturating_sub(elements / 2);
         let to = (closest + elements / 2).min(source.len());
 
         let lines = source[from..to].to_vec();
@@ -193,8 +878,346 @@ mod tests {
 
     fn log_line_with_index(index: usize) -> LogLine {
         LogLine {
-            index: index.to_string(),
+            index,
             ..Default::default()
         }
     }
+
+    #[test]
+    fn rolling_window_is_centered_on_target() {
+        let lines: Vec<Arc<LogLine>> = (0..100).map(log_line_with_index).map(Arc::new).collect();
+
+        let (window, offset, index) = InMemmoryAnalysisStore::find_rolling_window(&lines, 50, 10);
+
+        assert_eq!(offset, 45);
+        assert_eq!(window.len(), 10);
+        assert_eq!(window[index].index, 50);
+    }
+
+    #[test]
+    fn added_lines_are_assigned_consecutive_indexes() {
+        let store = InMemmoryAnalysisStore::new();
+        let lines: Vec<LogLine> = (0..10).map(log_line_with_index).collect();
+
+        assert_eq!(store.add_lines(&lines[..4]), 0..4);
+        assert_eq!(store.add_lines(&lines[4..]), 4..10);
+        assert_eq!(store.fetch_log()[7].index, 7);
+        assert!(Arc::ptr_eq(&store.get_log_lines(7, 8)[0], &store.fetch_log()[7]));
+    }
+
+    #[test]
+    fn retention_discards_the_oldest_lines_and_indexes_the_rest_again() {
+        let store = InMemmoryAnalysisStore::new();
+        let lines: Vec<LogLine> = (0..10)
+            .map(|i| LogLine {
+                log: format!("{}.log", i % 2).into(),
+                raw_index: i / 2,
+                payload: i.to_string().into(),
+                ..Default::default()
+            })
+            .collect();
+        store.add_lines(&lines);
+        let search: Vec<(LogLine, Vec<Highlight>)> = [2, 7]
+            .iter()
+            .map(|i| {
+                let line = LogLine {
+                    index: *i,
+                    ..lines[*i].clone()
+                };
+                let highlight = Highlight {
+                    column: 7,
+                    group: "RED".into(),
+                    range: 0..1,
+                };
+                (line, vec![highlight])
+            })
+            .collect();
+        store.add_search_lines(&search);
+
+        let retention = Retention {
+            max_lines: Some(6),
+            ..Default::default()
+        };
+        let raw_discarded = [("1.log".to_string(), 2)];
+        let window = store.get_log_lines(4, 5);
+        assert_eq!(store.apply_retention(&retention, &raw_discarded), 4);
+        assert_eq!(store.apply_retention(&retention, &[]), 0);
+        let log = store.get_log_lines(0, 10);
+        assert_eq!(log.len(), 6);
+        assert_eq!((log[0].index, log[0].payload.as_str()), (0, "4"));
+        assert_eq!((log[0].raw_index, log[1].raw_index), (2, 0));
+        // The window handed out before keeps the indexes it was read with
+        assert_eq!((window[0].index, window[0].raw_index), (4, 2));
+        let search = store.get_search_lines(0, 10);
+        assert_eq!(search.len(), 1);
+        let line = search[0].unformat();
+        assert_eq!((line.index, line.payload.as_str()), (3, "7"));
+        assert_eq!(search[0].payload, vec![(Some("RED".to_string()), "7".to_string())]);
+        assert_eq!(
+            store.get_lines_per_source(),
+            vec![("0.log".to_string(), 3), ("1.log".to_string(), 3)]
+        );
+    }
+
+    #[test]
+    fn retention_discards_the_lines_whose_raw_line_is_gone() {
+        let store = InMemmoryAnalysisStore::new();
+        let lines: Vec<LogLine> = (0..10)
+            .map(|i| LogLine {
+                log: format!("{}.log", i % 2).into(),
+                raw_index: i / 2,
+                timestamp: i.to_string().into(),
+                payload: i.to_string().into(),
+                ..Default::default()
+            })
+            .collect();
+        store.add_lines(&lines);
+        store.set_correlation_key(Some(Regex::new(r"\d").unwrap()));
+
+        // The first 3 raw lines of 1.log are gone, so are the lines of 0.log before them
+        let raw_discarded = [("1.log".to_string(), 3)];
+        assert_eq!(store.apply_retention(&Retention::default(), &raw_discarded), 6);
+        let log = store.get_log_lines(0, 10);
+        assert_eq!(log.len(), 4);
+        assert_eq!((log[0].index, log[0].raw_index, log[0].payload.as_str()), (0, 3, "6"));
+        assert_eq!((log[1].index, log[1].raw_index, log[1].payload.as_str()), (1, 0, "7"));
+        assert_eq!(store.get_indexes_in_time_range(0.0, 100.0), vec![0, 1, 2, 3]);
+        assert_eq!(store.get_correlated_lines("5"), Vec::<LogLine>::new());
+        assert_eq!(store.get_correlated_lines("8")[0].index, 2);
+
+        // The lines added next follow the ones left
+        let next = LogLine {
+            log: "1.log".into(),
+            raw_index: 2,
+            ..Default::default()
+        };
+        assert_eq!(store.add_lines(std::slice::from_ref(&next)), 4..5);
+        store.add_search_lines(&[(LogLine { index: 4, ..next }, vec![])]);
+        assert_eq!(store.get_log_lines(4, 5)[0].raw_index, 2);
+        let search = store.get_search_lines_containing(4, 10).0;
+        assert_eq!((search[0].unformat().index, search[0].unformat().raw_index), (4, 2));
+        assert_eq!(store.get_source_lines("1.log", 1)[0].raw_index, 0);
+    }
+
+    #[test]
+    fn raw_lines_are_found_in_their_source() {
+        let store = InMemmoryAnalysisStore::new();
+        let line = |log: &str, raw_index| LogLine {
+            log: log.into(),
+            raw_index,
+            ..Default::default()
+        };
+        store.add_lines(&[line("a", 0), line("b", 0), line("a", 4), line("b", 9), line("a", 8)]);
+
+        assert_eq!(store.find_raw_line(Some("a"), 4), Some(2));
+        assert_eq!(store.find_raw_line(Some("a"), 7), Some(4));
+        assert_eq!(store.find_raw_line(Some("b"), 5), Some(3));
+        assert_eq!(store.find_raw_line(Some("c"), 0), None);
+        assert_eq!(store.find_raw_line(None, 9), Some(3));
+
+        // The raw lines discarded move the raw indexes of their source back
+        let retention = Retention::default();
+        assert_eq!(store.apply_retention(&retention, &[("a".to_string(), 4)]), 1);
+        assert_eq!(store.find_raw_line(Some("a"), 0), Some(1));
+        assert_eq!(store.find_raw_line(Some("b"), 0), Some(0));
+    }
+
+    #[test]
+    fn memory_usage_is_tracked_and_reset() {
+        let store = InMemmoryAnalysisStore::new();
+        let lines: Vec<LogLine> = (0..10).map(log_line_with_index).collect();
+
+        store.add_lines(&lines);
+        let search: Vec<_> = lines[..5].iter().map(|line| (line.clone(), vec![])).collect();
+        store.add_search_lines(&search);
+        assert!(store.get_log_memory_usage() > store.get_search_memory_usage());
+        assert!(store.get_search_memory_usage() > 0);
+
+        store.reset_log();
+        store.reset_search();
+        assert_eq!(store.get_log_memory_usage(), 0);
+        assert_eq!(store.get_search_memory_usage(), 0);
+    }
+
+    #[test]
+    fn lines_are_grouped_by_source() {
+        let store = InMemmoryAnalysisStore::new();
+        let line = |log: &str| LogLine {
+            log: log.into(),
+            ..Default::default()
+        };
+
+        store.add_lines(&[line("b.log"), line("a.log"), line("b.log")]);
+        store.add_search_lines(&[(line("b.log"), vec![])]);
+        assert_eq!(
+            store.get_lines_per_source(),
+            vec![("a.log".to_string(), 1), ("b.log".to_string(), 2)]
+        );
+        assert_eq!(
+            store.get_search_lines_per_source(),
+            vec![("b.log".to_string(), 1)]
+        );
+        assert_eq!(store.get_source_lines("b.log", 1).len(), 1);
+
+        store.reset_log();
+        store.reset_search();
+        assert!(store.get_lines_per_source().is_empty());
+        assert!(store.get_search_lines_per_source().is_empty());
+    }
+
+    #[test]
+    fn statistics_are_counted_and_reset() {
+        let store = InMemmoryAnalysisStore::new();
+        let line = |severity: &str| LogLine {
+            severity: severity.into(),
+            ..Default::default()
+        };
+
+        store.add_lines(&[line("INFO"), line("INFO"), line("")]);
+        store.add_line_times(&[10, 10, 12], &[10]);
+        assert_eq!(store.get_lines_per_severity(), vec![("INFO".to_string(), 2)]);
+        assert!(store.get_lines_per_app().is_empty());
+        assert_eq!(store.get_rate(), vec![(10, 2, 1), (12, 1, 0)]);
+        let line = LogLine {
+            timestamp: "10.5".into(),
+            ..Default::default()
+        };
+        store.add_search_lines(&[(line, vec![])]);
+        assert_eq!(store.get_search_rate(), vec![(10, 1)]);
+
+        store.reset_log();
+        store.reset_search();
+        assert!(store.get_search_rate().is_empty());
+        assert!(store.get_lines_per_severity().is_empty());
+        assert!(store.get_rate().is_empty());
+    }
+
+    #[test]
+    fn clear_forgets_the_search_query() {
+        let store = InMemmoryAnalysisStore::new();
+        let lines: Vec<LogLine> = (0..10).map(log_line_with_index).collect();
+
+        store.add_lines(&lines);
+        store.add_search_query("1");
+        store.add_search_lines(&[(lines[1].clone(), vec![])]);
+
+        store.clear();
+        assert_eq!(store.get_total_filtered_lines(), 0);
+        assert_eq!(store.get_total_searched_lines(), 0);
+        assert_eq!(store.get_search_query(), None);
+    }
+
+    #[test]
+    fn replaced_log_keeps_the_query_and_the_correlation_key() {
+        let store = InMemmoryAnalysisStore::new();
+        let line = |payload: &str| LogLine {
+            payload: payload.into(),
+            ..Default::default()
+        };
+        store.add_lines(&[line("start req=1"), line("idle")]);
+        store.add_search_query("req");
+        store.add_search_lines(&[(line("start req=1"), vec![])]);
+        store.set_correlation_key(Some(Regex::new(r"req=(\d+)").unwrap()));
+        let window = store.get_log_lines(0, 2);
+
+        let staged = InMemmoryAnalysisStore::new();
+        staged.add_lines(&[line("idle"), line("end req=1"), line("end req=2")]);
+        staged.add_search_lines(&[(line("end req=1"), vec![]), (line("end req=2"), vec![])]);
+        store.replace_log(staged);
+
+        assert_eq!(store.get_total_filtered_lines(), 3);
+        assert_eq!(store.get_total_searched_lines(), 2);
+        assert_eq!(store.get_search_query().as_deref(), Some("req"));
+        assert_eq!(store.get_correlation_id(1).as_deref(), Some("1"));
+        assert_eq!(store.get_correlated_lines("2")[0].index, 2);
+        assert_eq!(window[1].payload, "idle");
+
+        let staged = InMemmoryAnalysisStore::new();
+        staged.add_search_lines(&[(line("end req=2"), vec![])]);
+        store.replace_search(staged);
+        assert_eq!(store.get_total_searched_lines(), 1);
+        assert_eq!(store.get_total_filtered_lines(), 3);
+    }
+
+    #[test]
+    fn lines_are_counted_per_column_value() {
+        let store = InMemmoryAnalysisStore::new();
+        let line = |app: &str, timestamp: &str| LogLine {
+            app: app.into(),
+            timestamp: timestamp.into(),
+            ..Default::default()
+        };
+        store.add_lines(&[line("netd", "1"), line("wifi", "2"), line("netd", "3"), line("", "4")]);
+        store.add_search_lines(&[(line("wifi", "2"), vec![])]);
+
+        let mut apps = store.get_lines_per_value("App", false, None).unwrap();
+        apps.sort();
+        assert_eq!(apps, vec![("netd".to_string(), 2), ("wifi".to_string(), 1)]);
+        let mut apps = store.get_lines_per_value("App", false, Some((2.0, 4.0))).unwrap();
+        apps.sort();
+        assert_eq!(apps, vec![("netd".to_string(), 1), ("wifi".to_string(), 1)]);
+        assert_eq!(
+            store.get_lines_per_value("App", true, None),
+            Some(vec![("wifi".to_string(), 1)])
+        );
+        assert_eq!(store.get_lines_per_value("Function", false, None), Some(vec![]));
+        assert_eq!(store.get_lines_per_value("Unknown", false, None), None);
+    }
+
+    #[test]
+    fn lines_are_indexed_by_correlation_id() {
+        let store = InMemmoryAnalysisStore::new();
+        let line = |payload: &str| LogLine {
+            payload: payload.into(),
+            ..Default::default()
+        };
+        store.add_lines(&[line("start req=1"), line("start req=2"), line("idle")]);
+        store.set_correlation_key(Some(Regex::new(r"req=(\d+)").unwrap()));
+        store.add_lines(&[line("end req=1")]);
+
+        assert_eq!(store.get_correlation_id(3).as_deref(), Some("1"));
+        assert_eq!(store.get_correlation_id(2), None);
+        let indexes = |id: &str| -> Vec<String> {
+            store.get_correlated_lines(id).into_iter().map(|line| line.index.to_string()).collect()
+        };
+        assert_eq!(indexes("1"), vec!["0", "3"]);
+        assert_eq!(indexes("2"), vec!["1"]);
+
+        store.reset_log();
+        assert!(indexes("1").is_empty());
+        store.add_lines(&[line("retry req=2")]);
+        assert_eq!(indexes("2"), vec!["0"]);
+
+        store.set_correlation_key(None);
+        assert!(indexes("2").is_empty());
+        assert_eq!(store.get_correlation_id(0), None);
+    }
+
+    #[test]
+    fn lines_are_indexed_by_time() {
+        let store = InMemmoryAnalysisStore::new();
+        let line = |timestamp: &str| LogLine {
+            timestamp: timestamp.into(),
+            ..Default::default()
+        };
+        store.add_lines(&[line("10"), line("30"), line(""), line("20"), line("30.5")]);
+
+        assert_eq!(store.get_indexes_in_time_range(15.0, 30.0), vec![1, 3]);
+        assert_eq!(store.get_indexes_in_time_range(30.0, 15.0), Vec::<usize>::new());
+        assert_eq!(store.get_index_at_time(0.0), Some(0));
+        assert_eq!(store.get_index_at_time(25.0), Some(1));
+        assert_eq!(store.get_index_at_time(30.25), Some(4));
+        assert_eq!(store.get_index_at_time(100.0), Some(4));
+
+        let retention = Retention {
+            max_lines: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(store.apply_retention(&retention, &[]), 2);
+        assert_eq!(store.get_indexes_in_time_range(0.0, 100.0), vec![1, 2]);
+        assert_eq!(store.get_index_at_time(0.0), Some(1));
+
+        store.reset_log();
+        assert_eq!(store.get_index_at_time(0.0), None);
+    }
 }
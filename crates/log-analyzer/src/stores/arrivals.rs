@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::models::retention::Retention;
+
+/// Lines received at each instant, oldest first, to find the lines older than a `Retention`
+///
+/// The lines received within a second are counted together, so they expire together
+#[derive(Default, Debug)]
+pub struct Arrivals {
+    batches: VecDeque<(Instant, usize)>,
+}
+
+impl Arrivals {
+    /// Count `lines` received now
+    pub fn add(&mut self, lines: usize) {
+        if lines == 0 {
+            return;
+        }
+        let now = Instant::now();
+        match self.batches.back_mut() {
+            Some((received, count)) if now.duration_since(*received) < Duration::from_secs(1) => {
+                *count += lines
+            }
+            _ => self.batches.push_back((now, lines)),
+        }
+    }
+
+    /// Number of the oldest of `len` lines to discard to respect the limits of `retention`
+    pub fn excess(&self, len: usize, retention: &Retention) -> usize {
+        let over_limit = retention
+            .max_lines
+            .map_or(0, |max_lines| len.saturating_sub(max_lines));
+        let expired = retention
+            .max_age_secs
+            .and_then(|max_age| Instant::now().checked_sub(Duration::from_secs(max_age)))
+            .map_or(0, |deadline| {
+                self.batches
+                    .iter()
+                    .take_while(|(received, _)| *received < deadline)
+                    .map(|(_, count)| count)
+                    .sum()
+            });
+        over_limit.max(expired).min(len)
+    }
+
+    /// Forget the `lines` oldest lines
+    pub fn discard(&mut self, mut lines: usize) {
+        while lines > 0 {
+            match self.batches.front_mut() {
+                Some((_, count)) if *count <= lines => {
+                    lines -= *count;
+                    self.batches.pop_front();
+                }
+                Some((_, count)) => {
+                    *count -= lines;
+                    lines = 0;
+                }
+                None => break,
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.batches.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excess_lines_are_the_oldest_beyond_the_limits() {
+        let mut arrivals = Arrivals::default();
+        arrivals
+            .batches
+            .push_back((Instant::now() - Duration::from_secs(120), 30));
+        arrivals.add(20);
+        arrivals.add(10);
+
+        let by_lines = Retention {
+            max_lines: Some(45),
+            ..Default::default()
+        };
+        let by_age = Retention {
+            max_age_secs: Some(60),
+            ..Default::default()
+        };
+        assert_eq!(arrivals.excess(60, &by_lines), 15);
+        assert_eq!(arrivals.excess(60, &by_age), 30);
+        assert_eq!(arrivals.excess(60, &Retention::default()), 0);
+
+        arrivals.discard(35);
+        assert_eq!(arrivals.batches.len(), 1);
+        assert_eq!(arrivals.batches[0].1, 25);
+        assert_eq!(arrivals.excess(25, &by_age), 0);
+    }
+}
@@ -0,0 +1,209 @@
+use std::ops::Range;
+
+use tracing::error;
+
+/// Bytes of lines gathered before compressing them in a block, the window of lz4
+const BLOCK_BYTES: usize = 64 * 1024;
+
+/// Estimation of the memory used by an uncompressed line
+pub fn line_memory_size(line: &str) -> usize {
+    std::mem::size_of::<String>() + line.len()
+}
+
+/// lz4 compressed lines, each one prefixed by its length
+struct Block {
+    data: Vec<u8>,
+    lines: usize,
+}
+
+impl Block {
+    fn compress(lines: &[String]) -> Self {
+        let mut bytes = Vec::with_capacity(lines.iter().map(|line| line.len() + 4).sum());
+        for line in lines {
+            bytes.extend_from_slice(&(line.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(line.as_bytes());
+        }
+        Self {
+            data: lz4_flex::compress_prepend_size(&bytes),
+            lines: lines.len(),
+        }
+    }
+
+    fn decompress(&self, lines: &mut Vec<String>) {
+        let bytes = match lz4_flex::decompress_size_prepended(&self.data) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                error!(error = %err, "Could not decompress a block of raw lines");
+                return;
+            }
+        };
+        let mut rest = bytes.as_slice();
+        while rest.len() >= 4 {
+            let len = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            let line = &rest[4..(4 + len).min(rest.len())];
+            lines.push(String::from_utf8_lossy(line).into_owned());
+            rest = &rest[4 + line.len()..];
+        }
+    }
+
+    fn memory_size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.data.len()
+    }
+}
+
+/// Raw lines of a log, compressed in blocks of about 64 KB as they are received
+///
+/// The last lines are kept as they are until they fill a block. The blocks are decompressed
+/// on demand, to process the lines again. Repetitive logs take several times less memory
+#[derive(Default)]
+pub struct CompressedLines {
+    blocks: Vec<Block>,
+    tail: Vec<String>,
+    tail_bytes: usize,
+    len: usize,
+    memory: usize,
+}
+
+impl CompressedLines {
+    /// Add `lines` after the others
+    pub fn extend<S: AsRef<str>>(&mut self, lines: &[S]) {
+        for line in lines {
+            let line = line.as_ref();
+            self.tail_bytes += line.len();
+            self.memory += line_memory_size(line);
+            self.tail.push(line.to_string());
+            if self.tail_bytes >= BLOCK_BYTES {
+                self.seal();
+            }
+        }
+        self.len += lines.len();
+    }
+
+    /// Compress the last lines in a new block
+    fn seal(&mut self) {
+        let block = Block::compress(&self.tail);
+        self.memory -= self.tail.iter().map(|line| line_memory_size(line)).sum::<usize>();
+        self.memory += block.memory_size();
+        self.blocks.push(block);
+        self.tail.clear();
+        self.tail_bytes = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Estimation of the memory used by the lines in bytes
+    pub fn memory_size(&self) -> usize {
+        self.memory
+    }
+
+    /// Decompress every line
+    pub fn to_vec(&self) -> Vec<String> {
+        let mut lines = Vec::with_capacity(self.len);
+        for block in &self.blocks {
+            block.decompress(&mut lines);
+        }
+        lines.extend(self.tail.iter().cloned());
+        lines
+    }
+
+    /// Decompress the lines in the `range` of positions, only the blocks holding them
+    pub fn range(&self, range: Range<usize>) -> Vec<String> {
+        let (from, to) = (range.start.min(self.len), range.end.min(self.len));
+        let mut lines = Vec::with_capacity(to.saturating_sub(from));
+        let mut first = 0;
+        for block in &self.blocks {
+            let last = first + block.lines;
+            if first < to && from < last {
+                let mut decompressed = Vec::with_capacity(block.lines);
+                block.decompress(&mut decompressed);
+                let end = (to - first).min(decompressed.len());
+                lines.extend(decompressed.drain(from.saturating_sub(first).min(end)..end));
+            }
+            first = last;
+        }
+        if from < to && to > first {
+            lines.extend_from_slice(&self.tail[from.saturating_sub(first)..to - first]);
+        }
+        lines
+    }
+
+    /// Forget the `lines` oldest lines. The block holding the first line left is compressed
+    /// again without the lines before it
+    pub fn discard(&mut self, lines: usize) {
+        let mut left = lines.min(self.len);
+        self.len -= left;
+
+        let whole_blocks = self
+            .blocks
+            .iter()
+            .take_while(|block| {
+                let whole = block.lines <= left;
+                if whole {
+                    left -= block.lines;
+                }
+                whole
+            })
+            .count();
+        for block in self.blocks.drain(..whole_blocks) {
+            self.memory -= block.memory_size();
+        }
+        if left == 0 {
+            return;
+        }
+
+        if let Some(block) = self.blocks.first_mut() {
+            let mut kept = Vec::with_capacity(block.lines);
+            block.decompress(&mut kept);
+            self.memory -= block.memory_size();
+            *block = Block::compress(&kept[left.min(kept.len())..]);
+            self.memory += block.memory_size();
+        } else {
+            for line in self.tail.drain(..left) {
+                self.tail_bytes -= line.len();
+                self.memory -= line_memory_size(&line);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compressed_lines_are_restored_in_order() {
+        let lines: Vec<String> = (0..5000)
+            .map(|i| format!("2022-06-05 12:00:00 INFO [server] Request {} served in 3ms", i))
+            .collect();
+        let mut compressed = CompressedLines::default();
+        compressed.extend(&lines[..10]);
+        compressed.extend(&lines[10..]);
+
+        assert_eq!(compressed.len(), 5000);
+        assert!(compressed.blocks.len() > 1);
+        assert!(
+            compressed.memory_size() * 3 < lines.iter().map(|line| line_memory_size(line)).sum()
+        );
+        assert_eq!(compressed.to_vec(), lines);
+        let first_block = compressed.blocks[0].lines;
+        let range = first_block - 2..first_block + 3;
+        assert_eq!(compressed.range(range.clone()), lines[range]);
+        assert_eq!(compressed.range(4990..6000), lines[4990..]);
+        assert!(compressed.range(6000..7000).is_empty());
+
+        compressed.discard(first_block + 7);
+        assert_eq!(compressed.len(), 5000 - first_block - 7);
+        assert_eq!(compressed.to_vec(), lines[first_block + 7..]);
+
+        compressed.discard(5000);
+        assert!(compressed.is_empty());
+        assert!(compressed.to_vec().is_empty());
+        assert_eq!(compressed.memory_size(), 0);
+    }
+}
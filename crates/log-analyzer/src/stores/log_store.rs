@@ -1,7 +1,19 @@
+use crate::models::retention::Retention;
 use log_source::source::log_source::LogSource;
 use parking_lot::RwLock;
 use rustc_hash::FxHashMap as HashMap;
-use std::{iter::Iterator, ops::Range, sync::Arc};
+use tracing::{instrument, trace};
+use std::{
+    iter::Iterator,
+    ops::Range,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use super::arrivals::Arrivals;
+use super::compressed_lines::CompressedLines;
 
 /// Store holding raw information
 ///
@@ -18,32 +30,53 @@ pub trait LogStore {
     /// Add a single line to the given log id
     fn add_line(&self, log_id: &str, line: &str);
     /// Add a many lines to the given log id
-    fn add_lines(&self, log_id: &str, lines: &[String]) -> Range<usize>;
+    fn add_lines(&self, log_id: &str, lines: &[&str]) -> Range<usize>;
     /// Get the format associated to the given log id
     fn get_format(&self, log_id: &str) -> Option<String>;
+    /// Change the format associated to the given log id, `None` to leave it unformatted
+    fn set_format(&self, log_id: &str, format: Option<&String>);
     /// Get a list of (enabled, log_id, format(if any))
     fn get_logs(&self) -> Vec<(bool, String, Option<String>)>;
     /// Get the log source associated to the log id
     fn get_source(&self, id: &str) -> Option<Arc<Box<dyn LogSource + Send + Sync>>>;
     /// Get a list of all the lines for the requested log. WARNING: clones
     fn get_lines(&self, log_id: &str) -> Vec<String>;
-    /// Get a list of all the lines for the requested log. WARNING: moves
-    fn extract_lines(&self, log_id: &str) -> Vec<String>;
+    /// Get the lines of the requested log in the `lines` range of raw indexes, leaving them in
+    /// the store
+    fn get_lines_range(&self, log_id: &str, lines: Range<usize>) -> Vec<String>;
     /// Get the count of all the lines
     fn get_total_lines(&self) -> usize;
+    /// Count the lines of each log
+    ///
+    /// Returns a list of (log_id, lines) in the order the logs received their first lines
+    fn get_lines_per_source(&self) -> Vec<(String, usize)>;
+    /// Get an estimation of the memory used by the raw lines in bytes
+    fn get_memory_usage(&self) -> usize;
     /// Change the enabled state of the given log
     fn toggle_log(&self, log_id: &str);
+    /// Discard the oldest lines of each log beyond the limits of `retention`. The lines left
+    /// take the raw indexes from 0
+    ///
+    /// Returns a list of (log_id, discarded lines) for the logs with discarded lines
+    fn apply_retention(&self, retention: &Retention) -> Vec<(String, usize)>;
+    /// Remove every log and its lines
+    fn clear(&self);
 }
 
+/// Store of the raw lines in memory, compressed in blocks as they are received
 pub struct InMemmoryLogStore {
     /// K: log_path -> V: lines
-    raw_lines: RwLock<Vec<(String, Vec<String>)>>,
+    raw_lines: RwLock<Vec<(String, CompressedLines)>>,
     /// K: log_path -> V: format
     format: RwLock<HashMap<String, String>>,
     /// K: log_path -> V: enabled
     enabled: RwLock<HashMap<String, bool>>,
     /// K: log_path -> V: source controller
     source: RwLock<HashMap<String, Arc<Box<dyn LogSource + Send + Sync>>>>,
+    /// K: log_path -> V: when its lines were received
+    arrivals: RwLock<HashMap<String, Arrivals>>,
+    /// Lines of every log, read without locking them
+    total_lines: AtomicUsize,
 }
 
 impl InMemmoryLogStore {
@@ -53,6 +86,8 @@ impl InMemmoryLogStore {
             format: RwLock::new(HashMap::default()),
             enabled: RwLock::new(HashMap::default()),
             source: RwLock::new(HashMap::default()),
+            arrivals: RwLock::new(HashMap::default()),
+            total_lines: AtomicUsize::new(0),
         }
     }
 }
@@ -89,38 +124,50 @@ impl LogStore for InMemmoryLogStore {
         let mut raw_lines_lock = self.raw_lines.write();
 
         if !raw_lines_lock.iter().any(|(id, _)| log_id == id) {
-            raw_lines_lock.push((log_id.to_string(), Vec::new()));
+            raw_lines_lock.push((log_id.to_string(), CompressedLines::default()));
         }
         let raw_lines = raw_lines_lock.iter_mut().find(|(id, _)| log_id == id).unwrap();
-        raw_lines.1.push(line.to_string());
+        raw_lines.1.extend(&[line]);
+        self.arrivals.write().entry(log_id.to_string()).or_default().add(1);
+        self.total_lines.fetch_add(1, Ordering::Relaxed);
     }
 
-    fn add_lines(&self, log_id: &str, lines: &[String]) -> Range<usize> {
+    fn add_lines(&self, log_id: &str, lines: &[&str]) -> Range<usize> {
         let mut raw_lines_lock = self.raw_lines.write();
 
         if !raw_lines_lock.iter().any(|(id, _)| log_id == id) {
-            raw_lines_lock.push((log_id.to_string(), Vec::new()));
+            raw_lines_lock.push((log_id.to_string(), CompressedLines::default()));
         }
         let (_, raw_lines) = raw_lines_lock.iter_mut().find(|(id, _)| log_id == id).unwrap();
         let current_len = raw_lines.len();
-        raw_lines.append(&mut lines.to_vec());
+        raw_lines.extend(lines);
+        self.arrivals
+            .write()
+            .entry(log_id.to_string())
+            .or_default()
+            .add(lines.len());
 
         let new_len = raw_lines.len();
+        self.total_lines.fetch_add(new_len - current_len, Ordering::Relaxed);
+        if new_len > current_len {
+            trace!(source = log_id, lines = lines.len(), "Stored raw lines");
+        }
         current_len..new_len
     }
 
     fn get_lines(&self, log_id: &str) -> Vec<String> {
         match self.raw_lines.read().iter().find(|(id, _)| log_id == id) {
-            Some((_, lines)) => lines.clone(),
+            Some((_, lines)) => lines.to_vec(),
             _ => Vec::new(),
         }
     }
 
-    fn extract_lines(&self, log_id: &str) -> Vec<String> {
-        let mut w = self.raw_lines.write();
-        let (_, lines) = std::mem::take(w.iter_mut().find(|(id, _)| log_id == id).unwrap());
-
-        lines
+    #[instrument(level = "trace", skip(self))]
+    fn get_lines_range(&self, log_id: &str, lines: Range<usize>) -> Vec<String> {
+        match self.raw_lines.read().iter().find(|(id, _)| log_id == id) {
+            Some((_, raw_lines)) => raw_lines.range(lines),
+            _ => Vec::new(),
+        }
     }
 
     fn get_logs(&self) -> Vec<(bool, String, Option<String>)> {
@@ -138,11 +185,32 @@ impl LogStore for InMemmoryLogStore {
         format_lock.get(log_id).cloned()
     }
 
+    fn set_format(&self, log_id: &str, format: Option<&String>) {
+        let mut format_lock = self.format.write();
+        match format {
+            Some(format) => format_lock.insert(log_id.to_string(), format.clone()),
+            None => format_lock.remove(log_id),
+        };
+    }
+
     fn get_total_lines(&self) -> usize {
+        self.total_lines.load(Ordering::Relaxed)
+    }
+
+    fn get_lines_per_source(&self) -> Vec<(String, usize)> {
         self.raw_lines
             .read()
             .iter()
-            .fold(0, |acc, (_, v)| acc + v.len())
+            .map(|(id, lines)| (id.clone(), lines.len()))
+            .collect()
+    }
+
+    fn get_memory_usage(&self) -> usize {
+        self.raw_lines
+            .read()
+            .iter()
+            .map(|(_, lines)| lines.memory_size())
+            .sum()
     }
 
     fn get_source(&self, id: &str) -> Option<Arc<Box<dyn LogSource + Send + Sync>>> {
@@ -163,4 +231,39 @@ impl LogStore for InMemmoryLogStore {
             *e = !*e;
         }
     }
+
+    fn apply_retention(&self, retention: &Retention) -> Vec<(String, usize)> {
+        let mut raw_lines_lock = self.raw_lines.write();
+        let mut arrivals_lock = self.arrivals.write();
+
+        let mut discarded = Vec::new();
+        for (id, lines) in raw_lines_lock.iter_mut() {
+            let arrivals = arrivals_lock.entry(id.clone()).or_default();
+            let excess = arrivals.excess(lines.len(), retention);
+            if excess == 0 {
+                continue;
+            }
+            arrivals.discard(excess);
+            lines.discard(excess);
+            self.total_lines.fetch_sub(excess, Ordering::Relaxed);
+            discarded.push((id.clone(), excess));
+        }
+        discarded
+    }
+
+    fn clear(&self) {
+        let (mut raw_lines_lock, mut format_lock, mut enabled_lock, mut source_lock) = (
+            self.raw_lines.write(),
+            self.format.write(),
+            self.enabled.write(),
+            self.source.write(),
+        );
+
+        raw_lines_lock.clear();
+        format_lock.clear();
+        enabled_lock.clear();
+        source_lock.clear();
+        self.arrivals.write().clear();
+        self.total_lines.store(0, Ordering::Relaxed);
+    }
 }
@@ -1,3 +1,8 @@
 pub mod log_store;
 pub mod processing_store;
-pub mod analysis_store;
\ No newline at end of file
+pub mod analysis_store;
+pub mod arrivals;
+pub mod compressed_lines;
+pub mod regex_cache;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
@@ -1,7 +1,9 @@
 use crate::models::{
+    column_filter::ColumnFilter,
     filter::{Filter, FilterAction},
     format::Format,
     log_line::LogLine,
+    severity::Severity,
 };
 use parking_lot::RwLock;
 
@@ -17,20 +19,44 @@ pub trait ProcessingStore {
     fn get_format(&self, id: &str) -> Option<String>;
     /// Get a list of formats
     fn get_formats(&self) -> Vec<Format>;
+    /// Remove the format, returning whether it existed
+    fn remove_format(&self, id: &str) -> bool;
+    /// Change the alias of the format, returning whether it existed
+    fn rename_format(&self, id: &str, new_id: &str) -> bool;
     /// Add a new filter to the store
     /// * `id`: alias
     /// * `filter`: log line regex definitions
     fn add_filter(&self, id: String, filter: LogLine, action: FilterAction, enabled: bool);
     /// Get a list of filters together with their enabled state
     fn get_filters(&self) -> Vec<(bool, Filter)>;
+    /// Remove the filter, returning its enabled state if it existed
+    fn remove_filter(&self, id: &str) -> Option<bool>;
+    /// Change the alias of the filter, returning whether it existed
+    fn rename_filter(&self, id: &str, new_id: &str) -> bool;
     /// Switch the enabled state for the given filter
     fn toggle_filter(&self, id: &str);
+    /// Switch the visibility of the lines with the given severity
+    fn toggle_severity(&self, severity: Severity);
+    /// Get the list of severities currently hidden
+    fn get_hidden_severities(&self) -> Vec<Severity>;
+    /// Add an ad-hoc column filter, replacing any previous one on the same column
+    fn add_column_filter(&self, column_filter: ColumnFilter);
+    /// Remove the ad-hoc filter of the given column
+    fn remove_column_filter(&self, column: &str);
+    /// Get the ad-hoc column filters in creation order
+    fn get_column_filters(&self) -> Vec<ColumnFilter>;
+    /// Remove every format, filter, hidden severity and column filter
+    fn clear(&self);
 }
 pub struct InMemmoryProcessingStore {
     /// Map of <alias, Regex string>
     formats: RwLock<HashMap<String, String>>,
     /// Map of <alias, Filter details>
     filters: RwLock<HashMap<String, (FilterAction, LogLine, bool)>>,
+    /// Severities hidden after applying the filters
+    hidden_severities: RwLock<Vec<Severity>>,
+    /// Ad-hoc column filters applied after the severities
+    column_filters: RwLock<Vec<ColumnFilter>>,
 }
 
 impl InMemmoryProcessingStore {
@@ -38,6 +64,8 @@ impl InMemmoryProcessingStore {
         Self {
             formats: RwLock::new(HashMap::default()),
             filters: RwLock::new(HashMap::default()),
+            hidden_severities: RwLock::new(Vec::new()),
+            column_filters: RwLock::new(Vec::new()),
         }
     }
 }
@@ -70,11 +98,44 @@ impl ProcessingStore for InMemmoryProcessingStore {
             .collect()
     }
 
+    fn remove_format(&self, id: &str) -> bool {
+        self.formats.write().remove(id).is_some()
+    }
+
+    fn rename_format(&self, id: &str, new_id: &str) -> bool {
+        let mut w = self.formats.write();
+        match w.remove(id) {
+            Some(format) => {
+                w.insert(new_id.to_string(), format);
+                true
+            }
+            None => false,
+        }
+    }
+
     fn add_filter(&self, id: String, filter: LogLine, action: FilterAction, enabled: bool) {
         let mut w = self.filters.write();
         w.insert(id, (action, filter, enabled));
     }
 
+    fn remove_filter(&self, id: &str) -> Option<bool> {
+        self.filters
+            .write()
+            .remove(id)
+            .map(|(_, _, enabled)| enabled)
+    }
+
+    fn rename_filter(&self, id: &str, new_id: &str) -> bool {
+        let mut w = self.filters.write();
+        match w.remove(id) {
+            Some(filter) => {
+                w.insert(new_id.to_string(), filter);
+                true
+            }
+            None => false,
+        }
+    }
+
     fn get_filters(&self) -> Vec<(bool, Filter)> {
         let r = self.filters.read();
 
@@ -101,4 +162,67 @@ impl ProcessingStore for InMemmoryProcessingStore {
             *enabled = !*enabled
         }
     }
+
+    fn toggle_severity(&self, severity: Severity) {
+        let mut w = self.hidden_severities.write();
+        match w.iter().position(|hidden| *hidden == severity) {
+            Some(position) => {
+                w.remove(position);
+            }
+            None => w.push(severity),
+        }
+    }
+
+    fn get_hidden_severities(&self) -> Vec<Severity> {
+        self.hidden_severities.read().clone()
+    }
+
+    fn add_column_filter(&self, column_filter: ColumnFilter) {
+        let mut w = self.column_filters.write();
+        w.retain(|filter| filter.column != column_filter.column);
+        w.push(column_filter);
+    }
+
+    fn remove_column_filter(&self, column: &str) {
+        self.column_filters
+            .write()
+            .retain(|filter| filter.column != column);
+    }
+
+    fn get_column_filters(&self) -> Vec<ColumnFilter> {
+        self.column_filters.read().clone()
+    }
+
+    fn clear(&self) {
+        self.formats.write().clear();
+        self.filters.write().clear();
+        self.hidden_severities.write().clear();
+        self.column_filters.write().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_keep_their_state_when_renamed() {
+        let store = InMemmoryProcessingStore::new();
+        store.add_filter("Old".into(), LogLine::default(), FilterAction::EXCLUDE, true);
+        store.add_format("Format".into(), "(?P<PAYLOAD>.*)".into());
+
+        assert!(store.rename_filter("Old", "New"));
+        assert!(!store.rename_filter("Old", "Other"));
+        let filters = store.get_filters();
+        assert_eq!(filters.len(), 1);
+        assert!(filters[0].0);
+        assert_eq!(filters[0].1.alias, "New");
+        assert_eq!(store.remove_filter("New"), Some(true));
+        assert_eq!(store.remove_filter("New"), None);
+
+        assert!(store.rename_format("Format", "Renamed"));
+        assert_eq!(store.get_format("Renamed").as_deref(), Some("(?P<PAYLOAD>.*)"));
+        assert!(store.remove_format("Renamed"));
+        assert!(store.get_formats().is_empty());
+    }
 }
@@ -0,0 +1,84 @@
+use std::collections::VecDeque;
+
+use parking_lot::RwLock;
+use regex::Regex;
+use rustc_hash::FxHashMap as HashMap;
+
+/// Expressions kept compiled at most. The oldest one is dropped when full, e.g. after many
+/// searches
+const CAPACITY: usize = 256;
+
+/// Compiled regexes of the formats and searches, shared by the workers
+///
+/// A `Regex` is cheap to clone and its clones share the compiled program, so every chunk of
+/// lines reuses the expression compiled the first time it was seen
+#[derive(Default)]
+pub struct RegexCache {
+    regexes: RwLock<Compiled>,
+}
+
+#[derive(Default)]
+struct Compiled {
+    /// K: expression -> V: its compiled regex
+    regexes: HashMap<String, Regex>,
+    /// Keys of `regexes` in the order they were compiled
+    order: VecDeque<String>,
+}
+
+impl RegexCache {
+    /// Get the compiled `regex`, compiling it if not seen yet. Invalid expressions are not kept
+    pub fn get(&self, regex: &str) -> Result<Regex, regex::Error> {
+        if let Some(compiled) = self.regexes.read().regexes.get(regex) {
+            return Ok(compiled.clone());
+        }
+
+        let compiled = Regex::new(regex)?;
+        let mut cache = self.regexes.write();
+        // Compiled meanwhile by another worker
+        if cache.regexes.contains_key(regex) {
+            return Ok(compiled);
+        }
+        if cache.regexes.len() >= CAPACITY {
+            if let Some(oldest) = cache.order.pop_front() {
+                cache.regexes.remove(&oldest);
+            }
+        }
+        cache.regexes.insert(regex.to_string(), compiled.clone());
+        cache.order.push_back(regex.to_string());
+        Ok(compiled)
+    }
+
+    /// Number of regexes kept compiled
+    pub fn len(&self) -> usize {
+        self.regexes.read().regexes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regexes_are_compiled_once() {
+        let cache = RegexCache::default();
+
+        assert!(cache.get(r"(?P<PAYLOAD>.*)").unwrap().is_match("line"));
+        assert!(cache.get(r"(?P<PAYLOAD>.*)").is_ok());
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get("(unclosed").is_err());
+        assert_eq!(cache.len(), 1);
+
+        // Only the oldest regex is dropped when full
+        for i in 0..CAPACITY {
+            cache.get(&i.to_string()).unwrap();
+        }
+        assert_eq!(cache.len(), CAPACITY);
+        let compiled = cache.regexes.read();
+        assert!(!compiled.regexes.contains_key(r"(?P<PAYLOAD>.*)"));
+        assert!(compiled.regexes.contains_key("0"));
+    }
+}
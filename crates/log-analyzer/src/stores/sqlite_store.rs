@@ -0,0 +1,715 @@
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use log_source::source::log_source::LogSource;
+use parking_lot::{lock_api::RwLockReadGuard, Mutex, RawRwLock, RwLock};
+use regex::Regex;
+use rusqlite::{params, Connection, OptionalExtension};
+use rustc_hash::FxHashMap as HashMap;
+use tracing::error;
+
+use super::analysis_store::{AnalysisStore, InMemmoryAnalysisStore};
+use super::arrivals::Arrivals;
+use super::log_store::LogStore;
+use crate::error::{LogAnalyzerError, Result};
+use crate::models::log_line::{LogLine, SmolStr};
+use crate::models::log_line_styled::{Highlight, LogLineStyled};
+use crate::models::retention::Retention;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS logs (
+        id TEXT PRIMARY KEY,
+        format TEXT,
+        enabled INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS raw_lines (
+        log TEXT NOT NULL,
+        raw_index INTEGER NOT NULL,
+        line TEXT NOT NULL,
+        PRIMARY KEY (log, raw_index)
+    );
+    CREATE TABLE IF NOT EXISTS filtered_lines (
+        line_index INTEGER PRIMARY KEY,
+        log TEXT NOT NULL,
+        raw_index INTEGER NOT NULL,
+        date TEXT NOT NULL,
+        timestamp TEXT NOT NULL,
+        app TEXT NOT NULL,
+        severity TEXT NOT NULL,
+        function TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        color INTEGER
+    );
+";
+
+/// Open (or create) the database at `path` with the tables of the stores
+fn open_database(path: &str) -> Result<Connection> {
+    let unavailable = |err: rusqlite::Error| LogAnalyzerError::DatabaseUnavailable {
+        path: path.to_string(),
+        reason: err.to_string(),
+    };
+    let connection = Connection::open(path).map_err(unavailable)?;
+    // Both stores write to the same file, each with its own connection
+    connection
+        .pragma_update(None, "journal_mode", "WAL")
+        .map_err(unavailable)?;
+    connection
+        .pragma_update(None, "synchronous", "NORMAL")
+        .map_err(unavailable)?;
+    connection
+        .busy_timeout(std::time::Duration::from_secs(5))
+        .map_err(unavailable)?;
+    connection.execute_batch(SCHEMA).map_err(unavailable)?;
+    Ok(connection)
+}
+
+/// The value of a query, or `None` after tracing the error. The store traits can't fail, a
+/// failing database loses the lines rather than stopping the analysis
+fn logged<T>(result: rusqlite::Result<T>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(err) => {
+            error!(%err, "SQLite store query failed");
+            None
+        }
+    }
+}
+
+/// Raw lines of a log kept in the database
+struct StoredLines {
+    log: String,
+    lines: usize,
+    /// Raw index in the database of the first line, the lines before it having been discarded
+    first: usize,
+    arrivals: Arrivals,
+}
+
+/// Store of the raw lines in a SQLite database
+///
+/// The logs with their format and enabled state and their raw lines are kept in the database,
+/// the sources only while they run. Reopening the database lists the logs and their lines
+/// without their sources, the lines being aged from then
+pub struct SqliteLogStore {
+    connection: Mutex<Connection>,
+    /// K: log_path -> V: source controller
+    source: RwLock<HashMap<String, Arc<Box<dyn LogSource + Send + Sync>>>>,
+    /// Lines of each log, in the order the logs received their first lines
+    stored: RwLock<Vec<StoredLines>>,
+    /// Lines of every log, read without locking them
+    total_lines: AtomicUsize,
+}
+
+impl SqliteLogStore {
+    /// Open the database at `path`, creating it if missing
+    pub fn open(path: &str) -> Result<Self> {
+        let connection = open_database(path)?;
+        let stored = connection
+            .prepare(
+                "SELECT log, COUNT(*), MIN(raw_index) FROM raw_lines
+                 GROUP BY log ORDER BY MIN(rowid)",
+            )
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| {
+                        let lines = row.get::<_, i64>(1)? as usize;
+                        let mut arrivals = Arrivals::default();
+                        arrivals.add(lines);
+                        Ok(StoredLines {
+                            log: row.get(0)?,
+                            lines,
+                            first: row.get::<_, i64>(2)? as usize,
+                            arrivals,
+                        })
+                    })?
+                    .collect::<rusqlite::Result<Vec<StoredLines>>>()
+            })
+            .map_err(|err| LogAnalyzerError::DatabaseUnavailable {
+                path: path.to_string(),
+                reason: err.to_string(),
+            })?;
+
+        let total_lines = stored.iter().map(|stored| stored.lines).sum();
+        Ok(Self {
+            connection: Mutex::new(connection),
+            source: RwLock::new(HashMap::default()),
+            stored: RwLock::new(stored),
+            total_lines: AtomicUsize::new(total_lines),
+        })
+    }
+
+    fn query_lines(connection: &Connection, log_id: &str) -> rusqlite::Result<Vec<String>> {
+        let mut statement = connection
+            .prepare_cached("SELECT line FROM raw_lines WHERE log = ?1 ORDER BY raw_index")?;
+        let lines = statement.query_map([log_id], |row| row.get(0))?.collect();
+        lines
+    }
+}
+
+impl LogStore for SqliteLogStore {
+    fn add_log(
+        &self,
+        log_id: &str,
+        log_source: Arc<Box<dyn LogSource + Send + Sync>>,
+        format: Option<&String>,
+        enabled: bool,
+    ) {
+        self.source.write().insert(log_id.to_string(), log_source);
+        logged(self.connection.lock().execute(
+            "INSERT INTO logs (id, format, enabled) VALUES (?1, ?2, ?3)
+             ON CONFLICT (id) DO UPDATE SET format = excluded.format, enabled = excluded.enabled",
+            params![log_id, format, enabled],
+        ));
+    }
+
+    fn add_line(&self, log_id: &str, line: &str) {
+        self.add_lines(log_id, &[line]);
+    }
+
+    fn add_lines(&self, log_id: &str, lines: &[&str]) -> Range<usize> {
+        let mut stored_lock = self.stored.write();
+        if !stored_lock.iter().any(|stored| stored.log == log_id) {
+            stored_lock.push(StoredLines {
+                log: log_id.to_string(),
+                lines: 0,
+                first: 0,
+                arrivals: Arrivals::default(),
+            });
+        }
+        let stored = stored_lock
+            .iter_mut()
+            .find(|stored| stored.log == log_id)
+            .unwrap();
+        let current_len = stored.lines;
+        let first = stored.first;
+
+        let mut connection = self.connection.lock();
+        let inserted = logged(connection.transaction().and_then(|transaction| {
+            {
+                let mut statement = transaction.prepare_cached(
+                    "INSERT OR REPLACE INTO raw_lines (log, raw_index, line) VALUES (?1, ?2, ?3)",
+                )?;
+                for (i, line) in lines.iter().enumerate() {
+                    statement.execute(params![log_id, (first + current_len + i) as i64, line])?;
+                }
+            }
+            transaction.commit()
+        }));
+
+        if inserted.is_some() {
+            stored.lines += lines.len();
+            stored.arrivals.add(lines.len());
+            self.total_lines.fetch_add(lines.len(), Ordering::Relaxed);
+        }
+        current_len..stored.lines
+    }
+
+    fn get_format(&self, log_id: &str) -> Option<String> {
+        let connection = self.connection.lock();
+        logged(
+            connection
+                .query_row("SELECT format FROM logs WHERE id = ?1", [log_id], |row| {
+                    row.get(0)
+                })
+                .optional(),
+        )
+        .flatten()
+        .flatten()
+    }
+
+    fn set_format(&self, log_id: &str, format: Option<&String>) {
+        logged(self.connection.lock().execute(
+            "UPDATE logs SET format = ?2 WHERE id = ?1",
+            params![log_id, format],
+        ));
+    }
+
+    fn get_logs(&self) -> Vec<(bool, String, Option<String>)> {
+        let connection = self.connection.lock();
+        logged(
+            connection
+                .prepare_cached("SELECT enabled, id, format FROM logs")
+                .and_then(|mut statement| {
+                    statement
+                        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                        .collect()
+                }),
+        )
+        .unwrap_or_default()
+    }
+
+    fn get_source(&self, id: &str) -> Option<Arc<Box<dyn LogSource + Send + Sync>>> {
+        self.source.read().get(id).cloned()
+    }
+
+    fn get_lines(&self, log_id: &str) -> Vec<String> {
+        logged(Self::query_lines(&self.connection.lock(), log_id)).unwrap_or_default()
+    }
+
+    fn get_lines_range(&self, log_id: &str, lines: Range<usize>) -> Vec<String> {
+        let first = match self.stored.read().iter().find(|stored| stored.log == log_id) {
+            Some(stored) => stored.first,
+            None => return Vec::new(),
+        };
+        let connection = self.connection.lock();
+        logged(
+            connection
+                .prepare_cached(
+                    "SELECT line FROM raw_lines WHERE log = ?1 AND raw_index >= ?2 \
+                     AND raw_index < ?3 ORDER BY raw_index",
+                )
+                .and_then(|mut statement| {
+                    statement
+                        .query_map(
+                            params![log_id, first + lines.start, first + lines.end],
+                            |row| row.get(0),
+                        )?
+                        .collect()
+                }),
+        )
+        .unwrap_or_default()
+    }
+
+    fn get_total_lines(&self) -> usize {
+        self.total_lines.load(Ordering::Relaxed)
+    }
+
+    fn get_lines_per_source(&self) -> Vec<(String, usize)> {
+        self.stored
+            .read()
+            .iter()
+            .map(|stored| (stored.log.clone(), stored.lines))
+            .collect()
+    }
+
+    /// The raw lines are kept on disk
+    fn get_memory_usage(&self) -> usize {
+        0
+    }
+
+    fn toggle_log(&self, log_id: &str) {
+        logged(self.connection.lock().execute(
+            "UPDATE logs SET enabled = NOT enabled WHERE id = ?1",
+            [log_id],
+        ));
+    }
+
+    fn apply_retention(&self, retention: &Retention) -> Vec<(String, usize)> {
+        let mut stored_lock = self.stored.write();
+        let connection = self.connection.lock();
+
+        let mut discarded = Vec::new();
+        for stored in stored_lock.iter_mut() {
+            let excess = stored.arrivals.excess(stored.lines, retention);
+            if excess == 0 {
+                continue;
+            }
+            // The lines left keep their raw index in the database
+            let deleted = logged(connection.execute(
+                "DELETE FROM raw_lines WHERE log = ?1 AND raw_index < ?2",
+                params![stored.log, (stored.first + excess) as i64],
+            ));
+            if deleted.is_some() {
+                stored.arrivals.discard(excess);
+                stored.lines -= excess;
+                stored.first += excess;
+                self.total_lines.fetch_sub(excess, Ordering::Relaxed);
+                discarded.push((stored.log.clone(), excess));
+            }
+        }
+        discarded
+    }
+
+    fn clear(&self) {
+        let mut stored_lock = self.stored.write();
+        logged(
+            self.connection
+                .lock()
+                .execute_batch("DELETE FROM raw_lines; DELETE FROM logs;"),
+        );
+        stored_lock.clear();
+        self.total_lines.store(0, Ordering::Relaxed);
+        self.source.write().clear();
+    }
+}
+
+/// Store of the filtered and searched lines keeping a copy of the filtered log in a SQLite
+/// database
+///
+/// The lines are served from memory. Reopening the database loads the filtered log stored by
+/// the previous run, so it is shown without filtering the raw lines again
+pub struct SqliteAnalysisStore {
+    memory: InMemmoryAnalysisStore,
+    connection: Mutex<Connection>,
+    /// Line index in the database of the first processed line, the lines before it having been
+    /// discarded
+    first: AtomicUsize,
+}
+
+/// Color packed as `0xRRGGBB`
+fn pack_color(color: Option<(u8, u8, u8)>) -> Option<i64> {
+    color.map(|(r, g, b)| (r as i64) << 16 | (g as i64) << 8 | b as i64)
+}
+
+fn unpack_color(color: Option<i64>) -> Option<(u8, u8, u8)> {
+    color.map(|color| ((color >> 16) as u8, (color >> 8) as u8, color as u8))
+}
+
+impl SqliteAnalysisStore {
+    /// Open the database at `path`, creating it if missing, and load its filtered log
+    pub fn open(path: &str) -> Result<Self> {
+        let connection = open_database(path)?;
+        let lines = connection
+            .prepare(
+                "SELECT log, raw_index, date, timestamp, app, severity, function, payload, color
+                 FROM filtered_lines ORDER BY line_index",
+            )
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| {
+                        let text = |column| row.get::<_, String>(column).map(SmolStr::from);
+                        Ok(LogLine {
+                            log: text(0)?,
+                            raw_index: row.get::<_, i64>(1)? as usize,
+                            date: text(2)?,
+                            timestamp: text(3)?,
+                            app: text(4)?,
+                            severity: text(5)?,
+                            function: text(6)?,
+                            payload: text(7)?,
+                            color: unpack_color(row.get(8)?),
+                            ..Default::default()
+                        })
+                    })?
+                    .collect::<rusqlite::Result<Vec<LogLine>>>()
+            })
+            .map_err(|err| LogAnalyzerError::DatabaseUnavailable {
+                path: path.to_string(),
+                reason: err.to_string(),
+            })?;
+
+        let first = connection
+            .query_row("SELECT MIN(line_index) FROM filtered_lines", [], |row| {
+                row.get::<_, Option<i64>>(0)
+            })
+            .map_err(|err| LogAnalyzerError::DatabaseUnavailable {
+                path: path.to_string(),
+                reason: err.to_string(),
+            })?;
+
+        let memory = InMemmoryAnalysisStore::new();
+        memory.add_lines(&lines);
+        Ok(Self {
+            memory,
+            connection: Mutex::new(connection),
+            first: AtomicUsize::new(first.unwrap_or_default() as usize),
+        })
+    }
+
+    /// Insert the processed `lines` with consecutive line indexes starting at `first`
+    fn insert_lines<'a>(
+        connection: &Connection,
+        first: usize,
+        lines: impl Iterator<Item = &'a LogLine>,
+    ) -> rusqlite::Result<()> {
+        let mut statement = connection.prepare_cached(
+            "INSERT OR REPLACE INTO filtered_lines (line_index, log, raw_index, date,
+             timestamp, app, severity, function, payload, color)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        )?;
+        for (index, line) in (first..).zip(lines) {
+            statement.execute(params![
+                index as i64,
+                line.log.as_str(),
+                line.raw_index as i64,
+                line.date.as_str(),
+                line.timestamp.as_str(),
+                line.app.as_str(),
+                line.severity.as_str(),
+                line.function.as_str(),
+                line.payload.as_str(),
+                pack_color(line.color),
+            ])?;
+        }
+        Ok(())
+    }
+}
+
+impl AnalysisStore for SqliteAnalysisStore {
+    fn add_lines(&self, lines: &[LogLine]) -> Range<usize> {
+        let indexes = self.memory.add_lines(lines);
+
+        let mut connection = self.connection.lock();
+        logged(connection.transaction().and_then(|transaction| {
+            let first = self.first.load(Ordering::Relaxed) + indexes.start;
+            Self::insert_lines(&transaction, first, lines.iter())?;
+            transaction.commit()
+        }));
+        indexes
+    }
+
+    fn add_search_lines(&self, lines: &[(LogLine, Vec<Highlight>)]) {
+        self.memory.add_search_lines(lines)
+    }
+
+    fn add_search_query(&self, query: &str) {
+        self.memory.add_search_query(query)
+    }
+
+    fn get_search_query(&self) -> Option<String> {
+        self.memory.get_search_query()
+    }
+
+    fn reset_log(&self) {
+        let connection = self.connection.lock();
+        self.memory.reset_log();
+        logged(connection.execute("DELETE FROM filtered_lines", []));
+        self.first.store(0, Ordering::Relaxed);
+    }
+
+    fn reset_search(&self) {
+        self.memory.reset_search()
+    }
+
+    fn fetch_log(&self) -> RwLockReadGuard<'_, RawRwLock, Vec<Arc<LogLine>>> {
+        self.memory.fetch_log()
+    }
+
+    fn fetch_search(&self) -> RwLockReadGuard<'_, RawRwLock, Vec<Arc<LogLine>>> {
+        self.memory.fetch_search()
+    }
+
+    fn show_line(&self, line: &LogLine) -> LogLine {
+        self.memory.show_line(line)
+    }
+
+    fn get_log_lines(&self, from: usize, to: usize) -> Vec<Arc<LogLine>> {
+        self.memory.get_log_lines(from, to)
+    }
+
+    fn get_search_lines(&self, from: usize, to: usize) -> Vec<LogLineStyled> {
+        self.memory.get_search_lines(from, to)
+    }
+
+    fn get_search_highlights(
+        &self,
+        from: usize,
+        to: usize,
+    ) -> Vec<(Arc<LogLine>, Vec<Highlight>)> {
+        self.memory.get_search_highlights(from, to)
+    }
+
+    fn get_log_lines_containing(
+        &self,
+        index: usize,
+        elements: usize,
+    ) -> (Vec<Arc<LogLine>>, usize, usize) {
+        self.memory.get_log_lines_containing(index, elements)
+    }
+
+    fn get_search_lines_containing(
+        &self,
+        index: usize,
+        elements: usize,
+    ) -> (Vec<LogLineStyled>, usize, usize) {
+        self.memory.get_search_lines_containing(index, elements)
+    }
+
+    fn get_total_filtered_lines(&self) -> usize {
+        self.memory.get_total_filtered_lines()
+    }
+
+    fn get_total_searched_lines(&self) -> usize {
+        self.memory.get_total_searched_lines()
+    }
+
+    fn get_log_memory_usage(&self) -> usize {
+        self.memory.get_log_memory_usage()
+    }
+
+    fn get_search_memory_usage(&self) -> usize {
+        self.memory.get_search_memory_usage()
+    }
+
+    fn get_lines_per_source(&self) -> Vec<(String, usize)> {
+        self.memory.get_lines_per_source()
+    }
+
+    fn get_search_lines_per_source(&self) -> Vec<(String, usize)> {
+        self.memory.get_search_lines_per_source()
+    }
+
+    fn get_source_lines(&self, source: &str, limit: usize) -> Vec<LogLine> {
+        self.memory.get_source_lines(source, limit)
+    }
+
+    fn find_raw_line(&self, source: Option<&str>, raw_index: usize) -> Option<usize> {
+        self.memory.find_raw_line(source, raw_index)
+    }
+
+    fn add_line_times(&self, raw: &[i64], filtered: &[i64]) {
+        self.memory.add_line_times(raw, filtered)
+    }
+
+    fn get_rate(&self) -> Vec<(i64, usize, usize)> {
+        self.memory.get_rate()
+    }
+
+    fn get_search_rate(&self) -> Vec<(i64, usize)> {
+        self.memory.get_search_rate()
+    }
+
+    fn get_lines_per_severity(&self) -> Vec<(String, usize)> {
+        self.memory.get_lines_per_severity()
+    }
+
+    fn get_lines_per_app(&self) -> Vec<(String, usize)> {
+        self.memory.get_lines_per_app()
+    }
+
+    fn get_lines_per_value(
+        &self,
+        column: &str,
+        search: bool,
+        time_range: Option<(f64, f64)>,
+    ) -> Option<Vec<(String, usize)>> {
+        self.memory.get_lines_per_value(column, search, time_range)
+    }
+
+    fn set_correlation_key(&self, key: Option<Regex>) {
+        self.memory.set_correlation_key(key)
+    }
+
+    fn get_correlation_id(&self, index: usize) -> Option<String> {
+        self.memory.get_correlation_id(index)
+    }
+
+    fn get_correlated_lines(&self, id: &str) -> Vec<LogLine> {
+        self.memory.get_correlated_lines(id)
+    }
+
+    fn get_indexes_in_time_range(&self, from: f64, to: f64) -> Vec<usize> {
+        self.memory.get_indexes_in_time_range(from, to)
+    }
+
+    fn get_index_at_time(&self, time: f64) -> Option<usize> {
+        self.memory.get_index_at_time(time)
+    }
+
+    fn apply_retention(&self, retention: &Retention, raw_discarded: &[(String, usize)]) -> usize {
+        let connection = self.connection.lock();
+        let discarded = self.memory.apply_retention(retention, raw_discarded);
+        // The lines left keep their line index in the database
+        let first = self.first.fetch_add(discarded, Ordering::Relaxed);
+        logged(connection.execute(
+            "DELETE FROM filtered_lines WHERE line_index < ?1",
+            [(first + discarded) as i64],
+        ));
+        for (source, lines) in raw_discarded {
+            logged(connection.execute(
+                "UPDATE filtered_lines
+                 SET raw_index = MAX(raw_index - ?2, 0)
+                 WHERE log = ?1",
+                params![source, *lines as i64],
+            ));
+        }
+        discarded
+    }
+
+    fn replace_log(&self, staged: InMemmoryAnalysisStore) {
+        let mut connection = self.connection.lock();
+        self.memory.replace_log(staged);
+        self.first.store(0, Ordering::Relaxed);
+        let log = self.memory.fetch_log();
+        logged(connection.transaction().and_then(|transaction| {
+            transaction.execute("DELETE FROM filtered_lines", [])?;
+            Self::insert_lines(&transaction, 0, log.iter().map(|line| line.as_ref()))?;
+            transaction.commit()
+        }));
+    }
+
+    fn replace_search(&self, staged: InMemmoryAnalysisStore) {
+        self.memory.replace_search(staged)
+    }
+
+    fn clear(&self) {
+        let connection = self.connection.lock();
+        self.memory.clear();
+        logged(connection.execute("DELETE FROM filtered_lines", []));
+        self.first.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reopened_stores_keep_their_lines() {
+        let dir = test_utils::tempdir().unwrap();
+        let path = dir.path().join("store.db");
+        let path = path.to_str().unwrap();
+        {
+            let log_store = SqliteLogStore::open(path).unwrap();
+            let lines = vec!["first", "second"];
+            assert_eq!(log_store.add_lines("b.log", &lines), 0..2);
+            assert_eq!(log_store.add_lines("a.log", &lines[..1]), 0..1);
+            assert_eq!(log_store.add_lines("b.log", &lines[..1]), 2..3);
+
+            let analysis_store = SqliteAnalysisStore::open(path).unwrap();
+            let line = LogLine {
+                log: "b.log".into(),
+                raw_index: 2,
+                payload: "second".into(),
+                color: Some((255, 128, 0)),
+                ..Default::default()
+            };
+            assert_eq!(analysis_store.add_lines(&[line]), 0..1);
+        }
+
+        let log_store = SqliteLogStore::open(path).unwrap();
+        assert_eq!(
+            log_store.get_lines_per_source(),
+            vec![("b.log".to_string(), 3), ("a.log".to_string(), 1)]
+        );
+        assert_eq!(
+            log_store.get_lines("b.log"),
+            vec!["first", "second", "first"]
+        );
+        assert_eq!(log_store.get_lines_range("b.log", 1..5), vec!["second", "first"]);
+        assert_eq!(log_store.get_lines_range("a.log", 0..1), vec!["first"]);
+        assert_eq!(log_store.get_total_lines(), 4);
+
+        let analysis_store = SqliteAnalysisStore::open(path).unwrap();
+        let lines = analysis_store.get_log_lines(0, 10);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].index, 0);
+        assert_eq!(lines[0].payload, "second");
+        assert_eq!(lines[0].color, Some((255, 128, 0)));
+
+        let retention = Retention {
+            max_lines: Some(1),
+            ..Default::default()
+        };
+        let discarded = log_store.apply_retention(&retention);
+        assert_eq!(discarded, vec![("b.log".to_string(), 2)]);
+        assert_eq!(analysis_store.apply_retention(&retention, &discarded), 0);
+        drop(log_store);
+        let log_store = SqliteLogStore::open(path).unwrap();
+        assert_eq!(log_store.get_lines("b.log"), vec!["first"]);
+        assert_eq!(log_store.add_lines("b.log", &["third"]), 1..2);
+        assert_eq!(log_store.get_lines("b.log"), vec!["first", "third"]);
+        drop(analysis_store);
+        let analysis_store = SqliteAnalysisStore::open(path).unwrap();
+        assert_eq!(analysis_store.get_log_lines(0, 10)[0].raw_index, 0);
+
+        analysis_store.reset_log();
+        drop(analysis_store);
+        assert_eq!(
+            SqliteAnalysisStore::open(path)
+                .unwrap()
+                .get_total_filtered_lines(),
+            0
+        );
+    }
+}
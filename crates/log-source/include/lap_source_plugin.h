@@ -0,0 +1,57 @@
+/*
+ * Interface of the source plugins, dynamic libraries loaded from the plugins directory to read
+ * the sources of transports the analyzer doesn't know.
+ *
+ * A plugin exports lap_source_plugin, returning its functions. The sources whose address has
+ * the name of the plugin as scheme, e.g. kafka://broker/topic for the plugin "kafka", are read
+ * by it. The functions can be called from any thread, one call at a time per source. They are
+ * called on a blocking thread, so read can wait for lines, briefly for the source to be stopped
+ * in time.
+ */
+#ifndef LAP_SOURCE_PLUGIN_H
+#define LAP_SOURCE_PLUGIN_H
+
+#include <stddef.h>
+#include <stdint.h>
+#include <sys/types.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define LAP_PLUGIN_ABI_VERSION 1
+
+/* Returned by read once the source has no more lines */
+#define LAP_SOURCE_END -1
+/* Returned by read when the source failed, last_error telling why */
+#define LAP_SOURCE_ERROR -2
+
+typedef struct LapSourcePlugin {
+    /* LAP_PLUGIN_ABI_VERSION the plugin was built for, plugins of other versions are not loaded */
+    uint32_t abi_version;
+    /* Name of the plugin, the scheme of the addresses it reads */
+    const char *name;
+    /* Open a source reading `address`, returning the state given to the other functions, or
+     * NULL if it can't be opened, last_error(NULL) telling why */
+    void *(*open)(const char *address);
+    /* Copy the lines available, each one followed by \n, to the `capacity` bytes of `buffer`.
+     * The bytes after the last \n start a line continued by the next read, so a line longer
+     * than `capacity` is copied over several reads.
+     * Returns the number of bytes copied, 0 if no line is available yet, LAP_SOURCE_END or
+     * LAP_SOURCE_ERROR */
+    ssize_t (*read)(void *source, uint8_t *buffer, size_t capacity);
+    /* Description of the last failure of `source`, or of open if NULL. Owned by the plugin and
+     * valid until its next call */
+    const char *(*last_error)(void *source);
+    /* Release a source returned by open */
+    void (*close)(void *source);
+} LapSourcePlugin;
+
+/* Entry point of the plugin, the returned functions must stay valid while it is loaded */
+const LapSourcePlugin *lap_source_plugin(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* LAP_SOURCE_PLUGIN_H */
@@ -0,0 +1,114 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use memchr::memchr;
+
+/// Lines read by a source, kept in a single buffer and separated by newlines.
+///
+/// A batch is sent through the pipeline without allocating each of its lines. They are split
+/// and decoded when processed, so the bytes read are not checked to be UTF-8 by the sources
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LineBatch {
+    bytes: Bytes,
+    /// Number of lines in `bytes`
+    len: usize,
+}
+
+impl LineBatch {
+    /// Batch of the `len` lines of `bytes`, each one followed by a newline except the last one
+    /// optionally
+    pub fn new(bytes: Bytes, len: usize) -> Self {
+        Self { bytes, len }
+    }
+
+    /// Number of lines
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Bytes of text held by the batch
+    pub fn size(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Lines of the batch without their line ending, like `BufRead::lines`. They are not
+    /// decoded and can hold invalid UTF-8
+    pub fn lines(&self) -> impl Iterator<Item = &[u8]> {
+        let mut rest = &self.bytes[..];
+        (0..self.len).map(move |_| {
+            let end = memchr(b'\n', rest).unwrap_or(rest.len());
+            let line = &rest[..end];
+            rest = &rest[(end + 1).min(rest.len())..];
+            line.strip_suffix(b"\r").unwrap_or(line)
+        })
+    }
+
+    /// Batch keeping one line out of `every`, from the first one
+    pub fn step_by(&self, every: usize) -> Self {
+        let mut buffer = LineBuffer::default();
+        for line in self.lines().step_by(every.max(1)) {
+            buffer.push(line);
+        }
+        buffer.take()
+    }
+}
+
+impl<S: AsRef<[u8]>> FromIterator<S> for LineBatch {
+    fn from_iter<T: IntoIterator<Item = S>>(lines: T) -> Self {
+        let mut buffer = LineBuffer::default();
+        for line in lines {
+            buffer.push(line);
+        }
+        buffer.take()
+    }
+}
+
+/// Lines being gathered by a source before sending them as a `LineBatch`
+#[derive(Default)]
+pub struct LineBuffer {
+    bytes: BytesMut,
+    len: usize,
+}
+
+impl LineBuffer {
+    /// Add a line, without its line ending
+    pub fn push(&mut self, line: impl AsRef<[u8]>) {
+        self.bytes.put_slice(line.as_ref());
+        self.bytes.put_u8(b'\n');
+        self.len += 1;
+    }
+
+    /// Number of lines gathered
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Take the lines gathered as a batch, leaving the buffer empty
+    pub fn take(&mut self) -> LineBatch {
+        let len = std::mem::take(&mut self.len);
+        LineBatch::new(self.bytes.split().freeze(), len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_split_without_their_endings() {
+        let batch = LineBatch::new(Bytes::from_static(b"first\r\n\nthird"), 3);
+        let lines: Vec<&[u8]> = batch.lines().collect();
+        assert_eq!(lines, vec![&b"first"[..], b"", b"third"]);
+
+        let batch: LineBatch = ["a", "b", "", "d", "e"].into_iter().collect();
+        assert_eq!((batch.len(), batch.size()), (5, 9));
+        assert_eq!(batch.step_by(2), ["a", "", "e"].into_iter().collect());
+        assert_eq!(LineBatch::default().lines().count(), 0);
+    }
+}
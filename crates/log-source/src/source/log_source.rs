@@ -1,24 +1,28 @@
-
+use std::io;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Result};
 
-use async_std::net::TcpStream;
-use async_std::{
-    fs::File,
-    io::{prelude::BufReadExt, BufReader},
-    prelude::StreamExt,
-};
 use async_trait::async_trait;
 use flume::Sender;
 use parking_lot::RwLock;
+use tracing::{debug, warn};
 
+use super::line_batch::{LineBatch, LineBuffer};
+use super::mapped_lines::MappedLines;
+use super::read_lines::ReadLines;
+use super::runtime::{self, Lines};
 
-#[derive(Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum SourceType {
     FILE,
     WS,
+    /// Lines piped to the standard input
+    STDIN,
+    /// Lines read by a source plugin, the one named after the scheme of the address, e.g.
+    /// `kafka` for `kafka://broker/topic`
+    PLUGIN,
 }
 
 impl TryFrom<usize> for SourceType {
@@ -28,6 +32,8 @@ impl TryFrom<usize> for SourceType {
         match value {
             0 => Ok(SourceType::FILE),
             1 => Ok(SourceType::WS),
+            2 => Ok(SourceType::STDIN),
+            3 => Ok(SourceType::PLUGIN),
             _ => Err(()),
         }
     }
@@ -38,24 +44,80 @@ impl From<SourceType> for usize {
         match val {
             SourceType::FILE => 0,
             SourceType::WS => 1,
+            SourceType::STDIN => 2,
+            SourceType::PLUGIN => 3,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+/// Changes in the state of a running source
+pub enum SourceStatus {
+    /// Connection established with the source
+    Connected,
+    /// Connection lost, the source keeps retrying
+    Disconnected,
+    /// The source could not be read
+    Unavailable(String),
+    /// The source can be read again after being unavailable, from where it stopped
+    Resumed,
+    /// Every line was read and the source stopped, e.g. a file loaded once or a closed stdin
+    Eof,
+    /// The lines present when a followed file was opened were read, the given number of lines,
+    /// stored or not by the receiver. The following lines are appended to the file live
+    CaughtUp(usize),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// Waits between the attempts to read again a followed file that can't be read, e.g. deleted
+/// or no longer readable. The wait doubles after each failed attempt
+pub struct Backoff {
+    /// Wait after the first failed attempt
+    pub initial: Duration,
+    /// Longest wait between two attempts
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(30),
         }
     }
 }
 
-async fn is_file_path_valid(path: &String) -> bool {
-    File::open(&path).await.is_ok()
+impl Backoff {
+    /// Wait after `failures` failed attempts in a row
+    pub fn delay(&self, failures: u32) -> Duration {
+        let factor = 2_u32.saturating_pow(failures.saturating_sub(1));
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+fn is_file_path_valid(path: &String) -> bool {
+    std::fs::File::open(path).is_ok()
 }
 
-pub async fn create_source(
+/// Create a source reading `source_address`.
+/// File sources keep reading the lines appended to the file when `follow` is set,
+/// otherwise they stop after reaching the end of the file. A followed file that can't be read
+/// is tried again after the waits of `retry`
+pub fn create_source(
     source: SourceType,
     source_address: String,
+    follow: bool,
+    retry: Backoff,
 ) -> Result<Box<dyn LogSource + Send + Sync>> {
     match source {
-        SourceType::FILE => match is_file_path_valid(&source_address).await {
+        SourceType::FILE => match is_file_path_valid(&source_address) {
             true => Ok(Box::new(FileSource {
                 path: source_address,
+                offset: RwLock::new(0),
                 read_lines: RwLock::new(0),
-                enabled: AtomicBool::new(true)
+                enabled: AtomicBool::new(true),
+                follow,
+                retry,
             })),
             false => Err(anyhow!(
                 "Could not open file.\nPlease ensure that path is correct"
@@ -63,49 +125,192 @@ pub async fn create_source(
         },
         SourceType::WS => Ok(Box::new(WsSource {
             address: source_address,
-            enabled: AtomicBool::new(true)
+            enabled: AtomicBool::new(true),
+        })),
+        SourceType::STDIN => Ok(Box::new(StdinSource {
+            address: source_address,
+            enabled: AtomicBool::new(true),
         })),
+        // Plugins are loaded by the application, see `SourcePlugins`
+        SourceType::PLUGIN => Err(anyhow!("No plugin reads {}", source_address)),
     }
 }
 
 #[async_trait]
 pub trait LogSource {
-    async fn run(&self, sender: Sender<(String, Vec<String>)>) -> Result<()>;
+    /// Read the source sending the lines through `sender` until stopped.
+    /// Changes in the state of the source are notified through `status`
+    async fn run(
+        &self,
+        sender: Sender<(String, LineBatch)>,
+        status: Sender<(String, SourceStatus)>,
+    ) -> Result<()>;
     fn stop(&self);
     fn get_address(&self) -> String;
+    fn get_type(&self) -> SourceType;
 }
 
 pub struct FileSource {
     path: String,
+    /// Offset of the first byte not read yet, where the next read resumes
+    offset: RwLock<usize>,
     read_lines: RwLock<usize>,
-    enabled: AtomicBool
+    enabled: AtomicBool,
+    follow: bool,
+    retry: Backoff,
+}
+
+/// Time between two reads of a followed file
+const FILE_POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Lines of a file read from an offset. Followed files can be truncated while read, so they are
+/// read instead of mapped
+enum FileLines {
+    Mapped(MappedLines),
+    Read(ReadLines),
+}
+
+impl FileLines {
+    fn next_batch(&mut self, capacity: usize) -> io::Result<Option<LineBatch>> {
+        match self {
+            FileLines::Mapped(lines) => Ok(lines.next_batch(capacity)),
+            FileLines::Read(lines) => lines.next_batch(capacity),
+        }
+    }
+
+    fn end(&self) -> usize {
+        match self {
+            FileLines::Mapped(lines) => lines.end(),
+            FileLines::Read(lines) => lines.end(),
+        }
+    }
+}
+
+impl FileSource {
+    /// Send the lines appended to `file`, of `length` bytes, since the last read in batches of
+    /// up to `capacity` lines. A last line without newline is only sent if `partial`, otherwise
+    /// it is still being written. An empty batch is sent without new lines
+    async fn read_new_lines(
+        &self,
+        file: std::fs::File,
+        length: usize,
+        partial: bool,
+        capacity: usize,
+        sender: &Sender<(String, LineBatch)>,
+    ) -> Result<()> {
+        let offset = *self.offset.read();
+        if length < offset {
+            debug!(source = %self.path, "File truncated, reading it again");
+            *self.offset.write() = 0;
+        }
+
+        let offset = *self.offset.read();
+        let mut sent = false;
+        if length > offset {
+            // The file is read out of the executor, so a slow disk doesn't hold the other sources
+            let follow = self.follow;
+            let mut lines = runtime::spawn_blocking(move || -> io::Result<FileLines> {
+                Ok(match follow {
+                    true => FileLines::Read(ReadLines::open(file, offset, length, partial)?),
+                    false => FileLines::Mapped(MappedLines::open(&file, offset, partial)?),
+                })
+            })
+            .await?;
+            loop {
+                let (read, batch) = runtime::spawn_blocking(move || {
+                    let batch = lines.next_batch(capacity);
+                    (lines, batch)
+                })
+                .await;
+                lines = read;
+                let Some(batch) = batch? else {
+                    break;
+                };
+                debug!(source = %self.path, lines = batch.len(), "Read batch");
+                *self.read_lines.write() += batch.len();
+                *self.offset.write() = lines.end();
+                sender.send_async((self.path.clone(), batch)).await?;
+                sent = true;
+            }
+        }
+        if !sent {
+            sender.send_async((self.path.clone(), LineBatch::default())).await?;
+        }
+        Ok(())
+    }
+
+    /// Wait `delay`, or less if the source is stopped meanwhile
+    async fn wait(&self, delay: Duration) {
+        let started = Instant::now();
+        while self.enabled.load(Ordering::Relaxed) && started.elapsed() < delay {
+            runtime::sleep(delay.saturating_sub(started.elapsed()).min(FILE_POLL_INTERVAL)).await;
+        }
+    }
 }
 
 #[async_trait]
 impl LogSource for FileSource {
-    async fn run(&self, sender: Sender<(String, Vec<String>)>) -> Result<()> {
+    async fn run(
+        &self,
+        sender: Sender<(String, LineBatch)>,
+        status: Sender<(String, SourceStatus)>,
+    ) -> Result<()> {
         let capacity = 1_000_000_usize;
+        let mut caught_up = false;
+        // A last line without newline is sent once the file stops growing
+        let mut last_length = None;
+        // Attempts in a row the file could not be read, only the first one is notified
+        let mut failures = 0;
         while self.enabled.load(Ordering::Relaxed) {
-            let file = File::open(&self.path).await;
-            match file {
-                Ok(f) => {
-                    let reader = BufReader::with_capacity(2_usize.pow(26), f);
-                    let mut v = Vec::with_capacity(capacity);
-                    let mut lines = reader.lines().skip(*self.read_lines.read());
-                    while let Some(line) = lines.next().await {
-                        v.push(line?);
-                        if v.len() >= capacity - 1 {
-                            sender.send_async((self.path.clone(), v)).await?;
-                            v = Vec::with_capacity(capacity);
-                        }
-                        *self.read_lines.write() += 1;
+            let read: Result<()> = async {
+                let path = self.path.clone();
+                let (file, length) = runtime::spawn_blocking(move || -> io::Result<_> {
+                    let file = std::fs::File::open(path)?;
+                    let length = file.metadata()?.len() as usize;
+                    Ok((file, length))
+                })
+                .await?;
+                let partial = !self.follow || last_length.is_none_or(|last| last == length);
+                last_length = Some(length);
+                self.read_new_lines(file, length, partial, capacity, &sender).await
+            }
+            .await;
+            match read {
+                Ok(()) if failures > 0 => {
+                    failures = 0;
+                    debug!(source = %self.path, "File readable again");
+                    status.send((self.path.clone(), SourceStatus::Resumed))?;
+                }
+                Ok(()) => {}
+                // Sending the lines failed, the analyzer is gone
+                Err(err) if !err.is::<io::Error>() => return Err(err),
+                Err(err) => {
+                    failures += 1;
+                    if failures == 1 {
+                        warn!(source = %self.path, error = %err, "Unable to read the file");
+                        status.send((
+                            self.path.clone(),
+                            SourceStatus::Unavailable(err.to_string()),
+                        ))?;
                     }
-                    sender.send((self.path.clone(), v))?;
+                    if !self.follow {
+                        break;
+                    }
+                    self.wait(self.retry.delay(failures)).await;
+                    continue;
                 }
-                Err(_) => break,
+            }
+            if self.follow && !caught_up {
+                caught_up = true;
+                let lines = *self.read_lines.read();
+                status.send((self.path.clone(), SourceStatus::CaughtUp(lines)))?;
+            }
+            if !self.follow {
+                status.send((self.path.clone(), SourceStatus::Eof))?;
+                break;
             }
 
-            async_std::task::sleep(Duration::from_millis(300)).await;
+            self.wait(FILE_POLL_INTERVAL).await;
         }
         // restore after quitting
         self.enabled.store(true, Ordering::Relaxed);
@@ -120,34 +325,116 @@ impl LogSource for FileSource {
         self.path.clone()
     }
 
+    fn get_type(&self) -> SourceType {
+        SourceType::FILE
+    }
 }
 
 pub struct WsSource {
     address: String,
-    enabled: AtomicBool
+    enabled: AtomicBool,
 }
 
 #[async_trait]
 impl LogSource for WsSource {
-    async fn run(&self, sender: Sender<(String, Vec<String>)>) -> Result<()> {
+    async fn run(
+        &self,
+        sender: Sender<(String, LineBatch)>,
+        status: Sender<(String, SourceStatus)>,
+    ) -> Result<()> {
+        // Only the first failed attempt is notified to avoid flooding while retrying
+        let mut notified_unavailable = false;
         while self.enabled.load(Ordering::Relaxed) {
-            let stream = match TcpStream::connect(&self.address).await {
-                Ok(stream) => Some(stream),
-                Err(_) => None,
-            };
-            if let Some(stream) = stream {
-                while self.enabled.load(Ordering::Relaxed) {
-                    let mut lines_from_server = BufReader::new(&stream).lines().fuse();
-                    match lines_from_server.next().await {
-                        Some(line) => {
-                            let line = line?;
-                            sender.send((self.address.clone(), vec![line]))?;
+            match Lines::connect(&self.address).await {
+                Ok(mut lines_from_server) => {
+                    notified_unavailable = false;
+                    debug!(source = %self.address, "Connected");
+                    status.send((self.address.clone(), SourceStatus::Connected))?;
+                    while self.enabled.load(Ordering::Relaxed) {
+                        match lines_from_server.next().await {
+                            Some(line) => {
+                                let batch = LineBatch::from_iter([line?]);
+                                sender.send_async((self.address.clone(), batch)).await?;
+                            }
+                            None => break,
                         }
-                        None => break,
                     }
+                    debug!(source = %self.address, "Disconnected");
+                    status.send((self.address.clone(), SourceStatus::Disconnected))?;
+                }
+                Err(err) if !notified_unavailable => {
+                    warn!(source = %self.address, error = %err, "Unable to connect");
+                    notified_unavailable = true;
+                    status.send((
+                        self.address.clone(),
+                        SourceStatus::Unavailable(err.to_string()),
+                    ))?;
+                }
+                Err(_) => {}
+            }
+            runtime::sleep(Duration::from_secs(3)).await;
+        }
+        // restore after quitting
+        self.enabled.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn stop(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+    }
+
+    fn get_address(&self) -> String {
+        self.address.clone()
+    }
+
+    fn get_type(&self) -> SourceType {
+        SourceType::WS
+    }
+}
+
+/// Time without new lines after which the lines read from stdin are sent
+const STDIN_FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct StdinSource {
+    address: String,
+    enabled: AtomicBool,
+}
+
+#[async_trait]
+impl LogSource for StdinSource {
+    async fn run(
+        &self,
+        sender: Sender<(String, LineBatch)>,
+        status: Sender<(String, SourceStatus)>,
+    ) -> Result<()> {
+        let capacity = 1_000_000_usize;
+        let mut lines = Lines::stdin();
+        let mut v = LineBuffer::default();
+        // Lines are sent in batches when the input pauses, so a piped file is not sent line by line
+        while self.enabled.load(Ordering::Relaxed) {
+            match runtime::timeout(STDIN_FLUSH_INTERVAL, lines.next()).await {
+                Some(Some(line)) => {
+                    v.push(line?);
+                    if v.len() >= capacity {
+                        sender.send_async((self.address.clone(), v.take())).await?;
+                    }
+                }
+                Some(None) => {
+                    if !v.is_empty() {
+                        sender.send_async((self.address.clone(), v.take())).await?;
+                    }
+                    status.send((self.address.clone(), SourceStatus::Eof))?;
+                    break;
                 }
+                None if !v.is_empty() => {
+                    debug!(source = %self.address, lines = v.len(), "Read batch");
+                    sender.send_async((self.address.clone(), v.take())).await?;
+                }
+                None => {}
             }
-            async_std::task::sleep(Duration::from_secs(3)).await;
+        }
+        if !v.is_empty() {
+            sender.send_async((self.address.clone(), v.take())).await?;
         }
         // restore after quitting
         self.enabled.store(true, Ordering::Relaxed);
@@ -161,4 +448,168 @@ impl LogSource for WsSource {
     fn get_address(&self) -> String {
         self.address.clone()
     }
+
+    fn get_type(&self) -> SourceType {
+        SourceType::STDIN
+    }
+}
+
+/// Time of a raw line in seconds, if it has one
+pub type LineTime = Box<dyn Fn(&str) -> Option<f64> + Send + Sync>;
+
+/// Longest wait between two replayed lines, whatever the time elapsed between them
+pub const MAX_REPLAY_PAUSE: Duration = Duration::from_secs(30);
+
+/// Source sending the lines of a file as if they were being written: each line waits the time
+/// elapsed since the previous one divided by `speed`. Lines without time are sent with the
+/// previous ones
+pub struct ReplaySource {
+    path: String,
+    speed: f64,
+    time_of: LineTime,
+    enabled: AtomicBool,
+}
+
+impl ReplaySource {
+    /// Replay the file at `path`, `speed` times faster than it was written (0.5 for twice slower)
+    pub fn new(path: String, speed: f64, time_of: LineTime) -> Result<Self> {
+        match is_file_path_valid(&path) {
+            true => Ok(Self {
+                path,
+                speed,
+                time_of,
+                enabled: AtomicBool::new(true),
+            }),
+            false => Err(anyhow!(
+                "Could not open file.\nPlease ensure that path is correct"
+            )),
+        }
+    }
+}
+
+/// Wait before a line written `elapsed` seconds after the previous one. A speed of zero or an
+/// elapsed time too large for a `Duration` waits the longest pause
+fn replay_pause(elapsed: f64, speed: f64) -> Duration {
+    Duration::try_from_secs_f64(elapsed / speed)
+        .unwrap_or(MAX_REPLAY_PAUSE)
+        .min(MAX_REPLAY_PAUSE)
+}
+
+#[async_trait]
+impl LogSource for ReplaySource {
+    async fn run(
+        &self,
+        sender: Sender<(String, LineBatch)>,
+        status: Sender<(String, SourceStatus)>,
+    ) -> Result<()> {
+        let mut lines = match Lines::open(&self.path).await {
+            Ok(lines) => lines,
+            Err(err) => {
+                warn!(source = %self.path, error = %err, "Unable to open the file");
+                status.send((self.path.clone(), SourceStatus::Unavailable(err.to_string())))?;
+                return Ok(());
+            }
+        };
+        let mut last_time = None;
+        let mut v = LineBuffer::default();
+        while self.enabled.load(Ordering::Relaxed) {
+            let line = match lines.next().await {
+                Some(line) => line?,
+                None => break,
+            };
+            if let Some(time) = (self.time_of)(&String::from_utf8_lossy(&line)) {
+                let elapsed = last_time.map(|last| time - last).unwrap_or_default();
+                last_time = Some(time);
+                if elapsed > 0.0 {
+                    if !v.is_empty() {
+                        sender.send_async((self.path.clone(), v.take())).await?;
+                    }
+                    runtime::sleep(replay_pause(elapsed, self.speed)).await;
+                }
+            }
+            v.push(line);
+        }
+        if !v.is_empty() {
+            sender.send_async((self.path.clone(), v.take())).await?;
+        }
+        status.send((self.path.clone(), SourceStatus::Eof))?;
+        // restore after quitting
+        self.enabled.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn stop(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+    }
+
+    fn get_address(&self) -> String {
+        self.path.clone()
+    }
+
+    /// Saved and restored as the file it replays
+    fn get_type(&self) -> SourceType {
+        SourceType::FILE
+    }
+}
+
+/// Source of lines restored from a snapshot: its lines are already stored, so it reads nothing
+/// and is saved as the source it was restored from
+pub struct RestoredSource {
+    address: String,
+    source_type: SourceType,
+}
+
+impl RestoredSource {
+    pub fn new(address: String, source_type: SourceType) -> Self {
+        Self {
+            address,
+            source_type,
+        }
+    }
+}
+
+#[async_trait]
+impl LogSource for RestoredSource {
+    async fn run(
+        &self,
+        _sender: Sender<(String, LineBatch)>,
+        _status: Sender<(String, SourceStatus)>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn stop(&self) {}
+
+    fn get_address(&self) -> String {
+        self.address.clone()
+    }
+
+    fn get_type(&self) -> SourceType {
+        self.source_type
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_wait_longer_up_to_the_max() {
+        let retry = Backoff {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(3),
+        };
+        let delays: Vec<u128> = (1..=5).map(|failures| retry.delay(failures).as_millis()).collect();
+        assert_eq!(delays, [500, 1000, 2000, 3000, 3000]);
+        assert_eq!(retry.delay(u32::MAX), retry.max);
+    }
+
+    #[test]
+    fn replay_pauses_are_clamped_to_the_max() {
+        assert_eq!(replay_pause(3.0, 2.0), Duration::from_millis(1500));
+        assert_eq!(replay_pause(3600.0, 1.0), MAX_REPLAY_PAUSE);
+        assert_eq!(replay_pause(1.0, 0.0), MAX_REPLAY_PAUSE);
+        assert_eq!(replay_pause(f64::MAX, 1e-300), MAX_REPLAY_PAUSE);
+        assert_eq!(replay_pause(1.0, f64::NAN), MAX_REPLAY_PAUSE);
+    }
 }
@@ -0,0 +1,89 @@
+use std::fs::File;
+use std::io;
+
+use bytes::Bytes;
+use memchr::memchr_iter;
+use memmap2::Mmap;
+
+use super::line_batch::LineBatch;
+
+/// Lines of a file mapped in memory, read in batches from an offset.
+///
+/// Only the lines of the next batch are looked for, so loading a large file neither reads it
+/// into memory at once nor indexes all its lines before sending the first ones
+pub struct MappedLines {
+    map: Mmap,
+    /// Offset of the first line not read yet
+    position: usize,
+    partial: bool,
+}
+
+impl MappedLines {
+    /// Map `file` to read its lines from the byte `offset` on. A last line without newline
+    /// is being written and left for later, unless `partial` is set
+    pub fn open(file: &File, offset: usize, partial: bool) -> io::Result<Self> {
+        // SAFETY: the mapping is undefined behavior if the file is truncated while mapped, a
+        // SIGBUS on most systems. Only the files loaded once are mapped, the followed ones,
+        // which can be truncated by a rotation, are read with `ReadLines`
+        let map = unsafe { Mmap::map(file)? };
+        let position = offset.min(map.len());
+        Ok(Self {
+            map,
+            position,
+            partial,
+        })
+    }
+
+    /// Next batch of up to `capacity` lines, `None` once every line was read
+    pub fn next_batch(&mut self, capacity: usize) -> Option<LineBatch> {
+        let rest = &self.map[self.position..];
+        let mut len = 0;
+        let mut end = 0;
+        for newline in memchr_iter(b'\n', rest).take(capacity) {
+            len += 1;
+            end = newline + 1;
+        }
+        if len < capacity && self.partial && end < rest.len() {
+            len += 1;
+            end = rest.len();
+        }
+        if len == 0 {
+            return None;
+        }
+
+        let bytes = Bytes::copy_from_slice(&rest[..end]);
+        self.position += end;
+        Some(LineBatch::new(bytes, len))
+    }
+
+    /// Offset following the last line read, where the next lines start
+    pub fn end(&self) -> usize {
+        self.position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_read_in_batches_from_the_offset() {
+        let dir = test_utils::tempdir().unwrap();
+        let path = dir.path().join("mapped.log");
+        std::fs::write(&path, "first\r\nsecond\nthird").unwrap();
+        let file = File::open(&path).unwrap();
+
+        let mut lines = MappedLines::open(&file, 0, false).unwrap();
+        let batch = lines.next_batch(1).unwrap();
+        assert_eq!(batch.lines().collect::<Vec<_>>(), vec![&b"first"[..]]);
+        assert_eq!(lines.next_batch(10).unwrap().len(), 1);
+        assert!(lines.next_batch(10).is_none());
+        assert_eq!(lines.end(), 14);
+
+        let mut lines = MappedLines::open(&file, lines.end(), true).unwrap();
+        let batch = lines.next_batch(10).unwrap();
+        assert_eq!(batch.lines().collect::<Vec<_>>(), vec![&b"third"[..]]);
+        assert_eq!(lines.end(), 19);
+        assert!(MappedLines::open(&file, lines.end(), true).unwrap().next_batch(10).is_none());
+    }
+}
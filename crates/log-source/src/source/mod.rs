@@ -1 +1,7 @@
-pub mod log_source;
\ No newline at end of file
+pub mod line_batch;
+pub mod log_source;
+pub mod mapped_lines;
+#[cfg(feature = "plugins")]
+pub mod plugin_source;
+pub mod read_lines;
+pub mod runtime;
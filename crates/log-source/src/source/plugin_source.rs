@@ -0,0 +1,410 @@
+//! Sources read by plugin libraries loaded at runtime, declared in `include/lap_source_plugin.h`.
+//!
+//! A plugin is a dynamic library exporting `lap_source_plugin`, which returns the functions of
+//! the plugin. Its sources are polled: `read` copies the lines available, each one ending with a
+//! newline. It is called on a blocking thread of the runtime, so it can wait for lines, briefly
+//! for the source to be stopped in time. A line longer than the buffer given to `read` can be
+//! copied over several reads: the bytes after the last newline are kept until the rest of their
+//! line is read.
+//!
+//! # Safety
+//! The functions of a plugin can be called from any thread, one call at a time per source, and
+//! must not unwind across the boundary
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use flume::Sender;
+use libloading::Library;
+use tracing::{debug, warn};
+
+use super::line_batch::LineBatch;
+use super::log_source::{LogSource, SourceStatus, SourceType};
+use super::runtime;
+
+/// Version of the plugin interface, a plugin built for another one is not loaded
+pub const LAP_PLUGIN_ABI_VERSION: u32 = 1;
+/// Returned by `read` once the source has no more lines
+pub const LAP_SOURCE_END: isize = -1;
+/// Returned by `read` when the source failed, `last_error` telling why
+pub const LAP_SOURCE_ERROR: isize = -2;
+
+/// Function exported by the plugin libraries
+const ENTRY_POINT: &[u8] = b"lap_source_plugin";
+/// Bytes of lines copied at most by a read of a plugin, longer lines take several reads
+const PLUGIN_BUFFER_SIZE: usize = 1 << 20;
+/// Time between two reads of a plugin source without lines available
+const PLUGIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Functions of a plugin, returned by its `lap_source_plugin` entry point. They stay valid while
+/// the library is loaded
+#[repr(C)]
+pub struct LapSourcePlugin {
+    /// `LAP_PLUGIN_ABI_VERSION` the plugin was built for
+    pub abi_version: u32,
+    /// Name of the plugin, the scheme of the addresses it reads
+    pub name: *const c_char,
+    /// Open a source reading `address`, returning the state given to the other functions, or
+    /// null if it can't be opened, `last_error(null)` telling why
+    pub open: unsafe extern "C" fn(address: *const c_char) -> *mut c_void,
+    /// Copy the lines available, each one followed by `\n`, to the `capacity` bytes of `buffer`.
+    /// The bytes after the last `\n` start a line continued by the next read.
+    /// Returns the number of bytes copied, 0 if no line is available yet, `LAP_SOURCE_END` or
+    /// `LAP_SOURCE_ERROR`
+    pub read: unsafe extern "C" fn(source: *mut c_void, buffer: *mut u8, capacity: usize) -> isize,
+    /// Description of the last failure of `source`, or of `open` if null. Owned by the plugin
+    /// and valid until its next call
+    pub last_error: unsafe extern "C" fn(source: *mut c_void) -> *const c_char,
+    /// Release a source returned by `open`
+    pub close: unsafe extern "C" fn(source: *mut c_void),
+}
+
+// The functions and the name are never changed, so plugins written in Rust can return a static
+unsafe impl Sync for LapSourcePlugin {}
+
+/// Signature of the `lap_source_plugin` entry point
+pub type LapSourcePluginEntry = unsafe extern "C" fn() -> *const LapSourcePlugin;
+
+/// Plugin loaded from a library
+pub struct SourcePlugin {
+    name: String,
+    functions: *const LapSourcePlugin,
+    /// Keeps `functions` loaded
+    _library: Option<Library>,
+}
+
+// The functions of a plugin can be called from any thread, see the module documentation
+unsafe impl Send for SourcePlugin {}
+unsafe impl Sync for SourcePlugin {}
+
+impl SourcePlugin {
+    /// Load the plugin of the library at `path`. Loading a library runs its initialization code,
+    /// only trusted libraries must be loaded
+    pub fn load(path: &Path) -> Result<Self> {
+        unsafe {
+            let library = Library::new(path)?;
+            let functions = library.get::<LapSourcePluginEntry>(ENTRY_POINT)?();
+            Self::new(functions, Some(library))
+        }
+    }
+
+    /// # Safety
+    /// `functions` must be null or valid while `library` is loaded
+    unsafe fn new(functions: *const LapSourcePlugin, library: Option<Library>) -> Result<Self> {
+        let plugin = functions
+            .as_ref()
+            .ok_or_else(|| anyhow!("The plugin returned no functions"))?;
+        if plugin.abi_version != LAP_PLUGIN_ABI_VERSION {
+            return Err(anyhow!(
+                "The plugin was built for the plugin interface {}, expected {}",
+                plugin.abi_version,
+                LAP_PLUGIN_ABI_VERSION
+            ));
+        }
+        if plugin.name.is_null() {
+            return Err(anyhow!("The plugin has no name"));
+        }
+        Ok(Self {
+            name: CStr::from_ptr(plugin.name).to_str()?.to_string(),
+            functions,
+            _library: library,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn functions(&self) -> &LapSourcePlugin {
+        unsafe { &*self.functions }
+    }
+
+    fn last_error(&self, source: *mut c_void) -> String {
+        let error = unsafe { (self.functions().last_error)(source) };
+        match error.is_null() {
+            true => "Unknown error".to_string(),
+            false => unsafe { CStr::from_ptr(error) }.to_string_lossy().to_string(),
+        }
+    }
+}
+
+/// Plugins loaded from a directory, each reading the addresses with its name as scheme
+#[derive(Default)]
+pub struct SourcePlugins {
+    plugins: Vec<Arc<SourcePlugin>>,
+}
+
+impl SourcePlugins {
+    /// Load the libraries of `dir` with the extension of the platform (`so`, `dylib` or `dll`).
+    /// The libraries that can't be loaded are skipped with a warning, as a missing directory
+    pub fn load(dir: &Path) -> Self {
+        let mut plugins = Self::default();
+        let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|extension| extension == std::env::consts::DLL_EXTENSION)
+                })
+                .collect(),
+            Err(_) => return plugins,
+        };
+        paths.sort();
+        for path in paths {
+            match SourcePlugin::load(&path) {
+                Ok(plugin) => plugins.add(plugin),
+                Err(err) => {
+                    warn!(plugin = %path.display(), error = %err, "Unable to load the plugin")
+                }
+            }
+        }
+        plugins
+    }
+
+    /// Add a plugin, ignored if another one has its name
+    pub fn add(&mut self, plugin: SourcePlugin) {
+        if self.plugins.iter().any(|loaded| loaded.name == plugin.name) {
+            warn!(plugin = plugin.name, "A plugin with the same name is already loaded");
+            return;
+        }
+        debug!(plugin = plugin.name, "Loaded plugin");
+        self.plugins.push(Arc::new(plugin));
+    }
+
+    /// Names of the plugins, in loading order
+    pub fn names(&self) -> Vec<String> {
+        self.plugins.iter().map(|plugin| plugin.name.clone()).collect()
+    }
+
+    /// Create a source reading `address` with the plugin named after its scheme
+    pub fn create_source(&self, address: &str) -> Result<Box<dyn LogSource + Send + Sync>> {
+        let scheme = address.split_once("://").map(|(scheme, _)| scheme);
+        let plugin = self
+            .plugins
+            .iter()
+            .find(|plugin| Some(plugin.name.as_str()) == scheme)
+            .ok_or_else(|| anyhow!("No plugin reads {}", address))?;
+        Ok(Box::new(PluginSource {
+            plugin: plugin.clone(),
+            address: address.to_string(),
+            enabled: AtomicBool::new(true),
+        }))
+    }
+}
+
+/// Source opened by a plugin, closed when dropped
+struct OpenedSource {
+    plugin: Arc<SourcePlugin>,
+    source: *mut c_void,
+}
+
+// Used by one task at a time, see the module documentation
+unsafe impl Send for OpenedSource {}
+
+impl OpenedSource {
+    /// Open `address` with `plugin`, or tell why it can't be opened
+    fn open(plugin: Arc<SourcePlugin>, address: &CStr) -> Result<Self, String> {
+        let source = unsafe { (plugin.functions().open)(address.as_ptr()) };
+        match source.is_null() {
+            true => Err(plugin.last_error(ptr::null_mut())),
+            false => Ok(Self { plugin, source }),
+        }
+    }
+
+    fn read(&self, buffer: &mut [u8]) -> isize {
+        unsafe { (self.plugin.functions().read)(self.source, buffer.as_mut_ptr(), buffer.len()) }
+    }
+
+    fn last_error(&self) -> String {
+        self.plugin.last_error(self.source)
+    }
+}
+
+impl Drop for OpenedSource {
+    fn drop(&mut self) {
+        unsafe { (self.plugin.functions().close)(self.source) }
+    }
+}
+
+pub struct PluginSource {
+    plugin: Arc<SourcePlugin>,
+    address: String,
+    enabled: AtomicBool,
+}
+
+#[async_trait]
+impl LogSource for PluginSource {
+    async fn run(
+        &self,
+        sender: Sender<(String, LineBatch)>,
+        status: Sender<(String, SourceStatus)>,
+    ) -> Result<()> {
+        // The plugin may block, so it is only called out of the executor
+        let address = CString::new(self.address.as_str())?;
+        let plugin = self.plugin.clone();
+        let mut source = match runtime::spawn_blocking(move || {
+            OpenedSource::open(plugin, &address)
+        })
+        .await
+        {
+            Ok(source) => source,
+            Err(error) => {
+                warn!(source = %self.address, error = %error, "Unable to open the plugin source");
+                status.send((self.address.clone(), SourceStatus::Unavailable(error)))?;
+                return Ok(());
+            }
+        };
+        status.send((self.address.clone(), SourceStatus::Connected))?;
+
+        let mut buffer = vec![0_u8; PLUGIN_BUFFER_SIZE];
+        // Start of a line continued by the next read
+        let mut pending: Vec<u8> = Vec::new();
+        while self.enabled.load(Ordering::Relaxed) {
+            let (opened, filled, read) = runtime::spawn_blocking(move || {
+                let read = source.read(&mut buffer);
+                (source, buffer, read)
+            })
+            .await;
+            (source, buffer) = (opened, filled);
+            match read {
+                0 => runtime::sleep(PLUGIN_POLL_INTERVAL).await,
+                LAP_SOURCE_END => {
+                    if !pending.is_empty() {
+                        let batch = LineBatch::new(Bytes::from(std::mem::take(&mut pending)), 1);
+                        sender.send_async((self.address.clone(), batch)).await?;
+                    }
+                    status.send((self.address.clone(), SourceStatus::Eof))?;
+                    break;
+                }
+                read if read > 0 => {
+                    pending.extend_from_slice(&buffer[..(read as usize).min(buffer.len())]);
+                    let end = match memchr::memrchr(b'\n', &pending) {
+                        Some(newline) => newline + 1,
+                        None => continue,
+                    };
+                    let rest = pending.split_off(end);
+                    let lines = std::mem::replace(&mut pending, rest);
+                    let len = memchr::memchr_iter(b'\n', &lines).count();
+                    let batch = LineBatch::new(Bytes::from(lines), len);
+                    sender.send_async((self.address.clone(), batch)).await?;
+                }
+                _ => {
+                    let error = source.last_error();
+                    warn!(source = %self.address, error = %error, "The plugin source failed");
+                    status.send((self.address.clone(), SourceStatus::Unavailable(error)))?;
+                    break;
+                }
+            }
+        }
+        runtime::spawn_blocking(move || drop(source)).await;
+        // restore after quitting
+        self.enabled.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn stop(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+    }
+
+    fn get_address(&self) -> String {
+        self.address.clone()
+    }
+
+    fn get_type(&self) -> SourceType {
+        SourceType::PLUGIN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plugin sending three lines, one over two reads and the last one without newline, then
+    /// ending. The addresses of other hosts are refused
+    mod counter {
+        use super::*;
+
+        pub unsafe extern "C" fn open(address: *const c_char) -> *mut c_void {
+            match CStr::from_ptr(address).to_bytes() {
+                b"counter://local" => Box::into_raw(Box::new(0_usize)) as *mut c_void,
+                _ => ptr::null_mut(),
+            }
+        }
+
+        pub unsafe extern "C" fn read(source: *mut c_void, buffer: *mut u8, _: usize) -> isize {
+            let reads = &mut *(source as *mut usize);
+            *reads += 1;
+            let lines: &[u8] = match reads {
+                1 => b"one\ntw",
+                2 => b"o\n",
+                3 => b"",
+                4 => b"three",
+                _ => return LAP_SOURCE_END,
+            };
+            ptr::copy_nonoverlapping(lines.as_ptr(), buffer, lines.len());
+            lines.len() as isize
+        }
+
+        pub unsafe extern "C" fn last_error(_: *mut c_void) -> *const c_char {
+            c"Unknown host".as_ptr()
+        }
+
+        pub unsafe extern "C" fn close(source: *mut c_void) {
+            drop(Box::from_raw(source as *mut usize));
+        }
+    }
+
+    static COUNTER: LapSourcePlugin = LapSourcePlugin {
+        abi_version: LAP_PLUGIN_ABI_VERSION,
+        name: c"counter".as_ptr(),
+        open: counter::open,
+        read: counter::read,
+        last_error: counter::last_error,
+        close: counter::close,
+    };
+
+    #[test]
+    fn plugin_sources_are_read_until_their_end() {
+        let mut plugins = SourcePlugins::default();
+        plugins.add(unsafe { SourcePlugin::new(&COUNTER, None) }.unwrap());
+        assert_eq!(plugins.names(), ["counter"]);
+        assert!(plugins.create_source("kafka://broker").is_err());
+
+        let read = |address: &str| {
+            let source = plugins.create_source(address).unwrap();
+            let (sender, lines) = flume::unbounded();
+            let (status_sender, status) = flume::unbounded();
+            runtime::block_on(source.run(sender, status_sender)).unwrap();
+            let lines: Vec<String> = lines
+                .drain()
+                .flat_map(|(_, batch)| {
+                    let lines: Vec<String> = batch
+                        .lines()
+                        .map(|line| String::from_utf8_lossy(line).to_string())
+                        .collect();
+                    lines
+                })
+                .collect();
+            (lines, status.drain().map(|(_, status)| status).collect::<Vec<_>>())
+        };
+        let (lines, status) = read("counter://local");
+        assert_eq!(lines, ["one", "two", "three"]);
+        assert_eq!(status, [SourceStatus::Connected, SourceStatus::Eof]);
+        let (lines, status) = read("counter://remote");
+        assert!(lines.is_empty());
+        assert_eq!(status, [SourceStatus::Unavailable("Unknown host".to_string())]);
+
+        let newer = LapSourcePlugin {
+            abi_version: LAP_PLUGIN_ABI_VERSION + 1,
+            ..COUNTER
+        };
+        assert!(unsafe { SourcePlugin::new(&newer, None) }.is_err());
+    }
+}
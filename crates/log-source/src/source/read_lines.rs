@@ -0,0 +1,93 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Take};
+
+use bytes::Bytes;
+
+use super::line_batch::LineBatch;
+
+/// Size of the reads from the file
+const READ_BUFFER_SIZE: usize = 1 << 20;
+
+/// Lines of a file read in batches from an offset up to a given length.
+///
+/// Unlike `MappedLines`, a file truncated while read only ends the lines early, so followed
+/// files, which are truncated when rotated, are read this way
+pub struct ReadLines {
+    reader: BufReader<Take<File>>,
+    /// Offset of the first line not read yet
+    position: usize,
+    partial: bool,
+}
+
+impl ReadLines {
+    /// Read the lines of `file` from the byte `offset` to `length`. A last line without newline
+    /// is being written and left for later, unless `partial` is set
+    pub fn open(mut file: File, offset: usize, length: usize, partial: bool) -> io::Result<Self> {
+        file.seek(SeekFrom::Start(offset as u64))?;
+        let file = file.take(length.saturating_sub(offset) as u64);
+        Ok(Self {
+            reader: BufReader::with_capacity(READ_BUFFER_SIZE, file),
+            position: offset,
+            partial,
+        })
+    }
+
+    /// Next batch of up to `capacity` lines, `None` once every line was read
+    pub fn next_batch(&mut self, capacity: usize) -> io::Result<Option<LineBatch>> {
+        let mut bytes = Vec::new();
+        let mut len = 0;
+        while len < capacity {
+            let start = bytes.len();
+            match self.reader.read_until(b'\n', &mut bytes)? {
+                0 => break,
+                _ if bytes.ends_with(b"\n") || self.partial => len += 1,
+                _ => {
+                    bytes.truncate(start);
+                    break;
+                }
+            }
+        }
+        if len == 0 {
+            return Ok(None);
+        }
+
+        self.position += bytes.len();
+        Ok(Some(LineBatch::new(Bytes::from(bytes), len)))
+    }
+
+    /// Offset following the last line read, where the next lines start
+    pub fn end(&self) -> usize {
+        self.position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_read_up_to_the_length() {
+        let dir = test_utils::tempdir().unwrap();
+        let path = dir.path().join("read.log");
+        std::fs::write(&path, "first\r\nsecond\nthird").unwrap();
+        let open = |offset, length, partial| {
+            ReadLines::open(File::open(&path).unwrap(), offset, length, partial).unwrap()
+        };
+
+        let mut lines = open(0, 19, false);
+        let batch = lines.next_batch(1).unwrap().unwrap();
+        assert_eq!(batch.lines().collect::<Vec<_>>(), vec![&b"first"[..]]);
+        assert_eq!(lines.next_batch(10).unwrap().unwrap().len(), 1);
+        assert!(lines.next_batch(10).unwrap().is_none());
+        assert_eq!(lines.end(), 14);
+
+        let mut lines = open(14, 19, true);
+        let batch = lines.next_batch(10).unwrap().unwrap();
+        assert_eq!(batch.lines().collect::<Vec<_>>(), vec![&b"third"[..]]);
+        assert_eq!(lines.end(), 19);
+        // The bytes appended after the length was taken are left for the next read
+        let mut lines = open(0, 10, true);
+        assert_eq!(lines.next_batch(10).unwrap().unwrap().len(), 2);
+        assert_eq!(lines.end(), 10);
+    }
+}
@@ -0,0 +1,248 @@
+//! Async runtime running the sources: async-std by default, or tokio with the `tokio` feature
+//! so embedders already using tokio don't pull in a second executor. Tokio is used when both
+//! features are enabled.
+//!
+//! The sources only reach the runtime through these functions and `Lines`
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+
+#[cfg(not(any(feature = "async-std", feature = "tokio")))]
+compile_error!("log-source needs the `async-std` or the `tokio` feature to run its sources");
+
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+mod imp {
+    use async_std::io::{prelude::BufReadExt, BufReader, Read, Split};
+    use async_std::prelude::StreamExt;
+
+    use super::*;
+
+    pub type Reader = Split<BufReader<Box<dyn Read + Send + Unpin>>>;
+
+    pub fn reader(read: impl Read + Send + Unpin + 'static) -> Reader {
+        let read: Box<dyn Read + Send + Unpin> = Box::new(read);
+        BufReader::new(read).split(b'\n')
+    }
+
+    pub async fn next(reader: &mut Reader) -> Option<io::Result<Vec<u8>>> {
+        reader.next().await
+    }
+
+    pub async fn connect(address: &str) -> io::Result<Reader> {
+        Ok(reader(async_std::net::TcpStream::connect(address).await?))
+    }
+
+    pub async fn open(path: &str) -> io::Result<Reader> {
+        Ok(reader(async_std::fs::File::open(path).await?))
+    }
+
+    pub fn stdin() -> Reader {
+        reader(async_std::io::stdin())
+    }
+
+    pub fn spawn<F: Future<Output = ()> + Send + 'static>(future: F) {
+        async_std::task::spawn(future);
+    }
+
+    pub fn block_on<F: Future + Send>(future: F) -> F::Output
+    where
+        F::Output: Send,
+    {
+        async_std::task::block_on(future)
+    }
+
+    pub async fn spawn_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+        async_std::task::spawn_blocking(f).await
+    }
+
+    pub async fn sleep(duration: Duration) {
+        async_std::task::sleep(duration).await
+    }
+
+    pub async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+        async_std::future::timeout(duration, future).await.ok()
+    }
+}
+
+#[cfg(feature = "tokio")]
+mod imp {
+    use std::sync::OnceLock;
+
+    use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader, Split};
+    use tokio::runtime::{Builder, Handle, Runtime};
+
+    use super::*;
+
+    pub type Reader = Split<BufReader<Box<dyn AsyncRead + Send + Unpin>>>;
+
+    pub fn reader(read: impl AsyncRead + Send + Unpin + 'static) -> Reader {
+        let read: Box<dyn AsyncRead + Send + Unpin> = Box::new(read);
+        BufReader::new(read).split(b'\n')
+    }
+
+    pub async fn next(reader: &mut Reader) -> Option<io::Result<Vec<u8>>> {
+        reader.next_segment().await.transpose()
+    }
+
+    pub async fn connect(address: &str) -> io::Result<Reader> {
+        Ok(reader(tokio::net::TcpStream::connect(address).await?))
+    }
+
+    pub async fn open(path: &str) -> io::Result<Reader> {
+        Ok(reader(tokio::fs::File::open(path).await?))
+    }
+
+    pub fn stdin() -> Reader {
+        reader(tokio::io::stdin())
+    }
+
+    /// Runtime of the sources, with the IO and time drivers they need whatever the runtime of
+    /// the caller enables
+    fn runtime() -> &'static Runtime {
+        static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+        RUNTIME.get_or_init(|| {
+            Builder::new_multi_thread()
+                .thread_name("log-source")
+                .enable_all()
+                .build()
+                .expect("Unable to start the tokio runtime of the sources")
+        })
+    }
+
+    pub fn spawn<F: Future<Output = ()> + Send + 'static>(future: F) {
+        drop(runtime().spawn(future));
+    }
+
+    pub fn block_on<F: Future + Send>(future: F) -> F::Output
+    where
+        F::Output: Send,
+    {
+        // A runtime can't be blocked on from the thread of another one
+        if Handle::try_current().is_err() {
+            return runtime().block_on(future);
+        }
+        std::thread::scope(|scope| match scope.spawn(|| runtime().block_on(future)).join() {
+            Ok(output) => output,
+            Err(panic) => std::panic::resume_unwind(panic),
+        })
+    }
+
+    pub async fn spawn_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+        match tokio::task::spawn_blocking(f).await {
+            Ok(output) => output,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        }
+    }
+
+    pub async fn sleep(duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+
+    pub async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+        tokio::time::timeout(duration, future).await.ok()
+    }
+}
+
+/// Lines read from a socket, a file or stdin, without their newline
+pub struct Lines {
+    reader: imp::Reader,
+}
+
+impl Lines {
+    /// Connect to the TCP socket at `address`
+    pub async fn connect(address: &str) -> io::Result<Self> {
+        Ok(Self {
+            reader: imp::connect(address).await?,
+        })
+    }
+
+    /// Open the file at `path`
+    pub async fn open(path: &str) -> io::Result<Self> {
+        Ok(Self {
+            reader: imp::open(path).await?,
+        })
+    }
+
+    /// Standard input of the process
+    pub fn stdin() -> Self {
+        Self {
+            reader: imp::stdin(),
+        }
+    }
+
+    /// Next line, `None` once the end is reached
+    pub async fn next(&mut self) -> Option<io::Result<Vec<u8>>> {
+        imp::next(&mut self.reader).await
+    }
+}
+
+/// Run `future` in the background. With tokio it runs on a runtime of the sources, apart from
+/// the runtime of the caller
+pub fn spawn<F: Future<Output = ()> + Send + 'static>(future: F) {
+    imp::spawn(future)
+}
+
+/// Run `future` to completion, blocking the thread. With tokio it can be called from a task of
+/// another runtime, whose worker stays blocked meanwhile
+pub fn block_on<F: Future + Send>(future: F) -> F::Output
+where
+    F::Output: Send,
+{
+    imp::block_on(future)
+}
+
+/// Run `f` on a thread of the runtime where blocking is allowed, e.g. to read a file, and wait
+/// for its output without holding the executor
+pub async fn spawn_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    imp::spawn_blocking(f).await
+}
+
+pub async fn sleep(duration: Duration) {
+    imp::sleep(duration).await
+}
+
+/// Output of `future` if it completes within `duration`
+pub async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    imp::timeout(duration, future).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_read_from_files() {
+        let dir = test_utils::tempdir().unwrap();
+        let path = dir.path().join("runtime.log");
+        std::fs::write(&path, "first\nsecond").unwrap();
+
+        let lines = block_on(async {
+            let mut lines = Lines::open(path.to_str().unwrap()).await.unwrap();
+            let mut read = Vec::new();
+            while let Some(line) = lines.next().await {
+                read.push(line.unwrap());
+            }
+            read
+        });
+        assert_eq!(lines, vec![b"first".to_vec(), b"second".to_vec()]);
+        assert_eq!(block_on(timeout(Duration::from_secs(1), sleep(Duration::ZERO))), Some(()));
+        assert_eq!(block_on(spawn_blocking(|| 1 + 1)), 2);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn sources_run_from_a_runtime_without_drivers() {
+        let caller = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+
+        let slept = caller.block_on(async {
+            spawn(async move {
+                sleep(Duration::from_millis(1)).await;
+                sender.send(()).unwrap();
+            });
+            block_on(timeout(Duration::from_secs(1), sleep(Duration::from_millis(1))))
+        });
+        assert_eq!(slept, Some(()));
+        receiver.recv_timeout(test_utils::DEADLINE).unwrap();
+    }
+}
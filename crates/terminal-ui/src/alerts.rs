@@ -0,0 +1,93 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, Result};
+use log_analyzer::models::alert::{Alert, AlertAction};
+use log_analyzer::models::log_line::LogLine;
+
+/// Build the shell invocation of a command alert. The line is passed as its first argument
+/// and the filter, source and line as the `LAP_ALERT_*` variables
+fn alert_command(command: &str, filter: &str, source: &str, line: &str) -> Command {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command, line]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command, "sh", line]);
+        cmd
+    };
+    cmd.env("LAP_ALERT_FILTER", filter)
+        .env("LAP_ALERT_SOURCE", source)
+        .env("LAP_ALERT_LINE", line)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    cmd
+}
+
+/// Show a desktop notification with the native tool of the platform
+fn desktop_notification(title: &str, body: &str) -> Result<Command> {
+    if cfg!(target_os = "linux") {
+        let mut cmd = Command::new("notify-send");
+        cmd.args([title, body]);
+        Ok(cmd)
+    } else if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification {:?} with title {:?}",
+            body.replace('"', "'"),
+            title.replace('"', "'")
+        );
+        let mut cmd = Command::new("osascript");
+        cmd.args(["-e", &script]);
+        Ok(cmd)
+    } else {
+        Err(anyhow!("Desktop notifications are not supported on this platform"))
+    }
+}
+
+/// Run the action of the alert triggered by `filter` matching `line`.
+/// Commands run in the background so the interface doesn't wait for them
+pub fn run_alert(alert: &Alert, filter: &str, line: &LogLine) -> Result<()> {
+    let text = line.text();
+    let mut cmd = match &alert.action {
+        AlertAction::Bell => {
+            let mut stdout = std::io::stdout();
+            stdout.write_all(b"\x07")?;
+            stdout.flush()?;
+            return Ok(());
+        }
+        AlertAction::Desktop => desktop_notification(&format!("Alert {}", filter), &text)?,
+        AlertAction::Command { command } => alert_command(command, filter, &line.log, &text),
+    };
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| anyhow!("Alert {}: can't run {:?}: {}", filter, cmd.get_program(), e))?;
+    // Reap the process when it ends
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_get_the_matching_line() {
+        let output = alert_command(
+            "echo \"$1|$LAP_ALERT_FILTER|$LAP_ALERT_SOURCE\"",
+            "Errors",
+            "app.log",
+            "ERROR disk full",
+        )
+        .stdout(Stdio::piped())
+        .output()
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            "ERROR disk full|Errors|app.log"
+        );
+    }
+}
@@ -1,12 +1,34 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crate::alerts;
+use crate::colors::ColorSupport;
+use crate::path_completion;
+use log_analyzer::error::LogAnalyzerError;
+use log_analyzer::models::burst::Burst;
+use log_analyzer::models::alert::AlertAction;
 use log_analyzer::models::filter::FilterAction;
+use log_analyzer::models::gap::Gap;
 use log_analyzer::models::log_line_styled::LogLineStyled;
-use log_analyzer::models::{filter::Filter, log_line::LogLine};
+use log_analyzer::models::metrics::PipelineMetrics;
+use log_analyzer::models::column_filter::ColumnFilter;
+use log_analyzer::models::diff::{DiffQuery, DiffSide};
+use log_analyzer::models::export::ExportFormat;
+use log_analyzer::models::notification::{Notification, NotificationLevel};
+use log_analyzer::models::recent_sources::{RecentSource, RecentSources};
+use log_analyzer::models::session::{ColumnLayout, Session, SESSION_EXTENSION};
+use log_analyzer::models::settings::Settings;
+use log_analyzer::models::snapshot::SNAPSHOT_EXTENSION;
+use log_analyzer::models::source_stats::SourceStats;
+use log_analyzer::models::top_values::TopValuesQuery;
+use log_analyzer::models::watch::Watch;
+use log_analyzer::models::{filter::Filter, log_line::LogLine, severity::Severity};
+use log_analyzer::services::event_coalescer::EventCoalescer;
 use log_analyzer::services::log_service::{Event as LogEvent, LogAnalyzer};
 use tui::style::Color;
 
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use tui_input::backend::crossterm as input_backend;
 use tui_input::Input;
@@ -15,9 +37,18 @@ use crate::data::lazy_stateful_table::{LazySource, LazyStatefulTable, CAPACITY};
 use crate::data::stateful_list::StatefulList;
 use crate::data::stateful_table::StatefulTable;
 use crate::data::Stateful;
+use crate::console::ScriptConsole;
+use crate::macros::MacroRecorder;
+use crate::session::{
+    export_settings, save_configuration, save_recent_sources, save_session, save_settings,
+};
+
+/// Source types of the add source popup tabs: file, websocket and plugin
+pub const SOURCE_POPUP_TYPES: [usize; 3] = [0, 1, 3];
 
 /* ------ NEW SOURCE INDEXES ------- */
-pub const INDEX_SOURCE_TYPE: usize = 0;
+pub const INDEX_SOURCE_RECENT: usize = 0;
+pub const INDEX_SOURCE_TYPE: usize = INDEX_SOURCE_RECENT + 1;
 pub const INDEX_SOURCE_PATH: usize = INDEX_SOURCE_TYPE + 1;
 pub const INDEX_SOURCE_FORMAT: usize = INDEX_SOURCE_PATH + 1;
 pub const INDEX_SOURCE_NEW_FORMAT_ALIAS: usize = INDEX_SOURCE_FORMAT + 1;
@@ -41,35 +72,134 @@ pub const INDEX_FILTER_OK_BUTTON: usize = INDEX_FILTER_BLUE_COLOR + 1;
 pub const INDEX_SEARCH: usize = INDEX_FILTER_OK_BUTTON + 1;
 /* ------ NAVIGATION INDEXES ------- */
 pub const INDEX_NAVIGATION: usize = INDEX_SEARCH + 1;
+/* ------ COLUMN FILTER INDEXES ------- */
+pub const INDEX_COLUMN_FILTER: usize = INDEX_NAVIGATION + 1;
+/* ------ SETTINGS INDEXES ------- */
+pub const INDEX_SETTINGS_PRIMARY_COLOR: usize = INDEX_COLUMN_FILTER + 1;
+pub const INDEX_SETTINGS_STRIPE_COLOR: usize = INDEX_SETTINGS_PRIMARY_COLOR + 1;
+pub const INDEX_SETTINGS_TICK_RATE: usize = INDEX_SETTINGS_STRIPE_COLOR + 1;
+pub const INDEX_SETTINGS_MEMORY_WARNING: usize = INDEX_SETTINGS_TICK_RATE + 1;
+pub const INDEX_SETTINGS_MEMORY_CRITICAL: usize = INDEX_SETTINGS_MEMORY_WARNING + 1;
+/* ------ TOP VALUES INDEXES ------- */
+pub const INDEX_TOP_VALUES_COLUMN: usize = INDEX_SETTINGS_MEMORY_CRITICAL + 1;
+pub const INDEX_TOP_VALUES_LIMIT: usize = INDEX_TOP_VALUES_COLUMN + 1;
+pub const INDEX_TOP_VALUES_SCOPE: usize = INDEX_TOP_VALUES_LIMIT + 1;
+pub const INDEX_TOP_VALUES_RANGE: usize = INDEX_TOP_VALUES_SCOPE + 1;
+/* ------ GAPS INDEXES ------- */
+pub const INDEX_GAPS_MIN: usize = INDEX_TOP_VALUES_RANGE + 1;
+pub const INDEX_GAPS_MARKERS: usize = INDEX_GAPS_MIN + 1;
+/* ------ DIFF INDEXES ------- */
+pub const INDEX_DIFF_LEFT_SOURCE: usize = INDEX_GAPS_MARKERS + 1;
+pub const INDEX_DIFF_LEFT_RANGE: usize = INDEX_DIFF_LEFT_SOURCE + 1;
+pub const INDEX_DIFF_RIGHT_SOURCE: usize = INDEX_DIFF_LEFT_RANGE + 1;
+pub const INDEX_DIFF_RIGHT_RANGE: usize = INDEX_DIFF_RIGHT_SOURCE + 1;
 /* ----------------------------------- */
-pub const INDEX_MAX: usize = INDEX_NAVIGATION + 1;
+pub const INDEX_WATCH_EXPRESSION: usize = INDEX_DIFF_RIGHT_RANGE + 1;
+
+pub const INDEX_CONSOLE_SCRIPT: usize = INDEX_WATCH_EXPRESSION + 1;
+
+pub const INDEX_PATH_POPUP: usize = INDEX_CONSOLE_SCRIPT + 1;
+
+pub const INDEX_MAX: usize = INDEX_PATH_POPUP + 1;
 /* ----------------------------------- */
 
+/// Default memory (in MB) used by the stores before displaying a warning
+pub const DEFAULT_MEMORY_WARNING_MB: usize = 1024;
+/// Default memory (in MB) used by the stores before displaying a critical alert
+pub const DEFAULT_MEMORY_CRITICAL_MB: usize = 4096;
+/// Primary color used when the settings don't define one
+pub const DEFAULT_PRIMARY_COLOR: Color = Color::LightBlue;
+/// Default time between two refreshes of the interface
+pub const DEFAULT_TICK_RATE_MS: u64 = 150;
+/// Maximum number of lines displayed by an expanded group of the grouped log
+pub const MAX_GROUP_LINES: usize = 1000;
+/// Maximum number of notifications kept in the history
+pub const MAX_NOTIFICATIONS: usize = 1000;
+/// Time between two consecutive saves of the session
+pub const SESSION_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
 pub struct PopupInteraction {
     pub response: bool,
     pub message: String,
     pub calling_module: Module,
 }
 
+#[derive(Default)]
 pub struct Processing {
     pub is_processing: bool,
+    /// Filtering in the background while the previous results are displayed
+    pub is_filtering: bool,
+    /// The lines around the viewport were swapped in before the rest, the filtered indexes of
+    /// the preview not matching the ones of the final log
+    pub previewed: bool,
     pub focus_on: usize,
 }
 
 impl Processing {
     fn set_focus(&mut self, focus: Option<usize>) {
-        self.focus_on = match focus {
-            Some(focus) => focus,
-            None => 0,
+        self.focus_on = focus.unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Row of the log grouped by source
+pub enum GroupRow {
+    /// Source header with its number of lines and whether it's expanded
+    Header(String, usize, bool),
+    Line(LogLine),
+    /// Number of lines of an expanded group exceeding `MAX_GROUP_LINES`
+    More(usize),
+}
+
+/// What is done with the file typed in the path popup
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PathAction {
+    /// Write the current formats and filters to a settings file
+    SaveConfiguration,
+    /// Write the whole analysis to a `.lapsession` file, or with its lines to a `.lapsnapshot` one
+    SaveSession,
+    /// Add the analysis of a `.lapsession` file to the current one, or replace it with the one
+    /// of a `.lapsnapshot` file
+    OpenSession,
+    /// Write the filtered log to a file in the format of its extension
+    ExportFiltered,
+    /// Write the search results to a file in the format of its extension
+    ExportSearch,
+}
+
+impl PathAction {
+    pub fn title(&self) -> &'static str {
+        match self {
+            PathAction::SaveConfiguration => "Save configuration",
+            PathAction::SaveSession => "Save session",
+            PathAction::OpenSession => "Open session",
+            PathAction::ExportFiltered => "Export filtered log",
+            PathAction::ExportSearch => "Export search results",
         }
     }
 }
 
-impl Default for Processing {
-    fn default() -> Self {
-        Self {
-            is_processing: false,
-            focus_on: 0,
+/// Kind of index requested by the navigation popup
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NavigationMode {
+    /// Row of the filtered log, as displayed in the index column
+    Filtered,
+    /// Line of the raw source, resolved to the closest filtered row
+    Raw,
+}
+
+impl NavigationMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NavigationMode::Filtered => "filtered row #",
+            NavigationMode::Raw => "raw line #",
+        }
+    }
+
+    pub fn toggle(&self) -> Self {
+        match self {
+            NavigationMode::Filtered => NavigationMode::Raw,
+            NavigationMode::Raw => NavigationMode::Filtered,
         }
     }
 }
@@ -79,12 +209,26 @@ pub enum Module {
     Sources,
     Filters,
     Logs,
+    PinnedLines,
+    ColumnHeader,
     Search,
     SearchResult,
     SourcePopup,
     FilterPopup,
     NavigationPopup,
     ErrorPopup,
+    RestorePopup,
+    ClearPopup,
+    NotificationsPopup,
+    SettingsPopup,
+    PathPopup,
+    TopValuesPopup,
+    GapsPopup,
+    TransactionPopup,
+    DiffPopup,
+    WatchesPopup,
+    LinePopup,
+    ConsolePopup,
     None,
 }
 
@@ -92,8 +236,8 @@ struct LogSourcer {
     log_analyzer: Box<Arc<dyn LogAnalyzer>>,
 }
 
-impl LazySource<LogLine> for LogSourcer {
-    fn source(&self, from: usize, to: usize) -> Vec<LogLine> {
+impl LazySource<Arc<LogLine>> for LogSourcer {
+    fn source(&self, from: usize, to: usize) -> Vec<Arc<LogLine>> {
         self.log_analyzer.get_log_lines(from, to)
     }
 
@@ -101,7 +245,7 @@ impl LazySource<LogLine> for LogSourcer {
         &self,
         index: usize,
         quantity: usize,
-    ) -> (Vec<LogLine>, usize, usize) {
+    ) -> (Vec<Arc<LogLine>>, usize, usize) {
         self.log_analyzer.get_log_lines_containing(index, quantity)
     }
 }
@@ -133,6 +277,10 @@ pub struct App {
 
     /// Primary color
     pub color: Color,
+    /// Background of every other row of the log tables. No striping if not set
+    pub stripe_color: Option<Color>,
+    /// Colors the terminal can render. RGB colors are quantized to them
+    pub color_support: ColorSupport,
 
     /// Currently selected module. Used to manage inputs and highlight focus
     pub selected_module: Module,
@@ -145,8 +293,38 @@ pub struct App {
     pub show_error_message: bool,
     /// Display the navigation popup
     pub show_navigation_popup: bool,
+    /// Kind of index typed in the navigation popup
+    pub navigation_mode: NavigationMode,
     /// Display the navigation popup
     pub show_log_options_popup: bool,
+    /// Display the popup offering to restore the previous session
+    pub show_restore_popup: bool,
+    /// Display the popup confirming to clear the whole analysis
+    pub show_clear_popup: bool,
+    /// Display the history of notifications
+    pub show_notifications_popup: bool,
+    /// Display the settings editor
+    pub show_settings_popup: bool,
+    /// Display the popup asking for the file of `path_action`
+    pub show_path_popup: bool,
+    /// Display the most frequent values of a column
+    pub show_top_values_popup: bool,
+    /// Display the gaps between the lines of the sources
+    pub show_gaps_popup: bool,
+    /// Display the lines sharing the correlation id of the selected line
+    pub show_transaction_popup: bool,
+    /// Display the comparison of two sources or two time ranges
+    pub show_diff_popup: bool,
+    /// Display the watch expressions to add or remove them
+    pub show_watches_popup: bool,
+    /// Show the whole raw line popup
+    pub show_line_popup: bool,
+    /// Display the scripting console
+    pub show_console_popup: bool,
+    /// Action run with the file typed in the path popup
+    pub path_action: PathAction,
+    /// Display the statistics panel below the filters
+    pub show_statistics: bool,
 
     /// Vector of user input. Entries are uniquely assigned to each UI input, and the selection is
     /// performed with the `input_buffer_index`
@@ -155,11 +333,19 @@ pub struct App {
     pub input_buffer_index: usize,
     /// Stateful list of all the current formats to be displayed in the source popup
     pub formats: StatefulList<String>,
+    /// Filesystem entries matching the path typed in the source popup
+    pub path_candidates: StatefulList<String>,
+    /// Recently added sources offered in the source popup, most recent first
+    pub recent_sources: StatefulList<RecentSource>,
+    /// File where the recent sources are remembered. Not remembered if not set
+    pub recent_sources_path: Option<PathBuf>,
 
     /// Tab selector index for Source Type
     pub source_type: usize,
     /// Tab selector index for Filter Type
     pub filter_type: usize,
+    /// Alias of the filter edited in the filter popup, `None` when adding a new one
+    pub edited_filter: Option<String>,
     /// Tab selector index for Filter Type
     pub filter_color: usize,
 
@@ -169,11 +355,23 @@ pub struct App {
     pub filters: StatefulTable<(bool, String)>,
 
     /// Lazy widget for the main view of the logs
-    pub log_lines: LazyStatefulTable<LogLine>,
+    pub log_lines: LazyStatefulTable<Arc<LogLine>>,
     /// Lazy widget for the main view of the search
     pub search_lines: LazyStatefulTable<LogLineStyled>,
     /// Apply an offset to the logs to simulate horizontal scrolling
     pub horizontal_offset: usize,
+    /// Position in the displayed columns of the header focused for quick filtering
+    pub selected_header: usize,
+    /// Lines pinned by the user to remain visible above the log
+    pub pinned_lines: StatefulTable<LogLine>,
+    /// Display the log grouped by source instead of the combined log
+    pub group_by_source: bool,
+    /// Sources whose lines are displayed in the grouped log
+    pub expanded_groups: Vec<String>,
+    /// Rows of the grouped log
+    pub grouped_log: StatefulTable<GroupRow>,
+    /// Start and end lines marked to measure the elapsed time between them
+    pub time_marks: (Option<LogLine>, Option<LogLine>),
 
     /// Resizing of the side_menu to the main view
     pub side_main_size_percentage: u16,
@@ -184,9 +382,110 @@ pub struct App {
 
     /// Active log columns to display in the log and the search
     pub log_columns: Vec<(String, bool)>,
+    /// Columns kept on the left and unaffected by horizontal scrolling
+    pub frozen_columns: Vec<String>,
+
+    /// Key sequences recorded by the user
+    pub macros: MacroRecorder,
 
     /// Auto scroll to the last receive elements. Used for live logs
     pub auto_scroll: bool,
+    /// Move the selection of the log and the search results together
+    pub sync_cursor: bool,
+
+    /// Memory used by the stores (in MB) from which a warning is displayed
+    pub memory_warning_mb: usize,
+    /// Memory used by the stores (in MB) from which a critical alert is displayed
+    pub memory_critical_mb: usize,
+    /// Time between two refreshes of the interface
+    pub tick_rate: Duration,
+
+    /// Display options of the settings, as edited in the settings popup
+    pub settings: Settings,
+    /// File where the settings are written back. Not saved if not set
+    pub settings_path: Option<PathBuf>,
+    /// Reason why the options of the settings popup can't be applied
+    pub settings_error: Option<String>,
+    /// Reason why the action of the path popup failed
+    pub path_popup_error: Option<String>,
+    /// Position in `LogLine::columns` of the column counted in the top values popup
+    pub top_values_column: usize,
+    /// Count the search results instead of the filtered log in the top values popup
+    pub top_values_search: bool,
+    /// Only count the lines between the time marks in the top values popup
+    pub top_values_between_marks: bool,
+    /// Most frequent values of the column with their lines, most frequent first
+    pub top_values: Vec<(String, usize)>,
+    /// Reason why the top values can't be counted
+    pub top_values_error: Option<String>,
+    /// Gaps longer than the minimum of the gaps popup, in the order of the log
+    pub gaps: StatefulTable<Gap>,
+    /// Underline the last line of a source before each gap in the log
+    pub gap_markers: bool,
+    /// Reason why the gaps can't be found
+    pub gaps_error: Option<String>,
+    /// Correlation id of the transaction popup
+    pub transaction_id: String,
+    /// Lines of the transaction popup, sorted by index
+    pub transaction_lines: StatefulTable<LogLine>,
+    /// Source of the left and right sides of the diff: 0 for every source, the position in
+    /// `sources` plus one otherwise
+    pub diff_sources: [usize; 2],
+    /// Time range (in seconds) of the left and right sides of the diff, every line if not set
+    pub diff_ranges: [Option<(f64, f64)>; 2],
+    /// Lines found on one side of the diff only, marked `-` for the left side and `+` for the
+    /// right one, sorted by index
+    pub diff_lines: StatefulTable<(char, LogLine)>,
+    /// Lines found on both sides of the diff
+    pub diff_common: usize,
+    /// Reason why the diff can't be computed
+    pub diff_error: Option<String>,
+    /// Source and raw index of the line of the raw line popup
+    pub raw_line_title: String,
+    /// Whole content of the line of the raw line popup, before being cut to `max_line_length`
+    pub raw_line: String,
+    /// Characters of `raw_line`, counted once when the popup is opened
+    pub raw_line_chars: usize,
+    /// First row displayed in the raw line popup
+    pub raw_line_scroll: usize,
+    /// Lines, lag and errors of each source, refreshed on every tick
+    pub source_stats: Vec<SourceStats>,
+    /// Live lines matched by the watch expressions per second, refreshed on every tick
+    pub watches: StatefulTable<Watch>,
+    /// Reason why the watch expression can't be added
+    pub watches_error: Option<String>,
+    /// Scripts run over the analysis with their output
+    pub console: ScriptConsole,
+    /// Rows of the console output scrolled up from its end
+    pub console_scroll: usize,
+    /// Last `.lapsession` or `.lapsnapshot` file saved or opened
+    pub session_file: Option<PathBuf>,
+    /// Last file the filtered log was exported to
+    pub export_file: Option<PathBuf>,
+
+    /// History of runtime events, oldest first
+    pub notifications: StatefulTable<Notification>,
+    /// Notifications received since the history was last opened
+    pub unread_notifications: usize,
+    /// Last throughput of the pipeline received
+    pub metrics: PipelineMetrics,
+    /// Intervals of log time with many more lines than usual, marked in the log
+    pub bursts: Vec<Burst>,
+
+    /// File where the session is autosaved. Autosave is disabled if not set
+    pub session_path: Option<PathBuf>,
+    /// Settings file written with the sources, formats and filters shown when quitting
+    pub export_path: Option<PathBuf>,
+    /// Whether the analysis runs on a server started with `lap serve`
+    pub is_remote: bool,
+    /// Session found on startup, waiting for the user to decide whether to restore it
+    pub pending_session: Option<Session>,
+    /// Last time the session was saved
+    last_autosave: Instant,
+    /// Restored log position to navigate to once enough lines are processed
+    pending_position: Option<usize>,
+    /// (source, raw index) of the selected line last sent to the analyzer
+    viewport: Option<(String, usize)>,
 
     /// Manage the popup interaction
     pub popup: PopupInteraction,
@@ -221,24 +520,46 @@ impl App {
         };
 
         let event_receiver = log_analyzer.on_event();
+        let console = ScriptConsole::new(log_analyzer.as_ref().clone());
 
         App {
             log_analyzer,
             color: primary_color,
+            stripe_color: None,
+            color_support: ColorSupport::TrueColor,
             selected_module: Module::Sources,
             show_source_popup: false,
             show_filter_popup: false,
             show_navigation_popup: false,
+            navigation_mode: NavigationMode::Filtered,
             show_error_message: false,
             show_log_options_popup: false,
+            show_restore_popup: false,
+            show_clear_popup: false,
+            show_notifications_popup: false,
+            show_settings_popup: false,
+            show_top_values_popup: false,
+            show_gaps_popup: false,
+            show_transaction_popup: false,
+            show_diff_popup: false,
+            show_watches_popup: false,
+            show_line_popup: false,
+            show_console_popup: false,
+            show_path_popup: false,
+            path_action: PathAction::SaveConfiguration,
+            show_statistics: false,
 
             input_buffers: vec![Input::default(); INDEX_MAX],
             input_buffer_index: 0,
 
             formats: StatefulList::with_items(formats),
+            path_candidates: StatefulList::with_items(Vec::new()),
+            recent_sources: StatefulList::with_items(Vec::new()),
+            recent_sources_path: None,
 
             source_type: 0,
             filter_type: 0,
+            edited_filter: None,
             filter_color: 0,
 
             sources: StatefulTable::with_items(sources),
@@ -247,6 +568,12 @@ impl App {
             log_lines: LazyStatefulTable::new(Box::new(log_sourcer)),
             search_lines: LazyStatefulTable::new(Box::new(search_sourcer)),
             horizontal_offset: 0,
+            selected_header: 0,
+            pinned_lines: StatefulTable::with_items(Vec::new()),
+            group_by_source: false,
+            expanded_groups: Vec::new(),
+            grouped_log: StatefulTable::with_items(Vec::new()),
+            time_marks: (None, None),
             log_filter_size_percentage: 50,
             log_search_size_percentage: 75,
             side_main_size_percentage: 25,
@@ -254,7 +581,58 @@ impl App {
                 .into_iter()
                 .map(|column| (column, true))
                 .collect(),
+            frozen_columns: vec!["Index".to_string()],
+            macros: MacroRecorder::default(),
             auto_scroll: false,
+            sync_cursor: false,
+
+            memory_warning_mb: DEFAULT_MEMORY_WARNING_MB,
+            memory_critical_mb: DEFAULT_MEMORY_CRITICAL_MB,
+            tick_rate: Duration::from_millis(DEFAULT_TICK_RATE_MS),
+
+            settings: Settings::default(),
+            settings_path: None,
+            settings_error: None,
+            path_popup_error: None,
+            top_values_column: 0,
+            top_values_search: false,
+            top_values_between_marks: false,
+            top_values: Vec::new(),
+            top_values_error: None,
+            gaps: StatefulTable::with_items(Vec::new()),
+            gap_markers: false,
+            gaps_error: None,
+            transaction_id: String::new(),
+            transaction_lines: StatefulTable::with_items(Vec::new()),
+            diff_sources: [0, 0],
+            diff_ranges: [None, None],
+            diff_lines: StatefulTable::with_items(Vec::new()),
+            diff_common: 0,
+            diff_error: None,
+            raw_line_title: String::new(),
+            raw_line: String::new(),
+            raw_line_chars: 0,
+            raw_line_scroll: 0,
+            source_stats: Vec::new(),
+            watches: StatefulTable::with_items(Vec::new()),
+            watches_error: None,
+            console,
+            console_scroll: 0,
+            session_file: None,
+            export_file: None,
+
+            notifications: StatefulTable::with_items(Vec::new()),
+            unread_notifications: 0,
+            metrics: PipelineMetrics::default(),
+            bursts: Vec::new(),
+
+            session_path: None,
+            export_path: None,
+            is_remote: false,
+            pending_session: None,
+            last_autosave: Instant::now(),
+            pending_position: None,
+            viewport: None,
 
             popup: PopupInteraction {
                 response: true,
@@ -287,16 +665,81 @@ impl App {
                 }
 
             },
-            _ => Some(self.formats.items[selected_format_index].clone())
+            _ => {
+                // Changing the alias of an existing format renames it
+                let alias = self.formats.items[selected_format_index].clone();
+                let new_alias = self.input_buffers[INDEX_SOURCE_NEW_FORMAT_ALIAS].value().trim().to_string();
+                if !new_alias.is_empty() && new_alias != alias {
+                    self.log_analyzer.rename_format(&alias, &new_alias)?;
+                    self.update_formats().await;
+                    Some(new_alias)
+                } else {
+                    Some(alias)
+                }
+            }
         };
 
         let path = self.input_buffers[INDEX_SOURCE_PATH].value().to_string();
         self.log_analyzer
             .add_log(self.source_type, &path, alias.as_ref())?;
+        self.remember_source(self.source_type, &path, alias.as_ref());
+
+        Ok(())
+    }
+
+    /// Add again a recently added source, defining its format if it is not available
+    pub async fn add_recent_log(&mut self, source: &RecentSource) -> Result<()> {
+        let alias = source.format.as_ref().map(|format| format.alias.clone());
+        if let Some(format) = &source.format {
+            if !self
+                .log_analyzer
+                .get_formats()
+                .iter()
+                .any(|available| available.alias == format.alias)
+            {
+                self.log_analyzer.add_format(&format.alias, &format.regex)?;
+                self.update_formats().await;
+            }
+        }
+
+        self.log_analyzer
+            .add_log(source.source_type, &source.address, alias.as_ref())?;
+        self.remember_source(source.source_type, &source.address, alias.as_ref());
 
         Ok(())
     }
 
+    /// Move the source to the top of the recent sources and save them
+    fn remember_source(&mut self, source_type: usize, address: &str, alias: Option<&String>) {
+        let format = alias.and_then(|alias| {
+            self.log_analyzer
+                .get_formats()
+                .into_iter()
+                .find(|format| &format.alias == alias)
+        });
+
+        let mut recent = RecentSources {
+            sources: std::mem::take(&mut self.recent_sources.items),
+        };
+        recent.add(RecentSource {
+            source_type,
+            address: address.to_string(),
+            format,
+        });
+        self.recent_sources = StatefulList::with_items(recent.sources);
+
+        if let Some(path) = &self.recent_sources_path {
+            if let Err(err) = save_recent_sources(path, &RecentSources {
+                sources: self.recent_sources.items.clone(),
+            }) {
+                self.notify(Notification::new(
+                    NotificationLevel::Warning,
+                    &format!("Unable to save the recent sources: {}", err),
+                ));
+            }
+        }
+    }
+
     pub async fn update_formats(&mut self) {
         let mut formats = vec!["New".to_string()];
         formats.extend(
@@ -338,64 +781,120 @@ impl App {
     }
 
     async fn pull_events(&mut self) {
+        // The lines received during the tick are handled once, whatever the number of events
+        let mut received = EventCoalescer::default();
         let mut events = Vec::new();
         while let Ok(event) = self.event_receiver.try_recv() {
-            events.push(event);
+            events.extend(received.add(event));
         }
+        let new_lines = received.new_lines().is_some();
+        let new_search_lines = received.new_search_lines().is_some();
 
         // Reload logs when some lines are received and there are no items displayed
-        if !self.processing.is_processing
-            && self.log_lines.items.len() < CAPACITY
-            && events.iter().any(|e| matches!(e, LogEvent::NewLines(_, _)))
-        {
+        if !self.processing.is_processing && self.log_lines.items.len() < CAPACITY && new_lines {
             self.log_lines.reload();
         }
 
         // Reload search logs when some search lines are received and there are no items displayed
         if !self.processing.is_processing
             && self.search_lines.items.len() < CAPACITY
-            && events
-                .iter()
-                .any(|e| matches!(e, LogEvent::NewSearchLines(_, _)))
+            && new_search_lines
         {
             self.search_lines.reload();
         }
 
+        for event in &events {
+            match event {
+                LogEvent::Metrics(metrics) => self.metrics = *metrics,
+                LogEvent::Burst(burst) => self.bursts.push(burst.clone()),
+                LogEvent::LinesDiscarded { filtered, .. } if *filtered > 0 => {
+                    self.shift_lines(*filtered)
+                }
+                // Anyone reaching the server can set alerts, only the bell is trusted from it.
+                // The others are still notified below
+                LogEvent::Alert { alert, .. }
+                    if self.is_remote && alert.action != AlertAction::Bell => {}
+                LogEvent::Alert {
+                    filter,
+                    alert,
+                    line,
+                } => {
+                    if let Err(e) = alerts::run_alert(alert, filter, line) {
+                        self.notify(Notification::new(NotificationLevel::Error, &e.to_string()));
+                    }
+                }
+                _ => {}
+            }
+            if let Some(notification) = event.notification() {
+                if let LogEvent::SourceError { .. } = event {
+                    self.show_runtime_error(&notification.message);
+                }
+                self.notify(notification);
+            }
+        }
+
+        // Refresh the grouped log as lines are processed
+        if self.group_by_source
+            && (new_lines
+                || events
+                    .iter()
+                    .any(|e| matches!(e, LogEvent::Filtering | LogEvent::FilterFinished)))
+        {
+            self.update_grouped_log();
+        }
+
         // Auto scroll
-        if self.auto_scroll && events.iter().any(|e| matches!(e, LogEvent::NewLines(_, _))) {
+        if self.auto_scroll && new_lines {
             self.log_lines.navigate_to_bottom();
         }
 
-        if self.auto_scroll
-            && events
-                .iter()
-                .any(|e| matches!(e, LogEvent::NewSearchLines(_, _)))
-        {
+        if self.auto_scroll && new_search_lines {
             self.search_lines.navigate_to_bottom();
         }
 
-        // Handle enter filtering
-        if events.iter().any(|e| matches!(e, LogEvent::Filtering)) {
-            self.processing.is_processing = true;
-
+        // Handle enter filtering. The view is not blocked, the previous lines are displayed until
+        // the filtered ones replace them
+        if events.iter().any(|e| matches!(e, LogEvent::Filtering)) && !self.processing.is_filtering
+        {
+            self.processing.is_filtering = true;
             self.processing.set_focus(
                 self.log_lines
                     .get_selected_item()
-                    .map(|l| l.index.parse().unwrap()),
+                    .map(|l| l.index),
             );
+        }
+
+        // Handle the preview of a refilter, the lines around the selected one being filtered first
+        if self.processing.is_filtering
+            && events.iter().any(|e| matches!(e, LogEvent::FilterPreview))
+        {
+            self.processing.previewed = true;
+            self.processing.set_focus(self.resolve_viewport());
             self.log_lines.clear();
             self.search_lines.clear();
+            self.log_lines.navigate_to(self.processing.focus_on);
         }
 
-        // Handle exit filtering
-        if self.processing.is_processing
+        // Handle exit filtering. Keep the focus on the line selected, if the user moved meanwhile
+        if self.processing.is_filtering
             && events.iter().any(|e| matches!(e, LogEvent::FilterFinished))
         {
+            if self.processing.previewed {
+                self.processing.set_focus(self.resolve_viewport());
+            } else if let Some(line) = self.log_lines.get_selected_item() {
+                self.processing.set_focus(Some(line.index));
+            }
+            self.log_lines.clear();
+            self.search_lines.clear();
             self.log_lines.navigate_to(self.processing.focus_on);
             self.search_lines.navigate_to(self.processing.focus_on);
 
-            self.processing.is_processing = false;
             self.processing = Processing::default();
+
+            // The markers follow the lines kept by the new filters
+            if self.gap_markers {
+                self.update_gaps();
+            }
         }
 
         // Handle enter searching
@@ -404,7 +903,7 @@ impl App {
             self.processing.set_focus(
                 self.search_lines
                     .get_selected_item()
-                    .map(|l| l.unformat().index.parse().unwrap()),
+                    .map(|l| l.unformat().index),
             );
             self.search_lines.clear();
         }
@@ -418,21 +917,571 @@ impl App {
         }
     }
 
+    /// Keep the lines displayed once the `discarded` oldest filtered lines are gone, the ones
+    /// left having moved back
+    fn shift_lines(&mut self, discarded: usize) {
+        match self.log_lines.get_selected_item() {
+            Some(line) => self.log_lines.navigate_to(line.index.saturating_sub(discarded)),
+            None => self.log_lines.reload(),
+        }
+        match self.search_lines.get_selected_item() {
+            Some(line) => {
+                let index = line.unformat().index.saturating_sub(discarded);
+                self.search_lines.navigate_to(index)
+            }
+            None => self.search_lines.reload(),
+        }
+    }
+
+    /// Position in the filtered log of the line last selected
+    fn resolve_viewport(&self) -> Option<usize> {
+        let (source, raw_index) = self.viewport.as_ref()?;
+        self.log_analyzer.resolve_raw_index(Some(source), *raw_index)
+    }
+
+    /// Tell the analyzer the line selected, filtered first by the refilters
+    fn update_viewport(&mut self) {
+        let viewport = match self.log_lines.get_selected_item() {
+            Some(line) => (line.log.to_string(), line.raw_index),
+            None => return,
+        };
+        if self.viewport.as_ref() != Some(&viewport) {
+            self.log_analyzer.set_viewport(&viewport.0, viewport.1);
+            self.viewport = Some(viewport);
+        }
+    }
+
     pub async fn on_tick(&mut self) {
         self.pull_events().await;
+        self.update_viewport();
+
+        if let Some(position) = self.pending_position {
+            if self.log_analyzer.get_total_filtered_lines() > position {
+                self.log_lines.navigate_to(position);
+                self.pending_position = None;
+            }
+        }
+
+        self.source_stats = self.log_analyzer.get_source_stats();
+        self.update_watches();
+
+        if self.last_autosave.elapsed() >= SESSION_AUTOSAVE_INTERVAL {
+            self.autosave();
+        }
+    }
+
+    /// Add the notification to the history, discarding the oldest one when full
+    pub fn notify(&mut self, notification: Notification) {
+        if self.notifications.items.len() >= MAX_NOTIFICATIONS {
+            self.notifications.items.remove(0);
+        }
+        self.notifications.items.push(notification);
+        if !self.show_notifications_popup {
+            self.unread_notifications += 1;
+        }
+    }
+
+    /// Display an error raised while running, e.g. by a source, unless a popup is open.
+    /// It is kept in the notifications history anyway
+    fn show_runtime_error(&mut self, message: &str) {
+        if matches!(
+            self.selected_module,
+            Module::Sources
+                | Module::Filters
+                | Module::Logs
+                | Module::PinnedLines
+                | Module::ColumnHeader
+                | Module::Search
+                | Module::SearchResult
+        ) {
+            self.popup.calling_module = self.selected_module;
+            self.popup.message = message.to_string();
+            self.selected_module = Module::ErrorPopup;
+            self.show_error_message = true;
+        }
+    }
+
+    /// Highest level among the unread notifications
+    pub fn get_unread_level(&self) -> Option<NotificationLevel> {
+        let unread = self.unread_notifications.min(self.notifications.items.len());
+        self.notifications.items[self.notifications.items.len() - unread..]
+            .iter()
+            .map(|notification| notification.level)
+            .max()
+    }
+
+    /// Show the lines as they were read or redacted again, when redactions are configured
+    pub fn toggle_originals(&mut self) {
+        if self.log_analyzer.get_redactions().is_empty() {
+            self.notify(Notification::new(
+                NotificationLevel::Warning,
+                "There are no redactions, add them with the `redactions` setting",
+            ));
+            return;
+        }
+        let show = !self.log_analyzer.is_showing_originals();
+        self.log_analyzer.set_show_originals(show);
+    }
+
+    /// Open or close the notifications history. Opening it marks every notification as read
+    pub fn toggle_notifications(&mut self) {
+        if self.show_notifications_popup {
+            self.show_notifications_popup = false;
+            self.selected_module = self.popup.calling_module;
+        } else if !matches!(
+            self.selected_module,
+            Module::RestorePopup | Module::ClearPopup | Module::ErrorPopup
+        ) {
+            self.show_notifications_popup = true;
+            self.unread_notifications = 0;
+            self.popup.calling_module = self.selected_module;
+            self.selected_module = Module::NotificationsPopup;
+            if !self.notifications.items.is_empty() {
+                self.notifications
+                    .state
+                    .select(Some(self.notifications.items.len() - 1));
+            }
+        }
+    }
+
+    /// Move the selection of the other panel to the line selected in `module`, or the closest one
+    fn sync_selection(&mut self, module: Module) {
+        if !self.sync_cursor {
+            return;
+        }
+        match module {
+            Module::Logs => {
+                if let Some(index) = self
+                    .log_lines
+                    .get_selected_item()
+                    .map(|line| line.index)
+                {
+                    self.search_lines.navigate_to(index);
+                }
+            }
+            Module::SearchResult => {
+                if let Some(index) = self
+                    .search_lines
+                    .get_selected_item()
+                    .map(|line| line.unformat().index)
+                {
+                    self.log_lines.navigate_to(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Apply the display options of the settings, falling back to the defaults for the missing ones
+    pub fn apply_settings(&mut self, settings: Settings) {
+        self.color = settings
+            .primary_color
+            .map(|(r, g, b)| self.color_support.rgb(r, g, b))
+            .unwrap_or(DEFAULT_PRIMARY_COLOR);
+        self.stripe_color = settings
+            .stripe_color
+            .map(|(r, g, b)| self.color_support.rgb(r, g, b));
+        self.memory_warning_mb = settings.memory_warning_mb.unwrap_or(DEFAULT_MEMORY_WARNING_MB);
+        self.memory_critical_mb = settings
+            .memory_critical_mb
+            .unwrap_or(DEFAULT_MEMORY_CRITICAL_MB);
+        self.tick_rate = Duration::from_millis(settings.tick_rate_ms.unwrap_or(DEFAULT_TICK_RATE_MS));
+        self.settings = settings;
+    }
+
+    /// Open the settings editor filled with the current options
+    pub fn open_settings_popup(&mut self) {
+        if !matches!(
+            self.selected_module,
+            Module::Sources
+                | Module::Filters
+                | Module::Logs
+                | Module::PinnedLines
+                | Module::Search
+                | Module::SearchResult
+        ) {
+            return;
+        }
+
+        let rgb = |color: Option<(u8, u8, u8)>| {
+            color
+                .map(|(r, g, b)| format!("{}, {}, {}", r, g, b))
+                .unwrap_or_default()
+        };
+        let number = |value: Option<String>| value.unwrap_or_default();
+        let values = [
+            rgb(self.settings.primary_color),
+            rgb(self.settings.stripe_color),
+            number(self.settings.tick_rate_ms.map(|v| v.to_string())),
+            number(self.settings.memory_warning_mb.map(|v| v.to_string())),
+            number(self.settings.memory_critical_mb.map(|v| v.to_string())),
+        ];
+        for (buffer, value) in self.input_buffers
+            [INDEX_SETTINGS_PRIMARY_COLOR..=INDEX_SETTINGS_MEMORY_CRITICAL]
+            .iter_mut()
+            .zip(values)
+        {
+            *buffer = Input::default().with_value(value);
+        }
+
+        self.show_settings_popup = true;
+        self.settings_error = None;
+        self.input_buffer_index = INDEX_SETTINGS_PRIMARY_COLOR;
+        self.popup.calling_module = self.selected_module;
+        self.selected_module = Module::SettingsPopup;
+    }
+
+    async fn handle_settings_popup_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => match self.read_settings_popup() {
+                Ok(settings) => {
+                    self.show_settings_popup = false;
+                    self.selected_module = self.popup.calling_module;
+                    self.apply_settings(settings);
+                    self.save_settings();
+                }
+                Err(err) => self.settings_error = Some(err.to_string()),
+            },
+            KeyCode::Esc => {
+                self.show_settings_popup = false;
+                self.selected_module = self.popup.calling_module;
+            }
+            KeyCode::Up => self.navigate(KeyCode::Up),
+            KeyCode::Down => self.navigate(KeyCode::Down),
+            _ => {
+                input_backend::to_input_request(Event::Key(key))
+                    .map(|req| self.input_buffers[self.input_buffer_index].handle(req));
+            }
+        }
+    }
+
+    /// Parse the settings popup. Empty fields fall back to the defaults
+    fn read_settings_popup(&self) -> Result<Settings> {
+        let value = |index: usize| self.input_buffers[index].value().trim();
+        let rgb = |index: usize, name: &str| match value(index) {
+            "" => Ok(None),
+            color => parse_rgb(color)
+                .map(Some)
+                .ok_or_else(|| anyhow!("{} must be three numbers from 0 to 255: r, g, b", name)),
+        };
+        let number = |index: usize, name: &str| match value(index) {
+            "" => Ok(None),
+            number => number
+                .parse::<usize>()
+                .map(Some)
+                .map_err(|_| anyhow!("{} must be a positive number", name)),
+        };
+
+        let settings = Settings {
+            primary_color: rgb(INDEX_SETTINGS_PRIMARY_COLOR, "Primary color")?,
+            stripe_color: rgb(INDEX_SETTINGS_STRIPE_COLOR, "Stripe color")?,
+            tick_rate_ms: number(INDEX_SETTINGS_TICK_RATE, "Tick rate")?.map(|v| v as u64),
+            memory_warning_mb: number(INDEX_SETTINGS_MEMORY_WARNING, "Memory warning")?,
+            memory_critical_mb: number(INDEX_SETTINGS_MEMORY_CRITICAL, "Memory critical")?,
+            palette: self.settings.palette.clone(),
+            burst_detection: self.settings.burst_detection,
+            correlation_key: self.settings.correlation_key.clone(),
+            watches: self.settings.watches.clone(),
+            retention: self.settings.retention,
+            overload_policy: self.settings.overload_policy,
+            pipeline: self.settings.pipeline,
+            max_line_length: self.settings.max_line_length,
+            invalid_utf8: self.settings.invalid_utf8,
+            file_retry: self.settings.file_retry,
+            dedupe: self.settings.dedupe,
+            plugins_dir: self.settings.plugins_dir.clone(),
+            ..Default::default()
+        };
+        if settings.tick_rate_ms == Some(0) {
+            return Err(anyhow!("Tick rate must be greater than 0"));
+        }
+        Ok(settings)
+    }
+
+    /// Write the display options back to the settings file, keeping its formats and filters
+    fn save_settings(&mut self) {
+        let path = match &self.settings_path {
+            Some(path) => path.clone(),
+            None => return,
+        };
+        let notification = match save_settings(&path, &self.settings) {
+            Ok(_) => Notification::new(
+                NotificationLevel::Info,
+                &format!("Settings saved to {}", path.display()),
+            ),
+            Err(err) => Notification::new(
+                NotificationLevel::Error,
+                &format!("Unable to save the settings: {}", err),
+            ),
+        };
+        self.notify(notification);
+    }
+
+    /// Open the popup asking for the file of the action, filled with the last file used for it
+    pub fn open_path_popup(&mut self, action: PathAction) {
+        if !matches!(
+            self.selected_module,
+            Module::Sources
+                | Module::Filters
+                | Module::Logs
+                | Module::PinnedLines
+                | Module::Search
+                | Module::SearchResult
+        ) {
+            return;
+        }
+
+        let path = match action {
+            PathAction::SaveConfiguration => self.settings_path.as_ref(),
+            PathAction::SaveSession | PathAction::OpenSession => self.session_file.as_ref(),
+            PathAction::ExportFiltered | PathAction::ExportSearch => self.export_file.as_ref(),
+        };
+        let path = path
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.input_buffers[INDEX_PATH_POPUP] = Input::default().with_value(path);
+
+        self.path_action = action;
+        self.show_path_popup = true;
+        self.path_popup_error = None;
+        self.input_buffer_index = INDEX_PATH_POPUP;
+        self.popup.calling_module = self.selected_module;
+        self.selected_module = Module::PathPopup;
+    }
+
+    /// Export the search results from the search panels, the filtered log otherwise
+    pub fn open_export_popup(&mut self) {
+        match self.selected_module {
+            Module::Search | Module::SearchResult => self.open_path_popup(PathAction::ExportSearch),
+            _ => self.open_path_popup(PathAction::ExportFiltered),
+        }
+    }
+
+    async fn handle_path_popup_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                let path = self.input_buffers[INDEX_PATH_POPUP].value().trim();
+                if path.is_empty() {
+                    self.path_popup_error = Some("The path can't be empty".to_string());
+                    return;
+                }
+                let path = PathBuf::from(path);
+                match self.run_path_action(path).await {
+                    Ok(_) => {
+                        self.show_path_popup = false;
+                        self.selected_module = self.popup.calling_module;
+                    }
+                    Err(err) => self.path_popup_error = Some(err.to_string()),
+                }
+            }
+            KeyCode::Esc => {
+                self.show_path_popup = false;
+                self.selected_module = self.popup.calling_module;
+            }
+            _ => {
+                input_backend::to_input_request(Event::Key(key))
+                    .map(|req| self.input_buffers[INDEX_PATH_POPUP].handle(req));
+            }
+        }
+    }
+
+    async fn run_path_action(&mut self, mut path: PathBuf) -> Result<()> {
+        let message = match self.path_action {
+            PathAction::SaveConfiguration => {
+                self.update_settings_watches();
+                save_configuration(&path, &self.settings, self.log_analyzer.get_session())
+                    .map_err(|err| anyhow!("Unable to save: {}", err))?;
+                let message = format!("Configuration saved to {}", path.display());
+                // The settings editor saves to the same file from now on
+                self.settings_path = Some(path);
+                message
+            }
+            PathAction::SaveSession if is_snapshot(&path) => {
+                let file = path.to_string_lossy();
+                let lines = self.log_analyzer.save_snapshot(&file, self.current_session())?;
+                let message = format!("Snapshot of {} lines saved to {}", lines, path.display());
+                self.session_file = Some(path);
+                message
+            }
+            PathAction::SaveSession => {
+                if path.extension().is_none() {
+                    path.set_extension(SESSION_EXTENSION);
+                }
+                self.current_session().save(&path)?;
+                let message = format!("Session saved to {}", path.display());
+                self.session_file = Some(path);
+                message
+            }
+            PathAction::OpenSession if is_snapshot(&path) => {
+                let session = match self.log_analyzer.load_snapshot(&path.to_string_lossy()) {
+                    Ok(session) => session,
+                    Err(err) => {
+                        // Part of the snapshot may be loaded already
+                        self.update_sources().await;
+                        self.update_filters().await;
+                        return Err(err.into());
+                    }
+                };
+                self.clear_view().await;
+                self.restore_view(session).await;
+                let message = format!("Snapshot opened from {}", path.display());
+                self.session_file = Some(path);
+                message
+            }
+            PathAction::OpenSession => {
+                let session = Session::load(&path)?;
+                self.restore_session(session).await;
+                let message = format!("Session opened from {}", path.display());
+                self.session_file = Some(path);
+                message
+            }
+            PathAction::ExportFiltered | PathAction::ExportSearch => {
+                let file = path.to_string_lossy();
+                let format = ExportFormat::from_path(&path);
+                let lines = match self.path_action {
+                    PathAction::ExportSearch => self.log_analyzer.export_search(&file, format)?,
+                    _ => self.log_analyzer.export_filtered(&file, format)?,
+                };
+                let message = format!("{} lines exported to {}", lines, path.display());
+                self.export_file = Some(path);
+                message
+            }
+        };
+        self.notify(Notification::new(NotificationLevel::Info, &message));
+        Ok(())
+    }
+
+    /// Ask the user whether to restore a previously saved session
+    pub fn offer_session_restore(&mut self, session: Session) {
+        self.pending_session = Some(session);
+        self.show_restore_popup = true;
+        self.popup.calling_module = self.selected_module;
+        self.selected_module = Module::RestorePopup;
+    }
+
+    /// Save the current session to the autosave file, if any.
+    /// Nothing is saved while the previous session is pending to be restored
+    pub fn autosave(&mut self) {
+        self.last_autosave = Instant::now();
+        if self.pending_session.is_some() {
+            return;
+        }
+
+        if let Some(path) = &self.session_path {
+            // Autosave is best effort, a failure must not interrupt the analysis
+            save_session(path, &self.current_session()).unwrap_or_default();
+        }
+    }
+
+    /// Write the settings shown, with the sources, formats and filters of the analysis, to the
+    /// `export_path` if any
+    pub fn export_settings(&mut self) -> Result<()> {
+        let path = match self.export_path.clone() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        self.update_settings_watches();
+        export_settings(&path, &self.settings, self.log_analyzer.get_session())
+            .map_err(|err| anyhow!("Unable to export the settings to {}: {}", path.display(), err))
+    }
+
+    /// Keep the watch expressions of the analysis in the settings to be saved
+    fn update_settings_watches(&mut self) {
+        let watches = self.log_analyzer.get_watches();
+        self.settings.watches = match watches.is_empty() {
+            true => None,
+            false => Some(watches.into_iter().map(|watch| watch.expression).collect()),
+        };
+    }
+
+    /// Snapshot of the analysis together with the pinned lines, position and columns of the view
+    fn current_session(&self) -> Session {
+        let mut session = self.log_analyzer.get_session();
+        session.pinned_lines = self.pinned_lines.items.clone();
+        session.log_position = self
+            .log_lines
+            .get_selected_item()
+            .map(|line| line.index);
+        session.columns = Some(ColumnLayout {
+            columns: self.log_columns.clone(),
+            frozen: self.frozen_columns.clone(),
+        });
+        session
+    }
+
+    async fn restore_session(&mut self, session: Session) {
+        if let Err(err) = self.log_analyzer.restore_session(&session) {
+            self.notify(Notification::new(
+                NotificationLevel::Error,
+                &format!("Session partially restored: {}", err),
+            ));
+        }
+        self.restore_view(session).await;
+    }
+
+    /// Restore the search input, pinned lines, position and columns of a session
+    async fn restore_view(&mut self, session: Session) {
+        if let Some(search) = &session.search {
+            self.input_buffers[INDEX_SEARCH] = Input::default().with_value(search.clone());
+        }
+        self.pinned_lines = StatefulTable::with_items(session.pinned_lines);
+        self.pending_position = session.log_position;
+        if let Some(layout) = session.columns {
+            // Columns unknown to this version are ignored
+            self.log_columns = LogLine::columns()
+                .into_iter()
+                .map(|column| {
+                    let hidden = layout
+                        .columns
+                        .iter()
+                        .any(|(name, enabled)| *name == column && !enabled);
+                    (column, !hidden)
+                })
+                .collect();
+            self.frozen_columns = layout.frozen;
+        }
+
+        self.update_sources().await;
+        self.update_filters().await;
     }
 
     pub async fn handle_input(&mut self, key: KeyEvent) {
         match self.selected_module {
             Module::Sources => self.handle_sources_input(key).await,
             Module::Filters => self.handle_filters_input(key).await,
-            Module::Logs => self.handle_log_input(key).await,
+            Module::Logs => {
+                let selected = self.log_lines.get_selected_item().map(|line| line.index);
+                self.handle_log_input(key).await;
+                if self.log_lines.get_selected_item().map(|line| line.index) != selected {
+                    self.sync_selection(Module::Logs);
+                }
+            }
+            Module::PinnedLines => self.handle_pinned_lines_input(key).await,
+            Module::ColumnHeader => self.handle_column_header_input(key).await,
             Module::Search => self.handle_search_input(key).await,
-            Module::SearchResult => self.handle_search_result_input(key).await,
+            Module::SearchResult => {
+                let selected = self.search_lines.get_selected_item().map(|line| line.index);
+                self.handle_search_result_input(key).await;
+                if self.search_lines.get_selected_item().map(|line| line.index) != selected {
+                    self.sync_selection(Module::SearchResult);
+                }
+            }
             Module::SourcePopup => self.handle_source_popup_input(key).await,
             Module::FilterPopup => self.handle_filter_popup_input(key).await,
             Module::NavigationPopup => self.handle_navigation_popup_input(key).await,
             Module::ErrorPopup => self.handle_error_popup_input(key).await,
+            Module::RestorePopup => self.handle_restore_popup_input(key).await,
+            Module::ClearPopup => self.handle_clear_popup_input(key).await,
+            Module::NotificationsPopup => self.handle_notifications_popup_input(key).await,
+            Module::SettingsPopup => self.handle_settings_popup_input(key).await,
+            Module::PathPopup => self.handle_path_popup_input(key).await,
+            Module::TopValuesPopup => self.handle_top_values_popup_input(key).await,
+            Module::GapsPopup => self.handle_gaps_popup_input(key).await,
+            Module::TransactionPopup => self.handle_transaction_popup_input(key).await,
+            Module::DiffPopup => self.handle_diff_popup_input(key).await,
+            Module::WatchesPopup => self.handle_watches_popup_input(key).await,
+            Module::LinePopup => self.handle_line_popup_input(key).await,
+            Module::ConsolePopup => self.handle_console_popup_input(key).await,
             _ => {}
         }
     }
@@ -477,7 +1526,13 @@ impl App {
             KeyCode::Char('i') | KeyCode::Char('+') | KeyCode::Char('a') => {
                 self.formats.state.select(Some(0));
                 self.show_source_popup = true;
-                self.input_buffer_index = INDEX_SOURCE_TYPE;
+                self.input_buffer_index = match self.recent_sources.items.is_empty() {
+                    true => INDEX_SOURCE_TYPE,
+                    false => {
+                        self.recent_sources.state.select(Some(0));
+                        INDEX_SOURCE_RECENT
+                    }
+                };
                 self.selected_module = Module::SourcePopup;
             }
             // Delete source
@@ -525,6 +1580,7 @@ impl App {
             // Add new filter -> Popup window
             KeyCode::Char('i') | KeyCode::Char('+') | KeyCode::Char('a') => {
                 self.show_filter_popup = true;
+                self.edited_filter = None;
                 self.input_buffer_index = INDEX_FILTER_NAME;
                 self.selected_module = Module::FilterPopup;
             }
@@ -536,6 +1592,7 @@ impl App {
 
                 if let Some(i) = self.filters.state.selected() {
                     let (_, alias) = &self.filters.items[i];
+                    self.edited_filter = Some(alias.clone());
                     if let Some((_, filter)) = self
                         .log_analyzer
                         .get_filters()
@@ -548,19 +1605,19 @@ impl App {
                         self.input_buffers[INDEX_FILTER_TYPE] =
                             Input::default().with_value("".into());
                         self.input_buffers[INDEX_FILTER_LOG] =
-                            Input::default().with_value(filter.filter.log);
+                            Input::default().with_value(filter.filter.log.to_string());
                         self.input_buffers[INDEX_FILTER_DATETIME] =
-                            Input::default().with_value(filter.filter.date);
+                            Input::default().with_value(filter.filter.date.to_string());
                         self.input_buffers[INDEX_FILTER_TIMESTAMP] =
-                            Input::default().with_value(filter.filter.timestamp);
+                            Input::default().with_value(filter.filter.timestamp.to_string());
                         self.input_buffers[INDEX_FILTER_APP] =
-                            Input::default().with_value(filter.filter.app);
+                            Input::default().with_value(filter.filter.app.to_string());
                         self.input_buffers[INDEX_FILTER_SEVERITY] =
-                            Input::default().with_value(filter.filter.severity);
+                            Input::default().with_value(filter.filter.severity.to_string());
                         self.input_buffers[INDEX_FILTER_FUNCTION] =
-                            Input::default().with_value(filter.filter.function);
+                            Input::default().with_value(filter.filter.function.to_string());
                         self.input_buffers[INDEX_FILTER_PAYLOAD] =
-                            Input::default().with_value(filter.filter.payload);
+                            Input::default().with_value(filter.filter.payload.to_string());
                         if let Some((r, g, b)) = filter.filter.color {
                             self.input_buffers[INDEX_FILTER_RED_COLOR] =
                                 Input::default().with_value(r.to_string());
@@ -572,36 +1629,228 @@ impl App {
                     }
                 }
             }
+            // Quick severity toggles
+            KeyCode::Char(c @ '1'..='4') => self.toggle_severity_key(c),
             // Delete filter
-            KeyCode::Char('-') | KeyCode::Char('d') | KeyCode::Delete => {}
+            KeyCode::Char('-') | KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some(i) = self.filters.state.selected() {
+                    let alias = self.filters.items[i].1.clone();
+                    if let Err(err) = self.log_analyzer.remove_filter(&alias) {
+                        self.notify(Notification::new(NotificationLevel::Error, &err.to_string()));
+                    }
+                    self.update_filters().await;
+                }
+            }
             // Nothing
             _ => {}
         }
     }
 
     async fn handle_log_input(&mut self, key: KeyEvent) {
-        self.handle_table_log_input(key).await;
-    }
-
-    async fn handle_search_result_input(&mut self, key: KeyEvent) {
-        self.handle_table_search_input(key).await;
+        match self.group_by_source {
+            true => self.handle_grouped_log_input(key).await,
+            false => self.handle_table_log_input(key).await,
+        }
     }
 
-    async fn handle_search_input(&mut self, key: KeyEvent) {
+    async fn handle_grouped_log_input(&mut self, key: KeyEvent) {
+        let steps = match key.code {
+            KeyCode::PageUp | KeyCode::PageDown => 100,
+            _ => 1,
+        };
         match key.code {
-            KeyCode::Enter => {
-                self.search_lines.clear();
-                self.log_analyzer
-                    .add_search(self.input_buffers[INDEX_SEARCH].value());
+            KeyCode::Up | KeyCode::PageUp => {
+                for _ in 0..steps {
+                    self.grouped_log.previous();
+                }
             }
-            _ => {
-                input_backend::to_input_request(Event::Key(key))
-                    .map(|req| self.input_buffers[INDEX_SEARCH].handle(req));
+            KeyCode::Down | KeyCode::PageDown => {
+                for _ in 0..steps {
+                    self.grouped_log.next();
+                }
             }
-        }
-    }
-
-    async fn handle_source_popup_input(&mut self, key: KeyEvent) {
+            KeyCode::Left => self.scroll_left(),
+            KeyCode::Right => self.scroll_right(),
+            // Expand or collapse a group, or open the line in the combined log
+            KeyCode::Enter => {
+                let selected = self
+                    .grouped_log
+                    .state
+                    .selected()
+                    .and_then(|i| self.grouped_log.items.get(i).cloned());
+                match selected {
+                    Some(GroupRow::Header(source, _, _)) => {
+                        match self.expanded_groups.iter().position(|group| *group == source) {
+                            Some(position) => {
+                                self.expanded_groups.remove(position);
+                            }
+                            None => self.expanded_groups.push(source),
+                        }
+                        self.update_grouped_log();
+                    }
+                    Some(GroupRow::Line(line)) => {
+                        self.group_by_source = false;
+                        self.log_lines.navigate_to(line.index);
+                    }
+                    _ => {}
+                }
+            }
+            KeyCode::Char('g') => self.group_by_source = false,
+            _ => {}
+        }
+    }
+
+    /// Rebuild the rows of the grouped log keeping the current selection
+    pub fn update_grouped_log(&mut self) {
+        let mut rows = Vec::new();
+        for (source, lines) in self.log_analyzer.get_filtered_lines_per_source() {
+            let expanded = self.expanded_groups.contains(&source);
+            rows.push(GroupRow::Header(source.clone(), lines, expanded));
+
+            if expanded {
+                rows.extend(
+                    self.log_analyzer
+                        .get_source_log_lines(&source, MAX_GROUP_LINES)
+                        .into_iter()
+                        .map(GroupRow::Line),
+                );
+                if lines > MAX_GROUP_LINES {
+                    rows.push(GroupRow::More(lines - MAX_GROUP_LINES));
+                }
+            }
+        }
+
+        let selected = self.grouped_log.state.selected();
+        let length = rows.len();
+        self.grouped_log = StatefulTable::with_items(rows);
+        self.grouped_log.state.select(match selected {
+            Some(i) if length > 0 => Some(i.min(length - 1)),
+            _ if length > 0 => Some(0),
+            _ => None,
+        });
+    }
+
+    async fn handle_search_result_input(&mut self, key: KeyEvent) {
+        self.handle_table_search_input(key).await;
+    }
+
+    async fn handle_pinned_lines_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Up => {
+                self.pinned_lines.previous();
+            }
+            KeyCode::Down => {
+                self.pinned_lines.next();
+            }
+            // Jump back to the pinned line in the log
+            KeyCode::Enter => {
+                if let Some(line) = self
+                    .pinned_lines
+                    .state
+                    .selected()
+                    .and_then(|i| self.pinned_lines.items.get(i))
+                {
+                    self.log_lines.navigate_to(line.index);
+                    self.selected_module = Module::Logs;
+                }
+            }
+            // Unpin
+            KeyCode::Char('-') | KeyCode::Char('d') | KeyCode::Delete | KeyCode::Char('P') => {
+                if let Some(line) = self
+                    .pinned_lines
+                    .state
+                    .selected()
+                    .and_then(|i| self.pinned_lines.items.get(i))
+                    .cloned()
+                {
+                    self.toggle_pinned_line(line);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Pin the line if not pinned yet or unpin it otherwise. Pinned lines are kept sorted by index
+    pub fn toggle_pinned_line(&mut self, line: LogLine) {
+        let items = &mut self.pinned_lines.items;
+        match items.iter().position(|pinned| pinned.index == line.index) {
+            Some(position) => {
+                items.remove(position);
+            }
+            None => {
+                items.push(line);
+                items.sort();
+            }
+        }
+
+        if items.is_empty() {
+            self.pinned_lines.unselect();
+            if self.selected_module == Module::PinnedLines {
+                self.selected_module = Module::Logs;
+            }
+        } else if let Some(selected) = self.pinned_lines.state.selected() {
+            self.pinned_lines
+                .state
+                .select(Some(selected.min(self.pinned_lines.items.len() - 1)));
+        }
+    }
+
+    async fn handle_column_header_input(&mut self, key: KeyEvent) {
+        let columns = self.get_displayed_columns();
+        let column = match columns.get(self.selected_header) {
+            Some((column, _)) => column.clone(),
+            None => {
+                self.selected_module = Module::Logs;
+                return;
+            }
+        };
+
+        match key.code {
+            KeyCode::Left => {
+                self.selected_header = self.selected_header.saturating_sub(1);
+            }
+            KeyCode::Right => {
+                self.selected_header = (self.selected_header + 1).min(columns.len() - 1);
+            }
+            // Create the filter or remove it if no value was typed
+            KeyCode::Enter => {
+                let value = self.input_buffers[INDEX_COLUMN_FILTER].value().to_string();
+                match value.is_empty() {
+                    true => self.log_analyzer.remove_column_filter(&column),
+                    false => self
+                        .log_analyzer
+                        .add_column_filter(ColumnFilter::new(&column, &value)),
+                }
+                self.input_buffers[INDEX_COLUMN_FILTER] = Input::default();
+                self.selected_module = Module::Logs;
+            }
+            KeyCode::Delete => self.log_analyzer.remove_column_filter(&column),
+            KeyCode::Esc => {
+                self.input_buffers[INDEX_COLUMN_FILTER] = Input::default();
+                self.selected_module = Module::Logs;
+            }
+            _ => {
+                input_backend::to_input_request(Event::Key(key))
+                    .map(|req| self.input_buffers[INDEX_COLUMN_FILTER].handle(req));
+            }
+        }
+    }
+
+    async fn handle_search_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                self.search_lines.clear();
+                self.log_analyzer
+                    .add_search(self.input_buffers[INDEX_SEARCH].value());
+            }
+            _ => {
+                input_backend::to_input_request(Event::Key(key))
+                    .map(|req| self.input_buffers[INDEX_SEARCH].handle(req));
+            }
+        }
+    }
+
+    async fn handle_source_popup_input(&mut self, key: KeyEvent) {
         let mut fill_format = |_: usize, current_format: &str| match current_format {
             "New" => {
                 self.input_buffers[INDEX_SOURCE_NEW_FORMAT_ALIAS] = Input::default();
@@ -625,6 +1874,7 @@ impl App {
         if key.code == KeyCode::Esc {
             self.show_source_popup = false;
             self.source_type = 0;
+            self.path_candidates = StatefulList::with_items(Vec::new());
             self.selected_module = Module::Sources;
             self.formats.state.select(Some(0));
             self.input_buffers[INDEX_SOURCE_TYPE..INDEX_SOURCE_NEW_FORMAT_REGEX]
@@ -634,58 +1884,164 @@ impl App {
         }
 
         match self.input_buffer_index {
-            INDEX_SOURCE_TYPE => {
-                // Switch between file and ws
-                if key.code == KeyCode::Right || key.code == KeyCode::Left {
-                    self.source_type = !self.source_type & 1;
+            INDEX_SOURCE_RECENT => match key.code {
+                KeyCode::Up => {
+                    self.recent_sources.previous();
+                }
+                KeyCode::Down => {
+                    self.recent_sources.next();
+                }
+                KeyCode::Enter => {
+                    if let Some(i) = self.recent_sources.state.selected() {
+                        let source = self.recent_sources.items[i].clone();
+                        match self.add_recent_log(&source).await {
+                            Ok(_) => self.close_source_popup().await,
+                            Err(err) => self.show_source_error(err),
+                        }
+                    }
+                }
+                _ => {}
+            },
+            INDEX_SOURCE_TYPE
+                // Switch between file, ws and plugin
+                if (key.code == KeyCode::Right || key.code == KeyCode::Left) => {
+                    let tabs = SOURCE_POPUP_TYPES.len();
+                    let tab = SOURCE_POPUP_TYPES
+                        .iter()
+                        .position(|source_type| *source_type == self.source_type)
+                        .unwrap_or_default();
+                    let tab = match key.code {
+                        KeyCode::Right => (tab + 1) % tabs,
+                        _ => (tab + tabs - 1) % tabs,
+                    };
+                    self.source_type = SOURCE_POPUP_TYPES[tab];
                 }
-            }
             INDEX_SOURCE_FORMAT => match key.code {
                 // Navigate up sources
-                KeyCode::Up => {
-                    if self.input_buffer_index == INDEX_SOURCE_FORMAT {
+                KeyCode::Up
+                    if self.input_buffer_index == INDEX_SOURCE_FORMAT => {
                         let i = self.formats.previous();
                         fill_format(i, self.formats.items[i].as_str());
                     }
-                }
                 // Navigate down sources
-                KeyCode::Down => {
-                    if self.input_buffer_index == INDEX_SOURCE_FORMAT {
+                KeyCode::Down
+                    if self.input_buffer_index == INDEX_SOURCE_FORMAT => {
                         let i = self.formats.next();
                         fill_format(i, self.formats.items[i].as_str());
                     }
+                // Remove the selected format, the sources using it are no longer formatted
+                KeyCode::Delete => {
+                    if let Some(i) = self.formats.state.selected().filter(|i| *i > 0) {
+                        let alias = self.formats.items[i].clone();
+                        match self.log_analyzer.remove_format(&alias) {
+                            Ok(_) => {
+                                self.update_formats().await;
+                                self.update_sources().await;
+                                self.input_buffers[INDEX_SOURCE_NEW_FORMAT_ALIAS] = Input::default();
+                                self.input_buffers[INDEX_SOURCE_NEW_FORMAT_REGEX] = Input::default();
+                            }
+                            Err(err) => self.show_source_error(err.into()),
+                        }
+                    }
                 }
                 _ => {}
             },
+            INDEX_SOURCE_PATH if !self.path_candidates.items.is_empty() => match key.code {
+                KeyCode::Up => {
+                    self.path_candidates.previous();
+                }
+                KeyCode::Down => {
+                    self.path_candidates.next();
+                }
+                KeyCode::Enter => {
+                    if let Some(i) = self.path_candidates.state.selected() {
+                        self.input_buffers[INDEX_SOURCE_PATH] =
+                            Input::default().with_value(self.path_candidates.items[i].clone());
+                    }
+                    self.path_candidates = StatefulList::with_items(Vec::new());
+                }
+                _ => {
+                    self.path_candidates = StatefulList::with_items(Vec::new());
+                    input_backend::to_input_request(Event::Key(key))
+                        .map(|req| self.input_buffers[INDEX_SOURCE_PATH].handle(req));
+                }
+            },
             index @ (INDEX_SOURCE_PATH
             | INDEX_SOURCE_NEW_FORMAT_ALIAS
             | INDEX_SOURCE_NEW_FORMAT_REGEX) => {
                 input_backend::to_input_request(Event::Key(key))
                     .map(|req| self.input_buffers[index].handle(req));
             }
-            INDEX_SOURCE_OK_BUTTON => {
-                if key.code == KeyCode::Enter {
+            INDEX_SOURCE_OK_BUTTON
+                if key.code == KeyCode::Enter => {
                     match self.add_log().await {
-                        Ok(_) => {
-                            self.show_source_popup = false;
-                            self.source_type = 0;
-                            self.selected_module = Module::Sources;
-                            self.update_sources().await;
-                            self.input_buffers[INDEX_SOURCE_TYPE..INDEX_SOURCE_NEW_FORMAT_REGEX]
-                                .iter_mut()
-                                .for_each(|b| *b = Input::default().with_value("".into()));
-                        }
-                        Err(err) => {
-                            self.selected_module = Module::ErrorPopup;
-                            self.show_error_message = true;
-                            self.popup.message = format!("{:?}", err);
-                            self.popup.calling_module = Module::SourcePopup;
-                        }
+                        Ok(_) => self.close_source_popup().await,
+                        Err(err) => self.show_source_error(err),
                     }
                 }
+            _ => {}
+        }
+    }
+
+    async fn close_source_popup(&mut self) {
+        self.show_source_popup = false;
+        self.source_type = 0;
+        self.path_candidates = StatefulList::with_items(Vec::new());
+        self.selected_module = Module::Sources;
+        self.update_sources().await;
+        self.input_buffers[INDEX_SOURCE_TYPE..INDEX_SOURCE_NEW_FORMAT_REGEX]
+            .iter_mut()
+            .for_each(|b| *b = Input::default().with_value("".into()));
+    }
+
+    fn show_source_error(&mut self, err: anyhow::Error) {
+        // Focus the input to correct once the error is dismissed
+        match err.downcast_ref::<LogAnalyzerError>() {
+            Some(LogAnalyzerError::EmptyAlias | LogAnalyzerError::FormatAlreadyExists(_)) => {
+                self.input_buffer_index = INDEX_SOURCE_NEW_FORMAT_ALIAS
+            }
+            Some(LogAnalyzerError::EmptyRegex | LogAnalyzerError::InvalidRegex { .. }) => {
+                self.input_buffer_index = INDEX_SOURCE_NEW_FORMAT_REGEX
+            }
+            Some(LogAnalyzerError::SourceUnavailable { .. }) => {
+                self.input_buffer_index = INDEX_SOURCE_PATH
             }
             _ => {}
         }
+        self.notify(Notification::new(
+            NotificationLevel::Error,
+            &err.to_string(),
+        ));
+        self.selected_module = Module::ErrorPopup;
+        self.show_error_message = true;
+        self.popup.message = format!("{:?}", err);
+        self.popup.calling_module = Module::SourcePopup;
+    }
+
+    /// Complete the path of a file source with the matching filesystem entries, listing them
+    /// when there is more than one. Returns false if there was nothing to complete
+    pub fn complete_source_path(&mut self) -> bool {
+        if self.selected_module != Module::SourcePopup
+            || self.input_buffer_index != INDEX_SOURCE_PATH
+            || self.source_type != 0
+        {
+            return false;
+        }
+
+        let input = self.input_buffers[INDEX_SOURCE_PATH].value().to_string();
+        let candidates = path_completion::get_candidates(&input);
+        let completed = path_completion::complete(&input, &candidates);
+        let changed = completed != input;
+        if changed {
+            self.input_buffers[INDEX_SOURCE_PATH] = Input::default().with_value(completed);
+        }
+
+        let listed = candidates.len() > 1 && !changed;
+        self.path_candidates = match listed {
+            true => StatefulList::with_items(candidates),
+            false => StatefulList::with_items(Vec::new()),
+        };
+        changed || listed
     }
 
     async fn handle_filter_popup_input(&mut self, key: KeyEvent) {
@@ -694,6 +2050,7 @@ impl App {
             self.show_filter_popup = false;
             self.selected_module = Module::Filters;
             self.filter_type = 0;
+            self.edited_filter = None;
             self.input_buffers[INDEX_FILTER_NAME..INDEX_FILTER_BLUE_COLOR]
                 .iter_mut()
                 .for_each(|b| *b = Input::default().with_value("".into()));
@@ -715,11 +2072,11 @@ impl App {
                 input_backend::to_input_request(Event::Key(key))
                     .map(|req| self.input_buffers[index].handle(req));
             }
-            INDEX_FILTER_TYPE => {
+            INDEX_FILTER_TYPE
                 // Switch tabs
-                if key.code == KeyCode::Right || key.code == KeyCode::Left {
+                if (key.code == KeyCode::Right || key.code == KeyCode::Left) => {
                     let circular_choice = |i: &mut usize, max, add: i32| {
-                        *i = match (*i as i32 + add) as i32 {
+                        *i = match *i as i32 + add {
                             r if r > max => 0_usize,    // if adding overflows -> set to 0
                             r if r < 0 => max as usize, // if adding underflows -> set to 0
                             r => r as usize,
@@ -731,29 +2088,20 @@ impl App {
                         circular_choice(&mut self.filter_type, 2, sum)
                     }
                 }
-            }
 
-            INDEX_FILTER_OK_BUTTON => {
-                if key.code == KeyCode::Enter {
+            INDEX_FILTER_OK_BUTTON
+                if key.code == KeyCode::Enter => {
                     let filter = Filter {
                         alias: self.input_buffers[INDEX_FILTER_NAME].value().to_string(),
                         action: FilterAction::from(self.filter_type),
                         filter: LogLine {
-                            log: self.input_buffers[INDEX_FILTER_LOG].value().to_string(),
-                            date: self.input_buffers[INDEX_FILTER_DATETIME]
-                                .value()
-                                .to_string(),
-                            timestamp: self.input_buffers[INDEX_FILTER_TIMESTAMP]
-                                .value()
-                                .to_string(),
-                            app: self.input_buffers[INDEX_FILTER_APP].value().to_string(),
-                            severity: self.input_buffers[INDEX_FILTER_SEVERITY]
-                                .value()
-                                .to_string(),
-                            function: self.input_buffers[INDEX_FILTER_FUNCTION]
-                                .value()
-                                .to_string(),
-                            payload: self.input_buffers[INDEX_FILTER_PAYLOAD].value().to_string(),
+                            log: self.input_buffers[INDEX_FILTER_LOG].value().into(),
+                            date: self.input_buffers[INDEX_FILTER_DATETIME].value().into(),
+                            timestamp: self.input_buffers[INDEX_FILTER_TIMESTAMP].value().into(),
+                            app: self.input_buffers[INDEX_FILTER_APP].value().into(),
+                            severity: self.input_buffers[INDEX_FILTER_SEVERITY].value().into(),
+                            function: self.input_buffers[INDEX_FILTER_FUNCTION].value().into(),
+                            payload: self.input_buffers[INDEX_FILTER_PAYLOAD].value().into(),
                             color: parse_color(
                                 self.input_buffers[INDEX_FILTER_RED_COLOR].value(),
                                 self.input_buffers[INDEX_FILTER_GREEN_COLOR].value(),
@@ -762,6 +2110,15 @@ impl App {
                             ..Default::default()
                         },
                     };
+                    // Renaming the edited filter keeps its enabled state
+                    if let Some(edited) = self.edited_filter.take() {
+                        if edited != filter.alias {
+                            if let Err(err) = self.log_analyzer.rename_filter(&edited, &filter.alias) {
+                                self.notify(Notification::new(NotificationLevel::Error, &err.to_string()));
+                                return;
+                            }
+                        }
+                    }
                     self.log_analyzer.add_filter(filter);
                     self.show_filter_popup = false;
                     self.selected_module = Module::Filters;
@@ -771,7 +2128,6 @@ impl App {
                         .iter_mut()
                         .for_each(|b| *b = Input::default().with_value("".into()));
                 }
-            }
             _ => {}
         }
     }
@@ -789,6 +2145,22 @@ impl App {
                         self.input_buffers[INDEX_NAVIGATION] =
                             Input::default().with_value("".into());
 
+                        let index = match self.navigation_mode {
+                            NavigationMode::Filtered => index,
+                            NavigationMode::Raw => {
+                                match self.log_analyzer.resolve_raw_index(None, index) {
+                                    Some(index) => index,
+                                    None => {
+                                        self.selected_module = Module::ErrorPopup;
+                                        self.show_error_message = true;
+                                        self.popup.message =
+                                            format!("No line found for raw line {}", index);
+                                        return;
+                                    }
+                                }
+                            }
+                        };
+
                         match self.selected_module {
                             Module::Logs => {
                                 self.log_lines.navigate_to(index);
@@ -811,6 +2183,7 @@ impl App {
                 self.selected_module = self.popup.calling_module;
                 self.input_buffers[INDEX_NAVIGATION] = Input::default().with_value("".into());
             }
+            KeyCode::Up | KeyCode::Down => self.navigation_mode = self.navigation_mode.toggle(),
             _ => {
                 input_backend::to_input_request(Event::Key(key))
                     .map(|req| self.input_buffers[INDEX_NAVIGATION].handle(req));
@@ -829,6 +2202,632 @@ impl App {
         }
     }
 
+    async fn handle_notifications_popup_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Up => {
+                self.notifications.previous();
+            }
+            KeyCode::Down => {
+                self.notifications.next();
+            }
+            // Clear the history
+            KeyCode::Char('c') => self.notifications = StatefulTable::with_items(Vec::new()),
+            KeyCode::Esc => self.toggle_notifications(),
+            _ => {}
+        }
+    }
+
+    async fn handle_restore_popup_input(&mut self, key: KeyEvent) {
+        let restore = match key.code {
+            KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => true,
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => false,
+            _ => return,
+        };
+
+        self.show_restore_popup = false;
+        self.selected_module = self.popup.calling_module;
+        if let Some(session) = self.pending_session.take() {
+            if restore {
+                self.restore_session(session).await;
+            }
+        }
+    }
+
+    /// Open the most frequent values of a column, counting the functions of the filtered log at first
+    pub fn open_top_values_popup(&mut self) {
+        if !matches!(
+            self.selected_module,
+            Module::Sources
+                | Module::Filters
+                | Module::Logs
+                | Module::PinnedLines
+                | Module::Search
+                | Module::SearchResult
+        ) {
+            return;
+        }
+
+        if self.input_buffers[INDEX_TOP_VALUES_LIMIT].value().is_empty() {
+            self.top_values_column = LogLine::columns()
+                .iter()
+                .position(|column| column == "Function")
+                .unwrap_or_default();
+            self.input_buffers[INDEX_TOP_VALUES_LIMIT] = Input::default().with_value("10".into());
+        }
+        self.show_top_values_popup = true;
+        self.input_buffer_index = INDEX_TOP_VALUES_COLUMN;
+        self.popup.calling_module = self.selected_module;
+        self.selected_module = Module::TopValuesPopup;
+        self.update_top_values();
+    }
+
+    async fn handle_top_values_popup_input(&mut self, key: KeyEvent) {
+        let columns = LogLine::columns().len();
+        match (self.input_buffer_index, key.code) {
+            (_, KeyCode::Esc) => {
+                self.show_top_values_popup = false;
+                self.selected_module = self.popup.calling_module;
+                return;
+            }
+            (_, KeyCode::Up) => self.navigate(KeyCode::Up),
+            (_, KeyCode::Down) => self.navigate(KeyCode::Down),
+            (_, KeyCode::Enter) => {}
+            (INDEX_TOP_VALUES_COLUMN, KeyCode::Left) => {
+                self.top_values_column = (self.top_values_column + columns - 1) % columns
+            }
+            (INDEX_TOP_VALUES_COLUMN, KeyCode::Right) => {
+                self.top_values_column = (self.top_values_column + 1) % columns
+            }
+            (INDEX_TOP_VALUES_SCOPE, KeyCode::Left | KeyCode::Right) => {
+                self.top_values_search = !self.top_values_search
+            }
+            (INDEX_TOP_VALUES_RANGE, KeyCode::Left | KeyCode::Right) => {
+                self.top_values_between_marks = !self.top_values_between_marks
+            }
+            (INDEX_TOP_VALUES_LIMIT, _) => {
+                input_backend::to_input_request(Event::Key(key))
+                    .map(|req| self.input_buffers[INDEX_TOP_VALUES_LIMIT].handle(req));
+            }
+            _ => return,
+        }
+        self.update_top_values();
+    }
+
+    /// Count the top values with the options of the popup
+    fn update_top_values(&mut self) {
+        let query = self.read_top_values_popup();
+        match query.and_then(|query| Ok(self.log_analyzer.get_top_values(&query)?)) {
+            Ok(values) => {
+                self.top_values = values;
+                self.top_values_error = None;
+            }
+            Err(err) => {
+                self.top_values.clear();
+                self.top_values_error = Some(err.to_string());
+            }
+        }
+    }
+
+    fn read_top_values_popup(&self) -> Result<TopValuesQuery> {
+        let top = match self.input_buffers[INDEX_TOP_VALUES_LIMIT].value().trim().parse::<usize>() {
+            Ok(top) if top > 0 => top,
+            _ => return Err(anyhow!("Top must be a number greater than 0")),
+        };
+        let time_range = match self.top_values_between_marks {
+            true => Some(self.get_marked_range().ok_or_else(|| {
+                anyhow!("Mark a start and an end line with a time first: Shift + T")
+            })?),
+            false => None,
+        };
+
+        Ok(TopValuesQuery {
+            column: LogLine::columns()[self.top_values_column].clone(),
+            top,
+            time_range,
+            search: self.top_values_search,
+        })
+    }
+
+    /// Open the gaps between the lines of the sources, longer than a minute at first
+    pub fn open_gaps_popup(&mut self) {
+        if !matches!(
+            self.selected_module,
+            Module::Sources
+                | Module::Filters
+                | Module::Logs
+                | Module::PinnedLines
+                | Module::Search
+                | Module::SearchResult
+        ) {
+            return;
+        }
+
+        if self.input_buffers[INDEX_GAPS_MIN].value().is_empty() {
+            self.input_buffers[INDEX_GAPS_MIN] = Input::default().with_value("60".into());
+        }
+        self.show_gaps_popup = true;
+        self.input_buffer_index = INDEX_GAPS_MIN;
+        self.popup.calling_module = self.selected_module;
+        self.selected_module = Module::GapsPopup;
+        self.update_gaps();
+    }
+
+    async fn handle_gaps_popup_input(&mut self, key: KeyEvent) {
+        match (self.input_buffer_index, key.code) {
+            (_, KeyCode::Esc) => {
+                self.show_gaps_popup = false;
+                self.selected_module = self.popup.calling_module;
+            }
+            (_, KeyCode::Up) => {
+                self.gaps.previous();
+            }
+            (_, KeyCode::Down) => {
+                self.gaps.next();
+            }
+            // Jump to the line after the selected gap
+            (_, KeyCode::Enter) => {
+                if let Some(gap) = self.gaps.state.selected().and_then(|i| self.gaps.items.get(i)) {
+                    let after = gap.after;
+                    self.show_gaps_popup = false;
+                    self.selected_module = Module::Logs;
+                    self.log_lines.navigate_to(after);
+                }
+            }
+            (INDEX_GAPS_MARKERS, KeyCode::Left | KeyCode::Right) => {
+                self.gap_markers = !self.gap_markers
+            }
+            (INDEX_GAPS_MIN, _) => {
+                input_backend::to_input_request(Event::Key(key))
+                    .map(|req| self.input_buffers[INDEX_GAPS_MIN].handle(req));
+                self.update_gaps();
+            }
+            _ => {}
+        }
+    }
+
+    /// Find the gaps longer than the minimum of the gaps popup
+    fn update_gaps(&mut self) {
+        let min_gap = self.input_buffers[INDEX_GAPS_MIN].value().trim().parse::<f64>();
+        match min_gap {
+            Ok(seconds) if seconds.is_finite() && seconds >= 0.0 => {
+                let gaps = self.log_analyzer.get_gaps(Duration::from_secs_f64(seconds));
+                self.gaps = StatefulTable::with_items(gaps);
+                if !self.gaps.items.is_empty() {
+                    self.gaps.state.select(Some(0));
+                }
+                self.gaps_error = None;
+            }
+            _ => self.gaps_error = Some("The minimum gap must be a positive number of seconds".into()),
+        }
+    }
+
+    /// The line is the last one of its source before a gap, marked in the log if enabled
+    pub fn is_before_gap(&self, index: usize) -> bool {
+        self.gap_markers && self.gaps.items.iter().any(|gap| gap.before == index)
+    }
+
+    /// Open every line sharing the correlation id of `line`, e.g. all the lines of a request
+    fn open_transaction_popup(&mut self, line: &LogLine) {
+        let id = self.log_analyzer.get_correlation_id(line.index);
+        let id = match id {
+            Some(id) => id,
+            None => {
+                self.notify(Notification::new(
+                    NotificationLevel::Warning,
+                    &format!(
+                        "No correlation id found in line {}, check the correlation_key setting",
+                        line.index
+                    ),
+                ));
+                return;
+            }
+        };
+
+        let lines = self.log_analyzer.get_correlated_lines(&id);
+        let selected = lines.iter().position(|l| l.index == line.index);
+        self.transaction_lines = StatefulTable::with_items(lines);
+        self.transaction_lines.state.select(selected.or(Some(0)));
+        self.transaction_id = id;
+        self.show_transaction_popup = true;
+        self.popup.calling_module = self.selected_module;
+        self.selected_module = Module::TransactionPopup;
+    }
+
+    /// Open the whole raw line of `line`, which may have been cut to `max_line_length`
+    fn open_line_popup(&mut self, line: &LogLine) {
+        let raw = match self.log_analyzer.get_raw_line(&line.log, line.raw_index) {
+            Some(raw) => raw,
+            None => {
+                self.notify(Notification::new(
+                    NotificationLevel::Warning,
+                    &format!("The raw line of line {} is no longer available", line.index),
+                ));
+                return;
+            }
+        };
+
+        self.raw_line_title = format!("{} - raw line {}", line.log, line.raw_index);
+        self.raw_line_chars = raw.chars().count();
+        self.raw_line = raw;
+        self.raw_line_scroll = 0;
+        self.show_line_popup = true;
+        self.popup.calling_module = self.selected_module;
+        self.selected_module = Module::LinePopup;
+    }
+
+    async fn handle_line_popup_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.show_line_popup = false;
+                self.raw_line = String::new();
+                self.selected_module = self.popup.calling_module;
+            }
+            // The scroll is limited to the last row when drawing
+            KeyCode::Up => self.raw_line_scroll = self.raw_line_scroll.saturating_sub(1),
+            KeyCode::Down => self.raw_line_scroll += 1,
+            KeyCode::PageUp => self.raw_line_scroll = self.raw_line_scroll.saturating_sub(20),
+            KeyCode::PageDown => self.raw_line_scroll += 20,
+            _ => {}
+        }
+    }
+
+    async fn handle_transaction_popup_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.show_transaction_popup = false;
+                self.selected_module = self.popup.calling_module;
+            }
+            KeyCode::Up => {
+                self.transaction_lines.previous();
+            }
+            KeyCode::Down => {
+                self.transaction_lines.next();
+            }
+            // Go to the selected line in the log
+            KeyCode::Enter => {
+                if let Some(index) = self
+                    .transaction_lines
+                    .state
+                    .selected()
+                    .and_then(|i| self.transaction_lines.items.get(i))
+                    .map(|line| line.index)
+                {
+                    self.show_transaction_popup = false;
+                    self.selected_module = Module::Logs;
+                    self.log_lines.navigate_to(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Open the comparison of two sources or two time ranges, the first two sources at first
+    pub fn open_diff_popup(&mut self) {
+        if !matches!(
+            self.selected_module,
+            Module::Sources
+                | Module::Filters
+                | Module::Logs
+                | Module::PinnedLines
+                | Module::Search
+                | Module::SearchResult
+        ) {
+            return;
+        }
+
+        let sources = self.sources.items.len();
+        if self.diff_sources.iter().any(|source| *source > sources) {
+            self.diff_sources = [0, 0];
+        }
+        if self.diff_sources == [0, 0] && sources > 1 {
+            self.diff_sources = [1, 2];
+        }
+        self.show_diff_popup = true;
+        self.input_buffer_index = INDEX_DIFF_LEFT_SOURCE;
+        self.popup.calling_module = self.selected_module;
+        self.selected_module = Module::DiffPopup;
+        self.update_diff();
+    }
+
+    async fn handle_diff_popup_input(&mut self, key: KeyEvent) {
+        let choices = self.sources.items.len() + 1;
+        let side = match self.input_buffer_index {
+            INDEX_DIFF_LEFT_SOURCE | INDEX_DIFF_LEFT_RANGE => 0,
+            _ => 1,
+        };
+        match (self.input_buffer_index, key.code) {
+            (_, KeyCode::Esc) => {
+                self.show_diff_popup = false;
+                self.selected_module = self.popup.calling_module;
+                return;
+            }
+            (_, KeyCode::Up) => {
+                self.diff_lines.previous();
+                return;
+            }
+            (_, KeyCode::Down) => {
+                self.diff_lines.next();
+                return;
+            }
+            // Go to the selected line in the log
+            (_, KeyCode::Enter) => {
+                if let Some(index) = self
+                    .diff_lines
+                    .state
+                    .selected()
+                    .and_then(|i| self.diff_lines.items.get(i))
+                    .map(|(_, line)| line.index)
+                {
+                    self.show_diff_popup = false;
+                    self.selected_module = Module::Logs;
+                    self.log_lines.navigate_to(index);
+                }
+                return;
+            }
+            (INDEX_DIFF_LEFT_SOURCE | INDEX_DIFF_RIGHT_SOURCE, KeyCode::Left) => {
+                self.diff_sources[side] = (self.diff_sources[side] + choices - 1) % choices
+            }
+            (INDEX_DIFF_LEFT_SOURCE | INDEX_DIFF_RIGHT_SOURCE, KeyCode::Right) => {
+                self.diff_sources[side] = (self.diff_sources[side] + 1) % choices
+            }
+            // Switch between every line and the lines between the current time marks
+            (INDEX_DIFF_LEFT_RANGE | INDEX_DIFF_RIGHT_RANGE, KeyCode::Left | KeyCode::Right) => {
+                self.diff_ranges[side] = match self.diff_ranges[side] {
+                    Some(_) => None,
+                    None => match self.get_marked_range() {
+                        Some(range) => Some(range),
+                        None => {
+                            self.diff_error = Some(
+                                "Mark a start and an end line with a time first: Shift + T".into(),
+                            );
+                            return;
+                        }
+                    },
+                }
+            }
+            _ => return,
+        }
+        self.update_diff();
+    }
+
+    /// Source of a side of the diff, every source if not set
+    pub fn get_diff_source(&self, side: usize) -> Option<&String> {
+        match self.diff_sources[side] {
+            0 => None,
+            source => self.sources.items.get(source - 1).map(|(_, path, _)| path),
+        }
+    }
+
+    /// Compare the sides of the diff popup
+    fn update_diff(&mut self) {
+        let side = |side: usize| DiffSide {
+            source: self.get_diff_source(side).cloned(),
+            time_range: self.diff_ranges[side],
+        };
+        let query = DiffQuery {
+            left: side(0),
+            right: side(1),
+        };
+        if query.left == query.right {
+            self.diff_lines = StatefulTable::with_items(Vec::new());
+            self.diff_common = 0;
+            self.diff_error = Some("Both sides are the same, change the source or the time".into());
+            return;
+        }
+
+        let diff = self.log_analyzer.get_diff(&query);
+        let mut lines: Vec<(char, LogLine)> = diff
+            .left
+            .into_iter()
+            .map(|line| ('-', line))
+            .chain(diff.right.into_iter().map(|line| ('+', line)))
+            .collect();
+        lines.sort_by_key(|(_, line)| line.index);
+
+        self.diff_lines = StatefulTable::with_items(lines);
+        if !self.diff_lines.items.is_empty() {
+            self.diff_lines.state.select(Some(0));
+        }
+        self.diff_common = diff.common;
+        self.diff_error = None;
+    }
+
+    /// Open the watch expressions counting the live lines they match
+    pub fn open_watches_popup(&mut self) {
+        if !matches!(
+            self.selected_module,
+            Module::Sources
+                | Module::Filters
+                | Module::Logs
+                | Module::PinnedLines
+                | Module::Search
+                | Module::SearchResult
+        ) {
+            return;
+        }
+
+        self.show_watches_popup = true;
+        self.input_buffer_index = INDEX_WATCH_EXPRESSION;
+        self.popup.calling_module = self.selected_module;
+        self.selected_module = Module::WatchesPopup;
+        self.watches_error = None;
+        self.update_watches();
+    }
+
+    async fn handle_watches_popup_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.show_watches_popup = false;
+                self.selected_module = self.popup.calling_module;
+            }
+            KeyCode::Up => {
+                self.watches.previous();
+            }
+            KeyCode::Down => {
+                self.watches.next();
+            }
+            KeyCode::Enter => {
+                let expression = self.input_buffers[INDEX_WATCH_EXPRESSION].value().trim().to_string();
+                if expression.is_empty() {
+                    return;
+                }
+                match self.log_analyzer.add_watch(&expression) {
+                    Ok(_) => {
+                        self.input_buffers[INDEX_WATCH_EXPRESSION] = Input::default();
+                        self.watches_error = None;
+                        self.update_watches();
+                        self.watches.state.select(Some(self.watches.items.len().saturating_sub(1)));
+                    }
+                    Err(err) => self.watches_error = Some(err.to_string()),
+                }
+            }
+            // Remove the selected watch
+            KeyCode::Delete => {
+                let selected = self.watches.state.selected();
+                if let Some(watch) = selected.and_then(|i| self.watches.items.get(i)) {
+                    if let Err(err) = self.log_analyzer.remove_watch(&watch.expression) {
+                        self.watches_error = Some(err.to_string());
+                    }
+                    self.update_watches();
+                }
+            }
+            _ => {
+                input_backend::to_input_request(Event::Key(key))
+                    .map(|req| self.input_buffers[INDEX_WATCH_EXPRESSION].handle(req));
+            }
+        }
+    }
+
+    /// Open the console running scripts over the analysis
+    pub fn open_console_popup(&mut self) {
+        if !matches!(
+            self.selected_module,
+            Module::Sources
+                | Module::Filters
+                | Module::Logs
+                | Module::PinnedLines
+                | Module::Search
+                | Module::SearchResult
+        ) {
+            return;
+        }
+
+        self.show_console_popup = true;
+        self.input_buffer_index = INDEX_CONSOLE_SCRIPT;
+        self.popup.calling_module = self.selected_module;
+        self.selected_module = Module::ConsolePopup;
+        self.console_scroll = 0;
+    }
+
+    async fn handle_console_popup_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.show_console_popup = false;
+                self.selected_module = self.popup.calling_module;
+            }
+            KeyCode::Enter => {
+                let script = self.input_buffers[INDEX_CONSOLE_SCRIPT].value().trim().to_string();
+                if script.is_empty() {
+                    return;
+                }
+                self.console.run(&script);
+                self.input_buffers[INDEX_CONSOLE_SCRIPT] = Input::default();
+                self.console_scroll = 0;
+                // The scripts may have changed the filters or the search
+                self.update_filters().await;
+            }
+            KeyCode::Up => {
+                if let Some(script) = self.console.previous_script() {
+                    self.input_buffers[INDEX_CONSOLE_SCRIPT] =
+                        Input::default().with_value(script.to_string());
+                }
+            }
+            KeyCode::Down => {
+                let script = self.console.next_script().unwrap_or_default().to_string();
+                self.input_buffers[INDEX_CONSOLE_SCRIPT] = Input::default().with_value(script);
+            }
+            // The scroll is limited to the first row when drawing
+            KeyCode::PageUp => self.console_scroll += 10,
+            KeyCode::PageDown => self.console_scroll = self.console_scroll.saturating_sub(10),
+            _ => {
+                input_backend::to_input_request(Event::Key(key))
+                    .map(|req| self.input_buffers[INDEX_CONSOLE_SCRIPT].handle(req));
+            }
+        }
+    }
+
+    /// Get the latest counts of the watches, keeping the selected one if it still exists
+    fn update_watches(&mut self) {
+        let watches = self.log_analyzer.get_watches();
+        let selected = self
+            .watches
+            .state
+            .selected()
+            .filter(|_| !watches.is_empty())
+            .map(|i| i.min(watches.len() - 1));
+        self.watches.items = watches;
+        self.watches.state.select(selected);
+    }
+
+    /// Ask the user to confirm clearing the whole analysis
+    pub fn open_clear_popup(&mut self) {
+        if !matches!(
+            self.selected_module,
+            Module::Sources
+                | Module::Filters
+                | Module::Logs
+                | Module::PinnedLines
+                | Module::Search
+                | Module::SearchResult
+        ) {
+            return;
+        }
+
+        self.show_clear_popup = true;
+        self.popup.calling_module = self.selected_module;
+        self.selected_module = Module::ClearPopup;
+    }
+
+    async fn handle_clear_popup_input(&mut self, key: KeyEvent) {
+        let clear = match key.code {
+            KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => true,
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => false,
+            _ => return,
+        };
+
+        self.show_clear_popup = false;
+        self.selected_module = self.popup.calling_module;
+        if clear {
+            self.clear_all().await;
+        }
+    }
+
+    /// Stop every source and start a fresh analysis. The display options are kept
+    async fn clear_all(&mut self) {
+        self.log_analyzer.clear_all();
+        self.clear_view().await;
+        self.notify(Notification::new(NotificationLevel::Info, "Analysis cleared"));
+    }
+
+    /// Drop the lines, sources and filters displayed, once the analysis is replaced
+    async fn clear_view(&mut self) {
+        self.input_buffers[INDEX_SEARCH] = Input::default();
+        self.log_lines.clear();
+        self.search_lines.clear();
+        self.horizontal_offset = 0;
+        self.pinned_lines = StatefulTable::with_items(Vec::new());
+        self.expanded_groups.clear();
+        self.grouped_log = StatefulTable::with_items(Vec::new());
+        self.time_marks = (None, None);
+        self.pending_position = None;
+        self.session_file = None;
+        self.bursts.clear();
+
+        self.sources = StatefulTable::with_items(Vec::new());
+        self.filters = StatefulTable::with_items(Vec::new());
+        self.update_formats().await;
+    }
+
     pub fn navigate(&mut self, direction: KeyCode) {
         match self.selected_module {
             Module::Sources => {
@@ -848,48 +2847,56 @@ impl App {
                 self.filters.unselect()
             }
             Module::Logs => match direction {
+                KeyCode::Up if !self.pinned_lines.items.is_empty() => {
+                    self.selected_module = Module::PinnedLines
+                }
                 KeyCode::Up => self.selected_module = Module::SearchResult,
                 KeyCode::Down => self.selected_module = Module::Search,
-                KeyCode::Left | KeyCode::Right => {
-                    if self.side_main_size_percentage > 0 {
-                        self.selected_module = Module::Sources
-                    }
+                KeyCode::Left | KeyCode::Right if self.side_main_size_percentage > 0 => {
+                    self.selected_module = Module::Sources
                 }
                 _ => {}
             },
             Module::Search => match direction {
                 KeyCode::Up => self.selected_module = Module::Logs,
                 KeyCode::Down => self.selected_module = Module::SearchResult,
-                KeyCode::Left | KeyCode::Right => {
-                    if self.side_main_size_percentage > 0 {
-                        self.selected_module = Module::Filters
-                    }
+                KeyCode::Left | KeyCode::Right if self.side_main_size_percentage > 0 => {
+                    self.selected_module = Module::Filters
+                }
+                _ => {}
+            },
+            Module::PinnedLines => match direction {
+                KeyCode::Up => self.selected_module = Module::SearchResult,
+                KeyCode::Down => self.selected_module = Module::Logs,
+                KeyCode::Left | KeyCode::Right if self.side_main_size_percentage > 0 => {
+                    self.selected_module = Module::Sources
                 }
                 _ => {}
             },
             Module::SearchResult => match direction {
                 KeyCode::Up => self.selected_module = Module::Search,
+                KeyCode::Down if !self.pinned_lines.items.is_empty() => {
+                    self.selected_module = Module::PinnedLines
+                }
                 KeyCode::Down => self.selected_module = Module::Logs,
-                KeyCode::Left | KeyCode::Right => {
-                    if self.side_main_size_percentage > 0 {
-                        self.selected_module = Module::Filters
-                    }
+                KeyCode::Left | KeyCode::Right if self.side_main_size_percentage > 0 => {
+                    self.selected_module = Module::Filters
                 }
                 _ => {}
             },
             Module::SourcePopup => {
                 match direction {
                     // Navigate up sources
-                    KeyCode::Up => {
-                        if self.input_buffer_index > INDEX_SOURCE_TYPE {
-                            self.input_buffer_index -= 1;
-                        }
+                    KeyCode::Up
+                        if self.input_buffer_index > INDEX_SOURCE_TYPE
+                            || (self.input_buffer_index > INDEX_SOURCE_RECENT
+                                && !self.recent_sources.items.is_empty()) =>
+                    {
+                        self.input_buffer_index -= 1;
                     }
                     // Navigate down sources
-                    KeyCode::Down => {
-                        if self.input_buffer_index < INDEX_SOURCE_OK_BUTTON {
-                            self.input_buffer_index += 1;
-                        }
+                    KeyCode::Down if self.input_buffer_index < INDEX_SOURCE_OK_BUTTON => {
+                        self.input_buffer_index += 1;
                     }
                     _ => {}
                 }
@@ -897,22 +2904,65 @@ impl App {
             Module::FilterPopup => {
                 match direction {
                     // Navigate up sources
-                    KeyCode::Up => {
-                        if self.input_buffer_index > INDEX_FILTER_NAME {
-                            self.input_buffer_index -= 1;
-                        }
+                    KeyCode::Up if self.input_buffer_index > INDEX_FILTER_NAME => {
+                        self.input_buffer_index -= 1;
                     }
                     // Navigate down sources
-                    KeyCode::Down => {
-                        if self.input_buffer_index < INDEX_FILTER_OK_BUTTON {
-                            self.input_buffer_index += 1;
-                        }
+                    KeyCode::Down if self.input_buffer_index < INDEX_FILTER_OK_BUTTON => {
+                        self.input_buffer_index += 1;
                     }
                     _ => {}
                 }
             }
             Module::ErrorPopup => (),
             Module::NavigationPopup => (),
+            Module::ColumnHeader => (),
+            Module::RestorePopup => (),
+            Module::ClearPopup => (),
+            Module::NotificationsPopup => (),
+            Module::SettingsPopup => match direction {
+                KeyCode::Up if self.input_buffer_index > INDEX_SETTINGS_PRIMARY_COLOR => {
+                    self.input_buffer_index -= 1;
+                }
+                KeyCode::Down if self.input_buffer_index < INDEX_SETTINGS_MEMORY_CRITICAL => {
+                    self.input_buffer_index += 1;
+                }
+                _ => {}
+            },
+            Module::PathPopup => (),
+            Module::TopValuesPopup => match direction {
+                KeyCode::Up if self.input_buffer_index > INDEX_TOP_VALUES_COLUMN => {
+                    self.input_buffer_index -= 1;
+                }
+                KeyCode::Down if self.input_buffer_index < INDEX_TOP_VALUES_RANGE => {
+                    self.input_buffer_index += 1;
+                }
+                _ => {}
+            },
+            // Up and down move through the gaps, tab switches between the options
+            Module::GapsPopup => {
+                self.input_buffer_index = match self.input_buffer_index {
+                    INDEX_GAPS_MIN => INDEX_GAPS_MARKERS,
+                    _ => INDEX_GAPS_MIN,
+                }
+            }
+            Module::TransactionPopup => (),
+            // Up and down move through the lines, tab goes through the options
+            Module::WatchesPopup => (),
+            Module::LinePopup => (),
+            // Up and down recall the scripts run
+            Module::ConsolePopup => (),
+            Module::DiffPopup => match direction {
+                KeyCode::Up if self.input_buffer_index > INDEX_DIFF_LEFT_SOURCE => {
+                    self.input_buffer_index -= 1;
+                }
+                KeyCode::Up => self.input_buffer_index = INDEX_DIFF_RIGHT_RANGE,
+                KeyCode::Down if self.input_buffer_index < INDEX_DIFF_RIGHT_RANGE => {
+                    self.input_buffer_index += 1;
+                }
+                KeyCode::Down => self.input_buffer_index = INDEX_DIFF_LEFT_SOURCE,
+                _ => {}
+            },
             Module::None => self.selected_module = Module::Logs,
         }
     }
@@ -926,22 +2976,25 @@ impl App {
     }
 
     pub fn get_column_lenght(&self, column: &str) -> u16 {
-        let lenght = |log_lines: &Vec<LogLine>| {
-            log_lines
-                .iter()
-                .map(|l| l.get(column).unwrap())
-                .max_by_key(|l| l.len())
-                .map(|l| l.len().clamp(0, u16::MAX as usize) as u16)
+        let lenght = |lenghts: &mut dyn Iterator<Item = usize>| {
+            lenghts
+                .max()
+                .map(|l| l.clamp(0, u16::MAX as usize) as u16)
         };
 
-        let max_log_lenght = lenght(&self.log_lines.items);
+        let max_log_lenght = lenght(
+            &mut self
+                .log_lines
+                .items
+                .iter()
+                .map(|line| line.get(column).unwrap().len()),
+        );
         let max_search_lenght = lenght(
-            &self
+            &mut self
                 .search_lines
                 .items
                 .iter()
-                .map(|line| line.unformat())
-                .collect(),
+                .map(|line| line.unformat().get(column).unwrap().len()),
         );
 
         match (max_log_lenght, max_search_lenght) {
@@ -952,6 +3005,109 @@ impl App {
         }
     }
 
+    /// Toggle the severity bound to the digit key pressed ('1' DEBUG .. '4' ERROR)
+    pub fn toggle_severity_key(&self, key: char) {
+        let severity = key
+            .to_digit(10)
+            .and_then(|digit| Severity::all().get((digit as usize).checked_sub(1)?).copied());
+        if let Some(severity) = severity {
+            self.log_analyzer.toggle_severity(severity);
+        }
+    }
+
+    /// Mark the start line, then the end line. Marking again starts a new measurement
+    pub fn mark_time(&mut self, line: LogLine) {
+        self.time_marks = match &self.time_marks {
+            (Some(start), None) => (Some(start.clone()), Some(line)),
+            _ => (Some(line), None),
+        }
+    }
+
+    /// Elapsed seconds between the marked lines if both are marked and have a parseable time
+    pub fn get_elapsed_time(&self) -> Option<f64> {
+        match &self.time_marks {
+            (Some(start), Some(end)) => Some(end.time()? - start.time()?),
+            _ => None,
+        }
+    }
+
+    /// The time of the line falls in a burst
+    pub fn is_in_burst(&self, line: &LogLine) -> bool {
+        !self.bursts.is_empty()
+            && line
+                .time()
+                .is_some_and(|time| self.bursts.iter().any(|burst| burst.contains(time)))
+    }
+
+    /// Times of the marked lines as (earliest, latest) if both are marked and have a parseable time
+    pub fn get_marked_range(&self) -> Option<(f64, f64)> {
+        match &self.time_marks {
+            (Some(start), Some(end)) => {
+                let (start, end) = (start.time()?, end.time()?);
+                Some((start.min(end), start.max(end)))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_frozen_column(&self, column: &str) -> bool {
+        self.frozen_columns.iter().any(|frozen| frozen == column)
+    }
+
+    /// Freeze the column if not frozen yet or unfreeze it otherwise
+    pub fn toggle_frozen_column(&mut self, column: &str) {
+        match self.frozen_columns.iter().position(|frozen| frozen == column) {
+            Some(position) => {
+                self.frozen_columns.remove(position);
+            }
+            None => self.frozen_columns.push(column.to_string()),
+        }
+    }
+
+    /// Get the enabled columns in display order (frozen columns first)
+    /// Returns a list of (column, is_frozen)
+    pub fn get_displayed_columns(&self) -> Vec<(String, bool)> {
+        let enabled = self.log_columns.iter().filter(|(_, enabled)| *enabled);
+
+        enabled
+            .clone()
+            .filter(|(column, _)| self.is_frozen_column(column))
+            .chain(enabled.filter(|(column, _)| !self.is_frozen_column(column)))
+            .map(|(column, _)| (column.clone(), self.is_frozen_column(column)))
+            .collect()
+    }
+
+    /// Scroll back to the left, showing again the columns hidden while scrolling right
+    fn scroll_left(&mut self) {
+        if self.horizontal_offset > 0 {
+            self.horizontal_offset = self.horizontal_offset.saturating_sub(10);
+            return;
+        }
+        for (i, (column, enabled)) in self.log_columns.iter().enumerate().rev() {
+            if !*enabled && !self.is_frozen_column(column) && self.get_column_lenght(column) != 0 {
+                self.log_columns[i].1 = true;
+                return;
+            }
+        }
+    }
+
+    /// Scroll to the right hiding columns first and then shifting the content. Frozen columns
+    /// are never hidden or shifted
+    fn scroll_right(&mut self) {
+        let last_column = self.log_columns.len() - 1;
+        for (i, (column, enabled)) in self.log_columns.iter().enumerate() {
+            if i != last_column
+                && *enabled
+                && !self.is_frozen_column(column)
+                && self.get_column_lenght(column) != 0
+            {
+                self.log_columns[i].1 = false;
+                return;
+            }
+        }
+        self.horizontal_offset += 10
+    }
+
     async fn handle_table_log_input(&mut self, key: KeyEvent) {
         let multiplier = if key.modifiers == KeyModifiers::ALT {
             10
@@ -978,6 +3134,28 @@ impl App {
                     self.popup.calling_module = Module::Logs;
                     self.selected_module = Module::NavigationPopup;
                 }
+                KeyCode::Char('F') => self.toggle_frozen_column("Date"),
+                KeyCode::Char('L') => self.sync_cursor = !self.sync_cursor,
+                KeyCode::Char('T') => {
+                    if let Some(line) = self.log_lines.get_selected_item() {
+                        self.mark_time(LogLine::clone(&line));
+                    }
+                }
+                KeyCode::Char('P') => {
+                    if let Some(line) = self.log_lines.get_selected_item() {
+                        self.toggle_pinned_line(LogLine::clone(&line));
+                    }
+                }
+                KeyCode::Char('C') => {
+                    if let Some(line) = self.log_lines.get_selected_item() {
+                        self.open_transaction_popup(&line);
+                    }
+                }
+                KeyCode::Char('V') => {
+                    if let Some(line) = self.log_lines.get_selected_item() {
+                        self.open_line_popup(&line);
+                    }
+                }
                 _ => {}
             },
             _ => match key.code {
@@ -1010,31 +3188,9 @@ impl App {
                     }
                 }
                 // Navigate up log_lines
-                KeyCode::Left => {
-                    if self.horizontal_offset > 0 {
-                        self.horizontal_offset -= if self.horizontal_offset == 0 { 0 } else { 10 };
-                        return;
-                    }
-                    for (i, (column, enabled)) in self.log_columns.iter().enumerate().rev() {
-                        if !*enabled && self.get_column_lenght(column) != 0 {
-                            self.log_columns[i].1 = true;
-                            return;
-                        }
-                    }
-                }
+                KeyCode::Left => self.scroll_left(),
                 // Navigate down log_lines
-                KeyCode::Right => {
-                    for (i, (column, enabled)) in self.log_columns.iter().enumerate() {
-                        if i != (self.log_columns.len() - 1)
-                            && *enabled
-                            && self.get_column_lenght(column) != 0
-                        {
-                            self.log_columns[i].1 = false;
-                            return;
-                        }
-                    }
-                    self.horizontal_offset += 10
-                }
+                KeyCode::Right => self.scroll_right(),
                 // Toogle columns
                 KeyCode::Char('l') => self.log_columns[0].1 = !self.log_columns[0].1,
                 KeyCode::Char('i') => self.log_columns[1].1 = !self.log_columns[1].1,
@@ -1045,13 +3201,32 @@ impl App {
                 KeyCode::Char('f') => self.log_columns[6].1 = !self.log_columns[6].1,
                 KeyCode::Char('p') => self.log_columns[7].1 = !self.log_columns[7].1,
                 KeyCode::Char('r') => self.auto_scroll = !self.auto_scroll,
+                // Quick severity toggles
+                KeyCode::Char(c @ '1'..='4') => self.toggle_severity_key(c),
+                // Switch to the log grouped by source
+                KeyCode::Char('g') => {
+                    self.group_by_source = true;
+                    self.update_grouped_log();
+                }
+                // Focus the headers to filter by column
+                KeyCode::Char('c') => {
+                    self.selected_header = 0;
+                    self.input_buffers[INDEX_COLUMN_FILTER] = Input::default();
+                    self.selected_module = Module::ColumnHeader;
+                }
+                // Remove the last column filter
+                KeyCode::Char('x') => {
+                    if let Some(last) = self.log_analyzer.get_column_filters().last() {
+                        self.log_analyzer.remove_column_filter(&last.column);
+                    }
+                }
                 // Nothing
                 _ => {}
             },
         }
     }
 
-    async fn handle_table_search_input(&mut self, key: KeyEvent){
+    async fn handle_table_search_input(&mut self, key: KeyEvent) {
         let multiplier = if key.modifiers == KeyModifiers::ALT {
             10
         } else {
@@ -1077,6 +3252,28 @@ impl App {
                     self.popup.calling_module = Module::SearchResult;
                     self.selected_module = Module::NavigationPopup;
                 }
+                KeyCode::Char('F') => self.toggle_frozen_column("Date"),
+                KeyCode::Char('L') => self.sync_cursor = !self.sync_cursor,
+                KeyCode::Char('T') => {
+                    if let Some(line) = self.search_lines.get_selected_item() {
+                        self.mark_time(line.unformat());
+                    }
+                }
+                KeyCode::Char('P') => {
+                    if let Some(line) = self.search_lines.get_selected_item() {
+                        self.toggle_pinned_line(line.unformat());
+                    }
+                }
+                KeyCode::Char('C') => {
+                    if let Some(line) = self.search_lines.get_selected_item() {
+                        self.open_transaction_popup(&line.unformat());
+                    }
+                }
+                KeyCode::Char('V') => {
+                    if let Some(line) = self.search_lines.get_selected_item() {
+                        self.open_line_popup(&line.unformat());
+                    }
+                }
                 _ => {}
             },
             _ => match key.code {
@@ -1109,31 +3306,9 @@ impl App {
                     }
                 }
                 // Navigate up log_lines
-                KeyCode::Left => {
-                    if self.horizontal_offset > 0 {
-                        self.horizontal_offset -= if self.horizontal_offset == 0 { 0 } else { 10 };
-                        return;
-                    }
-                    for (i, (column, enabled)) in self.log_columns.iter().enumerate().rev() {
-                        if !*enabled && self.get_column_lenght(column) != 0 {
-                            self.log_columns[i].1 = true;
-                            return;
-                        }
-                    }
-                }
+                KeyCode::Left => self.scroll_left(),
                 // Navigate down log_lines
-                KeyCode::Right => {
-                    for (i, (column, enabled)) in self.log_columns.iter().enumerate() {
-                        if i != (self.log_columns.len() - 1)
-                            && *enabled
-                            && self.get_column_lenght(column) != 0
-                        {
-                            self.log_columns[i].1 = false;
-                            return;
-                        }
-                    }
-                    self.horizontal_offset += 10
-                }
+                KeyCode::Right => self.scroll_right(),
                 // Toogle columns
                 KeyCode::Char('l') => self.log_columns[0].1 = !self.log_columns[0].1,
                 KeyCode::Char('i') => self.log_columns[1].1 = !self.log_columns[1].1,
@@ -1144,9 +3319,12 @@ impl App {
                 KeyCode::Char('f') => self.log_columns[6].1 = !self.log_columns[6].1,
                 KeyCode::Char('p') => self.log_columns[7].1 = !self.log_columns[7].1,
                 KeyCode::Char('r') => self.auto_scroll = !self.auto_scroll,
+                // Quick severity toggles
+                KeyCode::Char(c @ '1'..='4') => self.toggle_severity_key(c),
                 KeyCode::Enter => {
                     if let Some(current_line) = self.search_lines.get_selected_item() {
-                            self.log_lines.navigate_to(current_line.unformat().index.parse().unwrap());
+                        self.log_lines
+                            .navigate_to(current_line.unformat().index);
                     }
                 }
                 // Nothing
@@ -1156,6 +3334,24 @@ impl App {
     }
 }
 
+/// Whether the file at `path` is a snapshot of the analysis rather than a session
+fn is_snapshot(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == SNAPSHOT_EXTENSION)
+}
+
+/// Parse a color written as `r, g, b`
+pub fn parse_rgb(color: &str) -> Option<(u8, u8, u8)> {
+    let channels: Vec<u8> = color
+        .split(',')
+        .map(|channel| channel.trim().parse::<u8>())
+        .collect::<Result<_, _>>()
+        .ok()?;
+    match channels[..] {
+        [r, g, b] => Some((r, g, b)),
+        _ => None,
+    }
+}
+
 pub fn parse_color(r: &str, g: &str, b: &str) -> Option<(u8, u8, u8)> {
     match (r.parse::<u8>(), g.parse::<u8>(), b.parse::<u8>()) {
         parse
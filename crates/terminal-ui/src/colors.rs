@@ -0,0 +1,142 @@
+use std::env;
+
+use tui::style::Color;
+
+/// Levels of the 6x6x6 color cube of the 256-color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Standard 16-color palette with its usual xterm RGB values
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// Colors the terminal is able to render
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ColorSupport {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorSupport {
+    /// Detect the capabilities of the terminal from `COLORTERM` and `TERM`
+    pub fn detect() -> Self {
+        Self::from_env(
+            env::var("COLORTERM").ok().as_deref(),
+            env::var("TERM").ok().as_deref(),
+        )
+    }
+
+    fn from_env(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return ColorSupport::TrueColor;
+        }
+        match term {
+            Some(term) if term.contains("direct") => ColorSupport::TrueColor,
+            Some(term) if term.contains("256color") => ColorSupport::Ansi256,
+            // Windows terminals don't set TERM but render RGB
+            None if cfg!(windows) => ColorSupport::TrueColor,
+            _ => ColorSupport::Ansi16,
+        }
+    }
+
+    /// Build the closest color to the given RGB that the terminal can render
+    pub fn rgb(&self, r: u8, g: u8, b: u8) -> Color {
+        match self {
+            ColorSupport::TrueColor => Color::Rgb(r, g, b),
+            ColorSupport::Ansi256 => Color::Indexed(to_ansi256(r, g, b)),
+            ColorSupport::Ansi16 => to_ansi16(r, g, b),
+        }
+    }
+
+    /// Build the closest renderable color to an optional RGB, defaulting to `Color::Reset`
+    pub fn color(&self, color: Option<(u8, u8, u8)>) -> Color {
+        match color {
+            Some((r, g, b)) => self.rgb(r, g, b),
+            None => Color::Reset,
+        }
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+/// Index of the closest entry in the 256-color palette, choosing between the color cube
+/// and the grayscale ramp
+fn to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let cube_index = |v: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - v as i32).abs())
+            .unwrap()
+    };
+    let (ri, gi, bi) = (cube_index(r), cube_index(g), cube_index(b));
+    let cube = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+
+    // Grayscale ramp goes from 8 to 238 in steps of 10
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_index = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray_level = 8 + gray_index * 10;
+
+    if distance((gray_level, gray_level, gray_level), (r, g, b)) < distance(cube, (r, g, b)) {
+        232 + gray_index
+    } else {
+        16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8
+    }
+}
+
+/// Closest color of the standard 16-color palette
+fn to_ansi16(r: u8, g: u8, b: u8) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb, (r, g, b)))
+        .map(|(color, _)| *color)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_are_detected_from_env() {
+        assert_eq!(
+            ColorSupport::from_env(Some("truecolor"), Some("xterm")),
+            ColorSupport::TrueColor
+        );
+        assert_eq!(
+            ColorSupport::from_env(None, Some("xterm-256color")),
+            ColorSupport::Ansi256
+        );
+        assert_eq!(
+            ColorSupport::from_env(None, Some("linux")),
+            ColorSupport::Ansi16
+        );
+    }
+
+    #[test]
+    fn rgb_is_quantized_to_the_nearest_entry() {
+        assert_eq!(to_ansi256(255, 0, 0), 196);
+        assert_eq!(to_ansi256(0, 0, 0), 16);
+        assert_eq!(to_ansi256(128, 128, 128), 244);
+        assert_eq!(to_ansi16(250, 10, 10), Color::LightRed);
+        assert_eq!(to_ansi16(0, 0, 200), Color::Blue);
+        assert_eq!(ColorSupport::TrueColor.rgb(1, 2, 3), Color::Rgb(1, 2, 3));
+    }
+}
@@ -0,0 +1,434 @@
+use std::{
+    cell::{Cell, Ref, RefCell},
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use log_analyzer::{
+    models::{
+        filter::{Filter, FilterAction},
+        log_line::LogLine,
+    },
+    LogAnalyzer,
+};
+use regex::Regex;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FnPtr, Map, NativeCallContext, Position, Scope};
+
+/// Lines fetched at once while a script goes through the log
+const LINES_BATCH_SIZE: usize = 10_000;
+/// Time after which a script is stopped, so a mistaken loop doesn't freeze the terminal
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Entries kept in the console, the oldest ones being dropped
+const MAX_ENTRIES: usize = 1000;
+
+/// Functions available to the scripts, printed by `help()`
+pub const CONSOLE_HELP: &str = "\
+lines() / lines(from, to): filtered lines as maps of log, index, raw_index, date, timestamp, \
+app, severity, function and payload
+for_each_line(|l| ...): call the function with each filtered line without keeping them all
+total_lines(), raw_lines(): number of filtered and raw lines
+count(regex) / count(column, regex): filtered lines whose payload or column matches
+column_values(column, top): most frequent values of a column as [value, count]
+sources(), filters(): addresses of the sources and aliases of the filters
+add_filter(alias, action, regex) / add_filter(alias, action, #{column: regex}): add an \
+include, exclude or marker filter
+toggle_filter(alias), remove_filter(alias), search(regex)
+print(value): write to the console, clear(): empty it";
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Line of the console output
+pub enum ConsoleEntry {
+    /// Script run by the user
+    Script(String),
+    /// Printed or returned by a script
+    Output(String),
+    Error(String),
+}
+
+/// Rhai console running one-off scripts over the analysis, e.g.
+/// `lines().filter(|l| l.app == "netd").len()`. The variables declared by a script are kept
+/// for the next ones
+pub struct ScriptConsole {
+    engine: Engine,
+    scope: Scope<'static>,
+    entries: Rc<RefCell<Vec<ConsoleEntry>>>,
+    deadline: Deadline,
+    /// Scripts run, the last one at the end
+    history: Vec<String>,
+    /// Script of the history being recalled
+    history_position: Option<usize>,
+}
+
+#[derive(Clone)]
+/// When the script being run is stopped, checked as it runs and between the batches of lines
+struct Deadline {
+    /// Start of the script being run
+    started: Rc<Cell<Instant>>,
+    timeout: Rc<Cell<Duration>>,
+}
+
+impl Deadline {
+    fn check(&self) -> ScriptResult<()> {
+        match self.started.get().elapsed() > self.timeout.get() {
+            true => Err(EvalAltResult::ErrorTerminated(Dynamic::UNIT, Position::NONE).into()),
+            false => Ok(()),
+        }
+    }
+}
+
+impl ScriptConsole {
+    pub fn new(log_analyzer: Arc<dyn LogAnalyzer>) -> Self {
+        let entries = Rc::new(RefCell::new(Vec::new()));
+        let deadline = Deadline {
+            started: Rc::new(Cell::new(Instant::now())),
+            timeout: Rc::new(Cell::new(SCRIPT_TIMEOUT)),
+        };
+        let mut engine = Engine::new();
+
+        let printed = entries.clone();
+        engine.on_print(move |text| push_entry(&printed, ConsoleEntry::Output(text.to_string())));
+        let cleared = entries.clone();
+        engine.register_fn("clear", move || cleared.borrow_mut().clear());
+        let script_deadline = deadline.clone();
+        engine.on_progress(move |operations| {
+            match operations % 1024 == 0 && script_deadline.check().is_err() {
+                true => Some(Dynamic::UNIT),
+                false => None,
+            }
+        });
+        register_api(&mut engine, log_analyzer, &deadline);
+
+        Self {
+            engine,
+            scope: Scope::new(),
+            entries,
+            deadline,
+            history: Vec::new(),
+            history_position: None,
+        }
+    }
+
+    /// Run `script`, writing it to the console followed by what it prints and its result
+    pub fn run(&mut self, script: &str) {
+        push_entry(&self.entries, ConsoleEntry::Script(script.to_string()));
+        if self.history.last().map(String::as_str) != Some(script) {
+            self.history.push(script.to_string());
+        }
+        self.history_position = None;
+
+        self.deadline.started.set(Instant::now());
+        match self.engine.eval_with_scope::<Dynamic>(&mut self.scope, script) {
+            Ok(result) if result.is_unit() => {}
+            Ok(result) => push_entry(&self.entries, ConsoleEntry::Output(result.to_string())),
+            Err(err) => {
+                let err = match terminated(&err) {
+                    true => format!("Script stopped after {:?}", self.deadline.timeout.get()),
+                    false => err.to_string(),
+                };
+                push_entry(&self.entries, ConsoleEntry::Error(err))
+            }
+        }
+    }
+
+    pub fn entries(&self) -> Ref<'_, Vec<ConsoleEntry>> {
+        self.entries.borrow()
+    }
+
+    /// Recall the script run before the one recalled, the last one at first
+    pub fn previous_script(&mut self) -> Option<&str> {
+        let position = match self.history_position {
+            Some(position) => position.checked_sub(1)?,
+            None => self.history.len().checked_sub(1)?,
+        };
+        self.history_position = Some(position);
+        self.history.get(position).map(String::as_str)
+    }
+
+    /// Recall the script run after the one recalled, none once past the last one
+    pub fn next_script(&mut self) -> Option<&str> {
+        let position = self.history_position? + 1;
+        self.history_position = (position < self.history.len()).then_some(position);
+        self.history.get(position).map(String::as_str)
+    }
+}
+
+fn push_entry(entries: &RefCell<Vec<ConsoleEntry>>, entry: ConsoleEntry) {
+    let mut entries = entries.borrow_mut();
+    if entries.len() == MAX_ENTRIES {
+        entries.remove(0);
+    }
+    entries.push(entry);
+}
+
+/// Whether `err` is the script stopped, maybe in a function it called
+fn terminated(err: &EvalAltResult) -> bool {
+    match err {
+        EvalAltResult::ErrorTerminated(..) => true,
+        EvalAltResult::ErrorInFunctionCall(_, _, err, _) => terminated(err),
+        _ => false,
+    }
+}
+
+fn script_error(err: impl ToString) -> Box<EvalAltResult> {
+    err.to_string().into()
+}
+
+/// Column of `LogLine::columns` named `name`, ignoring the case
+fn column(name: &str) -> ScriptResult<String> {
+    LogLine::columns()
+        .into_iter()
+        .find(|column| column.eq_ignore_ascii_case(name))
+        .ok_or_else(|| script_error(format!("Unknown column {}", name)))
+}
+
+fn line_to_map(line: &LogLine) -> Map {
+    let mut map = Map::new();
+    let text = |text: &str| Dynamic::from(text.to_string());
+    map.insert("log".into(), text(&line.log));
+    map.insert("index".into(), Dynamic::from(line.index as i64));
+    map.insert("raw_index".into(), Dynamic::from(line.raw_index as i64));
+    map.insert("date".into(), text(&line.date));
+    map.insert("timestamp".into(), text(&line.timestamp));
+    map.insert("app".into(), text(&line.app));
+    map.insert("severity".into(), text(&line.severity));
+    map.insert("function".into(), text(&line.function));
+    map.insert("payload".into(), text(&line.payload));
+    map
+}
+
+/// Filter of `action` (include, exclude or marker) matching the `regexes` of its columns
+fn filter(alias: &str, action: &str, regexes: Map) -> ScriptResult<Filter> {
+    let action = match action.to_ascii_lowercase().as_str() {
+        "include" => FilterAction::INCLUDE,
+        "exclude" => FilterAction::EXCLUDE,
+        "marker" => FilterAction::MARKER,
+        _ => return Err(script_error("The action must be include, exclude or marker")),
+    };
+    let mut filter = LogLine::default();
+    for (name, regex) in regexes {
+        let regex = regex.into_string().map_err(script_error)?;
+        Regex::new(&regex).map_err(script_error)?;
+        let field = match column(&name)?.as_str() {
+            "Log" => &mut filter.log,
+            "Date" => &mut filter.date,
+            "Timestamp" => &mut filter.timestamp,
+            "App" => &mut filter.app,
+            "Severity" => &mut filter.severity,
+            "Function" => &mut filter.function,
+            "Payload" => &mut filter.payload,
+            _ => return Err(script_error(format!("Filters can't match the {} column", name))),
+        };
+        *field = regex.into();
+    }
+    Ok(Filter {
+        alias: alias.to_string(),
+        action,
+        filter,
+    })
+}
+
+/// Go through the filtered lines `from..to`, up to the last one, one batch after another.
+/// Stops at the first error of `f` or once the script is past its `deadline`
+fn for_each_line(
+    log_analyzer: &dyn LogAnalyzer,
+    deadline: &Deadline,
+    from: usize,
+    to: usize,
+    mut f: impl FnMut(&LogLine) -> ScriptResult<()>,
+) -> ScriptResult<()> {
+    let to = to.min(log_analyzer.get_total_filtered_lines());
+    for start in (from..to).step_by(LINES_BATCH_SIZE) {
+        deadline.check()?;
+        let end = (start + LINES_BATCH_SIZE).min(to);
+        for line in log_analyzer.get_log_lines(start, end) {
+            f(&line)?;
+        }
+    }
+    Ok(())
+}
+
+fn count_matches(
+    log_analyzer: &dyn LogAnalyzer,
+    deadline: &Deadline,
+    column: &str,
+    regex: &str,
+) -> ScriptResult<i64> {
+    let regex = Regex::new(regex).map_err(script_error)?;
+    let mut count = 0;
+    for_each_line(log_analyzer, deadline, 0, usize::MAX, |line| {
+        if line.get_text(column).is_some_and(|text| regex.is_match(text)) {
+            count += 1;
+        }
+        Ok(())
+    })?;
+    Ok(count)
+}
+
+/// Register the functions of `CONSOLE_HELP`
+fn register_api(engine: &mut Engine, log_analyzer: Arc<dyn LogAnalyzer>, deadline: &Deadline) {
+    engine.register_fn("help", || CONSOLE_HELP.to_string());
+
+    let (analyzer, deadline_of) = (log_analyzer.clone(), deadline.clone());
+    engine.register_fn("lines", move || -> ScriptResult<Array> {
+        let mut lines = Array::new();
+        for_each_line(analyzer.as_ref(), &deadline_of, 0, usize::MAX, |line| {
+            lines.push(line_to_map(line).into());
+            Ok(())
+        })?;
+        Ok(lines)
+    });
+    let (analyzer, deadline_of) = (log_analyzer.clone(), deadline.clone());
+    engine.register_fn("lines", move |from: i64, to: i64| -> ScriptResult<Array> {
+        let (from, to) = (from.max(0) as usize, to.max(0) as usize);
+        let mut lines = Array::new();
+        for_each_line(analyzer.as_ref(), &deadline_of, from, to, |line| {
+            lines.push(line_to_map(line).into());
+            Ok(())
+        })?;
+        Ok(lines)
+    });
+    let (analyzer, deadline_of) = (log_analyzer.clone(), deadline.clone());
+    engine.register_fn(
+        "for_each_line",
+        move |context: NativeCallContext, callback: FnPtr| {
+            for_each_line(analyzer.as_ref(), &deadline_of, 0, usize::MAX, |line| {
+                callback
+                    .call_within_context::<Dynamic>(&context, (line_to_map(line),))
+                    .map(drop)
+            })
+        },
+    );
+
+    let analyzer = log_analyzer.clone();
+    engine.register_fn("total_lines", move || analyzer.get_total_filtered_lines() as i64);
+    let analyzer = log_analyzer.clone();
+    engine.register_fn("raw_lines", move || analyzer.get_total_raw_lines() as i64);
+
+    let (analyzer, deadline_of) = (log_analyzer.clone(), deadline.clone());
+    engine.register_fn("count", move |regex: &str| {
+        count_matches(analyzer.as_ref(), &deadline_of, "Payload", regex)
+    });
+    let (analyzer, deadline_of) = (log_analyzer.clone(), deadline.clone());
+    engine.register_fn("count", move |name: &str, regex: &str| {
+        count_matches(analyzer.as_ref(), &deadline_of, &column(name)?, regex)
+    });
+
+    let analyzer = log_analyzer.clone();
+    engine.register_fn("column_values", move |name: &str, top: i64| -> ScriptResult<Array> {
+        let values = analyzer
+            .get_column_values(&column(name)?, top.max(0) as usize)
+            .map_err(script_error)?;
+        Ok(values
+            .into_iter()
+            .map(|(value, count)| {
+                let pair: Array = vec![value.into(), (count as i64).into()];
+                pair.into()
+            })
+            .collect())
+    });
+
+    let analyzer = log_analyzer.clone();
+    engine.register_fn("sources", move || -> Array {
+        analyzer
+            .get_logs()
+            .into_iter()
+            .map(|(_, address, _)| address.into())
+            .collect()
+    });
+    let analyzer = log_analyzer.clone();
+    engine.register_fn("filters", move || -> Array {
+        analyzer
+            .get_filters()
+            .into_iter()
+            .map(|(_, filter)| filter.alias.into())
+            .collect()
+    });
+
+    let analyzer = log_analyzer.clone();
+    engine.register_fn("add_filter", move |alias: &str, action: &str, regex: &str| {
+        let mut regexes = Map::new();
+        regexes.insert("payload".into(), regex.into());
+        analyzer.add_filter(filter(alias, action, regexes)?);
+        ScriptResult::Ok(())
+    });
+    let analyzer = log_analyzer.clone();
+    engine.register_fn("add_filter", move |alias: &str, action: &str, regexes: Map| {
+        analyzer.add_filter(filter(alias, action, regexes)?);
+        ScriptResult::Ok(())
+    });
+    let analyzer = log_analyzer.clone();
+    engine.register_fn("toggle_filter", move |alias: &str| analyzer.toggle_filter(alias));
+    let analyzer = log_analyzer.clone();
+    engine.register_fn("remove_filter", move |alias: &str| {
+        analyzer.remove_filter(alias).map_err(script_error)
+    });
+    let analyzer = log_analyzer;
+    engine.register_fn("search", move |regex: &str| analyzer.add_search(regex));
+}
+
+#[cfg(test)]
+mod tests {
+    use log_analyzer::LogAnalyzerBuilder;
+    use log_source::source::log_source::SourceType;
+    use test_utils::{tempdir, wait_until, DEADLINE};
+
+    use super::*;
+
+    #[test]
+    fn scripts_go_through_the_lines_and_add_filters() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("console.log");
+        std::fs::write(&path, "INFO started\nERROR failed\nINFO stopped\n").unwrap();
+        let analyzer: Arc<dyn LogAnalyzer> = LogAnalyzerBuilder::new().workers(1).build();
+        analyzer.add_format("Default", r"(?P<SEVERITY>\w+) (?P<PAYLOAD>.*)").unwrap();
+        analyzer
+            .add_log(SourceType::FILE.into(), path.to_str().unwrap(), Some(&"Default".into()))
+            .unwrap();
+        wait_until(DEADLINE, || analyzer.get_total_filtered_lines() >= 3);
+
+        let mut console = ScriptConsole::new(analyzer.clone());
+        console.deadline.timeout.set(Duration::from_millis(100));
+        console.run("let infos = lines().filter(|l| l.severity == \"INFO\").len();");
+        console.run("print(`${infos} infos`); count(\"st\")");
+        console.run("count(\"date\", \"(\")");
+        console.run("add_filter(\"Errors\", \"marker\", #{severity: \"ERROR\"})");
+        console.run("loop {}");
+
+        let entries = console.entries();
+        assert_eq!(entries[2], ConsoleEntry::Output("2 infos".into()));
+        assert_eq!(entries[3], ConsoleEntry::Output("2".into()));
+        assert!(matches!(&entries[5], ConsoleEntry::Error(err) if err.contains("regex")));
+        assert_eq!(entries.len(), 9);
+        assert_eq!(
+            entries[8],
+            ConsoleEntry::Error("Script stopped after 100ms".into())
+        );
+        drop(entries);
+        let filters = analyzer.get_filters();
+        assert_eq!(filters[0].1.alias, "Errors");
+        assert_eq!(filters[0].1.filter.severity, "ERROR");
+
+        assert_eq!(console.previous_script(), Some("loop {}"));
+        assert!(console.previous_script().unwrap().starts_with("add_filter"));
+        assert_eq!(console.next_script(), Some("loop {}"));
+        assert_eq!(console.next_script(), None);
+
+        // The lines are gone through without being kept, and only up to the last one
+        console.run("let seen = []; for_each_line(|l| seen.push(l.payload)); seen.len()");
+        console.run("lines(1, 100).len()");
+        let entries = console.entries();
+        assert_eq!(entries[10], ConsoleEntry::Output("3".into()));
+        assert_eq!(entries[12], ConsoleEntry::Output("2".into()));
+        drop(entries);
+        // Going through the lines stops once the script is past its deadline
+        console.deadline.timeout.set(Duration::ZERO);
+        console.run("lines().len()");
+        assert_eq!(
+            console.entries().last(),
+            Some(&ConsoleEntry::Error("Script stopped after 0ns".into()))
+        );
+    }
+}
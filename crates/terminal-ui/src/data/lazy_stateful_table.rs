@@ -84,6 +84,7 @@ impl<T: Clone> LazyStatefulTable<T> {
     pub fn clear(&mut self) {
         self.state.select(None);
         self.items.clear();
+        self.offset = 0;
     }
 
     fn select_and_set_scroll_on_top(&mut self, index: usize) {
@@ -155,11 +156,7 @@ impl<T: Clone> Stateful<T> for LazyStatefulTable<T> {
                         }
                     }
                     Area::Below => {
-                        let initial_element = if self.offset > ROOM {
-                            self.offset - ROOM
-                        } else {
-                            0
-                        };
+                        let initial_element = self.offset.saturating_sub(ROOM);
 
                         let new_data = self.source.source(initial_element, self.offset);
 
@@ -0,0 +1,81 @@
+use std::str::FromStr;
+
+use log_analyzer::models::settings::Settings;
+
+use crate::app::parse_rgb;
+
+/// Settings file used when `--settings` is not given
+pub const ENV_SETTINGS: &str = "LAP_SETTINGS";
+const ENV_PRIMARY_COLOR: &str = "LAP_PRIMARY_COLOR";
+const ENV_STRIPE_COLOR: &str = "LAP_STRIPE_COLOR";
+const ENV_TICK_RATE: &str = "LAP_TICK_RATE";
+const ENV_MEMORY_WARNING: &str = "LAP_MEMORY_WARNING_MB";
+const ENV_MEMORY_CRITICAL: &str = "LAP_MEMORY_CRITICAL_MB";
+
+/// Override the display options of the settings with the environment variables read with `var`.
+///
+/// Returns a description of every variable that could not be parsed, which is then ignored
+pub fn apply_env_overrides(settings: &mut Settings, var: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    let mut color = |name: &str, color: &mut Option<(u8, u8, u8)>| {
+        if let Some(value) = var(name) {
+            match parse_rgb(&value) {
+                Some(rgb) => *color = Some(rgb),
+                None => errors.push(format!("{}: expected a color as r, g, b, got {}", name, value)),
+            }
+        }
+    };
+    color(ENV_PRIMARY_COLOR, &mut settings.primary_color);
+    color(ENV_STRIPE_COLOR, &mut settings.stripe_color);
+
+    override_number(&var, ENV_TICK_RATE, &mut settings.tick_rate_ms, &mut errors);
+    override_number(&var, ENV_MEMORY_WARNING, &mut settings.memory_warning_mb, &mut errors);
+    override_number(&var, ENV_MEMORY_CRITICAL, &mut settings.memory_critical_mb, &mut errors);
+
+    errors
+}
+
+fn override_number<T: FromStr + Default + PartialOrd>(
+    var: impl Fn(&str) -> Option<String>,
+    name: &str,
+    number: &mut Option<T>,
+    errors: &mut Vec<String>,
+) {
+    if let Some(value) = var(name) {
+        match value.trim().parse::<T>() {
+            Ok(value) if value > T::default() => *number = Some(value),
+            _ => errors.push(format!("{}: expected a positive number, got {}", name, value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variables_override_the_settings() {
+        let mut settings = Settings {
+            primary_color: Some((1, 2, 3)),
+            tick_rate_ms: Some(150),
+            memory_warning_mb: Some(100),
+            ..Default::default()
+        };
+        let var = |name: &str| match name {
+            "LAP_PRIMARY_COLOR" => Some("200, 100, 0".to_string()),
+            "LAP_TICK_RATE" => Some("50".to_string()),
+            "LAP_MEMORY_WARNING_MB" => Some("lots".to_string()),
+            _ => None,
+        };
+
+        let errors = apply_env_overrides(&mut settings, var);
+
+        assert_eq!(settings.primary_color, Some((200, 100, 0)));
+        assert_eq!(settings.tick_rate_ms, Some(50));
+        assert_eq!(settings.memory_warning_mb, Some(100));
+        assert_eq!(settings.stripe_color, None);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("LAP_MEMORY_WARNING_MB"));
+    }
+}
@@ -1,26 +1,39 @@
+pub mod alerts;
 pub mod app;
+pub mod colors;
+pub mod console;
 pub mod styles;
 pub mod ui;
 pub mod data;
+pub mod env_overrides;
+pub mod macros;
+pub mod path_completion;
+pub mod remote;
+pub mod session;
 
-use app::App;
+use app::{App, PathAction};
 use crossterm::{
-    event::{self, DisableMouseCapture, Event, KeyCode, KeyModifiers, MouseEventKind},
+    event::{self, DisableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers, MouseEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use log_analyzer::{
-    models::settings::Settings,
-    services::log_service::{LogAnalyzer, LogService},
-    stores::{
-        analysis_store::InMemmoryAnalysisStore, log_store::InMemmoryLogStore,
-        processing_store::InMemmoryProcessingStore,
+    models::{
+        notification::{Notification, NotificationLevel},
+        pipeline::PipelineConfig,
+        settings::Settings,
     },
+    LogAnalyzer, LogAnalyzerBuilder,
 };
+use log_source::source::log_source::SourceType;
+use remote::RemoteAnalyzer;
 
+use colors::ColorSupport;
+use data::stateful_list::StatefulList;
 use std::{
     error::Error,
     fs, io,
+    path::PathBuf,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -31,11 +44,135 @@ use tui::{
 use ui::{
     ui_error_message::draw_error_popup, ui_filter_popup::draw_filter_popup,
     ui_loading_popup::draw_loading_popup, ui_log_analyzer::draw_log_analyzer_view,
-    ui_navigation_popup::draw_navigation_popup, ui_source_popup::draw_source_popup,
+    ui_notifications_popup::draw_notifications_popup,
+    ui_navigation_popup::draw_navigation_popup, ui_path_popup::draw_path_popup,
+    ui_restore_popup::{draw_clear_popup, draw_restore_popup}, ui_settings_popup::draw_settings_popup,
+    ui_source_popup::draw_source_popup, ui_top_values_popup::draw_top_values_popup,
+    ui_gaps_popup::draw_gaps_popup,
+    ui_transaction_popup::draw_transaction_popup,
+    ui_diff_popup::draw_diff_popup,
+    ui_watches_popup::draw_watches_popup,
+    ui_line_popup::draw_line_popup,
+    ui_console_popup::draw_console_popup,
 };
 
 
-pub async fn async_main(settings_path: Option<String>) -> Result<(), Box<dyn Error>> {
+/// Use the stores keeping the lines in the SQLite database at `path`
+#[cfg(feature = "sqlite")]
+fn open_database(
+    builder: LogAnalyzerBuilder,
+    path: &str,
+) -> Result<LogAnalyzerBuilder, Box<dyn Error>> {
+    use log_analyzer::stores::sqlite_store::{SqliteAnalysisStore, SqliteLogStore};
+
+    Ok(builder
+        .log_store(Arc::new(SqliteLogStore::open(path)?))
+        .analysis_store(Arc::new(SqliteAnalysisStore::open(path)?)))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn open_database(
+    _builder: LogAnalyzerBuilder,
+    _path: &str,
+) -> Result<LogAnalyzerBuilder, Box<dyn Error>> {
+    Err("lap was built without the sqlite feature, a database can't be used".into())
+}
+
+/// Read the sources of type `PLUGIN` with the libraries of the plugins directory of the settings.
+/// No library is loaded unless the directory is set
+#[cfg(feature = "plugins")]
+fn load_plugins(builder: LogAnalyzerBuilder, options: &Settings) -> LogAnalyzerBuilder {
+    use log_source::source::plugin_source::SourcePlugins;
+
+    match &options.plugins_dir {
+        Some(dir) => builder.plugins(SourcePlugins::load(std::path::Path::new(dir))),
+        None => builder,
+    }
+}
+
+#[cfg(not(feature = "plugins"))]
+fn load_plugins(builder: LogAnalyzerBuilder, _options: &Settings) -> LogAnalyzerBuilder {
+    builder
+}
+
+/// Whether `address` has the name of one of the `plugins` as scheme, e.g. `kafka://broker/topic`
+fn is_plugin_address(plugins: &[String], address: &str) -> bool {
+    address
+        .split_once("://")
+        .is_some_and(|(scheme, _)| plugins.iter().any(|name| name == scheme))
+}
+
+/// Name of the source reading the standard input
+const STDIN_ADDRESS: &str = "stdin";
+/// File name standing for the standard input
+pub const STDIN_FILE: &str = "-";
+
+/// Options of the terminal UI, given on the command line
+pub struct TuiOptions {
+    /// Settings file, the one of the platform config directory if not set
+    pub settings_path: Option<String>,
+    /// Files opened as sources, `STDIN_FILE` opening the lines piped to the standard input
+    pub files: Vec<String>,
+    /// Alias of the format of the files
+    pub format: Option<String>,
+    /// Keep reading the files after their end instead of loading them once
+    pub follow: bool,
+    /// Replay the files this many times faster than their lines were written
+    pub replay: Option<f64>,
+    /// MB the stored lines can use, the new lines being discarded once reached
+    pub max_memory: Option<usize>,
+    /// Options taking precedence over the pipeline of the settings
+    pub pipeline: PipelineConfig,
+    /// Url of the server started with `lap serve` running the analysis
+    pub remote: Option<String>,
+    /// SQLite database keeping the lines, the files it already holds not being read again
+    pub database: Option<String>,
+    /// Settings file the settings shown are written to when quitting
+    pub export_settings: Option<String>,
+}
+
+impl Default for TuiOptions {
+    fn default() -> Self {
+        Self {
+            settings_path: None,
+            files: Vec::new(),
+            format: None,
+            follow: true,
+            replay: None,
+            max_memory: None,
+            pipeline: PipelineConfig::default(),
+            remote: None,
+            database: None,
+            export_settings: None,
+        }
+    }
+}
+
+/// Run the terminal UI with the `options` given.
+///
+/// With a `remote` server url the analysis runs on the server started with `lap serve`,
+/// only the display options of the settings are used. Otherwise the lines are kept in the
+/// SQLite `database` if given
+pub async fn async_main(options: TuiOptions) -> Result<(), Box<dyn Error>> {
+    let TuiOptions {
+        settings_path,
+        files,
+        format,
+        follow,
+        replay,
+        max_memory,
+        pipeline,
+        remote,
+        database,
+        export_settings,
+    } = options;
+    // Connect first so an unreachable server is reported before taking the terminal
+    let remote = remote.as_deref().map(RemoteAnalyzer::connect).transpose()?;
+    let builder = match &database {
+        Some(database) if remote.is_none() => open_database(LogAnalyzerBuilder::new(), database)?,
+        _ => LogAnalyzerBuilder::new(),
+    };
+
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -43,38 +180,138 @@ pub async fn async_main(settings_path: Option<String>) -> Result<(), Box<dyn Err
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Create
-    let log_store = Arc::new(InMemmoryLogStore::new());
-    let processing_store = Arc::new(InMemmoryProcessingStore::new());
-    let analysis_store = Arc::new(InMemmoryAnalysisStore::new());
+    let settings_path = session::settings_path_or_default(settings_path);
+    let mut options = Settings::default();
+    let mut settings_session = None;
+    let mut settings_error = None;
 
-    let log_service = LogService::new(log_store, processing_store, analysis_store);
-    let mut color = Color::LightBlue;
-
-    if let Some(settings) = settings_path {
+    if let Some(settings) = &settings_path {
         if let Ok(file) = fs::read_to_string(settings) {
-            if let Ok(settings) = Settings::from_json(&file) {
-                if let Some(formats) = settings.formats {
-                    for format in formats {
-                        log_service.add_format(&format.alias, &format.regex)?;
-                    }
-                }
-                if let Some(filters) = settings.filters {
-                    for filter in filters {
-                        log_service.add_filter(filter);
-                    }
-                }
-                if let Some((r, g, b)) = settings.primary_color {
-                    color = Color::Rgb(r, g, b)
-                }
+            if let Ok(mut settings) = Settings::from_json(&file) {
+                settings_session = Some(settings.take_session());
+                options = settings;
             }
         }
     }
 
+    let env_errors = env_overrides::apply_env_overrides(&mut options, |name| std::env::var(name).ok());
+
+    let is_remote = remote.is_some();
+    let log_analyzer: Arc<dyn LogAnalyzer> = match remote {
+        Some(remote) => remote,
+        None => {
+            let log_service = load_plugins(builder, &options)
+                .follow(follow)
+                .max_memory(max_memory)
+                .pipeline(options.pipeline.unwrap_or_default())
+                .pipeline(pipeline)
+                .burst_detection(options.burst_detection)
+                .retention(options.retention)
+                .overload_policy(options.overload_policy.unwrap_or_default())
+                .max_line_length(options.max_line_length)
+                .invalid_utf8(options.invalid_utf8.unwrap_or_default())
+                .file_retry(options.file_retry.unwrap_or_default().backoff())
+                .dedupe(options.dedupe)
+                .build();
+            let correlation = log_service.set_correlation_key(options.correlation_key.as_deref());
+            // Transformed and redacted before the sources of the session are read
+            let transforms =
+                log_service.set_transforms(options.transforms.clone().unwrap_or_default());
+            let redactions =
+                log_service.set_redactions(options.redactions.clone().unwrap_or_default());
+            if let Some(session) = &settings_session {
+                settings_error = log_service.restore_session(session).err();
+            }
+            // Watches may reference the filters of the session
+            let watches = options
+                .watches
+                .iter()
+                .flatten()
+                .find_map(|watch| log_service.add_watch(watch).err());
+            settings_error = settings_error
+                .or(correlation.err())
+                .or(transforms.err())
+                .or(redactions.err())
+                .or(watches);
+            log_service
+        }
+    };
+
+    let loaded: Vec<String> = log_analyzer
+        .get_logs()
+        .into_iter()
+        .map(|(_, id, _)| id)
+        .collect();
+    let plugins = log_analyzer.get_source_plugins();
+    let mut file_errors = Vec::new();
+    for file in &files {
+        let result = match file.as_str() {
+            // Piped input can't be sent to a server
+            STDIN_FILE if is_remote => continue,
+            // Reopened from the database
+            path if loaded.iter().any(|id| id == path) => continue,
+            STDIN_FILE => {
+                log_analyzer.add_log(SourceType::STDIN.into(), STDIN_ADDRESS, format.as_ref())
+            }
+            // Read by the plugin named after the scheme of the address
+            address if is_plugin_address(&plugins, address) => {
+                log_analyzer.add_log(SourceType::PLUGIN.into(), address, format.as_ref())
+            }
+            path => match replay {
+                Some(speed) => log_analyzer.replay_log(path, format.as_ref(), speed),
+                None => log_analyzer.add_log(SourceType::FILE.into(), path, format.as_ref()),
+            },
+        };
+        if let Err(err) = result {
+            file_errors.push(format!("Unable to open {}: {}", file, err));
+        }
+    }
+
     // create app and run it
-    let tick_rate = Duration::from_millis(150);
-    let app = App::new(Box::new(log_service), color).await;
-    let res = run_app(&mut terminal, app, tick_rate).await;
+    let mut app = App::new(Box::new(log_analyzer), Color::LightBlue).await;
+    app.color_support = ColorSupport::detect();
+    app.apply_settings(options);
+    if let Some(err) = settings_error {
+        app.notify(Notification::new(
+            NotificationLevel::Error,
+            &format!("Settings partially loaded: {}", err),
+        ));
+    }
+    for err in env_errors {
+        app.notify(Notification::new(
+            NotificationLevel::Warning,
+            &format!("Environment variable ignored: {}", err),
+        ));
+    }
+    for err in file_errors {
+        app.notify(Notification::new(NotificationLevel::Error, &err));
+    }
+    app.settings_path = settings_path
+        .map(PathBuf::from)
+        .or_else(session::default_settings_path);
+    app.is_remote = is_remote;
+    app.export_path = export_settings.map(PathBuf::from);
+    // The session of a remote analysis belongs to the server
+    if !is_remote {
+        app.session_path = session::autosave_path();
+    }
+    app.recent_sources_path = session::recent_sources_path();
+    if let Some(recent) = app
+        .recent_sources_path
+        .as_deref()
+        .and_then(session::load_recent_sources)
+    {
+        app.recent_sources = StatefulList::with_items(recent.sources);
+    }
+    if let Some(session) = app
+        .session_path
+        .as_deref()
+        .and_then(session::load_session)
+        .filter(|session| !session.is_empty())
+    {
+        app.offer_session_restore(session);
+    }
+    let res = run_app(&mut terminal, app).await;
 
     // restore terminal
     disable_raw_mode()?;
@@ -92,17 +329,14 @@ pub async fn async_main(settings_path: Option<String>) -> Result<(), Box<dyn Err
 }
 
 
-async fn run_app<B: Backend>(
-    terminal: &mut Terminal<B>,
-    mut app: App,
-    tick_rate: Duration,
-) -> io::Result<()> {
+async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
     let mut last_tick = Instant::now();
 
     loop {
         terminal.draw(|f| ui(f, &mut app))?;
 
-        let timeout = tick_rate
+        let timeout = app
+            .tick_rate
             .checked_sub(last_tick.elapsed())
             .unwrap_or_else(|| Duration::from_secs(0));
         if crossterm::event::poll(timeout)? {
@@ -110,26 +344,34 @@ async fn run_app<B: Backend>(
 
             match event {
                 Event::Key(key) => {
-                    match key.modifiers {
-                        // Quit
-                        KeyModifiers::CONTROL => match key.code {
-                            KeyCode::Char('c') => return Ok(()),
-                            _ => async_std::task::block_on(app.handle_input(key)),
-                        },
-                        // Navigate
-                        KeyModifiers::SHIFT => match key.code {
-                            KeyCode::Char(_) => async_std::task::block_on(app.handle_input(key)),
-                            KeyCode::Up | KeyCode::BackTab => app.navigate(KeyCode::Up),
-                            KeyCode::Down | KeyCode::Tab => app.navigate(KeyCode::Down),
-                            KeyCode::Left => app.navigate(KeyCode::Left),
-                            KeyCode::Right => app.navigate(KeyCode::Right),
-                            _ => {}
-                        },
-                        // Handle in widget
-                        _ => match key.code {
-                            KeyCode::Tab => app.navigate(KeyCode::Down),
-                            _ => app.handle_input(key).await,
-                        },
+                    // Start or stop recording a macro
+                    if key.modifiers == KeyModifiers::CONTROL && key.code == KeyCode::Char('r') {
+                        app.macros.toggle_recording();
+                        continue;
+                    }
+                    // Select the register to record into
+                    if app.macros.is_awaiting_register() {
+                        if let KeyCode::Char(register) = key.code {
+                            app.macros.start_recording(register);
+                        }
+                        continue;
+                    }
+                    // Replay a macro
+                    if let (KeyModifiers::ALT, KeyCode::Char(register)) = (key.modifiers, key.code) {
+                        if let Some(keys) = app.macros.get_macro(register) {
+                            for key in keys {
+                                handle_key(&mut app, key).await;
+                            }
+                            continue;
+                        }
+                    }
+
+                    app.macros.record(key);
+                    if handle_key(&mut app, key).await {
+                        app.autosave();
+                        return app
+                            .export_settings()
+                            .map_err(|err| io::Error::other(err.to_string()));
                     }
                 }
                 Event::Mouse(mouse) => match mouse.kind {
@@ -145,13 +387,57 @@ async fn run_app<B: Backend>(
                 _ => {}
             }
         }
-        if last_tick.elapsed() >= tick_rate {
+        if last_tick.elapsed() >= app.tick_rate {
             app.on_tick().await;
             last_tick = Instant::now();
         }
     }
 }
 
+/// Dispatch a key to the app. Returns true when the key requests to quit
+async fn handle_key(app: &mut App, key: KeyEvent) -> bool {
+    match key.modifiers {
+        // Quit
+        KeyModifiers::CONTROL => match key.code {
+            KeyCode::Char('c') => return true,
+            KeyCode::Char('n') => app.toggle_notifications(),
+            KeyCode::Char('o') => app.open_settings_popup(),
+            KeyCode::Char('s') => app.open_path_popup(PathAction::SaveConfiguration),
+            KeyCode::Char('w') => app.open_path_popup(PathAction::SaveSession),
+            KeyCode::Char('l') => app.open_path_popup(PathAction::OpenSession),
+            KeyCode::Char('x') => app.open_clear_popup(),
+            KeyCode::Char('t') => app.show_statistics = !app.show_statistics,
+            KeyCode::Char('g') => app.open_top_values_popup(),
+            KeyCode::Char('p') => app.open_gaps_popup(),
+            KeyCode::Char('d') => app.open_diff_popup(),
+            KeyCode::Char('e') => app.open_watches_popup(),
+            KeyCode::Char('f') => app.open_export_popup(),
+            KeyCode::Char('u') => app.toggle_originals(),
+            KeyCode::Char('k') => app.open_console_popup(),
+            _ => app.handle_input(key).await,
+        },
+        // Navigate
+        KeyModifiers::SHIFT => match key.code {
+            KeyCode::Char(_) => app.handle_input(key).await,
+            KeyCode::Up | KeyCode::BackTab => app.navigate(KeyCode::Up),
+            KeyCode::Down | KeyCode::Tab => app.navigate(KeyCode::Down),
+            KeyCode::Left => app.navigate(KeyCode::Left),
+            KeyCode::Right => app.navigate(KeyCode::Right),
+            _ => {}
+        },
+        // Handle in widget
+        _ => match key.code {
+            KeyCode::Tab => {
+                if !app.complete_source_path() {
+                    app.navigate(KeyCode::Down)
+                }
+            }
+            _ => app.handle_input(key).await,
+        },
+    }
+    false
+}
+
 fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     draw_log_analyzer_view(f, app);
 
@@ -161,6 +447,32 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         draw_filter_popup(f, app)
     } else if app.show_navigation_popup {
         draw_navigation_popup(f, app)
+    } else if app.show_restore_popup {
+        draw_restore_popup(f, app)
+    } else if app.show_clear_popup {
+        draw_clear_popup(f, app)
+    } else if app.show_settings_popup {
+        draw_settings_popup(f, app)
+    } else if app.show_path_popup {
+        draw_path_popup(f, app)
+    } else if app.show_top_values_popup {
+        draw_top_values_popup(f, app)
+    } else if app.show_gaps_popup {
+        draw_gaps_popup(f, app)
+    } else if app.show_transaction_popup {
+        draw_transaction_popup(f, app)
+    } else if app.show_diff_popup {
+        draw_diff_popup(f, app)
+    } else if app.show_watches_popup {
+        draw_watches_popup(f, app)
+    } else if app.show_line_popup {
+        draw_line_popup(f, app)
+    } else if app.show_console_popup {
+        draw_console_popup(f, app)
+    }
+
+    if app.show_notifications_popup {
+        draw_notifications_popup(f, app)
     }
 
     if app.show_error_message {
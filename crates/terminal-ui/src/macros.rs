@@ -0,0 +1,79 @@
+use crossterm::event::KeyEvent;
+use std::collections::HashMap;
+
+/// Records key sequences into registers to replay them later
+#[derive(Default)]
+pub struct MacroRecorder {
+    /// K: register -> V: recorded keys
+    registers: HashMap<char, Vec<KeyEvent>>,
+    /// Register being recorded, if any
+    recording: Option<char>,
+    /// Waiting for the register to record into
+    awaiting_register: bool,
+}
+
+impl MacroRecorder {
+    /// Stop the current recording or start waiting for the register of a new one
+    pub fn toggle_recording(&mut self) {
+        match self.recording {
+            Some(_) => self.recording = None,
+            None => self.awaiting_register = true,
+        }
+    }
+
+    pub fn is_awaiting_register(&self) -> bool {
+        self.awaiting_register
+    }
+
+    /// Register being recorded, if any
+    pub fn get_recording(&self) -> Option<char> {
+        self.recording
+    }
+
+    /// Start recording into the register, discarding its previous content.
+    /// Only alphanumeric registers are valid, anything else cancels the recording
+    pub fn start_recording(&mut self, register: char) {
+        self.awaiting_register = false;
+        if register.is_ascii_alphanumeric() {
+            self.registers.insert(register, Vec::new());
+            self.recording = Some(register);
+        }
+    }
+
+    /// Append the key to the register being recorded, if any
+    pub fn record(&mut self, key: KeyEvent) {
+        if let Some(register) = self.recording {
+            self.registers.entry(register).or_default().push(key);
+        }
+    }
+
+    /// Keys recorded in the register
+    pub fn get_macro(&self, register: char) -> Option<Vec<KeyEvent>> {
+        self.registers.get(&register).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    #[test]
+    fn records_keys_into_register() {
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        let mut macros = MacroRecorder::default();
+
+        macros.record(key('x'));
+        macros.toggle_recording();
+        assert!(macros.is_awaiting_register());
+        macros.start_recording('a');
+        macros.record(key('i'));
+        macros.record(key('d'));
+        macros.toggle_recording();
+        macros.record(key('p'));
+
+        assert_eq!(macros.get_recording(), None);
+        assert_eq!(macros.get_macro('a'), Some(vec![key('i'), key('d')]));
+        assert_eq!(macros.get_macro('b'), None);
+    }
+}
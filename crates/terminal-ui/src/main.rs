@@ -1,9 +1,9 @@
 use std::error::Error;
 
-use terminal_ui::async_main;
+use terminal_ui::{async_main, TuiOptions};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    async_std::task::block_on(async_main(None))?;
+    async_std::task::block_on(async_main(TuiOptions::default()))?;
 
     Ok(())
 }
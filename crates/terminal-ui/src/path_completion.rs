@@ -0,0 +1,94 @@
+use std::{
+    fs,
+    path::{Path, MAIN_SEPARATOR},
+};
+
+/// Split a typed path into the directory to list and the prefix of the entry being typed
+fn split_path(input: &str) -> (String, String) {
+    match input.rfind(['/', MAIN_SEPARATOR]) {
+        Some(i) => (input[..=i].to_string(), input[i + 1..].to_string()),
+        None => (String::new(), input.to_string()),
+    }
+}
+
+/// Replace a leading `~` with the home directory
+fn expand_home(dir: &str) -> String {
+    match (dir.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) => format!("{}{}", home.display(), rest),
+        _ => dir.to_string(),
+    }
+}
+
+/// List the entries of the directory of `input` starting with the typed prefix, sorted by name.
+/// Directories are returned with a trailing separator so they can be completed further
+pub fn get_candidates(input: &str) -> Vec<String> {
+    let (dir, prefix) = split_path(input);
+    let expanded = expand_home(&dir);
+    let list_dir = if expanded.is_empty() { "." } else { expanded.as_str() };
+
+    let mut candidates: Vec<String> = match fs::read_dir(Path::new(list_dir)) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if !name.starts_with(&prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                    return None;
+                }
+                let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or_default();
+                Some(match is_dir {
+                    true => format!("{}{}{}", dir, name, MAIN_SEPARATOR),
+                    false => format!("{}{}", dir, name),
+                })
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    candidates.sort();
+    candidates
+}
+
+/// Complete `input` up to the longest prefix shared by all the candidates
+pub fn complete(input: &str, candidates: &[String]) -> String {
+    let mut common = match candidates.first() {
+        Some(first) => first.as_str(),
+        None => return input.to_string(),
+    };
+    for candidate in &candidates[1..] {
+        let shared = common
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map(|((i, _), _)| i)
+            .unwrap_or_else(|| common.len().min(candidate.len()));
+        common = &common[..shared];
+    }
+
+    match common.len() > input.len() {
+        true => common.to_string(),
+        false => input.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_is_completed_from_the_filesystem() {
+        let temp = test_utils::tempdir().unwrap();
+        let dir = temp.path();
+        fs::create_dir_all(dir.join("logs")).unwrap();
+        fs::write(dir.join("server.log"), "").unwrap();
+        fs::write(dir.join("server.log.1"), "").unwrap();
+
+        let input = format!("{}{}se", dir.display(), MAIN_SEPARATOR);
+        let candidates = get_candidates(&input);
+        let completed = complete(&input, &candidates);
+        let directories = get_candidates(&format!("{}{}l", dir.display(), MAIN_SEPARATOR));
+
+        assert_eq!(candidates.len(), 2);
+        assert!(completed.ends_with("server.log"));
+        assert_eq!(directories.len(), 1);
+        assert!(directories[0].ends_with(&format!("logs{}", MAIN_SEPARATOR)));
+    }
+}
@@ -0,0 +1,551 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::anyhow;
+use log_analyzer::{
+    error::{LogAnalyzerError, Result},
+    export_to_file,
+    models::{
+        alert::Alert,
+        column_filter::ColumnFilter,
+        diff::{Diff, DiffQuery},
+        export::ExportFormat,
+        filter::Filter,
+        format::Format,
+        gap::Gap,
+        log_line::LogLine,
+        log_line_styled::LogLineStyled,
+        memory_usage::MemoryUsage,
+        metrics::PipelineMetrics,
+        notification::{Notification, NotificationLevel},
+        rate_bucket::RateBucket,
+        redaction::Redaction,
+        session::Session,
+        severity::Severity,
+        sink::Sink,
+        source_stats::SourceStats,
+        statistics::Statistics,
+        top_values::TopValuesQuery,
+        transform::Transform,
+        watch::Watch,
+    },
+    services::{
+        api::ApiRequest,
+        log_service::{Event, LogAnalyzer},
+    },
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+/// Time between two polls of the events of the server
+const EVENTS_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Time after which a call to the server is abandoned
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Error of the transport to the server, as opposed to the errors returned by its analyzer
+fn remote_error(err: impl std::fmt::Display) -> LogAnalyzerError {
+    LogAnalyzerError::Remote(err.to_string())
+}
+
+/// `LogAnalyzer` running on a server started with `lap serve`, called through its HTTP API.
+///
+/// The logs are read and processed on the server, only the displayed lines are transferred.
+/// Failed calls are notified as events and return empty results
+pub struct RemoteAnalyzer {
+    url: String,
+    agent: ureq::Agent,
+    event_channel: broadcast::Sender<Event>,
+}
+
+impl RemoteAnalyzer {
+    /// Connect to the server at `url`, e.g. `http://127.0.0.1:7878`, and start polling its events
+    pub fn connect(url: &str) -> anyhow::Result<Arc<Self>> {
+        let (event_channel, _) = broadcast::channel(1_000_000_usize);
+        let remote = Arc::new(Self {
+            url: url.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+            event_channel,
+        });
+        remote.call(ApiRequest::GetLogs)?;
+
+        let poller = remote.clone();
+        std::thread::Builder::new()
+            .name("Remote events".to_string())
+            .spawn(move || poller.poll_events())?;
+
+        Ok(remote)
+    }
+
+    /// Forward the events of the server to the local channel, notifying once when the server
+    /// can't be reached
+    fn poll_events(&self) {
+        let mut last_id = 0;
+        let mut notified_unavailable = false;
+        loop {
+            let events = self
+                .agent
+                .get(&format!("{}/api/events", self.url))
+                .query("after", &last_id.to_string())
+                .call()
+                .map_err(|err| anyhow!(err))
+                .and_then(|response| Ok(response.into_string()?))
+                .and_then(|body| Ok(serde_json::from_str::<Vec<(u64, Event)>>(&body)?));
+
+            match events {
+                Ok(events) => {
+                    notified_unavailable = false;
+                    for (id, event) in events {
+                        last_id = id;
+                        self.event_channel.send(event).unwrap_or_default();
+                    }
+                }
+                Err(err) if !notified_unavailable => {
+                    notified_unavailable = true;
+                    self.notify_error(&format!("Unable to reach {}: {}", self.url, err));
+                }
+                Err(_) => {}
+            }
+            std::thread::sleep(EVENTS_POLL_INTERVAL);
+        }
+    }
+
+    /// Call a method of the server. The errors of the analyzer keep their kind, the others are
+    /// returned as `LogAnalyzerError::Remote`
+    fn call(&self, request: ApiRequest) -> Result<Value> {
+        let (method, params) = request.to_method().map_err(remote_error)?;
+        let url = format!("{}/api/{}", self.url, method);
+        let body = params.map(|params| params.to_string()).unwrap_or_default();
+
+        let response = match self.agent.post(&url).send_string(&body) {
+            Ok(response) => response.into_string().map_err(remote_error)?,
+            Err(ureq::Error::Status(_, response)) => {
+                let error: Value = response
+                    .into_string()
+                    .ok()
+                    .and_then(|body| serde_json::from_str(&body).ok())
+                    .unwrap_or_default();
+                return Err(serde_json::from_value(error["kind"].clone()).unwrap_or_else(|_| {
+                    LogAnalyzerError::Remote(
+                        error["error"]
+                            .as_str()
+                            .unwrap_or("Unknown error of the server")
+                            .to_string(),
+                    )
+                }));
+            }
+            Err(err) => {
+                return Err(LogAnalyzerError::Remote(format!(
+                    "Unable to reach {}: {}",
+                    self.url, err
+                )))
+            }
+        };
+
+        serde_json::from_str(&response).map_err(remote_error)
+    }
+
+    /// Call a method returning a value. Errors are notified and the default value returned
+    fn get<T: DeserializeOwned + Default>(&self, request: ApiRequest) -> T {
+        match self.call(request).and_then(|value| {
+            serde_json::from_value(value).map_err(remote_error)
+        }) {
+            Ok(value) => value,
+            Err(err) => {
+                self.notify_error(&err.to_string());
+                T::default()
+            }
+        }
+    }
+
+    /// Call a method without result. Errors are notified
+    fn run(&self, request: ApiRequest) {
+        if let Err(err) = self.call(request) {
+            self.notify_error(&err.to_string());
+        }
+    }
+
+    fn notify_error(&self, message: &str) {
+        self.event_channel
+            .send(Event::Notification(Notification::new(
+                NotificationLevel::Error,
+                message,
+            )))
+            .unwrap_or_default();
+    }
+}
+
+impl LogAnalyzer for RemoteAnalyzer {
+    fn add_log(
+        &self,
+        source_type: usize,
+        source_address: &str,
+        format: Option<&String>,
+    ) -> Result<()> {
+        self.call(ApiRequest::AddLog {
+            source_type,
+            address: source_address.to_string(),
+            format: format.cloned(),
+        })?;
+        Ok(())
+    }
+
+    fn replay_log(&self, path: &str, format: Option<&String>, speed: f64) -> Result<()> {
+        self.call(ApiRequest::ReplayLog {
+            path: path.to_string(),
+            format: format.cloned(),
+            speed,
+        })?;
+        Ok(())
+    }
+
+    fn add_format(&self, alias: &str, regex: &str) -> Result<()> {
+        self.call(ApiRequest::AddFormat {
+            alias: alias.to_string(),
+            regex: regex.to_string(),
+        })?;
+        Ok(())
+    }
+
+    fn add_search(&self, regex: &str) {
+        self.run(ApiRequest::AddSearch {
+            regex: regex.to_string(),
+        })
+    }
+
+    fn add_filter(&self, filter: Filter) {
+        self.run(ApiRequest::AddFilter { filter })
+    }
+
+    fn remove_format(&self, alias: &str) -> Result<()> {
+        self.call(ApiRequest::RemoveFormat {
+            alias: alias.to_string(),
+        })?;
+        Ok(())
+    }
+
+    fn rename_format(&self, alias: &str, new_alias: &str) -> Result<()> {
+        self.call(ApiRequest::RenameFormat {
+            alias: alias.to_string(),
+            new_alias: new_alias.to_string(),
+        })?;
+        Ok(())
+    }
+
+    fn remove_filter(&self, alias: &str) -> Result<()> {
+        self.call(ApiRequest::RemoveFilter {
+            alias: alias.to_string(),
+        })?;
+        Ok(())
+    }
+
+    fn rename_filter(&self, alias: &str, new_alias: &str) -> Result<()> {
+        self.call(ApiRequest::RenameFilter {
+            alias: alias.to_string(),
+            new_alias: new_alias.to_string(),
+        })?;
+        Ok(())
+    }
+
+    fn get_log_lines(&self, from: usize, to: usize) -> Vec<Arc<LogLine>> {
+        self.get(ApiRequest::GetLogLines { from, to })
+    }
+
+    fn get_search_lines(&self, from: usize, to: usize) -> Vec<LogLineStyled> {
+        self.get(ApiRequest::GetSearchLines { from, to })
+    }
+
+    fn get_log_lines_containing(
+        &self,
+        index: usize,
+        elements: usize,
+    ) -> (Vec<Arc<LogLine>>, usize, usize) {
+        self.get(ApiRequest::GetLogLinesContaining { index, elements })
+    }
+
+    fn get_statistics(&self, buckets: usize, top: usize) -> Statistics {
+        self.get(ApiRequest::GetStatistics { buckets, top })
+    }
+
+    fn get_column_values(&self, column: &str, top: usize) -> Result<Vec<(String, usize)>> {
+        let values = self.call(ApiRequest::GetColumnValues {
+            column: column.to_string(),
+            top,
+        })?;
+        serde_json::from_value(values).map_err(remote_error)
+    }
+
+    fn get_rate_histogram(&self, bucket: Duration) -> Vec<RateBucket> {
+        self.get(ApiRequest::GetRateHistogram { bucket })
+    }
+
+    fn get_top_values(&self, query: &TopValuesQuery) -> Result<Vec<(String, usize)>> {
+        let values = self.call(ApiRequest::GetTopValues {
+            query: query.clone(),
+        })?;
+        serde_json::from_value(values).map_err(remote_error)
+    }
+
+    fn get_gaps(&self, min_gap: Duration) -> Vec<Gap> {
+        self.get(ApiRequest::GetGaps { min_gap })
+    }
+
+    fn get_index_at_time(&self, time: f64) -> Option<usize> {
+        self.get(ApiRequest::GetIndexAtTime { time })
+    }
+
+    fn set_correlation_key(&self, key: Option<&str>) -> Result<()> {
+        self.call(ApiRequest::SetCorrelationKey {
+            key: key.map(|key| key.to_string()),
+        })?;
+        Ok(())
+    }
+
+    fn get_correlation_id(&self, index: usize) -> Option<String> {
+        self.get(ApiRequest::GetCorrelationId { index })
+    }
+
+    fn get_correlated_lines(&self, id: &str) -> Vec<LogLine> {
+        self.get(ApiRequest::GetCorrelatedLines { id: id.to_string() })
+    }
+
+    fn get_diff(&self, query: &DiffQuery) -> Diff {
+        self.get(ApiRequest::GetDiff {
+            query: query.clone(),
+        })
+    }
+
+    fn set_alert(&self, filter: &str, alert: Option<Alert>) -> Result<()> {
+        self.call(ApiRequest::SetAlert {
+            filter: filter.to_string(),
+            alert,
+        })?;
+        Ok(())
+    }
+
+    fn get_alerts(&self) -> Vec<(String, Alert)> {
+        self.get(ApiRequest::GetAlerts)
+    }
+
+    fn set_sink(&self, filter: &str, sink: Option<Sink>) -> Result<()> {
+        self.call(ApiRequest::SetSink {
+            filter: filter.to_string(),
+            sink,
+        })?;
+        Ok(())
+    }
+
+    fn get_sinks(&self) -> Vec<(String, Sink)> {
+        self.get(ApiRequest::GetSinks)
+    }
+
+    fn add_watch(&self, expression: &str) -> Result<()> {
+        self.call(ApiRequest::AddWatch {
+            expression: expression.to_string(),
+        })?;
+        Ok(())
+    }
+
+    fn remove_watch(&self, expression: &str) -> Result<()> {
+        self.call(ApiRequest::RemoveWatch {
+            expression: expression.to_string(),
+        })?;
+        Ok(())
+    }
+
+    fn get_watches(&self) -> Vec<Watch> {
+        self.get(ApiRequest::GetWatches)
+    }
+
+    fn set_transforms(&self, transforms: Vec<Transform>) -> Result<()> {
+        self.call(ApiRequest::SetTransforms { transforms })?;
+        Ok(())
+    }
+
+    fn get_transforms(&self) -> Vec<Transform> {
+        self.get(ApiRequest::GetTransforms)
+    }
+
+    fn set_redactions(&self, redactions: Vec<Redaction>) -> Result<()> {
+        self.call(ApiRequest::SetRedactions { redactions })?;
+        Ok(())
+    }
+
+    fn get_redactions(&self) -> Vec<Redaction> {
+        self.get(ApiRequest::GetRedactions)
+    }
+
+    fn set_show_originals(&self, show: bool) {
+        self.run(ApiRequest::SetShowOriginals { show })
+    }
+
+    fn is_showing_originals(&self) -> bool {
+        self.get(ApiRequest::IsShowingOriginals)
+    }
+
+    // The server doesn't write files for its clients, the lines are fetched and written here
+
+    fn export_filtered(&self, path: &str, format: ExportFormat) -> Result<usize> {
+        let total = self.get_total_filtered_lines();
+        export_to_file(path, format, "Filtered log", total, |from, to| {
+            self.get_log_lines(from, to)
+                .iter()
+                .map(|line| LogLineStyled::from(&**line))
+                .collect()
+        })
+    }
+
+    fn export_search(&self, path: &str, format: ExportFormat) -> Result<usize> {
+        let title = match self.get_session().search {
+            Some(query) => format!("Search results for {}", query),
+            None => "Search results".to_string(),
+        };
+        let total = self.get_total_searched_lines();
+        export_to_file(path, format, &title, total, |from, to| {
+            self.get_search_lines(from, to)
+        })
+    }
+
+    fn save_snapshot(&self, path: &str, session: Session) -> Result<usize> {
+        let lines = self.call(ApiRequest::SaveSnapshot {
+            path: path.to_string(),
+            session,
+        })?;
+        serde_json::from_value(lines).map_err(remote_error)
+    }
+
+    fn load_snapshot(&self, path: &str) -> Result<Session> {
+        let session = self.call(ApiRequest::LoadSnapshot {
+            path: path.to_string(),
+        })?;
+        serde_json::from_value(session).map_err(remote_error)
+    }
+
+    fn resolve_raw_index(&self, source: Option<&str>, raw_index: usize) -> Option<usize> {
+        self.get(ApiRequest::ResolveRawIndex {
+            source: source.map(str::to_string),
+            raw_index,
+        })
+    }
+
+    fn set_viewport(&self, source: &str, raw_index: usize) {
+        self.run(ApiRequest::SetViewport {
+            source: source.to_string(),
+            raw_index,
+        })
+    }
+
+    fn get_search_lines_containing(
+        &self,
+        index: usize,
+        elements: usize,
+    ) -> (Vec<LogLineStyled>, usize, usize) {
+        self.get(ApiRequest::GetSearchLinesContaining { index, elements })
+    }
+
+    fn get_logs(&self) -> Vec<(bool, String, Option<String>)> {
+        self.get(ApiRequest::GetLogs)
+    }
+
+    fn get_source_plugins(&self) -> Vec<String> {
+        self.get(ApiRequest::GetSourcePlugins)
+    }
+
+    fn get_formats(&self) -> Vec<Format> {
+        self.get(ApiRequest::GetFormats)
+    }
+
+    fn get_filters(&self) -> Vec<(bool, Filter)> {
+        self.get(ApiRequest::GetFilters)
+    }
+
+    fn get_total_raw_lines(&self) -> usize {
+        self.get(ApiRequest::GetTotalRawLines)
+    }
+
+    fn get_total_filtered_lines(&self) -> usize {
+        self.get(ApiRequest::GetTotalFilteredLines)
+    }
+
+    fn get_total_searched_lines(&self) -> usize {
+        self.get(ApiRequest::GetTotalSearchedLines)
+    }
+
+    fn get_filtered_lines_per_source(&self) -> Vec<(String, usize)> {
+        self.get(ApiRequest::GetFilteredLinesPerSource)
+    }
+
+    fn get_source_log_lines(&self, source: &str, limit: usize) -> Vec<LogLine> {
+        self.get(ApiRequest::GetSourceLogLines {
+            source: source.to_string(),
+            limit,
+        })
+    }
+
+    fn get_raw_line(&self, source: &str, raw_index: usize) -> Option<String> {
+        self.get(ApiRequest::GetRawLine {
+            source: source.to_string(),
+            raw_index,
+        })
+    }
+
+    fn get_memory_usage(&self) -> MemoryUsage {
+        self.get(ApiRequest::GetMemoryUsage)
+    }
+
+    fn get_metrics(&self) -> PipelineMetrics {
+        self.get(ApiRequest::GetMetrics)
+    }
+
+    fn get_source_stats(&self) -> Vec<SourceStats> {
+        self.get(ApiRequest::GetSourceStats)
+    }
+
+    fn toggle_source(&self, id: &str) {
+        self.run(ApiRequest::ToggleSource { id: id.to_string() })
+    }
+
+    fn toggle_filter(&self, id: &str) {
+        self.run(ApiRequest::ToggleFilter { id: id.to_string() })
+    }
+
+    fn toggle_severity(&self, severity: Severity) {
+        self.run(ApiRequest::ToggleSeverity { severity })
+    }
+
+    fn get_hidden_severities(&self) -> Vec<Severity> {
+        self.get(ApiRequest::GetHiddenSeverities)
+    }
+
+    fn add_column_filter(&self, column_filter: ColumnFilter) {
+        self.run(ApiRequest::AddColumnFilter { column_filter })
+    }
+
+    fn remove_column_filter(&self, column: &str) {
+        self.run(ApiRequest::RemoveColumnFilter {
+            column: column.to_string(),
+        })
+    }
+
+    fn get_column_filters(&self) -> Vec<ColumnFilter> {
+        self.get(ApiRequest::GetColumnFilters)
+    }
+
+    fn get_session(&self) -> Session {
+        self.get(ApiRequest::GetSession)
+    }
+
+    fn restore_session(&self, session: &Session) -> Result<()> {
+        // The session is moved into the request, serializing it is the simplest way to clone it
+        let session = session.to_json().and_then(|json| Session::from_json(&json))?;
+        self.call(ApiRequest::RestoreSession { session })?;
+        Ok(())
+    }
+
+    fn clear_all(&self) {
+        self.run(ApiRequest::ClearAll)
+    }
+
+    fn on_event(&self) -> broadcast::Receiver<Event> {
+        self.event_channel.subscribe()
+    }
+}
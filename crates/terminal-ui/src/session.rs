@@ -0,0 +1,247 @@
+use anyhow::Result;
+use crate::env_overrides::ENV_SETTINGS;
+use log_analyzer::models::{recent_sources::RecentSources, session::Session, settings::Settings};
+use serde_json::{Map, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Folder of the application inside the platform config directory
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("log-analyzer-pro"))
+}
+
+/// File where the session is periodically saved
+pub fn autosave_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("session.json"))
+}
+
+/// Settings file used when no other is given
+pub fn default_settings_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("settings.json"))
+}
+
+/// The given settings file or, if none, the one of the `LAP_SETTINGS` environment variable,
+/// or the default one when it exists
+pub fn settings_path_or_default(path: Option<String>) -> Option<String> {
+    path.or_else(|| std::env::var(ENV_SETTINGS).ok()).or_else(|| {
+        default_settings_path()
+            .filter(|path| path.is_file())
+            .map(|path| path.to_string_lossy().to_string())
+    })
+}
+
+/// File where the recently added sources are remembered
+pub fn recent_sources_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("recent_sources.json"))
+}
+
+/// Load the session stored in the file, if any
+pub fn load_session(path: &Path) -> Option<Session> {
+    let json = fs::read_to_string(path).ok()?;
+    Session::from_json(&json).ok()
+}
+
+/// Write the session to the file. The content is written to a temporary file first
+/// so a crash while saving never leaves a corrupted session behind
+pub fn save_session(path: &Path, session: &Session) -> Result<()> {
+    write_atomically(path, &session.to_json()?)
+}
+
+/// Load the recent sources stored in the file, if any
+pub fn load_recent_sources(path: &Path) -> Option<RecentSources> {
+    let json = fs::read_to_string(path).ok()?;
+    RecentSources::from_json(&json).ok()
+}
+
+/// Write the recent sources to the file
+pub fn save_recent_sources(path: &Path, recent: &RecentSources) -> Result<()> {
+    write_atomically(path, &recent.to_json()?)
+}
+
+/// Keys of the settings file edited by the settings editor
+const DISPLAY_OPTIONS: [&str; 5] = [
+    "primary_color",
+    "stripe_color",
+    "memory_warning_mb",
+    "memory_critical_mb",
+    "tick_rate_ms",
+];
+
+/// Write the display options to the settings file. The rest of the file is kept as written,
+/// including the palette color names used by its filters
+pub fn save_settings(path: &Path, options: &Settings) -> Result<()> {
+    let mut settings = match fs::read_to_string(path) {
+        Ok(json) => {
+            // Refuse to overwrite a file that can't be loaded
+            Settings::from_json(&json)?;
+            serde_json::from_str(&json)?
+        }
+        Err(_) => Value::Object(Map::new()),
+    };
+    let options = serde_json::to_value(options)?;
+    if let Some(settings) = settings.as_object_mut() {
+        for key in DISPLAY_OPTIONS {
+            settings.insert(key.to_string(), options[key].clone());
+        }
+    }
+    write_atomically(path, &serde_json::to_string_pretty(&settings)?)
+}
+
+/// Write the formats and filters of the session with the display options to the settings file.
+/// Sources already in the file are kept
+pub fn save_configuration(path: &Path, options: &Settings, session: Session) -> Result<()> {
+    let sources = match fs::read_to_string(path) {
+        Ok(json) => Settings::from_json(&json)?.sources,
+        Err(_) => None,
+    };
+    let settings = Settings {
+        sources,
+        ..configuration(options, session)
+    };
+    write_atomically(path, &settings.to_json()?)
+}
+
+/// Write the sources, formats and filters of the session with the display options to the
+/// settings file, replacing it
+pub fn export_settings(path: &Path, options: &Settings, session: Session) -> Result<()> {
+    write_atomically(path, &configuration(options, session).to_json()?)
+}
+
+/// Settings with the display options and the sources, formats and filters of the session
+fn configuration(options: &Settings, session: Session) -> Settings {
+    let options = Settings {
+        primary_color: options.primary_color,
+        stripe_color: options.stripe_color,
+        memory_warning_mb: options.memory_warning_mb,
+        memory_critical_mb: options.memory_critical_mb,
+        tick_rate_ms: options.tick_rate_ms,
+        palette: options.palette.clone(),
+        burst_detection: options.burst_detection,
+        correlation_key: options.correlation_key.clone(),
+        watches: options.watches.clone(),
+        transforms: options.transforms.clone(),
+        redactions: options.redactions.clone(),
+        retention: options.retention,
+        overload_policy: options.overload_policy,
+        pipeline: options.pipeline,
+        max_line_length: options.max_line_length,
+        invalid_utf8: options.invalid_utf8,
+        file_retry: options.file_retry,
+        dedupe: options.dedupe,
+        plugins_dir: options.plugins_dir.clone(),
+        ..Default::default()
+    };
+    options.with_session(session)
+}
+
+fn write_atomically(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, content)?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use log_analyzer::models::{format::Format, redaction::Redaction, retention::Retention};
+    use test_utils::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn saved_session_can_be_loaded() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        let session = Session {
+            search: Some("error".into()),
+            ..Default::default()
+        };
+
+        save_session(&path, &session).unwrap();
+        let loaded = load_session(&path).unwrap();
+
+        assert_eq!(loaded.search.as_deref(), Some("error"));
+    }
+
+    #[test]
+    fn saved_settings_keep_the_formats() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(
+            &path,
+            r#"{
+                "formats": [{"alias": "Default", "regex": "(?P<PAYLOAD>.*)"}],
+                "palette": {"critical": [255, 0, 0]},
+                "filters": [{"alias": "Errors", "action": "MARKER", "filter": {"color": "critical"}}],
+                "tick_rate_ms": 100
+            }"#,
+        )
+        .unwrap();
+        let options = Settings {
+            primary_color: Some((200, 200, 0)),
+            ..Default::default()
+        };
+
+        save_settings(&path, &options).unwrap();
+        let json = fs::read_to_string(&path).unwrap();
+        let saved = Settings::from_json(&json).unwrap();
+
+        assert_eq!(saved.formats.unwrap().len(), 1);
+        assert!(json.contains(r#""color": "critical""#));
+        assert_eq!(saved.primary_color, Some((200, 200, 0)));
+        assert_eq!(saved.tick_rate_ms, None);
+    }
+
+    #[test]
+    fn saved_configuration_replaces_the_formats_and_filters() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(
+            &path,
+            r#"{
+                "formats": [{"alias": "Old", "regex": "(?P<PAYLOAD>.*)"}],
+                "sources": [{"enabled": true, "source_type": 0, "address": "app.log", "format": null}]
+            }"#,
+        )
+        .unwrap();
+        let options = Settings {
+            tick_rate_ms: Some(100),
+            watches: Some(vec!["payload:retry".to_string()]),
+            redactions: Some(vec![Redaction::new("token=\\w+")]),
+            retention: Some(Retention {
+                max_lines: Some(100_000),
+                max_age_secs: None,
+            }),
+            ..Default::default()
+        };
+        let session = || Session {
+            formats: vec![Format::new("New", "(?P<PAYLOAD>.*)").unwrap()],
+            ..Default::default()
+        };
+
+        save_configuration(&path, &options, session()).unwrap();
+        let saved = Settings::from_json(&fs::read_to_string(&path).unwrap()).unwrap();
+        // The export writes the sources of the session instead
+        let exported = path.with_file_name("exported.json");
+        export_settings(&exported, &options, session()).unwrap();
+        let exported = Settings::from_json(&fs::read_to_string(&exported).unwrap()).unwrap();
+
+        assert!(exported.sources.unwrap().is_empty());
+        assert_eq!(exported.formats.unwrap()[0].alias, "New");
+        assert_eq!(exported.tick_rate_ms, Some(100));
+
+        let formats = saved.formats.unwrap();
+        assert_eq!(formats.len(), 1);
+        assert_eq!(formats[0].alias, "New");
+        assert_eq!(saved.sources.unwrap().len(), 1);
+        assert_eq!(saved.tick_rate_ms, Some(100));
+        assert_eq!(saved.watches, Some(vec!["payload:retry".to_string()]));
+        assert_eq!(saved.redactions.unwrap()[0].regex, "token=\\w+");
+        assert_eq!(saved.retention.unwrap().max_lines, Some(100_000));
+    }
+}
@@ -15,3 +15,18 @@ pub const ERROR_STYLE: Style = Style {
     add_modifier: Modifier::BOLD,
     sub_modifier: Modifier::empty(),
 };
+
+pub const WARNING_STYLE: Style = Style {
+    fg: Some(Color::Yellow),
+    bg: None,
+    add_modifier: Modifier::BOLD,
+    sub_modifier: Modifier::empty(),
+};
+
+/// Background of a table row when zebra striping is enabled. Odd rows are striped
+pub fn stripe_style(stripe_color: Option<Color>, row: usize) -> Style {
+    match stripe_color {
+        Some(color) if row % 2 == 1 => Style::default().bg(color),
+        _ => Style::default(),
+    }
+}
@@ -4,5 +4,16 @@ pub mod ui_filter_popup;
 pub mod ui_loading_popup;
 pub mod ui_navigation_popup;
 pub mod ui_error_message;
+pub mod ui_restore_popup;
+pub mod ui_notifications_popup;
+pub mod ui_settings_popup;
+pub mod ui_path_popup;
+pub mod ui_top_values_popup;
+pub mod ui_gaps_popup;
+pub mod ui_transaction_popup;
+pub mod ui_diff_popup;
+pub mod ui_watches_popup;
+pub mod ui_line_popup;
+pub mod ui_console_popup;
 pub mod ui_popup;
 pub mod ui_shared;
\ No newline at end of file
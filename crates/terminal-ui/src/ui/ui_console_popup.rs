@@ -0,0 +1,84 @@
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout},
+    style::Style,
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::{
+    app::{App, INDEX_CONSOLE_SCRIPT},
+    console::ConsoleEntry,
+    styles::{selected_style, ERROR_STYLE},
+};
+
+use super::{ui_popup::centered_rect, ui_shared::display_cursor};
+
+pub fn draw_console_popup<B>(f: &mut Frame<B>, app: &mut App)
+where
+    B: Backend,
+{
+    let block = Block::default()
+        .title("Console (Rhai), help() lists the functions")
+        .borders(Borders::ALL)
+        .border_style(selected_style(app.color));
+
+    let area = centered_rect(80, 30, f.size());
+    f.render_widget(Clear, area); //this clears out the background
+    f.render_widget(block, area);
+
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Min(1),
+                Constraint::Length(3),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .margin(1)
+        .split(area);
+
+    let rows: Vec<Spans> = app
+        .console
+        .entries()
+        .iter()
+        .flat_map(|entry| {
+            let (text, prompt, style) = match entry {
+                ConsoleEntry::Script(script) => (script, "> ", Style::default().fg(app.color)),
+                ConsoleEntry::Output(output) => (output, "", Style::default()),
+                ConsoleEntry::Error(err) => (err, "", ERROR_STYLE),
+            };
+            text.lines()
+                .enumerate()
+                .map(|(i, row)| {
+                    let prompt = if i == 0 { prompt } else { "" };
+                    Spans::from(Span::styled(format!("{}{}", prompt, row), style))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let height = popup_layout[0].height as usize;
+    app.console_scroll = app.console_scroll.min(rows.len().saturating_sub(height));
+    let first = rows.len().saturating_sub(height + app.console_scroll);
+    let output = rows.into_iter().skip(first).take(height).collect::<Vec<_>>();
+    f.render_widget(Paragraph::new(output), popup_layout[0]);
+
+    let script_widget = Paragraph::new(app.input_buffers[INDEX_CONSOLE_SCRIPT].value())
+        .style(selected_style(app.color))
+        .block(Block::default().borders(Borders::ALL).title("Script"));
+    f.render_widget(script_widget, popup_layout[1]);
+    display_cursor(
+        f,
+        popup_layout[1],
+        app.input_buffers[INDEX_CONSOLE_SCRIPT].cursor(),
+    );
+
+    let help = Paragraph::new(
+        "⏎ Enter to run, ↑/↓ to recall the scripts, PageUp/PageDown to scroll, Esc to close",
+    )
+    .alignment(Alignment::Center);
+    f.render_widget(help, popup_layout[2]);
+}
@@ -0,0 +1,150 @@
+use crate::{
+    app::{
+        App, INDEX_DIFF_LEFT_RANGE, INDEX_DIFF_LEFT_SOURCE, INDEX_DIFF_RIGHT_RANGE,
+        INDEX_DIFF_RIGHT_SOURCE,
+    },
+    styles::{selected_style, ERROR_STYLE},
+};
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table},
+    Frame,
+};
+
+use super::{ui_popup::centered_rect, ui_shared::format_clock};
+
+fn draw_option<B>(f: &mut Frame<B>, app: &App, area: Rect, index: usize, title: &str, value: &str)
+where
+    B: Backend,
+{
+    let option = Paragraph::new(format!("◀ {} ▶", value))
+        .style(match index == app.input_buffer_index {
+            false => Style::default(),
+            true => selected_style(app.color),
+        })
+        .block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(option, area);
+}
+
+fn draw_side<B>(f: &mut Frame<B>, app: &App, area: Rect, side: usize)
+where
+    B: Backend,
+{
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Length(3)].as_ref())
+        .split(area);
+
+    let (name, source_index, range_index) = match side {
+        0 => ("Left (-)", INDEX_DIFF_LEFT_SOURCE, INDEX_DIFF_LEFT_RANGE),
+        _ => ("Right (+)", INDEX_DIFF_RIGHT_SOURCE, INDEX_DIFF_RIGHT_RANGE),
+    };
+    let source = match app.get_diff_source(side) {
+        Some(source) => source.as_str(),
+        None => "All sources",
+    };
+    let range = match app.diff_ranges[side] {
+        Some((start, end)) => format!(
+            "{} → {}",
+            format_clock(start as u64),
+            format_clock(end as u64)
+        ),
+        None => "All".to_string(),
+    };
+
+    draw_option(f, app, layout[0], source_index, &format!("{} source", name), source);
+    draw_option(f, app, layout[1], range_index, &format!("{} time", name), &range);
+}
+
+fn draw_lines<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
+where
+    B: Backend,
+{
+    let header_cells = ["", "Index", "Log", "Timestamp", "Severity", "Payload"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Black)));
+    let header = Row::new(header_cells)
+        .style(Style::default().bg(app.color).add_modifier(Modifier::BOLD))
+        .bottom_margin(1);
+
+    let rows = app.diff_lines.items.iter().map(|(side, line)| {
+        let style = match side {
+            '-' => Style::default().fg(Color::Red),
+            _ => Style::default().fg(Color::Green),
+        };
+        Row::new(vec![
+            Cell::from(side.to_string()),
+            Cell::from(line.index.to_string()),
+            Cell::from(line.log.to_string()),
+            Cell::from(line.timestamp.to_string()),
+            Cell::from(line.severity.to_string()),
+            Cell::from(line.payload.to_string()),
+        ])
+        .style(style)
+    });
+
+    let t = Table::new(rows)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            "{} lines on one side only, {} on both",
+            app.diff_lines.items.len(),
+            app.diff_common
+        )))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(&[
+            Constraint::Length(1),
+            Constraint::Length(8),
+            Constraint::Length(16),
+            Constraint::Length(14),
+            Constraint::Length(10),
+            Constraint::Percentage(100),
+        ]);
+    f.render_stateful_widget(t, area, &mut app.diff_lines.state);
+}
+
+pub fn draw_diff_popup<B>(f: &mut Frame<B>, app: &mut App)
+where
+    B: Backend,
+{
+    let block = Block::default()
+        .title("Diff")
+        .borders(Borders::ALL)
+        .border_style(selected_style(app.color));
+
+    let area = centered_rect(80, 70, f.size());
+    f.render_widget(Clear, area); //this clears out the background
+    f.render_widget(block, area);
+
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(6),
+                Constraint::Min(3),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .margin(1)
+        .split(area);
+
+    let sides_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(popup_layout[0]);
+
+    draw_side(f, app, sides_layout[0], 0);
+    draw_side(f, app, sides_layout[1], 1);
+    draw_lines(f, app, popup_layout[1]);
+
+    let help = match &app.diff_error {
+        Some(err) => Paragraph::new(err.as_str()).style(ERROR_STYLE),
+        None => Paragraph::new(
+            "⇥ Tab to select an option, ←/→ to change it (time: between the Shift + T marks), ↑/↓ to select a line, ⏎ Enter to go to it, Esc to close",
+        ),
+    }
+    .alignment(Alignment::Center);
+    f.render_widget(help, popup_layout[2]);
+}
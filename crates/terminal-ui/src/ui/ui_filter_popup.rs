@@ -86,14 +86,11 @@ where
     draw_input_field(f, app, color_layout[2], INDEX_FILTER_BLUE_COLOR, "Blue");
 
     let w = Block::default().borders(Borders::ALL);
-    let color = match parse_color(
+    let color = app.color_support.color(parse_color(
         app.input_buffers[INDEX_FILTER_RED_COLOR].value(),
         app.input_buffers[INDEX_FILTER_GREEN_COLOR].value(),
         app.input_buffers[INDEX_FILTER_BLUE_COLOR].value(),
-    ) {
-        Some((r, g, b)) => Color::Rgb(r, g, b),
-        _ => Color::Reset,
-    };
+    ));
 
     //let (r, g, b) = app.input_buffers[INDEX_FILTER_RED_COLOR].value().parse(), app.input_buffers[INDEX_FILTER_GREEN_COLOR].value(), app.input_buffers[INDEX_FILTER_BLUE_COLOR].value()
     let w_color = Paragraph::new(if color == Color::Reset {
@@ -0,0 +1,125 @@
+use crate::{
+    app::{App, INDEX_GAPS_MARKERS, INDEX_GAPS_MIN},
+    styles::{selected_style, ERROR_STYLE},
+};
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, Tabs},
+    Frame,
+};
+
+use super::{
+    ui_popup::centered_rect,
+    ui_shared::{display_cursor, format_duration},
+};
+
+fn draw_options<B>(f: &mut Frame<B>, app: &App, area: Rect)
+where
+    B: Backend,
+{
+    let layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(area);
+
+    let min_selected = INDEX_GAPS_MIN == app.input_buffer_index;
+    let min_widget = Paragraph::new(app.input_buffers[INDEX_GAPS_MIN].value())
+        .style(match min_selected {
+            false => Style::default(),
+            true => selected_style(app.color),
+        })
+        .block(Block::default().borders(Borders::ALL).title("Minimum gap (s)"));
+    f.render_widget(min_widget, layout[0]);
+    if min_selected {
+        display_cursor(f, layout[0], app.input_buffers[INDEX_GAPS_MIN].cursor())
+    }
+
+    let titles = ["OFF", "ON"]
+        .iter()
+        .map(|t| Spans::from(vec![Span::styled(*t, Style::default().fg(Color::White))]))
+        .collect();
+    let markers_widget = Tabs::new(titles)
+        .block(Block::default().borders(Borders::ALL).title("Markers in the log"))
+        .select(app.gap_markers as usize)
+        .style(match INDEX_GAPS_MARKERS == app.input_buffer_index {
+            false => Style::default(),
+            true => selected_style(app.color),
+        })
+        .highlight_style(selected_style(app.color));
+    f.render_widget(markers_widget, layout[1]);
+}
+
+fn draw_gaps<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
+where
+    B: Backend,
+{
+    let header_cells = ["Source", "Line", "Duration"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Black)));
+    let header = Row::new(header_cells)
+        .style(Style::default().bg(app.color).add_modifier(Modifier::BOLD))
+        .bottom_margin(1);
+
+    let rows = app.gaps.items.iter().map(|gap| {
+        Row::new(vec![
+            Cell::from(gap.source.as_str()),
+            Cell::from(format!("{} → {}", gap.before, gap.after)),
+            Cell::from(format_duration(gap.duration())),
+        ])
+    });
+
+    let t = Table::new(rows)
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("{} gaps", app.gaps.items.len())),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(&[
+            Constraint::Percentage(50),
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
+        ]);
+    f.render_stateful_widget(t, area, &mut app.gaps.state);
+}
+
+pub fn draw_gaps_popup<B>(f: &mut Frame<B>, app: &mut App)
+where
+    B: Backend,
+{
+    let block = Block::default()
+        .title("Gaps between the lines of each source")
+        .borders(Borders::ALL)
+        .border_style(selected_style(app.color));
+
+    let area = centered_rect(60, 24, f.size());
+    f.render_widget(Clear, area); //this clears out the background
+    f.render_widget(block, area);
+
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Min(3),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .margin(1)
+        .split(area);
+
+    draw_options(f, app, popup_layout[0]);
+    draw_gaps(f, app, popup_layout[1]);
+
+    let help = match &app.gaps_error {
+        Some(err) => Paragraph::new(err.as_str()).style(ERROR_STYLE),
+        None => Paragraph::new("↑/↓ to select, ⏎ Enter to go to the line, ⇥ Tab to switch options, Esc to close"),
+    }
+    .alignment(Alignment::Center);
+    f.render_widget(help, popup_layout[2]);
+}
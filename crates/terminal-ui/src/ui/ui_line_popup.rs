@@ -0,0 +1,54 @@
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::{app::App, styles::selected_style};
+
+use super::{
+    ui_popup::centered_rect,
+    ui_shared::{format_bytes, wrap_rows},
+};
+
+pub fn draw_line_popup<B>(f: &mut Frame<B>, app: &mut App)
+where
+    B: Backend,
+{
+    let block = Block::default()
+        .title(format!(
+            "{} - {}",
+            app.raw_line_title,
+            format_bytes(app.raw_line.len())
+        ))
+        .borders(Borders::ALL)
+        .border_style(selected_style(app.color));
+
+    let area = centered_rect(80, 30, f.size());
+    f.render_widget(Clear, area); //this clears out the background
+    f.render_widget(block, area);
+
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)].as_ref())
+        .margin(1)
+        .split(area);
+
+    let width = popup_layout[0].width as usize;
+    let height = popup_layout[0].height as usize;
+    let rows = app.raw_line_chars.div_ceil(width.max(1));
+    app.raw_line_scroll = app.raw_line_scroll.min(rows.saturating_sub(height));
+
+    let text = wrap_rows(&app.raw_line, width, app.raw_line_scroll, height).join("\n");
+    f.render_widget(Paragraph::new(text), popup_layout[0]);
+
+    let help = Paragraph::new(format!(
+        "Rows {}-{} of {}, ↑/↓ PageUp/PageDown to scroll, Esc to close",
+        (app.raw_line_scroll + 1).min(rows),
+        (app.raw_line_scroll + height).min(rows),
+        rows
+    ))
+    .alignment(Alignment::Center);
+    f.render_widget(help, popup_layout[1]);
+}
@@ -1,19 +1,38 @@
-use log_analyzer::models::{log_line::LogLine, log_line_styled::LogLineStyled};
+use std::{borrow::Cow, collections::BTreeMap};
+
+use log_analyzer::models::{
+    log_line::LogLine, log_line_styled::LogLineStyled, notification::NotificationLevel,
+    severity::Severity, source_stats::SourceStats,
+};
 use tui::{
     backend::Backend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans, Text},
-    widgets::{Block, Borders, Cell, Gauge, Paragraph, Row, Table},
+    widgets::{Block, Borders, Cell, Gauge, Paragraph, Row, Sparkline, Table},
     Frame,
 };
 
 use crate::{
-    app::{App, Module, INDEX_SEARCH},
-    styles::selected_style,
+    colors::ColorSupport,
+    app::{App, GroupRow, Module, INDEX_COLUMN_FILTER, INDEX_SEARCH},
+    styles::{selected_style, stripe_style, ERROR_STYLE, WARNING_STYLE},
+};
+
+use super::ui_shared::{
+    display_cursor, format_bytes, format_duration, format_lag, format_thousands,
 };
 
-use super::ui_shared::display_cursor;
+/// Maximum number of rows displayed by the pinned lines panel before scrolling
+const MAX_PINNED_ROWS: usize = 5;
+/// Number of apps listed in the statistics panel
+const MAX_TOP_APPS: usize = 5;
+/// Rows of the statistics panel: both rates, severities and top apps
+const STATISTICS_HEIGHT: u16 = 11 + MAX_TOP_APPS as u16;
+/// Number of watches displayed by the watches panel
+const MAX_WATCH_ROWS: usize = 4;
+/// Seconds averaged by the rate of the watches
+pub const WATCH_RATE_SECS: usize = 10;
 
 trait Convert<T> {
     fn from_str(s: &str) -> Option<T>;
@@ -58,7 +77,13 @@ where
     let selected_style = Style::default().add_modifier(Modifier::REVERSED);
     let normal_style = Style::default().bg(app.color).add_modifier(Modifier::BOLD);
 
-    let header_cells = ["Enabled", "Log", "Format"]
+    let stats: BTreeMap<&str, SourceStats> = app
+        .source_stats
+        .iter()
+        .map(|stats| (stats.source.as_str(), stats.clone()))
+        .collect();
+
+    let header_cells = ["Enabled", "Log", "Format", "Raw", "Filtered", "Search"]
         .iter()
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Black)));
     let header = Row::new(header_cells).style(normal_style).bottom_margin(1);
@@ -73,10 +98,23 @@ where
             _ => "",
         };
 
+        let counts = stats.get(item.1.as_str()).cloned().unwrap_or_default();
+        // Unreadable sources are marked until they are read again
+        let log = match counts.error {
+            Some(_) => Cell::from(Spans::from(vec![
+                Span::styled("⚠ ", ERROR_STYLE),
+                Span::raw(item.1.as_str()),
+            ])),
+            None => Cell::from(Text::from(item.1.as_str())),
+        };
+
         let cells = vec![
             Cell::from(get_enabled_widget(item.0)),
-            Cell::from(Text::from(item.1.as_str())),
+            log,
             Cell::from(Text::from(format)),
+            Cell::from(format_thousands(counts.raw)),
+            Cell::from(format_thousands(counts.filtered)),
+            Cell::from(format_thousands(counts.search)),
         ];
         Row::new(cells).bottom_margin(0)
     });
@@ -85,9 +123,12 @@ where
         .block(sources_widget)
         .highlight_style(selected_style)
         .widths(&[
-            Constraint::Percentage(20),
-            Constraint::Percentage(50),
-            Constraint::Percentage(30),
+            Constraint::Percentage(10),
+            Constraint::Percentage(36),
+            Constraint::Percentage(18),
+            Constraint::Percentage(12),
+            Constraint::Percentage(12),
+            Constraint::Percentage(12),
         ]);
     f.render_stateful_widget(t, area, &mut app.sources.state);
 }
@@ -96,8 +137,21 @@ fn draw_filters<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
 where
     B: Backend,
 {
+    let hidden_severities = app.log_analyzer.get_hidden_severities();
+    let mut title = vec![Span::raw("Filters ")];
+    for (i, severity) in Severity::all().iter().enumerate() {
+        let style = match hidden_severities.contains(severity) {
+            true => Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::CROSSED_OUT),
+            false => Style::default().fg(app.color),
+        };
+        title.push(Span::styled(format!("[{}]{}", i + 1, severity.name()), style));
+        title.push(Span::raw(" "));
+    }
+
     let filters_widget = Block::default()
-        .title("Filters")
+        .title(Spans::from(title))
         .borders(Borders::ALL)
         .border_style(match app.selected_module {
             Module::Filters => selected_style(app.color),
@@ -135,6 +189,30 @@ fn draw_sidebar<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
 where
     B: Backend,
 {
+    let (area, statistics_area) = match app.show_statistics {
+        true => {
+            let split = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(STATISTICS_HEIGHT)].as_ref())
+                .split(area);
+            (split[0], Some(split[1]))
+        }
+        false => (area, None),
+    };
+    // A counter and a sparkline per watch
+    let (area, watches_area) = match app.watches.items.len().min(MAX_WATCH_ROWS) {
+        0 => (area, None),
+        watches => {
+            let split = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(
+                    [Constraint::Min(0), Constraint::Length(2 * watches as u16 + 2)].as_ref(),
+                )
+                .split(area);
+            (split[0], Some(split[1]))
+        }
+    };
+
     let left_modules = Layout::default()
         .direction(Direction::Vertical)
         .constraints(
@@ -148,39 +226,190 @@ where
 
     draw_sources(f, app, left_modules[0]);
     draw_filters(f, app, left_modules[1]);
+    if let Some(watches_area) = watches_area {
+        draw_watches(f, app, watches_area);
+    }
+    if let Some(statistics_area) = statistics_area {
+        draw_statistics(f, app, statistics_area);
+    }
+}
+
+fn draw_watches<B>(f: &mut Frame<B>, app: &App, area: Rect)
+where
+    B: Backend,
+{
+    let block = Block::default()
+        .title(format!("Watches (per second over {}s)", WATCH_RATE_SECS))
+        .borders(Borders::ALL);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let watches = &app.watches.items[..app.watches.items.len().min(MAX_WATCH_ROWS)];
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![Constraint::Length(1); 2 * watches.len()])
+        .split(inner);
+
+    for (watch, rows) in watches.iter().zip(rows.chunks(2)) {
+        f.render_widget(
+            Paragraph::new(Spans::from(vec![
+                Span::styled(
+                    format!("{:.1}/s", watch.rate(WATCH_RATE_SECS)),
+                    Style::default().fg(app.color),
+                ),
+                Span::raw(format!(
+                    " {} ({})",
+                    watch.expression,
+                    format_thousands(watch.total)
+                )),
+            ])),
+            rows[0],
+        );
+        // The latest seconds that fit in the panel
+        let counts: Vec<u64> = watch.per_second.iter().map(|count| *count as u64).collect();
+        let counts = &counts[counts.len().saturating_sub(inner.width as usize)..];
+        f.render_widget(
+            Sparkline::default()
+                .data(counts)
+                .style(Style::default().fg(app.color)),
+            rows[1],
+        );
+    }
+}
+
+fn draw_statistics<B>(f: &mut Frame<B>, app: &App, area: Rect)
+where
+    B: Backend,
+{
+    let block = Block::default().title("Statistics").borders(Borders::ALL);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let statistics = app
+        .log_analyzer
+        .get_statistics(inner.width as usize, MAX_TOP_APPS);
+    let raw: Vec<u64> = statistics.rate.iter().map(|(_, raw, _)| *raw as u64).collect();
+    let filtered: Vec<u64> = statistics
+        .rate
+        .iter()
+        .map(|(_, _, filtered)| *filtered as u64)
+        .collect();
+    // Share the scale so both rates can be compared
+    let max = raw.iter().chain(filtered.iter()).copied().max().unwrap_or_default();
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(1),
+                Constraint::Length(3),
+                Constraint::Length(1),
+                Constraint::Length(3),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(inner);
+
+    f.render_widget(
+        Paragraph::new(format!(
+            "Raw (max {} per {})",
+            format_thousands(max as usize),
+            format_duration(statistics.bucket_seconds as f64)
+        )),
+        rows[0],
+    );
+    f.render_widget(
+        Sparkline::default()
+            .data(&raw)
+            .max(max)
+            .style(Style::default().fg(Color::DarkGray)),
+        rows[1],
+    );
+    f.render_widget(Paragraph::new("Filtered"), rows[2]);
+    f.render_widget(
+        Sparkline::default()
+            .data(&filtered)
+            .max(max)
+            .style(Style::default().fg(app.color)),
+        rows[3],
+    );
+
+    let severities: Vec<String> = statistics
+        .severities
+        .iter()
+        .map(|(severity, lines)| format!("{} {}", severity, format_thousands(*lines)))
+        .collect();
+    f.render_widget(Paragraph::new(severities.join(" · ")), rows[4]);
+
+    let apps: Vec<Spans> = statistics
+        .top_apps
+        .iter()
+        .map(|(name, lines)| {
+            Spans::from(vec![
+                Span::styled(format_thousands(*lines), Style::default().fg(app.color)),
+                Span::raw(format!(" {}", name)),
+            ])
+        })
+        .collect();
+    f.render_widget(Paragraph::new(apps), rows[5]);
 }
 
-fn log_line_cell_builder<'a>(line: &'a LogLine, column: &'a str, offset: usize) -> Cell<'a> {
+/// Horizontal scroll applied to a column. Frozen columns are never scrolled
+fn column_offset(horizontal_offset: usize, frozen: bool) -> usize {
+    match frozen {
+        true => 0,
+        false => horizontal_offset,
+    }
+}
+
+fn log_line_cell_builder<'a>(
+    line: &'a LogLine,
+    column: &'a str,
+    offset: usize,
+    color_support: ColorSupport,
+) -> Cell<'a> {
     Cell::from(Span::styled(
-        line.get(column).unwrap().get(offset..).unwrap_or_default(),
-        Style::default().fg(if line.color.is_some() {
-            Color::Rgb(
-                line.color.unwrap().0,
-                line.color.unwrap().1,
-                line.color.unwrap().2,
-            )
-        } else {
-            Color::Reset
-        }),
+        cell_text(line, column, offset),
+        Style::default().fg(color_support.color(line.color)),
     ))
 }
 
-fn log_search_cell_builder<'a>(line: &'a LogLineStyled, column: &'a str, mut offset: usize) -> Cell<'a> {
+/// Text of the `column` of `line` without its first `offset` bytes, scrolled away
+fn cell_text<'a>(line: &'a LogLine, column: &str, offset: usize) -> Cow<'a, str> {
+    match line.get(column).unwrap() {
+        Cow::Borrowed(text) => Cow::Borrowed(text.get(offset..).unwrap_or_default()),
+        Cow::Owned(text) => Cow::Owned(text.get(offset..).unwrap_or_default().to_string()),
+    }
+}
+
+fn log_search_cell_builder<'a>(
+    line: &'a LogLineStyled,
+    column: &'a str,
+    mut offset: usize,
+    color_support: ColorSupport,
+    palette: Option<&BTreeMap<String, (u8, u8, u8)>>,
+) -> Cell<'a> {
     let groups = line.get(column).unwrap();
 
     Cell::from(Spans::from(
         groups
-            .into_iter()
+            .iter()
             .filter_map(|(highlight, content)| {
-                let style = match (line.color.is_some(), highlight.as_ref().map(|c| Color::from_str(c))) {
+                // Search groups are named after a basic color or a color of the palette
+                let highlight_color = highlight.as_ref().map(|name| {
+                    Color::from_str(name).or_else(|| {
+                        palette
+                            .and_then(|palette| palette.get(name))
+                            .map(|(r, g, b)| color_support.rgb(*r, *g, *b))
+                    })
+                });
+                let style = match (line.color, highlight_color) {
                     (_, Some(Some(color))) => {
                         Style::default().fg(color).add_modifier(Modifier::BOLD)
                     }
-                    (true, _) => Style::default().fg(Color::Rgb(
-                        line.color.unwrap().0,
-                        line.color.unwrap().1,
-                        line.color.unwrap().2,
-                    )),
+                    (Some((r, g, b)), _) => Style::default().fg(color_support.rgb(r, g, b)),
                     _ => Style::default(),
                 };
 
@@ -196,20 +425,37 @@ fn log_search_cell_builder<'a>(line: &'a LogLineStyled, column: &'a str, mut off
     ))
 }
 
-fn draw_log<'a, 's, B>(
+fn draw_log<B>(
     f: &mut Frame<B>,
-    app: &'s mut App,
+    app: &mut App,
     module: Module,
     title: &str,
     area: Rect,
 ) where
     B: Backend,
 {
-    let is_selected = app.selected_module == module;
+    let is_header_selected = app.selected_module == Module::ColumnHeader;
+    let is_selected = app.selected_module == module || is_header_selected;
     let items = &app.log_lines.items;
 
+    // Column filters are displayed as chips next to the title
+    let mut title = vec![Span::raw(title.to_string())];
+    if app.processing.is_filtering {
+        title.push(Span::styled(" (filtering...)", WARNING_STYLE));
+    }
+    if app.log_analyzer.is_showing_originals() {
+        title.push(Span::styled(" (originals, not redacted)", WARNING_STYLE));
+    }
+    for column_filter in app.log_analyzer.get_column_filters() {
+        title.push(Span::raw(" "));
+        title.push(Span::styled(
+            format!(" {} ", column_filter),
+            Style::default().fg(Color::Black).bg(app.color),
+        ));
+    }
+
     let log_widget = Block::default()
-        .title(title)
+        .title(Spans::from(title))
         .borders(Borders::ALL)
         .border_style(match is_selected {
             true => selected_style(app.color),
@@ -219,25 +465,46 @@ fn draw_log<'a, 's, B>(
     let selected_style = Style::default().add_modifier(Modifier::REVERSED);
     let normal_style = Style::default().bg(app.color).add_modifier(Modifier::BOLD);
 
-    let enabled_columns: Vec<&(String, bool)> = app
-        .log_columns
-        .iter()
-        .filter(|(_, enabled)| *enabled)
-        .collect();
-
-    let header_cells = enabled_columns
-        .iter()
-        .map(|(column, _)| Cell::from(column.clone()).style(Style::default().fg(Color::Black)));
+    let displayed_columns = app.get_displayed_columns();
+    let horizontal_offset = app.horizontal_offset;
+    let color_support = app.color_support;
+
+    let header_cells = displayed_columns.iter().enumerate().map(|(i, (column, _))| {
+        match is_header_selected && i == app.selected_header {
+            true => Cell::from(format!(
+                "{}: {}",
+                column,
+                app.input_buffers[INDEX_COLUMN_FILTER].value()
+            ))
+            .style(selected_style),
+            false => Cell::from(column.as_str()).style(Style::default().fg(Color::Black)),
+        }
+    });
     let header = Row::new(header_cells).style(normal_style).bottom_margin(1);
 
     let rows = items.iter().map(|item| {
-        let cells = enabled_columns
-            .iter()
-            .map(|(column, _)| log_line_cell_builder(item, column, app.horizontal_offset));
-        Row::new(cells).bottom_margin(0)
+        // The first column of the lines in a burst is highlighted to mark its region
+        let in_burst = app.is_in_burst(item);
+        let cells = displayed_columns.iter().enumerate().map(|(j, (column, frozen))| {
+            let offset = column_offset(horizontal_offset, *frozen);
+            match in_burst && j == 0 {
+                true => Cell::from(Span::styled(
+                    cell_text(item, column, offset),
+                    WARNING_STYLE,
+                )),
+                false => log_line_cell_builder(item, column, offset, color_support),
+            }
+        });
+        // Stripe by line index so the stripes don't flicker while scrolling
+        let row = item.index;
+        let style = match app.is_before_gap(row) {
+            true => stripe_style(app.stripe_color, row).add_modifier(Modifier::UNDERLINED),
+            false => stripe_style(app.stripe_color, row),
+        };
+        Row::new(cells).style(style).bottom_margin(0)
     });
 
-    let constraints: Vec<Constraint> = enabled_columns
+    let constraints: Vec<Constraint> = displayed_columns
         .iter()
         .map(|(name, _)| Constraint::Length(app.get_column_lenght(name)))
         .collect();
@@ -252,9 +519,143 @@ fn draw_log<'a, 's, B>(
     f.render_stateful_widget(t, area, state);
 }
 
-fn draw_search<'a, 's, B>(
+fn draw_grouped_log<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
+where
+    B: Backend,
+{
+    let log_widget = Block::default()
+        .title("Log grouped by source")
+        .borders(Borders::ALL)
+        .border_style(match app.selected_module {
+            Module::Logs => selected_style(app.color),
+            _ => Style::default(),
+        });
+
+    let selected_style = Style::default().add_modifier(Modifier::REVERSED);
+    let normal_style = Style::default().bg(app.color).add_modifier(Modifier::BOLD);
+
+    let displayed_columns = app.get_displayed_columns();
+    let horizontal_offset = app.horizontal_offset;
+    let color_support = app.color_support;
+
+    let group_label = |source: &str, lines: usize, expanded: bool| {
+        format!(
+            "{} {} ({})",
+            if expanded { "▼" } else { "▶" },
+            source,
+            format_thousands(lines)
+        )
+    };
+    let group_width = app
+        .grouped_log
+        .items
+        .iter()
+        .filter_map(|row| match row {
+            GroupRow::Header(source, lines, expanded) => {
+                Some(group_label(source, *lines, *expanded).chars().count())
+            }
+            _ => None,
+        })
+        .max()
+        .unwrap_or_default()
+        .clamp(6, 50) as u16;
+
+    let header_cells = std::iter::once("Source")
+        .chain(displayed_columns.iter().map(|(column, _)| column.as_str()))
+        .map(|h| Cell::from(h).style(Style::default().fg(Color::Black)));
+    let header = Row::new(header_cells).style(normal_style).bottom_margin(1);
+
+    let stripe_color = app.stripe_color;
+    let rows = app.grouped_log.items.iter().enumerate().map(|(i, row)| match row {
+        GroupRow::Header(source, lines, expanded) => Row::new(vec![Cell::from(Span::styled(
+            group_label(source, *lines, *expanded),
+            Style::default().fg(app.color).add_modifier(Modifier::BOLD),
+        ))]),
+        GroupRow::Line(line) => Row::new(std::iter::once(Cell::from("")).chain(
+            displayed_columns.iter().map(|(column, frozen)| {
+                log_line_cell_builder(
+                    line,
+                    column,
+                    column_offset(horizontal_offset, *frozen),
+                    color_support,
+                )
+            }),
+        ))
+        .style(stripe_style(stripe_color, i)),
+        GroupRow::More(lines) => Row::new(vec![
+            Cell::from(""),
+            Cell::from(Span::styled(
+                format!("... {} more lines", format_thousands(*lines)),
+                Style::default().add_modifier(Modifier::DIM),
+            )),
+        ]),
+    });
+
+    let constraints: Vec<Constraint> = std::iter::once(Constraint::Length(group_width))
+        .chain(
+            displayed_columns
+                .iter()
+                .map(|(name, _)| Constraint::Length(app.get_column_lenght(name))),
+        )
+        .collect();
+
+    let t = Table::new(rows)
+        .header(header)
+        .block(log_widget)
+        .highlight_style(selected_style)
+        .widths(&constraints);
+
+    f.render_stateful_widget(t, area, &mut app.grouped_log.state);
+}
+
+fn draw_pinned_lines<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
+where
+    B: Backend,
+{
+    let pinned_widget = Block::default()
+        .title("Pinned")
+        .borders(Borders::ALL)
+        .border_style(match app.selected_module {
+            Module::PinnedLines => selected_style(app.color),
+            _ => Style::default(),
+        });
+
+    let selected_style = Style::default().add_modifier(Modifier::REVERSED);
+
+    let displayed_columns = app.get_displayed_columns();
+    let horizontal_offset = app.horizontal_offset;
+    let color_support = app.color_support;
+
+    let rows = app.pinned_lines.items.iter().enumerate().map(|(i, item)| {
+        let cells = displayed_columns.iter().map(|(column, frozen)| {
+            log_line_cell_builder(
+                item,
+                column,
+                column_offset(horizontal_offset, *frozen),
+                color_support,
+            )
+        });
+        Row::new(cells)
+            .style(stripe_style(app.stripe_color, i))
+            .bottom_margin(0)
+    });
+
+    let constraints: Vec<Constraint> = displayed_columns
+        .iter()
+        .map(|(name, _)| Constraint::Length(app.get_column_lenght(name)))
+        .collect();
+
+    let t = Table::new(rows)
+        .block(pinned_widget)
+        .highlight_style(selected_style)
+        .widths(&constraints);
+
+    f.render_stateful_widget(t, area, &mut app.pinned_lines.state);
+}
+
+fn draw_search<B>(
     f: &mut Frame<B>,
-    app: &'s mut App,
+    app: &mut App,
     module: Module,
     title: &str,
     area: Rect,
@@ -275,25 +676,32 @@ fn draw_search<'a, 's, B>(
     let selected_style = Style::default().add_modifier(Modifier::REVERSED);
     let normal_style = Style::default().bg(app.color).add_modifier(Modifier::BOLD);
 
-    let enabled_columns: Vec<&(String, bool)> = app
-        .log_columns
-        .iter()
-        .filter(|(_, enabled)| *enabled)
-        .collect();
+    let displayed_columns = app.get_displayed_columns();
+    let horizontal_offset = app.horizontal_offset;
+    let color_support = app.color_support;
+    let palette = app.settings.palette.as_ref();
 
-    let header_cells = enabled_columns
+    let header_cells = displayed_columns
         .iter()
-        .map(|(column, _)| Cell::from(column.clone()).style(Style::default().fg(Color::Black)));
+        .map(|(column, _)| Cell::from(column.as_str()).style(Style::default().fg(Color::Black)));
     let header = Row::new(header_cells).style(normal_style).bottom_margin(1);
 
-    let rows = items.iter().map(|item| {
-        let cells = enabled_columns
-            .iter()
-            .map(|(column, _)| log_search_cell_builder(item, column, app.horizontal_offset));
-        Row::new(cells).bottom_margin(0)
+    let rows = items.iter().enumerate().map(|(i, item)| {
+        let cells = displayed_columns.iter().map(|(column, frozen)| {
+            log_search_cell_builder(
+                item,
+                column,
+                column_offset(horizontal_offset, *frozen),
+                color_support,
+                palette,
+            )
+        });
+        Row::new(cells)
+            .style(stripe_style(app.stripe_color, i))
+            .bottom_margin(0)
     });
 
-    let constraints: Vec<Constraint> = enabled_columns
+    let constraints: Vec<Constraint> = displayed_columns
         .iter()
         .map(|(name, _)| Constraint::Length(app.get_column_lenght(name)))
         .collect();
@@ -312,12 +720,21 @@ fn draw_search_box<B>(f: &mut Frame<B>, app: &mut App, area: Rect, index: usize,
 where
     B: Backend,
 {
+    let search_title = match app.input_buffers[index].value().is_empty() {
+        true => title.to_string(),
+        false => format!(
+            "{} ({} hits in {} lines)",
+            title,
+            format_thousands(app.log_analyzer.get_total_searched_lines()),
+            format_thousands(app.log_analyzer.get_total_filtered_lines())
+        ),
+    };
     let input_widget = Paragraph::new(app.input_buffers[index].value())
         .style(match app.selected_module {
             Module::Search => selected_style(app.color),
             _ => Style::default(),
         })
-        .block(Block::default().borders(Borders::ALL).title(title));
+        .block(Block::default().borders(Borders::ALL).title(search_title));
 
     f.render_widget(input_widget, area);
 
@@ -330,13 +747,25 @@ fn draw_bottom_bar<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
 where
     B: Backend,
 {
+    // Elapsed time is only displayed while measuring
+    let measuring = app.time_marks.0.is_some();
     let bottom_bar_layout = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(33),
-            Constraint::Percentage(33),
-            Constraint::Percentage(33),
-        ])
+        .constraints(match measuring {
+            false => vec![
+                Constraint::Percentage(20),
+                Constraint::Percentage(30),
+                Constraint::Percentage(30),
+                Constraint::Percentage(20),
+            ],
+            true => vec![
+                Constraint::Percentage(15),
+                Constraint::Percentage(25),
+                Constraint::Percentage(25),
+                Constraint::Percentage(15),
+                Constraint::Percentage(20),
+            ],
+        })
         .split(area);
 
     let auto_scroll = Paragraph::new("AUTO SCROLL")
@@ -345,17 +774,41 @@ where
             true => selected_style(app.color),
         })
         .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL));
+        .block(Block::default().borders(Borders::ALL).title(
+            match app.macros.get_recording() {
+                Some(register) => Span::styled(format!("REC @{}", register), ERROR_STYLE),
+                None if app.macros.is_awaiting_register() => Span::styled("REC @_", ERROR_STYLE),
+                None => match app.get_unread_level() {
+                    Some(level) => Span::styled(
+                        format!("{} new (Ctrl+N)", app.unread_notifications),
+                        match level {
+                            NotificationLevel::Error => ERROR_STYLE,
+                            NotificationLevel::Warning => WARNING_STYLE,
+                            NotificationLevel::Info => Style::default(),
+                        },
+                    ),
+                    None => Span::raw(""),
+                },
+            },
+        ));
 
     f.render_widget(auto_scroll, bottom_bar_layout[0]);
 
     let total = app.log_analyzer.get_total_raw_lines();
     let filtered = app.log_analyzer.get_total_filtered_lines();
     let label = format!(" {}/{}", filtered, total);
+    // Throughput is only displayed while lines are processed
+    let throughput = match app.metrics.is_idle() {
+        true => String::new(),
+        false => format!(
+            "{} lines/s",
+            format_thousands(app.metrics.processed_per_sec as usize)
+        ),
+    };
     let gauge = Gauge::default()
-        .block(Block::default().borders(Borders::ALL))
+        .block(Block::default().borders(Borders::ALL).title(throughput))
         .gauge_style(Style::default().fg(app.color))
-        .percent((if total > 0 { (filtered * 100 / total).min(100) } else { 0 }) as u16)
+        .percent((filtered * 100).checked_div(total).unwrap_or(0).min(100) as u16)
         .label(label);
     f.render_widget(gauge, bottom_bar_layout[1]);
 
@@ -364,24 +817,100 @@ where
     let gauge = Gauge::default()
         .block(Block::default().borders(Borders::ALL))
         .gauge_style(Style::default().fg(app.color))
-        .percent((if total > 0 { (searched * 100 / total).min(100) } else { 0 }) as u16)
+        .percent((searched * 100).checked_div(total).unwrap_or(0).min(100) as u16)
         .label(label);
 
     f.render_widget(gauge, bottom_bar_layout[2]);
+
+    draw_memory_usage(f, app, bottom_bar_layout[3]);
+
+    if measuring {
+        draw_elapsed_time(f, app, bottom_bar_layout[4]);
+    }
+}
+
+fn draw_elapsed_time<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
+where
+    B: Backend,
+{
+    let label = match &app.time_marks {
+        (Some(start), None) => format!("Δ from #{} ...", start.index),
+        (Some(start), Some(end)) => match app.get_elapsed_time() {
+            Some(elapsed) => format!(
+                "Δ {} (#{} → #{})",
+                format_duration(elapsed),
+                start.index,
+                end.index
+            ),
+            None => format!("Δ no time (#{} → #{})", start.index, end.index),
+        },
+        _ => String::new(),
+    };
+
+    let elapsed = Paragraph::new(label)
+        .style(selected_style(app.color))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+
+    f.render_widget(elapsed, area);
+}
+
+fn draw_memory_usage<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
+where
+    B: Backend,
+{
+    let usage = app.log_analyzer.get_memory_usage();
+    let total_mb = usage.total() / (1024 * 1024);
+
+    let style = match total_mb {
+        mb if mb >= app.memory_critical_mb => ERROR_STYLE,
+        mb if mb >= app.memory_warning_mb => WARNING_STYLE,
+        _ => Style::default(),
+    };
+
+    let label = format!(
+        "MEM {} (raw {} | log {} | search {})",
+        format_bytes(usage.total()),
+        format_bytes(usage.raw),
+        format_bytes(usage.filtered),
+        format_bytes(usage.search)
+    );
+    // Badge of the sources the pipeline doesn't keep up with
+    let stats = &app.source_stats;
+    let lag = format_lag(
+        stats.iter().any(|stats| stats.lagging),
+        stats.iter().map(|stats| stats.dropped).sum(),
+    );
+    let lag = match lag.is_empty() {
+        true => Span::raw(""),
+        false => Span::styled(lag, ERROR_STYLE.add_modifier(Modifier::REVERSED)),
+    };
+    let memory = Paragraph::new(label)
+        .style(style)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title(lag));
+
+    f.render_widget(memory, area);
 }
 
 fn draw_main_panel<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
 where
     B: Backend,
 {
-    let expandable = area.height - 3;
-    let log_lenght = expandable * (app.log_search_size_percentage) as u16 / 100;
-    let search_lenght = expandable * (100 - app.log_search_size_percentage) as u16 / 100;
+    // Pinned lines panel is only displayed when there are pinned lines
+    let pinned_lenght = match app.pinned_lines.items.len() {
+        0 => 0,
+        pinned => (pinned.min(MAX_PINNED_ROWS) + 2) as u16,
+    };
+    let expandable = area.height.saturating_sub(3 + pinned_lenght);
+    let log_lenght = expandable * app.log_search_size_percentage / 100;
+    let search_lenght = expandable * (100 - app.log_search_size_percentage) / 100;
 
     let main_modules = Layout::default()
         .direction(Direction::Vertical)
         .constraints(
             [
+                Constraint::Length(pinned_lenght),
                 Constraint::Length(log_lenght),
                 Constraint::Length(3),
                 Constraint::Length(search_lenght),
@@ -390,21 +919,19 @@ where
         )
         .split(area);
 
-    draw_log(
-        f,
-        app,
-        Module::Logs,
-        "Log",
-        main_modules[0],
-    );
-    draw_search_box(f, app, main_modules[1], INDEX_SEARCH, "Search");
-    draw_search(
-        f,
-        app,
-        Module::SearchResult,
-        "Search results",
-        main_modules[2],
-    );
+    if pinned_lenght > 0 {
+        draw_pinned_lines(f, app, main_modules[0]);
+    }
+    match app.group_by_source {
+        true => draw_grouped_log(f, app, main_modules[1]),
+        false => draw_log(f, app, Module::Logs, "Log", main_modules[1]),
+    }
+    draw_search_box(f, app, main_modules[2], INDEX_SEARCH, "Search");
+    let search_title = match app.sync_cursor {
+        true => "Search results (synced with the log)",
+        false => "Search results",
+    };
+    draw_search(f, app, Module::SearchResult, search_title, main_modules[3]);
 }
 
 pub fn draw_log_analyzer_view<B>(f: &mut Frame<B>, app: &mut App)
@@ -30,7 +30,10 @@ where
     B: Backend,
 {
     let block = Block::default()
-        .title("Navigate to index")
+        .title(format!(
+            "Navigate to {} (↑↓ to switch)",
+            app.navigation_mode.name()
+        ))
         .borders(Borders::ALL)
         .border_style(selected_style(app.color));
 
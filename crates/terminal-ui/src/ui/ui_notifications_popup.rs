@@ -0,0 +1,60 @@
+use log_analyzer::models::notification::NotificationLevel;
+use tui::{
+    backend::Backend,
+    layout::Constraint,
+    style::{Color, Modifier, Style},
+    text::Span,
+    widgets::{Block, Borders, Cell, Clear, Row, Table},
+    Frame,
+};
+
+use crate::{
+    app::App,
+    styles::{selected_style, ERROR_STYLE, WARNING_STYLE},
+};
+
+use super::{ui_popup::centered_rect, ui_shared::format_clock};
+
+pub fn draw_notifications_popup<B>(f: &mut Frame<B>, app: &mut App)
+where
+    B: Backend,
+{
+    let block = Block::default()
+        .title("Notifications (c: clear, Esc: close)")
+        .borders(Borders::ALL)
+        .border_style(selected_style(app.color));
+
+    let area = centered_rect(70, 60, f.size());
+    f.render_widget(Clear, area); //this clears out the background
+
+    let header_cells = ["Time (UTC)", "Level", "Message"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Black)));
+    let header = Row::new(header_cells)
+        .style(Style::default().bg(app.color).add_modifier(Modifier::BOLD))
+        .bottom_margin(1);
+
+    let rows = app.notifications.items.iter().map(|notification| {
+        let level = match notification.level {
+            NotificationLevel::Info => Span::raw("INFO"),
+            NotificationLevel::Warning => Span::styled("WARNING", WARNING_STYLE),
+            NotificationLevel::Error => Span::styled("ERROR", ERROR_STYLE),
+        };
+        Row::new(vec![
+            Cell::from(format_clock(notification.timestamp)),
+            Cell::from(level),
+            Cell::from(notification.message.as_str()),
+        ])
+    });
+
+    let t = Table::new(rows)
+        .header(header)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(&[
+            Constraint::Length(10),
+            Constraint::Length(8),
+            Constraint::Percentage(100),
+        ]);
+    f.render_stateful_widget(t, area, &mut app.notifications.state);
+}
@@ -0,0 +1,71 @@
+use crate::{
+    app::{App, PathAction, INDEX_PATH_POPUP},
+    styles::{selected_style, ERROR_STYLE},
+};
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use super::{ui_popup::centered_rect, ui_shared::display_cursor};
+
+pub fn draw_path_popup<B>(f: &mut Frame<B>, app: &mut App)
+where
+    B: Backend,
+{
+    let block = Block::default()
+        .title(app.path_action.title())
+        .borders(Borders::ALL)
+        .border_style(selected_style(app.color));
+
+    let area = centered_rect(60, 8, f.size());
+    f.render_widget(Clear, area); //this clears out the background
+    f.render_widget(block, area);
+
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Length(1)].as_ref())
+        .margin(1)
+        .split(area);
+
+    let (file, help) = match app.path_action {
+        PathAction::SaveConfiguration => (
+            "Settings file",
+            "⏎ Enter to save the formats and filters, Esc to cancel",
+        ),
+        PathAction::SaveSession => (
+            "Session file (.lapsession, or .lapsnapshot with the lines)",
+            "⏎ Enter to save the whole analysis, Esc to cancel",
+        ),
+        PathAction::OpenSession => (
+            "Session file (.lapsession, or .lapsnapshot replacing the analysis)",
+            "⏎ Enter to add the session to the analysis, Esc to cancel",
+        ),
+        PathAction::ExportFiltered => (
+            "Export file (.json, .ndjson, .csv, .html or text)",
+            "⏎ Enter to write the filtered log, Esc to cancel",
+        ),
+        PathAction::ExportSearch => (
+            "Export file (.json, .ndjson, .csv, .html or text)",
+            "⏎ Enter to write the search results, Esc to cancel",
+        ),
+    };
+    let path_widget = Paragraph::new(app.input_buffers[INDEX_PATH_POPUP].value())
+        .style(selected_style(app.color))
+        .block(Block::default().borders(Borders::ALL).title(file));
+    f.render_widget(path_widget, popup_layout[0]);
+    display_cursor(
+        f,
+        popup_layout[0],
+        app.input_buffers[INDEX_PATH_POPUP].cursor(),
+    );
+
+    let help = match &app.path_popup_error {
+        Some(err) => Paragraph::new(err.as_str()).style(ERROR_STYLE),
+        None => Paragraph::new(help),
+    }
+    .alignment(Alignment::Center);
+    f.render_widget(help, popup_layout[1]);
+}
@@ -0,0 +1,93 @@
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::{app::App, styles::selected_style};
+
+use super::ui_popup::centered_rect;
+
+fn draw_message<B>(f: &mut Frame<B>, message: String, area: Rect)
+where
+    B: Backend,
+{
+    let message_widget = Paragraph::new(message)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::NONE));
+    f.render_widget(message_widget, area);
+}
+
+fn draw_buttons<B>(f: &mut Frame<B>, app: &App, area: Rect)
+where
+    B: Backend,
+{
+    let buttons = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(area);
+
+    let yes_button_widget = Paragraph::new("[Y]es")
+        .style(selected_style(app.color))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    let no_button_widget = Paragraph::new("[N]o")
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(yes_button_widget, buttons[0]);
+    f.render_widget(no_button_widget, buttons[1]);
+}
+
+/// Popup asking a yes or no question
+fn draw_question_popup<B>(f: &mut Frame<B>, app: &App, title: &str, message: String)
+where
+    B: Backend,
+{
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(selected_style(app.color));
+
+    let area = centered_rect(50, 20, f.size());
+    f.render_widget(Clear, area); //this clears out the background
+    f.render_widget(block, area);
+
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(2), Constraint::Length(3)].as_ref())
+        .margin(1)
+        .split(area);
+
+    draw_message(f, message, popup_layout[0]);
+    draw_buttons(f, app, popup_layout[1]);
+}
+
+pub fn draw_restore_popup<B>(f: &mut Frame<B>, app: &mut App)
+where
+    B: Backend,
+{
+    let message = match &app.pending_session {
+        Some(session) => format!(
+            "A previous session was found with {} sources, {} formats and {} filters.\nDo you want to restore it?",
+            session.sources.len(),
+            session.formats.len(),
+            session.filters.len()
+        ),
+        None => String::new(),
+    };
+    draw_question_popup(f, app, "Restore session", message);
+}
+
+pub fn draw_clear_popup<B>(f: &mut Frame<B>, app: &mut App)
+where
+    B: Backend,
+{
+    let message = format!(
+        "Stop and remove the {} sources, {} formats and {} filters with all their lines?",
+        app.sources.items.len(),
+        app.log_analyzer.get_formats().len(),
+        app.filters.items.len()
+    );
+    draw_question_popup(f, app, "Clear analysis", message);
+}
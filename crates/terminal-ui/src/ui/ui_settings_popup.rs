@@ -0,0 +1,85 @@
+use crate::{
+    app::{
+        App, INDEX_SETTINGS_MEMORY_CRITICAL, INDEX_SETTINGS_MEMORY_WARNING,
+        INDEX_SETTINGS_PRIMARY_COLOR, INDEX_SETTINGS_STRIPE_COLOR, INDEX_SETTINGS_TICK_RATE,
+    },
+    styles::{selected_style, ERROR_STYLE},
+};
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Style,
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use super::{ui_popup::centered_rect, ui_shared::display_cursor};
+
+fn draw_settings_input<B>(f: &mut Frame<B>, app: &App, area: Rect, index: usize, title: &str)
+where
+    B: Backend,
+{
+    let input_widget = Paragraph::new(app.input_buffers[index].value())
+        .style(match index == app.input_buffer_index {
+            false => Style::default(),
+            true => selected_style(app.color),
+        })
+        .block(Block::default().borders(Borders::ALL).title(title));
+
+    f.render_widget(input_widget, area);
+    if index == app.input_buffer_index {
+        display_cursor(f, area, app.input_buffers[index].cursor())
+    }
+}
+
+pub fn draw_settings_popup<B>(f: &mut Frame<B>, app: &mut App)
+where
+    B: Backend,
+{
+    let title = match &app.settings_path {
+        Some(path) => format!("Settings ({})", path.display()),
+        None => "Settings".to_string(),
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(selected_style(app.color));
+
+    let area = centered_rect(60, 21, f.size());
+    f.render_widget(Clear, area); //this clears out the background
+    f.render_widget(block, area);
+
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .margin(1)
+        .split(area);
+
+    let fields = [
+        (INDEX_SETTINGS_PRIMARY_COLOR, "Primary color (r, g, b)"),
+        (INDEX_SETTINGS_STRIPE_COLOR, "Stripe color (r, g, b). Empty to disable"),
+        (INDEX_SETTINGS_TICK_RATE, "Tick rate (ms)"),
+        (INDEX_SETTINGS_MEMORY_WARNING, "Memory warning (MB)"),
+        (INDEX_SETTINGS_MEMORY_CRITICAL, "Memory critical (MB)"),
+    ];
+    for (i, (index, title)) in fields.into_iter().enumerate() {
+        draw_settings_input(f, app, popup_layout[i], index, title);
+    }
+
+    let help = match &app.settings_error {
+        Some(err) => Paragraph::new(err.as_str()).style(ERROR_STYLE),
+        None => Paragraph::new("⏎ Enter to apply and save, Esc to cancel. Empty fields use the defaults"),
+    }
+    .alignment(Alignment::Center);
+    f.render_widget(help, popup_layout[5]);
+}
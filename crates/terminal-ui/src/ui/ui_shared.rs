@@ -11,4 +11,117 @@ where
         // Move one line down, from the border to the input line
         area.y + 1,
     )
-}
\ No newline at end of file
+}
+
+/// Format an amount of bytes in a human readable way
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{} {}", bytes, UNITS[unit]),
+        _ => format!("{:.1} {}", value, UNITS[unit]),
+    }
+}
+
+/// Format an amount of seconds in a human readable way
+pub fn format_duration(seconds: f64) -> String {
+    let sign = if seconds < 0.0 { "-" } else { "" };
+    let seconds = seconds.abs();
+    let hours = (seconds / 3600.0).floor();
+    let minutes = ((seconds - hours * 3600.0) / 60.0).floor();
+    let remaining = seconds - hours * 3600.0 - minutes * 60.0;
+
+    match (hours as u64, minutes as u64) {
+        (0, 0) => format!("{}{:.3}s", sign, remaining),
+        (0, m) => format!("{}{}m {:06.3}s", sign, m, remaining),
+        (h, m) => format!("{}{}h {:02}m {:06.3}s", sign, h, m, remaining),
+    }
+}
+
+/// Format a number grouping the thousands with commas
+pub fn format_thousands(number: usize) -> String {
+    let digits = number.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Format the state of the sources the pipeline doesn't keep up with, empty if it does
+pub fn format_lag(lagging: bool, dropped: usize) -> String {
+    match (lagging, dropped) {
+        (false, 0) => String::new(),
+        (true, 0) => "lagging".to_string(),
+        (false, dropped) => format!("dropped {}", format_thousands(dropped)),
+        (true, dropped) => format!("lagging, dropped {}", format_thousands(dropped)),
+    }
+}
+
+/// Cut `text` in rows of `width` chars and return `count` of them starting at row `first`,
+/// without going through the rest of a very long text
+pub fn wrap_rows(text: &str, width: usize, first: usize, count: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut chars = text.chars().skip(first * width).take(count * width).peekable();
+    let mut rows = Vec::new();
+    while chars.peek().is_some() {
+        rows.push(chars.by_ref().take(width).collect());
+    }
+    rows
+}
+
+/// Format the seconds since the Unix epoch as a UTC wall clock time
+pub fn format_clock(timestamp: u64) -> String {
+    let seconds_of_day = timestamp % 86_400;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lag_is_described_when_lines_are_deferred_or_dropped() {
+        assert_eq!(format_lag(false, 0), "");
+        assert_eq!(format_lag(true, 0), "lagging");
+        assert_eq!(format_lag(false, 1200), "dropped 1,200");
+        assert_eq!(format_lag(true, 3), "lagging, dropped 3");
+    }
+
+    #[test]
+    fn only_the_visible_rows_are_wrapped() {
+        assert_eq!(wrap_rows("abcdefgh", 3, 0, 5), vec!["abc", "def", "gh"]);
+        assert_eq!(wrap_rows("abcdefgh", 3, 1, 1), vec!["def"]);
+        assert_eq!(wrap_rows("éàü", 2, 1, 1), vec!["ü"]);
+        assert!(wrap_rows("abc", 3, 1, 1).is_empty());
+    }
+
+    #[test]
+    fn thousands_are_grouped() {
+        assert_eq!(format_thousands(0), "0");
+        assert_eq!(format_thousands(999), "999");
+        assert_eq!(format_thousands(1234), "1,234");
+        assert_eq!(format_thousands(5678900), "5,678,900");
+    }
+
+    #[test]
+    fn clock_is_formatted_in_utc() {
+        // 2022-06-05T13:04:05Z
+        assert_eq!(format_clock(1_654_434_245), "13:04:05");
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     app::{
-        App, INDEX_SOURCE_FORMAT, INDEX_SOURCE_NEW_FORMAT_ALIAS, INDEX_SOURCE_NEW_FORMAT_REGEX,
-        INDEX_SOURCE_OK_BUTTON, INDEX_SOURCE_PATH, INDEX_SOURCE_TYPE,
+        App, INDEX_SOURCE_FORMAT, INDEX_SOURCE_RECENT, INDEX_SOURCE_NEW_FORMAT_ALIAS, INDEX_SOURCE_NEW_FORMAT_REGEX,
+        INDEX_SOURCE_OK_BUTTON, INDEX_SOURCE_PATH, INDEX_SOURCE_TYPE, SOURCE_POPUP_TYPES,
     },
     styles::selected_style,
 };
@@ -16,18 +16,69 @@ use tui::{
 
 use super::{ui_popup::centered_rect, ui_shared::display_cursor};
 
+/// Maximum number of recent sources displayed at once
+const MAX_RECENT_ROWS: u16 = 5;
+
+fn draw_recent_sources<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
+where
+    B: Backend,
+{
+    let recent: Vec<ListItem> = app
+        .recent_sources
+        .items
+        .iter()
+        .map(|source| {
+            let source_type = match source.source_type {
+                0 => "FILE",
+                3 => "PLUGIN",
+                _ => "WS",
+            };
+            let format = source
+                .format
+                .as_ref()
+                .map(|format| format!(" ({})", format.alias))
+                .unwrap_or_default();
+            ListItem::new(Spans::from(format!("{} {}{}", source_type, source.address, format)))
+                .style(Style::default().fg(Color::White))
+        })
+        .collect();
+
+    let recent = List::new(recent)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Recent")
+                .border_style(match INDEX_SOURCE_RECENT == app.input_buffer_index {
+                    false => Style::default(),
+                    true => selected_style(app.color),
+                }),
+        )
+        .highlight_style(match INDEX_SOURCE_RECENT == app.input_buffer_index {
+            false => Style::default(),
+            true => selected_style(app.color),
+        })
+        .highlight_symbol(">> ");
+
+    f.render_stateful_widget(recent, area, &mut app.recent_sources.state);
+}
+
 fn draw_source_type_selector<B>(f: &mut Frame<B>, app: &App, area: Rect)
 where
     B: Backend,
 {
-    let titles = ["FILE", "WS"]
+    let titles = ["FILE", "WS", "PLUGIN"]
         .iter()
         .map(|t| Spans::from(vec![Span::styled(*t, Style::default().fg(Color::White))]))
         .collect();
 
     let source_type_widget = Tabs::new(titles)
         .block(Block::default().borders(Borders::ALL).title("Source type"))
-        .select(app.source_type)
+        .select(
+            SOURCE_POPUP_TYPES
+                .iter()
+                .position(|source_type| *source_type == app.source_type)
+                .unwrap_or_default(),
+        )
         .style(match INDEX_SOURCE_TYPE == app.input_buffer_index {
             false => Style::default(),
             true => selected_style(app.color),
@@ -54,6 +105,31 @@ where
     }
 }
 
+fn draw_path_candidates<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
+where
+    B: Backend,
+{
+    let candidates: Vec<ListItem> = app
+        .path_candidates
+        .items
+        .iter()
+        .map(|i| ListItem::new(Spans::from(i.clone())).style(Style::default().fg(Color::White)))
+        .collect();
+
+    let candidates = List::new(candidates)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Matching paths (↑↓ to select, Enter to accept)")
+                .border_style(selected_style(app.color)),
+        )
+        .highlight_style(selected_style(app.color))
+        .highlight_symbol(">> ");
+
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(candidates, area, &mut app.path_candidates.state);
+}
+
 fn draw_format_list<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
 where
     B: Backend,
@@ -148,7 +224,11 @@ where
         .borders(Borders::ALL)
         .border_style(selected_style(app.color));
 
-    let area = centered_rect(60, 28, f.size());
+    let recent_height = match app.recent_sources.items.len() as u16 {
+        0 => 0,
+        rows => rows.min(MAX_RECENT_ROWS) + 2,
+    };
+    let area = centered_rect(60, 28 + recent_height, f.size());
     f.render_widget(Clear, area); //this clears out the background
     f.render_widget(block, area);
 
@@ -161,6 +241,7 @@ where
         .direction(Direction::Vertical)
         .constraints(
             [
+                Constraint::Length(recent_height),
                 Constraint::Max(3),
                 Constraint::Max(3),
                 Constraint::Percentage(40),
@@ -173,9 +254,15 @@ where
         .margin(1)
         .split(popup_layout[0]);
 
+    if recent_height > 0 {
+        draw_recent_sources(f, app, popup_layout[INDEX_SOURCE_RECENT]);
+    }
     draw_source_type_selector(f, app, popup_layout[INDEX_SOURCE_TYPE]);
     draw_source_path(f, app, popup_layout[INDEX_SOURCE_PATH]);
     draw_format_list(f, app, popup_layout[INDEX_SOURCE_FORMAT]);
+    if INDEX_SOURCE_PATH == app.input_buffer_index && !app.path_candidates.items.is_empty() {
+        draw_path_candidates(f, app, popup_layout[INDEX_SOURCE_FORMAT]);
+    }
     draw_new_format_alias(f, app, popup_layout[INDEX_SOURCE_NEW_FORMAT_ALIAS]);
     draw_new_format_regex(f, app, popup_layout[INDEX_SOURCE_NEW_FORMAT_REGEX]);
     draw_ok_button(f, app, popup_layout[INDEX_SOURCE_OK_BUTTON]);
@@ -0,0 +1,167 @@
+use crate::{
+    app::{
+        App, INDEX_TOP_VALUES_COLUMN, INDEX_TOP_VALUES_LIMIT, INDEX_TOP_VALUES_RANGE,
+        INDEX_TOP_VALUES_SCOPE,
+    },
+    styles::{selected_style, ERROR_STYLE},
+};
+use log_analyzer::models::log_line::LogLine;
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, Tabs},
+    Frame,
+};
+
+use super::{
+    ui_popup::centered_rect,
+    ui_shared::{display_cursor, format_thousands},
+};
+
+fn draw_selector<B>(
+    f: &mut Frame<B>,
+    app: &App,
+    area: Rect,
+    index: usize,
+    title: &str,
+    titles: &[String],
+    selected: usize,
+) where
+    B: Backend,
+{
+    let titles = titles
+        .iter()
+        .map(|t| {
+            Spans::from(vec![Span::styled(
+                t.as_str(),
+                Style::default().fg(Color::White),
+            )])
+        })
+        .collect();
+
+    let selector = Tabs::new(titles)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .select(selected)
+        .style(match index == app.input_buffer_index {
+            false => Style::default(),
+            true => selected_style(app.color),
+        })
+        .highlight_style(selected_style(app.color));
+
+    f.render_widget(selector, area);
+}
+
+fn draw_limit<B>(f: &mut Frame<B>, app: &App, area: Rect)
+where
+    B: Backend,
+{
+    let selected = INDEX_TOP_VALUES_LIMIT == app.input_buffer_index;
+    let input_widget = Paragraph::new(app.input_buffers[INDEX_TOP_VALUES_LIMIT].value())
+        .style(match selected {
+            false => Style::default(),
+            true => selected_style(app.color),
+        })
+        .block(Block::default().borders(Borders::ALL).title("Top"));
+
+    f.render_widget(input_widget, area);
+    if selected {
+        display_cursor(f, area, app.input_buffers[INDEX_TOP_VALUES_LIMIT].cursor())
+    }
+}
+
+fn draw_values<B>(f: &mut Frame<B>, app: &App, area: Rect)
+where
+    B: Backend,
+{
+    let header_cells = ["Value", "Lines"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Black)));
+    let header = Row::new(header_cells)
+        .style(Style::default().bg(app.color).add_modifier(Modifier::BOLD))
+        .bottom_margin(1);
+
+    let rows = app.top_values.iter().map(|(value, lines)| {
+        Row::new(vec![
+            Cell::from(value.as_str()),
+            Cell::from(format_thousands(*lines)),
+        ])
+    });
+
+    let t = Table::new(rows)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL))
+        .widths(&[Constraint::Percentage(80), Constraint::Percentage(20)]);
+    f.render_widget(t, area);
+}
+
+pub fn draw_top_values_popup<B>(f: &mut Frame<B>, app: &mut App)
+where
+    B: Backend,
+{
+    let block = Block::default()
+        .title("Top values")
+        .borders(Borders::ALL)
+        .border_style(selected_style(app.color));
+
+    let area = centered_rect(60, 30, f.size());
+    f.render_widget(Clear, area); //this clears out the background
+    f.render_widget(block, area);
+
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(3),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .margin(1)
+        .split(area);
+
+    let columns = LogLine::columns();
+    draw_selector(
+        f,
+        app,
+        popup_layout[0],
+        INDEX_TOP_VALUES_COLUMN,
+        "Column",
+        &columns,
+        app.top_values_column,
+    );
+    draw_limit(f, app, popup_layout[1]);
+    let scopes = ["Filtered log".to_string(), "Search results".to_string()];
+    draw_selector(
+        f,
+        app,
+        popup_layout[2],
+        INDEX_TOP_VALUES_SCOPE,
+        "Lines",
+        &scopes,
+        app.top_values_search as usize,
+    );
+    let ranges = ["All".to_string(), "Between time marks".to_string()];
+    draw_selector(
+        f,
+        app,
+        popup_layout[3],
+        INDEX_TOP_VALUES_RANGE,
+        "Time",
+        &ranges,
+        app.top_values_between_marks as usize,
+    );
+    draw_values(f, app, popup_layout[4]);
+
+    let help = match &app.top_values_error {
+        Some(err) => Paragraph::new(err.as_str()).style(ERROR_STYLE),
+        None => Paragraph::new("←/→ to change the options, ⏎ Enter to refresh, Esc to close"),
+    }
+    .alignment(Alignment::Center);
+    f.render_widget(help, popup_layout[5]);
+}
@@ -0,0 +1,60 @@
+use tui::{
+    backend::Backend,
+    layout::Constraint,
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Clear, Row, Table},
+    Frame,
+};
+
+use crate::{app::App, styles::selected_style};
+
+use super::ui_popup::centered_rect;
+
+pub fn draw_transaction_popup<B>(f: &mut Frame<B>, app: &mut App)
+where
+    B: Backend,
+{
+    let block = Block::default()
+        .title(format!(
+            "Transaction {} - {} lines (⏎ Enter: go to line, Esc: close)",
+            app.transaction_id,
+            app.transaction_lines.items.len()
+        ))
+        .borders(Borders::ALL)
+        .border_style(selected_style(app.color));
+
+    let area = centered_rect(80, 60, f.size());
+    f.render_widget(Clear, area); //this clears out the background
+
+    let header_cells = ["Index", "Log", "Date", "Timestamp", "Severity", "Payload"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Black)));
+    let header = Row::new(header_cells)
+        .style(Style::default().bg(app.color).add_modifier(Modifier::BOLD))
+        .bottom_margin(1);
+
+    let rows = app.transaction_lines.items.iter().map(|line| {
+        Row::new(vec![
+            Cell::from(line.index.to_string()),
+            Cell::from(line.log.to_string()),
+            Cell::from(line.date.to_string()),
+            Cell::from(line.timestamp.to_string()),
+            Cell::from(line.severity.to_string()),
+            Cell::from(line.payload.to_string()),
+        ])
+    });
+
+    let t = Table::new(rows)
+        .header(header)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(&[
+            Constraint::Length(8),
+            Constraint::Length(16),
+            Constraint::Length(12),
+            Constraint::Length(14),
+            Constraint::Length(10),
+            Constraint::Percentage(100),
+        ]);
+    f.render_stateful_widget(t, area, &mut app.transaction_lines.state);
+}
@@ -0,0 +1,98 @@
+use crate::{
+    app::{App, INDEX_WATCH_EXPRESSION},
+    styles::{selected_style, ERROR_STYLE},
+};
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table},
+    Frame,
+};
+
+use super::{
+    ui_log_analyzer::WATCH_RATE_SECS,
+    ui_popup::centered_rect,
+    ui_shared::{display_cursor, format_thousands},
+};
+
+pub fn draw_watches_popup<B>(f: &mut Frame<B>, app: &mut App)
+where
+    B: Backend,
+{
+    let block = Block::default()
+        .title("Watches counting the live lines")
+        .borders(Borders::ALL)
+        .border_style(selected_style(app.color));
+
+    let area = centered_rect(60, 24, f.size());
+    f.render_widget(Clear, area); //this clears out the background
+    f.render_widget(block, area);
+
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Min(3),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .margin(1)
+        .split(area);
+
+    let expression_widget = Paragraph::new(app.input_buffers[INDEX_WATCH_EXPRESSION].value())
+        .style(selected_style(app.color))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Expression (regex or query, e.g. payload:retry -severity:DEBUG)"),
+        );
+    f.render_widget(expression_widget, popup_layout[0]);
+    display_cursor(
+        f,
+        popup_layout[0],
+        app.input_buffers[INDEX_WATCH_EXPRESSION].cursor(),
+    );
+
+    let header_cells = ["Expression", "Per second", "Last second", "Total"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Black)));
+    let header = Row::new(header_cells)
+        .style(Style::default().bg(app.color).add_modifier(Modifier::BOLD))
+        .bottom_margin(1);
+
+    let rows = app.watches.items.iter().map(|watch| {
+        let last = watch.per_second.iter().rev().nth(1).copied().unwrap_or_default();
+        Row::new(vec![
+            Cell::from(watch.expression.as_str()),
+            Cell::from(format!("{:.1}", watch.rate(WATCH_RATE_SECS))),
+            Cell::from(format_thousands(last)),
+            Cell::from(format_thousands(watch.total)),
+        ])
+    });
+
+    let t = Table::new(rows)
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("{} watches", app.watches.items.len())),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(&[
+            Constraint::Percentage(46),
+            Constraint::Percentage(18),
+            Constraint::Percentage(18),
+            Constraint::Percentage(18),
+        ]);
+    f.render_stateful_widget(t, popup_layout[1], &mut app.watches.state);
+
+    let help = match &app.watches_error {
+        Some(err) => Paragraph::new(err.as_str()).style(ERROR_STYLE),
+        None => Paragraph::new("⏎ Enter to add, ↑/↓ to select, Del to remove, Esc to close"),
+    }
+    .alignment(Alignment::Center);
+    f.render_widget(help, popup_layout[2]);
+}
@@ -0,0 +1,40 @@
+//! Helpers shared by the tests of the workspace crates
+use std::time::{Duration, Instant};
+
+pub use tempfile::{tempdir, TempDir};
+
+/// Time the tests wait for the sources and the processing threads before failing
+pub const DEADLINE: Duration = Duration::from_secs(10);
+
+/// Poll `condition` until it holds, panicking once `deadline` is elapsed so a failing test
+/// stops instead of hanging
+pub fn wait_until(deadline: Duration, mut condition: impl FnMut() -> bool) {
+    let started = Instant::now();
+    while !condition() {
+        if started.elapsed() > deadline {
+            panic!("Condition not met after {:?}", deadline);
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_until_the_condition_holds() {
+        let mut polls = 0;
+        wait_until(DEADLINE, || {
+            polls += 1;
+            polls == 3
+        });
+        assert_eq!(polls, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Condition not met")]
+    fn panics_once_the_deadline_is_elapsed() {
+        wait_until(Duration::from_millis(20), || false);
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufRead, BufReader},
+    time::{Duration, Instant},
+};
+
+use log_analyzer::services::line_processor::LineProcessor;
+use regex::Regex;
+
+use crate::headless::{enabled_filters, load_settings, select_format};
+
+/// Throughput of a stage of the processing
+struct StageResult {
+    stage: &'static str,
+    lines: usize,
+    elapsed: Duration,
+}
+
+impl StageResult {
+    fn lines_per_second(&self) -> f64 {
+        self.lines as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Measure the throughput of every stage of the processing of the lines of `file`:
+/// reading, formatting with the settings format, filtering with the enabled settings filters
+/// and searching `search` if given.
+///
+/// Every stage runs on a single thread over the output of the previous one
+pub fn run_bench(
+    settings_path: Option<&str>,
+    file: &str,
+    format: Option<&str>,
+    search: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let settings = load_settings(settings_path)?;
+    let formats = settings.formats.unwrap_or_default();
+    let format = select_format(&formats, format)?;
+    let processor = LineProcessor::new(format, enabled_filters(settings.filters))?;
+    let search = search.map(Regex::new).transpose()?;
+    let mut results = Vec::new();
+
+    let start = Instant::now();
+    let lines = BufReader::new(File::open(file)?)
+        .lines()
+        .collect::<Result<Vec<String>, _>>()?;
+    results.push(StageResult {
+        stage: "Read",
+        lines: lines.len(),
+        elapsed: start.elapsed(),
+    });
+
+    let start = Instant::now();
+    let log_lines: Vec<_> = lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| processor.format(file, line, index))
+        .collect();
+    results.push(StageResult {
+        stage: "Format",
+        lines: log_lines.len(),
+        elapsed: start.elapsed(),
+    });
+
+    let start = Instant::now();
+    let filtered: Vec<_> = log_lines
+        .into_iter()
+        .filter_map(|log_line| processor.filter(log_line))
+        .collect();
+    results.push(StageResult {
+        stage: "Filter",
+        lines: lines.len(),
+        elapsed: start.elapsed(),
+    });
+
+    let mut matches = None;
+    if let Some(search) = &search {
+        let start = Instant::now();
+        matches = Some(
+            filtered
+                .iter()
+                .filter(|log_line| LineProcessor::search(search, log_line))
+                .count(),
+        );
+        results.push(StageResult {
+            stage: "Search",
+            lines: filtered.len(),
+            elapsed: start.elapsed(),
+        });
+    }
+
+    println!("{:<8} {:>12} {:>12} {:>14}", "Stage", "Lines", "Time (ms)", "Lines/s");
+    for result in &results {
+        println!(
+            "{:<8} {:>12} {:>12.1} {:>14.0}",
+            result.stage,
+            result.lines,
+            result.elapsed.as_secs_f64() * 1000.0,
+            result.lines_per_second()
+        );
+    }
+    let total: Duration = results.iter().map(|result| result.elapsed).sum();
+    println!(
+        "\n{} lines read, {} kept by the filters{} in {:.1} ms",
+        lines.len(),
+        filtered.len(),
+        matches
+            .map(|matches| format!(", {} matching the search", matches))
+            .unwrap_or_default(),
+        total.as_secs_f64() * 1000.0
+    );
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use std::{error::Error, fs};
+
+use log_analyzer::models::settings::Settings;
+
+/// Parse the settings file and check every format, filter, source and option.
+///
+/// Returns whether the settings are valid, printing the problems found to stderr
+pub fn check_settings(path: &str) -> Result<bool, Box<dyn Error>> {
+    let json = fs::read_to_string(path).map_err(|err| format!("Unable to read {}: {}", path, err))?;
+    let settings = match Settings::from_json(&json) {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            return Ok(false);
+        }
+    };
+
+    let errors = settings.validate();
+    for error in &errors {
+        eprintln!("{}: {}", path, error);
+    }
+    if errors.is_empty() {
+        println!(
+            "{}: OK ({} formats, {} filters, {} sources)",
+            path,
+            settings.formats.map(|formats| formats.len()).unwrap_or_default(),
+            settings.filters.map(|filters| filters.len()).unwrap_or_default(),
+            settings.sources.map(|sources| sources.len()).unwrap_or_default()
+        );
+    }
+
+    Ok(errors.is_empty())
+}
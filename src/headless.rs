@@ -0,0 +1,146 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+};
+
+use log_analyzer::{
+    models::{
+        filter::Filter,
+        format::Format,
+        settings::{Settings, SettingsFilter},
+    },
+    services::line_processor::LineProcessor,
+};
+
+/// Input name reading the lines piped to stdin
+pub const STDIN_INPUT: &str = terminal_ui::STDIN_FILE;
+
+/// Load the settings file, or empty settings if not given
+pub fn load_settings(settings_path: Option<&str>) -> Result<Settings, Box<dyn Error>> {
+    Ok(match settings_path {
+        Some(path) => Settings::from_json(&fs::read_to_string(path)?)?,
+        None => Settings::default(),
+    })
+}
+
+/// Filters of the settings loaded enabled, the ones applied without the terminal UI
+pub fn enabled_filters(filters: Option<Vec<SettingsFilter>>) -> Vec<Filter> {
+    filters
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|filter| filter.enabled)
+        .map(|SettingsFilter { filter, .. }| filter)
+        .collect()
+}
+
+/// Find the format with the given alias, or take the first one if not given
+pub fn select_format<'a>(
+    formats: &'a [Format],
+    alias: Option<&str>,
+) -> Result<Option<&'a Format>, Box<dyn Error>> {
+    Ok(match alias {
+        Some(alias) => Some(
+            formats
+                .iter()
+                .find(|format| format.alias == alias)
+                .ok_or_else(|| format!("Format {} not found in the settings", alias))?,
+        ),
+        None => formats.first(),
+    })
+}
+
+/// Open the input file, or stdin for `STDIN_INPUT`
+pub fn open_input(input: &str) -> Result<Box<dyn BufRead>, Box<dyn Error>> {
+    Ok(match input {
+        STDIN_INPUT => Box::new(io::stdin().lock()),
+        path => Box::new(BufReader::new(File::open(path)?)),
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// What is written for the lines kept by the headless mode or matched by a query
+pub enum MatchOutput {
+    /// The lines themselves
+    Lines,
+    /// Only the number of lines
+    Count,
+    /// Nothing, stopping at the first line. Useful when only the exit code matters
+    Quiet,
+}
+
+/// Writes the matched lines according to the `MatchOutput`, counting them
+pub struct MatchWriter {
+    writer: Box<dyn Write>,
+    output: MatchOutput,
+    count: usize,
+}
+
+impl MatchWriter {
+    pub fn new(writer: Box<dyn Write>, output: MatchOutput) -> Self {
+        Self {
+            writer,
+            output,
+            count: 0,
+        }
+    }
+
+    /// Write a matched line. Returns false when no more lines are needed
+    pub fn write(&mut self, line: &str) -> io::Result<bool> {
+        self.count += 1;
+        match self.output {
+            MatchOutput::Lines => writeln!(self.writer, "{}", line)?,
+            MatchOutput::Count => {}
+            MatchOutput::Quiet => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Write the count if requested and flush. Returns the number of lines matched
+    pub fn finish(mut self) -> io::Result<usize> {
+        if self.output == MatchOutput::Count {
+            writeln!(self.writer, "{}", self.count)?;
+        }
+        self.writer.flush()?;
+        Ok(self.count)
+    }
+}
+
+/// Apply the formats and enabled filters of the settings to the input files without starting the
+/// terminal UI. The lines kept are written unchanged to the output file or to stdout.
+///
+/// The format used is the one with the given alias, or the first of the settings if not given.
+/// Returns the number of lines kept
+pub fn run_headless(
+    settings_path: Option<&str>,
+    inputs: &[String],
+    output: Option<&str>,
+    format: Option<&str>,
+    match_output: MatchOutput,
+) -> Result<usize, Box<dyn Error>> {
+    if inputs.is_empty() {
+        return Err("Headless mode requires at least one --input file or piped input".into());
+    }
+
+    let settings = load_settings(settings_path)?;
+    let formats = settings.formats.unwrap_or_default();
+    let format = select_format(&formats, format)?;
+    let processor = LineProcessor::new(format, enabled_filters(settings.filters))?;
+
+    let writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+    let mut writer = MatchWriter::new(writer, match_output);
+
+    'inputs: for input in inputs {
+        for (index, line) in open_input(input)?.lines().enumerate() {
+            let line = line?;
+            if processor.process(input, &line, index).is_some() && !writer.write(&line)? {
+                break 'inputs;
+            }
+        }
+    }
+
+    Ok(writer.finish()?)
+}
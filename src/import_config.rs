@@ -0,0 +1,32 @@
+use std::{error::Error, fs, path::Path};
+
+use log_analyzer::models::settings::Settings;
+
+/// Convert an lnav format file or a glogg/klogg configuration and add its formats and filters
+/// to the settings file, creating it if needed. Entries with an alias already in the settings
+/// are left out
+pub fn import_settings(file: &str, settings_path: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let settings_path = settings_path.ok_or("There is no settings file to import into")?;
+    let content =
+        fs::read_to_string(file).map_err(|err| format!("Unable to read {}: {}", file, err))?;
+
+    let mut settings = match Path::new(settings_path).exists() {
+        true => Settings::from_json(&fs::read_to_string(settings_path)?)?,
+        false => Settings::default(),
+    };
+    let report = settings.import(&content)?;
+
+    if let Some(parent) = Path::new(settings_path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(settings_path, settings.to_json()?)?;
+    for entry in &report.skipped {
+        eprintln!("{}: skipped {}", file, entry);
+    }
+    println!(
+        "Imported {} formats and {} filters from {} to {}",
+        report.formats, report.filters, file, settings_path
+    );
+
+    Ok(())
+}
@@ -1,26 +1,288 @@
+mod bench;
+mod check_settings;
+mod headless;
+mod import_config;
+mod query;
+mod server;
 
 use std::error::Error;
+use std::fs::File;
+use std::io::{self, IsTerminal};
+use std::sync::Mutex;
 
 
-use terminal_ui::async_main;
+use terminal_ui::{async_main, session, TuiOptions};
 
 
-use clap::Parser;
+use bench::run_bench;
+use check_settings::check_settings;
+use clap::{Parser, Subcommand};
+use headless::{run_headless, MatchOutput, STDIN_INPUT};
+use import_config::import_settings;
+use log_analyzer::models::pipeline::PipelineConfig;
+use query::run_query;
+use server::run_server;
+use tracing_subscriber::{filter::LevelFilter, fmt::format::FmtSpan};
 
 /// Simple program to greet a person
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
-    /// Settings file containing formats, filters or color customization
-    #[clap(short, long)]
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// Log files opened at startup with the --format alias. Use - to read the lines piped to
+    /// stdin, which is detected automatically
+    files: Vec<String>,
+
+    /// Settings file containing formats, filters or color customization.
+    /// Defaults to settings.json in the log-analyzer-pro folder of the platform config directory
+    #[clap(short, long, global = true)]
     settings: Option<String>,
+
+    /// Keep reading the lines appended to the files after reaching their end (default)
+    #[clap(long, overrides_with = "no-follow")]
+    follow: bool,
+
+    /// Load the files once and stop reading them, e.g. for post-mortem analysis
+    #[clap(long, overrides_with = "follow")]
+    no_follow: bool,
+
+    /// Replay the files into the live view with the timing of their lines, this many times
+    /// faster (e.g. 1 for real time, 10 for ten times faster). The time of the lines is parsed
+    /// from their date or timestamp with the --format alias
+    #[clap(long)]
+    replay: Option<f64>,
+
+    /// Memory in MB the stored lines can use. Once reached, the new lines of the sources are
+    /// discarded and a notification is shown. Unlimited by default
+    #[clap(long, global = true)]
+    max_memory: Option<usize>,
+
+    /// Threads processing the lines in parallel, e.g. to cap the CPU used on a shared machine.
+    /// Defaults to the pipeline of the settings, or one per CPU
+    #[clap(long, global = true)]
+    workers: Option<usize>,
+
+    /// Lines processed at once by a worker. Defaults to the pipeline of the settings, or a size
+    /// adapted to the load: small chunks while following the sources, big ones for bulk loads
+    #[clap(long, global = true)]
+    chunk_size: Option<usize>,
+
+    /// Write the traces of the processing pipeline to this file: the batches read by the
+    /// sources, formatted, filtered and searched with the time spent on each of them
+    #[clap(long, global = true)]
+    trace_file: Option<String>,
+
+    /// Url of a server started with `lap serve` running the analysis, e.g. http://host:7878
+    #[clap(long)]
+    remote: Option<String>,
+
+    /// Keep the raw and filtered lines in this SQLite database. Reopening it shows its lines
+    /// without reading and filtering the files again. Requires the `sqlite` feature
+    #[clap(long)]
+    database: Option<String>,
+
+    /// Write the sources, formats, filters and options shown to this settings file when quitting
+    /// the terminal UI, to open them again with --settings
+    #[clap(long)]
+    export_settings: Option<String>,
+
+    /// Filter the input files without the terminal UI and write the kept lines to the output
+    #[clap(long)]
+    headless: bool,
+
+    /// Log file to process in headless mode, like the positional files. Can be repeated
+    #[clap(short, long, multiple_occurrences = true)]
+    input: Vec<String>,
+
+    /// File where the headless mode writes the kept lines. Defaults to stdout
+    #[clap(short, long)]
+    output: Option<String>,
+
+    /// Print only the number of lines kept in headless mode or matched by a query
+    #[clap(short, long, global = true, conflicts_with = "quiet")]
+    count: bool,
+
+    /// Print nothing in headless mode or queries, exiting with 0 at the first line kept or matched
+    #[clap(short, long, global = true)]
+    quiet: bool,
+
+    /// Alias of the settings format applied to the input. Defaults to the first one in headless
+    /// mode and queries
+    #[clap(short, long, global = true)]
+    format: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check that a settings file can be loaded, reporting every invalid format, filter, source or
+    /// option
+    CheckSettings {
+        /// Settings file to check
+        file: String,
+    },
+    /// Add the formats and filters of another log viewer to the settings file: lnav format files
+    /// (JSON) and glogg or klogg configurations with their filters and highlighters (INI)
+    Import {
+        /// lnav, glogg or klogg file to import, e.g. ~/.config/glogg/glogg.conf
+        file: String,
+    },
+    /// Print the lines of the files matching the expression, formatted with the settings format.
+    /// Terms separated by spaces must all match: `regex` on any field, `column:regex` on a
+    /// column, `@alias` for the lines kept by a settings filter and `-term` to negate a term
+    Query {
+        /// Query expression, e.g. "severity:ERROR -payload:timeout"
+        expression: String,
+        /// Log files to query. Reads the lines piped to stdin if none is given or for -
+        files: Vec<String>,
+    },
+    /// Measure the lines per second of every processing stage (read, format, filter and search)
+    /// for the file with the format and filters of the settings
+    Bench {
+        /// Log file to process
+        file: String,
+        /// Search regex to measure the search stage
+        #[clap(long)]
+        search: Option<String>,
+    },
+    /// Run the analyzer as a daemon controlled through an HTTP API, loading the sources, formats
+    /// and filters of the settings. There is no authentication: bind to a public address with care
+    Serve {
+        /// Address to listen on
+        #[clap(long, default_value = "127.0.0.1:7878")]
+        bind: String,
+    },
 }
 
+/// Exit like grep does: 0 if any line was matched, 1 if none and 2 on errors
+fn exit_with_matches(result: Result<usize, Box<dyn Error>>) -> ! {
+    match result {
+        Ok(0) => std::process::exit(1),
+        Ok(_) => std::process::exit(0),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(2)
+        }
+    }
+}
+
+/// Write the spans and events of the analysis to `path`, with the time spent in every span
+fn init_tracing(path: &str) -> Result<(), Box<dyn Error>> {
+    let file = File::create(path).map_err(|err| format!("Unable to create {}: {}", path, err))?;
+    tracing_subscriber::fmt()
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .with_thread_names(true)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_max_level(LevelFilter::TRACE)
+        .init();
+    Ok(())
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    if let Some(path) = &args.trace_file {
+        init_tracing(path)?;
+    }
+    args.settings = session::settings_path_or_default(args.settings);
+    let match_output = match (args.count, args.quiet) {
+        (true, _) => MatchOutput::Count,
+        (_, true) => MatchOutput::Quiet,
+        _ => MatchOutput::Lines,
+    };
+
+    let pipeline = PipelineConfig {
+        workers: args.workers,
+        chunk_size: args.chunk_size,
+        ..Default::default()
+    };
+    let errors = pipeline.validate();
+    if !errors.is_empty() {
+        return Err(format!("Invalid pipeline options: {}", errors.join(", ")).into());
+    }
+
+    match &args.command {
+        Some(Command::CheckSettings { file }) => {
+            if !check_settings(file)? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Command::Import { file }) => {
+            // The default settings file is created if there is none
+            let settings = args.settings.clone().or_else(|| {
+                session::default_settings_path().map(|path| path.to_string_lossy().to_string())
+            });
+            return import_settings(file, settings.as_deref());
+        }
+        Some(Command::Query { expression, files }) => {
+            let files = match files.is_empty() {
+                true => vec![STDIN_INPUT.to_string()],
+                false => files.clone(),
+            };
+            exit_with_matches(run_query(
+                args.settings.as_deref(),
+                expression,
+                &files,
+                args.format.as_deref(),
+                match_output,
+            ));
+        }
+        Some(Command::Bench { file, search }) => {
+            return run_bench(
+                args.settings.as_deref(),
+                file,
+                args.format.as_deref(),
+                search.as_deref(),
+            );
+        }
+        Some(Command::Serve { bind }) => {
+            return run_server(
+                args.settings.as_deref(),
+                bind,
+                !args.no_follow,
+                args.max_memory,
+                pipeline,
+            );
+        }
+        None => {}
+    }
+
+    let is_piped = !io::stdin().is_terminal();
+
+    if args.headless {
+        let mut inputs = args.input;
+        inputs.extend(args.files);
+        if inputs.is_empty() && is_piped {
+            inputs.push(STDIN_INPUT.to_string());
+        }
+        exit_with_matches(run_headless(
+            args.settings.as_deref(),
+            &inputs,
+            args.output.as_deref(),
+            args.format.as_deref(),
+            match_output,
+        ));
+    }
 
-    async_std::task::block_on(async_main(args.settings))?;
+    // The terminal UI reads the keys from /dev/tty, leaving stdin to the piped lines
+    let mut files = args.files;
+    if is_piped && !files.iter().any(|file| file == STDIN_INPUT) {
+        files.push(STDIN_INPUT.to_string());
+    }
+    async_std::task::block_on(async_main(TuiOptions {
+        settings_path: args.settings,
+        files,
+        format: args.format,
+        follow: !args.no_follow,
+        replay: args.replay,
+        max_memory: args.max_memory,
+        pipeline,
+        remote: args.remote,
+        database: args.database,
+        export_settings: args.export_settings,
+    }))?;
 
     Ok(())
 }
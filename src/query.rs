@@ -0,0 +1,58 @@
+use std::{
+    error::Error,
+    io::{self, BufRead, BufWriter},
+};
+
+use log_analyzer::{
+    models::{query::Query, settings::SettingsFilter},
+    services::line_processor::LineProcessor,
+};
+
+use crate::headless::{load_settings, open_input, select_format, MatchOutput, MatchWriter};
+
+/// Print the lines of the input files matching the query expression.
+///
+/// Lines are formatted with the format of the settings with the given alias, or the first one.
+/// The filters of the settings can be referenced from the expression with `@alias`.
+/// Lines are prefixed with their file name when there is more than one input, like grep does.
+/// Returns the number of lines matched
+pub fn run_query(
+    settings_path: Option<&str>,
+    expression: &str,
+    inputs: &[String],
+    format: Option<&str>,
+    match_output: MatchOutput,
+) -> Result<usize, Box<dyn Error>> {
+    let settings = load_settings(settings_path)?;
+    let formats = settings.formats.unwrap_or_default();
+    let filters: Vec<_> = settings
+        .filters
+        .unwrap_or_default()
+        .into_iter()
+        .map(|SettingsFilter { filter, .. }| filter)
+        .collect();
+    let query = Query::parse(expression, &filters)?;
+    // Only formats the lines, the query decides which ones are printed
+    let processor = LineProcessor::new(select_format(&formats, format)?, Vec::new())?;
+
+    let mut writer = MatchWriter::new(Box::new(BufWriter::new(io::stdout())), match_output);
+    'inputs: for input in inputs {
+        for (index, line) in open_input(input)?.lines().enumerate() {
+            let line = line?;
+            let is_match = processor
+                .process(input, &line, index)
+                .map(|log_line| query.matches(&log_line))
+                .unwrap_or_default();
+            let keep_going = match (is_match, inputs.len() > 1) {
+                (true, true) => writer.write(&format!("{}:{}", input, line))?,
+                (true, false) => writer.write(&line)?,
+                _ => true,
+            };
+            if !keep_going {
+                break 'inputs;
+            }
+        }
+    }
+
+    Ok(writer.finish()?)
+}
@@ -0,0 +1,124 @@
+use std::{error::Error, sync::Arc};
+
+use log_analyzer::{
+    models::pipeline::PipelineConfig,
+    services::api::{dispatch, ApiRequest, EventLog},
+    LogAnalyzer, LogAnalyzerBuilder, LogAnalyzerError,
+};
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::headless::load_settings;
+
+/// Prefix of the paths of the API
+const API_PATH: &str = "/api/";
+
+/// Run the analyzer as a daemon controlled through an HTTP API listening on `bind`.
+///
+/// * `POST /api/<method>` calls the `LogAnalyzer` method with the JSON body as params,
+///   e.g. `POST /api/get_log_lines` with `{"from": 0, "to": 100}`
+/// * `GET /api/events?after=<id>` returns the events numbered after `id`
+///
+/// The sources, formats and filters of the settings are loaded at startup.
+/// `max_memory` limits the MB used by the stored lines and the options set in `pipeline` take
+/// precedence over the pipeline of the settings
+pub fn run_server(
+    settings_path: Option<&str>,
+    bind: &str,
+    follow: bool,
+    max_memory: Option<usize>,
+    pipeline: PipelineConfig,
+) -> Result<(), Box<dyn Error>> {
+    let mut settings = load_settings(settings_path)?;
+    let log_service = LogAnalyzerBuilder::new()
+        .follow(follow)
+        .max_memory(max_memory)
+        .pipeline(settings.pipeline.unwrap_or_default())
+        .pipeline(pipeline)
+        .build();
+
+    log_service.set_burst_detection(settings.burst_detection);
+    log_service.set_retention(settings.retention);
+    log_service.set_overload_policy(settings.overload_policy.unwrap_or_default());
+    if let Err(err) = log_service.set_correlation_key(settings.correlation_key.as_deref()) {
+        eprintln!("Settings partially loaded: {}", err);
+    }
+    // Transformed and redacted before the sources of the settings are read
+    if let Err(err) = log_service.set_transforms(settings.transforms.take().unwrap_or_default()) {
+        eprintln!("Settings partially loaded: {}", err);
+    }
+    if let Err(err) = log_service.set_redactions(settings.redactions.take().unwrap_or_default()) {
+        eprintln!("Settings partially loaded: {}", err);
+    }
+    if let Err(err) = log_service.restore_session(&settings.take_session()) {
+        eprintln!("Settings partially loaded: {}", err);
+    }
+
+    let events = Arc::new(EventLog::default());
+    let mut receiver = log_service.on_event();
+    let event_log = events.clone();
+    std::thread::Builder::new()
+        .name("Events".to_string())
+        .spawn(move || loop {
+            match async_std::task::block_on(receiver.recv()) {
+                Ok(event) => event_log.record(event),
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                Err(_) => break,
+            }
+        })?;
+
+    let server = Server::http(bind).map_err(|err| format!("Unable to listen on {}: {}", bind, err))?;
+    println!("Listening on http://{}{}", bind, API_PATH);
+    for mut request in server.incoming_requests() {
+        let result = handle_request(&*log_service, &events, &mut request);
+        let (status, body) = match result {
+            Ok(value) => (200, value),
+            // Errors of the analyzer carry their kind so clients can tell them apart
+            Err(err) => match err.downcast_ref::<LogAnalyzerError>() {
+                Some(kind) => (400, json!({ "error": err.to_string(), "kind": kind })),
+                None => (400, json!({ "error": err.to_string() })),
+            },
+        };
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+        request.respond(response).unwrap_or_default();
+    }
+
+    Ok(())
+}
+
+fn handle_request(
+    log_analyzer: &dyn LogAnalyzer,
+    events: &EventLog,
+    request: &mut Request,
+) -> Result<Value, Box<dyn Error>> {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let method = path
+        .strip_prefix(API_PATH)
+        .ok_or_else(|| format!("Unknown path {}", path))?;
+
+    match (request.method(), method) {
+        (Method::Get, "events") => {
+            let after = query
+                .split('&')
+                .find_map(|param| param.strip_prefix("after="))
+                .map(|after| after.parse::<u64>())
+                .transpose()?
+                .unwrap_or_default();
+            Ok(json!(events.since(after)))
+        }
+        (Method::Post, method) => {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body)?;
+            let params = match body.trim().is_empty() {
+                true => None,
+                false => Some(serde_json::from_str(&body)?),
+            };
+            let request = ApiRequest::from_method(method, params)?;
+            Ok(dispatch(log_analyzer, request)?)
+        }
+        (method, path) => Err(format!("Unsupported {} {}", method, path).into()),
+    }
+}